@@ -0,0 +1,12 @@
+// Generate the `zord.v1` gRPC server from proto/zord/v1/zord.proto with the vendored protoc,
+// so building needs no system protobuf compiler.
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?);
+    println!("cargo:rerun-if-changed=proto/zord/v1/zord.proto");
+    // `assets::Embedded` compiles in every file of web/; rebuild when one is added or removed
+    println!("cargo:rerun-if-changed=web");
+    tonic_prost_build::configure()
+        .build_client(false)
+        .compile_protos(&["proto/zord/v1/zord.proto"], &["proto"])?;
+    Ok(())
+}
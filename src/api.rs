@@ -1,30 +1,58 @@
-use crate::db::Db;
-use crate::rpc::ZcashRpcClient;
+mod error;
+mod graphql;
+mod grpc;
+mod headers;
+mod jsonrpc;
+mod listen;
+mod logging;
+mod recursive;
+mod tls;
+mod v2;
+
+use error::{ApiError, ApiResult, Json, Path, Query};
+use crate::auth::{ApiKeys, Role};
+use crate::db::{
+    classify_mime, BalanceProof, BlockSummary, Cursor, Db, DbStats, Distribution, FeedFilter, FeedSort, parse_trait_filter,
+    Period, PeriodStats, Rarity, RoyaltyTotals, SearchIndex, StateChecksum, TraitCounts, SCAN_BATCH,
+};
+use crate::ipfs::{self, IpfsConfig, IpfsResolver};
+use crate::market::{self, MarketConfig, MarketData};
+use crate::names::{self, normalize_name};
+use crate::rpc::{RpcError, ZcashRpcClient};
+use crate::svg;
+use crate::thumbs;
+use crate::assets::{self, Assets};
+use crate::openapi::{self, routes, Access, ApiRouter};
+use crate::webhooks::{Dispatcher, Webhook, WebhookFilter};
+use crate::zrc20::{amount::Amount, Zrc20Engine};
 use axum::{
-    extract::{Path, Query, State},
-    http::{header, StatusCode},
-    response::{Html, IntoResponse, Response},
+    extract::{OriginalUri, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{Html, IntoResponse, Redirect, Response},
     routing::get,
-    Json, Router,
+    Extension, Router,
 };
 use axum::middleware::{self, Next};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
 use tower::BoxError;
 use tower::ServiceBuilder;
-use tower::limit::ConcurrencyLimitLayer;
+use tower::limit::GlobalConcurrencyLimitLayer;
 use tower::timeout::TimeoutLayer;
-use tower_http::cors::CorsLayer;
 use tower_http::compression::CompressionLayer;
+use tower_http::trace::TraceLayer;
 use axum::error_handling::HandleErrorLayer;
-use std::sync::{Arc, atomic::{AtomicUsize, AtomicU64, Ordering}};
+use std::sync::{Arc, atomic::{AtomicBool, AtomicUsize, AtomicU64, Ordering}};
 use std::time::{SystemTime, UNIX_EPOCH};
 use std::fs;
 use axum::body::Body;
-use tower_http::services::ServeDir;
 
-const FRONT_HTML: &str = include_str!("../web/index.html");
 const MAX_PAGE_SIZE: usize = 50000;
+// Entries accepted by one batch lookup
+const MAX_BATCH_ITEMS: usize = 1000;
+// Names one wildcard entry of a resolve batch expands to at most
+const MAX_WILDCARD_MATCHES: usize = 100;
 
 #[derive(Deserialize)]
 struct PaginationParams {
@@ -33,6 +61,11 @@ struct PaginationParams {
     q: Option<String>,
     tld: Option<String>,
     positive_only: Option<bool>,
+    // Historical view for balance listings
+    at_height: Option<u64>,
+    // Keyset cursors; when set, `page` is ignored
+    after_id: Option<String>,
+    before_id: Option<String>,
 }
 
 impl PaginationParams {
@@ -41,12 +74,125 @@ impl PaginationParams {
         let limit = self.limit.unwrap_or(24).clamp(1, MAX_PAGE_SIZE);
         (page, limit)
     }
+
+    fn cursor(&self) -> Option<Cursor<'_>> {
+        if let Some(id) = self.after_id.as_deref() {
+            Some(Cursor::After(id))
+        } else {
+            self.before_id.as_deref().map(Cursor::Before)
+        }
+    }
+}
+
+// Filters for `/api/v1/inscriptions`; any of them switches the feed to the facet indexes
+#[derive(Deserialize, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct FeedParams {
+    /// Category such as `image`, `text` or `html`
+    category: Option<String>,
+    /// Exact MIME type
+    content_type: Option<String>,
+    /// Lowest block height, inclusive
+    from_height: Option<u64>,
+    /// Highest block height, inclusive
+    to_height: Option<u64>,
+    /// Creating address
+    address: Option<String>,
+    /// Only shielded (true) or transparent (false) creators
+    shielded: Option<bool>,
+    /// `newest` (default), `oldest` or `largest`
+    sort: Option<String>,
+}
+
+impl FeedParams {
+    fn is_plain(&self) -> bool {
+        self.category.is_none()
+            && self.content_type.is_none()
+            && self.from_height.is_none()
+            && self.to_height.is_none()
+            && self.address.is_none()
+            && self.shielded.is_none()
+            && self.sort.is_none()
+    }
+
+    fn resolve(&self) -> ApiResult<(FeedFilter, FeedSort)> {
+        let sort = match self.sort.as_deref() {
+            None | Some("newest") => FeedSort::Newest,
+            Some("oldest") => FeedSort::Oldest,
+            Some("largest") => FeedSort::Largest,
+            Some(other) => {
+                return Err(ApiError::bad_request(format!(
+                    "unknown sort {:?}; expected newest, oldest or largest",
+                    other
+                )))
+            }
+        };
+        let filter = FeedFilter {
+            category: self.category.as_ref().map(|c| c.to_lowercase()),
+            content_type: self.content_type.clone(),
+            address: self.address.clone(),
+            shielded: self.shielded,
+            from_height: self.from_height,
+            to_height: self.to_height,
+        };
+        Ok((filter, sort))
+    }
+}
+
+// A full keyset page may have more behind it; hand back its last key to continue from
+fn next_cursor<T>(rows: &[(String, T)], limit: usize) -> Option<String> {
+    if rows.len() < limit {
+        return None;
+    }
+    rows.last().map(|(key, _)| key.clone())
+}
+
+// Visit every row of a snapshot scan; a read error ends the walk early and is logged
+fn scan_rows(
+    batches: impl Iterator<Item = anyhow::Result<Vec<(String, String)>>>,
+    mut visit: impl FnMut(String, String),
+) {
+    for batch in batches {
+        match batch {
+            Ok(rows) => rows.into_iter().for_each(|(key, value)| visit(key, value)),
+            Err(e) => {
+                tracing::warn!("Table scan stopped early: {}", e);
+                break;
+            }
+        }
+    }
 }
 
 #[derive(Clone)]
 pub struct AppState {
     db: Db,
+    // Absent on read-only API replicas, which run without RPC credentials
+    rpc: Option<ZcashRpcClient>,
     metrics: Arc<ServerMetrics>,
+    snapshots: Arc<SnapshotConfig>,
+    // Last storage report and when it was taken; walking every table is too slow per request
+    db_stats: Arc<tokio::sync::Mutex<Option<(std::time::Instant, DbStats)>>>,
+    auth: Arc<ApiKeys>,
+    // Origin HTML inscriptions are framed from; empty means this server
+    content_origin: Arc<str>,
+    // Strip active content from SVG inscriptions unless `?raw=true` is asked for
+    sanitize_svg: bool,
+    // Live block activity; absent on read-only API replicas, which do not index
+    events: Option<Dispatcher>,
+    // Frontend files, embedded unless WEB_DIR points at a checkout
+    assets: Assets,
+    // Latest external quotes; empty unless MARKET_DATA_URL is set
+    market: MarketData,
+    // Fetches ZRC-721 metadata and images; resolves nothing unless IPFS_GATEWAY is set
+    ipfs: IpfsResolver,
+    // Built once at startup; holds its own handle to the database
+    graphql: graphql::ZordSchema,
+}
+
+pub struct SnapshotConfig {
+    // Snapshots are only written when an operator opts in via SNAPSHOT_DIR
+    dir: Option<std::path::PathBuf>,
+    running: AtomicBool,
 }
 
 pub struct ServerMetrics {
@@ -54,19 +200,72 @@ pub struct ServerMetrics {
     requests_total: AtomicU64,
     responses_5xx_total: AtomicU64,
     start_unix: u64,
+    budgets: Vec<Budget>,
+}
+
+/// Concurrency cap and timeout shared by one class of routes, so slow full-table
+/// walks queue among themselves instead of holding up `/content/:id`.
+#[derive(Clone)]
+pub struct Budget {
+    class: &'static str,
     max_inflight: usize,
+    timeout: std::time::Duration,
+    permits: Arc<tokio::sync::Semaphore>,
+}
+
+impl Budget {
+    // `<prefix>MAX_INFLIGHT` and `<prefix>TIMEOUT_SECS`, falling back to the defaults
+    fn from_env(class: &'static str, prefix: &str, max_inflight: usize, timeout_secs: u64) -> Self {
+        let max_inflight = std::env::var(format!("{}MAX_INFLIGHT", prefix))
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .filter(|n| *n > 0)
+            .unwrap_or(max_inflight);
+        let timeout_secs: u64 = std::env::var(format!("{}TIMEOUT_SECS", prefix))
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(timeout_secs);
+        Budget {
+            class,
+            max_inflight,
+            timeout: std::time::Duration::from_secs(timeout_secs),
+            permits: Arc::new(tokio::sync::Semaphore::new(max_inflight)),
+        }
+    }
+
+    fn inflight(&self) -> usize {
+        self.max_inflight.saturating_sub(self.permits.available_permits())
+    }
+
+    // Every route of `router` draws from this budget's one pool of permits
+    fn apply(&self, router: Router<AppState>) -> Router<AppState> {
+        router.layer(
+            ServiceBuilder::new()
+                // Convert middleware errors (e.g., timeouts) into HTTP responses
+                .layer(HandleErrorLayer::new(|err: BoxError| async move {
+                    if err.is::<tower::timeout::error::Elapsed>() {
+                        return ApiError::new(StatusCode::REQUEST_TIMEOUT, "request timed out").into_response();
+                    }
+                    ApiError::internal("middleware error", err).into_response()
+                }))
+                .layer(TimeoutLayer::new(self.timeout))
+                .layer(GlobalConcurrencyLimitLayer::with_semaphore(self.permits.clone())),
+        )
+    }
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 struct PaginatedResponse<T> {
     page: usize,
     limit: usize,
     total: u64,
     has_more: bool,
+    /// Pass as `after_id` to fetch the next page
+    next_cursor: Option<String>,
     items: Vec<T>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 struct InscriptionSummary {
     id: String,
     content_type: String,
@@ -80,7 +279,7 @@ struct InscriptionSummary {
     preview_text: Option<String>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 struct TokenSummary {
     ticker: String,
     max: String,
@@ -92,20 +291,29 @@ struct TokenSummary {
     deployer: String,
     inscription_id: String,
     progress: f64,
+    /// Name, icon and description from the deployer's meta inscription
+    #[serde(skip_serializing_if = "Option::is_none")]
+    meta: Option<serde_json::Value>,
+    /// Price and volume from MARKET_DATA_URL; not derived from the chain
+    #[serde(skip_serializing_if = "Option::is_none")]
+    market: Option<serde_json::Value>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 struct Zrc721CollectionSummary {
     collection: String,
     supply: String,
     minted: u64,
+    owners: u64,
     meta: serde_json::Value,
     royalty: String,
     deployer: String,
     inscription_id: String,
+    /// Marked official by the operator
+    verified: bool,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 struct Zrc721TokenSummary {
     tick: String,
     token_id: String,
@@ -113,25 +321,26 @@ struct Zrc721TokenSummary {
     inscription_id: String,
     metadata: serde_json::Value,
     metadata_path: Option<String>,
+    /// Absent until the rarity job has scored the token
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rarity: Option<Rarity>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 struct NameSummary {
     name: String,
     owner: String,
     inscription_id: String,
+    /// Label shape stored at registration (graphemes, ascii_only, emoji_only, ...)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    attributes: Option<serde_json::Value>,
 }
 
-pub async fn start_api(db: Db, port: u16) {
-    // Runtime tunables: concurrency & request timeout
-    let max_inflight: usize = std::env::var("API_MAX_INFLIGHT")
-        .ok()
-        .and_then(|s| s.parse().ok())
-        .unwrap_or(2048);
-    let timeout_secs: u64 = std::env::var("API_TIMEOUT_SECS")
-        .ok()
-        .and_then(|s| s.parse().ok())
-        .unwrap_or(15);
+pub async fn start_api(db: Db, rpc: Option<ZcashRpcClient>, events: Option<Dispatcher>, port: u16) {
+    // Runtime tunables: concurrency & request timeout per route class
+    let standard = Budget::from_env("standard", "API_", 2048, 15);
+    let content = Budget::from_env("content", "API_CONTENT_", 512, 10);
+    let heavy = Budget::from_env("expensive", "API_EXPENSIVE_", 16, 60);
 
     let start_unix = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
     let metrics = Arc::new(ServerMetrics {
@@ -139,32 +348,96 @@ pub async fn start_api(db: Db, port: u16) {
         requests_total: AtomicU64::new(0),
         responses_5xx_total: AtomicU64::new(0),
         start_unix,
-        max_inflight,
+        budgets: vec![standard.clone(), content.clone(), heavy.clone()],
     });
-    let state = AppState { db, metrics: metrics.clone() };
+    let snapshots = Arc::new(SnapshotConfig {
+        dir: std::env::var("SNAPSHOT_DIR").ok().map(std::path::PathBuf::from),
+        running: AtomicBool::new(false),
+    });
+    let graphql = graphql::schema(db.clone());
+    let state = AppState {
+        db,
+        rpc,
+        metrics: metrics.clone(),
+        snapshots,
+        db_stats: Arc::new(tokio::sync::Mutex::new(None)),
+        auth: Arc::new(ApiKeys::from_env().expect("Invalid API_KEYS")),
+        content_origin: std::env::var("CONTENT_ORIGIN")
+            .map(|s| s.trim_end_matches('/').to_string())
+            .unwrap_or_default()
+            .into(),
+        sanitize_svg: std::env::var("SVG_SANITIZE")
+            .map(|v| !matches!(v.as_str(), "0" | "false" | "FALSE" | "no" | "NO"))
+            .unwrap_or(true),
+        events,
+        assets: Assets::from_env(),
+        market: MarketConfig::from_env()
+            .expect("Invalid market data configuration")
+            .map(market::spawn)
+            .unwrap_or_default(),
+        ipfs: IpfsConfig::from_env()
+            .expect("Invalid IPFS resolver configuration")
+            .map(IpfsResolver::new)
+            .unwrap_or_default(),
+        graphql,
+    };
+    let tls = tls::Tls::from_env().expect("Invalid TLS configuration");
+    if let Some(grpc_listeners) = listen::from_env("GRPC_LISTEN").expect("Invalid GRPC_LISTEN") {
+        grpc::start(state.clone(), grpc_listeners, tls.clone(), &standard)
+            .await
+            .expect("gRPC server failed to start");
+    }
+
+    // Operator endpoints: always behind an admin key
+    let admin = ApiRouter::new()
+        .routes(routes!(post_admin_snapshot))
+        .routes(routes!(get_admin_webhooks, post_admin_webhook))
+        .routes(routes!(get_admin_dead_letters))
+        .routes(routes!(delete_admin_webhook))
+        .routes(routes!(get_admin_verified_collections))
+        .routes(routes!(put_admin_verified_collection, delete_admin_verified_collection))
+        .routes(routes!(get_admin_reserved_names))
+        .routes(routes!(put_admin_name_grant, delete_admin_name_grant))
+        .access(Access::Key)
+        .route_layer(middleware::from_fn_with_state(state.clone(), require_admin));
+
+    // Full-table walks and dry runs; gated by a client key when API_PROTECT_EXPENSIVE is set
+    let expensive = ApiRouter::new()
+        .routes(routes!(post_decode))
+        .routes(routes!(get_db_metrics))
+        .routes(routes!(get_all_tokens_api))
+        .routes(routes!(get_all_names_api))
+        .routes(routes!(get_zrc20_holders_csv))
+        .routes(routes!(get_zrc20_holders_json))
+        .routes(routes!(get_zrc20_holders_ndjson))
+        .routes(routes!(get_zrc20_distribution))
+        .routes(routes!(get_zrc20_balance_proof))
+        .routes(routes!(get_zrc20_token_balances_at))
+        .routes(routes!(get_zrc721_collection_snapshot))
+        .routes(routes!(get_names_export))
+        .routes(routes!(get_inscriptions_export))
+        .routes(graphql::endpoint())
+        .access(Access::OptionalKey)
+        .route_layer(middleware::from_fn_with_state(state.clone(), require_client));
 
     let middleware = ServiceBuilder::new()
-        // Convert middleware errors (e.g., timeouts) into HTTP responses
-        .layer(HandleErrorLayer::new(|err: BoxError| async move {
-            if err.is::<tower::timeout::error::Elapsed>() {
-                return (
-                    axum::http::StatusCode::REQUEST_TIMEOUT,
-                    "request timed out",
-                )
-                    .into_response();
-            }
-            (
-                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                format!("internal error: {}", err),
-            )
-                .into_response()
-        }))
-        .layer(TimeoutLayer::new(std::time::Duration::from_secs(timeout_secs)))
-        .layer(ConcurrencyLimitLayer::new(max_inflight))
-        .layer(CorsLayer::permissive())
+        // Tag each request with an id, then log it once it completes
+        .layer(middleware::from_fn(logging::assign_request_id))
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(logging::request_span)
+                .on_request(())
+                .on_response(logging::log_response)
+                .on_failure(()),
+        )
+        .layer(headers::cors_layer().expect("Invalid CORS configuration"))
+        .layer(middleware::from_fn_with_state(
+            Arc::new(headers::ResponseHeaders::from_env().expect("Invalid response header configuration")),
+            headers::security_headers,
+        ))
         .layer(CompressionLayer::new());
 
-    let app = Router::new()
+    let app = ApiRouter::new()
         // Static HTML entry points
         .route("/", get(frontpage))
         .route("/tokens", get(tokens_page))
@@ -178,87 +451,128 @@ pub async fn start_api(db: Db, port: u16) {
         .route("/spec", get(spec_page))
         .route("/uptime", get(uptime_page))
         .route("/api", get(api_docs))
-        .route("/api/v1/metrics", get(get_metrics))
+        .routes(routes!(get_openapi))
+        .routes(routes!(get_metrics))
         // JSON feeds powering the frontend widgets
-        .route("/api/v1/inscriptions", get(get_inscriptions_feed))
-        .route("/api/v1/tokens", get(get_tokens_feed))
-        .route("/api/v1/names", get(get_names_feed))
-        .route("/api/v1/names/zec", get(get_names_feed_zec))
-        .route("/api/v1/names/zcash", get(get_names_feed_zcash))
-        .route("/api/v1/names/address/:address", get(get_names_by_address))
-        .route("/api/v1/status", get(get_status))
-        .route("/api/v1/zrc20/status", get(get_zrc20_status))
-        .route("/api/v1/zrc20/tokens", get(get_tokens_feed))
-        .route("/api/v1/zrc20/token/:tick", get(get_token_info))
-        .route(
-            "/api/v1/zrc20/token/:tick/summary",
-            get(get_zrc20_token_summary),
-        )
-        .route("/api/v1/zrc20/token/:tick/balances", get(get_zrc20_token_balances))
-        .route("/api/v1/zrc20/address/:address", get(get_zrc20_address_balances))
-        .route(
-            "/api/v1/zrc20/token/:tick/rank/:address",
-            get(get_zrc20_rank),
-        )
-        .route(
-            "/api/v1/zrc20/token/:tick/integrity",
-            get(get_zrc20_token_integrity),
-        )
-        .route("/api/v1/zrc20/transfer/:id", get(get_zrc20_transfer))
-        .route("/api/v1/zrc721/status", get(get_zrc721_status))
-        .route("/api/v1/zrc721/collections", get(get_zrc721_collections))
-        .route("/api/v1/zrc721/collection/:tick", get(get_zrc721_collection))
-        .route(
-            "/api/v1/zrc721/collection/:tick/tokens",
-            get(get_zrc721_collection_tokens),
-        )
-        .route("/api/v1/zrc721/address/:address", get(get_zrc721_address_tokens))
-        .route(
-            "/api/v1/zrc721/token/:collection/:id",
-            get(get_zrc721_token_info),
-        )
-        .route("/api/v1/healthz", get(get_healthz))
-        .route(
-            "/api/v1/zrc20/token/:tick/burned",
-            get(get_zrc20_burned),
-        )
+        .routes(routes!(get_inscriptions_feed))
+        .routes(routes!(post_inscriptions_batch))
+        .routes(routes!(get_tokens_feed))
+        .routes(routes!(get_names_feed))
+        .routes(routes!(get_names_feed_zec))
+        .routes(routes!(get_names_feed_zcash))
+        .routes(routes!(get_address_portfolio))
+        .routes(routes!(get_address_activity))
+        .routes(routes!(get_status))
+        .routes(routes!(get_activity_stats))
+        .routes(routes!(get_blocks_feed))
+        .routes(routes!(get_search))
+        .routes(routes!(get_search_content))
+        .routes(routes!(get_zrc20_status))
+        .alias("/api/v1/zrc20/tokens", routes!(get_tokens_feed))
+        .routes(routes!(get_token_info))
+        .routes(routes!(get_zrc20_token_summary))
+        .routes(routes!(get_zrc20_token_balances))
+        .routes(routes!(get_zrc20_address_balances))
+        .routes(routes!(post_zrc20_balances_batch))
+        .routes(routes!(post_zrc20_validate))
+        .routes(routes!(get_zrc20_address_transferable))
+        .routes(routes!(get_zrc20_address_cancellable))
+        .routes(routes!(get_zrc20_address_history))
+        .routes(routes!(get_zrc20_token_history))
+        .routes(routes!(get_zrc20_token_events))
+        .routes(routes!(get_zrc20_checksum))
+        .routes(routes!(get_zrc20_rejected_deploys))
+        .routes(routes!(get_zrc20_rank))
+        .routes(routes!(get_zrc20_token_integrity))
+        .routes(routes!(get_zrc20_transfer))
+        .routes(routes!(get_zrc721_status))
+        .routes(routes!(get_zrc721_collections))
+        .routes(routes!(get_zrc721_collection))
+        .routes(routes!(get_zrc721_collection_tokens))
+        .routes(routes!(get_zrc721_collection_owners))
+        .routes(routes!(get_zrc721_collection_royalties))
+        .routes(routes!(get_zrc721_collection_updates))
+        .routes(routes!(get_zrc721_collection_activity))
+        .routes(routes!(get_zrc721_collection_traits))
+        .routes(routes!(get_zrc721_address_tokens))
+        .routes(routes!(get_zrc721_token_info))
+        .routes(routes!(get_zrc721_token_image))
+        .routes(routes!(get_zrc721_token_history))
+        .routes(routes!(get_healthz))
+        .routes(routes!(get_zrc20_burned))
         // Compatibility endpoints for Ord-style tools
-        .route("/inscription/:id", get(get_inscription))
-        .route("/inscriptions", get(get_recent_inscriptions))
-        .route("/content/:id", get(get_inscription_content))
-        .route("/preview/:id", get(get_inscription_preview))
-        .route("/block/:query", get(get_block))
-        .route("/tx/:txid", get(get_transaction))
-        .route("/status", get(get_status))
+        .routes(routes!(get_inscription))
+        .routes(routes!(get_recent_inscriptions))
+        .routes(routes!(get_block))
+        .routes(routes!(get_transaction))
+        .alias("/status", routes!(get_status))
         // Misc helper endpoints
-        .route("/health", get(health))
-        .route("/block/height", get(get_block_height))
-        .route(
-            "/inscription/number/:number",
-            get(get_inscription_by_number),
-        )
-        .route(
-            "/address/:address/inscriptions",
-            get(get_address_inscriptions),
-        )
-        .route("/token/:tick", get(get_token_info))
-        .route("/token/:tick/balance/:address", get(get_balance))
-        .route("/tokens/list", get(get_all_tokens_api))
-        .route("/names/list", get(get_all_names_api))
-        .route("/name/:name", get(get_name_info))
-        .route("/resolve/:name", get(resolve_name))
-        .route("/api/v1/resolve/:name", get(resolve_name))
-        // Static asset server (keep last)
-        .nest_service("/static", ServeDir::new("web"))
-        .layer(middleware)
-        // Track in-flight requests for metrics
-        .layer(middleware::from_fn_with_state(state.clone(), track_inflight))
-        .with_state(state);
-
-    let addr = std::net::SocketAddr::from(([0, 0, 0, 0], port));
-    tracing::info!("API listening on {}", addr);
-    let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+        .routes(routes!(health))
+        .routes(routes!(get_block_height))
+        .routes(routes!(get_inscription_by_number))
+        .routes(routes!(get_address_inscriptions))
+        .alias("/token/{tick}", routes!(get_token_info))
+        .routes(routes!(get_balance))
+        .routes(routes!(get_names_by_address))
+        .routes(routes!(get_name_info))
+        .routes(routes!(get_name_history))
+        .routes(routes!(get_name_subdomains))
+        .routes(routes!(get_name_proof))
+        .routes(routes!(resolve_name))
+        .alias("/resolve/{name}", routes!(resolve_name))
+        .routes(routes!(post_resolve_batch))
+        .merge(v2::router())
+        .merge(jsonrpc::router())
+        .merge(recursive::router())
+        .route("/static/:file", get(get_static));
+    // Inscription bytes get their own pool so explorer pages keep loading under API load
+    let content_routes = Router::new()
+        .route("/content/:id", get(get_inscription_content).head(head_inscription_content))
+        .route("/preview/:id", get(get_inscription_preview))
+        .route("/thumb/:id", get(get_inscription_thumb));
+
+    let (app, app_operations) = app.split();
+    let (expensive, expensive_operations) = expensive.split();
+    let (admin, admin_operations) = admin.split();
+    let spec = openapi::document([app_operations, expensive_operations, admin_operations]);
+    let spec = OpenApiJson(serde_json::to_vec(&spec).expect("OpenAPI document serializes").into());
+
+    let public = standard
+        .apply(app)
+        .merge(content.apply(content_routes))
+        .merge(heavy.apply(expensive));
+    let admin = standard.apply(admin);
+    let finish = |router: Router<AppState>| {
+        router
+            .layer(middleware.clone())
+            // Track in-flight requests for metrics
+            .layer(middleware::from_fn_with_state(state.clone(), track_inflight))
+            .layer(Extension(spec.clone()))
+            .with_state(state.clone())
+    };
+
+    let public_listeners = listen::from_env("API_LISTEN")
+        .expect("Invalid API_LISTEN")
+        .unwrap_or_else(|| vec![listen::Listen::Tcp(std::net::SocketAddr::from(([0, 0, 0, 0], port)))]);
+    // Admin routes stay on the public listeners unless ADMIN_LISTEN gives them their own
+    let listeners = match listen::from_env("ADMIN_LISTEN").expect("Invalid ADMIN_LISTEN") {
+        Some(admin_listeners) => {
+            let public = finish(public);
+            let admin = finish(admin);
+            public_listeners
+                .into_iter()
+                .map(|l| (l, public.clone()))
+                .chain(admin_listeners.into_iter().map(|l| (l, admin.clone())))
+                .collect()
+        }
+        None => {
+            let app = finish(public.merge(admin));
+            public_listeners.into_iter().map(|l| (l, app.clone())).collect()
+        }
+    };
+    if let Err(e) = listen::serve(listeners, tls).await {
+        panic!("API server failed: {}", e);
+    }
 }
 
 async fn track_inflight(State(state): State<AppState>, req: axum::http::Request<Body>, next: Next) -> impl IntoResponse {
@@ -272,71 +586,586 @@ async fn track_inflight(State(state): State<AppState>, req: axum::http::Request<
     res
 }
 
-async fn get_metrics(State(state): State<AppState>) -> Json<serde_json::Value> {
+async fn require_admin(State(state): State<AppState>, req: axum::http::Request<Body>, next: Next) -> Response {
+    authorize(&state, Role::Admin, req, next).await
+}
+
+async fn require_client(State(state): State<AppState>, req: axum::http::Request<Body>, next: Next) -> Response {
+    if !state.auth.protects_expensive() {
+        return next.run(req).await;
+    }
+    authorize(&state, Role::Client, req, next).await
+}
+
+// 401 without a recognised key, 403 when the key's role is too low
+async fn authorize(state: &AppState, needed: Role, req: axum::http::Request<Body>, next: Next) -> Response {
+    match state.auth.role_for(req.headers()) {
+        Some(role) if role >= needed => next.run(req).await,
+        Some(_) => ApiError::new(StatusCode::FORBIDDEN, "API key lacks the required role").into_response(),
+        None => (
+            [(header::WWW_AUTHENTICATE, "Bearer")],
+            ApiError::new(StatusCode::UNAUTHORIZED, "API key required"),
+        )
+            .into_response(),
+    }
+}
+
+const DB_STATS_TTL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Per-table storage usage (cached for 60s)
+#[utoipa::path(get, path = "/api/v1/metrics/db", tag = "status", responses((status = 200, body = DbStats)))]
+async fn get_db_metrics(
+    State(state): State<AppState>,
+) -> ApiResult<Json<DbStats>> {
+    // Holding the lock while computing also collapses concurrent requests into one scan
+    let mut cached = state.db_stats.lock().await;
+    if let Some((taken, report)) = cached.as_ref() {
+        if taken.elapsed() < DB_STATS_TTL {
+            return Ok(Json(report.clone()));
+        }
+    }
+    let db = state.db.clone();
+    let stats = tokio::task::spawn_blocking(move || db.stats())
+        .await
+        .map_err(|e| ApiError::internal("db stats task failed", e))?
+        .map_err(|e| ApiError::internal("db stats error", e))?;
+    *cached = Some((std::time::Instant::now(), stats.clone()));
+    Ok(Json(stats))
+}
+
+#[derive(Serialize, ToSchema)]
+struct BudgetUsage {
+    inflight: usize,
+    max_inflight: usize,
+    timeout_secs: u64,
+}
+
+#[derive(Serialize, ToSchema)]
+struct FileLimit {
+    soft: Option<u64>,
+    hard: Option<u64>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct ProcessLimits {
+    nofile: FileLimit,
+}
+
+#[derive(Serialize, ToSchema)]
+struct ServerReport {
+    inflight: u64,
+    max_inflight: usize,
+    /// Usage per route class: standard, content and expensive
+    budgets: std::collections::BTreeMap<String, BudgetUsage>,
+    open_fds: Option<usize>,
+    limits: ProcessLimits,
+    start_time_unix: u64,
+    uptime_seconds: u64,
+    requests_total: u64,
+    responses_5xx_total: u64,
+    /// RPC client counters; null on read-only replicas
+    rpc: Option<serde_json::Value>,
+}
+
+/// Server request and file descriptor metrics
+#[utoipa::path(get, path = "/api/v1/metrics", tag = "status", responses((status = 200, body = ServerReport)))]
+async fn get_metrics(State(state): State<AppState>) -> Json<ServerReport> {
     let inflight = state.metrics.inflight.load(Ordering::Relaxed) as u64;
-    let open_fds = count_open_fds();
     let (soft, hard) = get_fd_limits();
     let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
-    let uptime_seconds = now.saturating_sub(state.metrics.start_unix);
-    let requests_total = state.metrics.requests_total.load(Ordering::Relaxed);
-    let responses_5xx_total = state.metrics.responses_5xx_total.load(Ordering::Relaxed);
-    Json(serde_json::json!({
-        "inflight": inflight,
-        "max_inflight": state.metrics.max_inflight,
-        "open_fds": open_fds,
-        "limits": { "nofile": { "soft": soft, "hard": hard } },
-        "start_time_unix": state.metrics.start_unix,
-        "uptime_seconds": uptime_seconds,
-        "requests_total": requests_total,
-        "responses_5xx_total": responses_5xx_total
-    }))
+    let budgets = state
+        .metrics
+        .budgets
+        .iter()
+        .map(|b| {
+            let usage = BudgetUsage {
+                inflight: b.inflight(),
+                max_inflight: b.max_inflight,
+                timeout_secs: b.timeout.as_secs(),
+            };
+            (b.class.to_string(), usage)
+        })
+        .collect();
+    Json(ServerReport {
+        inflight,
+        max_inflight: state.metrics.budgets[0].max_inflight,
+        budgets,
+        open_fds: count_open_fds(),
+        limits: ProcessLimits { nofile: FileLimit { soft, hard } },
+        start_time_unix: state.metrics.start_unix,
+        uptime_seconds: now.saturating_sub(state.metrics.start_unix),
+        requests_total: state.metrics.requests_total.load(Ordering::Relaxed),
+        responses_5xx_total: state.metrics.responses_5xx_total.load(Ordering::Relaxed),
+        rpc: state.rpc.as_ref().map(ZcashRpcClient::metrics),
+    })
+}
+
+#[derive(Serialize, ToSchema)]
+struct SnapshotStarted {
+    /// Always `started`; the export's outcome is logged
+    status: &'static str,
+    path: String,
+}
+
+/// Start a consistent snapshot into SNAPSHOT_DIR
+#[utoipa::path(post, path = "/api/v1/admin/snapshot", tag = "admin", responses((status = 202, body = SnapshotStarted)))]
+async fn post_admin_snapshot(State(state): State<AppState>) -> ApiResult<Response> {
+    let dir = state
+        .snapshots
+        .dir
+        .clone()
+        .ok_or_else(|| ApiError::not_found("Snapshots disabled (set SNAPSHOT_DIR)"))?;
+    if state.snapshots.running.swap(true, Ordering::SeqCst) {
+        return Err(ApiError::new(StatusCode::CONFLICT, "Snapshot already in progress"));
+    }
+
+    let file = format!("zord-{}.redb", Utc::now().format("%Y%m%dT%H%M%SZ"));
+    let path = dir.join(&file);
+
+    // Large databases take longer than the request timeout; run detached and report via logs
+    let db = state.db.clone();
+    let snapshots = state.snapshots.clone();
+    let target = path.clone();
+    tokio::spawn(async move {
+        let result = tokio::task::spawn_blocking(move || db.export_snapshot(&target)).await;
+        match result {
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => tracing::error!("Snapshot export failed: {}", e),
+            Err(e) => tracing::error!("Snapshot task panicked: {}", e),
+        }
+        snapshots.running.store(false, Ordering::SeqCst);
+    });
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(SnapshotStarted { status: "started", path: path.display().to_string() }),
+    )
+        .into_response())
+}
+
+#[derive(Deserialize, ToSchema)]
+struct WebhookRequest {
+    url: String,
+    /// HMAC-SHA256 key; one is generated when omitted
+    secret: Option<String>,
+    #[serde(flatten)]
+    filter: WebhookFilter,
+}
+
+/// Register a webhook
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/webhooks",
+    tag = "admin",
+    request_body = WebhookRequest,
+    responses((status = 201, description = "The subscription, with the only copy of its secret", body = Webhook))
+)]
+async fn post_admin_webhook(
+    State(state): State<AppState>,
+    Json(req): Json<WebhookRequest>,
+) -> ApiResult<Response> {
+    let hook = Webhook::new(req.url, req.filter, req.secret).map_err(|e| ApiError::bad_request(e.to_string()))?;
+    serde_json::to_string(&hook)
+        .map_err(anyhow::Error::from)
+        .and_then(|raw| state.db.put_webhook(&hook.id, &raw))
+        .map_err(|e| ApiError::internal("webhook register error", e))?;
+    // The secret is only ever returned here
+    Ok((StatusCode::CREATED, Json(hook)).into_response())
+}
+
+/// A subscription without its secret
+#[derive(Serialize, ToSchema)]
+struct WebhookSummary {
+    id: String,
+    url: String,
+    filter: WebhookFilter,
+    created_at: u64,
+}
+
+#[derive(Serialize, ToSchema)]
+struct WebhookList {
+    webhooks: Vec<WebhookSummary>,
+}
+
+/// List webhook subscriptions
+#[utoipa::path(get, path = "/api/v1/admin/webhooks", tag = "admin", responses((status = 200, body = WebhookList)))]
+async fn get_admin_webhooks(
+    State(state): State<AppState>,
+) -> ApiResult<Json<WebhookList>> {
+    let rows = state.db.list_webhooks().map_err(|e| ApiError::internal("webhook list error", e))?;
+    let webhooks = rows
+        .into_iter()
+        .filter_map(|(_, raw)| serde_json::from_str::<Webhook>(&raw).ok())
+        .map(|hook| WebhookSummary { id: hook.id, url: hook.url, filter: hook.filter, created_at: hook.created_at })
+        .collect();
+    Ok(Json(WebhookList { webhooks }))
+}
+
+/// Remove a webhook
+#[utoipa::path(
+    delete,
+    path = "/api/v1/admin/webhooks/{id}",
+    tag = "admin",
+    params(("id" = String, Path, description = "Webhook id")),
+    responses((status = 204, description = "Removed"))
+)]
+async fn delete_admin_webhook(State(state): State<AppState>, Path(id): Path<String>) -> ApiResult<StatusCode> {
+    match state.db.delete_webhook(&id) {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err(ApiError::not_found("Webhook not found")),
+        Err(e) => Err(ApiError::internal("webhook delete error", e)),
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+struct DeadLetters {
+    limit: usize,
+    /// Failed deliveries: webhook id and url, attempts, last error, time and payload
+    items: Vec<serde_json::Value>,
+}
+
+/// Newest-first failed deliveries
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/webhooks/dead-letters",
+    tag = "admin",
+    params(("limit" = Option<usize>, Query, description = "Page size (default 24, max 50000)")),
+    responses((status = 200, body = DeadLetters))
+)]
+async fn get_admin_dead_letters(
+    State(state): State<AppState>,
+    Query(params): Query<PaginationParams>,
+) -> ApiResult<Json<DeadLetters>> {
+    let (_, limit) = params.resolve();
+    let rows = state.db.list_dead_letters(limit).map_err(|e| ApiError::internal("dead letter list error", e))?;
+    let items = rows
+        .into_iter()
+        .filter_map(|(_, raw)| serde_json::from_str(&raw).ok())
+        .collect();
+    Ok(Json(DeadLetters { limit, items }))
+}
+
+#[derive(Deserialize, ToSchema)]
+struct VerificationRequest {
+    /// Why the collection is official, e.g. a link to the project's announcement
+    #[serde(default)]
+    note: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+struct Verification {
+    collection: String,
+    verified_at: i64,
+    note: Option<String>,
+}
+
+/// Mark a collection verified
+#[utoipa::path(
+    put,
+    path = "/api/v1/admin/zrc721/verified/{tick}",
+    tag = "admin",
+    params(("tick" = String, Path, description = "Collection ticker")),
+    request_body = VerificationRequest,
+    responses((status = 200, body = Verification))
+)]
+async fn put_admin_verified_collection(
+    State(state): State<AppState>,
+    Path(tick): Path<String>,
+    Json(req): Json<VerificationRequest>,
+) -> ApiResult<Json<Verification>> {
+    let tick = tick.to_lowercase();
+    if state
+        .db
+        .get_zrc721_collection(&tick)
+        .map_err(|e| ApiError::internal("collection lookup error", e))?
+        .is_none()
+    {
+        return Err(ApiError::not_found(format!("Collection {} not found", tick)));
+    }
+    let entry = Verification { collection: tick, verified_at: Utc::now().timestamp(), note: req.note };
+    serde_json::to_string(&entry)
+        .map_err(anyhow::Error::from)
+        .and_then(|raw| state.db.put_zrc721_verification(&entry.collection, &raw))
+        .map_err(|e| ApiError::internal("verification write error", e))?;
+    Ok(Json(entry))
+}
+
+/// Remove a collection's verification
+#[utoipa::path(
+    delete,
+    path = "/api/v1/admin/zrc721/verified/{tick}",
+    tag = "admin",
+    params(("tick" = String, Path, description = "Collection ticker")),
+    responses((status = 204, description = "Removed"))
+)]
+async fn delete_admin_verified_collection(
+    State(state): State<AppState>,
+    Path(tick): Path<String>,
+) -> ApiResult<StatusCode> {
+    match state.db.delete_zrc721_verification(&tick.to_lowercase()) {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err(ApiError::not_found("Collection is not verified")),
+        Err(e) => Err(ApiError::internal("verification delete error", e)),
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+struct VerificationList {
+    collections: Vec<Verification>,
+}
+
+/// List verified ZRC-721 collections
+#[utoipa::path(get, path = "/api/v1/admin/zrc721/verified", tag = "admin", responses((status = 200, body = VerificationList)))]
+async fn get_admin_verified_collections(
+    State(state): State<AppState>,
+) -> ApiResult<Json<VerificationList>> {
+    let rows = state
+        .db
+        .list_zrc721_verifications()
+        .map_err(|e| ApiError::internal("verification list error", e))?;
+    let collections = rows
+        .into_iter()
+        .filter_map(|(_, raw)| serde_json::from_str(&raw).ok())
+        .collect();
+    Ok(Json(VerificationList { collections }))
+}
+
+#[derive(Deserialize, ToSchema)]
+struct NameGrantRequest {
+    /// Only this address's registration of the reserved name counts
+    address: String,
+    #[serde(default)]
+    note: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+struct NameGrant {
+    name: String,
+    address: String,
+    granted_at: i64,
+    note: Option<String>,
+}
+
+/// Grant a reserved name to an address
+#[utoipa::path(
+    put,
+    path = "/api/v1/admin/names/reserved/{name}",
+    tag = "admin",
+    params(("name" = String, Path, description = "Reserved name")),
+    request_body = NameGrantRequest,
+    responses((status = 200, body = NameGrant))
+)]
+async fn put_admin_name_grant(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(req): Json<NameGrantRequest>,
+) -> ApiResult<Json<NameGrant>> {
+    let name = normalize_name(&name);
+    if !names::reserved::reserved().is_reserved(&name) {
+        return Err(ApiError::bad_request(format!("Name {} is not reserved", name)));
+    }
+    if state
+        .db
+        .get_name(&name)
+        .map_err(|e| ApiError::internal("name lookup error", e))?
+        .is_some()
+    {
+        return Err(ApiError::new(StatusCode::CONFLICT, format!("Name {} is already registered", name)));
+    }
+    let address = req.address.trim();
+    if address.is_empty() {
+        return Err(ApiError::bad_request("address is required"));
+    }
+    let entry = NameGrant { address: address.to_string(), granted_at: Utc::now().timestamp(), note: req.note, name };
+    serde_json::to_string(&entry)
+        .map_err(anyhow::Error::from)
+        .and_then(|raw| state.db.put_name_grant(&entry.name, &raw))
+        .map_err(|e| ApiError::internal("name grant write error", e))?;
+    Ok(Json(entry))
 }
 
-fn count_open_fds() -> serde_json::Value {
-    match fs::read_dir("/proc/self/fd") {
-        Ok(rd) => serde_json::json!(rd.count()),
-        Err(_) => serde_json::Value::Null,
+/// Revoke a reserved name's grant
+#[utoipa::path(
+    delete,
+    path = "/api/v1/admin/names/reserved/{name}",
+    tag = "admin",
+    params(("name" = String, Path, description = "Reserved name")),
+    responses((status = 204, description = "Revoked"))
+)]
+async fn delete_admin_name_grant(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> ApiResult<StatusCode> {
+    match state.db.delete_name_grant(&normalize_name(&name)) {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err(ApiError::not_found("Name has no grant")),
+        Err(e) => Err(ApiError::internal("name grant delete error", e)),
     }
 }
 
-fn get_fd_limits() -> (serde_json::Value, serde_json::Value) {
+#[derive(Serialize, ToSchema)]
+struct ReservedNameList {
+    /// Entries from ZNS_RESERVED_NAMES
+    reserved: &'static [String],
+    grants: Vec<NameGrant>,
+}
+
+/// List reserved names and operator grants
+#[utoipa::path(get, path = "/api/v1/admin/names/reserved", tag = "admin", responses((status = 200, body = ReservedNameList)))]
+async fn get_admin_reserved_names(
+    State(state): State<AppState>,
+) -> ApiResult<Json<ReservedNameList>> {
+    let rows = state
+        .db
+        .list_name_grants()
+        .map_err(|e| ApiError::internal("name grant list error", e))?;
+    let grants = rows
+        .into_iter()
+        .filter_map(|(_, raw)| serde_json::from_str(&raw).ok())
+        .collect();
+    Ok(Json(ReservedNameList { reserved: &names::reserved::reserved().entries, grants }))
+}
+
+fn count_open_fds() -> Option<usize> {
+    fs::read_dir("/proc/self/fd").ok().map(|rd| rd.count())
+}
+
+fn get_fd_limits() -> (Option<u64>, Option<u64>) {
     if let Ok(contents) = fs::read_to_string("/proc/self/limits") {
         for line in contents.lines() {
             if line.to_lowercase().contains("max open files") {
                 let parts: Vec<&str> = line.split_whitespace().collect();
                 if parts.len() >= 5 {
-                    let soft = parts[3].parse::<u64>().ok();
-                    let hard = parts[4].parse::<u64>().ok();
-                    return (serde_json::json!(soft), serde_json::json!(hard));
+                    return (parts[3].parse::<u64>().ok(), parts[4].parse::<u64>().ok());
                 }
             }
         }
     }
-    (serde_json::Value::Null, serde_json::Value::Null)
+    (None, None)
+}
+
+/// Runs `read` on tokio's blocking pool. Db calls are synchronous redb reads, and
+/// handlers that walk a range, a batch or a large value would otherwise hold an
+/// async worker thread for the whole scan.
+async fn blocking<T, F>(state: &AppState, read: F) -> T
+where
+    F: FnOnce(AppState) -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let state = state.clone();
+    match tokio::task::spawn_blocking(move || read(state)).await {
+        Ok(value) => value,
+        // A panicking read fails the request the same way it would have inline
+        Err(e) => std::panic::resume_unwind(e.into_panic()),
+    }
 }
 
-async fn health() -> Json<serde_json::Value> {
-    Json(serde_json::json!({ "status": "ok" }))
+#[derive(Serialize, ToSchema)]
+struct Liveness {
+    /// Always `ok`
+    status: &'static str,
 }
 
-async fn get_block_height(State(state): State<AppState>) -> Json<serde_json::Value> {
+/// Liveness probe
+#[utoipa::path(get, path = "/health", tag = "status", responses((status = 200, body = Liveness)))]
+async fn health() -> Json<Liveness> {
+    Json(Liveness { status: "ok" })
+}
+
+#[derive(Serialize, ToSchema)]
+struct IndexHeight {
+    /// Null until the first block is indexed
+    height: Option<u64>,
+}
+
+/// Latest indexed block height
+#[utoipa::path(get, path = "/block/height", tag = "status", responses((status = 200, body = IndexHeight)))]
+async fn get_block_height(State(state): State<AppState>) -> Json<IndexHeight> {
     let height = state.db.get_latest_indexed_height().unwrap_or(None);
-    Json(serde_json::json!({ "height": height }))
+    Json(IndexHeight { height })
 }
 
-async fn get_recent_inscriptions(State(state): State<AppState>) -> Json<serde_json::Value> {
-    let inscriptions = state.db.get_inscriptions_page(0, 50).unwrap_or_default();
-    let data: Vec<serde_json::Value> = inscriptions.into_iter().map(|(id, meta)| {
-        serde_json::json!({
-            "id": id,
-            "meta": serde_json::from_str::<serde_json::Value>(&meta).unwrap_or(serde_json::Value::String(meta))
-        })
-    }).collect();
-    Json(serde_json::json!(data))
+#[derive(Serialize, ToSchema)]
+struct InscriptionRecord {
+    id: String,
+    /// Stored metadata: content type and length, sender, receiver, txid, vout, block height and time
+    meta: Option<serde_json::Value>,
+}
+
+/// Latest 50 inscriptions with raw metadata
+#[utoipa::path(get, path = "/inscriptions", tag = "inscriptions", responses((status = 200, body = Vec<InscriptionRecord>)))]
+async fn get_recent_inscriptions(State(state): State<AppState>) -> Json<Vec<InscriptionRecord>> {
+    blocking(&state, move |state| {
+        let inscriptions = state.db.get_inscriptions_page(0, 50, None).unwrap_or_default();
+        let data = inscriptions.into_iter().map(|(id, meta)| {
+            InscriptionRecord {
+                id,
+                meta: Some(serde_json::from_str::<serde_json::Value>(&meta).unwrap_or(serde_json::Value::String(meta))),
+            }
+        }).collect();
+        Json(data)
+    })
+    .await
+}
+
+/// Inscription page, or its stored metadata with `id` when `Accept` prefers JSON
+#[utoipa::path(
+    get,
+    path = "/inscription/{id}",
+    tag = "inscriptions",
+    params(("id" = String, Path, description = "Inscription id")),
+    responses((
+        status = 200,
+        description = "HTML for browsers; stored metadata for clients that ask for JSON",
+        content((serde_json::Value = "application/json"), (String = "text/html"))
+    ))
+)]
+async fn get_inscription(State(state): State<AppState>, Path(id): Path<String>, headers: HeaderMap) -> Response {
+    blocking(&state, move |state| {
+        let mut response = if prefers_json(&headers) {
+            inscription_json(&state, id).into_response()
+        } else {
+            inscription_page(&state, &id)
+        };
+        response.headers_mut().insert(header::VARY, HeaderValue::from_static("accept"));
+        response
+    })
+    .await
+}
+
+fn inscription_json(state: &AppState, id: String) -> ApiResult<Json<serde_json::Value>> {
+    let raw = state
+        .db
+        .get_inscription(&id)
+        .map_err(|e| ApiError::internal("inscription lookup error", e))?
+        .ok_or_else(|| ApiError::not_found(format!("Inscription {} not found", id)))?;
+    Ok(Json(v2::inscription(id, &raw)))
 }
 
-async fn get_inscription(State(state): State<AppState>, Path(id): Path<String>) -> Response {
-    let meta = match state.db.get_inscription(&id).unwrap_or(None) {
+// Whether `Accept` names JSON and ranks it above HTML; wildcards keep the HTML page
+fn prefers_json(headers: &HeaderMap) -> bool {
+    let Some(accept) = headers.get(header::ACCEPT).and_then(|v| v.to_str().ok()) else {
+        return false;
+    };
+    let (mut json, mut html) = (0.0f32, 0.0f32);
+    for range in accept.split(',') {
+        let mut parts = range.split(';');
+        let media = parts.next().unwrap_or("").trim().to_ascii_lowercase();
+        let quality = parts
+            .filter_map(|p| p.trim().strip_prefix("q="))
+            .find_map(|q| q.trim().parse::<f32>().ok())
+            .unwrap_or(1.0);
+        match media.as_str() {
+            "application/json" => json = json.max(quality),
+            "text/html" | "application/xhtml+xml" => html = html.max(quality),
+            _ => {}
+        }
+    }
+    json > html
+}
+
+fn inscription_page(state: &AppState, id: &str) -> Response {
+    let meta = match state.db.get_inscription(id).unwrap_or(None) {
         Some(m) => m,
         None => {
             return Html(
@@ -367,8 +1196,9 @@ async fn get_inscription(State(state): State<AppState>, Path(id): Path<String>)
     };
 
     let content_type_raw = val["content_type"].as_str().unwrap_or("text/plain");
-    let content = val["content"].as_str().unwrap_or("");
-    let content_hex = val["content_hex"].as_str().unwrap_or("");
+    let content_bytes = state.db.get_content(id).unwrap_or(None).unwrap_or_default();
+    let content_text = String::from_utf8_lossy(&content_bytes);
+    let content: &str = &content_text;
     let sender_raw = val["sender"].as_str().unwrap_or("unknown");
     let receiver_raw = val["receiver"].as_str().unwrap_or("unknown");
     let txid_raw = val["txid"].as_str().unwrap_or("");
@@ -382,7 +1212,10 @@ async fn get_inscription(State(state): State<AppState>, Path(id): Path<String>)
     let id_text = html_escape::encode_text(&id).to_string();
     let id_attr = html_escape::encode_double_quoted_attribute(&id).to_string();
     let short_id: String = id_text.chars().take(16).collect();
-    let content_length_bytes = content_hex.len() / 2;
+    let content_length_bytes = val["content_length"]
+        .as_u64()
+        .map(|n| n as usize)
+        .unwrap_or(content_bytes.len());
     let size_display = format_byte_size(content_length_bytes);
     let timestamp_display = block_time.map(format_timestamp).unwrap_or_else(|| "—".into());
     let category = classify_mime(content_type_raw);
@@ -403,7 +1236,8 @@ async fn get_inscription(State(state): State<AppState>, Path(id): Path<String>)
         )
     } else if content_type_raw == "text/html" {
         format!(
-            r#"<div class=\"preview-box\"><iframe src=\"/content/{id}\" title=\"{short}\" loading=\"lazy\"></iframe></div>"#,
+            r#"<div class=\"preview-box\"><iframe src=\"{origin}/content/{id}\" title=\"{short}\" sandbox=\"allow-scripts\" loading=\"lazy\"></iframe></div>"#,
+            origin = html_escape::encode_double_quoted_attribute(&*state.content_origin),
             id = id_attr,
             short = short_id,
         )
@@ -503,426 +1337,2110 @@ async fn get_inscription(State(state): State<AppState>, Path(id): Path<String>)
     Html(html).into_response()
 }
 
+#[derive(Deserialize)]
+struct ContentParams {
+    // Skip SVG sanitizing and return the inscribed bytes
+    raw: Option<bool>,
+}
+
 async fn get_inscription_content(
     State(state): State<AppState>,
     Path(id): Path<String>,
+    Query(params): Query<ContentParams>,
+    headers: HeaderMap,
 ) -> Response {
-    let meta = match state.db.get_inscription(&id).unwrap_or(None) {
-        Some(m) => m,
-        None => return (StatusCode::NOT_FOUND, "Not found").into_response(),
-    };
-
-    let val: serde_json::Value = match serde_json::from_str(&meta) {
-        Ok(v) => v,
-        Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, "Invalid metadata").into_response(),
-    };
+    blocking(&state, move |state| {
+        let meta = match state.db.get_inscription(&id).unwrap_or(None) {
+            Some(m) => m,
+            None => return (StatusCode::NOT_FOUND, "Not found").into_response(),
+        };
 
-    let content_type = val["content_type"].as_str().unwrap_or("text/plain");
-    let content_hex = val["content_hex"].as_str().unwrap_or("");
+        let val: serde_json::Value = match serde_json::from_str(&meta) {
+            Ok(v) => v,
+            Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, "Invalid metadata").into_response(),
+        };
 
-    // Materialize stored hex payload
-    let content_bytes = match hex::decode(content_hex) {
-        Ok(bytes) => bytes,
-        Err(_) => {
-            return (StatusCode::INTERNAL_SERVER_ERROR, "Invalid content data").into_response()
+        let content_type = val["content_type"].as_str().unwrap_or("text/plain");
+        let sanitize = state.sanitize_svg && !params.raw.unwrap_or(false) && is_svg(content_type);
+        // Sanitized SVGs no longer match the inscribed length, so they are always served whole
+        let length = val["content_length"].as_u64().filter(|_| !sanitize);
+        let content_headers = content_headers(content_type, length.is_some());
+
+        // Serve one byte range when asked; multi-range requests fall back to the whole payload
+        let range = headers.get(header::RANGE).and_then(|v| v.to_str().ok());
+        if let (Some(range), Some(length)) = (range, length) {
+            let length = length as usize;
+            match parse_byte_range(range, length) {
+                Some(Ok((start, end))) => {
+                    return match state.db.get_content_range(&id, start, end) {
+                        Ok(Some((bytes, total))) => (
+                            StatusCode::PARTIAL_CONTENT,
+                            content_headers,
+                            [(
+                                header::CONTENT_RANGE,
+                                format!("bytes {}-{}/{}", start, start + bytes.len().saturating_sub(1), total),
+                            )],
+                            bytes,
+                        )
+                            .into_response(),
+                        Ok(None) => (StatusCode::NOT_FOUND, "Content not found").into_response(),
+                        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Invalid content data").into_response(),
+                    };
+                }
+                Some(Err(())) => {
+                    return (
+                        StatusCode::RANGE_NOT_SATISFIABLE,
+                        [(header::CONTENT_RANGE, format!("bytes */{}", length))],
+                    )
+                        .into_response()
+                }
+                None => {}
+            }
         }
-    };
 
-    // Preserve original MIME type
-    (
-        StatusCode::OK,
-        [(header::CONTENT_TYPE, content_type)],
-        content_bytes,
-    )
-        .into_response()
+        // Payload bytes live in the content store, not in the metadata
+        let content_bytes = match state.db.get_content(&id) {
+            Ok(Some(bytes)) => bytes,
+            Ok(None) => return (StatusCode::NOT_FOUND, "Content not found").into_response(),
+            Err(_) => {
+                return (StatusCode::INTERNAL_SERVER_ERROR, "Invalid content data").into_response()
+            }
+        };
+
+        let content_bytes = if sanitize { svg::sanitize(&content_bytes) } else { content_bytes };
+
+        // Preserve original MIME type
+        (StatusCode::OK, content_headers, content_bytes).into_response()
+    })
+    .await
 }
 
-async fn get_inscription_by_number(
+// Headers `GET /content/:id` would send, from the metadata alone. Sanitized SVGs only
+// know their length once rewritten, so theirs is left out.
+async fn head_inscription_content(
     State(state): State<AppState>,
-    Path(number): Path<u64>,
-) -> Json<serde_json::Value> {
-    // Lookup inscription by ordinal number
-
-    let id = state.db.get_inscription_by_number(number).unwrap_or(None);
-    if let Some(inscription_id) = id {
-        // Embed the resolved id/number in the JSON blob
-        let meta = state.db.get_inscription(&inscription_id).unwrap_or(None);
-        if let Some(m) = meta {
-            let mut val = serde_json::from_str::<serde_json::Value>(&m)
-                .unwrap_or(serde_json::Value::String(m));
-            if let Some(obj) = val.as_object_mut() {
-                obj.insert("id".to_string(), serde_json::Value::String(inscription_id));
-                obj.insert("number".to_string(), serde_json::json!(number));
-            }
-            Json(val)
-        } else {
-            Json(serde_json::json!({ "error": "Inscription data missing" }))
-        }
-    } else {
-        Json(serde_json::json!({ "error": "Not found" }))
+    Path(id): Path<String>,
+    Query(params): Query<ContentParams>,
+) -> Response {
+    let val: serde_json::Value = match state.db.get_inscription(&id) {
+        Ok(Some(meta)) => serde_json::from_str(&meta).unwrap_or_default(),
+        Ok(None) => return StatusCode::NOT_FOUND.into_response(),
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    };
+    let content_type = val["content_type"].as_str().unwrap_or("text/plain");
+    let sanitize = state.sanitize_svg && !params.raw.unwrap_or(false) && is_svg(content_type);
+    let length = val["content_length"].as_u64().filter(|_| !sanitize);
+    let mut headers = content_headers(content_type, length.is_some());
+    if let Some(length) = length {
+        headers.insert(header::CONTENT_LENGTH, HeaderValue::from(length));
     }
+    (StatusCode::OK, headers).into_response()
 }
 
-async fn get_address_inscriptions(
-    State(state): State<AppState>,
-    Path(address): Path<String>,
-) -> Json<serde_json::Value> {
-    let inscriptions = state
-        .db
-        .get_inscriptions_by_address(&address)
-        .unwrap_or_default();
-    Json(serde_json::json!(inscriptions))
+fn is_svg(content_type: &str) -> bool {
+    content_type.split(';').next().unwrap_or("").trim().eq_ignore_ascii_case("image/svg+xml")
 }
 
-async fn get_token_info(
-    State(state): State<AppState>,
-    Path(tick): Path<String>,
-) -> Json<serde_json::Value> {
-    let info = state.db.get_token_info(&tick).unwrap_or(None);
-    if let Some(i) = info {
-        let val =
-            serde_json::from_str::<serde_json::Value>(&i).unwrap_or(serde_json::Value::String(i));
-        Json(val)
+// Policy for documents served from `/content`: scripts may run, but in an opaque
+// origin with no access to this host's storage, and only recursive `/content` loads
+const CONTENT_SECURITY_POLICY: &str = "sandbox allow-scripts; default-src 'self' data: blob:; \
+script-src 'self' 'unsafe-inline' 'unsafe-eval' data: blob:; style-src 'self' 'unsafe-inline' data:; \
+connect-src 'self'; form-action 'none'; base-uri 'none'";
+
+// Response headers for raw inscription bytes. The inscribed MIME type is kept, but
+// browsers may not sniff past it, anything unparseable is served as opaque bytes and
+// anything a browser could render as a document is confined by `CONTENT_SECURITY_POLICY`.
+// `Accept-Ranges` is only sent when the response would honor a `Range` header.
+fn content_headers(content_type: &str, ranges: bool) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    let value = HeaderValue::from_str(content_type.trim())
+        .ok()
+        .filter(|v| v.to_str().is_ok_and(|t| t.contains('/')))
+        .unwrap_or(HeaderValue::from_static("application/octet-stream"));
+    let simple = content_type.split(';').next().unwrap_or("").trim().to_lowercase();
+    headers.insert(header::CONTENT_TYPE, value);
+    if ranges {
+        headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+    }
+    headers.insert(header::X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff"));
+    let passive = ["image/", "audio/", "video/"].iter().any(|p| simple.starts_with(p)) && simple != "image/svg+xml";
+    if !passive {
+        headers.insert(header::CONTENT_SECURITY_POLICY, HeaderValue::from_static(CONTENT_SECURITY_POLICY));
+    }
+    headers
+}
+
+#[derive(Deserialize)]
+struct ThumbParams {
+    size: Option<u32>,
+}
+
+// Inscriptions never change, so thumbnails can be cached for good
+async fn get_inscription_thumb(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<ThumbParams>,
+) -> Response {
+    let meta = match state.db.get_inscription(&id) {
+        Ok(Some(raw)) => serde_json::from_str::<serde_json::Value>(&raw).unwrap_or_default(),
+        Ok(None) => return (StatusCode::NOT_FOUND, "Not found").into_response(),
+        Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, "Invalid metadata").into_response(),
+    };
+    let size = thumbs::snap_size(params.size);
+    let content_type = meta["content_type"].as_str().unwrap_or("").to_string();
+    let digest = meta["content_sha256"].as_str().unwrap_or("").to_string();
+
+    let db = state.db.clone();
+    let sanitize_svg = state.sanitize_svg;
+    let rendered = tokio::task::spawn_blocking(move || -> anyhow::Result<thumbs::Thumb> {
+        if !digest.is_empty() {
+            if let Some(bytes) = db.get_thumbnail(&digest, size)? {
+                return Ok(thumbs::Thumb { content_type: "image/png".to_string(), bytes });
+            }
+        }
+        let content = db.get_content(&id)?.unwrap_or_default();
+        let mut thumb = thumbs::render(&content_type, &content, size, classify_mime(&content_type));
+        if sanitize_svg && is_svg(&content_type) {
+            thumb.bytes = svg::sanitize(&thumb.bytes);
+        }
+        // Only re-encoded rasters are cached; SVGs and placeholders are cheap to rebuild
+        if thumb.content_type == "image/png" && !digest.is_empty() {
+            if let Err(e) = db.put_thumbnail(&digest, size, &thumb.bytes) {
+                tracing::debug!("thumbnail not cached: {}", e);
+            }
+        }
+        Ok(thumb)
+    })
+    .await;
+
+    match rendered {
+        Ok(Ok(thumb)) => (
+            [
+                (header::CONTENT_TYPE, thumb.content_type),
+                (header::CACHE_CONTROL, "public, max-age=31536000, immutable".to_string()),
+                (header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+            ],
+            thumb.bytes,
+        )
+            .into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("thumbnail error: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+        Err(e) => {
+            tracing::error!("thumbnail task failed: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+// Single `bytes=` range as inclusive offsets: `None` if absent or unsupported (serve it all),
+// `Some(Err)` if it cannot be satisfied for a payload of `length` bytes
+fn parse_byte_range(value: &str, length: usize) -> Option<Result<(usize, usize), ()>> {
+    let spec = value.trim().strip_prefix("bytes=")?;
+    if spec.contains(',') {
+        return None;
+    }
+    let (first, last) = spec.split_once('-')?;
+    let (first, last) = (first.trim(), last.trim());
+    let range = if first.is_empty() {
+        // Suffix form: the final N bytes
+        let n = last.parse::<usize>().ok()?;
+        if n == 0 || length == 0 {
+            return Some(Err(()));
+        }
+        (length.saturating_sub(n), length - 1)
     } else {
-        Json(serde_json::json!({ "error": "Not found" }))
+        let start = first.parse::<usize>().ok()?;
+        let end = match last {
+            "" => length.saturating_sub(1),
+            end => end.parse::<usize>().ok()?.min(length.saturating_sub(1)),
+        };
+        if start >= length || start > end {
+            return Some(Err(()));
+        }
+        (start, end)
+    };
+    Some(Ok(range))
+}
+
+/// Inscription metadata by ordinal number
+#[utoipa::path(
+    get,
+    path = "/inscription/number/{number}",
+    tag = "inscriptions",
+    params(("number" = u64, Path, description = "Inscription number")),
+    responses((status = 200, description = "Stored metadata with `id` and `number`", body = serde_json::Value))
+)]
+async fn get_inscription_by_number(
+    State(state): State<AppState>,
+    Path(number): Path<u64>,
+) -> ApiResult<Json<serde_json::Value>> {
+    // Lookup inscription by ordinal number
+    let inscription_id = state
+        .db
+        .get_inscription_by_number(number)
+        .map_err(|e| ApiError::internal("inscription number lookup error", e))?
+        .ok_or_else(|| ApiError::not_found(format!("No inscription numbered {}", number)))?;
+    // Embed the resolved id/number in the JSON blob
+    let m = state
+        .db
+        .get_inscription(&inscription_id)
+        .map_err(|e| ApiError::internal("inscription lookup error", e))?
+        .ok_or_else(|| ApiError::internal("inscription data missing", &inscription_id))?;
+    let mut val = serde_json::from_str::<serde_json::Value>(&m).unwrap_or(serde_json::Value::String(m));
+    if let Some(obj) = val.as_object_mut() {
+        obj.insert("id".to_string(), serde_json::Value::String(inscription_id));
+        obj.insert("number".to_string(), serde_json::json!(number));
     }
+    Ok(Json(val))
+}
+
+/// Inscription ids created by an address
+#[utoipa::path(
+    get,
+    path = "/address/{address}/inscriptions",
+    tag = "inscriptions",
+    params(("address" = String, Path, description = "Creating address")),
+    responses((status = 200, body = Vec<String>))
+)]
+async fn get_address_inscriptions(
+    State(state): State<AppState>,
+    Path(address): Path<String>,
+) -> Json<Vec<String>> {
+    blocking(&state, move |state| {
+        let inscriptions = state
+            .db
+            .get_inscriptions_by_address(&address)
+            .unwrap_or_default();
+        Json(inscriptions)
+    })
+    .await
+}
+
+/// Stored deploy record
+#[utoipa::path(
+    get,
+    path = "/api/v1/zrc20/token/{tick}",
+    tag = "zrc20",
+    params(("tick" = String, Path, description = "Ticker")),
+    responses((status = 200, description = "Deploy record: max, lim, dec, supply, deployer, inscription_id", body = serde_json::Value))
+)]
+async fn get_token_info(
+    State(state): State<AppState>,
+    Path(tick): Path<String>,
+) -> ApiResult<Json<serde_json::Value>> {
+    let info = state
+        .db
+        .get_token_info(&tick)
+        .map_err(|e| ApiError::internal("token lookup error", e))?
+        .ok_or_else(|| ApiError::not_found(format!("Token {} not found", tick)))?;
+    Ok(Json(serde_json::from_str::<serde_json::Value>(&info).unwrap_or(serde_json::Value::String(info))))
+}
+
+#[derive(Serialize, ToSchema)]
+struct SupplyCheck {
+    /// Supply equals the holder sum plus burns
+    consistent: bool,
+    sum_holders_base_units: String,
+    burned_base_units: String,
+}
+
+#[derive(Serialize, ToSchema)]
+struct Zrc20Summary {
+    tick: String,
+    dec: String,
+    supply_base_units: String,
+    /// Addresses with a positive balance
+    holders: usize,
+    /// Every balance row, zero balances included
+    holders_total: usize,
+    transfers_completed: u64,
+    max: String,
+    lim: String,
+    integrity: SupplyCheck,
 }
 
+/// Holder counts, transfers and integrity summary
+#[utoipa::path(
+    get,
+    path = "/api/v1/zrc20/token/{tick}/summary",
+    tag = "zrc20",
+    params(("tick" = String, Path, description = "Ticker")),
+    responses((status = 200, body = Zrc20Summary))
+)]
 async fn get_zrc20_token_summary(
     State(state): State<AppState>,
     Path(tick): Path<String>,
-) -> impl IntoResponse {
-    let lower = tick.to_lowercase();
-    let token_info = state.db.get_token_info(&lower).unwrap_or(None);
-    if let Some(raw) = token_info {
-        if let Ok(info) = serde_json::from_str::<serde_json::Value>(&raw) {
-            let dec = info["dec"].as_str().unwrap_or("18");
-            let supply_base = info["supply"].as_str().unwrap_or("0").to_string();
-            let max = info["max"].as_str().unwrap_or("0");
-            let lim = info["lim"].as_str().unwrap_or("");
-            let (sum_overall, _sum_avail, holders_total, holders_positive) =
-                state.db.sum_balances_for_tick(&lower).unwrap_or((0, 0, 0, 0));
-            let transfers_completed = state
-                .db
-                .count_completed_transfers_for_tick(&lower)
-                .unwrap_or(0);
-            let burned = state.db.get_burned(&lower).unwrap_or(0);
-            let consistent = parse_u128(&supply_base) == sum_overall + burned;
-            let body = serde_json::json!({
-                "tick": lower,
-                "dec": dec,
-                "supply_base_units": supply_base,
-                // Report holders as positive-balance addresses; also include total rows for transparency
-                "holders": holders_positive,
-                "holders_total": holders_total,
-                "transfers_completed": transfers_completed,
-                "max": max,
-                "lim": lim,
-                "integrity": { "consistent": consistent, "sum_holders_base_units": sum_overall.to_string(), "burned_base_units": burned.to_string() }
-            });
-            let mut headers = axum::http::HeaderMap::new();
-            headers.insert(header::CACHE_CONTROL, axum::http::HeaderValue::from_static("public, max-age=10"));
-            return (headers, Json(body));
+) -> Response {
+    blocking(&state, move |state| {
+        let lower = tick.to_lowercase();
+        let token_info = state.db.get_token_info(&lower).unwrap_or(None);
+        if let Some(raw) = token_info {
+            if let Ok(info) = serde_json::from_str::<serde_json::Value>(&raw) {
+                let dec = info["dec"].as_str().unwrap_or("18");
+                let supply_base = info["supply"].as_str().unwrap_or("0").to_string();
+                let max = info["max"].as_str().unwrap_or("0");
+                let lim = info["lim"].as_str().unwrap_or("");
+                let (sum_overall, _sum_avail, holders_total, holders_positive) =
+                    state.db.sum_balances_for_tick(&lower).unwrap_or((0, 0, 0, 0));
+                let transfers_completed = state
+                    .db
+                    .count_completed_transfers_for_tick(&lower)
+                    .unwrap_or(0);
+                let burned = state.db.get_burned(&lower).unwrap_or(0);
+                let consistent = parse_u128(&supply_base) == sum_overall + burned;
+                let body = Zrc20Summary {
+                    tick: lower,
+                    dec: dec.to_string(),
+                    supply_base_units: supply_base,
+                    // Report holders as positive-balance addresses; also include total rows for transparency
+                    holders: holders_positive,
+                    holders_total,
+                    transfers_completed,
+                    max: max.to_string(),
+                    lim: lim.to_string(),
+                    integrity: SupplyCheck {
+                        consistent,
+                        sum_holders_base_units: sum_overall.to_string(),
+                        burned_base_units: burned.to_string(),
+                    },
+                };
+                let mut headers = HeaderMap::new();
+                headers.insert(header::CACHE_CONTROL, axum::http::HeaderValue::from_static("public, max-age=10"));
+                return (headers, Json(body)).into_response();
+            }
         }
-    }
-    {
-        let mut headers = axum::http::HeaderMap::new();
-        headers.insert(header::CACHE_CONTROL, axum::http::HeaderValue::from_static("public, max-age=10"));
-        (headers, Json(serde_json::json!({ "error": "Not found" })))
-    }
+        ApiError::not_found(format!("Token {} not found", lower)).into_response()
+    })
+    .await
+}
+
+/// Holder concentration, balance buckets and supply over time
+#[utoipa::path(
+    get,
+    path = "/api/v1/zrc20/token/{tick}/distribution",
+    tag = "zrc20",
+    params(("tick" = String, Path, description = "Ticker")),
+    responses((status = 200, body = Distribution))
+)]
+async fn get_zrc20_distribution(
+    State(state): State<AppState>,
+    Path(tick): Path<String>,
+) -> ApiResult<Json<Distribution>> {
+    blocking(&state, move |state| {
+        let distribution = state
+            .db
+            .zrc20_distribution(&tick)
+            .map_err(|e| ApiError::internal("zrc20 distribution error", e))?
+            .ok_or_else(|| ApiError::not_found(format!("Token {} not found", tick.to_lowercase())))?;
+        Ok(Json(distribution))
+    })
+    .await
 }
 
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ProofParams {
+    /// Epoch (multiple of 1000) whose commitment to use; the latest one by default
+    height: Option<u64>,
+}
+
+/// Merkle proof of a balance under a committed root
+#[utoipa::path(
+    get,
+    path = "/api/v1/zrc20/token/{tick}/proof/{address}",
+    tag = "zrc20",
+    params(
+        ("tick" = String, Path, description = "Ticker"),
+        ("address" = String, Path, description = "Holder address"),
+        ProofParams
+    ),
+    responses((status = 200, body = BalanceProof))
+)]
+async fn get_zrc20_balance_proof(
+    State(state): State<AppState>,
+    Path((tick, address)): Path<(String, String)>,
+    Query(params): Query<ProofParams>,
+) -> ApiResult<Json<BalanceProof>> {
+    blocking(&state, move |state| {
+        let tick = tick.to_lowercase();
+        let internal = |e: anyhow::Error| ApiError::internal("balance proof error", e);
+        let root = state
+            .db
+            .get_balance_root(&tick, params.height)
+            .map_err(internal)?
+            .ok_or_else(|| match params.height {
+                Some(height) => ApiError::not_found(format!("No balance root for {} at height {}", tick, height)),
+                None => ApiError::not_found(format!("No balance root committed for {} yet", tick)),
+            })?;
+        let proof = state
+            .db
+            .balance_proof(&tick, &address, &root)
+            .map_err(internal)?
+            .ok_or_else(|| {
+                ApiError::not_found(format!("{} held no {} at height {}", address, tick, root.height))
+            })?;
+        Ok(Json(proof))
+    })
+    .await
+}
+
+/// Checksum of the ZRC-20 state at a checkpoint epoch
+///
+/// Compare with other indexers: equal checksums at a height mean equal ZRC-20 state.
+#[utoipa::path(get, path = "/api/v1/zrc20/checksum", tag = "zrc20", params(ProofParams), responses((status = 200, body = StateChecksum)))]
+async fn get_zrc20_checksum(
+    State(state): State<AppState>,
+    Query(params): Query<ProofParams>,
+) -> ApiResult<Json<StateChecksum>> {
+    blocking(&state, move |state| {
+        let checksum = state
+            .db
+            .get_zrc20_checksum(params.height)
+            .map_err(|e| ApiError::internal("zrc20 checksum error", e))?
+            .ok_or_else(|| match params.height {
+                Some(height) => ApiError::not_found(format!("No ZRC-20 checksum at height {}", height)),
+                None => ApiError::not_found("No ZRC-20 checksum committed yet"),
+            })?;
+        Ok(Json(checksum))
+    })
+    .await
+}
+
+#[derive(Serialize, ToSchema)]
+struct HolderRank {
+    tick: String,
+    address: String,
+    /// 1 for the largest holder; 0 when the address holds none
+    rank: u64,
+    total_holders: u64,
+    /// Share of holders at or below this rank, 0-100
+    percentile: f64,
+}
+
+/// Holder rank and percentile
+#[utoipa::path(
+    get,
+    path = "/api/v1/zrc20/token/{tick}/rank/{address}",
+    tag = "zrc20",
+    params(("tick" = String, Path, description = "Ticker"), ("address" = String, Path, description = "Holder address")),
+    responses((status = 200, body = HolderRank))
+)]
 async fn get_zrc20_rank(
     State(state): State<AppState>,
     Path((tick, address)): Path<(String, String)>,
-) -> Json<serde_json::Value> {
-    let (rank, total) = state
-        .db
-        .rank_for_address_in_tick(&tick, &address)
-        .unwrap_or((0, 0));
-    let percentile = if total == 0 || rank == 0 {
-        0.0
-    } else {
-        // Higher balance = better (lower) rank; percentile as top share
-        let r = rank as f64;
-        let t = total as f64;
-        (1.0 - (r - 1.0) / t) * 100.0
-    };
-    Json(serde_json::json!({
-        "tick": tick,
-        "address": address,
-        "rank": rank,
-        "total_holders": total,
-        "percentile": percentile
-    }))
+) -> Json<HolderRank> {
+    blocking(&state, move |state| {
+        let (rank, total) = state
+            .db
+            .rank_for_address_in_tick(&tick, &address)
+            .unwrap_or((0, 0));
+        let percentile = if total == 0 || rank == 0 {
+            0.0
+        } else {
+            // Higher balance = better (lower) rank; percentile as top share
+            let r = rank as f64;
+            let t = total as f64;
+            (1.0 - (r - 1.0) / t) * 100.0
+        };
+        Json(HolderRank { tick, address, rank, total_holders: total, percentile })
+    })
+    .await
+}
+
+/// One address's balance of a ticker, in base units
+#[derive(Serialize, ToSchema)]
+struct TickBalance {
+    tick: String,
+    address: String,
+    available: String,
+    /// Locked in unused transfer inscriptions
+    transferable: String,
+    overall: String,
+}
+
+impl TickBalance {
+    fn of(db: &Db, tick: String, address: String, balance: crate::db::Balance) -> Self {
+        TickBalance {
+            transferable: transferable_of(db, &address, &tick).to_string(),
+            available: balance.available.to_string(),
+            overall: balance.overall.to_string(),
+            tick,
+            address,
+        }
+    }
 }
 
+/// Balance of one address (legacy path)
+#[utoipa::path(
+    get,
+    path = "/token/{tick}/balance/{address}",
+    tag = "zrc20",
+    params(("tick" = String, Path, description = "Ticker"), ("address" = String, Path, description = "Holder address")),
+    responses((status = 200, body = TickBalance))
+)]
 async fn get_balance(
     State(state): State<AppState>,
     Path((tick, address)): Path<(String, String)>,
-) -> Json<serde_json::Value> {
+) -> Json<TickBalance> {
     let balance = state
         .db
         .get_balance(&address, &tick)
-        .unwrap_or(crate::db::Balance {
-            available: 0,
-            overall: 0,
-        });
-    Json(serde_json::json!({
-        "tick": tick,
-        "address": address,
-        "available": balance.available,
-        "overall": balance.overall
+        .unwrap_or_default();
+    Json(TickBalance::of(&state.db, tick, address, balance))
+}
+
+#[derive(Deserialize, ToSchema)]
+struct BalancePair {
+    tick: String,
+    address: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+struct BalancesBatchRequest {
+    /// At most 1000 pairs
+    pairs: Vec<BalancePair>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct BalancesBatch {
+    /// In request order
+    items: Vec<TickBalance>,
+}
+
+/// Balances for up to 1000 `{ tick, address }` pairs
+#[utoipa::path(
+    post,
+    path = "/api/v1/zrc20/balances/batch",
+    tag = "zrc20",
+    request_body = BalancesBatchRequest,
+    responses((status = 200, body = BalancesBatch))
+)]
+async fn post_zrc20_balances_batch(
+    State(state): State<AppState>,
+    Json(req): Json<BalancesBatchRequest>,
+) -> ApiResult<Json<BalancesBatch>> {
+    blocking(&state, move |state| {
+        check_batch_size(req.pairs.len())?;
+        let mut items = Vec::with_capacity(req.pairs.len());
+        for pair in req.pairs {
+            let tick = pair.tick.to_lowercase();
+            let balance = state
+                .db
+                .get_balance(&pair.address, &tick)
+                .map_err(|e| ApiError::internal("batch balance error", e))?;
+            items.push(TickBalance::of(&state.db, tick, pair.address, balance));
+        }
+        Ok(Json(BalancesBatch { items }))
+    })
+    .await
+}
+
+#[derive(Deserialize, ToSchema)]
+struct Zrc20ValidateRequest {
+    /// The operation as an object, or the exact inscription body as a string
+    op: serde_json::Value,
+    sender: String,
+}
+
+#[derive(Serialize, ToSchema)]
+struct Zrc20Validation {
+    valid: bool,
+    /// The normalized operation, when valid
+    #[serde(skip_serializing_if = "Option::is_none")]
+    operation: Option<crate::zrc20::Zrc20Operation>,
+    /// Why the indexer would reject it, when not
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Pre-flight a ZRC-20 inscription against the committed state
+///
+/// A rejection is an answer (`valid: false`), not an HTTP error.
+#[utoipa::path(
+    post,
+    path = "/api/v1/zrc20/validate",
+    tag = "zrc20",
+    request_body = Zrc20ValidateRequest,
+    responses((status = 200, body = Zrc20Validation))
+)]
+async fn post_zrc20_validate(
+    State(state): State<AppState>,
+    Json(req): Json<Zrc20ValidateRequest>,
+) -> ApiResult<Json<Zrc20Validation>> {
+    let content = match req.op {
+        serde_json::Value::String(body) => body,
+        serde_json::Value::Object(_) => req.op.to_string(),
+        _ => return Err(ApiError::bad_request("op must be a JSON object or the inscription body as a string")),
+    };
+    if req.sender.is_empty() {
+        return Err(ApiError::bad_request("sender is required"));
+    }
+    blocking(&state, move |state| {
+        // Judged as if inscribed in the next block
+        let height = state
+            .db
+            .get_latest_indexed_height()
+            .map_err(|e| ApiError::internal("zrc20 validate error", e))?
+            .map_or(0, |h| h + 1);
+        Ok(Json(match Zrc20Engine.validate(&state.db, &req.sender, &content, height) {
+            Ok(op) => Zrc20Validation { valid: true, operation: Some(op), error: None },
+            Err(e) => Zrc20Validation { valid: false, operation: None, error: Some(e.to_string()) },
+        }))
+    })
+    .await
+}
+
+// Rows per chunk of a streamed holder export
+const EXPORT_CHUNK_ROWS: usize = 1000;
+
+/// One row of a holder export
+#[derive(Serialize, ToSchema)]
+struct HolderRow {
+    /// 1 for the largest holder
+    rank: usize,
+    address: String,
+    available: String,
+    overall: String,
+}
+
+/// Every positive holder as CSV (`rank,address,available,overall`), streamed
+#[utoipa::path(
+    get,
+    path = "/api/v1/zrc20/token/{tick}/holders.csv",
+    tag = "zrc20",
+    params(("tick" = String, Path, description = "Ticker")),
+    responses((status = 200, content_type = "text/csv", body = String))
+)]
+async fn get_zrc20_holders_csv(State(state): State<AppState>, Path(tick): Path<String>) -> Response {
+    export_holders(state, tick, ExportFormat::Csv).await
+}
+
+/// Every positive holder as one JSON array, streamed
+#[utoipa::path(
+    get,
+    path = "/api/v1/zrc20/token/{tick}/holders.json",
+    tag = "zrc20",
+    params(("tick" = String, Path, description = "Ticker")),
+    responses((status = 200, body = Vec<HolderRow>))
+)]
+async fn get_zrc20_holders_json(State(state): State<AppState>, Path(tick): Path<String>) -> Response {
+    export_holders(state, tick, ExportFormat::Json).await
+}
+
+/// Every positive holder, one JSON object per line, streamed
+#[utoipa::path(
+    get,
+    path = "/api/v1/zrc20/token/{tick}/holders.ndjson",
+    tag = "zrc20",
+    params(("tick" = String, Path, description = "Ticker")),
+    responses((status = 200, content_type = "application/x-ndjson", body = HolderRow))
+)]
+async fn get_zrc20_holders_ndjson(State(state): State<AppState>, Path(tick): Path<String>) -> Response {
+    export_holders(state, tick, ExportFormat::Ndjson).await
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ExportFormat {
+    Csv,
+    /// One JSON array
+    Json,
+    /// One JSON object per line
+    Ndjson,
+}
+
+impl ExportFormat {
+    fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Json => "application/json",
+            ExportFormat::Ndjson => "application/x-ndjson",
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+            ExportFormat::Ndjson => "ndjson",
+        }
+    }
+}
+
+fn export_response(format: ExportFormat, filename: &str, body: Body) -> Response {
+    (
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}.{}\"", filename, format.extension()),
+            ),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+// Every positive holder in rank order, streamed in chunks as CSV, a JSON array or NDJSON
+async fn export_holders(state: AppState, tick: String, format: ExportFormat) -> Response {
+    let tick = tick.to_lowercase();
+    match state.db.get_token_info(&tick) {
+        Ok(Some(_)) => {}
+        Ok(None) => return ApiError::not_found(format!("Token {} not found", tick)).into_response(),
+        Err(e) => return ApiError::internal("holders export error", e).into_response(),
+    }
+    let db = state.db.clone();
+    let lookup = tick.clone();
+    let rows = match tokio::task::spawn_blocking(move || {
+        db.list_balances_for_tick_filtered(&lookup, 0, usize::MAX, true)
+    })
+    .await
+    {
+        Ok(Ok((rows, _, _))) => rows,
+        Ok(Err(e)) => return ApiError::internal("holders export error", e).into_response(),
+        Err(e) => return ApiError::internal("holders export task failed", e).into_response(),
+    };
+
+    let preamble = match format {
+        ExportFormat::Csv => "rank,address,available,overall\n",
+        ExportFormat::Json => "[",
+        ExportFormat::Ndjson => "",
+    };
+    let mut rows = rows.into_iter().enumerate();
+    // Chunks are formatted lazily as the client reads them
+    let body = std::iter::from_fn(move || {
+        let mut out = String::new();
+        for (index, (address, balance)) in rows.by_ref().take(EXPORT_CHUNK_ROWS) {
+            if format == ExportFormat::Csv {
+                // Addresses are base58/bech32, so no field needs quoting
+                out.push_str(&format!("{},{},{},{}\n", index + 1, address, balance.available, balance.overall));
+                continue;
+            }
+            let row = HolderRow {
+                rank: index + 1,
+                available: balance.available.to_string(),
+                overall: balance.overall.to_string(),
+                address,
+            };
+            if format == ExportFormat::Json && index > 0 {
+                out.push(',');
+            }
+            out.push_str(&serde_json::to_string(&row).unwrap_or_default());
+            if format == ExportFormat::Ndjson {
+                out.push('\n');
+            }
+        }
+        (!out.is_empty()).then_some(Ok::<_, std::convert::Infallible>(out))
+    });
+    let footer = (format == ExportFormat::Json).then(|| Ok("]".to_string()));
+    let stream = futures::stream::iter(std::iter::once(Ok(preamble.to_string())).chain(body).chain(footer));
+    export_response(format, &format!("{}-holders", tick), Body::from_stream(stream))
+}
+
+// Chunks formatted ahead of a slow reader before the producer waits
+const EXPORT_QUEUE_CHUNKS: usize = 4;
+
+// Body fed by `produce` on a blocking thread, so table walks run inside one read
+// transaction without collecting the table first. `produce` gets a sender that
+// returns false once the client has gone away.
+fn blocking_body<F>(context: &'static str, produce: F) -> Body
+where
+    F: FnOnce(&dyn Fn(String) -> bool) -> anyhow::Result<()> + Send + 'static,
+{
+    let (tx, rx) = tokio::sync::mpsc::channel::<Result<String, std::io::Error>>(EXPORT_QUEUE_CHUNKS);
+    tokio::task::spawn_blocking(move || {
+        let send = |chunk: String| tx.blocking_send(Ok(chunk)).is_ok();
+        if let Err(e) = produce(&send) {
+            tracing::error!("{}: {}", context, e);
+            // Abort the transfer so the client cannot mistake a partial export for a full one
+            let _ = tx.blocking_send(Err(std::io::Error::other(e.to_string())));
+        }
+    });
+    Body::from_stream(futures::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|item| (item, rx))
     }))
 }
 
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ExportParams {
+    /// Resume after this key (name) or inscription number
+    after: Option<String>,
+}
+
+/// Every name record in name order, one JSON object per line
+#[utoipa::path(
+    get,
+    path = "/api/v1/names/export.ndjson",
+    tag = "names",
+    params(ExportParams),
+    responses((status = 200, content_type = "application/x-ndjson", description = "Stored name records", body = serde_json::Value))
+)]
+async fn get_names_export(State(state): State<AppState>, Query(params): Query<ExportParams>) -> Response {
+    let db = state.db.clone();
+    let body = blocking_body("names export error", move |send| {
+        let snapshot = db.snapshot()?;
+        for batch in snapshot.names(SCAN_BATCH).starting_after(params.after) {
+            let mut out = String::new();
+            for (_, data) in batch? {
+                out.push_str(&data);
+                out.push('\n');
+            }
+            if !send(out) {
+                break;
+            }
+        }
+        Ok(())
+    });
+    export_response(ExportFormat::Ndjson, "names", body)
+}
+
+/// Every inscription's metadata with its id and number, in number order, one per line
+#[utoipa::path(
+    get,
+    path = "/api/v1/inscriptions/export.ndjson",
+    tag = "inscriptions",
+    params(ExportParams),
+    responses((
+        status = 200,
+        content_type = "application/x-ndjson",
+        description = "Stored metadata with `id` and `number`",
+        body = serde_json::Value
+    ))
+)]
+async fn get_inscriptions_export(
+    State(state): State<AppState>,
+    Query(params): Query<ExportParams>,
+) -> ApiResult<Response> {
+    let mut after = match params.after.as_deref().map(str::parse::<u64>).transpose() {
+        Ok(after) => after,
+        Err(_) => return Err(ApiError::bad_request("after must be an inscription number")),
+    };
+    let db = state.db.clone();
+    let body = blocking_body("inscriptions export error", move |send| {
+        let snapshot = db.snapshot()?;
+        loop {
+            let rows = snapshot.inscriptions_after(after, SCAN_BATCH)?;
+            let Some((last, _, _)) = rows.last() else {
+                break;
+            };
+            after = Some(*last);
+            let mut out = String::new();
+            for (number, id, meta) in rows {
+                let mut row = v2::inscription(id, &meta);
+                row["number"] = number.into();
+                out.push_str(&row.to_string());
+                out.push('\n');
+            }
+            if !send(out) {
+                break;
+            }
+        }
+        Ok(())
+    });
+    Ok(export_response(ExportFormat::Ndjson, "inscriptions", body))
+}
+
+#[derive(Serialize, ToSchema)]
+struct HolderBalance {
+    address: String,
+    available: String,
+    /// Null for historical views: locks are only known for the tip
+    transferable: Option<String>,
+    overall: String,
+}
+
+#[derive(Serialize, ToSchema)]
+struct TokenHolders {
+    tick: String,
+    page: usize,
+    limit: usize,
+    positive_only: bool,
+    at_height: Option<u64>,
+    total_holders: usize,
+    total_positive_holders: usize,
+    holders: Vec<HolderBalance>,
+}
+
+/// Holders of a ticker, largest first
+#[utoipa::path(
+    get,
+    path = "/api/v1/zrc20/token/{tick}/balances",
+    tag = "zrc20",
+    params(
+        ("tick" = String, Path, description = "Ticker"),
+        ("page" = Option<usize>, Query, description = "Zero-based page"),
+        ("limit" = Option<usize>, Query, description = "Page size (default 24, max 50000)"),
+        ("positive_only" = Option<bool>, Query, description = "Skip zero balances"),
+        ("at_height" = Option<u64>, Query, description = "Redirects to `/balances/at/{height}`")
+    ),
+    responses(
+        (status = 200, body = TokenHolders),
+        (status = 308, description = "`at_height` was given; the historical listing")
+    )
+)]
 async fn get_zrc20_token_balances(
     State(state): State<AppState>,
+    OriginalUri(uri): OriginalUri,
     Path(tick): Path<String>,
     Query(params): Query<PaginationParams>,
-) -> Json<serde_json::Value> {
-    let (page, limit) = params.resolve();
-    let positive_only = params.positive_only.unwrap_or(false);
-    let (rows, total_all, total_positive) = state
-        .db
-        .list_balances_for_tick_filtered(&tick, page, limit, positive_only)
-        .unwrap_or((Vec::new(), 0, 0));
-    let holders: Vec<serde_json::Value> = rows
-        .into_iter()
-        .map(|(address, bal)| {
-            serde_json::json!({
-                "address": address,
-                "available": bal.available.to_string(),
-                "overall": bal.overall.to_string(),
+) -> ApiResult<Response> {
+    // Historical listings replay undo history, so they live on the gated expensive route
+    if let Some(height) = params.at_height {
+        let query = uri
+            .query()
+            .unwrap_or_default()
+            .split('&')
+            .filter(|pair| !pair.is_empty() && pair.split('=').next() != Some("at_height"))
+            .collect::<Vec<_>>()
+            .join("&");
+        let mut location = format!("{}/at/{}", uri.path(), height);
+        if !query.is_empty() {
+            location = format!("{}?{}", location, query);
+        }
+        return Ok(Redirect::permanent(&location).into_response());
+    }
+    Ok(token_holders(state, tick, params, None).await?.into_response())
+}
+
+/// Holders of a ticker as of a past block, largest first
+#[utoipa::path(
+    get,
+    path = "/api/v1/zrc20/token/{tick}/balances/at/{height}",
+    tag = "zrc20",
+    params(
+        ("tick" = String, Path, description = "Ticker"),
+        ("height" = u64, Path, description = "Block height"),
+        ("page" = Option<usize>, Query, description = "Zero-based page"),
+        ("limit" = Option<usize>, Query, description = "Page size (default 24, max 50000)"),
+        ("positive_only" = Option<bool>, Query, description = "Skip zero balances")
+    ),
+    responses((status = 200, body = TokenHolders))
+)]
+async fn get_zrc20_token_balances_at(
+    State(state): State<AppState>,
+    Path((tick, height)): Path<(String, u64)>,
+    Query(params): Query<PaginationParams>,
+) -> ApiResult<Json<TokenHolders>> {
+    token_holders(state, tick, params, Some(height)).await
+}
+
+async fn token_holders(
+    state: AppState,
+    tick: String,
+    params: PaginationParams,
+    at_height: Option<u64>,
+) -> ApiResult<Json<TokenHolders>> {
+    blocking(&state, move |state| {
+        let (page, limit) = params.resolve();
+        let positive_only = params.positive_only.unwrap_or(false);
+        let (rows, total_all, total_positive) = match at_height {
+            // Heights outside the checkpoint window are a bad request, not a server fault
+            Some(height) => state
+                .db
+                .list_balances_for_tick_at(&tick, height, page, limit, positive_only)
+                .map_err(|e| ApiError::bad_request(e.to_string()))?,
+            None => state
+                .db
+                .list_balances_for_tick_filtered(&tick, page, limit, positive_only)
+                .unwrap_or((Vec::new(), 0, 0)),
+        };
+        let holders = rows
+            .into_iter()
+            .map(|(address, bal)| HolderBalance {
+                available: bal.available.to_string(),
+                // Locks are only known for the tip
+                transferable: at_height
+                    .is_none()
+                    .then(|| transferable_of(&state.db, &address, &tick).to_string()),
+                overall: bal.overall.to_string(),
+                address,
             })
-        })
-        .collect();
-    Json(serde_json::json!({
-        "tick": tick,
-        "page": page,
-        "limit": limit,
-        "positive_only": positive_only,
-        "total_holders": total_all,
-        "total_positive_holders": total_positive,
-        "holders": holders
-    }))
+            .collect();
+        Ok(Json(TokenHolders {
+            tick,
+            page,
+            limit,
+            positive_only,
+            at_height,
+            total_holders: total_all,
+            total_positive_holders: total_positive,
+            holders,
+        }))
+    })
+    .await
+}
+
+#[derive(Serialize, ToSchema)]
+struct AddressBalance {
+    tick: String,
+    available: String,
+    transferable: String,
+    overall: String,
 }
 
+#[derive(Serialize, ToSchema)]
+struct AddressBalances {
+    address: String,
+    balances: Vec<AddressBalance>,
+}
+
+/// Balances held by an address
+#[utoipa::path(
+    get,
+    path = "/api/v1/zrc20/address/{address}",
+    tag = "zrc20",
+    params(("address" = String, Path, description = "Holder address")),
+    responses((status = 200, body = AddressBalances))
+)]
 async fn get_zrc20_address_balances(
     State(state): State<AppState>,
     Path(address): Path<String>,
-) -> Json<serde_json::Value> {
-    let rows = state
-        .db
-        .list_balances_for_address(&address)
-        .unwrap_or_default();
-    let entries: Vec<serde_json::Value> = rows
-        .into_iter()
-        .map(|(tick, bal)| {
-            serde_json::json!({
-                "tick": tick,
-                "available": bal.available.to_string(),
-                "overall": bal.overall.to_string(),
+) -> Json<AddressBalances> {
+    blocking(&state, move |state| {
+        let rows = state
+            .db
+            .list_balances_for_address(&address)
+            .unwrap_or_default();
+        let transferable = state.db.transferable_by_tick(&address).unwrap_or_default();
+        let balances = rows
+            .into_iter()
+            .map(|(tick, bal)| {
+                let locked = transferable.get(&tick).copied().unwrap_or_default();
+                AddressBalance {
+                    tick,
+                    available: bal.available.to_string(),
+                    transferable: locked.to_string(),
+                    overall: bal.overall.to_string(),
+                }
             })
-        })
-        .collect();
-    Json(serde_json::json!({
-        "address": address,
-        "balances": entries
-    }))
+            .collect();
+        Json(AddressBalances { address, balances })
+    })
+    .await
+}
+
+#[derive(Serialize, ToSchema)]
+struct PendingTransfer {
+    inscription_id: String,
+    #[schema(value_type = String)]
+    tick: serde_json::Value,
+    /// As inscribed (human units)
+    #[schema(value_type = String)]
+    amt: serde_json::Value,
+    /// `txid:vout` holding the inscription, once known
+    outpoint: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct PendingTransfers {
+    address: String,
+    transfers: Vec<PendingTransfer>,
+}
+
+/// Unused transfer inscriptions of an address
+#[utoipa::path(
+    get,
+    path = "/api/v1/zrc20/address/{address}/transferable",
+    tag = "zrc20",
+    params(("address" = String, Path, description = "Holder address")),
+    responses((status = 200, body = PendingTransfers))
+)]
+async fn get_zrc20_address_transferable(
+    State(state): State<AppState>,
+    Path(address): Path<String>,
+) -> Json<PendingTransfers> {
+    blocking(&state, move |state| {
+        let rows = state.db.list_transferable(&address).unwrap_or_default();
+        let transfers = rows
+            .into_iter()
+            .filter_map(|(id, raw)| {
+                let data: serde_json::Value = serde_json::from_str(&raw).ok()?;
+                Some(PendingTransfer {
+                    outpoint: state.db.find_outpoint_by_transfer_id(&id).unwrap_or(None),
+                    inscription_id: id,
+                    tick: data["tick"].clone(),
+                    amt: data["amt"].clone(),
+                })
+            })
+            .collect();
+        Json(PendingTransfers { address, transfers })
+    })
+    .await
+}
+
+/// Pending transfer inscriptions an address can cancel
+///
+/// Only transfers sitting on a known outpoint are listed; spending one back to the
+/// address cancels it and unlocks the amount.
+#[utoipa::path(
+    get,
+    path = "/api/v1/zrc20/address/{address}/cancellable",
+    tag = "zrc20",
+    params(("address" = String, Path, description = "Holder address")),
+    responses((status = 200, body = PendingTransfers))
+)]
+async fn get_zrc20_address_cancellable(
+    State(state): State<AppState>,
+    Path(address): Path<String>,
+) -> Json<PendingTransfers> {
+    blocking(&state, move |state| {
+        let rows = state.db.list_transferable(&address).unwrap_or_default();
+        let transfers = rows
+            .into_iter()
+            .filter_map(|(id, raw)| {
+                let data: serde_json::Value = serde_json::from_str(&raw).ok()?;
+                let outpoint = state.db.find_outpoint_by_transfer_id(&id).unwrap_or(None)?;
+                Some(PendingTransfer {
+                    inscription_id: id,
+                    tick: data["tick"].clone(),
+                    amt: data["amt"].clone(),
+                    outpoint: Some(outpoint),
+                })
+            })
+            .collect();
+        Json(PendingTransfers { address, transfers })
+    })
+    .await
+}
+
+#[derive(Serialize, ToSchema)]
+struct TokenHistory {
+    tick: String,
+    limit: usize,
+    /// Pass back as `after_id` for the next page; null on the last one
+    next_cursor: Option<String>,
+    /// Stored mint, transfer and burn records
+    items: Vec<serde_json::Value>,
 }
 
+/// Newest-first mints, transfers and burns
+#[utoipa::path(
+    get,
+    path = "/api/v1/zrc20/token/{tick}/history",
+    tag = "zrc20",
+    params(
+        ("tick" = String, Path, description = "Ticker"),
+        ("limit" = Option<usize>, Query, description = "Page size (default 24, max 50000)"),
+        ("after_id" = Option<String>, Query, description = "Continue after this `next_cursor`")
+    ),
+    responses((status = 200, body = TokenHistory))
+)]
+async fn get_zrc20_token_history(
+    State(state): State<AppState>,
+    Path(tick): Path<String>,
+    Query(params): Query<PaginationParams>,
+) -> Json<TokenHistory> {
+    blocking(&state, move |state| {
+        let tick = tick.to_lowercase();
+        let (_, limit) = params.resolve();
+        let rows = state
+            .db
+            .list_zrc20_tick_history(&tick, limit, params.cursor())
+            .unwrap_or_default();
+        let next_cursor = next_cursor(&rows, limit);
+        let items = rows
+            .into_iter()
+            .filter_map(|(_, raw)| serde_json::from_str(&raw).ok())
+            .collect();
+        Json(TokenHistory { tick, limit, next_cursor, items })
+    })
+    .await
+}
+
+#[derive(Serialize, ToSchema)]
+struct TokenEvents {
+    tick: String,
+    page: usize,
+    limit: usize,
+    next_cursor: Option<String>,
+    /// Stored event records
+    items: Vec<serde_json::Value>,
+}
+
+/// Newest-first deploy, mint, transfer and meta events
+///
+/// Every operation on a ticker, including deploys and transfer inscriptions that the
+/// history leaves out.
+#[utoipa::path(
+    get,
+    path = "/api/v1/zrc20/token/{tick}/events",
+    tag = "zrc20",
+    params(
+        ("tick" = String, Path, description = "Ticker"),
+        ("page" = Option<usize>, Query, description = "Zero-based page; ignored when a cursor is given"),
+        ("limit" = Option<usize>, Query, description = "Page size (default 24, max 50000)"),
+        ("after_id" = Option<String>, Query, description = "Continue after this `next_cursor`")
+    ),
+    responses((status = 200, body = TokenEvents))
+)]
+async fn get_zrc20_token_events(
+    State(state): State<AppState>,
+    Path(tick): Path<String>,
+    Query(params): Query<PaginationParams>,
+) -> ApiResult<Json<TokenEvents>> {
+    blocking(&state, move |state| {
+        let tick = tick.to_lowercase();
+        let (page, limit) = params.resolve();
+        let rows = state
+            .db
+            .list_zrc20_events(&tick, page, limit, params.cursor())
+            .map_err(|e| ApiError::internal("zrc20 events error", e))?;
+        let next_cursor = next_cursor(&rows, limit);
+        let items = rows
+            .into_iter()
+            .filter_map(|(_, raw)| serde_json::from_str(&raw).ok())
+            .collect();
+        Ok(Json(TokenEvents { tick, page, limit, next_cursor, items }))
+    })
+    .await
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct RejectedDeployParams {
+    /// Only this ticker's refused deploys
+    tick: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct RejectedDeploys {
+    tick: Option<String>,
+    page: usize,
+    limit: usize,
+    /// Refused deploys with the policy's reason
+    items: Vec<serde_json::Value>,
+}
+
+/// Newest-first deploys refused by the deploy policy
+///
+/// Deploys turned away by ZRC20_DEPLOY_POLICY are kept for audit.
+#[utoipa::path(
+    get,
+    path = "/api/v1/zrc20/deploys/rejected",
+    tag = "zrc20",
+    params(
+        RejectedDeployParams,
+        ("page" = Option<usize>, Query, description = "Zero-based page"),
+        ("limit" = Option<usize>, Query, description = "Page size (default 24, max 50000)")
+    ),
+    responses((status = 200, body = RejectedDeploys))
+)]
+async fn get_zrc20_rejected_deploys(
+    State(state): State<AppState>,
+    Query(filter): Query<RejectedDeployParams>,
+    Query(params): Query<PaginationParams>,
+) -> ApiResult<Json<RejectedDeploys>> {
+    blocking(&state, move |state| {
+        let tick = filter.tick.map(|t| t.to_lowercase());
+        let (page, limit) = params.resolve();
+        let items = state
+            .db
+            .list_rejected_deploys(tick.as_deref(), page, limit)
+            .map_err(|e| ApiError::internal("rejected deploys error", e))?;
+        Ok(Json(RejectedDeploys { tick, page, limit, items }))
+    })
+    .await
+}
+
+#[derive(Serialize, ToSchema)]
+struct AddressHistory {
+    address: String,
+    limit: usize,
+    next_cursor: Option<String>,
+    /// Stored mint, transfer and burn records involving the address
+    items: Vec<serde_json::Value>,
+}
+
+/// Newest-first ZRC-20 activity of an address
+#[utoipa::path(
+    get,
+    path = "/api/v1/zrc20/address/{address}/history",
+    tag = "zrc20",
+    params(
+        ("address" = String, Path, description = "Address"),
+        ("limit" = Option<usize>, Query, description = "Page size (default 24, max 50000)"),
+        ("after_id" = Option<String>, Query, description = "Continue after this `next_cursor`")
+    ),
+    responses((status = 200, body = AddressHistory))
+)]
+async fn get_zrc20_address_history(
+    State(state): State<AppState>,
+    Path(address): Path<String>,
+    Query(params): Query<PaginationParams>,
+) -> Json<AddressHistory> {
+    blocking(&state, move |state| {
+        let (_, limit) = params.resolve();
+        let rows = state
+            .db
+            .list_zrc20_address_history(&address, limit, params.cursor())
+            .unwrap_or_default();
+        let next_cursor = next_cursor(&rows, limit);
+        let items = rows
+            .into_iter()
+            .filter_map(|(_, raw)| serde_json::from_str(&raw).ok())
+            .collect();
+        Json(AddressHistory { address, limit, next_cursor, items })
+    })
+    .await
+}
+
+#[derive(Serialize, ToSchema)]
+struct TransferStatus {
+    inscription_id: String,
+    /// The transfer operation as inscribed
+    transfer: serde_json::Value,
+    /// `pending`, `used` or `cancelled`
+    state: &'static str,
+    used: bool,
+    cancelled: bool,
+    outpoint: Option<String>,
+}
+
+/// Transfer inscription state: pending, used or cancelled
+#[utoipa::path(
+    get,
+    path = "/api/v1/zrc20/transfer/{id}",
+    tag = "zrc20",
+    params(("id" = String, Path, description = "Transfer inscription id")),
+    responses((status = 200, body = TransferStatus))
+)]
 async fn get_zrc20_transfer(
     State(state): State<AppState>,
     Path(id): Path<String>,
-) -> Json<serde_json::Value> {
-    if let Some(raw) = state.db.get_transfer_inscription(&id).unwrap_or(None) {
-        let used = state.db.is_inscription_used(&id).unwrap_or(false);
-        let parsed: serde_json::Value = serde_json::from_str(&raw).unwrap_or_default();
-        let outpoint = state.db.find_outpoint_by_transfer_id(&id).unwrap_or(None);
-        return Json(serde_json::json!({
-            "inscription_id": id,
-            "transfer": parsed,
-            "used": used,
-            "outpoint": outpoint
-        }));
+) -> ApiResult<Json<TransferStatus>> {
+    let raw = state
+        .db
+        .get_transfer_inscription(&id)
+        .map_err(|e| ApiError::internal("transfer lookup error", e))?
+        .ok_or_else(|| ApiError::not_found(format!("Transfer {} not found", id)))?;
+    let transfer_state = transfer_state(&state.db, &id);
+    let parsed: serde_json::Value = serde_json::from_str(&raw).unwrap_or_default();
+    let outpoint = state.db.find_outpoint_by_transfer_id(&id).unwrap_or(None);
+    Ok(Json(TransferStatus {
+        inscription_id: id,
+        transfer: parsed,
+        state: transfer_state,
+        used: transfer_state != "pending",
+        cancelled: transfer_state == "cancelled",
+        outpoint,
+    }))
+}
+
+// Sum of `address`'s unused transfer inscriptions of `tick`
+fn transferable_of(db: &Db, address: &str, tick: &str) -> Amount {
+    db.transferable_by_tick(address)
+        .unwrap_or_default()
+        .remove(tick)
+        .unwrap_or_default()
+}
+
+// "pending" until the inscription is spent, then "used" or "cancelled"
+fn transfer_state(db: &Db, id: &str) -> &'static str {
+    match db.get_inscription_state(id).unwrap_or(None).as_deref() {
+        Some("used") => "used",
+        Some("cancelled") => "cancelled",
+        _ => "pending",
     }
-    Json(serde_json::json!({ "error": "Transfer not found" }))
 }
 
-async fn get_zrc20_token_integrity(
+#[derive(Serialize, ToSchema)]
+struct Zrc20Integrity {
+    tick: String,
+    dec: String,
+    supply_base_units: String,
+    sum_overall_base_units: String,
+    sum_available_base_units: String,
+    total_holders: usize,
+    holders_positive: usize,
+    burned_base_units: String,
+    /// Supply equals the holder sum plus burns
+    consistent: bool,
+}
+
+/// Supply against holder sum plus burns
+#[utoipa::path(
+    get,
+    path = "/api/v1/zrc20/token/{tick}/integrity",
+    tag = "zrc20",
+    params(("tick" = String, Path, description = "Ticker")),
+    responses((status = 200, body = Zrc20Integrity))
+)]
+async fn get_zrc20_token_integrity(
+    State(state): State<AppState>,
+    Path(tick): Path<String>,
+) -> Response {
+    blocking(&state, move |state| {
+        let lower = tick.to_lowercase();
+        let token_info = state.db.get_token_info(&lower).unwrap_or(None);
+        if let Some(info_str) = token_info {
+            if let Ok(info) = serde_json::from_str::<serde_json::Value>(&info_str) {
+                let supply_base = info["supply"]
+                    .as_str()
+                    .unwrap_or("0")
+                    .to_string();
+                let dec = info["dec"].as_str().unwrap_or("18");
+                let (sum_overall, sum_available, holders_total, holders_positive) =
+                    state.db.sum_balances_for_tick(&lower).unwrap_or((0, 0, 0, 0));
+                let burned = state.db.get_burned(&lower).unwrap_or(0);
+                let supply = parse_u128(&supply_base);
+                let consistent = supply == sum_overall + burned;
+                let body = Zrc20Integrity {
+                    tick: lower,
+                    dec: dec.to_string(),
+                    supply_base_units: supply_base,
+                    sum_overall_base_units: sum_overall.to_string(),
+                    sum_available_base_units: sum_available.to_string(),
+                    total_holders: holders_total,
+                    holders_positive,
+                    burned_base_units: burned.to_string(),
+                    consistent,
+                };
+                let mut headers = HeaderMap::new();
+                headers.insert(header::CACHE_CONTROL, axum::http::HeaderValue::from_static("public, max-age=10"));
+                return (headers, Json(body)).into_response();
+            }
+        }
+        ApiError::not_found(format!("Token {} not found", lower)).into_response()
+    })
+    .await
+}
+
+#[derive(Serialize, ToSchema)]
+struct CollectionList {
+    page: usize,
+    limit: usize,
+    next_cursor: Option<String>,
+    collections: Vec<Zrc721CollectionSummary>,
+}
+
+/// Collection list
+#[utoipa::path(
+    get,
+    path = "/api/v1/zrc721/collections",
+    tag = "zrc721",
+    params(
+        ("page" = Option<usize>, Query, description = "Zero-based page; ignored when a cursor is given"),
+        ("limit" = Option<usize>, Query, description = "Page size (default 24, max 50000)"),
+        ("after_id" = Option<String>, Query, description = "Continue after this `next_cursor`"),
+        ("before_id" = Option<String>, Query, description = "Return rows newer than this key")
+    ),
+    responses((status = 200, body = CollectionList))
+)]
+async fn get_zrc721_collections(
+    State(state): State<AppState>,
+    Query(params): Query<PaginationParams>,
+) -> Json<CollectionList> {
+    blocking(&state, move |state| {
+        let (page, limit) = params.resolve();
+        let rows = state
+            .db
+            .list_zrc721_collections(page, limit, params.cursor())
+            .unwrap_or_default();
+        let next_cursor = next_cursor(&rows, limit);
+        let items: Vec<Zrc721CollectionSummary> = rows
+            .into_iter()
+            .filter_map(|(_tick, raw)| serde_json::from_str::<serde_json::Value>(&raw).ok())
+            .map(|info| Zrc721CollectionSummary {
+                verified: info["collection"]
+                    .as_str()
+                    .is_some_and(|tick| state.db.is_zrc721_verified(tick).unwrap_or(false)),
+                collection: info["collection"].as_str().unwrap_or("").to_string(),
+                supply: info["supply"].as_str().unwrap_or("0").to_string(),
+                minted: info["minted"].as_u64().unwrap_or(0),
+                owners: info["owners"].as_u64().unwrap_or(0),
+                meta: info.get("meta").cloned().unwrap_or(serde_json::json!(null)),
+                royalty: info["royalty"].as_str().unwrap_or("").to_string(),
+                deployer: info["deployer"].as_str().unwrap_or("").to_string(),
+                inscription_id: info["inscription_id"].as_str().unwrap_or("").to_string(),
+            })
+            .collect();
+        Json(CollectionList { page, limit, next_cursor, collections: items })
+    })
+    .await
+}
+
+/// Collection deploy record
+#[utoipa::path(
+    get,
+    path = "/api/v1/zrc721/collection/{tick}",
+    tag = "zrc721",
+    params(("tick" = String, Path, description = "Collection ticker")),
+    responses((status = 200, description = "Stored deploy record with its `verified` flag", body = serde_json::Value))
+)]
+async fn get_zrc721_collection(
+    State(state): State<AppState>,
+    Path(tick): Path<String>,
+) -> ApiResult<Json<serde_json::Value>> {
+    let mut info = state
+        .db
+        .get_zrc721_collection(&tick)
+        .map_err(|e| ApiError::internal("collection lookup error", e))?
+        .and_then(|raw| serde_json::from_str::<serde_json::Value>(&raw).ok())
+        .ok_or_else(|| ApiError::not_found(format!("Collection {} not found", tick)))?;
+    info["verified"] = state.db.is_zrc721_verified(&tick).unwrap_or(false).into();
+    Ok(Json(info))
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct CollectionTokensParams {
+    /// Only tokens with every trait, as `Type:Value[,Type:Value]`
+    #[serde(rename = "trait")]
+    traits: Option<String>,
+    /// `id` (default) or `rarity`, rarest first
+    sort: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct CollectionTokens {
+    tick: String,
+    page: usize,
+    limit: usize,
+    tokens: Vec<Zrc721TokenSummary>,
+}
+
+/// Tokens minted in a collection
+#[utoipa::path(
+    get,
+    path = "/api/v1/zrc721/collection/{tick}/tokens",
+    tag = "zrc721",
+    params(
+        ("tick" = String, Path, description = "Collection ticker"),
+        CollectionTokensParams,
+        ("page" = Option<usize>, Query, description = "Zero-based page"),
+        ("limit" = Option<usize>, Query, description = "Page size (default 24, max 50000)")
+    ),
+    responses((status = 200, body = CollectionTokens))
+)]
+async fn get_zrc721_collection_tokens(
+    State(state): State<AppState>,
+    Path(tick): Path<String>,
+    Query(filter): Query<CollectionTokensParams>,
+    Query(params): Query<PaginationParams>,
+) -> ApiResult<Json<CollectionTokens>> {
+    let filters = match filter.traits.as_deref() {
+        Some(raw) => Some(parse_trait_filter(raw).map_err(|e| ApiError::bad_request(e.to_string()))?),
+        None => None,
+    };
+    let by_rarity = match filter.sort.as_deref() {
+        None | Some("id") => false,
+        Some("rarity") => true,
+        Some(other) => return Err(ApiError::bad_request(format!("Unknown sort {}, expected id or rarity", other))),
+    };
+    blocking(&state, move |state| {
+        let (page, limit) = params.resolve();
+        let rows = match &filters {
+            _ if by_rarity => state
+                .db
+                .list_zrc721_tokens_by_rarity(&tick, filters.as_deref(), page, limit)
+                .map_err(|e| ApiError::internal("rarity sort error", e))?,
+            Some(filters) => state
+                .db
+                .list_zrc721_tokens_with_traits(&tick, filters, page, limit)
+                .map_err(|e| ApiError::internal("trait filter error", e))?,
+            None => state.db.list_zrc721_tokens(&tick, page, limit).unwrap_or_default(),
+        };
+        // Try to fetch collection meta (CID) to derive metadata path
+        let meta_cid = state
+            .db
+            .get_zrc721_collection(&tick)
+            .ok()
+            .flatten()
+            .and_then(|raw| serde_json::from_str::<serde_json::Value>(&raw).ok())
+            .and_then(|v| v["meta"].as_str().map(|s| s.to_string()));
+
+        let tokens: Vec<Zrc721TokenSummary> = rows
+            .into_iter()
+            .map(|token| {
+                let metadata_path = meta_cid
+                    .as_ref()
+                    .map(|cid| format!("ipfs://{}/{}.json", cid, token.token_id));
+                Zrc721TokenSummary {
+                    rarity: state.db.get_zrc721_rarity(&token.tick, &token.token_id).ok().flatten(),
+                    tick: token.tick,
+                    token_id: token.token_id,
+                    owner: token.owner,
+                    inscription_id: token.inscription_id,
+                    metadata: token.metadata,
+                    metadata_path,
+                }
+            })
+            .collect();
+        Ok(Json(CollectionTokens { tick, page, limit, tokens }))
+    })
+    .await
+}
+
+#[derive(Serialize, ToSchema)]
+struct CollectionOwner {
+    rank: usize,
+    address: String,
+    tokens: u64,
+    /// Fraction of minted tokens held, 0.0-1.0
+    share: f64,
+}
+
+#[derive(Serialize, ToSchema)]
+struct CollectionOwners {
+    tick: String,
+    minted: u64,
+    total_owners: usize,
+    page: usize,
+    limit: usize,
+    owners: Vec<CollectionOwner>,
+}
+
+/// Owners ranked by tokens held
+#[utoipa::path(
+    get,
+    path = "/api/v1/zrc721/collection/{tick}/owners",
+    tag = "zrc721",
+    params(
+        ("tick" = String, Path, description = "Collection ticker"),
+        ("page" = Option<usize>, Query, description = "Zero-based page"),
+        ("limit" = Option<usize>, Query, description = "Page size (default 24, max 50000)")
+    ),
+    responses((status = 200, body = CollectionOwners))
+)]
+async fn get_zrc721_collection_owners(
+    State(state): State<AppState>,
+    Path(tick): Path<String>,
+    Query(params): Query<PaginationParams>,
+) -> ApiResult<Json<CollectionOwners>> {
+    blocking(&state, move |state| {
+        let raw = state
+            .db
+            .get_zrc721_collection(&tick)
+            .map_err(|e| ApiError::internal("collection lookup error", e))?
+            .ok_or_else(|| ApiError::not_found(format!("Collection {} not found", tick)))?;
+        let minted = serde_json::from_str::<serde_json::Value>(&raw)
+            .ok()
+            .and_then(|info| info["minted"].as_u64())
+            .unwrap_or(0);
+        let (page, limit) = params.resolve();
+        let (total, rows) = state
+            .db
+            .list_zrc721_owners(&tick, page, limit)
+            .map_err(|e| ApiError::internal("owner list error", e))?;
+        let offset = page.saturating_mul(limit);
+        let owners = rows
+            .into_iter()
+            .enumerate()
+            .map(|(i, (address, tokens))| CollectionOwner {
+                rank: offset + i + 1,
+                address,
+                tokens,
+                share: if minted == 0 { 0.0 } else { tokens as f64 / minted as f64 },
+            })
+            .collect();
+        Ok(Json(CollectionOwners { tick, minted, total_owners: total, page, limit, owners }))
+    })
+    .await
+}
+
+#[derive(Serialize, ToSchema)]
+struct CollectionRoyalties {
+    tick: String,
+    /// The deployer unless the collection names another address
+    #[schema(value_type = String)]
+    royalty_address: serde_json::Value,
+    #[schema(value_type = Option<String>)]
+    royalty_bps: serde_json::Value,
+    totals: RoyaltyTotals,
+    page: usize,
+    limit: usize,
+    /// Stored sale records, newest first
+    sales: Vec<serde_json::Value>,
+}
+
+/// Sales that paid the royalty address, with audit totals
+#[utoipa::path(
+    get,
+    path = "/api/v1/zrc721/collection/{tick}/royalties",
+    tag = "zrc721",
+    params(
+        ("tick" = String, Path, description = "Collection ticker"),
+        ("page" = Option<usize>, Query, description = "Zero-based page"),
+        ("limit" = Option<usize>, Query, description = "Page size (default 24, max 50000)")
+    ),
+    responses((status = 200, body = CollectionRoyalties))
+)]
+async fn get_zrc721_collection_royalties(
+    State(state): State<AppState>,
+    Path(tick): Path<String>,
+    Query(params): Query<PaginationParams>,
+) -> ApiResult<Json<CollectionRoyalties>> {
+    blocking(&state, move |state| {
+        let raw = state
+            .db
+            .get_zrc721_collection(&tick)
+            .map_err(|e| ApiError::internal("collection lookup error", e))?
+            .ok_or_else(|| ApiError::not_found(format!("Collection {} not found", tick)))?;
+        let info = serde_json::from_str::<serde_json::Value>(&raw).unwrap_or_default();
+        let (page, limit) = params.resolve();
+        let (totals, sales) = state
+            .db
+            .list_zrc721_sales(&tick, page, limit)
+            .map_err(|e| ApiError::internal("royalty sales error", e))?;
+        Ok(Json(CollectionRoyalties {
+            tick,
+            royalty_address: info.get("royalty_address").unwrap_or(&info["deployer"]).clone(),
+            royalty_bps: info["royalty"].clone(),
+            totals,
+            page,
+            limit,
+            sales,
+        }))
+    })
+    .await
+}
+
+#[derive(Serialize, ToSchema)]
+struct CollectionUpdates {
+    tick: String,
+    page: usize,
+    limit: usize,
+    /// Stored amendments with the values they replaced, newest first
+    updates: Vec<serde_json::Value>,
+}
+
+/// Deployer amendments with previous values
+#[utoipa::path(
+    get,
+    path = "/api/v1/zrc721/collection/{tick}/updates",
+    tag = "zrc721",
+    params(
+        ("tick" = String, Path, description = "Collection ticker"),
+        ("page" = Option<usize>, Query, description = "Zero-based page"),
+        ("limit" = Option<usize>, Query, description = "Page size (default 24, max 50000)")
+    ),
+    responses((status = 200, body = CollectionUpdates))
+)]
+async fn get_zrc721_collection_updates(
+    State(state): State<AppState>,
+    Path(tick): Path<String>,
+    Query(params): Query<PaginationParams>,
+) -> ApiResult<Json<CollectionUpdates>> {
+    blocking(&state, move |state| {
+        if state
+            .db
+            .get_zrc721_collection(&tick)
+            .map_err(|e| ApiError::internal("collection lookup error", e))?
+            .is_none()
+        {
+            return Err(ApiError::not_found(format!("Collection {} not found", tick)));
+        }
+        let (page, limit) = params.resolve();
+        let updates = state
+            .db
+            .list_zrc721_updates(&tick, page, limit)
+            .map_err(|e| ApiError::internal("collection updates error", e))?;
+        Ok(Json(CollectionUpdates { tick, page, limit, updates }))
+    })
+    .await
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct MintSeriesParams {
+    /// Most recent days with mints in `minted_per_day` (default 30, max 366)
+    days: Option<usize>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct MintDay {
+    /// `YYYY-MM-DD`
+    date: String,
+    minted: u64,
+}
+
+#[derive(Serialize, ToSchema)]
+struct CollectionActivity {
+    tick: String,
+    minted: u64,
+    supply: Option<u64>,
+    /// Minted share of the supply in percent, two decimals
+    progress_pct: Option<f64>,
+    minted_per_day: Vec<MintDay>,
+    page: usize,
+    limit: usize,
+    next_cursor: Option<String>,
+    /// Stored mint, transfer and burn records
+    items: Vec<serde_json::Value>,
+}
+
+/// Newest-first mints, transfers and burns with mint-out progress and tokens minted per day
+#[utoipa::path(
+    get,
+    path = "/api/v1/zrc721/collection/{tick}/activity",
+    tag = "zrc721",
+    params(
+        ("tick" = String, Path, description = "Collection ticker"),
+        MintSeriesParams,
+        ("page" = Option<usize>, Query, description = "Zero-based page; ignored when a cursor is given"),
+        ("limit" = Option<usize>, Query, description = "Page size (default 24, max 50000)"),
+        ("after_id" = Option<String>, Query, description = "Continue after this `next_cursor`")
+    ),
+    responses((status = 200, body = CollectionActivity))
+)]
+async fn get_zrc721_collection_activity(
     State(state): State<AppState>,
     Path(tick): Path<String>,
-) -> impl IntoResponse {
-    let lower = tick.to_lowercase();
-    let token_info = state.db.get_token_info(&lower).unwrap_or(None);
-    if let Some(info_str) = token_info {
-        if let Ok(info) = serde_json::from_str::<serde_json::Value>(&info_str) {
-            let supply_base = info["supply"]
-                .as_str()
-                .unwrap_or("0")
-                .to_string();
-            let dec = info["dec"].as_str().unwrap_or("18");
-            let (sum_overall, sum_available, holders_total, holders_positive) =
-                state.db.sum_balances_for_tick(&lower).unwrap_or((0, 0, 0, 0));
-            let burned = state.db.get_burned(&lower).unwrap_or(0);
-            let supply = parse_u128(&supply_base);
-            let consistent = supply == sum_overall + burned;
-            let body = serde_json::json!({
-                "tick": lower,
-                "dec": dec,
-                "supply_base_units": supply_base,
-                "sum_overall_base_units": sum_overall.to_string(),
-                "sum_available_base_units": sum_available.to_string(),
-                "total_holders": holders_total,
-                "holders_positive": holders_positive,
-                "burned_base_units": burned.to_string(),
-                "consistent": consistent
-            });
-            let mut headers = axum::http::HeaderMap::new();
-            headers.insert(header::CACHE_CONTROL, axum::http::HeaderValue::from_static("public, max-age=10"));
-            return (headers, Json(body));
-        }
-    }
-    {
-        let mut headers = axum::http::HeaderMap::new();
-        headers.insert(header::CACHE_CONTROL, axum::http::HeaderValue::from_static("public, max-age=10"));
-        (headers, Json(serde_json::json!({ "error": "Token not found" })))
-    }
+    Query(series): Query<MintSeriesParams>,
+    Query(params): Query<PaginationParams>,
+) -> ApiResult<Json<CollectionActivity>> {
+    blocking(&state, move |state| {
+        let raw = state
+            .db
+            .get_zrc721_collection(&tick)
+            .map_err(|e| ApiError::internal("collection lookup error", e))?
+            .ok_or_else(|| ApiError::not_found(format!("Collection {} not found", tick)))?;
+        let info: serde_json::Value = serde_json::from_str(&raw).unwrap_or_default();
+        let minted = info["minted"].as_u64().unwrap_or(0);
+        let supply = info["supply"].as_str().and_then(|s| s.parse::<u64>().ok());
+        let progress = supply
+            .filter(|supply| *supply > 0)
+            .map(|supply| ((minted as f64 / supply as f64) * 10000.0).round() / 100.0);
+
+        let days = series.days.unwrap_or(30).clamp(1, 366);
+        let per_day = state
+            .db
+            .zrc721_mints_per_day(&tick, days)
+            .map_err(|e| ApiError::internal("mint series error", e))?
+            .into_iter()
+            .map(|(date, minted)| MintDay { date, minted })
+            .collect();
+
+        let (page, limit) = params.resolve();
+        let rows = state
+            .db
+            .list_zrc721_activity(&tick, page, limit, params.cursor())
+            .map_err(|e| ApiError::internal("collection activity error", e))?;
+        let next_cursor = next_cursor(&rows, limit);
+        let items = rows
+            .into_iter()
+            .filter_map(|(_, raw)| serde_json::from_str(&raw).ok())
+            .collect();
+        Ok(Json(CollectionActivity {
+            tick,
+            minted,
+            supply,
+            progress_pct: progress,
+            minted_per_day: per_day,
+            page,
+            limit,
+            next_cursor,
+            items,
+        }))
+    })
+    .await
 }
 
-async fn get_zrc721_collections(
-    State(state): State<AppState>,
-    Query(params): Query<PaginationParams>,
-) -> Json<serde_json::Value> {
-    let (page, limit) = params.resolve();
-    let rows = state
-        .db
-        .list_zrc721_collections(page, limit)
-        .unwrap_or_default();
-    let items: Vec<Zrc721CollectionSummary> = rows
-        .into_iter()
-        .filter_map(|(_tick, raw)| serde_json::from_str::<serde_json::Value>(&raw).ok())
-        .map(|info| Zrc721CollectionSummary {
-            collection: info["collection"].as_str().unwrap_or("").to_string(),
-            supply: info["supply"].as_str().unwrap_or("0").to_string(),
-            minted: info["minted"].as_u64().unwrap_or(0),
-            meta: info.get("meta").cloned().unwrap_or(serde_json::json!(null)),
-            royalty: info["royalty"].as_str().unwrap_or("").to_string(),
-            deployer: info["deployer"].as_str().unwrap_or("").to_string(),
-            inscription_id: info["inscription_id"].as_str().unwrap_or("").to_string(),
-        })
-        .collect();
-    Json(serde_json::json!({
-        "page": page,
-        "limit": limit,
-        "collections": items
-    }))
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct HoldingsSnapshotParams {
+    /// Holdings once this block was indexed (default: the tip)
+    at_height: Option<u64>,
+    /// `json` (default) or `csv`
+    format: Option<String>,
 }
 
-async fn get_zrc721_collection(
+#[derive(Serialize, ToSchema)]
+struct SnapshotHolder {
+    address: String,
+    tokens: usize,
+    token_ids: Vec<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct HoldingsSnapshot {
+    tick: String,
+    at_height: u64,
+    owners: usize,
+    tokens: usize,
+    /// Tokens held by shielded addresses, which are not listed
+    shielded: usize,
+    /// Most tokens first
+    holders: Vec<SnapshotHolder>,
+}
+
+/// Token ids held by each owner at a height, for airdrops and allowlists
+#[utoipa::path(
+    get,
+    path = "/api/v1/zrc721/collection/{tick}/snapshot",
+    tag = "zrc721",
+    params(("tick" = String, Path, description = "Collection ticker"), HoldingsSnapshotParams),
+    responses((
+        status = 200,
+        content((HoldingsSnapshot = "application/json"), (String = "text/csv"))
+    ))
+)]
+async fn get_zrc721_collection_snapshot(
     State(state): State<AppState>,
     Path(tick): Path<String>,
-) -> Json<serde_json::Value> {
-    if let Some(raw) = state.db.get_zrc721_collection(&tick).unwrap_or(None) {
-        if let Ok(val) = serde_json::from_str::<serde_json::Value>(&raw) {
-            return Json(val);
+    Query(params): Query<HoldingsSnapshotParams>,
+) -> ApiResult<Response> {
+    let format = match params.format.as_deref() {
+        None | Some("json") => ExportFormat::Json,
+        Some("csv") => ExportFormat::Csv,
+        Some(other) => return Err(ApiError::bad_request(format!("Unknown format {}, expected json or csv", other))),
+    };
+    blocking(&state, move |state| {
+        let tick = tick.to_lowercase();
+        if state
+            .db
+            .get_zrc721_collection(&tick)
+            .map_err(|e| ApiError::internal("collection lookup error", e))?
+            .is_none()
+        {
+            return Err(ApiError::not_found(format!("Collection {} not found", tick)));
         }
-    }
-    Json(serde_json::json!({ "error": "Collection not found" }))
+        // Heights past the tip are a bad request, not a server fault
+        let (height, mut holdings) = state
+            .db
+            .zrc721_holdings_at(&tick, params.at_height)
+            .map_err(|e| ApiError::bad_request(e.to_string()))?;
+        let shielded = holdings.remove("shielded").map_or(0, |ids| ids.len());
+        let mut holders: Vec<(String, Vec<String>)> = holdings.into_iter().collect();
+        holders.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then_with(|| a.0.cmp(&b.0)));
+
+        if format == ExportFormat::Csv {
+            let mut out = String::from("address,tokens,token_ids\n");
+            for (address, ids) in &holders {
+                // Addresses are base58 and ids are digits, so nothing needs quoting
+                out.push_str(&format!("{},{},{}\n", address, ids.len(), ids.join(" ")));
+            }
+            return Ok(export_response(format, &format!("{}-holdings-{}", tick, height), Body::from(out)));
+        }
+        let tokens: usize = holders.iter().map(|(_, ids)| ids.len()).sum();
+        let holders: Vec<SnapshotHolder> = holders
+            .into_iter()
+            .map(|(address, token_ids)| SnapshotHolder { address, tokens: token_ids.len(), token_ids })
+            .collect();
+        Ok(Json(HoldingsSnapshot { tick, at_height: height, owners: holders.len(), tokens, shielded, holders })
+            .into_response())
+    })
+    .await
 }
 
-async fn get_zrc721_collection_tokens(
+#[derive(Serialize, ToSchema)]
+struct CollectionTraits {
+    tick: String,
+    traits: Vec<TraitCounts>,
+}
+
+/// Trait types of a collection with token counts per value
+#[utoipa::path(
+    get,
+    path = "/api/v1/zrc721/collection/{tick}/traits",
+    tag = "zrc721",
+    params(("tick" = String, Path, description = "Collection ticker")),
+    responses((status = 200, body = CollectionTraits))
+)]
+async fn get_zrc721_collection_traits(
     State(state): State<AppState>,
     Path(tick): Path<String>,
-    Query(params): Query<PaginationParams>,
-) -> Json<serde_json::Value> {
-    let (page, limit) = params.resolve();
-    let rows = state
-        .db
-        .list_zrc721_tokens(&tick, page, limit)
-        .unwrap_or_default();
-    // Try to fetch collection meta (CID) to derive metadata path
-    let meta_cid = state
-        .db
-        .get_zrc721_collection(&tick)
-        .ok()
-        .flatten()
-        .and_then(|raw| serde_json::from_str::<serde_json::Value>(&raw).ok())
-        .and_then(|v| v["meta"].as_str().map(|s| s.to_string()));
+) -> ApiResult<Json<CollectionTraits>> {
+    blocking(&state, move |state| {
+        if state
+            .db
+            .get_zrc721_collection(&tick)
+            .map_err(|e| ApiError::internal("collection lookup error", e))?
+            .is_none()
+        {
+            return Err(ApiError::not_found(format!("Collection {} not found", tick)));
+        }
+        let traits = state
+            .db
+            .zrc721_traits(&tick)
+            .map_err(|e| ApiError::internal("trait index error", e))?;
+        Ok(Json(CollectionTraits { tick, traits }))
+    })
+    .await
+}
 
-    let tokens: Vec<Zrc721TokenSummary> = rows
-        .into_iter()
-        .map(|token| {
-            let metadata_path = meta_cid
-                .as_ref()
-                .map(|cid| format!("ipfs://{}/{}.json", cid, token.token_id));
-            Zrc721TokenSummary {
-                tick: token.tick,
-                token_id: token.token_id,
-                owner: token.owner,
-                inscription_id: token.inscription_id,
-                metadata: token.metadata,
-                metadata_path,
-            }
-        })
-        .collect();
-    Json(serde_json::json!({
-        "tick": tick,
-        "page": page,
-        "limit": limit,
-        "tokens": tokens
-    }))
+#[derive(Serialize, ToSchema)]
+struct AddressTokens {
+    address: String,
+    page: usize,
+    limit: usize,
+    tokens: Vec<Zrc721TokenSummary>,
 }
 
+/// Tokens owned by an address
+#[utoipa::path(
+    get,
+    path = "/api/v1/zrc721/address/{address}",
+    tag = "zrc721",
+    params(
+        ("address" = String, Path, description = "Owner address"),
+        ("page" = Option<usize>, Query, description = "Zero-based page"),
+        ("limit" = Option<usize>, Query, description = "Page size (default 24, max 50000)")
+    ),
+    responses((status = 200, body = AddressTokens))
+)]
 async fn get_zrc721_address_tokens(
     State(state): State<AppState>,
     Path(address): Path<String>,
     Query(params): Query<PaginationParams>,
-) -> Json<serde_json::Value> {
-    let (page, limit) = params.resolve();
-    let rows = state
-        .db
-        .list_zrc721_tokens_by_address(&address, page, limit)
-        .unwrap_or_default();
-    // Derive metadata path if meta CID is available for each token's collection
-    let tokens: Vec<Zrc721TokenSummary> = rows
-        .into_iter()
-        .map(|token| {
-            let meta_cid = state
-                .db
-                .get_zrc721_collection(&token.tick)
-                .ok()
-                .flatten()
-                .and_then(|raw| serde_json::from_str::<serde_json::Value>(&raw).ok())
-                .and_then(|v| v["meta"].as_str().map(|s| s.to_string()));
-            let metadata_path = meta_cid
-                .as_ref()
-                .map(|cid| format!("ipfs://{}/{}.json", cid, token.token_id));
-            Zrc721TokenSummary {
-                tick: token.tick,
-                token_id: token.token_id,
-                owner: token.owner,
-                inscription_id: token.inscription_id,
-                metadata: token.metadata,
-                metadata_path,
-            }
-        })
-        .collect();
-    Json(serde_json::json!({
-        "address": address,
-        "page": page,
-        "limit": limit,
-        "tokens": tokens
-    }))
+) -> Json<AddressTokens> {
+    blocking(&state, move |state| {
+        let (page, limit) = params.resolve();
+        let rows = state
+            .db
+            .list_zrc721_tokens_by_address(&address, page, limit)
+            .unwrap_or_default();
+        // Derive metadata path if meta CID is available for each token's collection
+        let tokens: Vec<Zrc721TokenSummary> = rows
+            .into_iter()
+            .map(|token| {
+                let meta_cid = state
+                    .db
+                    .get_zrc721_collection(&token.tick)
+                    .ok()
+                    .flatten()
+                    .and_then(|raw| serde_json::from_str::<serde_json::Value>(&raw).ok())
+                    .and_then(|v| v["meta"].as_str().map(|s| s.to_string()));
+                let metadata_path = meta_cid
+                    .as_ref()
+                    .map(|cid| format!("ipfs://{}/{}.json", cid, token.token_id));
+                Zrc721TokenSummary {
+                    rarity: state.db.get_zrc721_rarity(&token.tick, &token.token_id).ok().flatten(),
+                    tick: token.tick,
+                    token_id: token.token_id,
+                    owner: token.owner,
+                    inscription_id: token.inscription_id,
+                    metadata: token.metadata,
+                    metadata_path,
+                }
+            })
+            .collect();
+        Json(AddressTokens { address, page, limit, tokens })
+    })
+    .await
 }
 
+/// Token owner, metadata and rarity
+#[utoipa::path(
+    get,
+    path = "/api/v1/zrc721/token/{collection}/{id}",
+    tag = "zrc721",
+    params(("collection" = String, Path, description = "Collection ticker"), ("id" = String, Path, description = "Token id")),
+    responses((
+        status = 200,
+        description = "Stored token record with `verified`, and `metadata_path`, `rarity` and `resolved_metadata` when known",
+        body = serde_json::Value
+    ))
+)]
 async fn get_zrc721_token_info(
     State(state): State<AppState>,
     Path((collection, id)): Path<(String, String)>,
-) -> Json<serde_json::Value> {
+) -> ApiResult<Json<serde_json::Value>> {
     let lower = collection.to_lowercase();
-    if let Ok(Some(raw)) = state.db.get_zrc721_token(&lower, &id) {
+    let found = state.db.get_zrc721_token(&lower, &id).map_err(|e| ApiError::internal("zrc721 token lookup error", e))?;
+    if let Some(raw) = found {
         if let Ok(mut token) = serde_json::from_str::<serde_json::Value>(&raw) {
             let meta_cid = state
                 .db
@@ -934,410 +3452,1204 @@ async fn get_zrc721_token_info(
             if let Some(cid) = meta_cid {
                 token["metadata_path"] = serde_json::json!(format!("ipfs://{}/{}.json", cid, id));
             }
-            return Json(token);
+            token["verified"] = state.db.is_zrc721_verified(&lower).unwrap_or(false).into();
+            if let Ok(Some(rarity)) = state.db.get_zrc721_rarity(&lower, &id) {
+                token["rarity"] = serde_json::json!(rarity);
+            }
+            if state.ipfs.enabled() {
+                token["resolved_metadata"] = resolve_token_metadata(&state, &token).await.unwrap_or_default();
+            }
+            return Ok(Json(token));
         }
     }
-    Json(serde_json::json!({ "error": "Token not found" }))
+    Err(ApiError::not_found(format!("Token {}#{} not found", lower, id)))
+}
+
+// Metadata document of a token through the IPFS resolver: the mint's `meta` when it
+// is a CID reference, otherwise the collection's `<cid>/<id>.json`
+async fn resolve_token_metadata(state: &AppState, token: &serde_json::Value) -> Option<serde_json::Value> {
+    let uri = token["metadata"]
+        .as_str()
+        .filter(|m| ipfs::ipfs_path(m).is_some())
+        .or_else(|| token["metadata_path"].as_str())?;
+    match state.ipfs.metadata(&state.db, uri).await {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            tracing::debug!("IPFS metadata {} not resolved: {}", uri, e);
+            None
+        }
+    }
+}
+
+fn metadata_image(metadata: &serde_json::Value) -> Option<&str> {
+    metadata["image"]
+        .as_str()
+        .or_else(|| metadata["img"].as_str())
+        .filter(|uri| ipfs::ipfs_path(uri).is_some())
+}
+
+/// Token image fetched through the IPFS resolver
+///
+/// The image is named by the token's metadata (`image` or `img`).
+#[utoipa::path(
+    get,
+    path = "/api/v1/zrc721/token/{collection}/{id}/image",
+    tag = "zrc721",
+    params(("collection" = String, Path, description = "Collection ticker"), ("id" = String, Path, description = "Token id")),
+    responses((status = 200, description = "Image bytes", content_type = "image/*", body = Vec<u8>))
+)]
+async fn get_zrc721_token_image(
+    State(state): State<AppState>,
+    Path((collection, id)): Path<(String, String)>,
+) -> ApiResult<Response> {
+    if !state.ipfs.enabled() {
+        return Err(ApiError::not_found("IPFS resolver is not enabled (IPFS_GATEWAY)"));
+    }
+    let lower = collection.to_lowercase();
+    let raw = state
+        .db
+        .get_zrc721_token(&lower, &id)
+        .map_err(|e| ApiError::internal("zrc721 token lookup error", e))?
+        .ok_or_else(|| ApiError::not_found(format!("Token {}#{} not found", lower, id)))?;
+    let mut token: serde_json::Value =
+        serde_json::from_str(&raw).map_err(|e| ApiError::internal("zrc721 token decode error", e))?;
+    let meta_cid = state
+        .db
+        .get_zrc721_collection(&lower)
+        .ok()
+        .flatten()
+        .and_then(|s| serde_json::from_str::<serde_json::Value>(&s).ok())
+        .and_then(|v| v["meta"].as_str().map(|s| s.to_string()));
+    if let Some(cid) = meta_cid {
+        token["metadata_path"] = serde_json::json!(format!("ipfs://{}/{}.json", cid, id));
+    }
+    // Inline mint metadata first, then the IPFS document
+    let resolved = match metadata_image(&token["metadata"]) {
+        Some(_) => None,
+        None => resolve_token_metadata(&state, &token).await,
+    };
+    let image = metadata_image(resolved.as_ref().unwrap_or(&token["metadata"]))
+        .ok_or_else(|| ApiError::not_found(format!("Token {}#{} has no IPFS image", lower, id)))?;
+    let object = state
+        .ipfs
+        .fetch(&state.db, image)
+        .await
+        .map_err(|e| ApiError::new(StatusCode::BAD_GATEWAY, format!("IPFS fetch of {} failed: {}", image, e)))?
+        .ok_or_else(|| ApiError::not_found(format!("Token {}#{} has no IPFS image", lower, id)))?;
+    if !object.content_type.starts_with("image/") {
+        return Err(ApiError::new(
+            StatusCode::BAD_GATEWAY,
+            format!("{} is {}, not an image", image, object.content_type),
+        ));
+    }
+    let bytes = if state.sanitize_svg && is_svg(&object.content_type) {
+        svg::sanitize(&object.bytes)
+    } else {
+        object.bytes
+    };
+    Ok((
+        [
+            (header::CONTENT_TYPE, object.content_type),
+            (header::CACHE_CONTROL, "public, max-age=3600".to_string()),
+            (header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+        ],
+        bytes,
+    )
+        .into_response())
+}
+
+#[derive(Serialize, ToSchema)]
+struct TokenMoves {
+    collection: String,
+    token_id: String,
+    next_cursor: Option<String>,
+    /// Stored ownership moves, newest first
+    history: Vec<serde_json::Value>,
+}
+
+/// Newest-first ownership moves
+#[utoipa::path(
+    get,
+    path = "/api/v1/zrc721/token/{collection}/{id}/history",
+    tag = "zrc721",
+    params(
+        ("collection" = String, Path, description = "Collection ticker"),
+        ("id" = String, Path, description = "Token id"),
+        ("limit" = Option<usize>, Query, description = "Page size (default 24, max 50000)"),
+        ("after_id" = Option<String>, Query, description = "Continue after this `next_cursor`")
+    ),
+    responses((status = 200, body = TokenMoves))
+)]
+async fn get_zrc721_token_history(
+    State(state): State<AppState>,
+    Path((collection, id)): Path<(String, String)>,
+    Query(params): Query<PaginationParams>,
+) -> Json<TokenMoves> {
+    blocking(&state, move |state| {
+        let lower = collection.to_lowercase();
+        let (_, limit) = params.resolve();
+        let rows = state
+            .db
+            .list_zrc721_history(&lower, &id, limit, params.cursor())
+            .unwrap_or_default();
+        let next_cursor = next_cursor(&rows, limit);
+        let history = rows
+            .into_iter()
+            .filter_map(|(_, raw)| serde_json::from_str(&raw).ok())
+            .collect();
+        Json(TokenMoves { collection: lower, token_id: id, next_cursor, history })
+    })
+    .await
+}
+
+#[derive(Serialize, ToSchema)]
+struct Burned {
+    tick: String,
+    burned_base_units: String,
 }
 
+/// Total burned in base units
+#[utoipa::path(
+    get,
+    path = "/api/v1/zrc20/token/{tick}/burned",
+    tag = "zrc20",
+    params(("tick" = String, Path, description = "Ticker")),
+    responses((status = 200, body = Burned))
+)]
 async fn get_zrc20_burned(
     State(state): State<AppState>,
     Path(tick): Path<String>,
-) -> Json<serde_json::Value> {
+) -> Json<Burned> {
     let lower = tick.to_lowercase();
     let burned = state.db.get_burned(&lower).unwrap_or(0);
-    Json(serde_json::json!({ "tick": lower, "burned_base_units": burned.to_string() }))
+    Json(Burned { tick: lower, burned_base_units: burned.to_string() })
+}
+
+/// How far one protocol has been indexed
+#[derive(Serialize, ToSchema)]
+struct ComponentSync {
+    height: Option<u64>,
+    tip: Option<u64>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct HealthComponents {
+    zrc20: ComponentSync,
+    zrc721: ComponentSync,
+    names: ComponentSync,
+}
+
+#[derive(Serialize, ToSchema)]
+struct Health {
+    height: Option<u64>,
+    chain_tip: Option<u64>,
+    components: HealthComponents,
+    /// Within one block of the chain tip
+    synced: bool,
+    schema_version: Option<u64>,
+    version: &'static str,
 }
 
-async fn get_healthz(State(state): State<AppState>) -> Json<serde_json::Value> {
+/// Health snapshot with sync state and schema version
+#[utoipa::path(get, path = "/api/v1/healthz", tag = "status", responses((status = 200, body = Health)))]
+async fn get_healthz(State(state): State<AppState>) -> Json<Health> {
     let height = state.db.get_latest_indexed_height().unwrap_or(None);
     let chain_tip = state.db.get_status("chain_tip").unwrap_or(None);
     let zrc20_height = state.db.get_status("zrc20_height").unwrap_or(None);
     let zrc721_height = state.db.get_status("zrc721_height").unwrap_or(None);
     let names_height = state.db.get_status("names_height").unwrap_or(None);
+    let schema_version = state.db.schema_version().unwrap_or(None);
     let synced = match (height, chain_tip) { (Some(h), Some(t)) => h >= t.saturating_sub(1), _ => false };
-    Json(serde_json::json!({
-        "height": height,
-        "chain_tip": chain_tip,
-        "components": {
-            "zrc20": { "height": zrc20_height, "tip": chain_tip },
-            "zrc721": { "height": zrc721_height, "tip": chain_tip },
-            "names": { "height": names_height, "tip": chain_tip }
+    Json(Health {
+        height,
+        chain_tip,
+        components: HealthComponents {
+            zrc20: ComponentSync { height: zrc20_height, tip: chain_tip },
+            zrc721: ComponentSync { height: zrc721_height, tip: chain_tip },
+            names: ComponentSync { height: names_height, tip: chain_tip },
         },
-        "synced": synced,
-        "version": env!("CARGO_PKG_VERSION")
-    }))
+        synced,
+        schema_version,
+        version: env!("CARGO_PKG_VERSION"),
+    })
 }
 
 // Minimal HTML shells used by browsers
 
-async fn frontpage() -> Html<&'static str> {
-    Html(FRONT_HTML)
+// Page or static file from the frontend bundle; cached briefly unless served live from WEB_DIR
+fn asset_response(assets: &Assets, name: &str) -> Response {
+    let Some(body) = assets.get(name) else {
+        return ApiError::not_found(format!("{} not found", name)).into_response();
+    };
+    let cache = if assets.is_live() { "no-cache" } else { "public, max-age=300" };
+    (
+        [(header::CONTENT_TYPE, assets::content_type(name)), (header::CACHE_CONTROL, cache)],
+        body.into_owned(),
+    )
+        .into_response()
 }
 
-async fn tokens_page() -> Html<String> {
-    match std::fs::read_to_string("web/tokens.html") {
-        Ok(content) => Html(content),
-        Err(_) => Html("<p>tokens page missing</p>".to_string()),
-    }
+async fn get_static(State(state): State<AppState>, Path(file): Path<String>) -> Response {
+    asset_response(&state.assets, &file)
 }
 
-async fn names_page() -> Html<String> {
-    match std::fs::read_to_string("web/names.html") {
-        Ok(content) => Html(content),
-        Err(_) => Html("<p>names page missing</p>".to_string()),
-    }
+async fn frontpage(State(state): State<AppState>) -> Response {
+    asset_response(&state.assets, "index.html")
 }
 
-async fn names_zec_page() -> Html<String> {
-    match std::fs::read_to_string("web/names_zec.html") {
-        Ok(content) => Html(content),
-        Err(_) => Html("<p>names .zec page missing</p>".to_string()),
-    }
+async fn tokens_page(State(state): State<AppState>) -> Response {
+    asset_response(&state.assets, "tokens.html")
 }
 
-async fn names_zcash_page() -> Html<String> {
-    match std::fs::read_to_string("web/names_zcash.html") {
-        Ok(content) => Html(content),
-        Err(_) => Html("<p>names .zcash page missing</p>".to_string()),
-    }
+async fn names_page(State(state): State<AppState>) -> Response {
+    asset_response(&state.assets, "names.html")
 }
 
-async fn collections_page() -> Html<String> {
-    match std::fs::read_to_string("web/collections.html") {
-        Ok(content) => Html(content),
-        Err(_) => Html("<p>collections page missing</p>".to_string()),
-    }
+async fn names_zec_page(State(state): State<AppState>) -> Response {
+    asset_response(&state.assets, "names_zec.html")
 }
 
-async fn collection_detail_page(Path(_tick): Path<String>) -> Html<String> {
-    match std::fs::read_to_string("web/collection.html") {
-        Ok(content) => Html(content),
-        Err(_) => Html("<p>collection page missing</p>".to_string()),
-    }
+async fn names_zcash_page(State(state): State<AppState>) -> Response {
+    asset_response(&state.assets, "names_zcash.html")
 }
 
-async fn docs_page() -> Html<String> {
-    match std::fs::read_to_string("web/docs.html") {
-        Ok(content) => Html(content),
-        Err(_) => Html("<p>docs page missing</p>".to_string()),
-    }
+async fn collections_page(State(state): State<AppState>) -> Response {
+    asset_response(&state.assets, "collections.html")
 }
 
-async fn spec_page() -> Html<String> {
-    match std::fs::read_to_string("web/spec.html") {
-        Ok(content) => Html(content),
-        Err(_) => Html("<p>spec page missing</p>".to_string()),
-    }
+async fn collection_detail_page(State(state): State<AppState>, Path(_tick): Path<String>) -> Response {
+    asset_response(&state.assets, "collection.html")
+}
+
+async fn docs_page(State(state): State<AppState>) -> Response {
+    asset_response(&state.assets, "docs.html")
+}
+
+async fn spec_page(State(state): State<AppState>) -> Response {
+    asset_response(&state.assets, "spec.html")
+}
+
+// The document is built once in `start_api`, from the routers it describes
+#[derive(Clone)]
+struct OpenApiJson(axum::body::Bytes);
+
+/// This document
+#[utoipa::path(get, path = "/api/v1/openapi.json", tag = "status", responses((status = 200, description = "OpenAPI 3.1 document", body = serde_json::Value)))]
+async fn get_openapi(Extension(OpenApiJson(spec)): Extension<OpenApiJson>) -> Response {
+    ([(header::CONTENT_TYPE, "application/json")], spec).into_response()
+}
+
+async fn uptime_page(State(state): State<AppState>) -> Response {
+    asset_response(&state.assets, "uptime.html")
+}
+
+#[derive(Deserialize, ToSchema)]
+struct InscriptionsBatchRequest {
+    /// At most 1000 inscription ids
+    ids: Vec<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct InscriptionsBatch {
+    /// In request order; unknown ids come back with `meta: null`
+    items: Vec<InscriptionRecord>,
+}
+
+/// Metadata for up to 1000 inscription ids
+#[utoipa::path(
+    post,
+    path = "/api/v1/inscriptions/batch",
+    tag = "inscriptions",
+    request_body = InscriptionsBatchRequest,
+    responses((status = 200, body = InscriptionsBatch))
+)]
+async fn post_inscriptions_batch(
+    State(state): State<AppState>,
+    Json(req): Json<InscriptionsBatchRequest>,
+) -> ApiResult<Json<InscriptionsBatch>> {
+    blocking(&state, move |state| {
+        check_batch_size(req.ids.len())?;
+        let mut items = Vec::with_capacity(req.ids.len());
+        for id in req.ids {
+            let meta = state
+                .db
+                .get_inscription(&id)
+                .map_err(|e| ApiError::internal("batch inscription error", e))?
+                .and_then(|m| serde_json::from_str::<serde_json::Value>(&m).ok());
+            items.push(InscriptionRecord { id, meta });
+        }
+        Ok(Json(InscriptionsBatch { items }))
+    })
+    .await
 }
 
-async fn uptime_page() -> Html<String> {
-    match std::fs::read_to_string("web/uptime.html") {
-        Ok(content) => Html(content),
-        Err(_) => Html("<p>uptime page missing</p>".to_string()),
+// 400 for an empty batch, 413 past MAX_BATCH_ITEMS
+fn check_batch_size(len: usize) -> ApiResult<()> {
+    if len == 0 {
+        Err(ApiError::bad_request("batch is empty"))
+    } else if len > MAX_BATCH_ITEMS {
+        Err(ApiError::new(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("batch exceeds {} entries", MAX_BATCH_ITEMS),
+        ))
+    } else {
+        Ok(())
     }
 }
 
+/// Inscription feed, optionally filtered and sorted
+///
+/// Any filter switches the feed to the facet indexes, which are cursor-only: `page` is
+/// ignored and `total` counts what the page returned.
+#[utoipa::path(
+    get,
+    path = "/api/v1/inscriptions",
+    tag = "inscriptions",
+    params(
+        ("page" = Option<usize>, Query, description = "Zero-based page; ignored when a cursor is given"),
+        ("limit" = Option<usize>, Query, description = "Page size (default 24, max 50000)"),
+        ("after_id" = Option<String>, Query, description = "Continue after this `next_cursor`"),
+        ("before_id" = Option<String>, Query, description = "Return rows newer than this key"),
+        FeedParams
+    ),
+    responses((status = 200, body = PaginatedResponse<InscriptionSummary>))
+)]
 async fn get_inscriptions_feed(
     State(state): State<AppState>,
     Query(params): Query<PaginationParams>,
-) -> Result<Json<PaginatedResponse<InscriptionSummary>>, StatusCode> {
-    let (page, limit) = params.resolve();
-    let total = state.db.get_inscription_count().map_err(|err| {
-        tracing::error!("inscription count error: {}", err);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
-    let rows = state.db.get_inscriptions_page(page, limit).map_err(|err| {
-        tracing::error!("inscriptions page error: {}", err);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
-
-    let offset = (page as u64).saturating_mul(limit as u64);
-    let has_more = offset + (rows.len() as u64) < total;
-
-    let mut items = Vec::with_capacity(rows.len());
-    for (id, payload) in rows {
-        let parsed: serde_json::Value = serde_json::from_str(&payload).unwrap_or_default();
-        let content_type = parsed["content_type"]
-            .as_str()
-            .unwrap_or("unknown")
-            .to_string();
-        let sender = parsed["sender"].as_str().unwrap_or("unknown").to_string();
-        let txid = parsed["txid"].as_str().unwrap_or("").to_string();
-        let block_time = parsed["block_time"].as_u64();
-        let block_height = parsed["block_height"].as_u64();
-        let content_length = parsed["content_hex"]
-            .as_str()
-            .map(|hex| hex.len() / 2)
-            .unwrap_or(0);
-        let shielded = parsed["sender"].as_str().map(|addr| addr.starts_with('z')).unwrap_or(false);
-        let category = classify_mime(&content_type).to_string();
-        let preview_text = build_preview(&content_type, &parsed);
-
-        items.push(InscriptionSummary {
-            id,
-            content_type,
-            sender,
-            txid,
-            block_time,
-            block_height,
-            content_length,
-            shielded,
-            category,
-            preview_text,
-        });
-    }
+    Query(feed): Query<FeedParams>,
+) -> ApiResult<Json<PaginatedResponse<InscriptionSummary>>> {
+    blocking(&state, move |state| {
+        let (page, limit) = params.resolve();
+        let (total, rows, next_cursor, has_more) = if feed.is_plain() {
+            let total = state.db.get_inscription_count().map_err(|e| ApiError::internal("inscription count error", e))?;
+            let cursor = params.cursor();
+            let rows = state.db.get_inscriptions_page(page, limit, cursor).map_err(|e| ApiError::internal("inscriptions page error", e))?;
+
+            let next_cursor = next_cursor(&rows, limit);
+            let has_more = if cursor.is_some() {
+                next_cursor.is_some()
+            } else {
+                let offset = (page as u64).saturating_mul(limit as u64);
+                offset + (rows.len() as u64) < total
+            };
+            (total, rows, next_cursor, has_more)
+        } else {
+            // Filtered walks are cursor-only; `total` counts what this page returned
+            let (filter, sort) = feed.resolve()?;
+            let (rows, next_cursor) = state
+                .db
+                .list_inscriptions_filtered(&filter, sort, limit, params.after_id.as_deref())
+                .map_err(|e| ApiError::internal("filtered inscriptions error", e))?;
+            let has_more = next_cursor.is_some();
+            (rows.len() as u64, rows, next_cursor, has_more)
+        };
 
-    Ok(Json(PaginatedResponse {
-        page,
-        limit,
-        total,
-        has_more,
-        items,
-    }))
+        let mut items = Vec::with_capacity(rows.len());
+        for (id, payload) in rows {
+            let parsed: serde_json::Value = serde_json::from_str(&payload).unwrap_or_default();
+            let content_type = parsed["content_type"]
+                .as_str()
+                .unwrap_or("unknown")
+                .to_string();
+            let sender = parsed["sender"].as_str().unwrap_or("unknown").to_string();
+            let txid = parsed["txid"].as_str().unwrap_or("").to_string();
+            let block_time = parsed["block_time"].as_u64();
+            let block_height = parsed["block_height"].as_u64();
+            let content_length = parsed["content_length"].as_u64().unwrap_or(0) as usize;
+            let shielded = parsed["sender"].as_str().map(|addr| addr.starts_with('z')).unwrap_or(false);
+            let category = classify_mime(&content_type).to_string();
+            let preview_text = build_preview(&state.db, &id, &content_type);
+
+            items.push(InscriptionSummary {
+                id,
+                content_type,
+                sender,
+                txid,
+                block_time,
+                block_height,
+                content_length,
+                shielded,
+                category,
+                preview_text,
+            });
+        }
+
+        Ok(Json(PaginatedResponse {
+            page,
+            limit,
+            total,
+            has_more,
+            next_cursor,
+            items,
+        }))
+    })
+    .await
 }
 
-// Convenience filters for TLD-specific name feeds
+/// `.zec` names
+#[utoipa::path(
+    get,
+    path = "/api/v1/names/zec",
+    tag = "names",
+    params(
+        ("page" = Option<usize>, Query, description = "Zero-based page; ignored when a cursor is given"),
+        ("limit" = Option<usize>, Query, description = "Page size (default 24, max 50000)"),
+        ("q" = Option<String>, Query, description = "Case-insensitive substring search"),
+        ("after_id" = Option<String>, Query, description = "Continue after this `next_cursor`"),
+        ("before_id" = Option<String>, Query, description = "Return rows newer than this key")
+    ),
+    responses((status = 200, body = PaginatedResponse<NameSummary>))
+)]
 async fn get_names_feed_zec(
     State(state): State<AppState>,
     Query(mut params): Query<PaginationParams>,
-) -> Result<Json<PaginatedResponse<NameSummary>>, StatusCode> {
+) -> ApiResult<Json<PaginatedResponse<NameSummary>>> {
     params.tld = Some("zec".to_string());
     get_names_feed(State(state), Query(params)).await
 }
 
+/// `.zcash` names
+#[utoipa::path(
+    get,
+    path = "/api/v1/names/zcash",
+    tag = "names",
+    params(
+        ("page" = Option<usize>, Query, description = "Zero-based page; ignored when a cursor is given"),
+        ("limit" = Option<usize>, Query, description = "Page size (default 24, max 50000)"),
+        ("q" = Option<String>, Query, description = "Case-insensitive substring search"),
+        ("after_id" = Option<String>, Query, description = "Continue after this `next_cursor`"),
+        ("before_id" = Option<String>, Query, description = "Return rows newer than this key")
+    ),
+    responses((status = 200, body = PaginatedResponse<NameSummary>))
+)]
 async fn get_names_feed_zcash(
     State(state): State<AppState>,
     Query(mut params): Query<PaginationParams>,
-) -> Result<Json<PaginatedResponse<NameSummary>>, StatusCode> {
+) -> ApiResult<Json<PaginatedResponse<NameSummary>>> {
     params.tld = Some("zcash".to_string());
     get_names_feed(State(state), Query(params)).await
 }
 
-async fn get_names_by_address(
+#[derive(Serialize, ToSchema)]
+struct PortfolioBalance {
+    tick: String,
+    dec: u32,
+    /// Base units
+    available: String,
+    transferable: String,
+    overall: String,
+    /// Whole tokens with `dec` decimals
+    available_display: String,
+    transferable_display: String,
+    overall_display: String,
+}
+
+#[derive(Serialize, ToSchema)]
+struct PortfolioCollection {
+    collection: String,
+    tokens: u64,
+}
+
+#[derive(Serialize, ToSchema)]
+struct Portfolio {
+    address: String,
+    /// Inscriptions created by the address
+    inscriptions: usize,
+    zrc20: Vec<PortfolioBalance>,
+    zrc721: Vec<PortfolioCollection>,
+    zrc721_total: u64,
+    names: Vec<String>,
+}
+
+/// Portfolio: inscription count, ZRC-20 balances, ZRC-721 holdings and names
+///
+/// A wallet home screen in one call: every part is a point lookup or a per-address range.
+#[utoipa::path(
+    get,
+    path = "/api/v1/address/{address}",
+    tag = "inscriptions",
+    params(("address" = String, Path, description = "Address")),
+    responses((status = 200, body = Portfolio))
+)]
+async fn get_address_portfolio(
     State(state): State<AppState>,
     Path(address): Path<String>,
-) -> Json<serde_json::Value> {
-    let all = state.db.get_all_names().unwrap_or_default();
-    let mut names = Vec::new();
-    for (_name, data_str) in all {
-        if let Ok(val) = serde_json::from_str::<serde_json::Value>(&data_str) {
-            if val["owner"].as_str().map(|s| s == address).unwrap_or(false) {
-                names.push(val);
-            }
-        }
+) -> ApiResult<Json<Portfolio>> {
+    blocking(&state, move |state| {
+        let internal = |e: anyhow::Error| ApiError::internal("portfolio error", e);
+        let inscriptions = state.db.get_inscriptions_by_address(&address).map_err(internal)?.len();
+        let balances = state.db.list_balances_for_address(&address).map_err(internal)?;
+        let holdings = state.db.list_holdings(&address).map_err(internal)?;
+        let transferable = state.db.transferable_by_tick(&address).map_err(internal)?;
+
+        let zrc20 = balances
+            .into_iter()
+            .map(|(tick, balance)| {
+                let dec = state
+                    .db
+                    .get_token_info(&tick)
+                    .ok()
+                    .flatten()
+                    .and_then(|raw| serde_json::from_str::<serde_json::Value>(&raw).ok())
+                    .and_then(|info| info["dec"].as_str().and_then(|d| d.parse::<u32>().ok()))
+                    .unwrap_or(18);
+                let locked = transferable.get(&tick).copied().unwrap_or_default();
+                PortfolioBalance {
+                    tick,
+                    dec,
+                    available: balance.available.to_string(),
+                    transferable: locked.to_string(),
+                    overall: balance.overall.to_string(),
+                    available_display: balance.available.display(dec),
+                    transferable_display: locked.display(dec),
+                    overall_display: balance.overall.display(dec),
+                }
+            })
+            .collect();
+        let zrc721_total: u64 = holdings.collections.values().sum();
+        let zrc721 = holdings
+            .collections
+            .into_iter()
+            .map(|(collection, tokens)| PortfolioCollection { collection, tokens })
+            .collect();
+
+        Ok(Json(Portfolio { address, inscriptions, zrc20, zrc721, zrc721_total, names: holdings.names }))
+    })
+    .await
+}
+
+#[derive(Serialize, ToSchema)]
+struct AddressActivity {
+    address: String,
+    page: usize,
+    limit: usize,
+    has_more: bool,
+    next_cursor: Option<String>,
+    /// Stored activity rows across protocols, each with a `kind`
+    items: Vec<serde_json::Value>,
+}
+
+/// Newest-first wallet activity across protocols
+#[utoipa::path(
+    get,
+    path = "/api/v1/address/{address}/activity",
+    tag = "inscriptions",
+    params(
+        ("address" = String, Path, description = "Address"),
+        ("page" = Option<usize>, Query, description = "Zero-based page; ignored when a cursor is given"),
+        ("limit" = Option<usize>, Query, description = "Page size (default 24, max 50000)"),
+        ("after_id" = Option<String>, Query, description = "Continue after this `next_cursor`"),
+        ("before_id" = Option<String>, Query, description = "Return rows newer than this key")
+    ),
+    responses((status = 200, body = AddressActivity))
+)]
+async fn get_address_activity(
+    State(state): State<AppState>,
+    Path(address): Path<String>,
+    Query(params): Query<PaginationParams>,
+) -> ApiResult<Json<AddressActivity>> {
+    blocking(&state, move |state| {
+        let (page, limit) = params.resolve();
+        let rows = state
+            .db
+            .list_address_activity(&address, page, limit, params.cursor())
+            .map_err(|e| ApiError::internal("address activity error", e))?;
+        let next_cursor = next_cursor(&rows, limit);
+        let items = rows
+            .into_iter()
+            .filter_map(|(_, raw)| serde_json::from_str(&raw).ok())
+            .collect();
+        Ok(Json(AddressActivity { address, page, limit, has_more: next_cursor.is_some(), next_cursor, items }))
+    })
+    .await
+}
+
+#[derive(Serialize, ToSchema)]
+struct SearchMatch {
+    /// `inscription`, `transaction`, `token`, `name`, `collection` or `address`
+    #[serde(rename = "type")]
+    kind: &'static str,
+    id: String,
+    /// 1000 for an exact key; prefixes score lower the longer the completion
+    score: u32,
+    /// Stored record of the match, or counts for transactions and addresses
+    data: serde_json::Value,
+}
+
+#[derive(Serialize, ToSchema)]
+struct SearchResults {
+    q: String,
+    /// Highest score first
+    results: Vec<SearchMatch>,
+}
+
+// Exact keys outrank prefixes; shorter completions rank above longer ones
+fn match_score(key: &str, query: &str) -> u32 {
+    if key == query {
+        1000
+    } else {
+        500u32.saturating_sub((key.len() - query.len()) as u32)
     }
-    Json(serde_json::json!({ "address": address, "names": names }))
 }
 
-async fn get_tokens_feed(
+fn is_hex64(value: &str) -> bool {
+    value.len() == 64 && value.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// Ranked matches across inscriptions, transactions, tokens, names, collections and addresses
+#[utoipa::path(
+    get,
+    path = "/api/v1/search",
+    tag = "status",
+    params(
+        ("q" = Option<String>, Query, description = "Inscription number, id or txid prefix, ticker, name or address prefix"),
+        ("limit" = Option<usize>, Query, description = "Maximum results (default 10, max 100)")
+    ),
+    responses((status = 200, body = SearchResults))
+)]
+async fn get_search(
     State(state): State<AppState>,
     Query(params): Query<PaginationParams>,
-) -> Result<Json<PaginatedResponse<TokenSummary>>, StatusCode> {
-    let (page, limit) = params.resolve();
-    
-    let (rows, total) = if let Some(query) = &params.q {
-        if query.trim().is_empty() {
-             let total = state.db.get_token_count().unwrap_or(0);
-             let rows = state.db.get_tokens_page(page, limit).unwrap_or_default();
-             (rows, total)
-        } else {
-            let rows = state.db.search_tokens(query, 100).unwrap_or_default();
-            let total = rows.len() as u64;
-            (rows, total)
+) -> ApiResult<Json<SearchResults>> {
+    blocking(&state, move |state| {
+        let query = params.q.as_deref().unwrap_or("").trim().to_string();
+        let limit = params.limit.unwrap_or(10).clamp(1, 100);
+        if query.is_empty() {
+            return Ok(Json(SearchResults { q: query, results: Vec::new() }));
+        }
+        let lower = query.to_lowercase();
+        let db = &state.db;
+        let fail = |e: anyhow::Error| ApiError::internal("search error", e);
+        let parse = |raw: &str| serde_json::from_str::<serde_json::Value>(raw).unwrap_or_default();
+
+        let mut results = Vec::new();
+
+        // A bare number is an inscription number
+        if let Ok(number) = query.parse::<u64>() {
+            if let Some(id) = db.get_inscription_by_number(number).map_err(fail)? {
+                let meta = db.get_inscription(&id).map_err(fail)?.map(|m| parse(&m));
+                results.push(SearchMatch {
+                    kind: "inscription",
+                    id,
+                    score: 1000,
+                    data: serde_json::json!({ "number": number, "meta": meta }),
+                });
+            }
         }
-    } else {
-        let total = state.db.get_token_count().map_err(|err| {
-            tracing::error!("token count error: {}", err);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-        let rows = state.db.get_tokens_page(page, limit).map_err(|err| {
-            tracing::error!("token page error: {}", err);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-        (rows, total)
-    };
 
-    let offset = (page as u64).saturating_mul(limit as u64);
-    let has_more = offset + (rows.len() as u64) < total;
-
-    let mut items = Vec::with_capacity(rows.len());
-    for (ticker, payload) in rows {
-        if let Ok(info) = serde_json::from_str::<serde_json::Value>(&payload) {
-            let max = info["max"].as_str().unwrap_or("0").to_string();
-            let lim = info["lim"].as_str().unwrap_or(&max).to_string();
-            let dec = info["dec"].as_str().unwrap_or("18").to_string();
-            let dec_value = dec.parse::<u32>().unwrap_or(18);
-            let deployer = info["deployer"].as_str().unwrap_or("unknown").to_string();
-            let inscription_id = info["inscription_id"].as_str().unwrap_or("").to_string();
-            let supply_base_units = info["supply"].as_str().unwrap_or("0").to_string();
-            let display_supply = format_supply_string(&supply_base_units, dec_value);
-            let max_base_units = parse_decimal_amount(&max, dec_value)
-                .map(|v| v.to_string())
-                .unwrap_or_else(|_| "0".to_string());
-            let max_units = parse_u128(&max_base_units);
-            let supply_units = parse_u128(&supply_base_units);
-            let progress = if max_units == 0 {
-                0.0
-            } else {
-                (supply_units as f64 / max_units as f64).clamp(0.0, 1.0)
-            };
+        // Hex ids: inscriptions by id prefix; a full txid also matches as a transaction
+        if lower.bytes().all(|b| b.is_ascii_hexdigit() || b == b'i') && lower.len() >= 4 {
+            let rows = db.search_prefix(SearchIndex::Inscriptions, &lower, limit).map_err(fail)?;
+            if is_hex64(&lower) && !rows.is_empty() {
+                results.push(SearchMatch {
+                    kind: "transaction",
+                    id: lower.clone(),
+                    score: 1000,
+                    data: serde_json::json!({ "inscriptions": rows.iter().map(|(id, _)| id).collect::<Vec<_>>() }),
+                });
+            }
+            for (id, meta) in rows {
+                results.push(SearchMatch { kind: "inscription", score: match_score(&id, &lower), data: parse(&meta), id });
+            }
+        }
+
+        for (index, kind) in [
+            (SearchIndex::Tokens, "token"),
+            (SearchIndex::Names, "name"),
+            (SearchIndex::Collections, "collection"),
+        ] {
+            for (key, raw) in db.search_prefix(index, &lower, limit).map_err(fail)? {
+                results.push(SearchMatch { kind, score: match_score(&key, &lower), data: parse(&raw), id: key });
+            }
+        }
 
-            items.push(TokenSummary {
-                ticker,
-                max,
-                max_base_units,
-                supply: display_supply,
-                supply_base_units,
-                lim,
-                dec,
-                deployer,
-                inscription_id,
-                progress,
+        // Addresses are case-sensitive
+        for (address, raw) in db.search_prefix(SearchIndex::Addresses, &query, limit).map_err(fail)? {
+            let inscriptions = serde_json::from_str::<Vec<String>>(&raw).map(|ids| ids.len()).unwrap_or(0);
+            results.push(SearchMatch {
+                kind: "address",
+                score: match_score(&address, &query),
+                data: serde_json::json!({ "inscriptions": inscriptions }),
+                id: address,
             });
         }
-    }
 
-    Ok(Json(PaginatedResponse {
-        page,
-        limit,
-        total,
-        has_more,
-        items,
-    }))
+        // Stable sort keeps the type order above among equal scores
+        results.sort_by_key(|m| std::cmp::Reverse(m.score));
+        results.truncate(limit);
+        Ok(Json(SearchResults { q: query, results }))
+    })
+    .await
 }
 
-async fn get_names_feed(
+#[derive(Serialize, ToSchema)]
+struct ContentMatch {
+    id: String,
+    meta: Option<serde_json::Value>,
+    preview_text: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct ContentMatches {
+    q: String,
+    limit: usize,
+    next_cursor: Option<String>,
+    items: Vec<ContentMatch>,
+}
+
+/// Text inscriptions containing a phrase (requires CONTENT_INDEX)
+#[utoipa::path(
+    get,
+    path = "/api/v1/search/content",
+    tag = "inscriptions",
+    params(
+        ("q" = Option<String>, Query, description = "Case-insensitive substring search"),
+        ("limit" = Option<usize>, Query, description = "Maximum results (default 24, max 100)"),
+        ("after_id" = Option<String>, Query, description = "Continue after this `next_cursor`")
+    ),
+    responses((status = 200, body = ContentMatches))
+)]
+async fn get_search_content(
     State(state): State<AppState>,
     Query(params): Query<PaginationParams>,
-) -> Result<Json<PaginatedResponse<NameSummary>>, StatusCode> {
-    let (page, limit) = params.resolve();
+) -> ApiResult<Json<ContentMatches>> {
+    blocking(&state, move |state| {
+        let query = params.q.as_deref().unwrap_or("").trim().to_string();
+        let limit = params.limit.unwrap_or(24).clamp(1, 100);
+        let (ids, next_cursor) = state
+            .db
+            .search_content(&query, limit, params.after_id.as_deref())
+            .map_err(|e| ApiError::internal("content search error", e))?;
+        let items = ids
+            .into_iter()
+            .map(|id| {
+                let meta = state
+                    .db
+                    .get_inscription(&id)
+                    .ok()
+                    .flatten()
+                    .and_then(|m| serde_json::from_str::<serde_json::Value>(&m).ok());
+                let content_type = meta.as_ref().and_then(|m| m["content_type"].as_str()).unwrap_or("");
+                let preview_text = build_preview(&state.db, &id, content_type);
+                ContentMatch { id, meta, preview_text }
+            })
+            .collect();
+        Ok(Json(ContentMatches { q: query, limit, next_cursor, items }))
+    })
+    .await
+}
 
-    // Pull all names and filter by optional tld and query for correctness
-    let names_all = match state.db.get_all_names() {
-        Ok(v) => v,
-        Err(err) => {
-            // During heavy reindexing, prefer a graceful empty result over a 500
-            tracing::warn!("names fetch error (returning empty set): {}", err);
-            Vec::new()
+#[derive(Serialize, ToSchema)]
+struct OwnedNames {
+    address: String,
+    page: usize,
+    limit: usize,
+    next_cursor: Option<String>,
+    /// Stored name records
+    names: Vec<serde_json::Value>,
+}
+
+/// Names owned by an address
+#[utoipa::path(
+    get,
+    path = "/api/v1/names/address/{address}",
+    tag = "names",
+    params(
+        ("address" = String, Path, description = "Owner address"),
+        ("page" = Option<usize>, Query, description = "Zero-based page; ignored when a cursor is given"),
+        ("limit" = Option<usize>, Query, description = "Page size (default 24, max 50000)"),
+        ("after_id" = Option<String>, Query, description = "Continue after this `next_cursor`")
+    ),
+    responses((status = 200, body = OwnedNames))
+)]
+async fn get_names_by_address(
+    State(state): State<AppState>,
+    Path(address): Path<String>,
+    Query(params): Query<PaginationParams>,
+) -> ApiResult<Json<OwnedNames>> {
+    blocking(&state, move |state| {
+        let (page, limit) = params.resolve();
+        let rows = state
+            .db
+            .list_names_by_owner(&address, page, limit, params.cursor())
+            .map_err(|e| ApiError::internal("names by owner error", e))?;
+        let next_cursor = next_cursor(&rows, limit);
+        let names = rows
+            .into_iter()
+            .filter_map(|(_, raw)| serde_json::from_str(&raw).ok())
+            .collect();
+        Ok(Json(OwnedNames { address, page, limit, next_cursor, names }))
+    })
+    .await
+}
+
+/// Token list
+#[utoipa::path(
+    get,
+    path = "/api/v1/tokens",
+    tag = "zrc20",
+    params(
+        ("page" = Option<usize>, Query, description = "Zero-based page; ignored when a cursor is given"),
+        ("limit" = Option<usize>, Query, description = "Page size (default 24, max 50000)"),
+        ("q" = Option<String>, Query, description = "Case-insensitive substring search"),
+        ("after_id" = Option<String>, Query, description = "Continue after this `next_cursor`"),
+        ("before_id" = Option<String>, Query, description = "Return rows newer than this key")
+    ),
+    responses((status = 200, body = PaginatedResponse<TokenSummary>))
+)]
+async fn get_tokens_feed(
+    State(state): State<AppState>,
+    Query(params): Query<PaginationParams>,
+) -> ApiResult<Json<PaginatedResponse<TokenSummary>>> {
+    blocking(&state, move |state| {
+        let (page, limit) = params.resolve();
+        let cursor = params.cursor();
+        
+        let (rows, total) = if let Some(query) = &params.q {
+            if query.trim().is_empty() {
+                 let total = state.db.get_token_count().unwrap_or(0);
+                 let rows = state.db.get_tokens_page(page, limit, cursor).unwrap_or_default();
+                 (rows, total)
+            } else {
+                let rows = state.db.search_tokens(query, 100).unwrap_or_default();
+                let total = rows.len() as u64;
+                (rows, total)
+            }
+        } else {
+            let total = state.db.get_token_count().map_err(|e| ApiError::internal("token count error", e))?;
+            let rows = state.db.get_tokens_page(page, limit, cursor).map_err(|e| ApiError::internal("token page error", e))?;
+            (rows, total)
+        };
+
+        // Search results are a single capped batch, so there is nothing to continue from
+        let searching = params.q.as_deref().is_some_and(|q| !q.trim().is_empty());
+        let next_cursor = if searching { None } else { next_cursor(&rows, limit) };
+        let has_more = if cursor.is_some() {
+            next_cursor.is_some()
+        } else {
+            let offset = (page as u64).saturating_mul(limit as u64);
+            offset + (rows.len() as u64) < total
+        };
+
+        let mut items = Vec::with_capacity(rows.len());
+        for (ticker, payload) in rows {
+            if let Ok(info) = serde_json::from_str::<serde_json::Value>(&payload) {
+                let max = info["max"].as_str().unwrap_or("0").to_string();
+                let lim = info["lim"].as_str().unwrap_or(&max).to_string();
+                let dec = info["dec"].as_str().unwrap_or("18").to_string();
+                let dec_value = dec.parse::<u32>().unwrap_or(18);
+                let deployer = info["deployer"].as_str().unwrap_or("unknown").to_string();
+                let inscription_id = info["inscription_id"].as_str().unwrap_or("").to_string();
+                let supply_base_units = info["supply"].as_str().unwrap_or("0").to_string();
+                let display_supply = Amount::parse_base_units(&supply_base_units)
+                    .unwrap_or_default()
+                    .display(dec_value);
+                let max_base_units = Amount::parse(&max, dec_value)
+                    .map(|v| v.to_string())
+                    .unwrap_or_else(|_| "0".to_string());
+                let max_units = parse_u128(&max_base_units);
+                let supply_units = parse_u128(&supply_base_units);
+                let progress = if max_units == 0 {
+                    0.0
+                } else {
+                    (supply_units as f64 / max_units as f64).clamp(0.0, 1.0)
+                };
+                let market = state.market.quote(&ticker);
+                let meta = Some(info["meta"].clone()).filter(|m| !m.is_null());
+
+                items.push(TokenSummary {
+                    ticker,
+                    max,
+                    max_base_units,
+                    supply: display_supply,
+                    supply_base_units,
+                    lim,
+                    dec,
+                    deployer,
+                    inscription_id,
+                    progress,
+                    meta,
+                    market,
+                });
+            }
         }
-    };
 
-    let tld = params.tld.as_ref().map(|s| s.to_lowercase());
-    let q_lower = params.q.as_ref().map(|s| s.to_lowercase());
-    let mut filtered: Vec<NameSummary> = Vec::new();
-    for (_key, payload) in names_all {
-        if let Ok(data) = serde_json::from_str::<serde_json::Value>(&payload) {
-            let name = data["name"].as_str().unwrap_or("").to_string();
-            // tld filter
-            let keep_tld = match tld.as_deref() {
-                Some("zec") => name.ends_with(".zec"),
-                Some("zcash") => name.ends_with(".zcash"),
-                _ => true,
+        Ok(Json(PaginatedResponse {
+            page,
+            limit,
+            total,
+            has_more,
+            next_cursor,
+            items,
+        }))
+    })
+    .await
+}
+
+/// Name list
+#[utoipa::path(
+    get,
+    path = "/api/v1/names",
+    tag = "names",
+    params(
+        ("page" = Option<usize>, Query, description = "Zero-based page; ignored when a cursor is given"),
+        ("limit" = Option<usize>, Query, description = "Page size (default 24, max 50000)"),
+        ("q" = Option<String>, Query, description = "Case-insensitive substring search"),
+        ("after_id" = Option<String>, Query, description = "Continue after this `next_cursor`"),
+        ("before_id" = Option<String>, Query, description = "Return rows newer than this key"),
+        ("tld" = Option<String>, Query, description = "`zec` or `zcash`")
+    ),
+    responses((status = 200, body = PaginatedResponse<NameSummary>))
+)]
+async fn get_names_feed(
+    State(state): State<AppState>,
+    Query(params): Query<PaginationParams>,
+) -> ApiResult<Json<PaginatedResponse<NameSummary>>> {
+    blocking(&state, move |state| {
+        let (page, limit) = params.resolve();
+        let tld = params.tld.as_ref().map(|s| s.to_lowercase());
+
+        // Without a search term the feed is a ranged scan: per-TLD index or the names table itself
+        if params.q.as_deref().map(str::trim).unwrap_or("").is_empty() {
+            let cursor = params.cursor();
+            let listed = match tld.as_deref() {
+                Some(tld @ ("zec" | "zcash")) => state
+                    .db
+                    .get_name_count_by_tld(tld)
+                    .and_then(|total| Ok((total, state.db.get_names_page_by_tld(tld, page, limit, cursor)?))),
+                _ => state
+                    .db
+                    .get_name_count()
+                    .and_then(|total| Ok((total, state.db.get_names_page(page, limit, cursor)?))),
             };
-            if !keep_tld { continue; }
-            // search filter
-            if let Some(q) = &q_lower {
-                if !name.to_lowercase().contains(q) { continue; }
-            }
-            let owner = data["owner"].as_str().unwrap_or("unknown").to_string();
-            let inscription_id = data["inscription_id"].as_str().unwrap_or("").to_string();
-            filtered.push(NameSummary { name, owner, inscription_id });
+            let (total, rows) = listed.map_err(|e| ApiError::internal("names page error", e))?;
+            let next_cursor = next_cursor(&rows, limit);
+            let has_more = if cursor.is_some() {
+                next_cursor.is_some()
+            } else {
+                (page as u64).saturating_mul(limit as u64) + (rows.len() as u64) < total
+            };
+            let items = rows
+                .into_iter()
+                .filter_map(|(_, payload)| serde_json::from_str::<serde_json::Value>(&payload).ok())
+                .map(|data| NameSummary {
+                    name: data["name"].as_str().unwrap_or("").to_string(),
+                    owner: data["owner"].as_str().unwrap_or("unknown").to_string(),
+                    inscription_id: data["inscription_id"].as_str().unwrap_or("").to_string(),
+                    attributes: data.get("attributes").cloned(),
+                })
+                .collect();
+            return Ok(Json(PaginatedResponse { page, limit, total, has_more, next_cursor, items }));
         }
-    }
-    // keep newest first by insertion order proxy
-    filtered.reverse();
-    let total = filtered.len() as u64;
-    let start = page.saturating_mul(limit);
-    let items: Vec<NameSummary> = filtered.into_iter().skip(start).take(limit).collect();
-    let has_more = (start as u64) + (items.len() as u64) < total;
 
-    Ok(Json(PaginatedResponse { page, limit, total, has_more, items }))
+        // Search: stream every name and filter by optional tld and query for correctness
+        let q_lower = params.q.as_ref().map(|s| s.to_lowercase());
+        let mut filtered: Vec<(String, NameSummary)> = Vec::new();
+        match state.db.snapshot() {
+            Ok(snapshot) => scan_rows(snapshot.names(SCAN_BATCH), |key, payload| {
+                if let Ok(data) = serde_json::from_str::<serde_json::Value>(&payload) {
+                    let name = data["name"].as_str().unwrap_or("").to_string();
+                    // tld filter
+                    let keep_tld = match tld.as_deref() {
+                        Some("zec") => name.ends_with(".zec"),
+                        Some("zcash") => name.ends_with(".zcash"),
+                        _ => true,
+                    };
+                    if !keep_tld { return; }
+                    // search filter
+                    if let Some(q) = &q_lower {
+                        if !name.to_lowercase().contains(q) { return; }
+                    }
+                    let owner = data["owner"].as_str().unwrap_or("unknown").to_string();
+                    let inscription_id = data["inscription_id"].as_str().unwrap_or("").to_string();
+                    let attributes = data.get("attributes").cloned();
+                    filtered.push((key, NameSummary { name, owner, inscription_id, attributes }));
+                }
+            }),
+            // During heavy reindexing, prefer a graceful empty result over a 500
+            Err(err) => tracing::warn!("names fetch error (returning empty set): {}", err),
+        }
+        // keep newest first by insertion order proxy
+        filtered.reverse();
+        let total = filtered.len() as u64;
+        // Cursors are table keys; the listing is in descending key order
+        let start = match params.cursor() {
+            Some(Cursor::After(key)) => filtered.partition_point(|(k, _)| k.as_str() >= key),
+            Some(Cursor::Before(key)) => filtered
+                .partition_point(|(k, _)| k.as_str() > key)
+                .saturating_sub(limit),
+            None => page.saturating_mul(limit),
+        };
+        let rows: Vec<(String, NameSummary)> = filtered.into_iter().skip(start).take(limit).collect();
+        let has_more = (start as u64) + (rows.len() as u64) < total;
+        let next_cursor = if has_more { rows.last().map(|(key, _)| key.clone()) } else { None };
+        let items = rows.into_iter().map(|(_, item)| item).collect();
+
+        Ok(Json(PaginatedResponse { page, limit, total, has_more, next_cursor, items }))
+    })
+    .await
 }
 async fn get_inscription_preview(
     State(state): State<AppState>,
     Path(id): Path<String>,
 ) -> Response {
-    let meta = match state.db.get_inscription(&id).unwrap_or(None) {
-        Some(m) => m,
-        None => {
-            return (
-                StatusCode::NOT_FOUND,
-                Html("<h1>Inscription not found</h1>"),
-            )
-                .into_response()
-        }
-    };
+    blocking(&state, move |state| {
+        let meta = match state.db.get_inscription(&id).unwrap_or(None) {
+            Some(m) => m,
+            None => {
+                return (
+                    StatusCode::NOT_FOUND,
+                    Html("<h1>Inscription not found</h1>"),
+                )
+                    .into_response()
+            }
+        };
 
-    let val: serde_json::Value = match serde_json::from_str(&meta) {
-        Ok(v) => v,
-        Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, "Invalid metadata").into_response(),
-    };
+        let val: serde_json::Value = match serde_json::from_str(&meta) {
+            Ok(v) => v,
+            Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, "Invalid metadata").into_response(),
+        };
 
-    let content_type = val["content_type"].as_str().unwrap_or("text/plain");
-    let content_hex = val["content_hex"].as_str().unwrap_or("");
-    let id_attr = html_escape::encode_double_quoted_attribute(&id).to_string();
-    let title = html_escape::encode_text(&id).to_string();
+        let content_type = val["content_type"].as_str().unwrap_or("text/plain");
+        let id_attr = html_escape::encode_double_quoted_attribute(&id).to_string();
+        let title = html_escape::encode_text(&id).to_string();
 
-    // Derive an inline preview depending on MIME type
-    let preview_html = if content_type.starts_with("image/") {
-        format!(
-            r#"<!DOCTYPE html>
-<html>
-<head><meta charset="utf-8"><title>{}</title>
-<style>body{{background:#111;margin:0;display:flex;align-items:center;justify-content:center;min-height:100vh;}}</style>
-</head>
-<body><img src="/content/{}" style="max-width:100%;max-height:100vh;"></body>
-</html>"#,
-            title, id_attr
-        )
-    } else if content_type == "text/html" {
-        // Wrap HTML inscriptions in an iframe so we sandbox execution
-        format!(
-            r#"<!DOCTYPE html>
-<html>
-<head><meta charset="utf-8"><title>{}</title></head>
-<body><iframe src="/content/{}" style="width:100%;height:100vh;border:none;"></iframe></body>
-</html>"#,
-            title, id_attr
-        )
-    } else if content_type.starts_with("text/") || content_type == "application/json" {
-        let content_bytes = hex::decode(content_hex).unwrap_or_default();
-        let text = String::from_utf8(content_bytes).unwrap_or_else(|_| "Invalid UTF-8".to_string());
-        format!(
-            r#"<!DOCTYPE html>
-<html>
-<head><meta charset="utf-8"><title>{}</title>
-<style>body{{background:#111;color:#fff;font-family:monospace;padding:20px;line-height:1.6;}}pre{{white-space:pre-wrap;word-wrap:break-word;}}</style>
-</head>
-<body><pre>{}</pre></body>
-</html>"#,
-            title,
-            html_escape::encode_text(&text)
-        )
-    } else {
-        format!(
-            r#"<!DOCTYPE html>
-<html>
-<head><meta charset="utf-8"><title>{}</title>
-<style>body{{background:#111;color:#fff;font-family:monospace;padding:40px;text-align:center;}}</style>
-</head>
-<body><h2>Binary Content ({})</h2><a href="/content/{}" style="color:#fff;">Download</a></body>
-</html>"#,
-            title,
-            html_escape::encode_text(content_type),
-            id_attr
-        )
-    };
+        // Derive an inline preview depending on MIME type
+        let preview_html = if content_type.starts_with("image/") {
+            format!(
+                r#"<!DOCTYPE html>
+    <html>
+    <head><meta charset="utf-8"><title>{}</title>
+    <style>body{{background:#111;margin:0;display:flex;align-items:center;justify-content:center;min-height:100vh;}}</style>
+    </head>
+    <body><img src="/content/{}" style="max-width:100%;max-height:100vh;"></body>
+    </html>"#,
+                title, id_attr
+            )
+        } else if content_type == "text/html" {
+            // Wrap HTML inscriptions in a sandboxed iframe so scripts run in an opaque origin
+            format!(
+                r#"<!DOCTYPE html>
+    <html>
+    <head><meta charset="utf-8"><title>{}</title></head>
+    <body><iframe src="{}/content/{}" sandbox="allow-scripts" style="width:100%;height:100vh;border:none;"></iframe></body>
+    </html>"#,
+                title,
+                html_escape::encode_double_quoted_attribute(&*state.content_origin),
+                id_attr
+            )
+        } else if content_type.starts_with("text/") || content_type == "application/json" {
+            let content_bytes = state.db.get_content(&id).unwrap_or(None).unwrap_or_default();
+            let text = String::from_utf8(content_bytes).unwrap_or_else(|_| "Invalid UTF-8".to_string());
+            format!(
+                r#"<!DOCTYPE html>
+    <html>
+    <head><meta charset="utf-8"><title>{}</title>
+    <style>body{{background:#111;color:#fff;font-family:monospace;padding:20px;line-height:1.6;}}pre{{white-space:pre-wrap;word-wrap:break-word;}}</style>
+    </head>
+    <body><pre>{}</pre></body>
+    </html>"#,
+                title,
+                html_escape::encode_text(&text)
+            )
+        } else {
+            format!(
+                r#"<!DOCTYPE html>
+    <html>
+    <head><meta charset="utf-8"><title>{}</title>
+    <style>body{{background:#111;color:#fff;font-family:monospace;padding:40px;text-align:center;}}</style>
+    </head>
+    <body><h2>Binary Content ({})</h2><a href="/content/{}" style="color:#fff;">Download</a></body>
+    </html>"#,
+                title,
+                html_escape::encode_text(content_type),
+                id_attr
+            )
+        };
+
+        ([(header::X_CONTENT_TYPE_OPTIONS, "nosniff")], Html(preview_html)).into_response()
+    })
+    .await
+}
+
+// Newest indexed blocks with activity counts; `after_id` takes a height
+/// Newest indexed blocks with inscription, token and name counts
+#[utoipa::path(
+    get,
+    path = "/api/v1/blocks",
+    tag = "status",
+    params(
+        ("page" = Option<usize>, Query, description = "Zero-based page; ignored when a cursor is given"),
+        ("limit" = Option<usize>, Query, description = "Page size (default 24, max 200)"),
+        ("after_id" = Option<String>, Query, description = "Continue below this height (`next_cursor`)")
+    ),
+    responses((status = 200, body = PaginatedResponse<BlockSummary>))
+)]
+async fn get_blocks_feed(
+    State(state): State<AppState>,
+    Query(params): Query<PaginationParams>,
+) -> ApiResult<Json<PaginatedResponse<BlockSummary>>> {
+    blocking(&state, move |state| {
+        let (page, limit) = params.resolve();
+        let limit = limit.min(200);
+        let before = match params.after_id.as_deref() {
+            Some(raw) => Some(raw.parse::<u64>().map_err(|_| ApiError::bad_request("after_id must be a block height"))?),
+            None => None,
+        };
+        let internal = |e: anyhow::Error| ApiError::internal("blocks feed error", e);
+        let total = state.db.get_block_count().map_err(internal)?;
+        let items = state.db.list_block_summaries(page, limit, before).map_err(internal)?;
 
-    Html(preview_html).into_response()
+        let next_cursor = match items.last() {
+            Some(last) if items.len() == limit && last.height > 0 => Some(last.height.to_string()),
+            _ => None,
+        };
+        let has_more = if before.is_some() {
+            next_cursor.is_some()
+        } else {
+            let offset = (page as u64).saturating_mul(limit as u64);
+            offset + (items.len() as u64) < total
+        };
+        Ok(Json(PaginatedResponse {
+            page,
+            limit,
+            total,
+            has_more,
+            next_cursor,
+            items,
+        }))
+    })
+    .await
 }
 
+/// Block by height or hash with its inscriptions (RPC for unindexed blocks)
+///
+/// Indexed blocks are served locally; RPC is only asked about blocks the index has not seen.
+#[utoipa::path(
+    get,
+    path = "/block/{query}",
+    tag = "status",
+    params(("query" = String, Path, description = "Block height or hash")),
+    responses((
+        status = 200,
+        description = "`hash`, `height`, `time`, `previous`, `source` (`index` or `rpc`) and `inscriptions`; \
+                       RPC answers also carry `tx`",
+        body = serde_json::Value
+    ))
+)]
 async fn get_block(
-    State(_state): State<AppState>,
+    State(state): State<AppState>,
     Path(query): Path<String>,
-) -> Json<serde_json::Value> {
-    let rpc = ZcashRpcClient::new();
+) -> ApiResult<Json<serde_json::Value>> {
+    let lookup = query.clone();
+    if let Some(block) = blocking(&state, move |state| indexed_block(&state, &lookup)).await {
+        return Ok(Json(block));
+    }
+
+    let Some(rpc) = state.rpc.as_ref() else {
+        return Err(rpc_unavailable().with(serde_json::json!({ "query": query })));
+    };
     // Accept either height (u64) or hash
     let result = if let Ok(height) = query.parse::<u64>() {
         match rpc.get_block_hash(height).await {
@@ -1350,22 +4662,122 @@ async fn get_block(
     };
 
     match result {
-        Ok((hash, blk)) => Json(serde_json::json!({
+        Ok((hash, blk)) => Ok(Json(serde_json::json!({
             "hash": hash,
             "height": blk.height,
             "time": blk.time,
             "tx": blk.tx,
-            "previous": blk.previousblockhash
-        })),
-        Err(e) => Json(serde_json::json!({ "error": e.to_string(), "query": query })),
+            "previous": blk.previousblockhash,
+            "source": "rpc",
+            "inscriptions": []
+        }))),
+        Err(e) => Err(rpc_lookup_error(e, "Block").with(serde_json::json!({ "query": query }))),
+    }
+}
+
+// The block as recorded by the index, when it has been indexed
+fn indexed_block(state: &AppState, query: &str) -> Option<serde_json::Value> {
+    let (height, hash) = match query.parse::<u64>() {
+        Ok(height) => state.db.get_block_hash(height).ok().flatten().map(|hash| (height, hash)),
+        Err(_) => state
+            .db
+            .get_block_height(query)
+            .ok()
+            .flatten()
+            .map(|height| (height, query.to_string())),
+    }?;
+    let rows = state.db.list_block_inscriptions(height).unwrap_or_default();
+    let previous = match height {
+        0 => None,
+        h => state.db.get_block_hash(h - 1).ok().flatten(),
+    };
+    let mut time = None;
+    let inscriptions: Vec<serde_json::Value> = rows
+        .into_iter()
+        .map(|(id, raw)| {
+            let meta: serde_json::Value = serde_json::from_str(&raw).unwrap_or_default();
+            time = time.or(meta["block_time"].as_u64());
+            inscription_envelope(&state.db, &id, &meta)
+        })
+        .collect();
+    Some(serde_json::json!({
+        "hash": hash,
+        "height": height,
+        // Only known when the block carried an inscription
+        "time": time,
+        "previous": previous,
+        "source": "index",
+        "inscriptions": inscriptions
+    }))
+}
+
+// The transaction's reveals as recorded by the index, when it revealed any
+fn indexed_transaction(state: &AppState, txid: &str) -> Option<serde_json::Value> {
+    let rows = state.db.list_tx_inscriptions(txid).unwrap_or_default();
+    let (_, first) = rows.first()?;
+    let first: serde_json::Value = serde_json::from_str(first).unwrap_or_default();
+    let inscriptions: Vec<serde_json::Value> = rows
+        .iter()
+        .map(|(id, raw)| {
+            let meta: serde_json::Value = serde_json::from_str(raw).unwrap_or_default();
+            inscription_envelope(&state.db, id, &meta)
+        })
+        .collect();
+    Some(serde_json::json!({
+        "txid": txid,
+        "height": first["block_height"],
+        "time": first["block_time"],
+        "source": "index",
+        "inscriptions": inscriptions
+    }))
+}
+
+fn rpc_unavailable() -> ApiError {
+    ApiError::new(StatusCode::SERVICE_UNAVAILABLE, "RPC not available on this replica")
+}
+
+// The node not knowing the block or transaction, or rejecting the id, means it does
+// not exist; a node that is busy asks for a retry; anything else is the upstream's fault
+fn rpc_lookup_error(err: RpcError, what: &str) -> ApiError {
+    match err {
+        RpcError::NotFound { .. } | RpcError::InvalidParams { .. } => ApiError::not_found(format!("{} not found", what)),
+        RpcError::WarmingUp { .. } | RpcError::CircuitOpen { .. } => {
+            ApiError::new(StatusCode::SERVICE_UNAVAILABLE, "RPC node busy, retry later")
+        }
+        err => {
+            tracing::error!("{} lookup via RPC failed: {}", what.to_lowercase(), err);
+            ApiError::new(StatusCode::BAD_GATEWAY, "RPC request failed")
+        }
     }
 }
 
+/// Inscriptions revealed by a transaction (RPC for other transactions)
+///
+/// Transactions that revealed inscriptions are answered from the index; others go to RPC.
+#[utoipa::path(
+    get,
+    path = "/tx/{txid}",
+    tag = "status",
+    params(("txid" = String, Path, description = "Transaction id")),
+    responses((
+        status = 200,
+        description = "`txid`, `source` (`index` or `rpc`) and `inscriptions`; index answers add `height` and \
+                       `time`, RPC answers `hex`, `vin` and `vout`",
+        body = serde_json::Value
+    ))
+)]
 async fn get_transaction(
-    State(_state): State<AppState>,
+    State(state): State<AppState>,
     Path(txid): Path<String>,
-) -> Json<serde_json::Value> {
-    let rpc = ZcashRpcClient::new();
+) -> ApiResult<Json<serde_json::Value>> {
+    let lookup = txid.clone();
+    if let Some(tx) = blocking(&state, move |state| indexed_transaction(&state, &lookup)).await {
+        return Ok(Json(tx));
+    }
+
+    let Some(rpc) = state.rpc.as_ref() else {
+        return Err(rpc_unavailable().with(serde_json::json!({ "txid": txid })));
+    };
     match rpc.get_raw_transaction(&txid).await {
         Ok(tx) => {
             let vins: Vec<serde_json::Value> = tx
@@ -1385,65 +4797,292 @@ async fn get_transaction(
                     "addresses": o.script_pub_key.addresses
                 }))
                 .collect();
-            Json(serde_json::json!({
+            Ok(Json(serde_json::json!({
                 "txid": tx.txid,
                 "hex": tx.hex,
                 "vin": vins,
-                "vout": vouts
-            }))
+                "vout": vouts,
+                "source": "rpc",
+                "inscriptions": []
+            })))
         }
-        Err(e) => Json(serde_json::json!({ "error": e.to_string(), "txid": txid })),
+        Err(e) => Err(rpc_lookup_error(e, "Transaction").with(serde_json::json!({ "txid": txid }))),
     }
 }
 
-async fn get_status(State(state): State<AppState>) -> Json<serde_json::Value> {
-    let height = state.db.get_latest_indexed_height().unwrap_or(None);
-    let inscriptions = state.db.get_inscription_count().unwrap_or(0);
-    let tokens = state.db.get_token_count().unwrap_or(0);
-    let names = state.db.get_name_count().unwrap_or(0);
-    let chain_tip = state.db.get_status("chain_tip").unwrap_or(None);
-    let zrc20_height = state.db.get_status("zrc20_height").unwrap_or(None);
-    let names_height = state.db.get_status("names_height").unwrap_or(None);
+#[derive(Deserialize, ToSchema)]
+struct DecodeRequest {
+    /// Serialized transaction
+    hex: String,
+}
 
-    Json(serde_json::json!({
-        "height": height,
-        "inscriptions": inscriptions,
-        "tokens": tokens,
-        "names": names,
-        "synced": true,
-        "version": env!("CARGO_PKG_VERSION"),
-        "chain_tip": chain_tip,
-        "components": {
-            "core": { "height": height, "tip": chain_tip },
-            "zrc20": { "height": zrc20_height, "tip": chain_tip },
-            "names": { "height": names_height, "tip": chain_tip },
+#[derive(Serialize, ToSchema)]
+struct DecodeReport {
+    txid: String,
+    /// The block the transaction was indexed into for the dry run
+    height: u64,
+    /// Each inscription found with the protocol engines' verdicts
+    inscriptions: Vec<serde_json::Value>,
+    /// Activity rows indexing would record
+    activity: Vec<serde_json::Value>,
+}
+
+/// Dry-run a raw transaction: the inscriptions, protocol verdicts and activity indexing it would produce
+///
+/// The transaction is indexed as if it were mined in the next block, then the block is
+/// thrown away. Engines run unchanged, so verdicts match what indexing would do.
+#[utoipa::path(
+    post,
+    path = "/api/v1/decode",
+    tag = "status",
+    request_body = DecodeRequest,
+    responses((status = 200, body = DecodeReport))
+)]
+async fn post_decode(
+    State(state): State<AppState>,
+    Json(req): Json<DecodeRequest>,
+) -> ApiResult<Json<DecodeReport>> {
+    let hex = req.hex.trim().to_string();
+    if hex.is_empty() || hex.len() % 2 != 0 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(ApiError::bad_request("hex must be a serialized transaction in hex"));
+    }
+    let Some(rpc) = state.rpc.as_ref() else {
+        return Err(rpc_unavailable());
+    };
+    let tx = match rpc.decode_raw_transaction(&hex).await {
+        Ok(tx) => tx,
+        // The node could not parse it
+        Err(RpcError::InvalidParams { .. }) => return Err(ApiError::bad_request("transaction could not be decoded")),
+        Err(e @ (RpcError::WarmingUp { .. } | RpcError::CircuitOpen { .. })) => {
+            tracing::warn!("decoderawtransaction via RPC unavailable: {}", e);
+            return Err(ApiError::new(StatusCode::SERVICE_UNAVAILABLE, "RPC node busy, retry later"));
         }
-    }))
+        Err(e) => {
+            tracing::error!("decoderawtransaction via RPC failed: {}", e);
+            return Err(ApiError::new(StatusCode::BAD_GATEWAY, "RPC request failed"));
+        }
+    };
+    blocking(&state, move |state| {
+        let internal = |e: anyhow::Error| ApiError::internal("decode dry run error", e);
+        let height = state.db.get_latest_indexed_height().map_err(internal)?.map_or(0, |h| h + 1);
+        let time = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let writer = state.db.block_writer(height, time).map_err(internal)?;
+        // Placed right after the coinbase, ahead of anything else the block may hold
+        let inscriptions = crate::indexer::index_transaction(&writer, &tx, 1, height, time, false).map_err(internal)?;
+        let activity = writer.discard();
+        Ok(Json(DecodeReport { txid: tx.txid, height, inscriptions, activity }))
+    })
+    .await
+}
+
+// Payloads up to this size are decoded for protocol annotations
+const ANNOTATE_MAX_BYTES: u64 = 4096;
+
+// Decoded envelope of one inscription with its protocol annotation
+fn inscription_envelope(db: &Db, id: &str, meta: &serde_json::Value) -> serde_json::Value {
+    let content_type = meta["content_type"].as_str().unwrap_or("");
+    serde_json::json!({
+        "id": id,
+        "content_type": content_type,
+        "content_length": meta["content_length"],
+        "category": classify_mime(content_type),
+        "sender": meta["sender"],
+        "receiver": meta["receiver"],
+        "vout": meta["vout"],
+        "annotation": annotate_inscription(db, id, meta),
+    })
+}
+
+// What the protocol engines made of a small text payload: a ZRC-20/ZRC-721 op or a
+// name registration this inscription won. Null for everything else.
+fn annotate_inscription(db: &Db, id: &str, meta: &serde_json::Value) -> serde_json::Value {
+    let content_type = meta["content_type"].as_str().unwrap_or("");
+    let small = meta["content_length"].as_u64().is_some_and(|len| len <= ANNOTATE_MAX_BYTES);
+    if !small || !(content_type.starts_with("text/") || content_type.starts_with("application/json")) {
+        return serde_json::Value::Null;
+    }
+    let Some(bytes) = db.get_content(id).ok().flatten() else {
+        return serde_json::Value::Null;
+    };
+    let text = String::from_utf8_lossy(&bytes);
+    let text = text.trim();
+
+    if let Ok(payload) = serde_json::from_str::<serde_json::Value>(text) {
+        let protocol = payload["p"].as_str().unwrap_or("").to_lowercase();
+        let op = payload["op"].as_str().unwrap_or("");
+        let tick = payload["tick"]
+            .as_str()
+            .or(payload["collection"].as_str())
+            .map(|t| t.to_lowercase());
+        return match protocol.as_str() {
+            "zrc-20" => {
+                let mut annotation = serde_json::json!({
+                    "protocol": "zrc-20",
+                    "op": op,
+                    "tick": tick,
+                    "amt": payload["amt"],
+                });
+                if op == "transfer" {
+                    annotation["transfer_state"] = transfer_state(db, id).into();
+                }
+                annotation
+            }
+            "zrc-721" => serde_json::json!({
+                "protocol": "zrc-721",
+                "op": op,
+                "collection": tick,
+                "token_id": payload["id"],
+            }),
+            "zns" => serde_json::json!({
+                "protocol": "zns",
+                "op": op,
+                "name": payload["name"].as_str().map(normalize_name),
+            }),
+            _ => serde_json::Value::Null,
+        };
+    }
+
+    if content_type.starts_with("text/plain") {
+        let name = normalize_name(text);
+        let record = db
+            .get_name(&name)
+            .ok()
+            .flatten()
+            .and_then(|raw| serde_json::from_str::<serde_json::Value>(&raw).ok());
+        if let Some(record) = record.filter(|r| r["inscription_id"].as_str() == Some(id)) {
+            return serde_json::json!({ "protocol": "zns", "name": name, "owner": record["owner"] });
+        }
+    }
+    serde_json::Value::Null
 }
 
-async fn get_zrc20_status(State(state): State<AppState>) -> Json<serde_json::Value> {
+#[derive(Serialize, ToSchema)]
+struct StatusComponents {
+    core: ComponentSync,
+    zrc20: ComponentSync,
+    names: ComponentSync,
+}
+
+#[derive(Serialize, ToSchema)]
+struct IndexStatus {
+    height: Option<u64>,
+    inscriptions: u64,
+    tokens: u64,
+    names: u64,
+    /// Always true; see `/api/v1/healthz` for the computed sync state
+    synced: bool,
+    version: &'static str,
+    chain_tip: Option<u64>,
+    components: StatusComponents,
+}
+
+/// Index height, chain tip and component heights
+#[utoipa::path(get, path = "/api/v1/status", tag = "status", responses((status = 200, body = IndexStatus)))]
+async fn get_status(State(state): State<AppState>) -> Json<IndexStatus> {
+    blocking(&state, move |state| {
+        let height = state.db.get_latest_indexed_height().unwrap_or(None);
+        let inscriptions = state.db.get_inscription_count().unwrap_or(0);
+        let tokens = state.db.get_token_count().unwrap_or(0);
+        let names = state.db.get_name_count().unwrap_or(0);
+        let chain_tip = state.db.get_status("chain_tip").unwrap_or(None);
+        let zrc20_height = state.db.get_status("zrc20_height").unwrap_or(None);
+        let names_height = state.db.get_status("names_height").unwrap_or(None);
+
+        Json(IndexStatus {
+            height,
+            inscriptions,
+            tokens,
+            names,
+            synced: true,
+            version: env!("CARGO_PKG_VERSION"),
+            chain_tip,
+            components: StatusComponents {
+                core: ComponentSync { height, tip: chain_tip },
+                zrc20: ComponentSync { height: zrc20_height, tip: chain_tip },
+                names: ComponentSync { height: names_height, tip: chain_tip },
+            },
+        })
+    })
+    .await
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct StatsParams {
+    /// `day` (default) or `week`
+    period: Option<String>,
+    /// Most recent periods to return (default 30, max 366)
+    limit: Option<usize>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct ActivityStats {
+    /// `day` or `week`
+    period: &'static str,
+    /// Newest period first
+    items: Vec<PeriodStats>,
+}
+
+// A year of days is the most one request returns
+const MAX_STATS_PERIODS: usize = 366;
+
+/// Inscriptions, categories, ZRC-20 mints, names and active addresses per day or week
+///
+/// Counters are maintained at index time per UTC day and ISO week.
+#[utoipa::path(get, path = "/api/v1/stats", tag = "status", params(StatsParams), responses((status = 200, body = ActivityStats)))]
+async fn get_activity_stats(
+    State(state): State<AppState>,
+    Query(params): Query<StatsParams>,
+) -> ApiResult<Json<ActivityStats>> {
+    let name = params.period.unwrap_or_else(|| "day".to_string());
+    let period = Period::parse(&name).ok_or_else(|| ApiError::bad_request("period must be day or week"))?;
+    let limit = params.limit.unwrap_or(30).clamp(1, MAX_STATS_PERIODS);
+    blocking(&state, move |state| {
+        let items = state
+            .db
+            .activity_stats(period, limit)
+            .map_err(|e| ApiError::internal("activity stats error", e))?;
+        Ok(Json(ActivityStats { period: if period == Period::Week { "week" } else { "day" }, items }))
+    })
+    .await
+}
+
+#[derive(Serialize, ToSchema)]
+struct Zrc20Status {
+    height: Option<u64>,
+    chain_tip: Option<u64>,
+    tokens: u64,
+    version: &'static str,
+}
+
+/// ZRC-20 module height and token count
+#[utoipa::path(get, path = "/api/v1/zrc20/status", tag = "zrc20", responses((status = 200, body = Zrc20Status)))]
+async fn get_zrc20_status(State(state): State<AppState>) -> Json<Zrc20Status> {
     let height = state.db.get_status("zrc20_height").unwrap_or(None);
     let chain_tip = state.db.get_status("chain_tip").unwrap_or(None);
     let tokens = state.db.get_token_count().unwrap_or(0);
-    Json(serde_json::json!({
-        "height": height,
-        "chain_tip": chain_tip,
-        "tokens": tokens,
-        "version": env!("CARGO_PKG_VERSION")
-    }))
+    Json(Zrc20Status { height, chain_tip, tokens, version: env!("CARGO_PKG_VERSION") })
 }
 
-async fn get_zrc721_status(State(state): State<AppState>) -> Json<serde_json::Value> {
+#[derive(Serialize, ToSchema)]
+struct Zrc721Status {
+    collections: usize,
+    tokens: usize,
+    height: Option<u64>,
+    chain_tip: Option<u64>,
+    version: &'static str,
+}
+
+/// ZRC-721 module height and counts
+#[utoipa::path(get, path = "/api/v1/zrc721/status", tag = "zrc721", responses((status = 200, body = Zrc721Status)))]
+async fn get_zrc721_status(State(state): State<AppState>) -> Json<Zrc721Status> {
     let (collections, tokens) = state.db.zrc721_counts().unwrap_or((0, 0));
     let height = state.db.get_status("zrc721_height").unwrap_or(None);
     let chain_tip = state.db.get_status("chain_tip").unwrap_or(None);
-    Json(serde_json::json!({
-        "collections": collections,
-        "tokens": tokens,
-        "height": height,
-        "chain_tip": chain_tip,
-        "version": env!("CARGO_PKG_VERSION")
-    }))
+    Json(Zrc721Status { collections, tokens, height, chain_tip, version: env!("CARGO_PKG_VERSION") })
 }
 
 async fn api_docs() -> Html<String> {
@@ -1469,6 +5108,7 @@ async fn api_docs() -> Html<String> {
             <li><code>/api/v1/names?page=0&limit=100</code></li>
             <li><code>/api/v1/status</code></li>
         </ul>
+        <p>The JSON API is described by OpenAPI at <a href=\"/api/v1/openapi.json\">/api/v1/openapi.json</a> and browsable at <a href=\"/spec\">/spec</a>.</p>
         <p>Full documentation lives in <a href=\"https://github.com/zatoshi/zord/tree/main/docs\">/docs</a> inside the repository.</p>
         <p>Legacy ord-compatible routes such as <code>/inscription/:id</code> and <code>/content/:id</code> remain available for tooling parity.</p>
     </div>
@@ -1476,76 +5116,59 @@ async fn api_docs() -> Html<String> {
 </html>"#.to_string())
 }
 
-async fn get_all_tokens_api(State(state): State<AppState>) -> Json<serde_json::Value> {
-    let tokens = state.db.get_all_tokens().unwrap_or_default();
-
-    let mut token_list: Vec<serde_json::Value> = Vec::new();
-    for (ticker, info_str) in tokens {
-        if let Ok(mut info) = serde_json::from_str::<serde_json::Value>(&info_str) {
-            info["ticker"] = serde_json::Value::String(ticker);
-
-            // Normalize supply/max based on decimals stored on-chain
-            let dec = info["dec"]
-                .as_str()
-                .and_then(|s| s.parse::<u32>().ok())
-                .unwrap_or(18);
-            let divisor = 10u64.pow(dec) as f64;
-
-            // Supply is persisted in base units
-            let supply_str = info["supply"].as_str().unwrap_or("0");
-            if let Ok(supply_base) = supply_str.parse::<u128>() {
-                info["supply_display"] =
-                    serde_json::json!((supply_base as f64 / divisor).to_string());
-            }
-
-            // Max field is human readable; convert to base units for comparison
-            let max_str = info["max"].as_str().unwrap_or("0");
-            if let Ok(max_value) = parse_decimal_amount(max_str, dec) {
-                info["max_display"] = serde_json::json!(max_str);
-                info["max_base"] = serde_json::json!(max_value.to_string());
-            }
+#[derive(Serialize, ToSchema)]
+struct AllTokens {
+    /// Deploy records with `ticker`, `supply_display`, `max_display` and `max_base`, newest first
+    tokens: Vec<serde_json::Value>,
+}
 
-            token_list.push(info);
+/// Every token with display supply
+#[utoipa::path(get, path = "/tokens/list", tag = "zrc20", responses((status = 200, body = AllTokens)))]
+async fn get_all_tokens_api(State(state): State<AppState>) -> Json<AllTokens> {
+    blocking(&state, move |state| {
+        let mut token_list: Vec<serde_json::Value> = Vec::new();
+        if let Ok(snapshot) = state.db.snapshot() {
+            scan_rows(snapshot.tokens(SCAN_BATCH), |ticker, info_str| {
+                if let Ok(mut info) = serde_json::from_str::<serde_json::Value>(&info_str) {
+                    info["ticker"] = serde_json::Value::String(ticker);
+
+                    // Normalize supply/max based on decimals stored on-chain
+                    let dec = info["dec"]
+                        .as_str()
+                        .and_then(|s| s.parse::<u32>().ok())
+                        .unwrap_or(18);
+
+                    // Supply is persisted in base units
+                    let supply_str = info["supply"].as_str().unwrap_or("0");
+                    if let Ok(supply_base) = Amount::parse_base_units(supply_str) {
+                        info["supply_display"] = serde_json::json!(supply_base.display(dec));
+                    }
+
+                    // Max field is human readable; convert to base units for comparison
+                    let max_str = info["max"].as_str().unwrap_or("0");
+                    if let Ok(max_value) = Amount::parse(max_str, dec) {
+                        info["max_display"] = serde_json::json!(max_str);
+                        info["max_base"] = serde_json::json!(max_value.to_string());
+                    }
+
+                    token_list.push(info);
+                }
+            });
         }
-    }
 
-    // Order newest-first by inscription id (ids encode creation order)
-    token_list.sort_by(|a, b| {
-        let id_a = a["inscription_id"].as_str().unwrap_or("");
-        let id_b = b["inscription_id"].as_str().unwrap_or("");
-        id_b.cmp(id_a) // Keep newest entries at the top
-    });
+        // Order newest-first by inscription id (ids encode creation order)
+        token_list.sort_by(|a, b| {
+            let id_a = a["inscription_id"].as_str().unwrap_or("");
+            let id_b = b["inscription_id"].as_str().unwrap_or("");
+            id_b.cmp(id_a) // Keep newest entries at the top
+        });
 
-    Json(serde_json::json!({
-        "tokens": token_list
-    }))
+        Json(AllTokens { tokens: token_list })
+    })
+    .await
 }
 
 // Parse a human-readable quantity into base units respecting decimals
-fn parse_decimal_amount(amount_str: &str, decimals: u32) -> Result<u128, std::num::ParseIntError> {
-    if amount_str.contains('.') {
-        let parts: Vec<&str> = amount_str.split('.').collect();
-        let whole: u128 = parts[0].parse()?;
-        let frac = if parts.len() > 1 { parts[1] } else { "0" };
-
-        // Clamp fractional digits to declared precision
-        let frac_truncated = if frac.len() > decimals as usize {
-            &frac[..decimals as usize]
-        } else {
-            frac
-        };
-
-        // Pad right side so we can treat the value as an integer
-        let frac_padded = format!("{:0<width$}", frac_truncated, width = decimals as usize);
-        let frac_value: u128 = frac_padded.parse()?;
-
-        Ok(whole * 10u128.pow(decimals) + frac_value)
-    } else {
-        let whole: u128 = amount_str.parse()?;
-        Ok(whole * 10u128.pow(decimals))
-    }
-}
-
 fn format_byte_size(bytes: usize) -> String {
     const UNITS: [&str; 4] = ["bytes", "KB", "MB", "GB"];
     let mut size = bytes as f64;
@@ -1569,136 +5192,408 @@ fn format_timestamp(ts: u64) -> String {
     }
 }
 
-fn build_preview(content_type: &str, value: &serde_json::Value) -> Option<String> {
+fn build_preview(db: &Db, id: &str, content_type: &str) -> Option<String> {
     if content_type.starts_with("text/") || content_type == "application/json" {
-        if let Some(body) = value["content"].as_str() {
-            let snippet: String = body.chars().take(240).collect();
-            if snippet.is_empty() {
-                None
-            } else {
-                Some(snippet)
-            }
-        } else {
+        let bytes = db.get_content(id).ok().flatten()?;
+        let body = String::from_utf8(bytes).ok()?;
+        let snippet: String = body.chars().take(240).collect();
+        if snippet.is_empty() {
             None
+        } else {
+            Some(snippet)
         }
     } else {
         None
     }
 }
 
-fn format_supply_string(base_units: &str, decimals: u32) -> String {
-    let value = parse_u128(base_units);
-    if decimals == 0 {
-        return value.to_string();
-    }
-    let scale = 10u128.pow(decimals);
-    let whole = value / scale;
-    let frac = value % scale;
-    if frac == 0 {
-        return whole.to_string();
-    }
-    let mut frac_str = format!("{:0width$}", frac, width = decimals as usize);
-    while frac_str.ends_with('0') {
-        frac_str.pop();
-    }
-    if frac_str.is_empty() {
-        whole.to_string()
-    } else {
-        format!("{}.{}", whole, frac_str)
-    }
-}
-
 fn parse_u128(value: &str) -> u128 {
     value.parse::<u128>().unwrap_or(0)
 }
 
-fn classify_mime(content_type: &str) -> &'static str {
-    let lower = content_type.to_lowercase();
-    if lower == "image/png" {
-        "png"
-    } else if lower == "image/jpeg" || lower == "image/jpg" {
-        "jpeg"
-    } else if lower == "image/gif" {
-        "gif"
-    } else if lower == "image/svg+xml" {
-        "svg"
-    } else if lower == "text/html" || lower == "application/xhtml+xml" {
-        "html"
-    } else if lower == "text/javascript" || lower == "application/javascript" {
-        "javascript"
-    } else if lower.starts_with("text/") {
-        "text"
-    } else if lower.starts_with("audio/") {
-        "audio"
-    } else if lower.starts_with("video/") {
-        "video"
-    } else if lower.starts_with("model/") {
-        "3d"
-    } else if lower.starts_with("image/") {
-        "image"
-    } else {
-        "binary"
-    }
+// ZNS helper endpoints
+#[derive(Serialize, ToSchema)]
+struct AllNames {
+    /// Stored name records in mint order
+    names: Vec<serde_json::Value>,
 }
 
-// ZNS helper endpoints
-async fn get_all_names_api(State(state): State<AppState>) -> Json<serde_json::Value> {
-    let names = state.db.get_all_names().unwrap_or_default();
+/// Every name in mint order
+#[utoipa::path(get, path = "/names/list", tag = "names", responses((status = 200, body = AllNames)))]
+async fn get_all_names_api(State(state): State<AppState>) -> Json<AllNames> {
+    blocking(&state, move |state| {
+        let mut name_list: Vec<serde_json::Value> = Vec::new();
+        if let Ok(snapshot) = state.db.snapshot() {
+            scan_rows(snapshot.names(SCAN_BATCH), |_name_lower, data_str| {
+                if let Ok(data) = serde_json::from_str::<serde_json::Value>(&data_str) {
+                    name_list.push(data);
+                }
+            });
+        }
+
+        // Preserve mint order (inscription_id encodes creation sequence)
+        name_list.sort_by(|a, b| {
+            let id_a = a["inscription_id"].as_str().unwrap_or("");
+            let id_b = b["inscription_id"].as_str().unwrap_or("");
+            id_a.cmp(id_b)
+        });
+
+        Json(AllNames { names: name_list })
+    })
+    .await
+}
 
-    let mut name_list: Vec<serde_json::Value> = Vec::new();
-    for (_name_lower, data_str) in names {
-        if let Ok(data) = serde_json::from_str::<serde_json::Value>(&data_str) {
-            name_list.push(data);
+/// Stored name record with its `reserved` flag
+///
+/// A reserved name that is not registered answers with `owner: null` instead of 404.
+#[utoipa::path(
+    get,
+    path = "/name/{name}",
+    tag = "names",
+    params(("name" = String, Path, description = "Name, e.g. `alice.zec`")),
+    responses((status = 200, description = "Stored name record with `reserved`", body = serde_json::Value))
+)]
+async fn get_name_info(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> ApiResult<Json<serde_json::Value>> {
+    let name_lower = normalize_name(&name);
+    let reserved = names::reserved::reserved().is_reserved(&name_lower);
+    let found = state
+        .db
+        .get_name(&name_lower)
+        .map_err(|e| ApiError::internal("name lookup error", e))?
+        .and_then(|raw| serde_json::from_str::<serde_json::Value>(&raw).ok());
+    match found {
+        Some(mut data) => {
+            data["reserved"] = reserved.into();
+            Ok(Json(data))
         }
+        // Unregistered but not open for registration either
+        None if reserved => Ok(Json(serde_json::json!({
+            "name": name,
+            "name_lower": name_lower,
+            "owner": null,
+            "reserved": true,
+        }))),
+        None => Err(ApiError::not_found(format!("Name {} not found", name_lower))),
     }
+}
 
-    // Preserve mint order (inscription_id encodes creation sequence)
-    name_list.sort_by(|a, b| {
-        let id_a = a["inscription_id"].as_str().unwrap_or("");
-        let id_b = b["inscription_id"].as_str().unwrap_or("");
-        id_a.cmp(id_b)
-    });
+#[derive(Serialize, ToSchema)]
+struct NameHistory {
+    name: String,
+    next_cursor: Option<String>,
+    /// Stored ownership changes, newest first
+    history: Vec<serde_json::Value>,
+}
 
-    Json(serde_json::json!({
-        "names": name_list
-    }))
+/// Newest-first ownership changes
+#[utoipa::path(
+    get,
+    path = "/api/v1/name/{name}/history",
+    tag = "names",
+    params(
+        ("name" = String, Path, description = "Name"),
+        ("limit" = Option<usize>, Query, description = "Page size (default 24, max 50000)"),
+        ("after_id" = Option<String>, Query, description = "Continue after this `next_cursor`")
+    ),
+    responses((status = 200, body = NameHistory))
+)]
+async fn get_name_history(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Query(params): Query<PaginationParams>,
+) -> Json<NameHistory> {
+    blocking(&state, move |state| {
+        let name_lower = normalize_name(&name);
+        let (_, limit) = params.resolve();
+        let rows = state
+            .db
+            .list_name_history(&name_lower, limit, params.cursor())
+            .unwrap_or_default();
+        let next_cursor = next_cursor(&rows, limit);
+        let history = rows
+            .into_iter()
+            .filter_map(|(_, raw)| serde_json::from_str(&raw).ok())
+            .collect();
+        Json(NameHistory { name: name_lower, next_cursor, history })
+    })
+    .await
 }
 
-async fn get_name_info(
+#[derive(Serialize, ToSchema)]
+struct Subdomain {
+    #[schema(value_type = String)]
+    name: serde_json::Value,
+    #[schema(value_type = String)]
+    owner: serde_json::Value,
+    #[schema(value_type = String)]
+    inscription_id: serde_json::Value,
+    /// Records set by the owner; `{}` when none were set
+    records: serde_json::Value,
+}
+
+#[derive(Serialize, ToSchema)]
+struct Subdomains {
+    name: String,
+    next_cursor: Option<String>,
+    subdomains: Vec<Subdomain>,
+}
+
+/// Newest-first subdomains registered under a name
+#[utoipa::path(
+    get,
+    path = "/api/v1/name/{name}/subdomains",
+    tag = "names",
+    params(
+        ("name" = String, Path, description = "Parent name"),
+        ("limit" = Option<usize>, Query, description = "Page size (default 24, max 50000)"),
+        ("after_id" = Option<String>, Query, description = "Continue after this `next_cursor`")
+    ),
+    responses((status = 200, body = Subdomains))
+)]
+async fn get_name_subdomains(
     State(state): State<AppState>,
     Path(name): Path<String>,
-) -> Json<serde_json::Value> {
-    let name_lower = name.to_lowercase();
+    Query(params): Query<PaginationParams>,
+) -> ApiResult<Json<Subdomains>> {
+    blocking(&state, move |state| {
+        let name_lower = normalize_name(&name);
+        let (_, limit) = params.resolve();
+        let rows = state
+            .db
+            .list_name_subdomains(&name_lower, limit, params.cursor())
+            .map_err(|e| ApiError::internal("subdomain listing error", e))?;
+        let next_cursor = next_cursor(&rows, limit);
+        let subdomains = rows
+            .into_iter()
+            .filter_map(|(_, raw)| serde_json::from_str::<serde_json::Value>(&raw).ok())
+            .map(|data| Subdomain {
+                records: name_records(&data),
+                name: data["name"].clone(),
+                owner: data["owner"].clone(),
+                inscription_id: data["inscription_id"].clone(),
+            })
+            .collect();
+        Ok(Json(Subdomains { name: name_lower, next_cursor, subdomains }))
+    })
+    .await
+}
 
-    if let Ok(Some(data_str)) = state.db.get_name(&name_lower) {
-        if let Ok(data) = serde_json::from_str::<serde_json::Value>(&data_str) {
-            return Json(data);
-        }
+/// Registering transaction with its Merkle path in the block
+///
+/// A client checks the path against its own node.
+#[utoipa::path(
+    get,
+    path = "/api/v1/name/{name}/proof",
+    tag = "names",
+    params(("name" = String, Path, description = "Name")),
+    responses((
+        status = 200,
+        description = "Stored proof: txid, block hash and height, tx index and the Merkle branch",
+        body = serde_json::Value
+    ))
+)]
+async fn get_name_proof(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> ApiResult<Json<serde_json::Value>> {
+    let name_lower = normalize_name(&name);
+    let found = state
+        .db
+        .get_name_proof(&name_lower)
+        .map_err(|e| ApiError::internal("name proof lookup error", e))?
+        .and_then(|raw| serde_json::from_str::<serde_json::Value>(&raw).ok());
+    match found {
+        Some(proof) => Ok(Json(proof)),
+        None => Err(ApiError::not_found(format!("No registration proof for {}", name_lower))),
     }
+}
 
-    Json(serde_json::json!({
-        "error": "Name not found"
-    }))
+/// A name's owner and records; fields are null in batch answers for unregistered names
+#[derive(Serialize, ToSchema)]
+struct Resolution {
+    name: String,
+    address: Option<String>,
+    /// Records set by the owner; `{}` when none were set
+    records: Option<serde_json::Value>,
+    /// Earlier name this one is a look-alike of
+    confusable_with: Option<String>,
 }
 
+/// Resolve a name to its owner address
+#[utoipa::path(
+    get,
+    path = "/api/v1/resolve/{name}",
+    tag = "names",
+    params(("name" = String, Path, description = "Name, e.g. `alice.zec`")),
+    responses((status = 200, body = Resolution))
+)]
 async fn resolve_name(
     State(state): State<AppState>,
     Path(name): Path<String>,
-) -> Json<serde_json::Value> {
-    let name_lower = name.to_lowercase();
-
-    if let Ok(Some(data_str)) = state.db.get_name(&name_lower) {
-        if let Ok(data) = serde_json::from_str::<serde_json::Value>(&data_str) {
-            if let Some(owner) = data["owner"].as_str() {
-                return Json(serde_json::json!({
-                    "name": data["name"].as_str().unwrap_or(&name),
-                    "address": owner
-                }));
+) -> ApiResult<Json<Resolution>> {
+    let name_lower = normalize_name(&name);
+    let found = state.db.get_name(&name_lower).map_err(|e| ApiError::internal("name lookup error", e))?;
+    if let Some(data) = found.and_then(|raw| serde_json::from_str::<serde_json::Value>(&raw).ok()) {
+        if let Some(owner) = data["owner"].as_str() {
+            return Ok(Json(Resolution {
+                name: data["name"].as_str().unwrap_or(&name).to_string(),
+                address: Some(owner.to_string()),
+                records: Some(name_records(&data)),
+                confusable_with: data["confusable_with"].as_str().map(str::to_string),
+            }));
+        }
+    }
+    Err(ApiError::not_found(format!("Name {} not found", name_lower)))
+}
+
+// Records set by the name's owner; `{}` when none were set
+fn name_records(data: &serde_json::Value) -> serde_json::Value {
+    match &data["records"] {
+        serde_json::Value::Object(records) => serde_json::Value::Object(records.clone()),
+        _ => serde_json::json!({}),
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+struct ResolveBatchRequest {
+    /// At most 1000 names, or prefixes ending in `*` such as `alice.*`
+    names: Vec<String>,
+}
+
+/// Every name starting with a `*` prefix
+#[derive(Serialize, ToSchema)]
+struct WildcardResolution {
+    /// The prefix as given, `*` included
+    name: String,
+    matches: Vec<Resolution>,
+    /// More names matched than are listed
+    truncated: bool,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(untagged)]
+enum BatchResolution {
+    Name(Resolution),
+    Wildcard(WildcardResolution),
+}
+
+#[derive(Serialize, ToSchema)]
+struct ResolveBatch {
+    /// In request order
+    items: Vec<BatchResolution>,
+}
+
+/// Resolve up to 1000 names or `alice.*` prefixes
+#[utoipa::path(
+    post,
+    path = "/api/v1/resolve/batch",
+    tag = "names",
+    request_body = ResolveBatchRequest,
+    responses((status = 200, body = ResolveBatch))
+)]
+async fn post_resolve_batch(
+    State(state): State<AppState>,
+    Json(req): Json<ResolveBatchRequest>,
+) -> ApiResult<Json<ResolveBatch>> {
+    blocking(&state, move |state| {
+        check_batch_size(req.names.len())?;
+        let mut items = Vec::with_capacity(req.names.len());
+        for name in req.names {
+            // A trailing '*' asks for every name starting with the rest, e.g. "alice.*"
+            if let Some(prefix) = name.strip_suffix('*') {
+                let prefix = normalize_name(prefix);
+                if prefix.is_empty() {
+                    return Err(ApiError::bad_request("wildcard needs a prefix, e.g. alice.*"));
+                }
+                let mut rows = state
+                    .db
+                    .names_with_prefix(&prefix, MAX_WILDCARD_MATCHES + 1)
+                    .map_err(|e| ApiError::internal("batch resolve error", e))?;
+                let truncated = rows.len() > MAX_WILDCARD_MATCHES;
+                rows.truncate(MAX_WILDCARD_MATCHES);
+                let matches = rows
+                    .iter()
+                    .filter_map(|(_, raw)| serde_json::from_str::<serde_json::Value>(raw).ok())
+                    .map(|data| resolved_name(data["name"].as_str().unwrap_or_default().to_string(), Some(&data)))
+                    .collect();
+                items.push(BatchResolution::Wildcard(WildcardResolution { name, matches, truncated }));
+                continue;
             }
+            let data = state
+                .db
+                .get_name(&normalize_name(&name))
+                .map_err(|e| ApiError::internal("batch resolve error", e))?
+                .and_then(|d| serde_json::from_str::<serde_json::Value>(&d).ok());
+            items.push(BatchResolution::Name(resolved_name(name, data.as_ref())));
         }
+        Ok(Json(ResolveBatch { items }))
+    })
+    .await
+}
+
+// Batch entry for one name; fields are null when it is unregistered
+fn resolved_name(name: String, data: Option<&serde_json::Value>) -> Resolution {
+    Resolution {
+        name,
+        address: data.and_then(|d| d["owner"].as_str()).map(str::to_string),
+        records: data.map(name_records),
+        confusable_with: data.and_then(|d| d["confusable_with"].as_str()).map(str::to_string),
     }
+}
 
-    Json(serde_json::json!({
-        "error": "Name not found"
-    }))
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn byte_ranges_clamp_to_the_payload() {
+        assert_eq!(parse_byte_range("bytes=0-99", 1000), Some(Ok((0, 99))));
+        assert_eq!(parse_byte_range("bytes=500-", 1000), Some(Ok((500, 999))));
+        assert_eq!(parse_byte_range("bytes=900-5000", 1000), Some(Ok((900, 999))));
+        assert_eq!(parse_byte_range(" bytes= 10 - 19 ", 1000), Some(Ok((10, 19))));
+        // Suffix form: the last N bytes, or all of them when N is larger
+        assert_eq!(parse_byte_range("bytes=-100", 1000), Some(Ok((900, 999))));
+        assert_eq!(parse_byte_range("bytes=-5000", 1000), Some(Ok((0, 999))));
+        assert_eq!(parse_byte_range("bytes=999-999", 1000), Some(Ok((999, 999))));
+    }
+
+    #[test]
+    fn unsatisfiable_ranges_are_errors() {
+        assert_eq!(parse_byte_range("bytes=1000-", 1000), Some(Err(())));
+        assert_eq!(parse_byte_range("bytes=20-10", 1000), Some(Err(())));
+        assert_eq!(parse_byte_range("bytes=-0", 1000), Some(Err(())));
+        assert_eq!(parse_byte_range("bytes=0-", 0), Some(Err(())));
+        assert_eq!(parse_byte_range("bytes=-1", 0), Some(Err(())));
+    }
+
+    #[test]
+    fn unsupported_ranges_fall_back_to_the_whole_payload() {
+        assert_eq!(parse_byte_range("bytes=0-1,5-6", 1000), None);
+        assert_eq!(parse_byte_range("items=0-1", 1000), None);
+        assert_eq!(parse_byte_range("bytes=a-b", 1000), None);
+        assert_eq!(parse_byte_range("bytes=5", 1000), None);
+        assert_eq!(parse_byte_range("bytes=-", 1000), None);
+    }
+
+    #[test]
+    fn content_headers_confine_documents_and_advertise_ranges_on_request() {
+        let png = content_headers("image/png", true);
+        assert_eq!(png[header::ACCEPT_RANGES], "bytes");
+        assert_eq!(png[header::X_CONTENT_TYPE_OPTIONS], "nosniff");
+        assert!(png.get(header::CONTENT_SECURITY_POLICY).is_none());
+
+        let svg = content_headers("image/svg+xml", false);
+        assert!(svg.get(header::ACCEPT_RANGES).is_none());
+        assert!(svg.get(header::CONTENT_SECURITY_POLICY).is_some());
+
+        let html = content_headers("text/html;charset=utf-8", true);
+        assert_eq!(html[header::CONTENT_TYPE], "text/html;charset=utf-8");
+        assert!(html.get(header::CONTENT_SECURITY_POLICY).is_some());
+
+        // Anything that is not a MIME type is served as opaque bytes
+        assert_eq!(content_headers("garbage", true)[header::CONTENT_TYPE], "application/octet-stream");
+    }
 }
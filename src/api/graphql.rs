@@ -0,0 +1,612 @@
+//! `/graphql`: a read-only GraphQL view over the index, executed by async-graphql.
+//!
+//! Types and their fields:
+//!
+//! - `Query`: `inscription(id)`, `inscriptions(limit, after)`, `token(tick)`,
+//!   `tokens(limit, after)`, `balance(tick, address)`, `address(address)`,
+//!   `collection(tick)`, `collections(limit, after)`, `name(name)`, `names(limit, after)`
+//! - `Inscription`: `id contentType contentLength contentSha256 txid vout sender
+//!   receiver blockHeight blockTime category contentUrl account`
+//! - `Token`: `tick dec max lim supply deployer inscriptionId inscription holders(limit, page, positiveOnly)`
+//! - `Balance`: `tick address available transferable overall token account inscriptions(limit, page)`
+//! - `Address`: `address inscriptions(limit, page) balances names nfts(limit, page)`
+//! - `Collection`: `collection supply minted owners verified meta royalty deployer inscriptionId inscription tokens(limit, page)`
+//! - `Nft`: `tick tokenId owner inscriptionId metadata inscription collection account`
+//! - `Name`: `name owner inscriptionId confusableWith inscription account`
+//!
+//! Lists default to 20 entries and cap at 100. A query may nest 8 levels deep and
+//! have a complexity of at most 5,000, where each field counts once and a list
+//! field counts its selection once per requested entry.
+//!
+//! Resolvers read redb directly, so a whole query executes on the blocking pool.
+
+use super::*;
+use crate::db::Zrc721Token;
+use crate::names::normalize_name;
+use crate::openapi::Endpoint;
+use async_graphql::{Context, EmptyMutation, EmptySubscription, Object, Schema, Variables};
+
+const DEFAULT_LIST: usize = 20;
+const MAX_LIST: usize = 100;
+const MAX_DEPTH: usize = 8;
+const MAX_COMPLEXITY: usize = 5000;
+
+pub(super) type ZordSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+pub(super) fn schema(db: Db) -> ZordSchema {
+    Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .data(db)
+        .limit_depth(MAX_DEPTH)
+        .limit_complexity(MAX_COMPLEXITY)
+        .finish()
+}
+
+pub(super) fn endpoint() -> Endpoint<AppState> {
+    routes!(get_graphql, post_graphql)
+}
+
+// GET carries the variables as a JSON-encoded query parameter
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub(super) struct GraphqlGetRequest {
+    /// GraphQL document
+    query: String,
+    /// JSON-encoded variables
+    variables: Option<String>,
+    /// Operation to run when the document has several
+    #[serde(rename = "operationName")]
+    operation_name: Option<String>,
+}
+
+/// POST body, as `async_graphql::Request` reads it
+#[derive(ToSchema)]
+#[allow(dead_code)]
+struct GraphqlBody {
+    query: String,
+    variables: Option<serde_json::Value>,
+    #[schema(rename = "operationName")]
+    operation_name: Option<String>,
+}
+
+/// Run a GraphQL query
+#[utoipa::path(
+    get,
+    path = "/graphql",
+    tag = "graphql",
+    params(GraphqlGetRequest),
+    responses((status = 200, description = "`{ data, errors? }`", body = serde_json::Value))
+)]
+pub(super) async fn get_graphql(
+    State(state): State<AppState>,
+    Query(req): Query<GraphqlGetRequest>,
+) -> Response {
+    let variables = match req.variables.as_deref().map(serde_json::from_str).transpose() {
+        Ok(v) => v,
+        Err(e) => return request_error(format!("variables are not valid JSON: {}", e)),
+    };
+    let mut request = async_graphql::Request::new(req.query).variables(Variables::from_json(variables.unwrap_or_default()));
+    if let Some(name) = req.operation_name {
+        request = request.operation_name(name);
+    }
+    execute(state, request).await
+}
+
+/// Run a GraphQL query
+#[utoipa::path(
+    post,
+    path = "/graphql",
+    tag = "graphql",
+    request_body = GraphqlBody,
+    responses((status = 200, description = "`{ data, errors? }`", body = serde_json::Value))
+)]
+pub(super) async fn post_graphql(State(state): State<AppState>, Json(req): Json<async_graphql::Request>) -> Response {
+    execute(state, req).await
+}
+
+async fn execute(state: AppState, request: async_graphql::Request) -> Response {
+    let schema = state.graphql.clone();
+    let result = tokio::task::spawn_blocking(move || futures::executor::block_on(schema.execute(request))).await;
+    match result {
+        // Documents that could not run at all (syntax, validation, limits) fail without a field path
+        Ok(response) if response.is_err() && response.errors.iter().all(|e| e.path.is_empty()) => {
+            (StatusCode::BAD_REQUEST, Json(response)).into_response()
+        }
+        Ok(response) => Json(response).into_response(),
+        Err(e) => ApiError::internal("graphql task failed", e).into_response(),
+    }
+}
+
+// GraphQL clients expect `errors`, not problem+json
+fn request_error(message: String) -> Response {
+    (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "errors": [{ "message": message }] }))).into_response()
+}
+
+fn db<'a>(ctx: &Context<'a>) -> &'a Db {
+    ctx.data_unchecked::<Db>()
+}
+
+fn internal(err: anyhow::Error) -> async_graphql::Error {
+    tracing::error!("graphql resolver error: {}", err);
+    async_graphql::Error::new("internal error")
+}
+
+fn parse_json(raw: &str) -> serde_json::Value {
+    serde_json::from_str(raw).unwrap_or_default()
+}
+
+fn text(record: &serde_json::Value, key: &str) -> Option<String> {
+    record[key].as_str().map(str::to_string)
+}
+
+fn list_len(limit: usize) -> usize {
+    limit.clamp(1, MAX_LIST)
+}
+
+fn inscription(db: &Db, id: &str) -> async_graphql::Result<Option<Inscription>> {
+    let raw = db.get_inscription(id).map_err(internal)?;
+    Ok(raw.map(|raw| Inscription { id: id.to_string(), meta: parse_json(&raw) }))
+}
+
+fn token(db: &Db, tick: &str) -> async_graphql::Result<Option<Token>> {
+    let tick = tick.to_lowercase();
+    let raw = db.get_token_info(&tick).map_err(internal)?;
+    Ok(raw.map(|raw| Token { tick, info: parse_json(&raw) }))
+}
+
+fn collection(db: &Db, tick: &str) -> async_graphql::Result<Option<Collection>> {
+    let tick = tick.to_lowercase();
+    let raw = db.get_zrc721_collection(&tick).map_err(internal)?;
+    Ok(raw.map(|raw| Collection { tick, info: parse_json(&raw) }))
+}
+
+fn name(db: &Db, name: &str) -> async_graphql::Result<Option<Name>> {
+    let raw = db.get_name(&normalize_name(name)).map_err(internal)?;
+    Ok(raw.map(|raw| Name(parse_json(&raw))))
+}
+
+// Inscriptions held by an address, paged in index order
+fn address_inscriptions(db: &Db, address: &str, limit: usize, page: usize) -> async_graphql::Result<Vec<Inscription>> {
+    let limit = list_len(limit);
+    let ids = db.get_inscriptions_by_address(address).map_err(internal)?;
+    let mut out = Vec::new();
+    for id in ids.iter().skip(page.saturating_mul(limit)).take(limit) {
+        out.extend(inscription(db, id)?);
+    }
+    Ok(out)
+}
+
+pub(super) struct QueryRoot;
+
+#[Object(name = "Query")]
+impl QueryRoot {
+    async fn inscription(&self, ctx: &Context<'_>, id: String) -> async_graphql::Result<Option<Inscription>> {
+        inscription(db(ctx), &id)
+    }
+
+    #[graphql(complexity = "list_len(limit) * child_complexity")]
+    async fn inscriptions(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default_with = "DEFAULT_LIST")] limit: usize,
+        after: Option<String>,
+    ) -> async_graphql::Result<Vec<Inscription>> {
+        let rows = db(ctx)
+            .get_inscriptions_page(0, list_len(limit), after.as_deref().map(Cursor::After))
+            .map_err(internal)?;
+        Ok(rows.into_iter().map(|(id, raw)| Inscription { id, meta: parse_json(&raw) }).collect())
+    }
+
+    async fn token(&self, ctx: &Context<'_>, tick: String) -> async_graphql::Result<Option<Token>> {
+        token(db(ctx), &tick)
+    }
+
+    #[graphql(complexity = "list_len(limit) * child_complexity")]
+    async fn tokens(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default_with = "DEFAULT_LIST")] limit: usize,
+        after: Option<String>,
+    ) -> async_graphql::Result<Vec<Token>> {
+        let rows = db(ctx)
+            .get_tokens_page(0, list_len(limit), after.as_deref().map(Cursor::After))
+            .map_err(internal)?;
+        Ok(rows.into_iter().map(|(tick, raw)| Token { tick, info: parse_json(&raw) }).collect())
+    }
+
+    async fn balance(&self, ctx: &Context<'_>, tick: String, address: String) -> async_graphql::Result<Balance> {
+        let tick = tick.to_lowercase();
+        let balance = db(ctx).get_balance(&address, &tick).map_err(internal)?;
+        Ok(Balance { tick, address, balance })
+    }
+
+    async fn address(&self, address: String) -> Address {
+        Address(address)
+    }
+
+    async fn collection(&self, ctx: &Context<'_>, tick: String) -> async_graphql::Result<Option<Collection>> {
+        collection(db(ctx), &tick)
+    }
+
+    #[graphql(complexity = "list_len(limit) * child_complexity")]
+    async fn collections(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default_with = "DEFAULT_LIST")] limit: usize,
+        after: Option<String>,
+    ) -> async_graphql::Result<Vec<Collection>> {
+        let rows = db(ctx)
+            .list_zrc721_collections(0, list_len(limit), after.as_deref().map(Cursor::After))
+            .map_err(internal)?;
+        Ok(rows.into_iter().map(|(tick, raw)| Collection { tick, info: parse_json(&raw) }).collect())
+    }
+
+    async fn name(&self, ctx: &Context<'_>, name: String) -> async_graphql::Result<Option<Name>> {
+        self::name(db(ctx), &name)
+    }
+
+    #[graphql(complexity = "list_len(limit) * child_complexity")]
+    async fn names(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default_with = "DEFAULT_LIST")] limit: usize,
+        after: Option<String>,
+    ) -> async_graphql::Result<Vec<Name>> {
+        let rows = db(ctx)
+            .get_names_page(0, list_len(limit), after.as_deref().map(Cursor::After))
+            .map_err(internal)?;
+        Ok(rows.into_iter().map(|(_, raw)| Name(parse_json(&raw))).collect())
+    }
+}
+
+pub(super) struct Inscription {
+    id: String,
+    meta: serde_json::Value,
+}
+
+#[Object]
+impl Inscription {
+    async fn id(&self) -> &str {
+        &self.id
+    }
+
+    async fn content_type(&self) -> Option<String> {
+        text(&self.meta, "content_type")
+    }
+
+    async fn content_length(&self) -> Option<u64> {
+        self.meta["content_length"].as_u64()
+    }
+
+    async fn content_sha256(&self) -> Option<String> {
+        text(&self.meta, "content_sha256")
+    }
+
+    async fn txid(&self) -> Option<String> {
+        text(&self.meta, "txid")
+    }
+
+    async fn vout(&self) -> Option<u64> {
+        self.meta["vout"].as_u64()
+    }
+
+    async fn sender(&self) -> Option<String> {
+        text(&self.meta, "sender")
+    }
+
+    async fn receiver(&self) -> Option<String> {
+        text(&self.meta, "receiver")
+    }
+
+    async fn block_height(&self) -> Option<u64> {
+        self.meta["block_height"].as_u64()
+    }
+
+    async fn block_time(&self) -> Option<u64> {
+        self.meta["block_time"].as_u64()
+    }
+
+    async fn category(&self) -> &'static str {
+        classify_mime(self.meta["content_type"].as_str().unwrap_or(""))
+    }
+
+    async fn content_url(&self) -> String {
+        format!("/content/{}", self.id)
+    }
+
+    async fn account(&self) -> Option<Address> {
+        text(&self.meta, "sender").map(Address)
+    }
+}
+
+pub(super) struct Token {
+    tick: String,
+    info: serde_json::Value,
+}
+
+#[Object]
+impl Token {
+    async fn tick(&self) -> &str {
+        &self.tick
+    }
+
+    async fn dec(&self) -> Option<String> {
+        text(&self.info, "dec")
+    }
+
+    async fn max(&self) -> Option<String> {
+        text(&self.info, "max")
+    }
+
+    async fn lim(&self) -> Option<String> {
+        text(&self.info, "lim")
+    }
+
+    async fn supply(&self) -> Option<String> {
+        text(&self.info, "supply")
+    }
+
+    async fn deployer(&self) -> Option<String> {
+        text(&self.info, "deployer")
+    }
+
+    async fn inscription_id(&self) -> Option<String> {
+        text(&self.info, "inscription_id")
+    }
+
+    async fn inscription(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<Inscription>> {
+        match self.info["inscription_id"].as_str() {
+            Some(id) => inscription(db(ctx), id),
+            None => Ok(None),
+        }
+    }
+
+    /// Holders in balance-table order; `positiveOnly` skips addresses whose balance went back to zero.
+    #[graphql(complexity = "list_len(limit) * child_complexity")]
+    async fn holders(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default_with = "DEFAULT_LIST")] limit: usize,
+        #[graphql(default)] page: usize,
+        #[graphql(default = true)] positive_only: bool,
+    ) -> async_graphql::Result<Vec<Balance>> {
+        let (rows, _, _) = db(ctx)
+            .list_balances_for_tick_filtered(&self.tick, page, list_len(limit), positive_only)
+            .map_err(internal)?;
+        Ok(rows
+            .into_iter()
+            .map(|(address, balance)| Balance { tick: self.tick.clone(), address, balance })
+            .collect())
+    }
+}
+
+pub(super) struct Balance {
+    tick: String,
+    address: String,
+    balance: crate::db::Balance,
+}
+
+#[Object]
+impl Balance {
+    async fn tick(&self) -> &str {
+        &self.tick
+    }
+
+    async fn address(&self) -> &str {
+        &self.address
+    }
+
+    async fn available(&self) -> String {
+        self.balance.available.to_string()
+    }
+
+    async fn transferable(&self, ctx: &Context<'_>) -> async_graphql::Result<String> {
+        let mut totals = db(ctx).transferable_by_tick(&self.address).map_err(internal)?;
+        Ok(totals.remove(self.tick.as_str()).unwrap_or_default().to_string())
+    }
+
+    async fn overall(&self) -> String {
+        self.balance.overall.to_string()
+    }
+
+    async fn token(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<Token>> {
+        token(db(ctx), &self.tick)
+    }
+
+    async fn account(&self) -> Address {
+        Address(self.address.clone())
+    }
+
+    #[graphql(complexity = "list_len(limit) * child_complexity")]
+    async fn inscriptions(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default_with = "DEFAULT_LIST")] limit: usize,
+        #[graphql(default)] page: usize,
+    ) -> async_graphql::Result<Vec<Inscription>> {
+        address_inscriptions(db(ctx), &self.address, limit, page)
+    }
+}
+
+pub(super) struct Address(String);
+
+#[Object]
+impl Address {
+    async fn address(&self) -> &str {
+        &self.0
+    }
+
+    #[graphql(complexity = "list_len(limit) * child_complexity")]
+    async fn inscriptions(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default_with = "DEFAULT_LIST")] limit: usize,
+        #[graphql(default)] page: usize,
+    ) -> async_graphql::Result<Vec<Inscription>> {
+        address_inscriptions(db(ctx), &self.0, limit, page)
+    }
+
+    // Unpaged; counted as a full list
+    #[graphql(complexity = "MAX_LIST * child_complexity")]
+    async fn balances(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<Balance>> {
+        let rows = db(ctx).list_balances_for_address(&self.0).map_err(internal)?;
+        Ok(rows
+            .into_iter()
+            .map(|(tick, balance)| Balance { tick, address: self.0.clone(), balance })
+            .collect())
+    }
+
+    #[graphql(complexity = "MAX_LIST * child_complexity")]
+    async fn names(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<Name>> {
+        let db = db(ctx);
+        let holdings = db.list_holdings(&self.0).map_err(internal)?;
+        let mut out = Vec::new();
+        for held in holdings.names {
+            out.extend(name(db, &held)?);
+        }
+        Ok(out)
+    }
+
+    #[graphql(complexity = "list_len(limit) * child_complexity")]
+    async fn nfts(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default_with = "DEFAULT_LIST")] limit: usize,
+        #[graphql(default)] page: usize,
+    ) -> async_graphql::Result<Vec<Nft>> {
+        let rows = db(ctx).list_zrc721_tokens_by_address(&self.0, page, list_len(limit)).map_err(internal)?;
+        Ok(rows.into_iter().map(Nft).collect())
+    }
+}
+
+pub(super) struct Collection {
+    tick: String,
+    info: serde_json::Value,
+}
+
+#[Object]
+impl Collection {
+    async fn collection(&self) -> Option<String> {
+        text(&self.info, "collection")
+    }
+
+    async fn supply(&self) -> Option<String> {
+        text(&self.info, "supply")
+    }
+
+    async fn minted(&self) -> Option<u64> {
+        self.info["minted"].as_u64()
+    }
+
+    async fn owners(&self) -> Option<u64> {
+        self.info["owners"].as_u64()
+    }
+
+    async fn verified(&self, ctx: &Context<'_>) -> async_graphql::Result<bool> {
+        db(ctx).is_zrc721_verified(&self.tick).map_err(internal)
+    }
+
+    /// Deploy `meta`: a CID string or an inline JSON object.
+    async fn meta(&self) -> async_graphql::Json<serde_json::Value> {
+        async_graphql::Json(self.info["meta"].clone())
+    }
+
+    async fn royalty(&self) -> Option<String> {
+        text(&self.info, "royalty")
+    }
+
+    async fn deployer(&self) -> Option<String> {
+        text(&self.info, "deployer")
+    }
+
+    async fn inscription_id(&self) -> Option<String> {
+        text(&self.info, "inscription_id")
+    }
+
+    async fn inscription(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<Inscription>> {
+        match self.info["inscription_id"].as_str() {
+            Some(id) => inscription(db(ctx), id),
+            None => Ok(None),
+        }
+    }
+
+    #[graphql(complexity = "list_len(limit) * child_complexity")]
+    async fn tokens(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default_with = "DEFAULT_LIST")] limit: usize,
+        #[graphql(default)] page: usize,
+    ) -> async_graphql::Result<Vec<Nft>> {
+        let rows = db(ctx).list_zrc721_tokens(&self.tick, page, list_len(limit)).map_err(internal)?;
+        Ok(rows.into_iter().map(Nft).collect())
+    }
+}
+
+pub(super) struct Nft(Zrc721Token);
+
+#[Object]
+impl Nft {
+    async fn tick(&self) -> &str {
+        &self.0.tick
+    }
+
+    async fn token_id(&self) -> &str {
+        &self.0.token_id
+    }
+
+    async fn owner(&self) -> &str {
+        &self.0.owner
+    }
+
+    async fn inscription_id(&self) -> &str {
+        &self.0.inscription_id
+    }
+
+    async fn metadata(&self) -> async_graphql::Json<serde_json::Value> {
+        async_graphql::Json(self.0.metadata.clone())
+    }
+
+    async fn inscription(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<Inscription>> {
+        inscription(db(ctx), &self.0.inscription_id)
+    }
+
+    async fn collection(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<Collection>> {
+        collection(db(ctx), &self.0.tick)
+    }
+
+    async fn account(&self) -> Address {
+        Address(self.0.owner.clone())
+    }
+}
+
+pub(super) struct Name(serde_json::Value);
+
+#[Object]
+impl Name {
+    async fn name(&self) -> Option<String> {
+        text(&self.0, "name")
+    }
+
+    async fn owner(&self) -> Option<String> {
+        text(&self.0, "owner")
+    }
+
+    async fn inscription_id(&self) -> Option<String> {
+        text(&self.0, "inscription_id")
+    }
+
+    /// Established name this one is confusable with, if any.
+    async fn confusable_with(&self) -> Option<String> {
+        text(&self.0, "confusable_with")
+    }
+
+    async fn inscription(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<Inscription>> {
+        match self.0["inscription_id"].as_str() {
+            Some(id) => inscription(db(ctx), id),
+            None => Ok(None),
+        }
+    }
+
+    async fn account(&self) -> Option<Address> {
+        text(&self.0, "owner").map(Address)
+    }
+}
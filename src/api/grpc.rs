@@ -0,0 +1,259 @@
+//! `zord.v1.Zord` gRPC service (`proto/zord/v1/zord.proto`), served by tonic.
+//!
+//! Messages and the service trait are generated from the proto by `build.rs`.
+//! The service is off unless `GRPC_LISTEN` names listeners for it; they need
+//! their own because gRPC runs over HTTP/2 end to end. They take the same entries
+//! as `API_LISTEN`, use the API's TLS certificate on TCP and also accept gRPC-Web
+//! over HTTP/1.1, so browser clients connect without a translating proxy.
+
+use super::*;
+use futures::Stream;
+use proto::zord_server::{Zord, ZordServer};
+use std::collections::HashMap;
+use std::pin::Pin;
+use tokio::sync::broadcast;
+use tonic::{Request, Response as GrpcResponse, Status};
+
+pub mod proto {
+    tonic::include_proto!("zord.v1");
+}
+
+/// Serve the gRPC API on `listeners`. Calls draw on `budget`, the REST API's
+/// standard concurrency cap and timeout; a server stream holds its permit only
+/// until the stream starts. Binding happens before this returns, so a bad address
+/// fails startup.
+pub(super) async fn start(
+    state: AppState,
+    listeners: Vec<listen::Listen>,
+    tls: Option<Arc<tls::Tls>>,
+    budget: &Budget,
+) -> anyhow::Result<()> {
+    for listen in listeners {
+        let acceptor = match (&listen, &tls) {
+            (listen::Listen::Tcp(_), Some(tls)) => Some(tls.grpc_acceptor()),
+            _ => None,
+        };
+        let scheme = if acceptor.is_some() { "TLS" } else { "plaintext" };
+        let incoming = listen::incoming(&listen, acceptor).await?;
+        tracing::info!("gRPC listening on {} ({})", listen, scheme);
+        let server = tonic::transport::Server::builder()
+            .accept_http1(true)
+            .timeout(budget.timeout)
+            .layer(GlobalConcurrencyLimitLayer::with_semaphore(budget.permits.clone()))
+            // Browser gRPC-Web clients get the same CORS policy as the REST API
+            .layer(headers::cors_layer()?)
+            .layer(tonic_web::GrpcWebLayer::new())
+            .add_service(ZordServer::new(ZordService { state: state.clone() }));
+        tokio::spawn(async move {
+            if let Err(e) = server.serve_with_incoming(incoming).await {
+                tracing::error!("gRPC server on {} failed: {}", listen, e);
+            }
+        });
+    }
+    Ok(())
+}
+
+struct ZordService {
+    state: AppState,
+}
+
+fn internal(context: &str, cause: impl std::fmt::Display) -> Status {
+    tracing::error!("{}: {}", context, cause);
+    Status::internal("Internal error")
+}
+
+fn required(value: String, name: &str) -> Result<String, Status> {
+    if value.is_empty() {
+        return Err(Status::invalid_argument(format!("{} is required", name)));
+    }
+    Ok(value)
+}
+
+type EventStream = Pin<Box<dyn Stream<Item = Result<proto::BlockEvents, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl Zord for ZordService {
+    async fn get_inscription(
+        &self,
+        request: Request<proto::GetInscriptionRequest>,
+    ) -> Result<GrpcResponse<proto::Inscription>, Status> {
+        let id = required(request.into_inner().id, "id")?;
+        let reply = blocking(&self.state, move |state| -> Result<_, Status> {
+            let raw = state
+                .db
+                .get_inscription(&id)
+                .map_err(|e| internal("grpc inscription error", e))?
+                .ok_or_else(|| Status::not_found(format!("Inscription {} not found", id)))?;
+            let meta: serde_json::Value = serde_json::from_str(&raw).unwrap_or_default();
+            Ok(inscription(&id, &meta))
+        })
+        .await?;
+        Ok(GrpcResponse::new(reply))
+    }
+
+    async fn get_balance(
+        &self,
+        request: Request<proto::GetBalanceRequest>,
+    ) -> Result<GrpcResponse<proto::Balance>, Status> {
+        let request = request.into_inner();
+        let tick = required(request.tick, "tick")?.to_lowercase();
+        let address = required(request.address, "address")?;
+        let reply = blocking(&self.state, move |state| -> Result<_, Status> {
+            let known = state
+                .db
+                .get_token_info(&tick)
+                .map_err(|e| internal("grpc token error", e))?;
+            if known.is_none() {
+                return Err(Status::not_found(format!("Token {} not found", tick)));
+            }
+            let balance = state
+                .db
+                .get_balance(&address, &tick)
+                .map_err(|e| internal("grpc balance error", e))?;
+            let transferable = state
+                .db
+                .transferable_by_tick(&address)
+                .map_err(|e| internal("grpc balance error", e))?;
+            Ok(balance_message(&tick, &address, &balance, &transferable))
+        })
+        .await?;
+        Ok(GrpcResponse::new(reply))
+    }
+
+    async fn list_balances(
+        &self,
+        request: Request<proto::ListBalancesRequest>,
+    ) -> Result<GrpcResponse<proto::ListBalancesResponse>, Status> {
+        let address = required(request.into_inner().address, "address")?;
+        let reply = blocking(&self.state, move |state| -> Result<_, Status> {
+            let rows = state
+                .db
+                .list_balances_for_address(&address)
+                .map_err(|e| internal("grpc balances error", e))?;
+            let transferable = state
+                .db
+                .transferable_by_tick(&address)
+                .map_err(|e| internal("grpc balances error", e))?;
+            let balances = rows
+                .iter()
+                .map(|(tick, balance)| balance_message(tick, &address, balance, &transferable))
+                .collect();
+            Ok(proto::ListBalancesResponse { balances })
+        })
+        .await?;
+        Ok(GrpcResponse::new(reply))
+    }
+
+    async fn resolve_name(
+        &self,
+        request: Request<proto::ResolveNameRequest>,
+    ) -> Result<GrpcResponse<proto::Name>, Status> {
+        let name = normalize_name(&required(request.into_inner().name, "name")?);
+        let reply = blocking(&self.state, move |state| -> Result<_, Status> {
+            let data: serde_json::Value = state
+                .db
+                .get_name(&name)
+                .map_err(|e| internal("grpc name error", e))?
+                .and_then(|raw| serde_json::from_str(&raw).ok())
+                .ok_or_else(|| Status::not_found(format!("Name {} not found", name)))?;
+            Ok(proto::Name {
+                name: data["name"].as_str().unwrap_or(&name).to_string(),
+                owner: data["owner"].as_str().unwrap_or_default().to_string(),
+                inscription_id: data["inscription_id"].as_str().unwrap_or_default().to_string(),
+            })
+        })
+        .await?;
+        Ok(GrpcResponse::new(reply))
+    }
+
+    type StreamEventsStream = EventStream;
+
+    // One message per matching block until the indexer stops or the subscriber
+    // lags; the stream is dropped with the connection
+    async fn stream_events(
+        &self,
+        request: Request<proto::StreamEventsRequest>,
+    ) -> Result<GrpcResponse<EventStream>, Status> {
+        let Some(events) = &self.state.events else {
+            return Err(Status::unavailable("Live events are not available on read-only replicas"));
+        };
+        let request = request.into_inner();
+        let non_empty = |s: String| Some(s).filter(|s| !s.is_empty());
+        let filter = WebhookFilter {
+            tick: non_empty(request.tick),
+            address: non_empty(request.address),
+            kinds: request.kinds,
+        };
+        let stream = futures::stream::unfold(Some((events.subscribe(), filter)), |subscription| async move {
+            let (mut rx, filter) = subscription?;
+            loop {
+                match rx.recv().await {
+                    Ok(block) => {
+                        if let Some(message) = block_message(&block, &filter) {
+                            return Some((Ok(message), Some((rx, filter))));
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(_)) => {
+                        let status = Status::aborted("Subscriber fell behind; reconnect to resume");
+                        return Some((Err(status), None));
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        });
+        Ok(GrpcResponse::new(Box::pin(stream)))
+    }
+}
+
+fn inscription(id: &str, meta: &serde_json::Value) -> proto::Inscription {
+    let text = |key: &str| meta[key].as_str().unwrap_or_default().to_string();
+    let number = |key: &str| meta[key].as_u64().unwrap_or(0);
+    proto::Inscription {
+        id: id.to_string(),
+        content_type: text("content_type"),
+        content_length: number("content_length"),
+        content_sha256: text("content_sha256"),
+        txid: text("txid"),
+        vout: number("vout") as u32,
+        sender: text("sender"),
+        receiver: text("receiver"),
+        block_height: number("block_height"),
+        block_time: number("block_time"),
+        category: classify_mime(meta["content_type"].as_str().unwrap_or_default()).to_string(),
+    }
+}
+
+// `transferable` is the address's per-ticker total from `Db::transferable_by_tick`
+fn balance_message(
+    tick: &str,
+    address: &str,
+    balance: &crate::db::Balance,
+    transferable: &HashMap<String, Amount>,
+) -> proto::Balance {
+    proto::Balance {
+        tick: tick.to_string(),
+        address: address.to_string(),
+        available: balance.available.to_string(),
+        overall: balance.overall.to_string(),
+        transferable: transferable.get(tick).copied().unwrap_or_default().to_string(),
+    }
+}
+
+fn block_message(block: &Arc<crate::webhooks::BlockEvents>, filter: &WebhookFilter) -> Option<proto::BlockEvents> {
+    let events: Vec<proto::Event> = block
+        .events
+        .iter()
+        .filter(|e| filter.matches(e))
+        .map(|event| proto::Event {
+            kind: event["kind"].as_str().unwrap_or_default().to_string(),
+            address: event["address"].as_str().unwrap_or_default().to_string(),
+            tick: event["tick"].as_str().unwrap_or_default().to_string(),
+            json: event.to_string(),
+        })
+        .collect();
+    (!events.is_empty()).then(|| proto::BlockEvents {
+        height: block.height,
+        block_hash: block.hash.clone(),
+        events,
+    })
+}
@@ -0,0 +1,145 @@
+//! Cross-origin policy and the headers added to every response, configured from
+//! the environment.
+//!
+//! Defaults keep the API open to any origin, as it was before these were
+//! configurable, and add only headers that cannot break a plain-HTTP deployment:
+//! HSTS and a default `Cache-Control` are off until asked for.
+
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderName, HeaderValue, Method},
+    middleware::Next,
+    response::Response,
+};
+use std::sync::Arc;
+use std::time::Duration;
+use tower_http::cors::{AllowMethods, AllowOrigin, Any, CorsLayer};
+
+/// `CorsLayer` from `CORS_ALLOWED_ORIGINS`, `CORS_ALLOWED_METHODS` and `CORS_MAX_AGE`.
+pub(super) fn cors_layer() -> anyhow::Result<CorsLayer> {
+    let origins = match list("CORS_ALLOWED_ORIGINS") {
+        Some(origins) if !origins.iter().any(|o| o == "*") => AllowOrigin::list(
+            origins
+                .iter()
+                .map(|o| {
+                    HeaderValue::from_str(o.trim_end_matches('/'))
+                        .map_err(|_| anyhow::anyhow!("Invalid CORS origin: {}", o))
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?,
+        ),
+        _ => AllowOrigin::from(Any),
+    };
+    let methods = match list("CORS_ALLOWED_METHODS") {
+        Some(methods) if !methods.iter().any(|m| m == "*") => AllowMethods::list(
+            methods
+                .iter()
+                .map(|m| {
+                    Method::from_bytes(m.to_ascii_uppercase().as_bytes())
+                        .map_err(|_| anyhow::anyhow!("Invalid CORS method: {}", m))
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?,
+        ),
+        _ => AllowMethods::from(Any),
+    };
+    let mut cors = CorsLayer::new()
+        .allow_origin(origins)
+        .allow_methods(methods)
+        .allow_headers(Any)
+        .expose_headers(Any);
+    if let Some(secs) = seconds("CORS_MAX_AGE")? {
+        cors = cors.max_age(Duration::from_secs(secs));
+    }
+    Ok(cors)
+}
+
+/// Headers `security_headers` adds to responses that do not already carry them.
+pub(super) struct ResponseHeaders {
+    hsts: Option<HeaderValue>,
+    frame_options: Option<HeaderValue>,
+    referrer_policy: Option<HeaderValue>,
+    cache_control: Option<HeaderValue>,
+}
+
+impl ResponseHeaders {
+    pub(super) fn from_env() -> anyhow::Result<Self> {
+        let hsts = seconds("HSTS_MAX_AGE")?.map(|secs| {
+            let subdomains = std::env::var("HSTS_INCLUDE_SUBDOMAINS")
+                .map(|v| matches!(v.as_str(), "1" | "true" | "TRUE" | "yes" | "YES"))
+                .unwrap_or(false);
+            let value = if subdomains {
+                format!("max-age={}; includeSubDomains", secs)
+            } else {
+                format!("max-age={}", secs)
+            };
+            HeaderValue::from_str(&value).expect("HSTS value is ASCII")
+        });
+        let frame_options = match setting("FRAME_OPTIONS", "SAMEORIGIN").map(|v| v.to_ascii_uppercase()) {
+            Some(v) if v != "DENY" && v != "SAMEORIGIN" => {
+                return Err(anyhow::anyhow!("FRAME_OPTIONS must be DENY, SAMEORIGIN or off"))
+            }
+            v => v.map(|v| HeaderValue::from_str(&v).expect("frame option is ASCII")),
+        };
+        let referrer_policy = setting("REFERRER_POLICY", "strict-origin-when-cross-origin")
+            .map(|v| HeaderValue::from_str(&v).map_err(|_| anyhow::anyhow!("Invalid REFERRER_POLICY: {}", v)))
+            .transpose()?;
+        let cache_control = seconds("API_CACHE_MAX_AGE")?
+            .map(|secs| HeaderValue::from_str(&format!("public, max-age={}", secs)).expect("cache value is ASCII"));
+        Ok(ResponseHeaders { hsts, frame_options, referrer_policy, cache_control })
+    }
+}
+
+pub(super) async fn security_headers(
+    State(config): State<Arc<ResponseHeaders>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    // Inscription content is framed by the explorer, possibly from CONTENT_ORIGIN
+    let framed = req.uri().path().starts_with("/content/");
+    // Only anonymous reads may land in a shared cache
+    let cacheable = matches!(*req.method(), Method::GET | Method::HEAD)
+        && !req.headers().contains_key(header::AUTHORIZATION)
+        && !req.headers().contains_key("x-api-key")
+        && !req.uri().path().starts_with("/api/v1/admin/");
+    let mut response = next.run(req).await;
+    let cacheable = cacheable && response.status().is_success();
+    let headers = response.headers_mut();
+    let mut add = |name: HeaderName, value: &Option<HeaderValue>| {
+        if let Some(value) = value {
+            headers.entry(name).or_insert_with(|| value.clone());
+        }
+    };
+    add(header::STRICT_TRANSPORT_SECURITY, &config.hsts);
+    add(header::REFERRER_POLICY, &config.referrer_policy);
+    if !framed {
+        add(header::X_FRAME_OPTIONS, &config.frame_options);
+    }
+    if cacheable {
+        add(header::CACHE_CONTROL, &config.cache_control);
+    }
+    response
+}
+
+// Comma-separated values of `name`, or `None` when unset or blank
+fn list(name: &str) -> Option<Vec<String>> {
+    let raw = std::env::var(name).ok()?;
+    let items: Vec<String> = raw.split(',').map(str::trim).filter(|s| !s.is_empty()).map(String::from).collect();
+    (!items.is_empty()).then_some(items)
+}
+
+fn seconds(name: &str) -> anyhow::Result<Option<u64>> {
+    match std::env::var(name) {
+        Ok(raw) if !raw.trim().is_empty() => raw
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|_| anyhow::anyhow!("{} must be a number of seconds", name)),
+        _ => Ok(None),
+    }
+}
+
+// Header value from `name`, `default` when unset, `None` when set to `off`
+fn setting(name: &str, default: &str) -> Option<String> {
+    let value = std::env::var(name).unwrap_or_else(|_| default.to_string());
+    let value = value.trim();
+    (!value.is_empty() && !value.eq_ignore_ascii_case("off")).then(|| value.to_string())
+}
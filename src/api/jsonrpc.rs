@@ -0,0 +1,202 @@
+//! `/rpc`: JSON-RPC 2.0 over the same lookups as the REST routes, for ord tooling.
+//!
+//! Methods take positional (`[tick, address]`) or named (`{ "tick": ... }`) params:
+//!
+//! - `getblockcount` → latest indexed height
+//! - `getinscription(id)` → stored metadata with its `id`
+//! - `gettoken(tick)` → ZRC-20 deploy record, as `/token/:tick`
+//! - `getbalance(tick, address)` → `{ tick, address, available, transferable, overall }` in base units
+//! - `resolvename(name)` → `{ name, address }`
+//!
+//! A batch is an array of up to `MAX_BATCH_ITEMS` calls. Transport-level problems
+//! still answer 200 with a JSON-RPC error object; notifications get no reply.
+
+use super::*;
+
+// Standard JSON-RPC error codes, plus one for lookups that find nothing
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+const NOT_FOUND: i64 = -32001;
+
+pub(super) fn router() -> ApiRouter<AppState> {
+    ApiRouter::new().routes(routes!(post_rpc))
+}
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        RpcError { code, message: message.into() }
+    }
+
+    fn internal(context: &str, cause: impl std::fmt::Display) -> Self {
+        tracing::error!("{}: {}", context, cause);
+        Self::new(INTERNAL_ERROR, "Internal error")
+    }
+}
+
+type RpcResult = Result<serde_json::Value, RpcError>;
+
+fn reply(id: serde_json::Value, result: RpcResult) -> serde_json::Value {
+    match result {
+        Ok(result) => serde_json::json!({ "jsonrpc": "2.0", "result": result, "id": id }),
+        Err(e) => serde_json::json!({
+            "jsonrpc": "2.0",
+            "error": { "code": e.code, "message": e.message },
+            "id": id,
+        }),
+    }
+}
+
+/// JSON-RPC 2.0 call or batch (`getinscription`, `gettoken`, `getbalance`, `resolvename`, `getblockcount`)
+#[utoipa::path(
+    post,
+    path = "/rpc",
+    tag = "rpc",
+    request_body(
+        content = serde_json::Value,
+        description = "One call `{ jsonrpc: \"2.0\", method, params, id }` or an array of up to 1000"
+    ),
+    responses(
+        (status = 200, description = "Reply `{ jsonrpc, result | error, id }`, or an array of replies for a batch", body = serde_json::Value),
+        (status = 204, description = "The request held only notifications")
+    )
+)]
+async fn post_rpc(State(state): State<AppState>, body: axum::body::Bytes) -> Response {
+    blocking(&state, move |state| {
+        let request: serde_json::Value = match serde_json::from_slice(&body) {
+            Ok(v) => v,
+            Err(e) => {
+                let error = RpcError::new(PARSE_ERROR, format!("Parse error: {}", e));
+                return Json(reply(serde_json::Value::Null, Err(error))).into_response();
+            }
+        };
+        match request {
+            serde_json::Value::Array(calls) => {
+                let oversized = match calls.len() {
+                    0 => Some("batch is empty".to_string()),
+                    n if n > MAX_BATCH_ITEMS => Some(format!("batch exceeds {} entries", MAX_BATCH_ITEMS)),
+                    _ => None,
+                };
+                if let Some(message) = oversized {
+                    return Json(reply(serde_json::Value::Null, Err(RpcError::new(INVALID_REQUEST, message)))).into_response();
+                }
+                let replies: Vec<serde_json::Value> = calls.iter().filter_map(|call| dispatch(&state, call)).collect();
+                if replies.is_empty() {
+                    return StatusCode::NO_CONTENT.into_response();
+                }
+                Json(serde_json::Value::Array(replies)).into_response()
+            }
+            call => match dispatch(&state, &call) {
+                Some(reply) => Json(reply).into_response(),
+                None => StatusCode::NO_CONTENT.into_response(),
+            },
+        }
+    })
+    .await
+}
+
+// Reply to one call, or `None` for a notification
+fn dispatch(state: &AppState, call: &serde_json::Value) -> Option<serde_json::Value> {
+    let Some(obj) = call.as_object() else {
+        return Some(reply(serde_json::Value::Null, Err(RpcError::new(INVALID_REQUEST, "Invalid request"))));
+    };
+    let id = obj.get("id").cloned();
+    let result = match (obj.get("jsonrpc").and_then(|v| v.as_str()), obj.get("method").and_then(|v| v.as_str())) {
+        (Some("2.0"), Some(method)) => {
+            let params = obj.get("params").cloned().unwrap_or(serde_json::Value::Null);
+            call_method(state, method, &params)
+        }
+        _ => Err(RpcError::new(INVALID_REQUEST, "Invalid request")),
+    };
+    // Invalid requests are answered even without an id, since they cannot be notifications
+    match (id, &result) {
+        (None, Err(e)) if e.code == INVALID_REQUEST => Some(reply(serde_json::Value::Null, result)),
+        (None, _) => None,
+        (Some(id), _) => Some(reply(id, result)),
+    }
+}
+
+// Positional param `index` or named param `name`, as a non-empty string
+fn string_param(params: &serde_json::Value, index: usize, name: &str) -> Result<String, RpcError> {
+    let value = match params {
+        serde_json::Value::Array(items) => items.get(index),
+        serde_json::Value::Object(map) => map.get(name),
+        _ => None,
+    };
+    match value.and_then(|v| v.as_str()) {
+        Some(s) if !s.is_empty() => Ok(s.to_string()),
+        _ => Err(RpcError::new(INVALID_PARAMS, format!("{} must be a non-empty string", name))),
+    }
+}
+
+fn call_method(state: &AppState, method: &str, params: &serde_json::Value) -> RpcResult {
+    match method {
+        "getblockcount" => state
+            .db
+            .get_latest_indexed_height()
+            .map(|h| serde_json::json!(h))
+            .map_err(|e| RpcError::internal("rpc height error", e)),
+        "getinscription" => {
+            let id = string_param(params, 0, "id")?;
+            let raw = state
+                .db
+                .get_inscription(&id)
+                .map_err(|e| RpcError::internal("rpc inscription error", e))?
+                .ok_or_else(|| RpcError::new(NOT_FOUND, format!("Inscription {} not found", id)))?;
+            Ok(v2::inscription(id, &raw))
+        }
+        "gettoken" => {
+            let tick = string_param(params, 0, "tick")?.to_lowercase();
+            let info = state
+                .db
+                .get_token_info(&tick)
+                .map_err(|e| RpcError::internal("rpc token error", e))?
+                .ok_or_else(|| RpcError::new(NOT_FOUND, format!("Token {} not found", tick)))?;
+            Ok(serde_json::from_str(&info).unwrap_or(serde_json::Value::String(info)))
+        }
+        "getbalance" => {
+            let tick = string_param(params, 0, "tick")?.to_lowercase();
+            let address = string_param(params, 1, "address")?;
+            let balance = state
+                .db
+                .get_balance(&address, &tick)
+                .map_err(|e| RpcError::internal("rpc balance error", e))?;
+            let transferable = state
+                .db
+                .transferable_by_tick(&address)
+                .map_err(|e| RpcError::internal("rpc balance error", e))?
+                .remove(&tick)
+                .unwrap_or_default();
+            Ok(serde_json::json!({
+                "tick": tick,
+                "address": address,
+                "available": balance.available.to_string(),
+                "transferable": transferable.to_string(),
+                "overall": balance.overall.to_string(),
+            }))
+        }
+        "resolvename" => {
+            let name = normalize_name(&string_param(params, 0, "name")?);
+            let data = state
+                .db
+                .get_name(&name)
+                .map_err(|e| RpcError::internal("rpc name error", e))?
+                .and_then(|raw| serde_json::from_str::<serde_json::Value>(&raw).ok());
+            match data.as_ref().and_then(|d| d["owner"].as_str()) {
+                Some(owner) => Ok(serde_json::json!({
+                    "name": data.as_ref().and_then(|d| d["name"].as_str()).unwrap_or(&name),
+                    "address": owner,
+                })),
+                None => Err(RpcError::new(NOT_FOUND, format!("Name {} not found", name))),
+            }
+        }
+        other => Err(RpcError::new(METHOD_NOT_FOUND, format!("Method {} not found", other))),
+    }
+}
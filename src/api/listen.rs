@@ -0,0 +1,260 @@
+//! Addresses the API is served on.
+//!
+//! `API_LISTEN` takes a comma-separated list of `host:port` and `unix:<path>`
+//! entries, so one process can answer on IPv4 and IPv6, or only on a socket file
+//! behind a reverse proxy. `ADMIN_LISTEN` moves `/api/v1/admin/*` onto listeners of
+//! its own (typically `127.0.0.1:<port>`), off the public ones. `GRPC_LISTEN`
+//! takes the same entries for the gRPC service. With TLS configured every TCP
+//! listener speaks HTTPS; Unix sockets stay plain HTTP.
+
+use super::tls::Tls;
+use axum::extract::ConnectInfo;
+use axum::Router;
+use hyper_util::rt::TokioIo;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::sync::mpsc;
+use tower::ServiceExt;
+
+// Connections that have not finished the TLS handshake by then are dropped
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub(super) enum Listen {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+}
+
+impl std::fmt::Display for Listen {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Listen::Tcp(addr) => write!(f, "{}", addr),
+            Listen::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+/// Listeners from env var `name`, or `None` when it is unset or blank.
+pub(super) fn from_env(name: &str) -> anyhow::Result<Option<Vec<Listen>>> {
+    let Ok(raw) = std::env::var(name) else {
+        return Ok(None);
+    };
+    let listeners = raw
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|entry| match entry.strip_prefix("unix:") {
+            Some("") => Err(anyhow::anyhow!("{}: unix: needs a socket path", name)),
+            Some(path) => Ok(Listen::Unix(PathBuf::from(path))),
+            None => entry
+                .parse()
+                .map(Listen::Tcp)
+                .map_err(|_| anyhow::anyhow!("{}: {} is not host:port or unix:<path>", name, entry)),
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    Ok((!listeners.is_empty()).then_some(listeners))
+}
+
+enum Bound {
+    Tcp(tokio::net::TcpListener),
+    Tls(tokio::net::TcpListener, Arc<Tls>),
+    Unix(tokio::net::UnixListener),
+}
+
+/// Bind every listener up front so a bad address fails startup, then serve `app`
+/// on all of them until one stops.
+pub(super) async fn serve(listeners: Vec<(Listen, Router)>, tls: Option<Arc<Tls>>) -> anyhow::Result<()> {
+    let mut servers = Vec::with_capacity(listeners.len());
+    for (listen, app) in listeners {
+        let bound = match (&listen, &tls) {
+            (Listen::Tcp(addr), None) => Bound::Tcp(tokio::net::TcpListener::bind(addr).await?),
+            (Listen::Tcp(addr), Some(tls)) => Bound::Tls(tokio::net::TcpListener::bind(addr).await?, tls.clone()),
+            (Listen::Unix(path), _) => Bound::Unix(bind_unix(path)?),
+        };
+        let scheme = if matches!(bound, Bound::Tls(..)) { "https" } else { "http" };
+        tracing::info!("API listening on {} ({})", listen, scheme);
+        servers.push(tokio::spawn(async move {
+            match bound {
+                Bound::Tcp(listener) => axum::serve(
+                    listener,
+                    app.into_make_service_with_connect_info::<SocketAddr>(),
+                )
+                .await
+                .map_err(anyhow::Error::from),
+                Bound::Tls(listener, tls) => serve_tls(listener, app, tls).await,
+                Bound::Unix(listener) => serve_unix(listener, app).await,
+            }
+        }));
+    }
+    let (result, _, _) = futures::future::select_all(servers).await;
+    result?
+}
+
+trait Io: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<S: AsyncRead + AsyncWrite + Unpin + Send> Io for S {}
+
+/// An accepted connection, TLS already negotiated, for servers that take a stream
+/// of connections instead of a `Router`.
+pub(super) struct Conn(Box<dyn Io>);
+
+impl tonic::transport::server::Connected for Conn {
+    type ConnectInfo = ();
+
+    fn connect_info(&self) -> Self::ConnectInfo {}
+}
+
+impl AsyncRead for Conn {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().0).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for Conn {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.get_mut().0).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().0).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().0).poll_shutdown(cx)
+    }
+}
+
+/// Bind `listen` and accept on it in the background, handing connections over as a
+/// stream. TCP connections go through `acceptor` when TLS is configured.
+pub(super) async fn incoming(
+    listen: &Listen,
+    acceptor: Option<tokio_rustls::TlsAcceptor>,
+) -> anyhow::Result<impl futures::Stream<Item = Result<Conn, std::io::Error>>> {
+    let (tx, rx) = mpsc::channel::<Conn>(64);
+    match listen {
+        Listen::Tcp(addr) => {
+            let listener = tokio::net::TcpListener::bind(addr).await?;
+            tokio::spawn(async move {
+                loop {
+                    let (stream, peer) = match listener.accept().await {
+                        Ok(accepted) => accepted,
+                        Err(e) => {
+                            accept_failed(e).await;
+                            continue;
+                        }
+                    };
+                    let tx = tx.clone();
+                    let Some(acceptor) = acceptor.clone() else {
+                        if tx.send(Conn(Box::new(stream))).await.is_err() {
+                            return;
+                        }
+                        continue;
+                    };
+                    tokio::spawn(async move {
+                        match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                            Ok(Ok(stream)) => {
+                                let _ = tx.send(Conn(Box::new(stream))).await;
+                            }
+                            Ok(Err(e)) => tracing::debug!("TLS handshake with {} failed: {}", peer, e),
+                            Err(_) => tracing::debug!("TLS handshake with {} timed out", peer),
+                        }
+                    });
+                }
+            });
+        }
+        Listen::Unix(path) => {
+            let listener = bind_unix(path)?;
+            tokio::spawn(async move {
+                loop {
+                    match listener.accept().await {
+                        Ok((stream, _)) => {
+                            if tx.send(Conn(Box::new(stream))).await.is_err() {
+                                return;
+                            }
+                        }
+                        Err(e) => accept_failed(e).await,
+                    }
+                }
+            });
+        }
+    }
+    Ok(futures::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|conn| (Ok(conn), rx))
+    }))
+}
+
+// A socket file left behind by an earlier run is replaced; any other file is not
+fn bind_unix(path: &std::path::Path) -> anyhow::Result<tokio::net::UnixListener> {
+    use std::os::unix::fs::FileTypeExt;
+    if let Ok(meta) = std::fs::symlink_metadata(path) {
+        if !meta.file_type().is_socket() {
+            return Err(anyhow::anyhow!("{} exists and is not a socket", path.display()));
+        }
+        std::fs::remove_file(path)?;
+    }
+    Ok(tokio::net::UnixListener::bind(path)?)
+}
+
+async fn serve_tls(listener: tokio::net::TcpListener, app: Router, tls: Arc<Tls>) -> anyhow::Result<()> {
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                accept_failed(e).await;
+                continue;
+            }
+        };
+        let acceptor = tls.acceptor();
+        let app = app.clone();
+        tokio::spawn(async move {
+            match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                Ok(Ok(stream)) => serve_connection(stream, app, Some(peer)).await,
+                Ok(Err(e)) => tracing::debug!("TLS handshake with {} failed: {}", peer, e),
+                Err(_) => tracing::debug!("TLS handshake with {} timed out", peer),
+            }
+        });
+    }
+}
+
+async fn serve_unix(listener: tokio::net::UnixListener, app: Router) -> anyhow::Result<()> {
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(e) => {
+                accept_failed(e).await;
+                continue;
+            }
+        };
+        tokio::spawn(serve_connection(stream, app.clone(), None));
+    }
+}
+
+// Usually descriptor exhaustion; back off instead of spinning, as axum::serve does
+async fn accept_failed(e: std::io::Error) {
+    tracing::error!("accept failed: {}", e);
+    tokio::time::sleep(Duration::from_secs(1)).await;
+}
+
+// One HTTP/1.1 connection; the peer address is exposed to handlers as axum's ConnectInfo
+async fn serve_connection<S>(stream: S, app: Router, peer: Option<SocketAddr>)
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let service = hyper::service::service_fn(move |mut req: axum::http::Request<hyper::body::Incoming>| {
+        if let Some(peer) = peer {
+            req.extensions_mut().insert(ConnectInfo(peer));
+        }
+        app.clone().oneshot(req)
+    });
+    let connection = hyper::server::conn::http1::Builder::new()
+        .serve_connection(TokioIo::new(stream), service)
+        .with_upgrades()
+        .await;
+    if let Err(e) = connection {
+        tracing::debug!("connection ended: {}", e);
+    }
+}
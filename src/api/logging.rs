@@ -0,0 +1,89 @@
+//! Request IDs and access logs.
+//!
+//! Every request gets an `x-request-id`: the caller's own when it sends a usable
+//! one (so IDs from a proxy or client carry through), otherwise a fresh one. It is
+//! echoed on the response and attached to the request's tracing span, so error
+//! logs written while handling the request can be matched to its access log line.
+
+use axum::{
+    extract::{ConnectInfo, MatchedPath, Request},
+    http::{HeaderName, HeaderValue, Response},
+    middleware::Next,
+};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::Span;
+
+pub(super) static REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+// Longest caller-supplied ID that is kept instead of replaced
+const MAX_ID_LEN: usize = 128;
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+// Distinguishes IDs across restarts; the counter orders them within one process
+fn boot_prefix() -> &'static str {
+    static PREFIX: OnceLock<String> = OnceLock::new();
+    PREFIX.get_or_init(|| {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+        format!("{:x}", nanos as u64)
+    })
+}
+
+fn usable(id: &HeaderValue) -> bool {
+    let bytes = id.as_bytes();
+    !bytes.is_empty() && bytes.len() <= MAX_ID_LEN && bytes.iter().all(|b| b.is_ascii_graphic())
+}
+
+pub(super) async fn assign_request_id(mut req: Request, next: Next) -> axum::response::Response {
+    let id = match req.headers().get(&REQUEST_ID) {
+        Some(id) if usable(id) => id.clone(),
+        _ => {
+            let seq = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+            let id = HeaderValue::from_str(&format!("{}-{:x}", boot_prefix(), seq)).expect("request id is ASCII");
+            req.headers_mut().insert(REQUEST_ID.clone(), id.clone());
+            id
+        }
+    };
+    let mut response = next.run(req).await;
+    response.headers_mut().insert(REQUEST_ID.clone(), id);
+    response
+}
+
+/// Span for one request: its ID, method, matched route and client address.
+pub(super) fn request_span<B>(req: &axum::http::Request<B>) -> Span {
+    let id = req.headers().get(&REQUEST_ID).and_then(|v| v.to_str().ok()).unwrap_or("-");
+    // The route template keeps ids and addresses out of the field, so logs group by endpoint
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str())
+        .unwrap_or_else(|| req.uri().path());
+    let client = req
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip().to_string())
+        .unwrap_or_default();
+    // Reported as sent; only meaningful behind a proxy that sets it
+    let forwarded_for = req.headers().get("x-forwarded-for").and_then(|v| v.to_str().ok()).unwrap_or("");
+    tracing::info_span!(
+        "request",
+        id,
+        method = %req.method(),
+        route,
+        client = %client,
+        forwarded_for,
+    )
+}
+
+pub(super) fn log_response<B>(response: &Response<B>, latency: Duration, _span: &Span) {
+    let status = response.status().as_u16();
+    let latency_us = latency.as_micros() as u64;
+    if response.status().is_server_error() {
+        tracing::warn!(status, latency_us, "request failed");
+    } else {
+        tracing::info!(status, latency_us, "request served");
+    }
+}
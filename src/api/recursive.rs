@@ -0,0 +1,229 @@
+//! ord's recursion endpoints (`/r/*`), so HTML and JS inscriptions written for ord
+//! can fetch chain state when served from `/content/:id`.
+//!
+//! Responses use ord's shapes. Zcash inscriptions carry no parent or metadata
+//! tags and zats are not tracked individually, so children, parents and sats
+//! always come back as empty lists and metadata as 404; scripts written for ord
+//! already handle those cases.
+
+use super::*;
+
+// Total zats that will ever exist: 21M ZEC at 10^8 zats each
+const MAX_ZAT: u64 = 2_100_000_000_000_000;
+
+pub(super) fn router() -> ApiRouter<AppState> {
+    ApiRouter::new()
+        .routes(routes!(get_block_height))
+        .routes(routes!(get_latest_block_hash))
+        .routes(routes!(get_block_hash))
+        .routes(routes!(get_block_time))
+        .routes(routes!(get_children))
+        .routes(routes!(get_children_page))
+        .routes(routes!(get_parents))
+        .routes(routes!(get_parents_page))
+        .routes(routes!(get_metadata))
+        .routes(routes!(get_sat))
+        .routes(routes!(get_sat_page))
+        .routes(routes!(get_sat_at))
+}
+
+fn latest_height(state: &AppState) -> ApiResult<u64> {
+    state
+        .db
+        .get_latest_indexed_height()
+        .map_err(|e| ApiError::internal("recursive height error", e))?
+        .ok_or_else(|| ApiError::not_found("No blocks indexed yet"))
+}
+
+/// Latest indexed block height
+#[utoipa::path(get, path = "/r/blockheight", tag = "recursion", responses((status = 200, body = u64)))]
+async fn get_block_height(State(state): State<AppState>) -> ApiResult<Json<u64>> {
+    latest_height(&state).map(Json)
+}
+
+/// Hash of the latest indexed block
+#[utoipa::path(get, path = "/r/blockhash", tag = "recursion", responses((status = 200, body = String)))]
+async fn get_latest_block_hash(State(state): State<AppState>) -> ApiResult<Json<String>> {
+    let height = latest_height(&state)?;
+    block_hash(&state, height).map(Json)
+}
+
+/// Hash of an indexed block
+#[utoipa::path(
+    get,
+    path = "/r/blockhash/{height}",
+    tag = "recursion",
+    params(("height" = u64, Path, description = "Block height")),
+    responses((status = 200, body = String))
+)]
+async fn get_block_hash(State(state): State<AppState>, Path(height): Path<u64>) -> ApiResult<Json<String>> {
+    block_hash(&state, height).map(Json)
+}
+
+fn block_hash(state: &AppState, height: u64) -> ApiResult<String> {
+    state
+        .db
+        .get_block_hash(height)
+        .map_err(|e| ApiError::internal("recursive block hash error", e))?
+        .ok_or_else(|| ApiError::not_found(format!("Block {} not indexed", height)))
+}
+
+/// Header time of the latest indexed block
+///
+/// Asks the node when the index predates `tip_time` or was just rolled back.
+#[utoipa::path(get, path = "/r/blocktime", tag = "recursion", responses((status = 200, description = "Unix seconds", body = u64)))]
+async fn get_block_time(State(state): State<AppState>) -> ApiResult<Json<u64>> {
+    let stored = state
+        .db
+        .get_status("tip_time")
+        .map_err(|e| ApiError::internal("recursive block time error", e))?;
+    if let Some(time) = stored {
+        return Ok(Json(time));
+    }
+    let rpc = state.rpc.as_ref().ok_or_else(rpc_unavailable)?;
+    let hash = block_hash(&state, latest_height(&state)?)?;
+    let block = rpc.get_block(&hash).await.map_err(|e| rpc_lookup_error(e, "Block"))?;
+    Ok(Json(block.time))
+}
+
+/// ord's page of inscription ids
+#[derive(Serialize, ToSchema)]
+struct IdPage {
+    ids: Vec<String>,
+    more: bool,
+    page: usize,
+}
+
+fn id_page(page: usize) -> Json<IdPage> {
+    Json(IdPage { ids: Vec::new(), more: false, page })
+}
+
+fn require_inscription(state: &AppState, id: &str) -> ApiResult<String> {
+    state
+        .db
+        .get_inscription(id)
+        .map_err(|e| ApiError::internal("recursive inscription error", e))?
+        .ok_or_else(|| ApiError::not_found(format!("Inscription {} not found", id)))
+}
+
+/// Child inscription ids (always empty on Zcash)
+#[utoipa::path(
+    get,
+    path = "/r/children/{id}",
+    tag = "recursion",
+    params(("id" = String, Path, description = "Inscription id")),
+    responses((status = 200, body = IdPage))
+)]
+async fn get_children(State(state): State<AppState>, Path(id): Path<String>) -> ApiResult<Json<IdPage>> {
+    require_inscription(&state, &id)?;
+    Ok(id_page(0))
+}
+
+/// Page of child inscription ids
+#[utoipa::path(
+    get,
+    path = "/r/children/{id}/{page}",
+    tag = "recursion",
+    params(("id" = String, Path, description = "Inscription id"), ("page" = usize, Path, description = "Zero-based page")),
+    responses((status = 200, body = IdPage))
+)]
+async fn get_children_page(
+    State(state): State<AppState>,
+    Path((id, page)): Path<(String, usize)>,
+) -> ApiResult<Json<IdPage>> {
+    require_inscription(&state, &id)?;
+    Ok(id_page(page))
+}
+
+/// Parent inscription ids (always empty on Zcash)
+#[utoipa::path(
+    get,
+    path = "/r/parents/{id}",
+    tag = "recursion",
+    params(("id" = String, Path, description = "Inscription id")),
+    responses((status = 200, body = IdPage))
+)]
+async fn get_parents(State(state): State<AppState>, Path(id): Path<String>) -> ApiResult<Json<IdPage>> {
+    require_inscription(&state, &id)?;
+    Ok(id_page(0))
+}
+
+/// Page of parent inscription ids
+#[utoipa::path(
+    get,
+    path = "/r/parents/{id}/{page}",
+    tag = "recursion",
+    params(("id" = String, Path, description = "Inscription id"), ("page" = usize, Path, description = "Zero-based page")),
+    responses((status = 200, body = IdPage))
+)]
+async fn get_parents_page(
+    State(state): State<AppState>,
+    Path((id, page)): Path<(String, usize)>,
+) -> ApiResult<Json<IdPage>> {
+    require_inscription(&state, &id)?;
+    Ok(id_page(page))
+}
+
+/// Hex CBOR metadata (404: not carried by Zcash envelopes)
+#[utoipa::path(
+    get,
+    path = "/r/metadata/{id}",
+    tag = "recursion",
+    params(("id" = String, Path, description = "Inscription id")),
+    responses((status = 200, body = String))
+)]
+async fn get_metadata(State(state): State<AppState>, Path(id): Path<String>) -> ApiResult<Json<String>> {
+    require_inscription(&state, &id)?;
+    Err(ApiError::not_found(format!("Inscription {} has no metadata", id)))
+}
+
+fn check_zat(number: u64) -> ApiResult<()> {
+    if number >= MAX_ZAT {
+        return Err(ApiError::bad_request(format!("zat {} is beyond the supply", number)));
+    }
+    Ok(())
+}
+
+/// Inscriptions on a zat (not tracked; always empty)
+#[utoipa::path(
+    get,
+    path = "/r/sat/{number}",
+    tag = "recursion",
+    params(("number" = u64, Path, description = "Zat number")),
+    responses((status = 200, body = IdPage))
+)]
+async fn get_sat(Path(number): Path<u64>) -> ApiResult<Json<IdPage>> {
+    check_zat(number)?;
+    Ok(id_page(0))
+}
+
+/// Page of inscriptions on a zat
+#[utoipa::path(
+    get,
+    path = "/r/sat/{number}/{page}",
+    tag = "recursion",
+    params(("number" = u64, Path, description = "Zat number"), ("page" = usize, Path, description = "Zero-based page")),
+    responses((status = 200, body = IdPage))
+)]
+async fn get_sat_page(Path((number, page)): Path<(u64, usize)>) -> ApiResult<Json<IdPage>> {
+    check_zat(number)?;
+    Ok(id_page(page))
+}
+
+#[derive(Serialize, ToSchema)]
+struct SatInscription {
+    id: Option<String>,
+}
+
+/// Inscription at an index on a zat (always null)
+#[utoipa::path(
+    get,
+    path = "/r/sat/{number}/at/{index}",
+    tag = "recursion",
+    params(("number" = u64, Path, description = "Zat number"), ("index" = i64, Path, description = "Index; negative counts from the end")),
+    responses((status = 200, body = SatInscription))
+)]
+async fn get_sat_at(Path((number, _index)): Path<(u64, i64)>) -> ApiResult<Json<SatInscription>> {
+    check_zat(number)?;
+    Ok(Json(SatInscription { id: None }))
+}
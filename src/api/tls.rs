@@ -0,0 +1,113 @@
+//! HTTPS for the TCP listeners when `TLS_CERT` and `TLS_KEY` are set.
+//!
+//! Both are PEM files: the certificate chain (leaf first) and its private key in
+//! PKCS#8, PKCS#1 (`BEGIN RSA PRIVATE KEY`) or SEC1 (`BEGIN EC PRIVATE KEY`) form. The files are checked every `TLS_RELOAD_SECS` and swapped in when
+//! either changes, so a renewed certificate takes effect without a restart; a
+//! pair that fails to load is logged and the previous one kept.
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+use tokio_rustls::rustls::{self, crypto::ring};
+use tokio_rustls::TlsAcceptor;
+
+const DEFAULT_RELOAD_SECS: u64 = 60;
+
+pub(super) struct Tls {
+    cert: PathBuf,
+    key: PathBuf,
+    acceptors: RwLock<Acceptors>,
+}
+
+// One certificate, offered with the ALPN protocols of each kind of listener
+#[derive(Clone)]
+struct Acceptors {
+    http: TlsAcceptor,
+    grpc: TlsAcceptor,
+}
+
+impl Tls {
+    pub(super) fn from_env() -> anyhow::Result<Option<Arc<Tls>>> {
+        let cert = std::env::var("TLS_CERT").ok().filter(|v| !v.is_empty());
+        let key = std::env::var("TLS_KEY").ok().filter(|v| !v.is_empty());
+        let (cert, key) = match (cert, key) {
+            (Some(cert), Some(key)) => (PathBuf::from(cert), PathBuf::from(key)),
+            (None, None) => return Ok(None),
+            _ => return Err(anyhow::anyhow!("TLS_CERT and TLS_KEY must be set together")),
+        };
+        let acceptors = load(&cert, &key)?;
+        let tls = Arc::new(Tls { cert, key, acceptors: RwLock::new(acceptors) });
+
+        let reload_secs = std::env::var("TLS_RELOAD_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_RELOAD_SECS);
+        if reload_secs > 0 {
+            tokio::spawn(watch(tls.clone(), Duration::from_secs(reload_secs)));
+        }
+        Ok(Some(tls))
+    }
+
+    /// Acceptor for the certificate currently loaded.
+    pub(super) fn acceptor(&self) -> TlsAcceptor {
+        self.acceptors.read().unwrap_or_else(|e| e.into_inner()).http.clone()
+    }
+
+    /// Acceptor for the gRPC listeners, which negotiate HTTP/2.
+    pub(super) fn grpc_acceptor(&self) -> TlsAcceptor {
+        self.acceptors.read().unwrap_or_else(|e| e.into_inner()).grpc.clone()
+    }
+}
+
+fn load(cert: &Path, key: &Path) -> anyhow::Result<Acceptors> {
+    let cert_pem = std::fs::read(cert).map_err(|e| anyhow::anyhow!("reading {}: {}", cert.display(), e))?;
+    let key_pem = std::fs::read(key).map_err(|e| anyhow::anyhow!("reading {}: {}", key.display(), e))?;
+    let chain = rustls_pemfile::certs(&mut cert_pem.as_slice()).collect::<Result<Vec<_>, _>>()?;
+    if chain.is_empty() {
+        return Err(anyhow::anyhow!("no certificates in {}", cert.display()));
+    }
+    let private_key = rustls_pemfile::private_key(&mut key_pem.as_slice())?
+        .ok_or_else(|| anyhow::anyhow!("no private key in {}", key.display()))?;
+    // TLS 1.2 and 1.3 only; the API listeners speak HTTP/1.1, gRPC HTTP/2 and
+    // gRPC-Web over HTTP/1.1
+    let mut http = rustls::ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()?
+        .with_no_client_auth()
+        .with_single_cert(chain, private_key)?;
+    let mut grpc = http.clone();
+    http.alpn_protocols = vec![b"http/1.1".to_vec()];
+    grpc.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(Acceptors {
+        http: TlsAcceptor::from(Arc::new(http)),
+        grpc: TlsAcceptor::from(Arc::new(grpc)),
+    })
+}
+
+fn modified(cert: &Path, key: &Path) -> Option<(SystemTime, SystemTime)> {
+    let time = |path: &Path| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    Some((time(cert)?, time(key)?))
+}
+
+// Reload when either file's modification time moves
+async fn watch(tls: Arc<Tls>, every: Duration) {
+    let mut seen = modified(&tls.cert, &tls.key);
+    let mut ticker = tokio::time::interval(every);
+    ticker.tick().await;
+    loop {
+        ticker.tick().await;
+        let current = modified(&tls.cert, &tls.key);
+        if current.is_none() || current == seen {
+            continue;
+        }
+        // Renewal tools may write the two files a moment apart; a mismatched pair
+        // fails to load and is retried on the next tick
+        match load(&tls.cert, &tls.key) {
+            Ok(acceptors) => {
+                *tls.acceptors.write().unwrap_or_else(|e| e.into_inner()) = acceptors;
+                seen = current;
+                tracing::info!("Reloaded TLS certificate from {}", tls.cert.display());
+            }
+            Err(e) => tracing::warn!("TLS certificate reload failed, keeping the previous one: {}", e),
+        }
+    }
+}
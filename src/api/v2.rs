@@ -0,0 +1,306 @@
+//! `/api/v2`: the surface where breaking response changes land.
+//!
+//! `/api/v1` and the ord-compatible routes keep their shapes. Everything here is
+//! wrapped in an [`Envelope`] and follows three rules v1 could not adopt without
+//! breaking clients:
+//!
+//! - Lists are keyset-paginated only: `?limit=&cursor=`, with `next_cursor` null on
+//!   the last page. There are no offsets or totals to drift under concurrent writes.
+//! - Token amounts are base-unit strings and everything else countable is a JSON
+//!   number (v1 mixes `"dec": "18"` with human-formatted supplies).
+//! - Errors are problem+json with the HTTP status, never a 200 body.
+
+use super::*;
+
+/// Version reported in every v2 envelope.
+pub const API_VERSION: u32 = 2;
+
+const DEFAULT_LIMIT: usize = 50;
+const MAX_LIMIT: usize = 500;
+
+/// Response wrapper for every v2 endpoint.
+#[derive(Serialize, ToSchema)]
+pub struct Envelope<T> {
+    pub api_version: u32,
+    pub data: T,
+    /// Set on list endpoints; pass back as `cursor` for the next page
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>)]
+    pub next_cursor: Option<Option<String>>,
+}
+
+impl<T> Envelope<T> {
+    fn item(data: T) -> Json<Self> {
+        Json(Envelope { api_version: API_VERSION, data, next_cursor: None })
+    }
+
+    fn list(data: T, next_cursor: Option<String>) -> Json<Self> {
+        Json(Envelope { api_version: API_VERSION, data, next_cursor: Some(next_cursor) })
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ListParams {
+    /// Page size, 1-500 (default 50)
+    limit: Option<usize>,
+    /// `next_cursor` of the previous page
+    cursor: Option<String>,
+}
+
+impl ListParams {
+    fn limit(&self) -> usize {
+        self.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT)
+    }
+
+    fn cursor(&self) -> Option<Cursor<'_>> {
+        self.cursor.as_deref().map(Cursor::After)
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+struct Token {
+    tick: String,
+    dec: u32,
+    max: String,
+    lim: String,
+    supply: String,
+    deployer: String,
+    inscription_id: String,
+}
+
+#[derive(Serialize, ToSchema)]
+struct Name {
+    name: String,
+    owner: String,
+    inscription_id: String,
+    /// Earlier name this one is a look-alike of
+    #[serde(skip_serializing_if = "Option::is_none")]
+    confusable_with: Option<String>,
+    /// Label shape: grapheme count and ASCII, digit, emoji and mixed-script flags
+    #[serde(skip_serializing_if = "Option::is_none")]
+    attributes: Option<serde_json::Value>,
+}
+
+pub(super) fn router() -> ApiRouter<AppState> {
+    ApiRouter::new()
+        .routes(routes!(get_status))
+        .routes(routes!(list_inscriptions))
+        .routes(routes!(get_inscription))
+        .routes(routes!(list_tokens))
+        .routes(routes!(get_token))
+        .routes(routes!(list_names))
+        .routes(routes!(get_name))
+}
+
+/// Heights each protocol has been indexed to
+#[derive(Serialize, ToSchema)]
+struct Components {
+    zrc20: Option<u64>,
+    zrc721: Option<u64>,
+    names: Option<u64>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct Status {
+    height: Option<u64>,
+    chain_tip: Option<u64>,
+    inscriptions: u64,
+    tokens: u64,
+    names: u64,
+    components: Components,
+    version: &'static str,
+}
+
+/// Index height, chain tip, counts and component heights
+#[utoipa::path(get, path = "/api/v2/status", tag = "v2", responses((status = 200, body = Envelope<Status>)))]
+async fn get_status(State(state): State<AppState>) -> ApiResult<Json<Envelope<Status>>> {
+    let fail = |e: anyhow::Error| ApiError::internal("v2 status error", e);
+    let height = state.db.get_latest_indexed_height().map_err(fail)?;
+    let chain_tip = state.db.get_status("chain_tip").map_err(fail)?;
+    Ok(Envelope::item(Status {
+        height,
+        chain_tip,
+        inscriptions: state.db.get_inscription_count().map_err(fail)?,
+        tokens: state.db.get_token_count().map_err(fail)?,
+        names: state.db.get_name_count().map_err(fail)?,
+        components: Components {
+            zrc20: state.db.get_status("zrc20_height").map_err(fail)?,
+            zrc721: state.db.get_status("zrc721_height").map_err(fail)?,
+            names: state.db.get_status("names_height").map_err(fail)?,
+        },
+        version: env!("CARGO_PKG_VERSION"),
+    }))
+}
+
+// Stored metadata plus its id; numeric fields are already numbers in the store
+pub(super) fn inscription(id: String, raw: &str) -> serde_json::Value {
+    let mut meta: serde_json::Value = serde_json::from_str(raw).unwrap_or_default();
+    if let Some(obj) = meta.as_object_mut() {
+        obj.insert("id".to_string(), serde_json::Value::String(id));
+    }
+    meta
+}
+
+/// Stored inscription metadata (`content_type`, `content_length`, `txid`, `vout`,
+/// `sender`, `receiver`, `block_height`, `block_time`) with its `id`
+#[derive(Serialize, ToSchema)]
+#[serde(transparent)]
+#[schema(value_type = Object)]
+struct Inscription(serde_json::Value);
+
+/// Newest-first inscriptions
+#[utoipa::path(
+    get,
+    path = "/api/v2/inscriptions",
+    tag = "v2",
+    params(ListParams),
+    responses((status = 200, body = Envelope<Vec<Inscription>>))
+)]
+async fn list_inscriptions(
+    State(state): State<AppState>,
+    Query(params): Query<ListParams>,
+) -> ApiResult<Json<Envelope<Vec<Inscription>>>> {
+    blocking(&state, move |state| {
+        let limit = params.limit();
+        let rows = state
+            .db
+            .get_inscriptions_page(0, limit, params.cursor())
+            .map_err(|e| ApiError::internal("v2 inscriptions error", e))?;
+        let next = next_cursor(&rows, limit);
+        Ok(Envelope::list(rows.into_iter().map(|(id, raw)| Inscription(inscription(id, &raw))).collect(), next))
+    })
+    .await
+}
+
+/// Inscription metadata
+#[utoipa::path(
+    get,
+    path = "/api/v2/inscriptions/{id}",
+    tag = "v2",
+    params(("id" = String, Path, description = "Inscription id")),
+    responses((status = 200, body = Envelope<Inscription>))
+)]
+async fn get_inscription(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> ApiResult<Json<Envelope<Inscription>>> {
+    let raw = state
+        .db
+        .get_inscription(&id)
+        .map_err(|e| ApiError::internal("v2 inscription error", e))?
+        .ok_or_else(|| ApiError::not_found(format!("Inscription {} not found", id)))?;
+    Ok(Envelope::item(Inscription(inscription(id, &raw))))
+}
+
+// Deploy parameters are stored as inscribed (human units); supply is already base units
+fn token(tick: String, raw: &str) -> Option<Token> {
+    let info: serde_json::Value = serde_json::from_str(raw).ok()?;
+    let dec = info["dec"].as_str().and_then(|d| d.parse().ok()).unwrap_or(18);
+    let base = |field: &str| {
+        let human = info[field].as_str().unwrap_or("0");
+        Amount::parse(human, dec).map(|v| v.to_string()).unwrap_or_else(|_| "0".to_string())
+    };
+    let max = base("max");
+    let lim = if info["lim"].is_string() { base("lim") } else { max.clone() };
+    Some(Token {
+        tick,
+        dec,
+        max,
+        lim,
+        supply: info["supply"].as_str().unwrap_or("0").to_string(),
+        deployer: info["deployer"].as_str().unwrap_or("").to_string(),
+        inscription_id: info["inscription_id"].as_str().unwrap_or("").to_string(),
+    })
+}
+
+/// ZRC-20 tokens with base-unit amounts
+#[utoipa::path(get, path = "/api/v2/zrc20/tokens", tag = "v2", params(ListParams), responses((status = 200, body = Envelope<Vec<Token>>)))]
+async fn list_tokens(
+    State(state): State<AppState>,
+    Query(params): Query<ListParams>,
+) -> ApiResult<Json<Envelope<Vec<Token>>>> {
+    blocking(&state, move |state| {
+        let limit = params.limit();
+        let rows = state
+            .db
+            .get_tokens_page(0, limit, params.cursor())
+            .map_err(|e| ApiError::internal("v2 tokens error", e))?;
+        let next = next_cursor(&rows, limit);
+        Ok(Envelope::list(rows.into_iter().filter_map(|(tick, raw)| token(tick, &raw)).collect(), next))
+    })
+    .await
+}
+
+/// One ZRC-20 token with base-unit amounts
+#[utoipa::path(
+    get,
+    path = "/api/v2/zrc20/tokens/{tick}",
+    tag = "v2",
+    params(("tick" = String, Path, description = "Ticker")),
+    responses((status = 200, body = Envelope<Token>))
+)]
+async fn get_token(
+    State(state): State<AppState>,
+    Path(tick): Path<String>,
+) -> ApiResult<Json<Envelope<Token>>> {
+    let tick = tick.to_lowercase();
+    let raw = state
+        .db
+        .get_token_info(&tick)
+        .map_err(|e| ApiError::internal("v2 token error", e))?
+        .ok_or_else(|| ApiError::not_found(format!("Token {} not found", tick)))?;
+    let token = token(tick, &raw).ok_or_else(|| ApiError::internal("v2 token error", "unreadable token record"))?;
+    Ok(Envelope::item(token))
+}
+
+fn name(raw: &str) -> Option<Name> {
+    let data: serde_json::Value = serde_json::from_str(raw).ok()?;
+    Some(Name {
+        name: data["name"].as_str()?.to_string(),
+        owner: data["owner"].as_str().unwrap_or("").to_string(),
+        inscription_id: data["inscription_id"].as_str().unwrap_or("").to_string(),
+        confusable_with: data["confusable_with"].as_str().map(str::to_string),
+        attributes: data.get("attributes").cloned(),
+    })
+}
+
+/// Registered names
+#[utoipa::path(get, path = "/api/v2/names", tag = "v2", params(ListParams), responses((status = 200, body = Envelope<Vec<Name>>)))]
+async fn list_names(
+    State(state): State<AppState>,
+    Query(params): Query<ListParams>,
+) -> ApiResult<Json<Envelope<Vec<Name>>>> {
+    blocking(&state, move |state| {
+        let limit = params.limit();
+        let rows = state
+            .db
+            .get_names_page(0, limit, params.cursor())
+            .map_err(|e| ApiError::internal("v2 names error", e))?;
+        let next = next_cursor(&rows, limit);
+        Ok(Envelope::list(rows.iter().filter_map(|(_, raw)| name(raw)).collect(), next))
+    })
+    .await
+}
+
+/// One registered name
+#[utoipa::path(
+    get,
+    path = "/api/v2/names/{name}",
+    tag = "v2",
+    params(("name" = String, Path, description = "Name, e.g. `alice.zec`")),
+    responses((status = 200, body = Envelope<Name>))
+)]
+async fn get_name(
+    State(state): State<AppState>,
+    Path(lookup): Path<String>,
+) -> ApiResult<Json<Envelope<Name>>> {
+    let lower = normalize_name(&lookup);
+    state
+        .db
+        .get_name(&lower)
+        .map_err(|e| ApiError::internal("v2 name error", e))?
+        .and_then(|raw| name(&raw))
+        .map(Envelope::item)
+        .ok_or_else(|| ApiError::not_found(format!("Name {} not found", lower)))
+}
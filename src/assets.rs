@@ -0,0 +1,74 @@
+//! Frontend files from `web/`, compiled into the binary so a deployment needs
+//! nothing beside the executable.
+//!
+//! Setting `WEB_DIR` serves the files from that directory instead, read on every
+//! request, so pages and scripts can be edited without rebuilding.
+
+use std::borrow::Cow;
+use std::path::PathBuf;
+
+// Every file in `web/`, so adding a page needs no change here
+#[derive(rust_embed::Embed)]
+#[folder = "web/"]
+#[exclude = "Archive.zip"]
+struct Embedded;
+
+#[derive(Clone, Default)]
+pub struct Assets {
+    dir: Option<PathBuf>,
+}
+
+impl Assets {
+    pub fn from_env() -> Self {
+        let dir = std::env::var("WEB_DIR").ok().filter(|d| !d.is_empty()).map(PathBuf::from);
+        if let Some(dir) = &dir {
+            tracing::info!("Serving frontend from {}", dir.display());
+        }
+        Assets { dir }
+    }
+
+    /// True when files come from `WEB_DIR` and may change between requests.
+    pub fn is_live(&self) -> bool {
+        self.dir.is_some()
+    }
+
+    /// Contents of `name`, a file directly inside `web/`.
+    pub fn get(&self, name: &str) -> Option<Cow<'static, [u8]>> {
+        if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+            return None;
+        }
+        match &self.dir {
+            Some(dir) => std::fs::read(dir.join(name)).ok().map(Cow::Owned),
+            None => Embedded::get(name).map(|file| file.data),
+        }
+    }
+}
+
+pub fn content_type(name: &str) -> &'static str {
+    match name.rsplit_once('.').map(|(_, ext)| ext) {
+        Some("html") => "text/html; charset=utf-8",
+        Some("js") => "text/javascript; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        Some("json") => "application/json",
+        Some("txt") => "text/plain; charset=utf-8",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("ico") => "image/x-icon",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn embeds_every_file_in_web_but_the_archive() {
+        let assets = Assets::default();
+        for entry in std::fs::read_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/web")).unwrap() {
+            let name = entry.unwrap().file_name().into_string().unwrap();
+            assert_eq!(assets.get(&name).is_some(), name != "Archive.zip", "{}", name);
+        }
+        assert!(assets.get("../Cargo.toml").is_none());
+    }
+}
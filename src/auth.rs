@@ -0,0 +1,122 @@
+//! Static API keys with roles.
+//!
+//! `API_KEYS` holds comma-separated `role:key` pairs, e.g. `admin:s3cret,client:abc`.
+//! Admin routes always require an `admin` key, so they are closed until one is
+//! configured. With `API_PROTECT_EXPENSIVE` set, full-table endpoints also require
+//! a `client` (or `admin`) key. Everything else stays public.
+
+use anyhow::Result;
+use axum::http::HeaderMap;
+use sha2::{Digest, Sha256};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Role {
+    Client,
+    Admin,
+}
+
+pub struct ApiKeys {
+    // sha256 of each key, so lookups compare fixed-length digests
+    keys: Vec<([u8; 32], Role)>,
+    protect_expensive: bool,
+}
+
+impl ApiKeys {
+    pub fn from_env() -> Result<Self> {
+        let protect_expensive = std::env::var("API_PROTECT_EXPENSIVE")
+            .map(|v| matches!(v.as_str(), "1" | "true" | "TRUE" | "yes" | "YES"))
+            .unwrap_or(false);
+        let keys = Self::parse(&std::env::var("API_KEYS").unwrap_or_default(), protect_expensive)?;
+        if !keys.keys.iter().any(|(_, role)| *role == Role::Admin) {
+            tracing::warn!("No admin API key configured; admin routes are disabled");
+        }
+        Ok(keys)
+    }
+
+    /// Keys from an `API_KEYS` value.
+    fn parse(raw: &str, protect_expensive: bool) -> Result<Self> {
+        let mut keys = Vec::new();
+        for entry in raw.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (role, key) = entry
+                .split_once(':')
+                .ok_or_else(|| anyhow::anyhow!("API_KEYS entry must be role:key"))?;
+            let role = match role.trim() {
+                "admin" => Role::Admin,
+                "client" => Role::Client,
+                other => return Err(anyhow::anyhow!("Unknown API key role: {}", other)),
+            };
+            if key.is_empty() {
+                return Err(anyhow::anyhow!("Empty API key for role {:?}", role));
+            }
+            keys.push((digest(key), role));
+        }
+        Ok(Self { keys, protect_expensive })
+    }
+
+    /// Role granted by the request's `Authorization: Bearer` or `X-API-Key` header.
+    pub fn role_for(&self, headers: &HeaderMap) -> Option<Role> {
+        let presented = headers
+            .get(axum::http::header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .or_else(|| headers.get("x-api-key").and_then(|v| v.to_str().ok()))?;
+        let presented = digest(presented.trim());
+        self.keys
+            .iter()
+            .filter(|(key, _)| constant_time_eq(key, &presented))
+            .map(|(_, role)| *role)
+            .max()
+    }
+
+    pub fn protects_expensive(&self) -> bool {
+        self.protect_expensive
+    }
+}
+
+fn digest(key: &str) -> [u8; 32] {
+    Sha256::digest(key.as_bytes()).into()
+}
+
+fn constant_time_eq(a: &[u8; 32], b: &[u8; 32]) -> bool {
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(name: &str, value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(axum::http::HeaderName::from_bytes(name.as_bytes()).unwrap(), value.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn keys_grant_their_role_through_either_header() {
+        let keys = ApiKeys::parse("admin:s3cret, client:abc", false).unwrap();
+        assert_eq!(keys.role_for(&headers("authorization", "Bearer s3cret")), Some(Role::Admin));
+        assert_eq!(keys.role_for(&headers("x-api-key", "abc")), Some(Role::Client));
+        assert_eq!(keys.role_for(&headers("x-api-key", " abc ")), Some(Role::Client));
+        assert_eq!(keys.role_for(&headers("authorization", "Bearer wrong")), None);
+        // Only the Bearer scheme carries a key
+        assert_eq!(keys.role_for(&headers("authorization", "Basic s3cret")), None);
+        assert_eq!(keys.role_for(&HeaderMap::new()), None);
+        assert!(!keys.protects_expensive());
+    }
+
+    #[test]
+    fn a_key_listed_twice_gets_its_highest_role() {
+        let keys = ApiKeys::parse("client:shared,admin:shared", true).unwrap();
+        assert_eq!(keys.role_for(&headers("authorization", "Bearer shared")), Some(Role::Admin));
+        assert!(Role::Admin > Role::Client);
+        assert!(keys.protects_expensive());
+    }
+
+    #[test]
+    fn malformed_entries_are_rejected() {
+        assert!(ApiKeys::parse("", false).unwrap().keys.is_empty());
+        assert!(ApiKeys::parse("s3cret", false).is_err());
+        assert!(ApiKeys::parse("root:s3cret", false).is_err());
+        assert!(ApiKeys::parse("admin:", false).is_err());
+    }
+}
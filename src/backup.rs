@@ -0,0 +1,142 @@
+//! Periodic hot backups with retention.
+//!
+//! When `BACKUP_DIR` is set, a background task writes a snapshot of the live
+//! database every `BACKUP_INTERVAL` (same mechanism as the admin snapshot
+//! endpoint) and keeps only the newest `BACKUP_KEEP` files.
+
+use crate::db::Db;
+use anyhow::Result;
+use chrono::Utc;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+const BACKUP_PREFIX: &str = "zord-backup-";
+const BACKUP_SUFFIX: &str = ".redb";
+
+pub struct BackupConfig {
+    dir: PathBuf,
+    interval: Duration,
+    keep: usize,
+}
+
+impl BackupConfig {
+    /// Read `BACKUP_DIR`, `BACKUP_INTERVAL` (default `24h`) and `BACKUP_KEEP` (default 7).
+    /// Returns `None` when backups are not enabled.
+    pub fn from_env() -> Result<Option<Self>> {
+        let dir = match std::env::var("BACKUP_DIR") {
+            Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => return Ok(None),
+        };
+        let interval = match std::env::var("BACKUP_INTERVAL") {
+            Ok(raw) => parse_interval("BACKUP_INTERVAL", &raw)?,
+            Err(_) => Duration::from_secs(24 * 3600),
+        };
+        let keep = match std::env::var("BACKUP_KEEP") {
+            Ok(raw) => raw.parse::<usize>()?.max(1),
+            Err(_) => 7,
+        };
+        Ok(Some(Self { dir, interval, keep }))
+    }
+}
+
+/// Run the backup loop until the process exits. The first backup is due one interval
+/// after the newest file already in `dir`, so restarts do not trigger extra copies.
+pub fn spawn(db: Db, config: BackupConfig) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        tracing::info!(
+            "Backups enabled: every {:?} into {:?}, keeping {}",
+            config.interval,
+            config.dir,
+            config.keep
+        );
+        loop {
+            let wait = newest_backup_age(&config.dir)
+                .map(|age| config.interval.saturating_sub(age))
+                .unwrap_or_default();
+            tokio::time::sleep(wait).await;
+
+            let db = db.clone();
+            let dir = config.dir.clone();
+            let keep = config.keep;
+            let result = tokio::task::spawn_blocking(move || run_backup(&db, &dir, keep)).await;
+            match result {
+                Ok(Ok(path)) => tracing::info!("Backup written to {:?}", path),
+                Ok(Err(e)) => tracing::error!("Backup failed: {}", e),
+                Err(e) => tracing::error!("Backup task panicked: {}", e),
+            }
+            // Never spin if the clock or the directory misbehaves
+            if newest_backup_age(&config.dir).is_none_or(|age| age >= config.interval) {
+                tokio::time::sleep(config.interval).await;
+            }
+        }
+    })
+}
+
+fn run_backup(db: &Db, dir: &Path, keep: usize) -> Result<PathBuf> {
+    let file = format!(
+        "{}{}{}",
+        BACKUP_PREFIX,
+        Utc::now().format("%Y%m%dT%H%M%SZ"),
+        BACKUP_SUFFIX
+    );
+    let path = dir.join(file);
+    db.export_snapshot(&path)?;
+    prune(dir, keep)?;
+    Ok(path)
+}
+
+// Backup files in `dir`, oldest first (timestamps in the name sort chronologically)
+fn list_backups(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let is_backup = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|n| n.starts_with(BACKUP_PREFIX) && n.ends_with(BACKUP_SUFFIX));
+        if is_backup {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn prune(dir: &Path, keep: usize) -> Result<()> {
+    let files = list_backups(dir)?;
+    let excess = files.len().saturating_sub(keep);
+    for path in &files[..excess] {
+        std::fs::remove_file(path)?;
+        tracing::info!("Pruned old backup {:?}", path);
+    }
+    Ok(())
+}
+
+fn newest_backup_age(dir: &Path) -> Option<Duration> {
+    let newest = list_backups(dir).ok()?.pop()?;
+    let modified = std::fs::metadata(newest).ok()?.modified().ok()?;
+    SystemTime::now().duration_since(modified).ok()
+}
+
+/// "90s", "30m", "6h", "1d"; a bare number is hours. `name` is the variable it came from.
+pub fn parse_interval(name: &str, raw: &str) -> Result<Duration> {
+    let raw = raw.trim();
+    let (digits, unit) = match raw.find(|c: char| !c.is_ascii_digit()) {
+        Some(split) => raw.split_at(split),
+        None => (raw, "h"),
+    };
+    let value = digits
+        .parse::<u64>()
+        .map_err(|_| anyhow::anyhow!("Invalid {}: {}", name, raw))?;
+    let secs = match unit {
+        "s" => value,
+        "m" => value * 60,
+        "h" => value * 3600,
+        "d" => value * 86400,
+        _ => return Err(anyhow::anyhow!("Invalid {} unit: {}", name, raw)),
+    };
+    if secs == 0 {
+        return Err(anyhow::anyhow!("{} must be positive", name));
+    }
+    Ok(Duration::from_secs(secs))
+}
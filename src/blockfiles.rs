@@ -0,0 +1,549 @@
+//! Initial sync straight from a co-located zcashd's block files.
+//!
+//! When `ZCASH_BLOCKS_DIR` points at a node's `blocks/` directory, startup scans
+//! the `blk*.dat` files into a height index and the indexer reads blocks and
+//! their transactions from disk instead of over RPC, so catching up needs no
+//! node calls at all. Once the index reaches the last block on disk, the
+//! indexer follows the node over RPC and ZMQ as usual.
+//!
+//! zcashd's own block index (LevelDB under `blocks/index/`) is not read. Each
+//! record's header is hashed and its height taken from the coinbase (BIP 34);
+//! the best chain is the run of parent links ending at the block with the most
+//! accumulated work, the tip zcashd itself would pick. Files whose blocks all lie
+//! below the starting height are skipped from the height of the next file's first
+//! block, allowing for zcashd writing blocks somewhat out of order.
+//!
+//! A block is served from disk only if its transactions hash back to the header's
+//! Merkle root; any block that cannot be read or checked is fetched over RPC
+//! instead. The node may keep writing to the files while zord reads them; blocks
+//! written after the scan are simply picked up over RPC.
+
+pub mod script;
+pub mod tx;
+
+use crate::rpc::{BlockResponse, TxResponse};
+use anyhow::{anyhow, Result};
+use script::AddressPrefixes;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use tx::{display_hex, sha256d, Reader};
+
+const MAINNET_MAGIC: [u8; 4] = [0x24, 0xe9, 0x27, 0x64];
+const TESTNET_MAGIC: [u8; 4] = [0xfa, 0x1a, 0xf9, 0xbf];
+const REGTEST_MAGIC: [u8; 4] = [0xaa, 0xe8, 0x3f, 0x5f];
+
+// Enough of a record to hash the header (an Equihash 200,9 solution is 1344
+// bytes) and reach the coinbase height
+const SCAN_BYTES: u64 = 4096;
+// How far ahead of the others zcashd may write a block (twice its download window)
+const OUT_OF_ORDER: u64 = 2048;
+// Larger than any consensus-valid block
+const MAX_RECORD: u32 = 8 << 20;
+
+struct Location {
+    file: usize,
+    // Start of the block, after the magic and length
+    offset: u64,
+    len: u32,
+    hash: [u8; 32],
+}
+
+struct Scanned {
+    height: u64,
+    prev: [u8; 32],
+    // Proof of work of this block alone, from its nBits
+    work: u128,
+    location: Location,
+}
+
+/// A block read from disk, shaped like the RPC answers it replaces.
+pub struct LocalBlock {
+    pub hash: String,
+    pub block: BlockResponse,
+    pub txs: Vec<TxResponse>,
+}
+
+pub struct BlockFiles {
+    files: Vec<PathBuf>,
+    chain: BTreeMap<u64, Location>,
+    magic: [u8; 4],
+    prefixes: AddressPrefixes,
+}
+
+impl BlockFiles {
+    /// Scan `ZCASH_BLOCKS_DIR` for blocks from `from_height` up. Returns `None` when
+    /// local ingestion is not enabled.
+    pub fn from_env(from_height: u64) -> Result<Option<Self>> {
+        match std::env::var("ZCASH_BLOCKS_DIR") {
+            Ok(dir) if !dir.is_empty() => Self::open(Path::new(&dir), from_height).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    pub fn open(dir: &Path, from_height: u64) -> Result<Self> {
+        let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+            .map_err(|e| anyhow!("Cannot read ZCASH_BLOCKS_DIR {}: {}", dir.display(), e))?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|n| n.to_str())
+                    .is_some_and(|n| n.starts_with("blk") && n.ends_with(".dat"))
+            })
+            .collect();
+        // blk00000.dat, blk00001.dat, ...: zero-padded, so name order is write order
+        files.sort();
+        if files.is_empty() {
+            return Err(anyhow!("No blk*.dat files in ZCASH_BLOCKS_DIR {}", dir.display()));
+        }
+
+        let magic = record_magic(&files[0])?;
+        let prefixes = match magic {
+            MAINNET_MAGIC => script::MAINNET,
+            TESTNET_MAGIC | REGTEST_MAGIC => script::TESTNET,
+            other => return Err(anyhow!("{} is not a Zcash block file (magic {})", files[0].display(), hex::encode(other))),
+        };
+
+        let first_heights: Vec<Option<u64>> = files.iter().map(|path| first_height(path, magic)).collect();
+        let mut scanned = Vec::new();
+        for (index, path) in files.iter().enumerate() {
+            let next_first = first_heights.get(index + 1).copied().flatten();
+            if next_first.is_some_and(|next| next + OUT_OF_ORDER < from_height) {
+                continue;
+            }
+            scan_file(path, index, magic, from_height.saturating_sub(1), usize::MAX, &mut scanned)?;
+        }
+
+        let chain = best_chain(scanned);
+        match (chain.first_key_value(), chain.last_key_value()) {
+            (Some((low, _)), Some((high, _))) => tracing::info!(
+                "Block files in {}: heights {}..={} across {} files",
+                dir.display(),
+                low,
+                high,
+                files.len()
+            ),
+            _ => tracing::warn!("Block files in {} hold no blocks from height {}", dir.display(), from_height),
+        }
+        Ok(Self {
+            files,
+            chain,
+            magic,
+            prefixes,
+        })
+    }
+
+    /// The highest block on disk, if any lies above the starting height.
+    pub fn tip(&self) -> Option<u64> {
+        self.chain.last_key_value().map(|(height, _)| *height)
+    }
+
+    /// The block at `height` and its transactions, or `None` when it is not on disk
+    /// or does not check out, so the caller asks the node instead.
+    pub fn read_block(&self, height: u64) -> Option<LocalBlock> {
+        let location = self.chain.get(&height)?;
+        match self.load(height, location) {
+            Ok(block) => Some(block),
+            Err(e) => {
+                tracing::warn!("Block {} from {}: {} - fetching over RPC", height, self.files[location.file].display(), e);
+                None
+            }
+        }
+    }
+
+    fn load(&self, height: u64, location: &Location) -> Result<LocalBlock> {
+        let mut file = File::open(&self.files[location.file])?;
+        file.seek(SeekFrom::Start(location.offset - 8))?;
+        let mut data = vec![0u8; location.len as usize + 8];
+        file.read_exact(&mut data)?;
+        if data[..4] != self.magic || data[4..8] != location.len.to_le_bytes() {
+            return Err(anyhow!("record moved since the scan"));
+        }
+
+        let mut r = Reader::new(&data[8..]);
+        let header = Header::read(&mut r)?;
+        if header.hash != location.hash {
+            return Err(anyhow!("record moved since the scan"));
+        }
+        let count = r.compact_size()?;
+        let txs = (0..count)
+            .map(|_| tx::parse(&mut r, self.prefixes))
+            .collect::<Result<Vec<_>>>()?;
+        let txids: Vec<String> = txs.iter().map(|tx| tx.txid.clone()).collect();
+
+        // Guards the transaction decoding as much as the file: txids must rebuild the root
+        let merkleroot = display_hex(&header.merkle);
+        let (root, _) = crate::names::proof::merkle_branch(&txids, 0)?;
+        if root != merkleroot {
+            return Err(anyhow!("transactions do not match merkle root {}", merkleroot));
+        }
+
+        let hash = display_hex(&header.hash);
+        Ok(LocalBlock {
+            hash: hash.clone(),
+            block: BlockResponse {
+                height,
+                hash,
+                tx: txids,
+                time: u64::from(header.time),
+                previousblockhash: (header.prev != [0; 32]).then(|| display_hex(&header.prev)),
+                merkleroot: Some(merkleroot),
+            },
+            txs,
+        })
+    }
+}
+
+struct Header {
+    hash: [u8; 32],
+    prev: [u8; 32],
+    merkle: [u8; 32],
+    time: u32,
+    bits: u32,
+}
+
+impl Header {
+    fn read(r: &mut Reader<'_>) -> Result<Self> {
+        let start = r.pos();
+        r.skip(4)?; // nVersion
+        let prev = r.hash()?;
+        let merkle = r.hash()?;
+        r.skip(32)?; // hashBlockCommitments
+        let time = r.u32()?;
+        let bits = r.u32()?;
+        r.skip(32)?; // nNonce
+        let solution = r.compact_size()?;
+        r.skip(solution)?;
+        Ok(Self {
+            hash: sha256d(r.slice_from(start)),
+            prev,
+            merkle,
+            time,
+            bits,
+        })
+    }
+}
+
+// Expected hashes to find a block at compact target `bits`: 2^256 / (target + 1),
+// to within one. Zcash targets never fall below 2^128, so the quotient fits.
+fn block_work(bits: u32) -> u128 {
+    let mantissa = u128::from(bits & 0x007f_ffff);
+    let exponent = bits >> 24;
+    if mantissa == 0 || bits & 0x0080_0000 != 0 {
+        return 0;
+    }
+    // target = mantissa * 256^(exponent - 3)
+    let shift = 8 * i64::from(exponent) - 24;
+    match 256 - shift {
+        free if free >= 128 => u128::MAX,
+        free if free <= 0 => 0,
+        free => (1u128 << free) / mantissa,
+    }
+}
+
+// BIP 34 height from the start of a block's coinbase transaction
+fn coinbase_height(r: &mut Reader<'_>) -> Result<Option<u64>> {
+    r.compact_size()?; // transaction count
+    let header = r.u32()?;
+    if header & (1 << 31) != 0 {
+        r.skip(4)?; // nVersionGroupId
+        if header & !(1 << 31) >= 5 {
+            r.skip(12)?; // nConsensusBranchId, nLockTime, nExpiryHeight
+        }
+    }
+    r.compact_size()?; // input count
+    r.skip(36)?; // null prevout
+    let len = r.compact_size()?;
+    let script = r.take(len)?;
+    Ok(script::leading_number(script).and_then(|n| u64::try_from(n).ok()))
+}
+
+fn record_magic(path: &Path) -> Result<[u8; 4]> {
+    let mut magic = [0u8; 4];
+    File::open(path)?.read_exact(&mut magic)?;
+    Ok(magic)
+}
+
+fn first_height(path: &Path, magic: [u8; 4]) -> Option<u64> {
+    let mut first = Vec::new();
+    scan_file(path, 0, magic, 0, 1, &mut first).ok()?;
+    first.first().map(|s| s.height)
+}
+
+// Append the blocks at or above `min_height` among the first `limit` records of a file
+fn scan_file(
+    path: &Path,
+    index: usize,
+    magic: [u8; 4],
+    min_height: u64,
+    limit: usize,
+    out: &mut Vec<Scanned>,
+) -> Result<()> {
+    let mut file = File::open(path)?;
+    let size = file.metadata()?.len();
+    let mut offset = 0u64;
+    let mut prefix = [0u8; 8];
+    let mut buf = Vec::with_capacity(SCAN_BYTES as usize);
+    for _ in 0..limit {
+        if offset + 8 > size {
+            break;
+        }
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut prefix)?;
+        // zcashd preallocates files with zeros; the first gap ends the data
+        if prefix[..4] != magic {
+            break;
+        }
+        let len = u32::from_le_bytes(prefix[4..].try_into()?);
+        let start = offset + 8;
+        if len > MAX_RECORD || start + u64::from(len) > size {
+            break;
+        }
+        offset = start + u64::from(len);
+
+        buf.resize(u64::from(len).min(SCAN_BYTES) as usize, 0);
+        file.read_exact(&mut buf)?;
+        let mut r = Reader::new(&buf);
+        let Ok(header) = Header::read(&mut r) else {
+            continue;
+        };
+        let height = if header.prev == [0; 32] {
+            Some(0)
+        } else {
+            coinbase_height(&mut r).ok().flatten()
+        };
+        match height {
+            Some(height) if height >= min_height => out.push(Scanned {
+                height,
+                prev: header.prev,
+                work: block_work(header.bits),
+                location: Location {
+                    file: index,
+                    offset: start,
+                    len,
+                    hash: header.hash,
+                },
+            }),
+            Some(_) => {}
+            None => tracing::debug!("No height in block {} of {}", display_hex(&header.hash), path.display()),
+        }
+    }
+    Ok(())
+}
+
+// Walk parent links down from the block with the most accumulated work; stale
+// branches and orphans drop out. Work counts from the lowest scanned blocks, which
+// every branch shares. Of equal-work tips the one written first wins, since zcashd
+// keeps the branch it received first.
+fn best_chain(scanned: Vec<Scanned>) -> BTreeMap<u64, Location> {
+    let mut by_hash: HashMap<[u8; 32], Scanned> = HashMap::with_capacity(scanned.len());
+    let mut order: Vec<([u8; 32], u64)> = Vec::with_capacity(scanned.len());
+    for block in scanned {
+        order.push((block.location.hash, block.height));
+        by_hash.insert(block.location.hash, block);
+    }
+    // Parents before children, then disk order among blocks at one height
+    order.sort_by_key(|(hash, height)| {
+        let location = &by_hash[hash].location;
+        (*height, location.file, location.offset)
+    });
+
+    let mut total: HashMap<[u8; 32], u128> = HashMap::with_capacity(order.len());
+    let mut tip: Option<([u8; 32], u128)> = None;
+    for (hash, height) in &order {
+        let block = &by_hash[hash];
+        let parent = total
+            .get(&block.prev)
+            .copied()
+            .filter(|_| by_hash.get(&block.prev).is_some_and(|p| p.height + 1 == *height))
+            .unwrap_or(0);
+        let work = parent.saturating_add(block.work);
+        total.insert(*hash, work);
+        if tip.is_none_or(|(_, best)| work > best) {
+            tip = Some((*hash, work));
+        }
+    }
+    let Some((tip, _)) = tip else {
+        return BTreeMap::new();
+    };
+
+    let mut chain = BTreeMap::new();
+    let mut next = Some(tip);
+    let mut expected = by_hash[&tip].height;
+    while let Some(block) = next.and_then(|hash| by_hash.remove(&hash)) {
+        if block.height != expected {
+            break;
+        }
+        next = Some(block.prev);
+        chain.insert(block.height, block.location);
+        let Some(below) = expected.checked_sub(1) else {
+            break;
+        };
+        expected = below;
+    }
+    chain
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EASY: u32 = 0x2007_ffff;
+    const HARD: u32 = 0x1f07_ffff;
+
+    // A v1 coinbase carrying its BIP 34 height
+    fn coinbase(height: u64) -> Vec<u8> {
+        let height = height.to_le_bytes();
+        let height = &height[..=height.iter().rposition(|b| *b != 0).unwrap_or(0)];
+        let mut tx = 1u32.to_le_bytes().to_vec();
+        tx.extend([1]);
+        tx.extend([0; 32]);
+        tx.extend([0xff; 4]);
+        tx.extend([height.len() as u8 + 1, height.len() as u8]);
+        tx.extend(height);
+        tx.extend([0xff; 4]);
+        tx.extend([1]);
+        tx.extend(0u64.to_le_bytes());
+        tx.extend([1, 0x51]);
+        tx.extend([0; 4]);
+        tx
+    }
+
+    // A block with an empty Equihash solution: the parser does not check proof of work
+    fn block(prev: [u8; 32], height: u64, bits: u32) -> ([u8; 32], Vec<u8>) {
+        let txs = [coinbase(height)];
+        let txids: Vec<String> = txs.iter().map(|tx| display_hex(&sha256d(tx))).collect();
+        let (root, _) = crate::names::proof::merkle_branch(&txids, 0).unwrap();
+        let mut merkle = hex::decode(root).unwrap();
+        merkle.reverse();
+
+        let mut header = 4u32.to_le_bytes().to_vec();
+        header.extend(prev);
+        header.extend(merkle);
+        header.extend([0; 32]);
+        header.extend(1_700_000_000u32.to_le_bytes());
+        header.extend(bits.to_le_bytes());
+        header.extend([0; 32]);
+        header.extend([0]);
+        let hash = sha256d(&header);
+
+        let mut data = header;
+        data.push(txs.len() as u8);
+        txs.iter().for_each(|tx| data.extend(tx));
+        (hash, data)
+    }
+
+    fn record(data: &[u8]) -> Vec<u8> {
+        [&REGTEST_MAGIC[..], &(data.len() as u32).to_le_bytes(), data].concat()
+    }
+
+    fn scanned(height: u64, prev: u8, hash: u8, work: u128, offset: u64) -> Scanned {
+        Scanned {
+            height,
+            prev: [prev; 32],
+            work,
+            location: Location {
+                file: 0,
+                offset,
+                len: 0,
+                hash: [hash; 32],
+            },
+        }
+    }
+
+    #[test]
+    fn work_follows_the_target() {
+        // 2^256 / (0x07ffff * 2^224)
+        assert_eq!(block_work(HARD), 8192);
+        assert_eq!(block_work(EASY), 32);
+        // Negative and zero targets do no work
+        assert_eq!(block_work(0x1f80_0001), 0);
+        assert_eq!(block_work(0x1f00_0000), 0);
+    }
+
+    #[test]
+    fn most_work_beats_most_blocks() {
+        // 1 <- 2a <- 3a (easy), 1 <- 2b (hard)
+        let chain = best_chain(vec![
+            scanned(1, 0, 1, 10, 0),
+            scanned(2, 1, 0x2a, 10, 1),
+            scanned(3, 0x2a, 0x3a, 10, 2),
+            scanned(2, 1, 0x2b, 100, 3),
+        ]);
+        assert_eq!(chain.keys().copied().collect::<Vec<_>>(), [1, 2]);
+        assert_eq!(chain[&2].hash, [0x2b; 32]);
+    }
+
+    #[test]
+    fn equal_work_keeps_the_block_written_first() {
+        let chain = best_chain(vec![
+            scanned(1, 0, 1, 10, 0),
+            scanned(2, 1, 0x2b, 10, 20),
+            scanned(2, 1, 0x2a, 10, 10),
+        ]);
+        assert_eq!(chain[&2].hash, [0x2a; 32]);
+    }
+
+    #[test]
+    fn reads_the_heaviest_branch_from_disk() {
+        let dir = std::env::temp_dir().join(format!("zord-blockfiles-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let (h1, b1) = block([7; 32], 1, EASY);
+        let (h2a, b2a) = block(h1, 2, EASY);
+        let (_, b3a) = block(h2a, 3, EASY);
+        let (h2b, b2b) = block(h1, 2, HARD);
+        let mut file = [record(&b1), record(&b2a), record(&b3a), record(&b2b)].concat();
+        // Preallocated space after the last record
+        file.extend([0; 64]);
+        std::fs::write(dir.join("blk00000.dat"), file).unwrap();
+
+        let files = BlockFiles::open(&dir, 1).unwrap();
+        assert_eq!(files.tip(), Some(2));
+        let local = files.read_block(2).expect("block on disk");
+        assert_eq!(local.hash, display_hex(&h2b));
+        assert_eq!(local.block.previousblockhash, Some(display_hex(&h1)));
+        assert_eq!(local.block.tx, [local.txs[0].txid.clone()]);
+        assert!(files.read_block(3).is_none());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn damaged_records_are_left_to_rpc() {
+        let dir = std::env::temp_dir().join(format!("zord-blockfiles-damaged-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let (h1, b1) = block([7; 32], 1, EASY);
+        let (h2, mut b2) = block(h1, 2, EASY);
+        // The coinbase's output script no longer hashes to the header's Merkle root
+        *b2.iter_mut().rev().nth(4).unwrap() ^= 1;
+        let (_, b3) = block(h2, 3, EASY);
+        // A record running past the end of the file ends the scan
+        let mut truncated = record(&b3);
+        truncated.truncate(truncated.len() - 10);
+        std::fs::write(dir.join("blk00000.dat"), [record(&b1), record(&b2), truncated].concat()).unwrap();
+
+        let files = BlockFiles::open(&dir, 1).unwrap();
+        assert_eq!(files.tip(), Some(2));
+        assert!(files.read_block(1).is_some());
+        assert!(files.read_block(2).is_none());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn only_zcash_block_files_are_accepted() {
+        let dir = std::env::temp_dir().join(format!("zord-blockfiles-magic-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        assert!(BlockFiles::open(&dir, 0).is_err());
+
+        // Bitcoin mainnet's magic
+        std::fs::write(dir.join("blk00000.dat"), [0xf9, 0xbe, 0xb4, 0xd9, 0, 0, 0, 0]).unwrap();
+        let err = BlockFiles::open(&dir, 0).err().expect("foreign magic refused");
+        assert!(err.to_string().contains("not a Zcash block file"), "{}", err);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
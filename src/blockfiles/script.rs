@@ -0,0 +1,450 @@
+//! Transparent scripts rendered the way zcashd's `getrawtransaction` does: the
+//! `asm` string (`ScriptToAsmStr`), the output `type` and its t-addresses.
+
+use ripemd::Ripemd160;
+use sha2::{Digest, Sha256};
+
+/// Base58Check version bytes of transparent addresses on one network.
+#[derive(Clone, Copy, Debug)]
+pub struct AddressPrefixes {
+    pub p2pkh: [u8; 2],
+    pub p2sh: [u8; 2],
+}
+
+pub const MAINNET: AddressPrefixes = AddressPrefixes {
+    p2pkh: [0x1c, 0xb8],
+    p2sh: [0x1c, 0xbd],
+};
+
+pub const TESTNET: AddressPrefixes = AddressPrefixes {
+    p2pkh: [0x1d, 0x25],
+    p2sh: [0x1c, 0xba],
+};
+
+const OP_PUSHDATA1: u8 = 0x4c;
+const OP_PUSHDATA2: u8 = 0x4d;
+const OP_PUSHDATA4: u8 = 0x4e;
+const OP_RETURN: u8 = 0x6a;
+
+enum Op<'a> {
+    Push(&'a [u8]),
+    Code(u8),
+}
+
+// Script operations in order; `Err` marks a truncated push at the end
+fn ops(script: &[u8]) -> Vec<Result<Op<'_>, ()>> {
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < script.len() {
+        let opcode = script[i];
+        i += 1;
+        if opcode > OP_PUSHDATA4 {
+            out.push(Ok(Op::Code(opcode)));
+            continue;
+        }
+        let width = match opcode {
+            OP_PUSHDATA1 => 1,
+            OP_PUSHDATA2 => 2,
+            OP_PUSHDATA4 => 4,
+            _ => 0,
+        };
+        let len = if width == 0 {
+            opcode as usize
+        } else {
+            let Some(bytes) = script.get(i..i + width) else {
+                out.push(Err(()));
+                break;
+            };
+            i += width;
+            bytes.iter().rev().fold(0usize, |acc, b| (acc << 8) | *b as usize)
+        };
+        match script.get(i..i.saturating_add(len)) {
+            Some(data) => out.push(Ok(Op::Push(data))),
+            None => {
+                out.push(Err(()));
+                break;
+            }
+        }
+        i += len;
+    }
+    out
+}
+
+/// `asm` of a script. `sighash_decode` is set for scriptSigs, where signatures
+/// print with their hash type as `[ALL]` instead of the trailing byte.
+pub fn asm(script: &[u8], sighash_decode: bool) -> String {
+    let unspendable = script.first() == Some(&OP_RETURN);
+    let mut parts = Vec::new();
+    for op in ops(script) {
+        match op {
+            Ok(Op::Push(data)) if data.len() <= 4 => parts.push(script_num(data).to_string()),
+            Ok(Op::Push(data)) => match sighash_name(data).filter(|_| sighash_decode && !unspendable) {
+                Some(name) => parts.push(format!("{}[{}]", hex::encode(&data[..data.len() - 1]), name)),
+                None => parts.push(hex::encode(data)),
+            },
+            Ok(Op::Code(opcode)) => parts.push(op_name(opcode).to_string()),
+            Err(()) => {
+                parts.push("[error]".to_string());
+                break;
+            }
+        }
+    }
+    parts.join(" ")
+}
+
+/// The number a script starts with, such as the BIP 34 height in a coinbase scriptSig.
+pub fn leading_number(script: &[u8]) -> Option<i64> {
+    match ops(script).into_iter().next()? {
+        Ok(Op::Push(data)) if data.len() <= 4 => Some(script_num(data)),
+        Ok(Op::Code(opcode @ 0x51..=0x60)) => Some(i64::from(opcode - 0x50)),
+        _ => None,
+    }
+}
+
+// CScriptNum: little-endian magnitude with the sign in the top bit of the last byte
+fn script_num(data: &[u8]) -> i64 {
+    let Some(last) = data.last() else {
+        return 0;
+    };
+    let mut value = data.iter().rev().fold(0i64, |acc, b| (acc << 8) | i64::from(*b));
+    if last & 0x80 != 0 {
+        value &= !(0x80i64 << (8 * (data.len() - 1)));
+        value = -value;
+    }
+    value
+}
+
+// Hash type of a strictly DER-encoded signature (BIP 66) with a defined sighash byte
+fn sighash_name(sig: &[u8]) -> Option<&'static str> {
+    let len = sig.len();
+    if !(9..=73).contains(&len) || sig[0] != 0x30 || sig[1] as usize != len - 3 || sig[2] != 0x02 {
+        return None;
+    }
+    let len_r = sig[3] as usize;
+    if 5 + len_r >= len {
+        return None;
+    }
+    let len_s = sig[5 + len_r] as usize;
+    if len_r + len_s + 7 != len
+        || len_r == 0
+        || sig[4] & 0x80 != 0
+        || (len_r > 1 && sig[4] == 0 && sig[5] & 0x80 == 0)
+        || sig[len_r + 4] != 0x02
+        || len_s == 0
+        || sig[len_r + 6] & 0x80 != 0
+        || (len_s > 1 && sig[len_r + 6] == 0 && sig[len_r + 7] & 0x80 == 0)
+    {
+        return None;
+    }
+    match sig[len - 1] {
+        0x01 => Some("ALL"),
+        0x02 => Some("NONE"),
+        0x03 => Some("SINGLE"),
+        0x81 => Some("ALL|ANYONECANPAY"),
+        0x82 => Some("NONE|ANYONECANPAY"),
+        0x83 => Some("SINGLE|ANYONECANPAY"),
+        _ => None,
+    }
+}
+
+/// Output `type` and the addresses zcashd lists for it.
+pub fn classify(script: &[u8], prefixes: AddressPrefixes) -> (&'static str, Option<Vec<String>>) {
+    let key_address = |key: &[u8]| base58check(prefixes.p2pkh, &hash160(key));
+    match script {
+        [0x76, 0xa9, 0x14, hash @ .., 0x88, 0xac] if hash.len() == 20 => {
+            ("pubkeyhash", Some(vec![base58check(prefixes.p2pkh, hash)]))
+        }
+        [0xa9, 0x14, hash @ .., 0x87] if hash.len() == 20 => ("scripthash", Some(vec![base58check(prefixes.p2sh, hash)])),
+        [OP_RETURN, ..] => ("nulldata", None),
+        [0x21, key @ .., 0xac] if key.len() == 33 => ("pubkey", Some(vec![key_address(key)])),
+        [0x41, key @ .., 0xac] if key.len() == 65 => ("pubkey", Some(vec![key_address(key)])),
+        [m @ 0x51..=0x60, keys @ .., n @ 0x51..=0x60, 0xae] if m <= n => match multisig_keys(keys) {
+            Some(keys) if keys.len() == usize::from(n - 0x50) => {
+                ("multisig", Some(keys.into_iter().map(key_address).collect()))
+            }
+            _ => ("nonstandard", None),
+        },
+        _ => ("nonstandard", None),
+    }
+}
+
+// The public keys between OP_m and OP_n of a bare multisig script
+fn multisig_keys(script: &[u8]) -> Option<Vec<&[u8]>> {
+    ops(script)
+        .into_iter()
+        .map(|op| match op {
+            Ok(Op::Push(key)) if key.len() == 33 || key.len() == 65 => Some(key),
+            _ => None,
+        })
+        .collect()
+}
+
+fn hash160(data: &[u8]) -> [u8; 20] {
+    Ripemd160::digest(Sha256::digest(data)).into()
+}
+
+fn base58check(prefix: [u8; 2], hash: &[u8]) -> String {
+    const ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+    let mut payload = prefix.to_vec();
+    payload.extend_from_slice(hash);
+    let checksum = Sha256::digest(Sha256::digest(&payload));
+    payload.extend_from_slice(&checksum[..4]);
+
+    // Repeated division of the big-endian number by 58
+    let mut digits: Vec<u8> = Vec::new();
+    for byte in &payload {
+        let mut carry = u32::from(*byte);
+        for digit in digits.iter_mut() {
+            carry += u32::from(*digit) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+    let zeros = payload.iter().take_while(|b| **b == 0).count();
+    std::iter::repeat_n(b'1', zeros)
+        .chain(digits.iter().rev().map(|d| ALPHABET[*d as usize]))
+        .map(char::from)
+        .collect()
+}
+
+fn op_name(opcode: u8) -> &'static str {
+    const NUMBERS: [&str; 16] = [
+        "1", "2", "3", "4", "5", "6", "7", "8", "9", "10", "11", "12", "13", "14", "15", "16",
+    ];
+    const NAMES: [&str; 0xba - 0x61] = [
+        "OP_NOP",
+        "OP_VER",
+        "OP_IF",
+        "OP_NOTIF",
+        "OP_VERIF",
+        "OP_VERNOTIF",
+        "OP_ELSE",
+        "OP_ENDIF",
+        "OP_VERIFY",
+        "OP_RETURN",
+        "OP_TOALTSTACK",
+        "OP_FROMALTSTACK",
+        "OP_2DROP",
+        "OP_2DUP",
+        "OP_3DUP",
+        "OP_2OVER",
+        "OP_2ROT",
+        "OP_2SWAP",
+        "OP_IFDUP",
+        "OP_DEPTH",
+        "OP_DROP",
+        "OP_DUP",
+        "OP_NIP",
+        "OP_OVER",
+        "OP_PICK",
+        "OP_ROLL",
+        "OP_ROT",
+        "OP_SWAP",
+        "OP_TUCK",
+        "OP_CAT",
+        "OP_SUBSTR",
+        "OP_LEFT",
+        "OP_RIGHT",
+        "OP_SIZE",
+        "OP_INVERT",
+        "OP_AND",
+        "OP_OR",
+        "OP_XOR",
+        "OP_EQUAL",
+        "OP_EQUALVERIFY",
+        "OP_RESERVED1",
+        "OP_RESERVED2",
+        "OP_1ADD",
+        "OP_1SUB",
+        "OP_2MUL",
+        "OP_2DIV",
+        "OP_NEGATE",
+        "OP_ABS",
+        "OP_NOT",
+        "OP_0NOTEQUAL",
+        "OP_ADD",
+        "OP_SUB",
+        "OP_MUL",
+        "OP_DIV",
+        "OP_MOD",
+        "OP_LSHIFT",
+        "OP_RSHIFT",
+        "OP_BOOLAND",
+        "OP_BOOLOR",
+        "OP_NUMEQUAL",
+        "OP_NUMEQUALVERIFY",
+        "OP_NUMNOTEQUAL",
+        "OP_LESSTHAN",
+        "OP_GREATERTHAN",
+        "OP_LESSTHANOREQUAL",
+        "OP_GREATERTHANOREQUAL",
+        "OP_MIN",
+        "OP_MAX",
+        "OP_WITHIN",
+        "OP_RIPEMD160",
+        "OP_SHA1",
+        "OP_SHA256",
+        "OP_HASH160",
+        "OP_HASH256",
+        "OP_CODESEPARATOR",
+        "OP_CHECKSIG",
+        "OP_CHECKSIGVERIFY",
+        "OP_CHECKMULTISIG",
+        "OP_CHECKMULTISIGVERIFY",
+        "OP_NOP1",
+        "OP_CHECKLOCKTIMEVERIFY",
+        "OP_NOP3",
+        "OP_NOP4",
+        "OP_NOP5",
+        "OP_NOP6",
+        "OP_NOP7",
+        "OP_NOP8",
+        "OP_NOP9",
+        "OP_NOP10",
+    ];
+    match opcode {
+        0x4f => "-1",
+        0x50 => "OP_RESERVED",
+        0x51..=0x60 => NUMBERS[(opcode - 0x51) as usize],
+        0x61..=0xb9 => NAMES[(opcode - 0x61) as usize],
+        0xff => "OP_INVALIDOPCODE",
+        _ => "OP_UNKNOWN",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A strictly DER-encoded signature without its hash type byte
+    const SIG: &str = "3044022011a7b5c34e3b0c83c6a07e7e7e4bd6e7f3d0ab14d2f98c4d9a4e1f7cb8f5b6c2022022a1d8f0e3b7c9a6d5f4e3c2b1a0998877665544332211ffeeddccbbaa998877";
+    const PUBKEY: &str = "02a3f1b2c3d4e5f60718293a4b5c6d7e8f90a1b2c3d4e5f60718293a4b5c6d7e8f";
+
+    fn push(data: &[u8]) -> Vec<u8> {
+        let mut out = match data.len() {
+            len @ 0..=0x4b => vec![len as u8],
+            len @ 0x4c..=0xff => vec![OP_PUSHDATA1, len as u8],
+            len => {
+                let mut prefix = vec![OP_PUSHDATA2];
+                prefix.extend_from_slice(&(len as u16).to_le_bytes());
+                prefix
+            }
+        };
+        out.extend_from_slice(data);
+        out
+    }
+
+    fn signature(hash_type: u8) -> Vec<u8> {
+        let mut sig = hex::decode(SIG).unwrap();
+        sig.push(hash_type);
+        sig
+    }
+
+    fn spend(sig: &[u8]) -> Vec<u8> {
+        [push(sig), push(&hex::decode(PUBKEY).unwrap())].concat()
+    }
+
+    #[test]
+    fn signatures_print_their_hash_type() {
+        assert_eq!(asm(&spend(&signature(0x01)), true), format!("{}[ALL] {}", SIG, PUBKEY));
+        for (hash_type, name) in [
+            (0x02, "NONE"),
+            (0x03, "SINGLE"),
+            (0x81, "ALL|ANYONECANPAY"),
+            (0x82, "NONE|ANYONECANPAY"),
+            (0x83, "SINGLE|ANYONECANPAY"),
+        ] {
+            assert_eq!(asm(&spend(&signature(hash_type)), true), format!("{}[{}] {}", SIG, name, PUBKEY));
+        }
+    }
+
+    #[test]
+    fn undecodable_signatures_print_whole() {
+        // Undefined hash type
+        assert_eq!(asm(&spend(&signature(0x04)), true), format!("{}04 {}", SIG, PUBKEY));
+        // Only scriptSigs are decoded
+        assert_eq!(asm(&spend(&signature(0x01)), false), format!("{}01 {}", SIG, PUBKEY));
+        // Nor anything after OP_RETURN
+        let mut data = vec![OP_RETURN];
+        data.extend(push(&signature(0x01)));
+        assert_eq!(asm(&data, true), format!("OP_RETURN {}01", SIG));
+        // R padded with a needless zero byte is not strict DER (BIP 66)
+        let mut padded = hex::decode(SIG).unwrap();
+        padded[1] += 1;
+        padded[3] += 1;
+        padded.insert(4, 0x00);
+        padded.push(0x01);
+        assert_eq!(asm(&push(&padded), true), hex::encode(&padded));
+    }
+
+    #[test]
+    fn short_pushes_print_as_numbers() {
+        let script = [
+            &[0x00][..],
+            &[0x01, 0x81],
+            &[0x02, 0xff, 0x00],
+            &[0x4f, 0x51, 0x60],
+            &[0x04, 0xff, 0xff, 0xff, 0xff],
+            &[OP_PUSHDATA1, 0x02, 0x10, 0x27],
+            &[0x05, 0x01, 0x02, 0x03, 0x04, 0x05],
+        ]
+        .concat();
+        assert_eq!(asm(&script, true), "0 -1 255 -1 1 16 -2147483647 10000 0102030405");
+    }
+
+    #[test]
+    fn opcodes_use_zcashd_names() {
+        let hash = "fb5e512425fc9449316ec95969ebe71e2d576dba";
+        let p2pkh = [&[0x76, 0xa9, 0x14][..], &hex::decode(hash).unwrap(), &[0x88, 0xac]].concat();
+        assert_eq!(asm(&p2pkh, false), format!("OP_DUP OP_HASH160 {} OP_EQUALVERIFY OP_CHECKSIG", hash));
+        assert_eq!(
+            asm(&[0xb1, 0xb2, 0xb9, 0xba, 0xff, 0x50, 0x61, 0xae], false),
+            "OP_CHECKLOCKTIMEVERIFY OP_NOP3 OP_NOP10 OP_UNKNOWN OP_INVALIDOPCODE OP_RESERVED OP_NOP OP_CHECKMULTISIG"
+        );
+    }
+
+    #[test]
+    fn truncated_pushes_end_in_error() {
+        assert_eq!(asm(&[0x76, 0x05, 0x01, 0x02], false), "OP_DUP [error]");
+        assert_eq!(asm(&[OP_PUSHDATA1], false), "[error]");
+        assert_eq!(asm(&[OP_PUSHDATA2, 0x01], false), "[error]");
+    }
+
+    #[test]
+    fn long_pushes_print_as_hex() {
+        let payload = vec![0xab; 300];
+        let script = [push(b"text/plain"), push(&payload[..100]), push(&payload)].concat();
+        assert_eq!(
+            asm(&script, true),
+            format!("{} {} {}", hex::encode("text/plain"), "ab".repeat(100), "ab".repeat(300))
+        );
+    }
+
+    #[test]
+    fn outputs_classify_with_network_addresses() {
+        let hash = hex::decode("fb5e512425fc9449316ec95969ebe71e2d576dba").unwrap();
+        let p2pkh = [&[0x76, 0xa9, 0x14][..], &hash, &[0x88, 0xac]].concat();
+        assert_eq!(
+            classify(&p2pkh, MAINNET),
+            ("pubkeyhash", Some(vec!["t1gnibjzn69hfCi8UDK3RubN9HKkbCLLHrJ".to_string()]))
+        );
+        let p2sh = [&[0xa9, 0x14][..], &hash, &[0x87]].concat();
+        let (kind, addresses) = classify(&p2sh, MAINNET);
+        assert_eq!(kind, "scripthash");
+        assert!(addresses.unwrap()[0].starts_with("t3"));
+        assert_eq!(classify(&[OP_RETURN, 0x01, 0x01], MAINNET), ("nulldata", None));
+        assert_eq!(classify(&[0x51], MAINNET), ("nonstandard", None));
+    }
+
+    #[test]
+    fn coinbase_heights() {
+        assert_eq!(leading_number(&[0x03, 0x40, 0x0d, 0x03, 0xff]), Some(200_000));
+        assert_eq!(leading_number(&[0x55]), Some(5));
+        assert_eq!(leading_number(&[0x76]), None);
+        assert_eq!(leading_number(&[]), None);
+    }
+}
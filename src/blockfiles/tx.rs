@@ -0,0 +1,587 @@
+//! Raw Zcash transactions (v1 to v5) decoded into the same `TxResponse` that
+//! `getrawtransaction` returns, so the indexer cannot tell the two sources apart.
+//!
+//! Only the transparent parts are decoded; shielded bundles are walked over, and
+//! read only as far as the v5 txid (ZIP 244) commits to them.
+
+use super::script::{self, AddressPrefixes};
+use crate::rpc::{ScriptPubKey, ScriptSig, TxResponse, Vin, Vout};
+use anyhow::{anyhow, Result};
+use sha2::{Digest, Sha256};
+
+const OVERWINTER_FLAG: u32 = 1 << 31;
+
+// Shielded field sizes in bytes
+const JOINSPLIT_PHGR: usize = 1802;
+const JOINSPLIT_GROTH: usize = 1698;
+const V4_SPEND: usize = 384;
+const V4_OUTPUT: usize = 948;
+const GROTH_PROOF: usize = 192;
+const ENC_CIPHERTEXT: usize = 580;
+const OUT_CIPHERTEXT: usize = 80;
+// Split of an encrypted note into its compact part and memo (ZIP 244 / ZIP 307)
+const COMPACT_NOTE: usize = 52;
+const MEMO_END: usize = 564;
+
+/// A cursor over serialized consensus data.
+pub struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    pub fn pos(&self) -> usize {
+        self.pos
+    }
+
+    pub fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.data.len())
+            .ok_or_else(|| anyhow!("truncated at byte {} (wanted {} more)", self.pos, len))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    /// The bytes read since position `start`.
+    pub fn slice_from(&self, start: usize) -> &'a [u8] {
+        &self.data[start..self.pos]
+    }
+
+    pub fn skip(&mut self, len: usize) -> Result<()> {
+        self.take(len).map(|_| ())
+    }
+
+    pub fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+
+    pub fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into()?))
+    }
+
+    pub fn hash(&mut self) -> Result<[u8; 32]> {
+        Ok(self.take(32)?.try_into()?)
+    }
+
+    pub fn compact_size(&mut self) -> Result<usize> {
+        let value = match self.u8()? {
+            0xfd => u64::from(u16::from_le_bytes(self.take(2)?.try_into()?)),
+            0xfe => u64::from(self.u32()?),
+            0xff => self.u64()?,
+            n => u64::from(n),
+        };
+        // Nothing in a block comes close; a larger count is corrupt data
+        if value > 0x0200_0000 {
+            return Err(anyhow!("size {} out of range at byte {}", value, self.pos));
+        }
+        Ok(value as usize)
+    }
+
+    /// `count` items of `size` bytes each, failing before allocating on corrupt counts.
+    fn items(&mut self, count: usize, size: usize) -> Result<Vec<&'a [u8]>> {
+        let all = self.take(count.checked_mul(size).ok_or_else(|| anyhow!("size overflow"))?)?;
+        Ok(all.chunks(size).collect())
+    }
+}
+
+/// Display hex of a hash kept in internal byte order.
+pub fn display_hex(hash: &[u8]) -> String {
+    let mut bytes = hash.to_vec();
+    bytes.reverse();
+    hex::encode(bytes)
+}
+
+pub fn sha256d(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(Sha256::digest(data)).into()
+}
+
+struct Input<'a> {
+    prevout: &'a [u8],
+    script: &'a [u8],
+    sequence: u32,
+}
+
+struct Output<'a> {
+    value: u64,
+    script: &'a [u8],
+    // Serialized form, for the ZIP 244 outputs digest
+    raw: &'a [u8],
+}
+
+fn transparent<'a>(r: &mut Reader<'a>) -> Result<(Vec<Input<'a>>, Vec<Output<'a>>)> {
+    let mut inputs = Vec::new();
+    for _ in 0..r.compact_size()? {
+        let prevout = r.take(36)?;
+        let len = r.compact_size()?;
+        let script = r.take(len)?;
+        inputs.push(Input {
+            prevout,
+            script,
+            sequence: r.u32()?,
+        });
+    }
+    let mut outputs = Vec::new();
+    for _ in 0..r.compact_size()? {
+        let start = r.pos();
+        let value = r.u64()?;
+        let len = r.compact_size()?;
+        let script = r.take(len)?;
+        outputs.push(Output {
+            value,
+            script,
+            raw: r.slice_from(start),
+        });
+    }
+    Ok((inputs, outputs))
+}
+
+/// Read one transaction from `r` and decode it for the indexer.
+pub fn parse(r: &mut Reader<'_>, prefixes: AddressPrefixes) -> Result<TxResponse> {
+    let start = r.pos();
+    let header = r.u32()?;
+    let overwintered = header & OVERWINTER_FLAG != 0;
+    let version = header & !OVERWINTER_FLAG;
+
+    let (inputs, outputs, v5_txid) = if overwintered && version >= 5 {
+        let version_group = r.u32()?;
+        let branch_id = r.u32()?;
+        let lock_time = r.u32()?;
+        let expiry = r.u32()?;
+        let (inputs, outputs) = transparent(r)?;
+        let header_digest = blake2b(
+            b"ZTxIdHeadersHash",
+            &[
+                &header.to_le_bytes(),
+                &version_group.to_le_bytes(),
+                &branch_id.to_le_bytes(),
+                &lock_time.to_le_bytes(),
+                &expiry.to_le_bytes(),
+            ],
+        );
+        let transparent_digest = transparent_digest(&inputs, &outputs);
+        let sapling_digest = sapling_v5(r)?;
+        let orchard_digest = orchard_v5(r)?;
+
+        let mut personal = *b"ZcashTxHash_\0\0\0\0";
+        personal[12..].copy_from_slice(&branch_id.to_le_bytes());
+        let txid = blake2b(
+            &personal,
+            &[&header_digest, &transparent_digest, &sapling_digest, &orchard_digest],
+        );
+        (inputs, outputs, Some(txid))
+    } else {
+        if overwintered {
+            r.skip(4)?; // nVersionGroupId
+        }
+        let (inputs, outputs) = transparent(r)?;
+        r.skip(4)?; // nLockTime
+        if overwintered {
+            r.skip(4)?; // nExpiryHeight
+        }
+        let mut sapling = 0;
+        if overwintered && version >= 4 {
+            r.skip(8)?; // valueBalance
+            let spends = r.compact_size()?;
+            r.skip(spends.saturating_mul(V4_SPEND))?;
+            let outputs = r.compact_size()?;
+            r.skip(outputs.saturating_mul(V4_OUTPUT))?;
+            sapling = spends + outputs;
+        }
+        if version >= 2 {
+            let joinsplits = r.compact_size()?;
+            let size = if overwintered && version >= 4 {
+                JOINSPLIT_GROTH
+            } else {
+                JOINSPLIT_PHGR
+            };
+            r.skip(joinsplits.saturating_mul(size))?;
+            if joinsplits > 0 {
+                r.skip(32 + 64)?; // joinSplitPubKey, joinSplitSig
+            }
+        }
+        if sapling > 0 {
+            r.skip(64)?; // bindingSig
+        }
+        (inputs, outputs, None)
+    };
+
+    let raw = r.slice_from(start);
+    let txid = v5_txid.unwrap_or_else(|| sha256d(raw));
+
+    let coinbase = inputs.len() == 1 && inputs[0].prevout[..32] == [0; 32];
+    let vin = inputs
+        .iter()
+        .map(|input| {
+            if coinbase {
+                return Vin {
+                    txid: None,
+                    vout: None,
+                    script_sig: None,
+                };
+            }
+            Vin {
+                txid: Some(display_hex(&input.prevout[..32])),
+                vout: Some(u32::from_le_bytes(input.prevout[32..].try_into().expect("4 bytes"))),
+                script_sig: Some(ScriptSig {
+                    hex: hex::encode(input.script),
+                    asm: script::asm(input.script, true),
+                }),
+            }
+        })
+        .collect();
+    let vout = outputs
+        .iter()
+        .enumerate()
+        .map(|(n, output)| {
+            let (kind, addresses) = script::classify(output.script, prefixes);
+            Vout {
+                value: output.value as f64 / 100_000_000.0,
+                value_zat: Some(output.value),
+                n: n as u32,
+                script_pub_key: ScriptPubKey {
+                    hex: hex::encode(output.script),
+                    asm: script::asm(output.script, false),
+                    r#type: kind.to_string(),
+                    addresses,
+                },
+            }
+        })
+        .collect();
+
+    Ok(TxResponse {
+        txid: display_hex(&txid),
+        hex: hex::encode(raw),
+        vin,
+        vout,
+    })
+}
+
+fn blake2b(personal: &[u8; 16], parts: &[&[u8]]) -> [u8; 32] {
+    let mut state = blake2b_simd::Params::new().hash_length(32).personal(personal).to_state();
+    for part in parts {
+        state.update(part);
+    }
+    state.finalize().as_bytes().try_into().expect("32 bytes")
+}
+
+// ZIP 244 T
+fn transparent_digest(inputs: &[Input<'_>], outputs: &[Output<'_>]) -> [u8; 32] {
+    if inputs.is_empty() && outputs.is_empty() {
+        return blake2b(b"ZTxIdTranspaHash", &[]);
+    }
+    let prevouts: Vec<&[u8]> = inputs.iter().map(|i| i.prevout).collect();
+    let sequences: Vec<[u8; 4]> = inputs.iter().map(|i| i.sequence.to_le_bytes()).collect();
+    let sequences: Vec<&[u8]> = sequences.iter().map(|s| &s[..]).collect();
+    let raw_outputs: Vec<&[u8]> = outputs.iter().map(|o| o.raw).collect();
+    blake2b(
+        b"ZTxIdTranspaHash",
+        &[
+            &blake2b(b"ZTxIdPrevoutHash", &prevouts),
+            &blake2b(b"ZTxIdSequencHash", &sequences),
+            &blake2b(b"ZTxIdOutputsHash", &raw_outputs),
+        ],
+    )
+}
+
+// Read the v5 Sapling bundle and return its ZIP 244 S digest
+fn sapling_v5(r: &mut Reader<'_>) -> Result<[u8; 32]> {
+    let spend_count = r.compact_size()?;
+    let spends = r.items(spend_count, 96)?; // cv, nullifier, rk
+    let output_count = r.compact_size()?;
+    let outputs = r.items(output_count, 32 * 3 + ENC_CIPHERTEXT + OUT_CIPHERTEXT)?; // cv, cmu, epk, ciphertexts
+    if spend_count + output_count == 0 {
+        return Ok(blake2b(b"ZTxIdSaplingHash", &[]));
+    }
+    let value_balance = r.take(8)?;
+    let anchor = if spend_count > 0 { r.take(32)? } else { &[] };
+    r.skip(spend_count.saturating_mul(GROTH_PROOF + 64))?; // proofs, spendAuthSigs
+    r.skip(output_count.saturating_mul(GROTH_PROOF))?;
+    r.skip(64)?; // bindingSig
+
+    let spends_digest = if spends.is_empty() {
+        blake2b(b"ZTxIdSSpendsHash", &[])
+    } else {
+        let compact: Vec<&[u8]> = spends.iter().map(|s| &s[32..64]).collect();
+        let noncompact: Vec<&[u8]> = spends.iter().flat_map(|s| [&s[..32], anchor, &s[64..]]).collect();
+        blake2b(
+            b"ZTxIdSSpendsHash",
+            &[
+                &blake2b(b"ZTxIdSSpendCHash", &compact),
+                &blake2b(b"ZTxIdSSpendNHash", &noncompact),
+            ],
+        )
+    };
+    let outputs_digest = if outputs.is_empty() {
+        blake2b(b"ZTxIdSOutputHash", &[])
+    } else {
+        // cv, cmu and the ephemeral key come before enc_ciphertext, then out_ciphertext
+        let compact: Vec<&[u8]> = outputs.iter().map(|o| &o[32..96 + COMPACT_NOTE]).collect();
+        let memos: Vec<&[u8]> = outputs.iter().map(|o| &o[96 + COMPACT_NOTE..96 + MEMO_END]).collect();
+        let noncompact: Vec<&[u8]> = outputs.iter().flat_map(|o| [&o[..32], &o[96 + MEMO_END..]]).collect();
+        blake2b(
+            b"ZTxIdSOutputHash",
+            &[
+                &blake2b(b"ZTxIdSOutC__Hash", &compact),
+                &blake2b(b"ZTxIdSOutM__Hash", &memos),
+                &blake2b(b"ZTxIdSOutN__Hash", &noncompact),
+            ],
+        )
+    };
+    Ok(blake2b(b"ZTxIdSaplingHash", &[&spends_digest, &outputs_digest, value_balance]))
+}
+
+// Read the v5 Orchard bundle and return its ZIP 244 O digest
+fn orchard_v5(r: &mut Reader<'_>) -> Result<[u8; 32]> {
+    let count = r.compact_size()?;
+    // cv, nullifier, rk, cmx, ephemeral key, ciphertexts
+    let actions = r.items(count, 32 * 5 + ENC_CIPHERTEXT + OUT_CIPHERTEXT)?;
+    if count == 0 {
+        return Ok(blake2b(b"ZTxIdOrchardHash", &[]));
+    }
+    let flags = r.take(1)?;
+    let value_balance = r.take(8)?;
+    let anchor = r.take(32)?;
+    let proof_len = r.compact_size()?;
+    r.skip(proof_len)?;
+    r.skip(count.saturating_mul(64) + 64)?; // spendAuthSigs, bindingSig
+
+    // enc_ciphertext starts at byte 160, after the ephemeral key
+    let compact: Vec<&[u8]> = actions
+        .iter()
+        .flat_map(|a| [&a[32..64], &a[96..160 + COMPACT_NOTE]])
+        .collect();
+    let memos: Vec<&[u8]> = actions.iter().map(|a| &a[160 + COMPACT_NOTE..160 + MEMO_END]).collect();
+    let noncompact: Vec<&[u8]> = actions
+        .iter()
+        .flat_map(|a| [&a[..32], &a[64..96], &a[160 + MEMO_END..]])
+        .collect();
+    Ok(blake2b(
+        b"ZTxIdOrchardHash",
+        &[
+            &blake2b(b"ZTxIdOrcActCHash", &compact),
+            &blake2b(b"ZTxIdOrcActMHash", &memos),
+            &blake2b(b"ZTxIdOrcActNHash", &noncompact),
+            flags,
+            value_balance,
+            anchor,
+        ],
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Deterministic filler for shielded fields: SHA-256 of a counter from `seed`
+    fn fill(seed: u32, len: usize) -> Vec<u8> {
+        (seed..).flat_map(|n| Sha256::digest(n.to_le_bytes())).take(len).collect()
+    }
+
+    fn compact(n: usize) -> Vec<u8> {
+        match u16::try_from(n).unwrap() {
+            n @ 0..0xfd => vec![n as u8],
+            n => [&[0xfd][..], &n.to_le_bytes()].concat(),
+        }
+    }
+
+    fn push(data: &[u8]) -> Vec<u8> {
+        [&[data.len() as u8][..], data].concat()
+    }
+
+    fn decode(raw: &[u8]) -> TxResponse {
+        let mut r = Reader::new(raw);
+        let tx = parse(&mut r, script::MAINNET).expect("transaction parses");
+        assert_eq!(r.pos(), raw.len(), "whole transaction consumed");
+        tx
+    }
+
+    // A P2PKH spend paying one P2PKH output and one OP_RETURN, as in every
+    // vector below
+    fn transparent_parts() -> (Vec<u8>, Vec<u8>) {
+        let sig = [&[0x30, 0x44, 0x02, 0x20, 0x11][..], &fill(1, 31), &[0x02, 0x20, 0x22], &fill(2, 31), &[0x01]].concat();
+        let pubkey = [&[0x02][..], &fill(3, 32)].concat();
+        let script_sig = [push(&sig), push(&pubkey)].concat();
+        let p2pkh = [&[0x76, 0xa9, 0x14][..], &fill(4, 20), &[0x88, 0xac]].concat();
+        let inputs = [
+            compact(1),
+            fill(5, 32),
+            1u32.to_le_bytes().to_vec(),
+            compact(script_sig.len()),
+            script_sig,
+            0xffff_fffeu32.to_le_bytes().to_vec(),
+        ]
+        .concat();
+        let outputs = [
+            compact(2),
+            150_000u64.to_le_bytes().to_vec(),
+            compact(p2pkh.len()),
+            p2pkh,
+            0u64.to_le_bytes().to_vec(),
+            vec![0x03, 0x6a, 0x01, 0x01],
+        ]
+        .concat();
+        (inputs, outputs)
+    }
+
+    fn v5_header() -> Vec<u8> {
+        [0x8000_0005u32, 0x26a7_270a, 0xc2d6_d0b4, 0, 2_000_040]
+            .iter()
+            .flat_map(|field| field.to_le_bytes())
+            .collect()
+    }
+
+    #[test]
+    fn genesis_coinbase_hashes_to_the_mainnet_merkle_root() {
+        let timestamp = b"Zcash0b9c4eef8b7cc417ee5001e3500984b6fea35683a7cac141a043c42064835d34";
+        let script_sig = [&hex::decode("04ffff071f0104").unwrap()[..], &push(timestamp)].concat();
+        let pubkey = "04678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5f";
+        let script_pubkey = [push(&hex::decode(pubkey).unwrap()), vec![0xac]].concat();
+        let raw = [
+            1u32.to_le_bytes().to_vec(),
+            compact(1),
+            vec![0; 32],
+            vec![0xff; 4],
+            compact(script_sig.len()),
+            script_sig,
+            vec![0xff; 4],
+            compact(1),
+            0u64.to_le_bytes().to_vec(),
+            compact(script_pubkey.len()),
+            script_pubkey,
+            vec![0; 4],
+        ]
+        .concat();
+
+        let tx = decode(&raw);
+        // The only transaction of block 0, so its txid is the block's Merkle root
+        assert_eq!(tx.txid, "c4eaa58879081de3c24a7b117ed2b28300e7ec4c4c1dff1d3f1268b7857a4ddb");
+        assert!(tx.vin[0].script_sig.is_none());
+        assert_eq!(tx.vout[0].script_pub_key.asm, format!("{} OP_CHECKSIG", pubkey));
+        assert_eq!(tx.vout[0].script_pub_key.r#type, "pubkey");
+    }
+
+    #[test]
+    fn v4_with_sapling_and_joinsplit() {
+        let (inputs, outputs) = transparent_parts();
+        let raw = [
+            0x8000_0004u32.to_le_bytes().to_vec(),
+            0x892f_2085u32.to_le_bytes().to_vec(),
+            inputs,
+            outputs,
+            0u32.to_le_bytes().to_vec(),
+            2_000_000u32.to_le_bytes().to_vec(),
+            (-5000i64).to_le_bytes().to_vec(),
+            compact(1),
+            fill(10, V4_SPEND),
+            compact(1),
+            fill(11, V4_OUTPUT),
+            compact(1),
+            fill(12, JOINSPLIT_GROTH),
+            fill(13, 32),
+            fill(14, 64),
+            fill(15, 64),
+        ]
+        .concat();
+
+        let tx = decode(&raw);
+        assert_eq!(tx.txid, "208c6e8c8e544abfd3de5d985c94de61c44aff18f9aabf5016812e8000723f92");
+        assert_eq!(tx.hex, hex::encode(&raw));
+        let script_sig = tx.vin[0].script_sig.as_ref().unwrap();
+        assert_eq!(
+            script_sig.asm,
+            format!(
+                "3044022011{}022022{}[ALL] 02{}",
+                hex::encode(fill(1, 31)),
+                hex::encode(fill(2, 31)),
+                hex::encode(fill(3, 32))
+            )
+        );
+        assert_eq!(tx.vin[0].txid.as_deref(), Some(display_hex(&fill(5, 32)).as_str()));
+        assert_eq!(tx.vin[0].vout, Some(1));
+        assert_eq!(tx.vout[0].value_zat, Some(150_000));
+        assert_eq!(tx.vout[0].script_pub_key.r#type, "pubkeyhash");
+        assert_eq!(
+            tx.vout[0].script_pub_key.addresses,
+            Some(vec!["t1gnibjzn69hfCi8UDK3RubN9HKkbCLLHrJ".to_string()])
+        );
+        assert_eq!(tx.vout[1].script_pub_key.asm, "OP_RETURN 1");
+        assert_eq!(tx.vout[1].script_pub_key.r#type, "nulldata");
+    }
+
+    #[test]
+    fn v5_txid_commits_to_every_bundle() {
+        let (inputs, outputs) = transparent_parts();
+        let sapling_output = 32 * 3 + ENC_CIPHERTEXT + OUT_CIPHERTEXT;
+        let action = 32 * 5 + ENC_CIPHERTEXT + OUT_CIPHERTEXT;
+        let proof = fill(61, 300);
+        let raw = [
+            v5_header(),
+            inputs,
+            outputs,
+            // Sapling: two spends, one output
+            compact(2),
+            fill(20, 96),
+            fill(21, 96),
+            compact(1),
+            fill(30, sapling_output),
+            12345i64.to_le_bytes().to_vec(),
+            fill(40, 32),
+            fill(41, 2 * GROTH_PROOF),
+            fill(42, 2 * 64),
+            fill(43, GROTH_PROOF),
+            fill(44, 64),
+            // Orchard: two actions
+            compact(2),
+            fill(50, action),
+            fill(51, action),
+            vec![0x03],
+            (-777i64).to_le_bytes().to_vec(),
+            fill(60, 32),
+            compact(proof.len()),
+            proof,
+            fill(62, 2 * 64),
+            fill(63, 64),
+        ]
+        .concat();
+
+        let tx = decode(&raw);
+        assert_eq!(tx.txid, "779c194add0778da9981e9069d75258edd8882517aca86f4f7ad78cdc0547db4");
+        assert_eq!(tx.vin.len(), 1);
+        assert_eq!(tx.vout.len(), 2);
+
+        // Memos are committed too: flip a byte in the second action's
+        let mut changed = raw.clone();
+        // flags, valueBalance, anchor, proof size and proof, signatures follow the actions
+        let action_start = raw.len() - (1 + 8 + 32 + 3 + 300 + 2 * 64 + 64) - action;
+        changed[action_start + 160 + COMPACT_NOTE + 100] ^= 1;
+        assert_ne!(decode(&changed).txid, tx.txid);
+    }
+
+    #[test]
+    fn v5_without_bundles_uses_the_empty_digests() {
+        let raw = [v5_header(), vec![0; 5]].concat();
+        let tx = decode(&raw);
+        assert_eq!(tx.txid, "4560edb2ed06e61f94e3edb1a160536dae0ee60f0b77b11293631477b15d005e");
+        assert!(tx.vin.is_empty() && tx.vout.is_empty());
+    }
+
+    #[test]
+    fn truncated_transactions_fail() {
+        let raw = [v5_header(), vec![0; 5]].concat();
+        assert!(parse(&mut Reader::new(&raw[..raw.len() - 1]), script::MAINNET).is_err());
+        // An absurd count fails before allocating
+        let huge = [v5_header(), vec![0xff; 9]].concat();
+        assert!(parse(&mut Reader::new(&huge), script::MAINNET).is_err());
+    }
+}
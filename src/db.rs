@@ -1,13 +1,54 @@
+use crate::zrc20::amount::{self, Amount, Delta};
 use anyhow::Result;
-use redb::{Database, ReadableTable, TableDefinition};
-use std::sync::Arc;
+use redb::{
+    Database, ReadTransaction, ReadableTable, RedbKey, RedbValue, Table, TableDefinition,
+    TableHandle, WriteTransaction,
+};
+use std::ops::Bound;
+use std::sync::{Arc, Mutex};
 use std::{
+    collections::{BTreeMap, HashMap},
     fs,
     path::{Path, PathBuf},
 };
 
+mod cache;
+mod distribution;
+mod dump;
+mod feed;
+mod fulltext;
+mod ipfs_cache;
+mod merkle;
+mod migrations;
+mod rarity;
+mod reservations;
+mod scan;
+mod thumbnails;
+mod traits;
+mod trends;
+mod verification;
+mod verify;
+mod webhooks;
+mod writer;
+
+use cache::ReadCache;
+pub use distribution::Distribution;
+pub use feed::{classify_mime, FeedFilter, FeedSort};
+pub use ipfs_cache::CachedObject;
+pub use merkle::{BalanceProof, StateChecksum};
+pub use rarity::Rarity;
+pub use scan::SCAN_BATCH;
+pub use traits::{parse_trait_filter, TraitCounts};
+pub use trends::{Period, PeriodStats};
+pub use writer::BlockWriter;
+
+// Status key recording when the file was last rewritten by `Db::compact`
+const LAST_COMPACTION_KEY: &str = "last_compaction_unix";
+
 // redb table schemas
 const BLOCKS: TableDefinition<u64, &str> = TableDefinition::new("blocks");
+// Block hash -> decimal height, the reverse of BLOCKS
+const BLOCK_HASHES: TableDefinition<&str, &str> = TableDefinition::new("block_hashes");
 const INSCRIPTIONS: TableDefinition<&str, &str> = TableDefinition::new("inscriptions");
 const TOKENS: TableDefinition<&str, &str> = TableDefinition::new("tokens");
 
@@ -19,9 +60,65 @@ const TRANSFER_INSCRIPTIONS: TableDefinition<&str, &str> =
     TableDefinition::new("transfer_inscriptions");
 // ZRC-20 burned amounts per ticker (base units as string)
 const ZRC20_BURNS: TableDefinition<&str, &str> = TableDefinition::new("zrc20_burns");
+// Mints per "<ticker>:<address>" for tokens deployed with mint throttling:
+// {"minted":"<base units>","last_height":h}
+const ZRC20_MINTS: TableDefinition<&str, &str> = TableDefinition::new("zrc20_mints");
 // Map outpoint ("<txid>:<vout>") -> transfer inscription id
 const TRANSFER_OUTPOINTS: TableDefinition<&str, &str> =
     TableDefinition::new("transfer_outpoints");
+// Sender address -> JSON list of its unused transfer inscription ids
+const ADDRESS_TRANSFERS: TableDefinition<&str, &str> = TableDefinition::new("address_transfers");
+// ZRC-20 mint/transfer/burn rows keyed "<tick>:<height>:<seq>" and "<address>:<height>:<seq>"
+const ZRC20_TICK_HISTORY: TableDefinition<&str, &str> = TableDefinition::new("zrc20_tick_history");
+// Every ZRC-20 operation per ticker, "<ticker>:<height>:<seq>": the history rows plus
+// deploys, transfer inscriptions and metadata
+const ZRC20_EVENTS: TableDefinition<&str, &str> = TableDefinition::new("zrc20_events");
+const ZRC20_ADDRESS_HISTORY: TableDefinition<&str, &str> =
+    TableDefinition::new("zrc20_address_history");
+// Deploys refused by ZRC20_DEPLOY_POLICY, keyed "<tick>:<height>:<seq>"
+const ZRC20_REJECTED_DEPLOYS: TableDefinition<&str, &str> = TableDefinition::new("zrc20_rejected_deploys");
+// Outpoint ("<txid>:<vout>") currently carrying each name's inscription -> name_lower
+const NAME_OUTPOINTS: TableDefinition<&str, &str> = TableDefinition::new("name_outpoints");
+// Name ownership changes keyed "<name_lower>:<height>:<seq>"
+const NAME_HISTORY: TableDefinition<&str, &str> = TableDefinition::new("name_history");
+// ZRC-721 ownership moves keyed "<collection>#<token_id>:<height>:<seq>"
+const ZRC721_HISTORY: TableDefinition<&str, &str> = TableDefinition::new("zrc721_history");
+// Mints, transfers and burns across a collection keyed "<collection>:<height>:<seq>"
+const ZRC721_ACTIVITY: TableDefinition<&str, &str> = TableDefinition::new("zrc721_activity");
+// Unified wallet feed keyed "<address>:<height>:<seq>"; rows carry a `kind`
+const ADDRESS_ACTIVITY: TableDefinition<&str, &str> = TableDefinition::new("address_activity");
+// Subdomains under their parent name, keyed "<parent>:<height>:<seq>" -> subdomain name_lower
+const NAME_SUBDOMAINS: TableDefinition<&str, &str> = TableDefinition::new("name_subdomains");
+// Pending commit-reveal registrations, "<commitment hash>" -> {address, height, inscription_id}
+const NAME_COMMITMENTS: TableDefinition<&str, &str> = TableDefinition::new("name_commitments");
+// Inclusion proof of each name's registering transaction, name_lower -> {txid, block_hash, merkle_path, ...}
+const NAME_PROOFS: TableDefinition<&str, &str> = TableDefinition::new("name_proofs");
+// Confusable skeleton -> the first name_lower registered with it (see `names::name_skeleton`)
+const NAME_SKELETONS: TableDefinition<&str, &str> = TableDefinition::new("name_skeletons");
+// TLD-partitioned name index "<tld>:<label>" -> name_lower, so per-TLD feeds are range scans
+const NAME_TLDS: TableDefinition<&str, &str> = TableDefinition::new("name_tlds");
+// ZRC-20 balances at epoch ends, keyed "<tick>:<address>:<epoch height>" ("null" if absent).
+// The first epoch stores every balance; later epochs only the keys changed during them.
+const BALANCE_CHECKPOINTS: TableDefinition<&str, &str> =
+    TableDefinition::new("balance_checkpoints");
+// Merkle root over each ticker's positive balances at epoch ends, "<tick>:<epoch height>"
+const ZRC20_BALANCE_ROOTS: TableDefinition<&str, &str> = TableDefinition::new("zrc20_balance_roots");
+// Checksum of the whole ZRC-20 state at epoch ends, keyed by zero-padded epoch height
+const ZRC20_CHECKSUMS: TableDefinition<&str, &str> = TableDefinition::new("zrc20_checksums");
+// Blocks per balance checkpoint epoch
+const BALANCE_EPOCH: u64 = 1000;
+// Stats key holding the first checkpointed epoch; earlier heights cannot be reconstructed
+const CHECKPOINTS_SINCE_KEY: &str = "balance_checkpoints_since";
+
+// Full-text postings "<term>:<inscription id>" -> "" (only written with CONTENT_INDEX)
+const CONTENT_TERMS: TableDefinition<&str, &str> = TableDefinition::new("content_terms");
+
+// Feed facets "<facet>:<value>:<height>:<number>" and "size:<length>:<number>" -> inscription id
+const INSCRIPTION_FEED: TableDefinition<&str, &str> = TableDefinition::new("inscription_feed");
+
+// Names and ZRC-721 tokens by current owner: "<owner>:name:<name>" and
+// "<owner>:nft:<tick>#<token id>" -> "" (shielded owners are not indexed)
+const OWNER_HOLDINGS: TableDefinition<&str, &str> = TableDefinition::new("owner_holdings");
 
 // Ordinal number -> inscription id mapping
 const INSCRIPTION_NUMBERS: TableDefinition<u64, &str> = TableDefinition::new("inscription_numbers");
@@ -33,6 +130,11 @@ const INSCRIPTION_STATE: TableDefinition<&str, &str> = TableDefinition::new("ins
 // Simple aggregate counters and status values
 const STATS: TableDefinition<&str, u64> = TableDefinition::new("stats");
 const STATUS: TableDefinition<&str, u64> = TableDefinition::new("status");
+// Per-period activity counters keyed "<day|week>:<period>:<metric>", e.g.
+// "day:2026-10-16:inscriptions" or "week:2026-W42:category:png"
+const ACTIVITY_STATS: TableDefinition<&str, u64> = TableDefinition::new("activity_stats");
+// Addresses already counted as active in a period, keyed "<day|week>:<period>:<address>"
+const ACTIVE_ADDRESSES: TableDefinition<&str, &str> = TableDefinition::new("active_addresses");
 
 // ZNS backing store
 const NAMES: TableDefinition<&str, &str> = TableDefinition::new("names");
@@ -41,17 +143,254 @@ const ZRC721_COLLECTIONS: TableDefinition<&str, &str> =
 const ZRC721_TOKENS: TableDefinition<&str, &str> = TableDefinition::new("zrc721_tokens");
 const ZRC721_OUTPOINTS: TableDefinition<&str, &str> =
     TableDefinition::new("zrc721_outpoints");
+// Token traits from mint metadata, "<collection>:<trait type>:<value>:<token id>" -> ""
+const ZRC721_TRAITS: TableDefinition<&str, &str> = TableDefinition::new("zrc721_traits");
+// Tokens held per collection and owner, "<collection>:<owner>" -> count; empty tallies are
+// removed and shielded owners are not counted. The collection record keeps the row count as `owners`
+const ZRC721_OWNERS: TableDefinition<&str, &str> = TableDefinition::new("zrc721_owners");
+// Addresses of a collection's explicit mint allowlist, "<collection>:<address>" -> ""
+const ZRC721_ALLOWLIST: TableDefinition<&str, &str> = TableDefinition::new("zrc721_allowlist");
+// Token moves that paid the collection's royalty address, "<collection>:<height>:<seq>" -> sale JSON
+const ZRC721_SALES: TableDefinition<&str, &str> = TableDefinition::new("zrc721_sales");
+// Deployer amendments of a collection record, "<collection>:<height>:<seq>" -> {inscription_id,height,changes}
+const ZRC721_UPDATES: TableDefinition<&str, &str> = TableDefinition::new("zrc721_updates");
+
+// Legacy per-inscription payload table (schema v2); superseded by CONTENT_BLOBS
+const CONTENT: TableDefinition<&str, &[u8]> = TableDefinition::new("content");
+// Deduplicated payloads keyed by sha256 hex; metadata keeps only length/digest/type
+const CONTENT_BLOBS: TableDefinition<&str, &[u8]> = TableDefinition::new("content_blobs");
+// Number of inscriptions referencing each blob digest
+const CONTENT_REFS: TableDefinition<&str, u64> = TableDefinition::new("content_refs");
+
+// Rendered thumbnails keyed "<content sha256>:<edge>"; a disposable cache, never
+// journaled, snapshotted or compacted
+const THUMBNAILS: TableDefinition<&str, &[u8]> = TableDefinition::new("thumbnails");
+// IPFS objects fetched through IPFS_GATEWAY keyed "<cid>/<path>"; disposable like THUMBNAILS
+const IPFS_CACHE: TableDefinition<&str, &[u8]> = TableDefinition::new("ipfs_cache");
+// ZRC-721 rarity keyed "<collection>#<id>" plus a "<collection>" marker row; derived
+// by the rarity job, never journaled but snapshotted so replicas can serve it
+const ZRC721_RARITY: TableDefinition<&str, &str> = TableDefinition::new("zrc721_rarity");
+
+// Block height -> JSON list of inverse operations recorded while indexing that block
+const UNDO: TableDefinition<u64, &str> = TableDefinition::new("undo");
+
+// Webhook subscriptions keyed by id
+const WEBHOOKS: TableDefinition<&str, &str> = TableDefinition::new("webhooks");
+// Webhook deliveries that exhausted their retries, keyed "<unix ms>:<webhook id>"
+const WEBHOOK_DEAD_LETTERS: TableDefinition<&str, &str> =
+    TableDefinition::new("webhook_dead_letters");
+// Collections verified by the operator, "<collection>" -> {verified_at, note}
+const ZRC721_VERIFIED: TableDefinition<&str, &str> = TableDefinition::new("zrc721_verified");
+// Reserved names granted by the operator, "<name_lower>" -> {address, note, granted_at}
+const NAME_GRANTS: TableDefinition<&str, &str> = TableDefinition::new("name_grants");
+
+// Operator-managed tables: copied by snapshots, never journaled or rolled back
+const OPERATOR_TABLES: [TableDefinition<&str, &str>; 4] =
+    [WEBHOOKS, WEBHOOK_DEAD_LETTERS, ZRC721_VERIFIED, NAME_GRANTS];
+
+// String-keyed tables whose writes are journaled for rollback
+const UNDOABLE_TABLES: [TableDefinition<&str, &str>; 41] = [
+    INSCRIPTIONS,
+    TOKENS,
+    BALANCES,
+    TRANSFER_INSCRIPTIONS,
+    ZRC20_BURNS,
+    ZRC20_MINTS,
+    TRANSFER_OUTPOINTS,
+    ADDRESS_INSCRIPTIONS,
+    INSCRIPTION_STATE,
+    NAMES,
+    ZRC721_COLLECTIONS,
+    ZRC721_TOKENS,
+    ZRC721_OUTPOINTS,
+    ZRC721_TRAITS,
+    ZRC721_OWNERS,
+    ZRC721_ALLOWLIST,
+    ZRC721_SALES,
+    ZRC721_UPDATES,
+    ADDRESS_TRANSFERS,
+    ZRC20_TICK_HISTORY,
+    ZRC20_ADDRESS_HISTORY,
+    ZRC20_EVENTS,
+    ZRC20_REJECTED_DEPLOYS,
+    NAME_OUTPOINTS,
+    NAME_HISTORY,
+    ZRC721_HISTORY,
+    ZRC721_ACTIVITY,
+    ADDRESS_ACTIVITY,
+    NAME_TLDS,
+    NAME_SUBDOMAINS,
+    NAME_SKELETONS,
+    NAME_COMMITMENTS,
+    NAME_PROOFS,
+    BALANCE_CHECKPOINTS,
+    ZRC20_BALANCE_ROOTS,
+    ZRC20_CHECKSUMS,
+    CONTENT_TERMS,
+    INSCRIPTION_FEED,
+    OWNER_HOLDINGS,
+    BLOCK_HASHES,
+    ACTIVE_ADDRESSES,
+];
 
 #[derive(Clone)]
 /// Shared handle to the redb-backed state store.
 pub struct Db {
     db: Arc<Database>,
+    path: PathBuf,
+    read_only: bool,
+    cache: Arc<ReadCache>,
 }
 
+/// Inverse of a single write: the value the key held before the block touched it.
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum UndoOp {
+    /// `&str -> &str` tables (balances, names, tokens, NFT ownership, ...)
+    Text {
+        table: String,
+        key: String,
+        prev: Option<String>,
+    },
+    /// `&str -> u64` aggregate counters
+    Counter {
+        table: String,
+        key: String,
+        prev: Option<u64>,
+    },
+    /// `u64 -> &str` tables (blocks, inscription numbers)
+    Indexed {
+        table: String,
+        key: u64,
+        prev: Option<String>,
+    },
+    /// `&str -> &[u8]` blob tables, prior bytes hex-encoded
+    Bytes {
+        table: String,
+        key: String,
+        prev: Option<String>,
+    },
+    /// `item` appended to the JSON id list under `key`; undone by removing it again, so
+    /// growing a long list does not copy the whole list into every block's record
+    Appended {
+        table: String,
+        key: String,
+        item: String,
+    },
+}
+
+/// Status key holding the lowest height `rollback_to` can reach: blocks at or below
+/// it have no undo record (indexed before the journal existed, imported, or replayed).
+const UNDO_FLOOR_KEY: &str = "undo_floor";
+
+/// A rollback asked to unwind blocks that have no undo record. Retrying cannot help;
+/// the operator has to rebuild the index or restore an older snapshot.
+#[derive(Debug)]
+pub struct UndoHistoryMissing {
+    /// Lowest height the undo history can roll back to.
+    pub floor: u64,
+    /// Height the rollback asked for.
+    pub target: u64,
+}
+
+impl std::fmt::Display for UndoHistoryMissing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Undo history starts at height {}, so the index cannot roll back to {}; \
+             reindex (RE_INDEX=TRUE) or restore a snapshot taken at or below height {}",
+            self.floor, self.target, self.target
+        )
+    }
+}
+
+impl std::error::Error for UndoHistoryMissing {}
+
+/// Keyset position in a newest-first listing, taken from a key the caller already saw.
+/// Unlike page offsets, cursors stay stable while new rows land mid-scroll.
+#[derive(Clone, Copy)]
+pub enum Cursor<'a> {
+    /// Rows that follow `key` in listing order (older entries)
+    After(&'a str),
+    /// Rows that precede `key` in listing order (newer entries)
+    Before(&'a str),
+}
+
+/// Key-ordered tables that `Db::search_prefix` can range-scan.
+#[derive(Clone, Copy)]
+pub enum SearchIndex {
+    /// Inscription ids (`<txid>i<n>`), so txid prefixes match too
+    Inscriptions,
+    Tokens,
+    Names,
+    Collections,
+    /// Addresses that created inscriptions
+    Addresses,
+}
+
+/// What an address owns besides ZRC-20 balances, read from the owner index.
+#[derive(Debug, Default)]
+pub struct Holdings {
+    pub names: Vec<String>,
+    /// ZRC-721 collection tick -> tokens held
+    pub collections: std::collections::BTreeMap<String, u64>,
+}
+
+/// Royalty audit totals over every recorded sale of a ZRC-721 collection.
+#[derive(Debug, Default, serde::Serialize, utoipa::ToSchema)]
+pub struct RoyaltyTotals {
+    pub sales: u64,
+    /// Sales that paid at least the royalty due.
+    pub compliant_sales: u64,
+    pub volume_zat: u64,
+    pub royalty_paid_zat: u64,
+    pub royalty_due_zat: u64,
+}
+
+/// Storage used by one table, as reported by redb.
+#[derive(Clone, Debug, serde::Serialize, utoipa::ToSchema)]
+pub struct TableUsage {
+    pub name: String,
+    pub entries: u64,
+    /// Key and value bytes, excluding b-tree overhead
+    pub stored_bytes: u64,
+    pub metadata_bytes: u64,
+    pub fragmented_bytes: u64,
+}
+
+/// Storage report for the whole database file.
+#[derive(Clone, Debug, serde::Serialize, utoipa::ToSchema)]
+pub struct DbStats {
+    pub file_bytes: u64,
+    pub page_size: usize,
+    pub allocated_pages: u64,
+    pub stored_bytes: u64,
+    pub metadata_bytes: u64,
+    pub fragmented_bytes: u64,
+    /// File space not held by any allocated page; reclaimable by compaction
+    pub free_bytes: u64,
+    pub tables: Vec<TableUsage>,
+}
+
+/// Per-block activity counts, tallied from the block's undo record.
+#[derive(Debug, Default, serde::Serialize, utoipa::ToSchema)]
+pub struct BlockSummary {
+    pub height: u64,
+    pub hash: String,
+    pub inscriptions: u64,
+    pub token_deploys: u64,
+    /// ZRC-20 mints, transfers and burns
+    pub token_ops: u64,
+    pub name_registrations: u64,
+    pub nft_mints: u64,
+}
+
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
 pub struct Balance {
-    pub available: u128,
-    pub overall: u128,
+    #[serde(with = "amount::as_number")]
+    pub available: Amount,
+    #[serde(with = "amount::as_number")]
+    pub overall: Amount,
 }
 
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
@@ -79,17 +418,44 @@ impl Db {
             fs::remove_file(&path)?;
         }
 
+        let fresh = !path.exists();
         let db = Database::create(&path)?;
 
         let write_txn = db.begin_write()?;
         {
             write_txn.open_table(BLOCKS)?;
+            write_txn.open_table(BLOCK_HASHES)?;
             write_txn.open_table(INSCRIPTIONS)?;
             write_txn.open_table(TOKENS)?;
             write_txn.open_table(BALANCES)?;
             write_txn.open_table(TRANSFER_INSCRIPTIONS)?;
             write_txn.open_table(ZRC20_BURNS)?;
+            write_txn.open_table(ZRC20_MINTS)?;
             write_txn.open_table(TRANSFER_OUTPOINTS)?;
+            write_txn.open_table(ADDRESS_TRANSFERS)?;
+            write_txn.open_table(ZRC20_TICK_HISTORY)?;
+            write_txn.open_table(ZRC20_EVENTS)?;
+            write_txn.open_table(ZRC20_REJECTED_DEPLOYS)?;
+            write_txn.open_table(ZRC20_ADDRESS_HISTORY)?;
+            write_txn.open_table(NAME_OUTPOINTS)?;
+            write_txn.open_table(NAME_HISTORY)?;
+            write_txn.open_table(ZRC721_HISTORY)?;
+            write_txn.open_table(ZRC721_ACTIVITY)?;
+            write_txn.open_table(ADDRESS_ACTIVITY)?;
+            write_txn.open_table(NAME_TLDS)?;
+            write_txn.open_table(NAME_SUBDOMAINS)?;
+            write_txn.open_table(NAME_SKELETONS)?;
+            write_txn.open_table(NAME_COMMITMENTS)?;
+            write_txn.open_table(NAME_PROOFS)?;
+            write_txn.open_table(BALANCE_CHECKPOINTS)?;
+            write_txn.open_table(ZRC20_BALANCE_ROOTS)?;
+            write_txn.open_table(ZRC20_CHECKSUMS)?;
+            write_txn.open_table(CONTENT_TERMS)?;
+            write_txn.open_table(THUMBNAILS)?;
+            write_txn.open_table(IPFS_CACHE)?;
+            write_txn.open_table(ZRC721_RARITY)?;
+            write_txn.open_table(INSCRIPTION_FEED)?;
+            write_txn.open_table(OWNER_HOLDINGS)?;
             write_txn.open_table(INSCRIPTION_STATE)?;
             write_txn.open_table(INSCRIPTION_NUMBERS)?;
             write_txn.open_table(ADDRESS_INSCRIPTIONS)?;
@@ -99,118 +465,433 @@ impl Db {
             write_txn.open_table(ZRC721_COLLECTIONS)?;
             write_txn.open_table(ZRC721_TOKENS)?;
             write_txn.open_table(ZRC721_OUTPOINTS)?;
+            write_txn.open_table(ZRC721_TRAITS)?;
+            write_txn.open_table(ZRC721_OWNERS)?;
+            write_txn.open_table(ZRC721_ALLOWLIST)?;
+            write_txn.open_table(ZRC721_SALES)?;
+            write_txn.open_table(ZRC721_UPDATES)?;
+            write_txn.open_table(CONTENT_BLOBS)?;
+            write_txn.open_table(CONTENT_REFS)?;
+            write_txn.open_table(ACTIVITY_STATS)?;
+            write_txn.open_table(ACTIVE_ADDRESSES)?;
+            write_txn.open_table(UNDO)?;
+            write_txn.open_table(WEBHOOKS)?;
+            write_txn.open_table(WEBHOOK_DEAD_LETTERS)?;
+            write_txn.open_table(ZRC721_VERIFIED)?;
+            write_txn.open_table(NAME_GRANTS)?;
         }
         write_txn.commit()?;
 
-        Ok(Self { db: Arc::new(db) })
+        migrations::run(&db, fresh)?;
+
+        Ok(Self {
+            db: Arc::new(db),
+            path,
+            read_only: false,
+            cache: Arc::new(ReadCache::new()),
+        })
     }
 
-    pub fn get_latest_indexed_height(&self) -> Result<Option<u64>> {
-        let read_txn = self.db.begin_read()?;
-        let table = read_txn.open_table(BLOCKS)?;
-        let result = match table.last()? {
-            Some((k, _)) => Some(k.value()),
-            None => None,
+    /// Open an existing database for serving reads only (API replicas).
+    /// Tables are neither created nor migrated, and every write is rejected.
+    /// redb still takes its exclusive file lock, so this fails while any other process
+    /// has the file open; each replica needs its own copy (e.g. a snapshot).
+    pub fn open_read_only(path: impl AsRef<Path>) -> Result<Self> {
+        let db = Database::open(path.as_ref())?;
+        let db = Self {
+            db: Arc::new(db),
+            path: path.as_ref().to_path_buf(),
+            read_only: true,
+            cache: Arc::new(ReadCache::new()),
         };
-        Ok(result)
-    }
 
-    pub fn insert_block(&self, height: u64, hash: &str) -> Result<()> {
-        let write_txn = self.db.begin_write()?;
-        {
-            let mut table = write_txn.open_table(BLOCKS)?;
-            table.insert(height, hash)?;
+        // A replica cannot migrate, so the file must already match this binary
+        let version = db.schema_version()?.unwrap_or(0);
+        if version != migrations::current_version() {
+            return Err(anyhow::anyhow!(
+                "Database schema v{} does not match this binary (v{}); migrate it with a writer first",
+                version,
+                migrations::current_version()
+            ));
+        }
+        Ok(db)
+    }
 
-            let mut status = write_txn.open_table(STATUS)?;
-            status.insert("core_height", height)?;
+    fn begin_write(&self) -> Result<WriteTransaction<'_>> {
+        if self.read_only {
+            return Err(anyhow::anyhow!("Database is open read-only"));
         }
+        Ok(self.db.begin_write()?)
+    }
+
+    // Commit and drop cached reads so they never outlive the state they came from
+    fn commit(&self, write_txn: WriteTransaction) -> Result<()> {
         write_txn.commit()?;
+        self.cache.invalidate();
         Ok(())
     }
 
-    pub fn insert_inscription(&self, id: &str, data: &str) -> Result<()> {
-        let write_txn = self.db.begin_write()?;
-        {
-            let mut table = write_txn.open_table(INSCRIPTIONS)?;
-            table.insert(id, data)?;
-
-            // Maintain monotonic inscription numbering for API lookups
-            let mut stats = write_txn.open_table(STATS)?;
-            let count = stats
-                .get("inscription_count")?
-                .map(|v| v.value())
-                .unwrap_or(0);
-            let number = count + 1;
-            stats.insert("inscription_count", number)?;
-
-            let mut numbers = write_txn.open_table(INSCRIPTION_NUMBERS)?;
-            numbers.insert(number, id)?;
-
-            // Index sender so `/address/:addr/inscriptions` can return results
-            if let Ok(json) = serde_json::from_str::<serde_json::Value>(data) {
-                if let Some(sender) = json["sender"].as_str() {
-                    let mut addr_index = write_txn.open_table(ADDRESS_INSCRIPTIONS)?;
-                    let mut list = if let Some(existing) = addr_index.get(sender)? {
-                        serde_json::from_str::<Vec<String>>(existing.value()).unwrap_or_default()
-                    } else {
-                        Vec::new()
-                    };
-                    list.push(id.to_string());
-                    addr_index.insert(sender, serde_json::to_string(&list)?.as_str())?;
-                }
-                // Receiver tracking is future work; today we key by sender only
+    /// Write a consistent copy of the database to `path` while the indexer keeps running.
+    /// Every table is read from one snapshot transaction, so the copy reflects a single
+    /// committed state. The file is written beside `path` and renamed into place on success.
+    /// Returns the number of rows copied.
+    pub fn export_snapshot(&self, path: impl AsRef<Path>) -> Result<u64> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent)?;
             }
         }
-        write_txn.commit()?;
-        Ok(())
+        let tmp = path.with_extension("partial");
+        if tmp.exists() {
+            fs::remove_file(&tmp)?;
+        }
+
+        let read_txn = self.db.begin_read()?;
+        let rows = {
+            let target = Database::create(&tmp)?;
+            let write_txn = target.begin_write()?;
+            let mut rows = 0;
+            rows += copy_table(&read_txn, &write_txn, BLOCKS)?;
+            rows += copy_table(&read_txn, &write_txn, INSCRIPTION_NUMBERS)?;
+            rows += copy_table(&read_txn, &write_txn, STATS)?;
+            rows += copy_table(&read_txn, &write_txn, STATUS)?;
+            rows += copy_table(&read_txn, &write_txn, CONTENT_BLOBS)?;
+            rows += copy_table(&read_txn, &write_txn, CONTENT_REFS)?;
+            rows += copy_table(&read_txn, &write_txn, ACTIVITY_STATS)?;
+            rows += copy_table(&read_txn, &write_txn, UNDO)?;
+            rows += copy_table(&read_txn, &write_txn, ZRC721_RARITY)?;
+            for def in UNDOABLE_TABLES.into_iter().chain(OPERATOR_TABLES) {
+                rows += copy_table(&read_txn, &write_txn, def)?;
+            }
+            write_txn.commit()?;
+            rows
+        };
+        drop(read_txn);
+
+        fs::rename(&tmp, path)?;
+        tracing::info!("Exported snapshot to {:?} ({} rows)", path, rows);
+        Ok(rows)
     }
 
-    pub fn get_inscriptions_page(
+    /// Rewrite the database at `path` into a fresh file and rename it into place, dropping
+    /// free and fragmented pages. Nothing else may have the file open. Returns the file size
+    /// before and after.
+    pub fn compact(path: impl AsRef<Path>) -> Result<(u64, u64)> {
+        let path = path.as_ref();
+        let before = fs::metadata(path)?.len();
+        let fresh = path.with_extension("compact");
+        {
+            let db = Self::new(path, false)?;
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+            // Stamped before the copy so the new file carries it
+            db.set_status(LAST_COMPACTION_KEY, now)?;
+            db.export_snapshot(&fresh)?;
+        }
+        fs::rename(&fresh, path)?;
+        let after = fs::metadata(path)?.len();
+        tracing::info!(
+            "Compacted {:?}: {} -> {} bytes ({} reclaimed)",
+            path,
+            before,
+            after,
+            before.saturating_sub(after)
+        );
+        Ok((before, after))
+    }
+
+    /// Compact `path` before it is opened when the last compaction is older than `max_age`
+    /// (or never ran). Nothing compacts a running process; this only runs at startup.
+    pub fn compact_on_startup(path: impl AsRef<Path>, max_age: std::time::Duration) -> Result<Option<(u64, u64)>> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(None);
+        }
+        let last = Self::new(path, false)?.get_status(LAST_COMPACTION_KEY)?;
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        if last.is_some_and(|t| now.saturating_sub(t) < max_age.as_secs()) {
+            return Ok(None);
+        }
+        Self::compact(path).map(Some)
+    }
+
+    /// Per-table entry counts and sizes plus file-level page usage.
+    /// Walks every table, so it is slow on large databases; callers should cache it.
+    pub fn stats(&self) -> Result<DbStats> {
+        let read_txn = self.db.begin_read()?;
+        let mut tables = vec![
+            table_usage(&read_txn, BLOCKS)?,
+            table_usage(&read_txn, INSCRIPTION_NUMBERS)?,
+            table_usage(&read_txn, STATS)?,
+            table_usage(&read_txn, STATUS)?,
+            table_usage(&read_txn, CONTENT_BLOBS)?,
+            table_usage(&read_txn, CONTENT_REFS)?,
+            table_usage(&read_txn, ACTIVITY_STATS)?,
+            table_usage(&read_txn, UNDO)?,
+            table_usage(&read_txn, THUMBNAILS)?,
+            table_usage(&read_txn, IPFS_CACHE)?,
+            table_usage(&read_txn, ZRC721_RARITY)?,
+        ];
+        for def in UNDOABLE_TABLES.into_iter().chain(OPERATOR_TABLES) {
+            tables.push(table_usage(&read_txn, def)?);
+        }
+        drop(read_txn);
+        tables.sort_by_key(|t| std::cmp::Reverse(t.stored_bytes + t.metadata_bytes));
+
+        // redb only reports file-level stats from a write transaction; it is aborted unused
+        let write_txn = self.db.begin_write()?;
+        let db_stats = write_txn.stats()?;
+        write_txn.abort()?;
+
+        let file_bytes = fs::metadata(&self.path).map(|m| m.len()).unwrap_or(0);
+        let allocated_bytes = db_stats.allocated_pages() * db_stats.page_size() as u64;
+        Ok(DbStats {
+            file_bytes,
+            page_size: db_stats.page_size(),
+            allocated_pages: db_stats.allocated_pages(),
+            stored_bytes: db_stats.stored_bytes(),
+            metadata_bytes: db_stats.metadata_bytes(),
+            fragmented_bytes: db_stats.fragmented_bytes(),
+            free_bytes: file_bytes.saturating_sub(allocated_bytes),
+            tables,
+        })
+    }
+
+    /// Schema version the open database has been migrated to.
+    pub fn schema_version(&self) -> Result<Option<u64>> {
+        self.get_status(migrations::SCHEMA_VERSION_KEY)
+    }
+
+    pub fn get_block_hash(&self, height: u64) -> Result<Option<String>> {
+        let read_txn = self.db.begin_read()?;
+        let table = read_txn.open_table(BLOCKS)?;
+        let val = table.get(height)?.map(|v| v.value().to_string());
+        Ok(val)
+    }
+
+    /// Height of an indexed block by hash.
+    pub fn get_block_height(&self, hash: &str) -> Result<Option<u64>> {
+        let read_txn = self.db.begin_read()?;
+        let table = read_txn.open_table(BLOCK_HASHES)?;
+        let val = table.get(hash)?.and_then(|v| v.value().parse().ok());
+        Ok(val)
+    }
+
+    /// Newest-first indexed blocks, starting below `before` when given, else at `page`.
+    pub fn list_block_summaries(
         &self,
         page: usize,
         limit: usize,
-    ) -> Result<Vec<(String, String)>> {
-        let offset = page.saturating_mul(limit);
+        before: Option<u64>,
+    ) -> Result<Vec<BlockSummary>> {
         let read_txn = self.db.begin_read()?;
-        let table = read_txn.open_table(INSCRIPTIONS)?;
-        let mut items = Vec::new();
+        let blocks = read_txn.open_table(BLOCKS)?;
+        let undo = read_txn.open_table(UNDO)?;
+        let heights: Vec<(u64, String)> = match before {
+            Some(height) => blocks
+                .range(..height)?
+                .rev()
+                .take(limit)
+                .map(|item| item.map(|(k, v)| (k.value(), v.value().to_string())))
+                .collect::<Result<_, _>>()?,
+            None => blocks
+                .iter()?
+                .rev()
+                .skip(page.saturating_mul(limit))
+                .take(limit)
+                .map(|item| item.map(|(k, v)| (k.value(), v.value().to_string())))
+                .collect::<Result<_, _>>()?,
+        };
 
-        for item in table.iter()?.rev().skip(offset).take(limit) {
-            let (k, v) = item?;
-            items.push((k.value().to_string(), v.value().to_string()));
+        let mut summaries = Vec::with_capacity(heights.len());
+        for (height, hash) in heights {
+            let mut summary = BlockSummary { height, hash, ..Default::default() };
+            let ops: Vec<UndoOp> = match undo.get(height)? {
+                Some(raw) => serde_json::from_str(raw.value())?,
+                None => Vec::new(),
+            };
+            for op in ops {
+                let UndoOp::Text { table, prev, .. } = op else {
+                    continue;
+                };
+                // A key written with no previous value is a new row
+                let created = prev.is_none();
+                match table.as_str() {
+                    t if t == INSCRIPTIONS.name() && created => summary.inscriptions += 1,
+                    t if t == TOKENS.name() && created => summary.token_deploys += 1,
+                    t if t == ZRC20_TICK_HISTORY.name() => summary.token_ops += 1,
+                    t if t == NAMES.name() && created => summary.name_registrations += 1,
+                    t if t == ZRC721_TOKENS.name() && created => summary.nft_mints += 1,
+                    _ => {}
+                }
+            }
+            summaries.push(summary);
         }
+        Ok(summaries)
+    }
 
-        Ok(items)
+    /// Number of indexed blocks.
+    pub fn get_block_count(&self) -> Result<u64> {
+        let read_txn = self.db.begin_read()?;
+        let table = read_txn.open_table(BLOCKS)?;
+        let count = table.len()?;
+        Ok(count)
     }
 
-    // Token operations
-    pub fn deploy_token(&self, ticker: &str, info: &str) -> Result<()> {
-        let write_txn = self.db.begin_write()?;
+    /// `(id, metadata)` of the inscriptions revealed in the block at `height`, in
+    /// numbering order.
+    pub fn list_block_inscriptions(&self, height: u64) -> Result<Vec<(String, String)>> {
+        let read_txn = self.db.begin_read()?;
+        let feed = read_txn.open_table(INSCRIPTION_FEED)?;
+        let inscriptions = read_txn.open_table(INSCRIPTIONS)?;
+        let start = format!("height:{:010}:", height);
+        let end = format!("height:{:010};", height);
+        let mut rows = Vec::new();
+        for item in feed.range::<&str>(start.as_str()..end.as_str())? {
+            let (_, v) = item?;
+            if let Some(meta) = inscriptions.get(v.value())? {
+                rows.push((v.value().to_string(), meta.value().to_string()));
+            }
+        }
+        Ok(rows)
+    }
+
+    /// `(id, metadata)` of the inscriptions revealed by `txid`, in envelope order.
+    pub fn list_tx_inscriptions(&self, txid: &str) -> Result<Vec<(String, String)>> {
+        let read_txn = self.db.begin_read()?;
+        let table = read_txn.open_table(INSCRIPTIONS)?;
+        // Ids are "<txid>i<index>"; 'j' bounds every index of the transaction
+        let start = format!("{}i", txid);
+        let end = format!("{}j", txid);
+        let mut rows = Vec::new();
+        for item in table.range::<&str>(start.as_str()..end.as_str())? {
+            let (k, v) = item?;
+            rows.push((k.value().to_string(), v.value().to_string()));
+        }
+        rows.sort_by_key(|(id, _)| id[start.len()..].parse::<u64>().unwrap_or(u64::MAX));
+        Ok(rows)
+    }
+
+    /// Unwind every block above `height` by replaying its undo record in reverse.
+    /// Runs in a single write transaction, so a missing record leaves state untouched.
+    /// Returns the number of blocks removed.
+    pub fn rollback_to(&self, height: u64) -> Result<u64> {
+        let write_txn = self.begin_write()?;
+        let mut unwound = 0;
         {
-            let mut table = write_txn.open_table(TOKENS)?;
-            if table.get(ticker)?.is_some() {
-                return Err(anyhow::anyhow!("Token already exists"));
+            let tip = write_txn
+                .open_table(BLOCKS)?
+                .last()?
+                .map(|(k, _)| k.value());
+            let tip = match tip {
+                Some(tip) if tip > height => tip,
+                _ => return Ok(0),
+            };
+
+            // Refuse up front rather than unwind part of the range and fail midway
+            let floor = write_txn.open_table(STATUS)?.get(UNDO_FLOOR_KEY)?.map(|v| v.value());
+            if let Some(floor) = floor.filter(|floor| height < *floor) {
+                return Err(UndoHistoryMissing { floor, target: height }.into());
+            }
+
+            for block in (height + 1..=tip).rev() {
+                let raw = write_txn
+                    .open_table(UNDO)?
+                    .remove(block)?
+                    .map(|v| v.value().to_string())
+                    .ok_or(UndoHistoryMissing { floor: block, target: height })?;
+                let ops: Vec<UndoOp> = serde_json::from_str(&raw)?;
+                for op in ops.iter().rev() {
+                    apply_undo(&write_txn, op)?;
+                }
+                unwound += 1;
             }
-            table.insert(ticker, info)?;
 
-            let mut stats = write_txn.open_table(STATS)?;
-            let count = stats.get("token_count")?.map(|v| v.value()).unwrap_or(0);
-            stats.insert("token_count", count + 1)?;
+            let mut status = write_txn.open_table(STATUS)?;
+            for key in ["core_height", "zrc20_height", "names_height", "zrc721_height"] {
+                status.insert(key, height)?;
+            }
+            // Belonged to an unwound block; the next indexed block sets it again
+            status.remove("tip_time")?;
         }
-        write_txn.commit()?;
-        Ok(())
+        self.commit(write_txn)?;
+
+        tracing::warn!("Rolled back {} blocks to height {}", unwound, height);
+        Ok(unwound)
     }
 
-    pub fn get_tokens_page(&self, page: usize, limit: usize) -> Result<Vec<(String, String)>> {
-        let offset = page.saturating_mul(limit);
+    pub fn get_latest_indexed_height(&self) -> Result<Option<u64>> {
+        if let Some(height) = self.cache.latest_height() {
+            return Ok(height);
+        }
+        let generation = self.cache.generation();
         let read_txn = self.db.begin_read()?;
-        let table = read_txn.open_table(TOKENS)?;
-        let mut tokens = Vec::new();
-        for item in table.iter()?.rev().skip(offset).take(limit) {
-            let (k, v) = item?;
-            tokens.push((k.value().to_string(), v.value().to_string()));
+        let table = read_txn.open_table(BLOCKS)?;
+        let result = table.last()?.map(|(k, _)| k.value());
+        self.cache.put_latest_height(generation, result);
+        Ok(result)
+    }
+
+    pub fn get_inscriptions_page(
+        &self,
+        page: usize,
+        limit: usize,
+        cursor: Option<Cursor>,
+    ) -> Result<Vec<(String, String)>> {
+        self.page_desc(INSCRIPTIONS, page, limit, cursor)
+    }
+
+    // Newest-first page of a string-keyed table: by cursor when given, else by offset
+    fn page_desc(
+        &self,
+        def: TableDefinition<&str, &str>,
+        page: usize,
+        limit: usize,
+        cursor: Option<Cursor>,
+    ) -> Result<Vec<(String, String)>> {
+        let read_txn = self.db.begin_read()?;
+        let table = read_txn.open_table(def)?;
+        let mut items = Vec::new();
+        match cursor {
+            None => {
+                let offset = page.saturating_mul(limit);
+                for item in table.iter()?.rev().skip(offset).take(limit) {
+                    let (k, v) = item?;
+                    items.push((k.value().to_string(), v.value().to_string()));
+                }
+            }
+            Some(Cursor::After(key)) => {
+                for item in table.range::<&str>(..key)?.rev().take(limit) {
+                    let (k, v) = item?;
+                    items.push((k.value().to_string(), v.value().to_string()));
+                }
+            }
+            Some(Cursor::Before(key)) => {
+                // Walk forward from the cursor, then flip back into listing order
+                let bounds: (Bound<&str>, Bound<&str>) = (Bound::Excluded(key), Bound::Unbounded);
+                for item in table.range::<&str>(bounds)?.take(limit) {
+                    let (k, v) = item?;
+                    items.push((k.value().to_string(), v.value().to_string()));
+                }
+                items.reverse();
+            }
         }
-        Ok(tokens)
+        Ok(items)
+    }
+
+    pub fn get_tokens_page(
+        &self,
+        page: usize,
+        limit: usize,
+        cursor: Option<Cursor>,
+    ) -> Result<Vec<(String, String)>> {
+        self.page_desc(TOKENS, page, limit, cursor)
     }
 
     pub fn search_tokens(&self, query: &str, limit: usize) -> Result<Vec<(String, String)>> {
@@ -232,81 +913,30 @@ impl Db {
         Ok(tokens)
     }
 
-    pub fn get_token_info(&self, ticker: &str) -> Result<Option<String>> {
+    /// Up to `limit` rows whose key starts with `prefix`, in key order.
+    pub fn search_prefix(&self, index: SearchIndex, prefix: &str, limit: usize) -> Result<Vec<(String, String)>> {
+        let def = match index {
+            SearchIndex::Inscriptions => INSCRIPTIONS,
+            SearchIndex::Tokens => TOKENS,
+            SearchIndex::Names => NAMES,
+            SearchIndex::Collections => ZRC721_COLLECTIONS,
+            SearchIndex::Addresses => ADDRESS_INSCRIPTIONS,
+        };
         let read_txn = self.db.begin_read()?;
-        let table = read_txn.open_table(TOKENS)?;
-        let val = table.get(ticker)?.map(|v| v.value().to_string());
-        Ok(val)
-    }
-
-    pub fn update_token_supply(&self, ticker: &str, new_supply: u128) -> Result<()> {
-        let write_txn = self.db.begin_write()?;
-        {
-            let mut table = write_txn.open_table(TOKENS)?;
-            let info_str = table
-                .get(ticker)?
-                .ok_or(anyhow::anyhow!("Token not found"))?
-                .value()
-                .to_string();
-
-            let mut info: serde_json::Value = serde_json::from_str(&info_str)?;
-            info["supply"] = serde_json::Value::String(new_supply.to_string());
-            table.insert(ticker, info.to_string().as_str())?;
+        let table = read_txn.open_table(def)?;
+        let mut rows = Vec::new();
+        for item in table.range::<&str>(prefix..)? {
+            let (k, v) = item?;
+            if !k.value().starts_with(prefix) || rows.len() >= limit {
+                break;
+            }
+            rows.push((k.value().to_string(), v.value().to_string()));
         }
-        write_txn.commit()?;
-        Ok(())
+        Ok(rows)
     }
 
-    /// Atomically credit a mint: increase token supply and holder balance
-    /// in a single write transaction to prevent supply/balance drift.
-    pub fn mint_credit_atomic(&self, ticker: &str, address: &str, amt: u128) -> Result<()> {
-        let write_txn = self.db.begin_write()?;
-        {
-            // Update token supply
-            let mut tokens = write_txn.open_table(TOKENS)?;
-            let info_str = tokens
-                .get(ticker)?
-                .ok_or(anyhow::anyhow!("Token not found"))?
-                .value()
-                .to_string();
-            let mut info: serde_json::Value = serde_json::from_str(&info_str)?;
-            let current_supply: u128 = info["supply"]
-                .as_str()
-                .and_then(|s| s.parse::<u128>().ok())
-                .unwrap_or(0);
-            let new_supply = current_supply
-                .checked_add(amt)
-                .ok_or_else(|| anyhow::anyhow!("Supply overflow"))?;
-            info["supply"] = serde_json::Value::String(new_supply.to_string());
-            tokens.insert(ticker, info.to_string().as_str())?;
-
-            // Update holder balance (available and overall)
-            let mut balances = write_txn.open_table(BALANCES)?;
-            let key = format!("{}:{}", address, ticker);
-            let current = if let Some(val) = balances.get(key.as_str())? {
-                serde_json::from_str::<Balance>(val.value())?
-            } else {
-                Balance {
-                    available: 0,
-                    overall: 0,
-                }
-            };
-
-            let next_available = (current.available as u128)
-                .checked_add(amt)
-                .ok_or_else(|| anyhow::anyhow!("Available balance overflow"))?;
-            let next_overall = (current.overall as u128)
-                .checked_add(amt)
-                .ok_or_else(|| anyhow::anyhow!("Overall balance overflow"))?;
-
-            let new_balance = Balance {
-                available: next_available,
-                overall: next_overall,
-            };
-            balances.insert(key.as_str(), serde_json::to_string(&new_balance)?.as_str())?;
-        }
-        write_txn.commit()?;
-        Ok(())
+    pub fn get_token_info(&self, ticker: &str) -> Result<Option<String>> {
+        self.cached_get(TOKENS, ticker)
     }
 
     // Balance helpers (available vs overall mirrors BRC-20 semantics)
@@ -318,64 +948,12 @@ impl Db {
         let balance = if let Some(val) = table.get(key.as_str())? {
             serde_json::from_str::<Balance>(val.value())?
         } else {
-            Balance {
-                available: 0,
-                overall: 0,
-            }
+            Balance::default()
         };
         Ok(balance)
     }
 
-    pub fn update_balance(
-        &self,
-        address: &str,
-        ticker: &str,
-        available_delta: i128,
-        overall_delta: i128,
-    ) -> Result<()> {
-        let key = format!("{}:{}", address, ticker);
-        let write_txn = self.db.begin_write()?;
-        {
-            let mut table = write_txn.open_table(BALANCES)?;
-            let current = if let Some(val) = table.get(key.as_str())? {
-                serde_json::from_str::<Balance>(val.value())?
-            } else {
-                Balance {
-                    available: 0,
-                    overall: 0,
-                }
-            };
-
-            let next_available = (current.available as i128)
-                .checked_add(available_delta)
-                .ok_or_else(|| anyhow::anyhow!("Available balance overflow"))?;
-            if next_available < 0 {
-                return Err(anyhow::anyhow!("Insufficient available balance"));
-            }
-
-            let next_overall = (current.overall as i128)
-                .checked_add(overall_delta)
-                .ok_or_else(|| anyhow::anyhow!("Overall balance overflow"))?;
-            if next_overall < 0 {
-                return Err(anyhow::anyhow!("Insufficient overall balance"));
-            }
-
-            let new_balance = Balance {
-                available: next_available as u128,
-                overall: next_overall as u128,
-            };
-
-            // Prune storage for true zero rows to keep holder counts tidy
-            if new_balance.available == 0 && new_balance.overall == 0 {
-                let _ = table.remove(key.as_str());
-            } else {
-                table.insert(key.as_str(), serde_json::to_string(&new_balance)?.as_str())?;
-            }
-        }
-        write_txn.commit()?;
-        Ok(())
-    }
-
+    #[allow(dead_code)]
     pub fn list_balances_for_tick(
         &self,
         tick: &str,
@@ -397,7 +975,7 @@ impl Db {
                 }
             }
         }
-        rows.sort_by(|a, b| b.1.overall.cmp(&a.1.overall));
+        rows.sort_by_key(|row| std::cmp::Reverse(row.1.overall));
         let total = rows.len();
         let page_rows = rows.into_iter().skip(offset).take(limit).collect();
         Ok((page_rows, total))
@@ -405,6 +983,7 @@ impl Db {
 
     /// List balances for a ticker with optional positive-only filter.
     /// Returns (rows(page-limited), total_all_rows, total_positive_rows).
+    #[allow(clippy::type_complexity)]
     pub fn list_balances_for_tick_filtered(
         &self,
         tick: &str,
@@ -413,29 +992,110 @@ impl Db {
         positive_only: bool,
     ) -> Result<(Vec<(String, Balance)>, usize, usize)> {
         let needle = tick.to_lowercase();
-        let offset = page.saturating_mul(limit);
         let read_txn = self.db.begin_read()?;
         let table = read_txn.open_table(BALANCES)?;
         let mut rows: Vec<(String, Balance)> = Vec::new();
-        let mut total_all: usize = 0;
-        let mut total_positive: usize = 0;
         for item in table.iter()? {
             let (k, v) = item?;
             let key = k.value();
             if let Some((address, token)) = key.split_once(':') {
                 if token == needle {
-                    let bal = serde_json::from_str::<Balance>(v.value())?;
-                    total_all += 1;
-                    if bal.overall > 0 { total_positive += 1; }
-                    if !positive_only || bal.overall > 0 {
-                        rows.push((address.to_string(), bal));
+                    rows.push((address.to_string(), serde_json::from_str::<Balance>(v.value())?));
+                }
+            }
+        }
+        Ok(page_balances(rows, page, limit, positive_only))
+    }
+
+    /// Holder balances for `tick` as they stood after block `height`.
+    /// Starts from the checkpoint at the end of the enclosing epoch (or the live table near
+    /// the tip) and rewinds with undo records, so at most one epoch of blocks is replayed.
+    #[allow(clippy::type_complexity)]
+    pub fn list_balances_for_tick_at(
+        &self,
+        tick: &str,
+        height: u64,
+        page: usize,
+        limit: usize,
+        positive_only: bool,
+    ) -> Result<(Vec<(String, Balance)>, usize, usize)> {
+        let needle = tick.to_lowercase();
+        let read_txn = self.db.begin_read()?;
+        let tip = read_txn.open_table(BLOCKS)?.last()?.map(|(k, _)| k.value()).unwrap_or(0);
+        if height >= tip {
+            drop(read_txn);
+            return self.list_balances_for_tick_filtered(&needle, page, limit, positive_only);
+        }
+
+        // address -> raw balance JSON at `end`
+        let mut state: std::collections::BTreeMap<String, String> = Default::default();
+        let epoch_end = height.div_ceil(BALANCE_EPOCH) * BALANCE_EPOCH;
+        let end = if epoch_end <= tip {
+            let since = read_txn.open_table(STATS)?.get(CHECKPOINTS_SINCE_KEY)?.map(|v| v.value());
+            if since.is_none_or(|since| since > epoch_end) {
+                return Err(anyhow::anyhow!("No balance checkpoint covers height {}", height));
+            }
+            let checkpoints = read_txn.open_table(BALANCE_CHECKPOINTS)?;
+            let start = format!("{}:", needle);
+            let stop = format!("{};", needle);
+            // Rows sort by address then epoch, so the last one at or below the epoch wins
+            for item in checkpoints.range::<&str>(start.as_str()..stop.as_str())? {
+                let (k, v) = item?;
+                let Some((address, epoch)) = k.value()[start.len()..].rsplit_once(':') else {
+                    continue;
+                };
+                if epoch.parse::<u64>().is_ok_and(|e| e <= epoch_end) {
+                    state.insert(address.to_string(), v.value().to_string());
+                }
+            }
+            epoch_end
+        } else {
+            let balances = read_txn.open_table(BALANCES)?;
+            for item in balances.iter()? {
+                let (k, v) = item?;
+                if let Some((address, token)) = k.value().split_once(':') {
+                    if token == needle {
+                        state.insert(address.to_string(), v.value().to_string());
                     }
                 }
             }
+            tip
+        };
+
+        // The first record after `height` that touched a key holds its value at `height`
+        let undo = read_txn.open_table(UNDO)?;
+        let mut seen = std::collections::HashSet::new();
+        for h in height + 1..=end {
+            let record = undo
+                .get(h)?
+                .ok_or_else(|| anyhow::anyhow!("Undo history missing at height {}", h))?;
+            for op in serde_json::from_str::<Vec<UndoOp>>(record.value())? {
+                let UndoOp::Text { table, key, prev } = op else {
+                    continue;
+                };
+                if table != BALANCES.name() || !seen.insert(key.clone()) {
+                    continue;
+                }
+                let Some((address, token)) = key.split_once(':') else {
+                    continue;
+                };
+                if token != needle {
+                    continue;
+                }
+                match prev {
+                    Some(raw) => state.insert(address.to_string(), raw),
+                    None => state.remove(address),
+                };
+            }
         }
-        rows.sort_by(|a, b| b.1.overall.cmp(&a.1.overall));
-        let page_rows = rows.into_iter().skip(offset).take(limit).collect();
-        Ok((page_rows, total_all, total_positive))
+
+        let mut rows = Vec::with_capacity(state.len());
+        for (address, raw) in state {
+            if raw != "null" {
+                rows.push((address, serde_json::from_str::<Balance>(&raw)?));
+            }
+        }
+        Ok(page_balances(rows, page, limit, positive_only))
     }
 
     /// Sum balances for a given ticker across all addresses.
@@ -455,13 +1115,13 @@ impl Db {
                 if token == needle {
                     let bal = serde_json::from_str::<Balance>(v.value())?;
                     sum_overall = sum_overall
-                        .checked_add(bal.overall)
+                        .checked_add(bal.overall.base_units())
                         .ok_or_else(|| anyhow::anyhow!("overall sum overflow"))?;
                     sum_available = sum_available
-                        .checked_add(bal.available)
+                        .checked_add(bal.available.base_units())
                         .ok_or_else(|| anyhow::anyhow!("available sum overflow"))?;
                     total_rows += 1;
-                    if bal.overall > 0 {
+                    if !bal.overall.is_zero() {
                         holders_positive += 1;
                     }
                 }
@@ -470,33 +1130,113 @@ impl Db {
         Ok((sum_overall, sum_available, total_rows, holders_positive))
     }
 
-    pub fn add_burned(&self, tick: &str, amt: u128) -> Result<()> {
-        let write_txn = self.db.begin_write()?;
-        {
-            let mut burns = write_txn.open_table(ZRC20_BURNS)?;
-            let current: u128 = burns
-                .get(tick)?
-                .and_then(|v| v.value().parse::<u128>().ok())
-                .unwrap_or(0);
-            let next = current
-                .checked_add(amt)
-                .ok_or_else(|| anyhow::anyhow!("burn overflow"))?;
-            burns.insert(tick, next.to_string().as_str())?;
-        }
-        write_txn.commit()?;
-        Ok(())
+    /// Newest-first ZRC-20 activity for a ticker.
+    pub fn list_zrc20_tick_history(
+        &self,
+        tick: &str,
+        limit: usize,
+        cursor: Option<Cursor>,
+    ) -> Result<Vec<(String, String)>> {
+        self.prefix_page(ZRC20_TICK_HISTORY, tick, limit, cursor)
     }
 
-    pub fn get_burned(&self, tick: &str) -> Result<u128> {
-        let read_txn = self.db.begin_read()?;
-        let burns = read_txn.open_table(ZRC20_BURNS)?;
-        let v = burns
-            .get(tick)?
-            .and_then(|v| v.value().parse::<u128>().ok())
+    /// Newest-first ZRC-20 events for a ticker; `page` is skipped unless a cursor is given.
+    pub fn list_zrc20_events(
+        &self,
+        tick: &str,
+        page: usize,
+        limit: usize,
+        cursor: Option<Cursor>,
+    ) -> Result<Vec<(String, String)>> {
+        let skip = if cursor.is_some() { 0 } else { page.saturating_mul(limit) };
+        self.prefix_page_from(ZRC20_EVENTS, tick, skip, limit, cursor)
+    }
+
+    /// Deploys refused by the deploy policy, newest first, for one ticker or all of them.
+    pub fn list_rejected_deploys(&self, tick: Option<&str>, page: usize, limit: usize) -> Result<Vec<serde_json::Value>> {
+        let skip = page.saturating_mul(limit);
+        if let Some(tick) = tick {
+            let rows = self.prefix_page_from(ZRC20_REJECTED_DEPLOYS, tick, skip, limit, None)?;
+            return Ok(rows.into_iter().filter_map(|(_, raw)| serde_json::from_str(&raw).ok()).collect());
+        }
+        let read_txn = self.db.begin_read()?;
+        let mut rows: Vec<(u64, String, serde_json::Value)> = Vec::new();
+        for item in read_txn.open_table(ZRC20_REJECTED_DEPLOYS)?.iter()? {
+            let (k, v) = item?;
+            let row: serde_json::Value = serde_json::from_str(v.value())?;
+            rows.push((row["height"].as_u64().unwrap_or(0), k.value().to_string(), row));
+        }
+        // Keys sort by ticker; order by height across tickers
+        rows.sort_by(|a, b| (b.0, &b.1).cmp(&(a.0, &a.1)));
+        Ok(rows.into_iter().skip(skip).take(limit).map(|(_, _, row)| row).collect())
+    }
+
+    /// Newest-first ZRC-20 activity for an address across all tickers.
+    pub fn list_zrc20_address_history(
+        &self,
+        address: &str,
+        limit: usize,
+        cursor: Option<Cursor>,
+    ) -> Result<Vec<(String, String)>> {
+        self.prefix_page(ZRC20_ADDRESS_HISTORY, address, limit, cursor)
+    }
+
+    // Newest-first rows keyed under `<owner>:`, resuming after a row key
+    fn prefix_page(
+        &self,
+        def: TableDefinition<&str, &str>,
+        owner: &str,
+        limit: usize,
+        cursor: Option<Cursor>,
+    ) -> Result<Vec<(String, String)>> {
+        self.prefix_page_from(def, owner, 0, limit, cursor)
+    }
+
+    fn prefix_page_from(
+        &self,
+        def: TableDefinition<&str, &str>,
+        owner: &str,
+        skip: usize,
+        limit: usize,
+        cursor: Option<Cursor>,
+    ) -> Result<Vec<(String, String)>> {
+        let start = format!("{}:", owner);
+        // ';' sorts right after ':', so this bounds every key with the prefix
+        let end = format!("{};", owner);
+        let upper = match cursor {
+            Some(Cursor::After(key)) if key.starts_with(&start) => Bound::Excluded(key),
+            _ => Bound::Excluded(end.as_str()),
+        };
+        let read_txn = self.db.begin_read()?;
+        let table = read_txn.open_table(def)?;
+        let mut rows = Vec::new();
+        let bounds: (Bound<&str>, Bound<&str>) = (Bound::Included(start.as_str()), upper);
+        for item in table.range::<&str>(bounds)?.rev().skip(skip).take(limit) {
+            let (k, v) = item?;
+            rows.push((k.value().to_string(), v.value().to_string()));
+        }
+        Ok(rows)
+    }
+
+    pub fn get_burned(&self, tick: &str) -> Result<u128> {
+        let read_txn = self.db.begin_read()?;
+        let burns = read_txn.open_table(ZRC20_BURNS)?;
+        let v = burns
+            .get(tick)?
+            .and_then(|v| v.value().parse::<u128>().ok())
             .unwrap_or(0);
         Ok(v)
     }
 
+    /// Mint record of `address` for a throttled ticker, as `{"minted","last_height"}` JSON.
+    pub fn get_mint_record(&self, tick: &str, address: &str) -> Result<Option<String>> {
+        let key = format!("{}:{}", tick, address);
+        let read_txn = self.db.begin_read()?;
+        let mints = read_txn.open_table(ZRC20_MINTS)?;
+        let v = mints.get(key.as_str())?.map(|v| v.value().to_string());
+        Ok(v)
+    }
+
     /// Count completed (settled) transfer inscriptions for a given ticker.
     pub fn count_completed_transfers_for_tick(&self, tick: &str) -> Result<u64> {
         let needle = tick.to_lowercase();
@@ -533,13 +1273,13 @@ impl Db {
             if let Some((addr, token)) = k.value().split_once(':') {
                 if token == needle {
                     let bal = serde_json::from_str::<Balance>(v.value())?;
-                    if bal.overall > 0 {
-                        rows.push((addr.to_string(), bal.overall));
+                    if !bal.overall.is_zero() {
+                        rows.push((addr.to_string(), bal.overall.base_units()));
                     }
                 }
             }
         }
-        rows.sort_by(|a, b| b.1.cmp(&a.1));
+        rows.sort_by_key(|row| std::cmp::Reverse(row.1));
         let total = rows.len() as u64;
         let mut rank: u64 = 0;
         for (idx, (addr, _)) in rows.iter().enumerate() {
@@ -554,186 +1294,93 @@ impl Db {
     pub fn list_balances_for_address(&self, address: &str) -> Result<Vec<(String, Balance)>> {
         let read_txn = self.db.begin_read()?;
         let table = read_txn.open_table(BALANCES)?;
+        // Balance keys are "<address>:<tick>", so one range covers the address
+        let start = format!("{}:", address);
+        let end = format!("{};", address);
         let mut rows = Vec::new();
-        for item in table.iter()? {
+        for item in table.range::<&str>(start.as_str()..end.as_str())? {
             let (k, v) = item?;
-            let key = k.value();
-            if let Some((addr, token)) = key.split_once(':') {
-                if addr == address {
-                    let bal = serde_json::from_str::<Balance>(v.value())?;
-                    rows.push((token.to_string(), bal));
-                }
-            }
+            let token = &k.value()[start.len()..];
+            let bal = serde_json::from_str::<Balance>(v.value())?;
+            rows.push((token.to_string(), bal));
         }
-        rows.sort_by(|a, b| b.1.overall.cmp(&a.1.overall));
+        rows.sort_by_key(|row| std::cmp::Reverse(row.1.overall));
         Ok(rows)
     }
 
-    pub fn set_status(&self, key: &str, value: u64) -> Result<()> {
-        let write_txn = self.db.begin_write()?;
-        {
-            let mut table = write_txn.open_table(STATUS)?;
-            table.insert(key, value)?;
-        }
-        write_txn.commit()?;
-        Ok(())
-    }
-
-    pub fn get_status(&self, key: &str) -> Result<Option<u64>> {
+    /// Names and per-collection ZRC-721 token counts currently owned by `address`.
+    pub fn list_holdings(&self, address: &str) -> Result<Holdings> {
         let read_txn = self.db.begin_read()?;
-        let table = read_txn.open_table(STATUS)?;
-        let value = table.get(key)?.map(|v| v.value());
-        Ok(value)
-    }
-
-    pub fn register_zrc721_collection(
-        &self,
-        tick: &str,
-        payload: &serde_json::Value,
-    ) -> Result<()> {
-        let write_txn = self.db.begin_write()?;
-        {
-            let mut table = write_txn.open_table(ZRC721_COLLECTIONS)?;
-            if table.get(tick)?.is_some() {
-                return Err(anyhow::anyhow!("Collection already exists"));
+        let table = read_txn.open_table(OWNER_HOLDINGS)?;
+        let start = format!("{}:", address);
+        let end = format!("{};", address);
+        let mut holdings = Holdings::default();
+        for item in table.range::<&str>(start.as_str()..end.as_str())? {
+            let (k, _) = item?;
+            match k.value()[start.len()..].split_once(':') {
+                Some(("name", name)) => holdings.names.push(name.to_string()),
+                Some(("nft", token)) => {
+                    let tick = token.split_once('#').map(|(tick, _)| tick).unwrap_or(token);
+                    *holdings.collections.entry(tick.to_string()).or_default() += 1;
+                }
+                _ => {}
             }
-            table.insert(tick, payload.to_string().as_str())?;
-        }
-        write_txn.commit()?;
-        Ok(())
-    }
-
-    pub fn get_zrc721_collection(&self, tick: &str) -> Result<Option<String>> {
-        let read_txn = self.db.begin_read()?;
-        let table = read_txn.open_table(ZRC721_COLLECTIONS)?;
-        let val = table.get(tick)?.map(|v| v.value().to_string());
-        Ok(val)
-    }
-
-    pub fn list_zrc721_collections(&self, page: usize, limit: usize) -> Result<Vec<(String, String)>> {
-        let offset = page.saturating_mul(limit);
-        let read_txn = self.db.begin_read()?;
-        let table = read_txn.open_table(ZRC721_COLLECTIONS)?;
-        let mut rows = Vec::new();
-        for item in table.iter()?.rev().skip(offset).take(limit) {
-            let (k, v) = item?;
-            rows.push((k.value().to_string(), v.value().to_string()));
         }
-        Ok(rows)
+        Ok(holdings)
     }
 
-    pub fn insert_zrc721_token(
+    /// A page of `(index key, name data)` rows for the names `address` owns, read from
+    /// the holdings index in reverse name order.
+    pub fn list_names_by_owner(
         &self,
-        tick: &str,
-        token_id: &str,
-        owner: &str,
-        inscription_id: &str,
-        metadata: &serde_json::Value,
-    ) -> Result<()> {
-        let key = format!("{}#{}", tick, token_id);
-        let write_txn = self.db.begin_write()?;
-        {
-            let mut collections = write_txn.open_table(ZRC721_COLLECTIONS)?;
-            let mut tokens = write_txn.open_table(ZRC721_TOKENS)?;
-
-            if tokens.get(key.as_str())?.is_some() {
-                return Err(anyhow::anyhow!("Token already minted"));
-            }
+        address: &str,
+        page: usize,
+        limit: usize,
+        cursor: Option<Cursor>,
+    ) -> Result<Vec<(String, String)>> {
+        let skip = if cursor.is_some() { 0 } else { page.saturating_mul(limit) };
+        let prefix = format!("{}:name", address);
+        let keys = self.prefix_page_from(OWNER_HOLDINGS, &prefix, skip, limit, cursor)?;
 
-            let mut collection: serde_json::Value = match collections.get(tick)? {
-                Some(raw) => serde_json::from_str(raw.value())?,
-                None => return Err(anyhow::anyhow!("Collection not found")),
-            };
-            // Enforce supply-based cap and token id range (0..=supply-1)
-            let current_minted = collection["minted"].as_u64().unwrap_or(0);
-            let max_allowed = collection["supply"].as_str().and_then(|s| s.parse::<u64>().ok());
-            if let Some(max_total) = max_allowed {
-                if current_minted >= max_total {
-                    return Err(anyhow::anyhow!("Max token count reached"));
-                }
-                if let Ok(id_num) = token_id.parse::<u64>() {
-                    if id_num >= max_total {
-                        return Err(anyhow::anyhow!("Token id out of range"));
-                    }
-                }
+        let read_txn = self.db.begin_read()?;
+        let names = read_txn.open_table(NAMES)?;
+        let mut rows = Vec::with_capacity(keys.len());
+        for (index_key, _) in keys {
+            if let Some(data) = names.get(&index_key[prefix.len() + 1..])? {
+                rows.push((index_key, data.value().to_string()));
             }
-            let minted = current_minted + 1;
-            collection["minted"] = serde_json::json!(minted);
-            collections.insert(tick, collection.to_string().as_str())?;
-
-            let token = Zrc721Token {
-                tick: tick.to_string(),
-                token_id: token_id.to_string(),
-                owner: owner.to_string(),
-                inscription_id: inscription_id.to_string(),
-                metadata: metadata.clone(),
-                shielded_burn: false,
-            };
-            tokens.insert(key.as_str(), serde_json::to_string(&token)?.as_str())?;
         }
-        write_txn.commit()?;
-        Ok(())
+        Ok(rows)
     }
 
-    pub fn register_zrc721_outpoint(&self, txid: &str, vout: u32, collection: &str, token_id: &str) -> Result<()> {
-        let key = format!("{}:{}", txid, vout);
-        let value = format!("{}#{}", collection, token_id);
-        let write_txn = self.db.begin_write()?;
+    pub fn set_status(&self, key: &str, value: u64) -> Result<()> {
+        let write_txn = self.begin_write()?;
         {
-            let mut table = write_txn.open_table(ZRC721_OUTPOINTS)?;
-            table.insert(key.as_str(), value.as_str())?;
+            let mut table = write_txn.open_table(STATUS)?;
+            table.insert(key, value)?;
         }
-        write_txn.commit()?;
+        self.commit(write_txn)?;
         Ok(())
     }
 
-    pub fn zrc721_by_outpoint(&self, txid: &str, vout: u32) -> Result<Option<(String, String)>> {
+    pub fn get_status(&self, key: &str) -> Result<Option<u64>> {
         let read_txn = self.db.begin_read()?;
-        let table = read_txn.open_table(ZRC721_OUTPOINTS)?;
-        let key = format!("{}:{}", txid, vout);
-        if let Some(val) = table.get(key.as_str())? {
-            let s = val.value();
-            if let Some((c, id)) = s.split_once('#') {
-                return Ok(Some((c.to_string(), id.to_string())));
-            }
-        }
-        Ok(None)
+        let table = read_txn.open_table(STATUS)?;
+        let value = table.get(key)?.map(|v| v.value());
+        Ok(value)
     }
 
-    pub fn move_zrc721_outpoint(&self, prev_txid: &str, prev_vout: u32, new_txid: &str, new_vout: u32) -> Result<()> {
-        let prev = format!("{}:{}", prev_txid, prev_vout);
-        let next = format!("{}:{}", new_txid, new_vout);
-        let write_txn = self.db.begin_write()?;
-        {
-            let mut table = write_txn.open_table(ZRC721_OUTPOINTS)?;
-            let v = match table.get(prev.as_str())? {
-                Some(val) => val.value().to_string(),
-                None => {
-                    // nothing to move
-                    return Ok(());
-                }
-            };
-            table.insert(next.as_str(), v.as_str())?;
-            let _ = table.remove(prev.as_str());
-        }
-        write_txn.commit()?;
-        Ok(())
+    pub fn get_zrc721_collection(&self, tick: &str) -> Result<Option<String>> {
+        self.cached_get(ZRC721_COLLECTIONS, tick)
     }
 
-    pub fn update_zrc721_owner(&self, collection: &str, token_id: &str, owner: &str, shielded_burn: bool) -> Result<()> {
-        let key = format!("{}#{}", collection, token_id);
-        let write_txn = self.db.begin_write()?;
-        {
-            let mut table = write_txn.open_table(ZRC721_TOKENS)?;
-            let current = match table.get(key.as_str())? { Some(r) => r.value().to_string(), None => return Ok(()) };
-            let mut t: Zrc721Token = serde_json::from_str(&current)?;
-            t.owner = owner.to_string();
-            t.shielded_burn = shielded_burn;
-            let s = serde_json::to_string(&t)?;
-            table.insert(key.as_str(), s.as_str())?;
-        }
-        write_txn.commit()?;
-        Ok(())
+    pub fn list_zrc721_collections(
+        &self,
+        page: usize,
+        limit: usize,
+        cursor: Option<Cursor>,
+    ) -> Result<Vec<(String, String)>> {
+        self.page_desc(ZRC721_COLLECTIONS, page, limit, cursor)
     }
 
     pub fn list_zrc721_tokens(
@@ -756,10 +1403,70 @@ impl Db {
                 }
             }
         }
-        rows.sort_by(|a, b| a.token_id.cmp(&b.token_id));
+        rows.sort_by_key(|t| t.token_id.clone());
         Ok(rows.into_iter().skip(offset).take(limit).collect())
     }
 
+    /// Owners of `tick` ranked by tokens held (ties by address), with the total number
+    /// of owners.
+    pub fn list_zrc721_owners(&self, tick: &str, page: usize, limit: usize) -> Result<(usize, Vec<(String, u64)>)> {
+        let read_txn = self.db.begin_read()?;
+        let table = read_txn.open_table(ZRC721_OWNERS)?;
+        let start = format!("{}:", tick);
+        let end = format!("{};", tick);
+        let mut rows = Vec::new();
+        for item in table.range::<&str>(start.as_str()..end.as_str())? {
+            let (k, v) = item?;
+            let held: u64 = v.value().parse().unwrap_or(0);
+            rows.push((k.value()[start.len()..].to_string(), held));
+        }
+        rows.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        let total = rows.len();
+        Ok((total, rows.into_iter().skip(page.saturating_mul(limit)).take(limit).collect()))
+    }
+
+    /// Amendments of `tick`'s collection record, newest first.
+    pub fn list_zrc721_updates(&self, tick: &str, page: usize, limit: usize) -> Result<Vec<serde_json::Value>> {
+        let read_txn = self.db.begin_read()?;
+        let table = read_txn.open_table(ZRC721_UPDATES)?;
+        let start = format!("{}:", tick);
+        let end = format!("{};", tick);
+        let mut rows = Vec::new();
+        for item in table
+            .range::<&str>(start.as_str()..end.as_str())?
+            .rev()
+            .skip(page.saturating_mul(limit))
+            .take(limit)
+        {
+            rows.push(serde_json::from_str(item?.1.value())?);
+        }
+        Ok(rows)
+    }
+
+    /// Recorded sales of `tick`, newest first, with totals over all of them.
+    pub fn list_zrc721_sales(&self, tick: &str, page: usize, limit: usize) -> Result<(RoyaltyTotals, Vec<serde_json::Value>)> {
+        let read_txn = self.db.begin_read()?;
+        let table = read_txn.open_table(ZRC721_SALES)?;
+        let start = format!("{}:", tick);
+        let end = format!("{};", tick);
+        let mut totals = RoyaltyTotals::default();
+        let mut rows = Vec::new();
+        let offset = page.saturating_mul(limit);
+        for (i, item) in table.range::<&str>(start.as_str()..end.as_str())?.rev().enumerate() {
+            let (_, v) = item?;
+            let sale: serde_json::Value = serde_json::from_str(v.value())?;
+            totals.sales += 1;
+            totals.compliant_sales += u64::from(sale["compliant"].as_bool().unwrap_or(false));
+            totals.volume_zat += sale["price_zat"].as_u64().unwrap_or(0);
+            totals.royalty_paid_zat += sale["royalty_paid_zat"].as_u64().unwrap_or(0);
+            totals.royalty_due_zat += sale["royalty_due_zat"].as_u64().unwrap_or(0);
+            if i >= offset && rows.len() < limit {
+                rows.push(sale);
+            }
+        }
+        Ok((totals, rows))
+    }
+
     pub fn list_zrc721_tokens_by_address(
         &self,
         address: &str,
@@ -798,50 +1505,6 @@ impl Db {
         Ok((collection_count, token_count))
     }
 
-    // Transfer inscription helpers
-    pub fn create_transfer_inscription(&self, inscription_id: &str, data: &str) -> Result<()> {
-        let write_txn = self.db.begin_write()?;
-        {
-            let mut table = write_txn.open_table(TRANSFER_INSCRIPTIONS)?;
-            table.insert(inscription_id, data)?;
-
-            let mut state_table = write_txn.open_table(INSCRIPTION_STATE)?;
-            state_table.insert(inscription_id, "unused")?;
-        }
-        write_txn.commit()?;
-        Ok(())
-    }
-
-    pub fn register_transfer_outpoint(&self, txid: &str, vout: u32, inscription_id: &str) -> Result<()> {
-        let key = format!("{}:{}", txid, vout);
-        let write_txn = self.db.begin_write()?;
-        {
-            let mut table = write_txn.open_table(TRANSFER_OUTPOINTS)?;
-            table.insert(key.as_str(), inscription_id)?;
-        }
-        write_txn.commit()?;
-        Ok(())
-    }
-
-    pub fn get_transfer_by_outpoint(&self, txid: &str, vout: u32) -> Result<Option<String>> {
-        let read_txn = self.db.begin_read()?;
-        let table = read_txn.open_table(TRANSFER_OUTPOINTS)?;
-        let key = format!("{}:{}", txid, vout);
-        let val = table.get(key.as_str())?.map(|v| v.value().to_string());
-        Ok(val)
-    }
-
-    pub fn remove_transfer_outpoint(&self, txid: &str, vout: u32) -> Result<()> {
-        let key = format!("{}:{}", txid, vout);
-        let write_txn = self.db.begin_write()?;
-        {
-            let mut table = write_txn.open_table(TRANSFER_OUTPOINTS)?;
-            let _ = table.remove(key.as_str());
-        }
-        write_txn.commit()?;
-        Ok(())
-    }
-
     /// Reverse lookup helper for debugging/APIs: find outpoint for a transfer inscription id.
     pub fn find_outpoint_by_transfer_id(&self, inscription_id: &str) -> Result<Option<String>> {
         let read_txn = self.db.begin_read()?;
@@ -862,23 +1525,46 @@ impl Db {
         Ok(val)
     }
 
-    pub fn mark_inscription_used(&self, inscription_id: &str) -> Result<()> {
-        let write_txn = self.db.begin_write()?;
-        {
-            let mut table = write_txn.open_table(INSCRIPTION_STATE)?;
-            table.insert(inscription_id, "used")?;
+    /// Unused transfer inscriptions staged by `address`, with their transfer data.
+    pub fn list_transferable(&self, address: &str) -> Result<Vec<(String, String)>> {
+        let read_txn = self.db.begin_read()?;
+        let pending = read_txn.open_table(ADDRESS_TRANSFERS)?;
+        let ids = match pending.get(address)? {
+            Some(existing) => serde_json::from_str::<Vec<String>>(existing.value())?,
+            None => Vec::new(),
+        };
+        let transfers = read_txn.open_table(TRANSFER_INSCRIPTIONS)?;
+        let mut rows = Vec::with_capacity(ids.len());
+        for id in ids {
+            if let Some(data) = transfers.get(id.as_str())? {
+                rows.push((id.clone(), data.value().to_string()));
+            }
         }
-        write_txn.commit()?;
-        Ok(())
+        Ok(rows)
+    }
+
+    /// Amounts locked in `address`'s unused transfer inscriptions, per ticker: the
+    /// BRC-20 "transferable" balance. Tickers without any are left out.
+    pub fn transferable_by_tick(&self, address: &str) -> Result<HashMap<String, Amount>> {
+        let mut totals: HashMap<String, Amount> = HashMap::new();
+        for (_, raw) in self.list_transferable(address)? {
+            let data: serde_json::Value = serde_json::from_str(&raw)?;
+            let (Some(tick), Some(amt)) = (data["tick"].as_str(), data["amt"].as_str()) else {
+                continue;
+            };
+            let total = totals.entry(tick.to_string()).or_default();
+            *total = total
+                .checked_add(Amount::parse_base_units(amt)?)
+                .ok_or_else(|| anyhow::anyhow!("Transferable balance overflow"))?;
+        }
+        Ok(totals)
     }
 
-    pub fn is_inscription_used(&self, inscription_id: &str) -> Result<bool> {
+    /// Settlement state of a transfer inscription: `unused`, `used` or `cancelled`.
+    pub fn get_inscription_state(&self, inscription_id: &str) -> Result<Option<String>> {
         let read_txn = self.db.begin_read()?;
         let table = read_txn.open_table(INSCRIPTION_STATE)?;
-        let val = table
-            .get(inscription_id)?
-            .map(|v| v.value() == "used")
-            .unwrap_or(false);
+        let val = table.get(inscription_id)?.map(|v| v.value().to_string());
         Ok(val)
     }
 
@@ -889,6 +1575,48 @@ impl Db {
         Ok(val)
     }
 
+    /// Raw payload bytes for an inscription, resolved through its content digest.
+    pub fn get_content(&self, id: &str) -> Result<Option<Vec<u8>>> {
+        let read_txn = self.db.begin_read()?;
+        let inscriptions = read_txn.open_table(INSCRIPTIONS)?;
+        let digest = match inscriptions.get(id)? {
+            Some(raw) => serde_json::from_str::<serde_json::Value>(raw.value())?["content_sha256"]
+                .as_str()
+                .map(|s| s.to_string()),
+            None => None,
+        };
+        let Some(digest) = digest else {
+            return Ok(None);
+        };
+        let blobs = read_txn.open_table(CONTENT_BLOBS)?;
+        let val = blobs.get(digest.as_str())?.map(|v| v.value().to_vec());
+        Ok(val)
+    }
+
+    /// Bytes `start..=end` of an inscription's payload (clamped to its length), plus the
+    /// full length. Only the requested slice is copied out of the content store.
+    pub fn get_content_range(&self, id: &str, start: usize, end: usize) -> Result<Option<(Vec<u8>, usize)>> {
+        let read_txn = self.db.begin_read()?;
+        let inscriptions = read_txn.open_table(INSCRIPTIONS)?;
+        let digest = match inscriptions.get(id)? {
+            Some(raw) => serde_json::from_str::<serde_json::Value>(raw.value())?["content_sha256"]
+                .as_str()
+                .map(|s| s.to_string()),
+            None => None,
+        };
+        let Some(digest) = digest else {
+            return Ok(None);
+        };
+        let blobs = read_txn.open_table(CONTENT_BLOBS)?;
+        let val = blobs.get(digest.as_str())?.map(|v| {
+            let bytes = v.value();
+            let end = end.min(bytes.len().saturating_sub(1));
+            let slice = if start <= end { bytes[start..=end].to_vec() } else { Vec::new() };
+            (slice, bytes.len())
+        });
+        Ok(val)
+    }
+
     pub fn get_inscription_by_number(&self, number: u64) -> Result<Option<String>> {
         let read_txn = self.db.begin_read()?;
         let table = read_txn.open_table(INSCRIPTION_NUMBERS)?;
@@ -908,17 +1636,6 @@ impl Db {
         Ok(result)
     }
 
-    pub fn get_all_tokens(&self) -> Result<Vec<(String, String)>> {
-        let read_txn = self.db.begin_read()?;
-        let table = read_txn.open_table(TOKENS)?;
-        let mut tokens = Vec::new();
-        for item in table.iter()? {
-            let (k, v) = item?;
-            tokens.push((k.value().to_string(), v.value().to_string()));
-        }
-        Ok(tokens)
-    }
-
     pub fn get_inscription_count(&self) -> Result<u64> {
         let read_txn = self.db.begin_read()?;
         let table = read_txn.open_table(STATS)?;
@@ -929,37 +1646,99 @@ impl Db {
         Ok(count)
     }
 
-    // Name (ZNS) helpers
-    pub fn register_name(&self, name: &str, data: &str) -> Result<()> {
-        let write_txn = self.db.begin_write()?;
-        {
-            let mut table = write_txn.open_table(NAMES)?;
-            // Enforce first-writer-wins
-            if table.get(name)?.is_some() {
-                return Err(anyhow::anyhow!("Name already registered"));
-            }
-            table.insert(name, data)?;
+    /// Newest-first wallet activity (inscriptions, ZRC-20, names, NFTs) for an address.
+    pub fn list_address_activity(
+        &self,
+        address: &str,
+        page: usize,
+        limit: usize,
+        cursor: Option<Cursor>,
+    ) -> Result<Vec<(String, String)>> {
+        let offset = if cursor.is_some() { 0 } else { page.saturating_mul(limit) };
+        let mut rows = self.prefix_page(ADDRESS_ACTIVITY, address, offset + limit, cursor)?;
+        Ok(rows.split_off(offset.min(rows.len())))
+    }
 
-            let mut stats = write_txn.open_table(STATS)?;
-            let count = stats.get("name_count")?.map(|v| v.value()).unwrap_or(0);
-            stats.insert("name_count", count + 1)?;
-        }
-        write_txn.commit()?;
-        Ok(())
+    /// Newest-first ownership moves for a ZRC-721 token.
+    pub fn list_zrc721_history(
+        &self,
+        collection: &str,
+        token_id: &str,
+        limit: usize,
+        cursor: Option<Cursor>,
+    ) -> Result<Vec<(String, String)>> {
+        let owner = format!("{}#{}", collection, token_id);
+        self.prefix_page(ZRC721_HISTORY, owner.as_str(), limit, cursor)
     }
 
-    pub fn get_names_page(&self, page: usize, limit: usize) -> Result<Vec<(String, String)>> {
-        let offset = page.saturating_mul(limit);
+    /// Newest-first mints, transfers and burns across a ZRC-721 collection; `page` is
+    /// skipped unless a cursor is given.
+    pub fn list_zrc721_activity(
+        &self,
+        collection: &str,
+        page: usize,
+        limit: usize,
+        cursor: Option<Cursor>,
+    ) -> Result<Vec<(String, String)>> {
+        let skip = if cursor.is_some() { 0 } else { page.saturating_mul(limit) };
+        self.prefix_page_from(ZRC721_ACTIVITY, collection, skip, limit, cursor)
+    }
+
+    /// Token ids of `collection` held by each owner once block `height` (the tip when
+    /// `None`) was indexed, replayed from the collection activity feed. Shielded burns
+    /// are held by "shielded". Returns the height used.
+    pub fn zrc721_holdings_at(
+        &self,
+        collection: &str,
+        height: Option<u64>,
+    ) -> Result<(u64, BTreeMap<String, Vec<String>>)> {
         let read_txn = self.db.begin_read()?;
-        let table = read_txn.open_table(NAMES)?;
-        let mut names = Vec::new();
-        for item in table.iter()?.rev().skip(offset).take(limit) {
-            let (k, v) = item?;
-            names.push((k.value().to_string(), v.value().to_string()));
+        let tip = read_txn.open_table(BLOCKS)?.last()?.map(|(k, _)| k.value()).unwrap_or(0);
+        let height = height.unwrap_or(tip);
+        if height > tip {
+            return Err(anyhow::anyhow!("Height {} is above the indexed tip {}", height, tip));
         }
-        Ok(names)
+        let start = format!("{}:", collection);
+        let end = format!("{}:{:010};", collection, height);
+        let mut owners: HashMap<String, String> = HashMap::new();
+        for item in read_txn.open_table(ZRC721_ACTIVITY)?.range::<&str>(start.as_str()..end.as_str())? {
+            let (_, v) = item?;
+            let row: serde_json::Value = serde_json::from_str(v.value())?;
+            if let (Some(token_id), Some(to)) = (row["token_id"].as_str(), row["to"].as_str()) {
+                owners.insert(token_id.to_string(), to.to_string());
+            }
+        }
+        let mut holdings: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for (token_id, owner) in owners {
+            holdings.entry(owner).or_default().push(token_id);
+        }
+        for ids in holdings.values_mut() {
+            // Numeric ids in numeric order
+            ids.sort_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)));
+        }
+        Ok((height, holdings))
+    }
+
+    /// Newest-first ownership changes for a name, starting with its registration.
+    pub fn list_name_history(
+        &self,
+        name: &str,
+        limit: usize,
+        cursor: Option<Cursor>,
+    ) -> Result<Vec<(String, String)>> {
+        self.prefix_page(NAME_HISTORY, name, limit, cursor)
+    }
+
+    pub fn get_names_page(
+        &self,
+        page: usize,
+        limit: usize,
+        cursor: Option<Cursor>,
+    ) -> Result<Vec<(String, String)>> {
+        self.page_desc(NAMES, page, limit, cursor)
     }
 
+    #[allow(dead_code)]
     pub fn search_names(&self, query: &str, limit: usize) -> Result<Vec<(String, String)>> {
         let read_txn = self.db.begin_read()?;
         let table = read_txn.open_table(NAMES)?;
@@ -980,6 +1759,21 @@ impl Db {
         Ok(names)
     }
 
+    /// Up to `limit` names whose normalized key starts with `prefix`, in key order.
+    pub fn names_with_prefix(&self, prefix: &str, limit: usize) -> Result<Vec<(String, String)>> {
+        let read_txn = self.db.begin_read()?;
+        let table = read_txn.open_table(NAMES)?;
+        let mut names = Vec::new();
+        for item in table.range::<&str>(prefix..)? {
+            let (k, v) = item?;
+            if !k.value().starts_with(prefix) || names.len() >= limit {
+                break;
+            }
+            names.push((k.value().to_string(), v.value().to_string()));
+        }
+        Ok(names)
+    }
+
     pub fn get_token_count(&self) -> Result<u64> {
         let read_txn = self.db.begin_read()?;
         let count;
@@ -1000,21 +1794,325 @@ impl Db {
         Ok(count)
     }
 
+    /// Newest-first `(index key, name data)` rows for one TLD, read as a range scan.
+    pub fn get_names_page_by_tld(
+        &self,
+        tld: &str,
+        page: usize,
+        limit: usize,
+        cursor: Option<Cursor>,
+    ) -> Result<Vec<(String, String)>> {
+        let offset = if cursor.is_some() { 0 } else { page.saturating_mul(limit) };
+        let mut keys = self.prefix_page(NAME_TLDS, tld, offset + limit, cursor)?;
+        let keys = keys.split_off(offset.min(keys.len()));
+
+        let read_txn = self.db.begin_read()?;
+        let names = read_txn.open_table(NAMES)?;
+        let mut rows = Vec::with_capacity(keys.len());
+        for (index_key, name) in keys {
+            if let Some(data) = names.get(name.as_str())? {
+                rows.push((index_key, data.value().to_string()));
+            }
+        }
+        Ok(rows)
+    }
+
+    /// Newest-first `(index key, name data)` rows for the subdomains registered under `parent`.
+    pub fn list_name_subdomains(
+        &self,
+        parent: &str,
+        limit: usize,
+        cursor: Option<Cursor>,
+    ) -> Result<Vec<(String, String)>> {
+        let keys = self.prefix_page(NAME_SUBDOMAINS, parent, limit, cursor)?;
+        let read_txn = self.db.begin_read()?;
+        let names = read_txn.open_table(NAMES)?;
+        let mut rows = Vec::with_capacity(keys.len());
+        for (index_key, name) in keys {
+            if let Some(data) = names.get(name.as_str())? {
+                rows.push((index_key, data.value().to_string()));
+            }
+        }
+        Ok(rows)
+    }
+
+    pub fn get_name_count_by_tld(&self, tld: &str) -> Result<u64> {
+        self.get_stat(&format!("name_count:{}", tld))
+    }
+
+    fn get_stat(&self, key: &str) -> Result<u64> {
+        let read_txn = self.db.begin_read()?;
+        let table = read_txn.open_table(STATS)?;
+        let value = table.get(key)?.map(|v| v.value()).unwrap_or(0);
+        Ok(value)
+    }
+
     pub fn get_name(&self, name: &str) -> Result<Option<String>> {
+        self.cached_get(NAMES, name)
+    }
+
+    /// Inclusion proof of the transaction that registered `name`, stored at index time.
+    pub fn get_name_proof(&self, name: &str) -> Result<Option<String>> {
+        self.cached_get(NAME_PROOFS, name)
+    }
+
+    // Point lookup served from the read cache when possible (misses are cached too)
+    fn cached_get(&self, def: TableDefinition<&str, &str>, key: &str) -> Result<Option<String>> {
+        let name = def.name();
+        if let Some(val) = self.cache.get(name, key) {
+            return Ok(val);
+        }
+        let generation = self.cache.generation();
         let read_txn = self.db.begin_read()?;
-        let table = read_txn.open_table(NAMES)?;
-        let val = table.get(name)?.map(|v| v.value().to_string());
+        let table = read_txn.open_table(def)?;
+        let val = table.get(key)?.map(|v| v.value().to_string());
+        self.cache.put(generation, name, key, val.clone());
         Ok(val)
     }
 
-    pub fn get_all_names(&self) -> Result<Vec<(String, String)>> {
-        let read_txn = self.db.begin_read()?;
-        let table = read_txn.open_table(NAMES)?;
-        let mut names = Vec::new();
-        for item in table.iter()? {
-            let (k, v) = item?;
-            names.push((k.value().to_string(), v.value().to_string()));
+}
+
+// Sort holders by overall balance and cut one page; also returns (all rows, positive rows)
+fn page_balances(
+    rows: Vec<(String, Balance)>,
+    page: usize,
+    limit: usize,
+    positive_only: bool,
+) -> (Vec<(String, Balance)>, usize, usize) {
+    let total_all = rows.len();
+    let total_positive = rows.iter().filter(|(_, bal)| !bal.overall.is_zero()).count();
+    let mut rows: Vec<(String, Balance)> = rows
+        .into_iter()
+        .filter(|(_, bal)| !positive_only || !bal.overall.is_zero())
+        .collect();
+    rows.sort_by_key(|row| std::cmp::Reverse(row.1.overall));
+    let offset = page.saturating_mul(limit);
+    let page_rows = rows.into_iter().skip(offset).take(limit).collect();
+    (page_rows, total_all, total_positive)
+}
+
+// Record balances at the end of epoch `height` inside `insert_block`, journaling into `ops`.
+// Only keys changed during the epoch are written, unless there is no earlier checkpoint or
+// part of the epoch lacks undo records; then every balance is copied.
+fn write_balance_checkpoint(txn: &WriteTransaction, height: u64, ops: &mut Vec<UndoOp>) -> Result<()> {
+    let balances = txn.open_table(BALANCES)?;
+    let mut stats = txn.open_table(STATS)?;
+    let since = stats.get(CHECKPOINTS_SINCE_KEY)?.map(|v| v.value());
+
+    let mut changed = std::collections::BTreeSet::new();
+    let mut complete = since.is_some();
+    if complete {
+        let mut collect = |ops: &[UndoOp]| {
+            for op in ops {
+                if let UndoOp::Text { table, key, .. } = op {
+                    if table == BALANCES.name() {
+                        changed.insert(key.clone());
+                    }
+                }
+            }
+        };
+        collect(ops);
+        let undo = txn.open_table(UNDO)?;
+        for h in height + 1 - BALANCE_EPOCH..height {
+            match undo.get(h)? {
+                Some(record) => collect(&serde_json::from_str::<Vec<UndoOp>>(record.value())?),
+                None => complete = false,
+            }
+        }
+    }
+    let keys: Vec<String> = if complete {
+        changed.into_iter().collect()
+    } else {
+        let mut all = Vec::new();
+        for item in balances.iter()? {
+            all.push(item?.0.value().to_string());
+        }
+        all
+    };
+
+    let mut checkpoints = txn.open_table(BALANCE_CHECKPOINTS)?;
+    for key in keys {
+        let Some((address, tick)) = key.split_once(':') else {
+            continue;
+        };
+        let checkpoint_key = format!("{}:{}:{:010}", tick, address, height);
+        let value = balances
+            .get(key.as_str())?
+            .map(|v| v.value().to_string())
+            .unwrap_or_else(|| "null".to_string());
+        ops.push(UndoOp::Text {
+            table: BALANCE_CHECKPOINTS.name().to_string(),
+            key: checkpoint_key.clone(),
+            prev: checkpoints.get(checkpoint_key.as_str())?.map(|v| v.value().to_string()),
+        });
+        checkpoints.insert(checkpoint_key.as_str(), value.as_str())?;
+    }
+
+    if since.is_none() {
+        ops.push(UndoOp::Counter {
+            table: STATS.name().to_string(),
+            key: CHECKPOINTS_SINCE_KEY.to_string(),
+            prev: None,
+        });
+        stats.insert(CHECKPOINTS_SINCE_KEY, height)?;
+    }
+    Ok(())
+}
+
+// "<tld>:<label>" index key for a lower-cased name such as "alice.zec"
+fn name_tld_key(name: &str) -> Option<(&str, String)> {
+    let (label, tld) = name.rsplit_once('.')?;
+    Some((tld, format!("{}:{}", tld, label)))
+}
+
+// Owner index key for a name ("name") or ZRC-721 token ("nft"); shielded owners are skipped
+fn holding_key(owner: &str, kind: &str, item: &str) -> Option<String> {
+    if owner.is_empty() || owner == "shielded" {
+        return None;
+    }
+    Some(format!("{}:{}:{}", owner, kind, item))
+}
+
+// Next free "<owner>:<height>:<seq>" key; zero padding keeps keys in chain order
+fn next_history_key(
+    table: &Table<&'static str, &'static str>,
+    owner: &str,
+    height: u64,
+) -> Result<String> {
+    let prefix = format!("{}:{:010}:", owner, height);
+    let end = format!("{}:{:010};", owner, height);
+    // One past the last sequence number, so keys freed by a token rebuild are not reused
+    let seq = match table.range::<&str>(prefix.as_str()..end.as_str())?.next_back() {
+        Some(item) => item?.0.value()[prefix.len()..].parse::<u64>().map_or(0, |last| last + 1),
+        None => 0,
+    };
+    Ok(format!("{}{:06}", prefix, seq))
+}
+
+/// Hex-encoded sha256 of an inscription payload.
+pub fn content_digest(content: &[u8]) -> String {
+    use sha2::{Digest, Sha256};
+    hex::encode(Sha256::digest(content))
+}
+
+// Row count and redb's byte accounting for `def`, for the storage report
+fn table_usage<K: RedbKey + 'static, V: RedbValue + 'static>(
+    txn: &ReadTransaction,
+    def: TableDefinition<K, V>,
+) -> Result<TableUsage> {
+    let table = txn.open_table(def)?;
+    let stats = table.stats()?;
+    Ok(TableUsage {
+        name: def.name().to_string(),
+        entries: table.len()?,
+        stored_bytes: stats.stored_bytes(),
+        metadata_bytes: stats.metadata_bytes(),
+        fragmented_bytes: stats.fragmented_bytes(),
+    })
+}
+
+// Copy every row of `def` from a snapshot read into the target write transaction
+fn copy_table<K: RedbKey + 'static, V: RedbValue + 'static>(
+    source: &ReadTransaction,
+    target: &WriteTransaction,
+    def: TableDefinition<K, V>,
+) -> Result<u64> {
+    let from = source.open_table(def)?;
+    let mut to = target.open_table(def)?;
+    let mut rows = 0;
+    for item in from.iter()? {
+        let (k, v) = item?;
+        to.insert(k.value(), v.value())?;
+        rows += 1;
+    }
+    Ok(rows)
+}
+
+// Restore the pre-block value captured by an undo op
+fn apply_undo(txn: &WriteTransaction, op: &UndoOp) -> Result<()> {
+    match op {
+        UndoOp::Text { table, key, prev } => {
+            let def = UNDOABLE_TABLES
+                .into_iter()
+                .find(|t| t.name() == table)
+                .ok_or_else(|| anyhow::anyhow!("Unknown table in undo record: {}", table))?;
+            let mut t = txn.open_table(def)?;
+            match prev {
+                Some(v) => {
+                    t.insert(key.as_str(), v.as_str())?;
+                }
+                None => {
+                    t.remove(key.as_str())?;
+                }
+            }
+        }
+        UndoOp::Counter { table, key, prev } => {
+            let def = [STATS, CONTENT_REFS, ACTIVITY_STATS]
+                .into_iter()
+                .find(|t| t.name() == table)
+                .ok_or_else(|| anyhow::anyhow!("Unknown table in undo record: {}", table))?;
+            let mut t = txn.open_table(def)?;
+            match prev {
+                Some(v) => {
+                    t.insert(key.as_str(), *v)?;
+                }
+                None => {
+                    t.remove(key.as_str())?;
+                }
+            }
+        }
+        UndoOp::Bytes { table, key, prev } => {
+            // Records written under schema v2 point at the retired per-id table
+            if table == CONTENT.name() {
+                return Ok(());
+            }
+            if table != CONTENT_BLOBS.name() {
+                return Err(anyhow::anyhow!("Unknown table in undo record: {}", table));
+            }
+            let mut t = txn.open_table(CONTENT_BLOBS)?;
+            match prev {
+                Some(v) => {
+                    t.insert(key.as_str(), hex::decode(v)?.as_slice())?;
+                }
+                None => {
+                    t.remove(key.as_str())?;
+                }
+            }
+        }
+        UndoOp::Appended { table, key, item } => {
+            let def = UNDOABLE_TABLES
+                .into_iter()
+                .find(|t| t.name() == table)
+                .ok_or_else(|| anyhow::anyhow!("Unknown table in undo record: {}", table))?;
+            let mut t = txn.open_table(def)?;
+            let mut list = match t.get(key.as_str())? {
+                Some(raw) => serde_json::from_str::<Vec<String>>(raw.value())?,
+                None => Vec::new(),
+            };
+            if let Some(pos) = list.iter().rposition(|id| id == item) {
+                list.remove(pos);
+            }
+            if list.is_empty() {
+                t.remove(key.as_str())?;
+            } else {
+                t.insert(key.as_str(), serde_json::to_string(&list)?.as_str())?;
+            }
+        }
+        UndoOp::Indexed { table, key, prev } => {
+            let def = [BLOCKS, INSCRIPTION_NUMBERS]
+                .into_iter()
+                .find(|t| t.name() == table)
+                .ok_or_else(|| anyhow::anyhow!("Unknown table in undo record: {}", table))?;
+            let mut t = txn.open_table(def)?;
+            match prev {
+                Some(v) => {
+                    t.insert(*key, v.as_str())?;
+                }
+                None => {
+                    t.remove(*key)?;
+                }
+            }
         }
-        Ok(names)
     }
+    Ok(())
 }
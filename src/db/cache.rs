@@ -0,0 +1,90 @@
+//! In-process cache for hot point lookups (token info, collections, names, tip height).
+//!
+//! Entries are dropped wholesale after every committed write transaction, so a
+//! cached value is never older than the last commit. Lookups that raced with a
+//! commit are not stored: each read records the generation it started in and
+//! the insert is skipped if a commit landed in between.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Maximum number of keyed entries held before the least recently used is evicted.
+const CAPACITY: usize = 4096;
+
+pub(super) struct ReadCache {
+    inner: Mutex<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    generation: u64,
+    tick: u64,
+    // (table, key) -> (value, last use); misses are cached as `None`
+    entries: HashMap<(String, String), (Option<String>, u64)>,
+    latest_height: Option<Option<u64>>,
+}
+
+impl ReadCache {
+    pub(super) fn new() -> Self {
+        Self {
+            inner: Mutex::new(Inner::default()),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Current generation; pass it back to `put*` after reading from redb.
+    pub(super) fn generation(&self) -> u64 {
+        self.lock().generation
+    }
+
+    pub(super) fn get(&self, table: &str, key: &str) -> Option<Option<String>> {
+        let mut inner = self.lock();
+        inner.tick += 1;
+        let tick = inner.tick;
+        let entry = inner.entries.get_mut(&(table.to_string(), key.to_string()))?;
+        entry.1 = tick;
+        Some(entry.0.clone())
+    }
+
+    pub(super) fn put(&self, generation: u64, table: &str, key: &str, value: Option<String>) {
+        let mut inner = self.lock();
+        if inner.generation != generation {
+            return;
+        }
+        if inner.entries.len() >= CAPACITY {
+            let oldest = inner
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(k, _)| k.clone());
+            if let Some(oldest) = oldest {
+                inner.entries.remove(&oldest);
+            }
+        }
+        inner.tick += 1;
+        let tick = inner.tick;
+        inner.entries.insert((table.to_string(), key.to_string()), (value, tick));
+    }
+
+    pub(super) fn latest_height(&self) -> Option<Option<u64>> {
+        self.lock().latest_height
+    }
+
+    pub(super) fn put_latest_height(&self, generation: u64, height: Option<u64>) {
+        let mut inner = self.lock();
+        if inner.generation == generation {
+            inner.latest_height = Some(height);
+        }
+    }
+
+    /// Drop everything; called once a write transaction has committed.
+    pub(super) fn invalidate(&self) {
+        let mut inner = self.lock();
+        inner.generation += 1;
+        inner.entries.clear();
+        inner.latest_height = None;
+    }
+}
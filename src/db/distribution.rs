@@ -0,0 +1,188 @@
+//! Holder distribution of a ZRC-20 token behind `/api/v1/zrc20/token/:tick/distribution`.
+//!
+//! The snapshot figures come from the live balances. The series has one point per
+//! balance checkpoint epoch plus the tip: supply is the running total of the
+//! ticker's mint history, holders are counted from the checkpoints, so it starts
+//! at the first checkpointed epoch.
+
+use super::*;
+use std::collections::BTreeMap;
+
+/// Holders whose balance lies in `[from, to)` whole tokens.
+#[derive(Debug, serde::Serialize, utoipa::ToSchema)]
+pub struct BalanceBucket {
+    pub from: String,
+    /// `None` for the open-ended top bucket.
+    pub to: Option<String>,
+    pub holders: usize,
+}
+
+#[derive(Debug, serde::Serialize, utoipa::ToSchema)]
+pub struct SupplyPoint {
+    pub height: u64,
+    pub supply_base_units: String,
+    pub holders: usize,
+}
+
+#[derive(Debug, serde::Serialize, utoipa::ToSchema)]
+pub struct Distribution {
+    pub tick: String,
+    pub supply_base_units: String,
+    /// Addresses with a positive overall balance.
+    pub holders: usize,
+    pub top10_base_units: String,
+    pub top100_base_units: String,
+    /// Shares of the supply held by the top 10 and top 100 holders, 0.0-1.0.
+    pub top10_share: f64,
+    pub top100_share: f64,
+    pub median_base_units: String,
+    /// Holders by order of magnitude of their balance in whole tokens.
+    pub buckets: Vec<BalanceBucket>,
+    pub series: Vec<SupplyPoint>,
+}
+
+impl Db {
+    /// Distribution of `tick`'s supply over its holders, or `None` if it was never deployed.
+    pub fn zrc20_distribution(&self, tick: &str) -> Result<Option<Distribution>> {
+        let needle = tick.to_lowercase();
+        let Some(raw) = self.get_token_info(&needle)? else {
+            return Ok(None);
+        };
+        let info: serde_json::Value = serde_json::from_str(&raw)?;
+        let supply: u128 = info["supply"].as_str().and_then(|s| s.parse().ok()).unwrap_or(0);
+        let dec: u32 = info["dec"].as_str().and_then(|d| d.parse().ok()).unwrap_or(18);
+
+        let read_txn = self.db.begin_read()?;
+        let mut balances: Vec<u128> = Vec::new();
+        for item in read_txn.open_table(BALANCES)?.iter()? {
+            let (k, v) = item?;
+            if k.value().split_once(':').is_some_and(|(_, token)| token == needle) {
+                let bal = serde_json::from_str::<Balance>(v.value())?;
+                if !bal.overall.is_zero() {
+                    balances.push(bal.overall.base_units());
+                }
+            }
+        }
+        balances.sort_unstable_by(|a, b| b.cmp(a));
+
+        let top = |n: usize| balances.iter().take(n).sum::<u128>();
+        let share = |held: u128| if supply == 0 { 0.0 } else { held as f64 / supply as f64 };
+        let (top10, top100) = (top(10), top(100));
+        let median = match balances.len() {
+            0 => 0,
+            n if n % 2 == 1 => balances[n / 2],
+            n => (balances[n / 2 - 1] + balances[n / 2]) / 2,
+        };
+
+        Ok(Some(Distribution {
+            tick: needle.clone(),
+            supply_base_units: supply.to_string(),
+            holders: balances.len(),
+            top10_base_units: top10.to_string(),
+            top100_base_units: top100.to_string(),
+            top10_share: share(top10),
+            top100_share: share(top100),
+            median_base_units: median.to_string(),
+            buckets: magnitude_buckets(&balances, dec),
+            series: supply_series(&read_txn, &needle, supply, balances.len())?,
+        }))
+    }
+}
+
+// Buckets [0, 1), [1, 10), [10, 100), ... whole tokens, up to the largest balance
+fn magnitude_buckets(balances: &[u128], dec: u32) -> Vec<BalanceBucket> {
+    let scale = 10u128.checked_pow(dec).unwrap_or(u128::MAX);
+    let mut counts: Vec<usize> = Vec::new();
+    for &bal in balances {
+        let whole = bal / scale;
+        // Digits of the whole part; 0 for balances under one token
+        let digits = if whole == 0 { 0 } else { whole.ilog10() as usize + 1 };
+        if counts.len() <= digits {
+            counts.resize(digits + 1, 0);
+        }
+        counts[digits] += 1;
+    }
+    let last = counts.len().saturating_sub(1);
+    counts
+        .into_iter()
+        .enumerate()
+        .map(|(digits, holders)| BalanceBucket {
+            from: if digits == 0 { "0".to_string() } else { 10u128.pow(digits as u32 - 1).to_string() },
+            to: (digits < last).then(|| 10u128.pow(digits as u32).to_string()),
+            holders,
+        })
+        .collect()
+}
+
+fn supply_series(txn: &ReadTransaction, tick: &str, supply: u128, holders: usize) -> Result<Vec<SupplyPoint>> {
+    let tip = txn.open_table(BLOCKS)?.last()?.map(|(k, _)| k.value()).unwrap_or(0);
+    let mut series = Vec::new();
+    let since = txn.open_table(STATS)?.get(CHECKPOINTS_SINCE_KEY)?.map(|v| v.value());
+    if let Some(since) = since {
+        let epochs: Vec<u64> = (since..=tip).step_by(BALANCE_EPOCH as usize).collect();
+
+        // Holder count changes per epoch: +1 when an address turns positive, -1 when it empties
+        let mut delta: BTreeMap<u64, i64> = BTreeMap::new();
+        let start = format!("{}:", tick);
+        let stop = format!("{};", tick);
+        let checkpoints = txn.open_table(BALANCE_CHECKPOINTS)?;
+        // Rows sort by address then epoch
+        let mut current: Option<(String, bool)> = None;
+        for item in checkpoints.range::<&str>(start.as_str()..stop.as_str())? {
+            let (k, v) = item?;
+            let Some((address, epoch)) = k.value()[start.len()..].rsplit_once(':') else {
+                continue;
+            };
+            let Ok(epoch) = epoch.parse::<u64>() else {
+                continue;
+            };
+            let positive = serde_json::from_str::<Balance>(v.value()).is_ok_and(|b| !b.overall.is_zero());
+            let was = match &current {
+                Some((prev, was)) if prev == address => *was,
+                _ => false,
+            };
+            if positive != was {
+                *delta.entry(epoch).or_default() += if positive { 1 } else { -1 };
+            }
+            current = Some((address.to_string(), positive));
+        }
+
+        // Minted supply per height from the ticker's history ("<tick>:<height>:<seq>")
+        let mut minted: BTreeMap<u64, u128> = BTreeMap::new();
+        for item in txn.open_table(ZRC20_TICK_HISTORY)?.range::<&str>(start.as_str()..stop.as_str())? {
+            let (k, v) = item?;
+            let row: serde_json::Value = serde_json::from_str(v.value()).unwrap_or_default();
+            if row["op"] != "mint" {
+                continue;
+            }
+            let height = k.value().rsplit(':').nth(1).and_then(|h| h.parse().ok()).unwrap_or(0);
+            let amt: u128 = row["amt"].as_str().and_then(|a| a.parse().ok()).unwrap_or(0);
+            *minted.entry(height).or_default() += amt;
+        }
+
+        let mut minted_iter = minted.into_iter().peekable();
+        let mut delta_iter = delta.into_iter().peekable();
+        let (mut running_supply, mut running_holders) = (0u128, 0i64);
+        for epoch in epochs {
+            while let Some((_, amt)) = minted_iter.next_if(|(h, _)| *h <= epoch) {
+                running_supply += amt;
+            }
+            while let Some((_, d)) = delta_iter.next_if(|(e, _)| *e <= epoch) {
+                running_holders += d;
+            }
+            series.push(SupplyPoint {
+                height: epoch,
+                supply_base_units: running_supply.to_string(),
+                holders: running_holders.max(0) as usize,
+            });
+        }
+    }
+    if series.last().is_none_or(|p| p.height < tip) {
+        series.push(SupplyPoint {
+            height: tip,
+            supply_base_units: supply.to_string(),
+            holders,
+        });
+    }
+    Ok(series)
+}
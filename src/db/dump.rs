@@ -0,0 +1,426 @@
+//! Portable, versioned dump of the index state, as JSONL or Parquet.
+//!
+//! In JSONL the first line is a header describing the dump; every following line
+//! is one row: `{"table": "...", "key": ..., "value": ...}`. JSON payloads are
+//! embedded verbatim so balances and other u128 fields survive without precision
+//! loss. Parquet files carry the same rows as `table`, `key` and `value` string
+//! columns, `key` and `value` holding the JSON text of the JSONL fields, with the
+//! header in the file's key-value metadata. Operator tables (webhooks, verified
+//! collections, name grants) are included. Undo records are machine-local history
+//! and are not exported, so an imported database cannot roll back below its tip.
+
+use super::*;
+use parquet::basic::{Compression, ZstdLevel};
+use parquet::data_type::{ByteArray, ByteArrayType};
+use parquet::file::properties::WriterProperties;
+use parquet::file::reader::FileReader;
+use parquet::file::serialized_reader::SerializedFileReader;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::format::KeyValue;
+use parquet::record::RowAccessor;
+use serde_json::value::RawValue;
+use std::io::{BufRead, Write};
+
+/// Bumped whenever the line format itself changes.
+const DUMP_FORMAT_VERSION: u64 = 1;
+const DUMP_FORMAT: &str = "zord-dump";
+// Parquet key-value metadata entry holding the JSON header
+const PARQUET_HEADER_KEY: &str = "zord.header";
+// Rows buffered per Parquet row group
+const PARQUET_ROW_GROUP: usize = 65_536;
+const PARQUET_SCHEMA: &str = "
+    message zord_dump {
+        required binary table (UTF8);
+        required binary key (UTF8);
+        required binary value (UTF8);
+    }
+";
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct DumpHeader {
+    format: String,
+    version: u64,
+    schema_version: u64,
+    height: Option<u64>,
+}
+
+#[derive(serde::Serialize)]
+struct DumpRow<'a> {
+    table: &'a str,
+    key: serde_json::Value,
+    value: &'a RawValue,
+}
+
+#[derive(serde::Deserialize)]
+struct DumpRowOwned {
+    table: String,
+    key: serde_json::Value,
+    value: Box<RawValue>,
+}
+
+impl Db {
+    /// Stream every table (except undo history) from a single snapshot as JSONL.
+    /// Returns the number of rows written.
+    pub fn export_jsonl(&self, mut out: impl Write) -> Result<u64> {
+        let snapshot = self.snapshot()?;
+        writeln!(out, "{}", serde_json::to_string(&dump_header(&snapshot)?)?)?;
+        let rows = dump_rows(&snapshot, |table, key, value| {
+            writeln!(out, "{}", serde_json::to_string(&DumpRow { table, key, value })?)?;
+            Ok(())
+        })?;
+        out.flush()?;
+        Ok(rows)
+    }
+
+    /// Write the rows of `export_jsonl` to a Parquet file, `PARQUET_ROW_GROUP` rows
+    /// per row group. Returns the number of rows written.
+    pub fn export_parquet(&self, out: std::fs::File) -> Result<u64> {
+        let snapshot = self.snapshot()?;
+        let header = serde_json::to_string(&dump_header(&snapshot)?)?;
+        let props = WriterProperties::builder()
+            .set_compression(Compression::ZSTD(ZstdLevel::default()))
+            .set_key_value_metadata(Some(vec![KeyValue::new(PARQUET_HEADER_KEY.to_string(), header)]))
+            .build();
+        let schema = parquet::schema::parser::parse_message_type(PARQUET_SCHEMA)?;
+        let mut writer = SerializedFileWriter::new(out, Arc::new(schema), Arc::new(props))?;
+
+        let mut columns: [Vec<ByteArray>; 3] = Default::default();
+        let rows = dump_rows(&snapshot, |table, key, value| {
+            columns[0].push(table.into());
+            columns[1].push(key.to_string().as_str().into());
+            columns[2].push(value.get().into());
+            if columns[0].len() >= PARQUET_ROW_GROUP {
+                write_row_group(&mut writer, &mut columns)?;
+            }
+            Ok(())
+        })?;
+        if !columns[0].is_empty() {
+            write_row_group(&mut writer, &mut columns)?;
+        }
+        writer.close()?;
+        Ok(rows)
+    }
+
+    /// Load a JSONL dump into an empty database in one write transaction.
+    /// Returns the number of rows imported.
+    pub fn import_jsonl(&self, input: impl BufRead) -> Result<u64> {
+        let mut lines = input.lines();
+        let header: DumpHeader = match lines.next() {
+            Some(line) => serde_json::from_str(&line?)?,
+            None => return Err(anyhow::anyhow!("Empty dump")),
+        };
+        let rows = lines.enumerate().filter_map(|(n, line)| {
+            let row = line.map_err(anyhow::Error::from).and_then(|line| {
+                if line.trim().is_empty() {
+                    return Ok(None);
+                }
+                Ok(Some(serde_json::from_str::<DumpRowOwned>(&line)?))
+            });
+            // Line 1 is the header
+            row.map_err(|e| anyhow::anyhow!("Line {}: {}", n + 2, e))
+                .transpose()
+                .map(|row| (format!("Line {}", n + 2), row))
+        });
+        self.import_rows(header, rows)
+    }
+
+    /// Load a Parquet dump written by `export_parquet` into an empty database.
+    /// Returns the number of rows imported.
+    pub fn import_parquet(&self, input: std::fs::File) -> Result<u64> {
+        let reader = SerializedFileReader::new(input)?;
+        let header = reader
+            .metadata()
+            .file_metadata()
+            .key_value_metadata()
+            .and_then(|entries| entries.iter().find(|kv| kv.key == PARQUET_HEADER_KEY))
+            .and_then(|kv| kv.value.as_deref())
+            .ok_or_else(|| anyhow::anyhow!("Parquet file has no {} metadata", PARQUET_HEADER_KEY))?;
+        let header: DumpHeader = serde_json::from_str(header)?;
+        let rows = reader.get_row_iter(None)?.enumerate().map(|(n, row)| {
+            let row = row.map_err(anyhow::Error::from).and_then(|row| {
+                Ok(DumpRowOwned {
+                    table: row.get_string(0)?.clone(),
+                    key: serde_json::from_str(row.get_string(1)?)?,
+                    value: RawValue::from_string(row.get_string(2)?.clone())?,
+                })
+            });
+            (format!("Row {}", n + 1), row)
+        });
+        self.import_rows(header, rows)
+    }
+
+    // Shared import path: `rows` pairs each row with where it came from, for errors
+    fn import_rows(
+        &self,
+        header: DumpHeader,
+        rows: impl Iterator<Item = (String, Result<DumpRowOwned>)>,
+    ) -> Result<u64> {
+        if self.get_latest_indexed_height()?.is_some() {
+            return Err(anyhow::anyhow!(
+                "Refusing to import into a non-empty database; point DB_PATH at a new file"
+            ));
+        }
+        if header.format != DUMP_FORMAT || header.version > DUMP_FORMAT_VERSION {
+            return Err(anyhow::anyhow!(
+                "Unsupported dump format {} v{}",
+                header.format,
+                header.version
+            ));
+        }
+        if header.schema_version > migrations::current_version() {
+            return Err(anyhow::anyhow!(
+                "Dump was written by schema v{}, newer than this binary (v{})",
+                header.schema_version,
+                migrations::current_version()
+            ));
+        }
+
+        let write_txn = self.begin_write()?;
+        let mut count = 0;
+        for (at, row) in rows {
+            let row = row.map_err(|e| anyhow::anyhow!("{}: {}", at, e))?;
+            import_row(&write_txn, &row).map_err(|e| anyhow::anyhow!("{}: {}", at, e))?;
+            count += 1;
+        }
+        {
+            // The dump reflects an older layout; rerun migrations on next open
+            let mut status = write_txn.open_table(STATUS)?;
+            status.insert(migrations::SCHEMA_VERSION_KEY, header.schema_version)?;
+            // Undo history stays behind, so nothing below the imported tip can be unwound
+            if let Some(height) = header.height {
+                status.insert(UNDO_FLOOR_KEY, height)?;
+            }
+        }
+        self.commit(write_txn)?;
+        migrations::run(&self.db, false)?;
+
+        tracing::info!("Imported {} rows (tip {:?})", count, header.height);
+        Ok(count)
+    }
+}
+
+fn dump_header(snapshot: &scan::Snapshot<'_>) -> Result<DumpHeader> {
+    Ok(DumpHeader {
+        format: DUMP_FORMAT.to_string(),
+        version: DUMP_FORMAT_VERSION,
+        schema_version: migrations::current_version(),
+        height: snapshot.height()?,
+    })
+}
+
+// Hand every exported row to `emit` as (table, key, value JSON); returns the row count
+fn dump_rows(
+    snapshot: &scan::Snapshot<'_>,
+    mut emit: impl FnMut(&str, serde_json::Value, &RawValue) -> Result<()>,
+) -> Result<u64> {
+    let read_txn = snapshot.txn();
+    let mut rows = 0;
+    for def in [BLOCKS, INSCRIPTION_NUMBERS] {
+        let table = read_txn.open_table(def)?;
+        for item in table.iter()? {
+            let (k, v) = item?;
+            emit(def.name(), serde_json::json!(k.value()), &text_value(v.value())?)?;
+            rows += 1;
+        }
+    }
+    for def in [STATS, STATUS, CONTENT_REFS, ACTIVITY_STATS] {
+        let table = read_txn.open_table(def)?;
+        for item in table.iter()? {
+            let (k, v) = item?;
+            let value = RawValue::from_string(v.value().to_string())?;
+            emit(def.name(), serde_json::json!(k.value()), &value)?;
+            rows += 1;
+        }
+    }
+    {
+        // Payloads are binary; export them as hex strings
+        let table = read_txn.open_table(CONTENT_BLOBS)?;
+        for item in table.iter()? {
+            let (k, v) = item?;
+            let value = RawValue::from_string(serde_json::to_string(&hex::encode(v.value()))?)?;
+            emit(CONTENT_BLOBS.name(), serde_json::json!(k.value()), &value)?;
+            rows += 1;
+        }
+    }
+    for def in UNDOABLE_TABLES.into_iter().chain(OPERATOR_TABLES) {
+        for batch in snapshot.batches(def, scan::SCAN_BATCH) {
+            for (k, v) in batch? {
+                emit(def.name(), serde_json::json!(k), &text_value(&v)?)?;
+                rows += 1;
+            }
+        }
+    }
+    Ok(rows)
+}
+
+fn write_row_group(writer: &mut SerializedFileWriter<std::fs::File>, columns: &mut [Vec<ByteArray>; 3]) -> Result<()> {
+    let mut group = writer.next_row_group()?;
+    for values in columns.iter_mut() {
+        let mut column = group
+            .next_column()?
+            .ok_or_else(|| anyhow::anyhow!("Parquet schema has fewer columns than a dump row"))?;
+        column.typed::<ByteArrayType>().write_batch(values, None, None)?;
+        column.close()?;
+        values.clear();
+    }
+    group.close()?;
+    Ok(())
+}
+
+// Embed JSON documents verbatim; everything else becomes a JSON string
+fn text_value(value: &str) -> Result<Box<RawValue>> {
+    let trimmed = value.trim_start();
+    if (trimmed.starts_with('{') || trimmed.starts_with('['))
+        && serde_json::from_str::<&RawValue>(value).is_ok()
+    {
+        Ok(RawValue::from_string(value.to_string())?)
+    } else {
+        Ok(RawValue::from_string(serde_json::to_string(value)?)?)
+    }
+}
+
+fn import_row(txn: &WriteTransaction, row: &DumpRowOwned) -> Result<()> {
+    let raw = row.value.get();
+    // Strings were quoted on export; documents were embedded as-is
+    let text = || -> Result<String> {
+        if raw.starts_with('"') {
+            Ok(serde_json::from_str::<String>(raw)?)
+        } else {
+            Ok(raw.to_string())
+        }
+    };
+
+    if let Some(def) = [BLOCKS, INSCRIPTION_NUMBERS]
+        .into_iter()
+        .find(|t| t.name() == row.table)
+    {
+        let key = row
+            .key
+            .as_u64()
+            .ok_or_else(|| anyhow::anyhow!("Expected numeric key for {}", row.table))?;
+        txn.open_table(def)?.insert(key, text()?.as_str())?;
+    } else if let Some(def) = [STATS, STATUS, CONTENT_REFS, ACTIVITY_STATS]
+        .into_iter()
+        .find(|t| t.name() == row.table)
+    {
+        let key = row
+            .key
+            .as_str()
+            .ok_or_else(|| anyhow::anyhow!("Expected string key for {}", row.table))?;
+        let value: u64 = serde_json::from_str(raw)?;
+        txn.open_table(def)?.insert(key, value)?;
+    } else if let Some(def) = [CONTENT_BLOBS, CONTENT]
+        .into_iter()
+        .find(|t| t.name() == row.table)
+    {
+        // `content` rows come from schema v2 dumps; migrations rekey them after import
+        let key = row
+            .key
+            .as_str()
+            .ok_or_else(|| anyhow::anyhow!("Expected string key for {}", row.table))?;
+        let bytes = hex::decode(serde_json::from_str::<String>(raw)?)?;
+        txn.open_table(def)?.insert(key, bytes.as_slice())?;
+    } else if let Some(def) = UNDOABLE_TABLES
+        .into_iter()
+        .chain(OPERATOR_TABLES)
+        .find(|t| t.name() == row.table)
+    {
+        let key = row
+            .key
+            .as_str()
+            .ok_or_else(|| anyhow::anyhow!("Expected string key for {}", row.table))?;
+        txn.open_table(def)?.insert(key, text()?.as_str())?;
+    } else {
+        return Err(anyhow::anyhow!("Unknown table {}", row.table));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zrc20::amount::{Amount, Delta};
+
+    fn temp_db(name: &str) -> (Db, std::path::PathBuf) {
+        let dir = std::env::temp_dir().join(format!("zord-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        (Db::new(dir.join("zord.db"), false).expect("open db"), dir)
+    }
+
+    fn export(db: &Db) -> String {
+        let mut out = Vec::new();
+        db.export_jsonl(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    // One block with shared and binary payloads, a token, a balance past u64, a name,
+    // and rows in the operator tables
+    fn populated(name: &str) -> (Db, std::path::PathBuf) {
+        let (db, dir) = temp_db(name);
+        let writer = db.block_writer(1, 0).unwrap();
+        let meta = |id: &str| serde_json::json!({ "id": id, "content_type": "text/plain", "block_height": 1 }).to_string();
+        writer.insert_inscription("i1", &meta("i1"), b"same").unwrap();
+        writer.insert_inscription("i2", &meta("i2"), b"same").unwrap();
+        writer.insert_inscription("i3", &meta("i3"), &[0, 159, 255, b'\n', b'"']).unwrap();
+        writer.deploy_token("huge", r#"{"tick":"huge","max":"1","dec":"0"}"#).unwrap();
+        let max = Amount::parse(&u128::MAX.to_string(), 0).unwrap();
+        writer.update_balance("t1holder", "huge", Delta::Credit(max), Delta::Credit(max)).unwrap();
+        writer.register_name("alice.zec", r#"{"owner":"t1holder"}"#, &"c".repeat(64), 0, 1).unwrap();
+        writer.commit("block1").unwrap();
+        db.put_webhook("hook", r#"{"url":"https://example.com/hook"}"#).unwrap();
+        db.put_name_grant("zcash.zec", "t1holder").unwrap();
+        (db, dir)
+    }
+
+    // Everything comes back except the undo history, so the floor moves up to the tip
+    fn expected_after_import(dump: &str) -> String {
+        dump.replace(r#""key":"undo_floor","value":0"#, r#""key":"undo_floor","value":1"#)
+    }
+
+    #[test]
+    fn jsonl_dump_round_trips() {
+        let (source, dir) = populated("dump-jsonl");
+        let dump = export(&source);
+        assert!(dump.contains(r#""table":"webhooks""#));
+
+        let copy = Db::new(dir.join("copy.db"), false).unwrap();
+        let rows = copy.import_jsonl(dump.as_bytes()).unwrap();
+        assert_eq!(rows as usize, dump.lines().count() - 1);
+        assert_eq!(export(&copy), expected_after_import(&dump));
+        assert_eq!(copy.get_content("i3").unwrap().unwrap(), [0, 159, 255, b'\n', b'"']);
+        assert_eq!(copy.get_balance("t1holder", "huge").unwrap().overall.base_units(), u128::MAX);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn parquet_dump_carries_the_same_rows() {
+        let (source, dir) = populated("dump-parquet");
+        let dump = export(&source);
+        let path = dir.join("dump.parquet");
+        source.export_parquet(std::fs::File::create(&path).unwrap()).unwrap();
+
+        let copy = Db::new(dir.join("copy.db"), false).unwrap();
+        copy.import_parquet(std::fs::File::open(&path).unwrap()).unwrap();
+        assert_eq!(export(&copy), expected_after_import(&dump));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn imports_refuse_non_empty_databases_and_newer_dumps() {
+        let (source, dir) = populated("dump-refuse");
+        let dump = export(&source);
+        assert!(source.import_jsonl(dump.as_bytes()).is_err());
+
+        let newer = dump.replacen(
+            &format!(r#""schema_version":{}"#, migrations::current_version()),
+            &format!(r#""schema_version":{}"#, migrations::current_version() + 1),
+            1,
+        );
+        let copy = Db::new(dir.join("copy.db"), false).unwrap();
+        assert!(copy.import_jsonl(newer.as_bytes()).is_err());
+        assert!(copy.import_jsonl(&b""[..]).is_err());
+        // A refused import writes nothing
+        assert_eq!(copy.get_latest_indexed_height().unwrap(), None);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,191 @@
+//! Secondary indexes behind the filtered inscriptions feed.
+//!
+//! Every inscription gets one `INSCRIPTION_FEED` key per facet, each pointing at
+//! its id. Chronological facets end in `<height>:<number>` so a height window is
+//! a single key range; the size facet orders by payload length instead. A query
+//! walks the most selective facet it was given and checks the remaining filters
+//! against the metadata.
+
+use super::*;
+
+// Index entries examined per request before handing back a cursor
+const MAX_FEED_SCAN: usize = 5000;
+
+/// Coarse content category shown in listings and accepted by the `category` filter.
+pub fn classify_mime(content_type: &str) -> &'static str {
+    let lower = content_type.to_lowercase();
+    if lower == "image/png" {
+        "png"
+    } else if lower == "image/jpeg" || lower == "image/jpg" {
+        "jpeg"
+    } else if lower == "image/gif" {
+        "gif"
+    } else if lower == "image/svg+xml" {
+        "svg"
+    } else if lower == "text/html" || lower == "application/xhtml+xml" {
+        "html"
+    } else if lower == "text/javascript" || lower == "application/javascript" {
+        "javascript"
+    } else if lower.starts_with("text/") {
+        "text"
+    } else if lower.starts_with("audio/") {
+        "audio"
+    } else if lower.starts_with("video/") {
+        "video"
+    } else if lower.starts_with("model/") {
+        "3d"
+    } else if lower.starts_with("image/") {
+        "image"
+    } else {
+        "binary"
+    }
+}
+
+/// Filters accepted by `Db::list_inscriptions_filtered`; unset fields match everything.
+#[derive(Clone, Debug, Default)]
+pub struct FeedFilter {
+    pub category: Option<String>,
+    /// Exact MIME type, compared without parameters and case-insensitively
+    pub content_type: Option<String>,
+    /// Creating (sender) address
+    pub address: Option<String>,
+    pub shielded: Option<bool>,
+    pub from_height: Option<u64>,
+    pub to_height: Option<u64>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FeedSort {
+    #[default]
+    Newest,
+    Oldest,
+    /// Largest payload first
+    Largest,
+}
+
+/// `(id, metadata)` rows plus the cursor key to continue from.
+pub type FeedPage = (Vec<(String, String)>, Option<String>);
+
+impl FeedFilter {
+    fn matches(&self, meta: &serde_json::Value) -> bool {
+        let content_type = meta["content_type"].as_str().unwrap_or("");
+        let sender = meta["sender"].as_str().unwrap_or("");
+        let height = meta["block_height"].as_u64().unwrap_or(0);
+        self.category.as_deref().is_none_or(|c| classify_mime(content_type) == c)
+            && self.content_type.as_deref().is_none_or(|t| simple_type(content_type) == simple_type(t))
+            && self.address.as_deref().is_none_or(|a| sender == a)
+            && self.shielded.is_none_or(|s| sender.starts_with('z') == s)
+            && self.from_height.is_none_or(|h| height >= h)
+            && self.to_height.is_none_or(|h| height <= h)
+    }
+
+    // Facet prefix to walk: the narrowest filter given, else every inscription by height
+    fn driving_prefix(&self) -> String {
+        if let Some(address) = &self.address {
+            format!("sender:{}:", address)
+        } else if let Some(content_type) = &self.content_type {
+            format!("type:{}:", simple_type(content_type))
+        } else if let Some(category) = &self.category {
+            format!("category:{}:", category)
+        } else if let Some(shielded) = self.shielded {
+            format!("shielded:{}:", shielded as u8)
+        } else {
+            "height:".to_string()
+        }
+    }
+}
+
+fn simple_type(content_type: &str) -> String {
+    content_type.split(';').next().unwrap_or("").trim().to_lowercase()
+}
+
+// Feed index keys for one inscription; every key maps to its id
+pub(super) fn feed_keys(meta: &serde_json::Value, number: u64) -> Vec<String> {
+    let content_type = meta["content_type"].as_str().unwrap_or("");
+    let sender = meta["sender"].as_str();
+    let height = meta["block_height"].as_u64().unwrap_or(0);
+    let length = meta["content_length"].as_u64().unwrap_or(0);
+    let position = format!("{:010}:{:012}", height, number);
+
+    let mut keys = vec![
+        format!("height:{}", position),
+        format!("category:{}:{}", classify_mime(content_type), position),
+        format!("type:{}:{}", simple_type(content_type), position),
+        format!("size:{:012}:{:012}", length, number),
+    ];
+    if let Some(sender) = sender {
+        keys.push(format!("sender:{}:{}", sender, position));
+        keys.push(format!("shielded:{}:{}", sender.starts_with('z') as u8, position));
+    }
+    keys
+}
+
+impl Db {
+    /// One page of inscriptions matching `filter`, ordered by `sort`, starting after the
+    /// cursor key returned by a previous call. Rows are `(id, metadata)`; the cursor is
+    /// set whenever the walk stopped before the end of the index.
+    pub fn list_inscriptions_filtered(
+        &self,
+        filter: &FeedFilter,
+        sort: FeedSort,
+        limit: usize,
+        after: Option<&str>,
+    ) -> Result<FeedPage> {
+        let prefix = match sort {
+            FeedSort::Largest => "size:".to_string(),
+            _ => filter.driving_prefix(),
+        };
+        let (mut lower, mut upper) = if sort == FeedSort::Largest {
+            (prefix.clone(), format!("{};", prefix.trim_end_matches(':')))
+        } else {
+            let from = filter.from_height.unwrap_or(0);
+            let to = filter.to_height.map(|h| format!("{}{:010};", prefix, h));
+            (
+                format!("{}{:010}:", prefix, from),
+                to.unwrap_or_else(|| format!("{};", prefix.trim_end_matches(':'))),
+            )
+        };
+        // The cursor narrows whichever end the walk starts from
+        if let Some(key) = after {
+            match sort {
+                FeedSort::Oldest if key >= lower.as_str() => lower = format!("{}\0", key),
+                FeedSort::Newest | FeedSort::Largest if key < upper.as_str() => upper = key.to_string(),
+                _ => {}
+            }
+        }
+        if lower >= upper {
+            return Ok((Vec::new(), None));
+        }
+
+        let read_txn = self.db.begin_read()?;
+        let index = read_txn.open_table(INSCRIPTION_FEED)?;
+        let inscriptions = read_txn.open_table(INSCRIPTIONS)?;
+        let range = index.range::<&str>(lower.as_str()..upper.as_str())?;
+        let entries: Box<dyn Iterator<Item = _>> = match sort {
+            FeedSort::Oldest => Box::new(range),
+            _ => Box::new(range.rev()),
+        };
+
+        let mut rows = Vec::new();
+        let mut scanned = 0;
+        let mut last = None;
+        for item in entries {
+            let (k, v) = item?;
+            scanned += 1;
+            last = Some(k.value().to_string());
+            let id = v.value();
+            if let Some(raw) = inscriptions.get(id)? {
+                let meta: serde_json::Value = serde_json::from_str(raw.value()).unwrap_or_default();
+                if filter.matches(&meta) {
+                    rows.push((id.to_string(), raw.value().to_string()));
+                }
+            }
+            if rows.len() >= limit || scanned >= MAX_FEED_SCAN {
+                break;
+            }
+        }
+
+        let exhausted = rows.len() < limit && scanned < MAX_FEED_SCAN;
+        Ok((rows, if exhausted { None } else { last }))
+    }
+}
@@ -0,0 +1,159 @@
+//! Optional inverted index over text-like inscription bodies.
+//!
+//! Each distinct word of a payload becomes a `CONTENT_TERMS` posting keyed
+//! `<term>:<inscription id>`. Queries walk the postings of their longest term,
+//! probe the others by point lookup and confirm the phrase against the payload.
+
+use super::*;
+use std::collections::BTreeSet;
+
+// Payload bytes tokenized per inscription; the tail of larger bodies is not indexed
+const MAX_INDEXED_BYTES: usize = 64 * 1024;
+const MAX_TERMS: usize = 512;
+const MIN_TERM_LEN: usize = 2;
+const MAX_TERM_LEN: usize = 40;
+// Postings examined per query before handing back a cursor
+const MAX_CANDIDATES: usize = 5000;
+
+/// Whether payloads of `content_type` are tokenized.
+pub fn is_text_like(content_type: &str) -> bool {
+    let simple = content_type.split(';').next().unwrap_or("").trim().to_lowercase();
+    simple.starts_with("text/") || simple == "application/json" || simple.ends_with("+json")
+}
+
+/// Lower-cased alphanumeric words of `text`, deduplicated.
+pub(super) fn terms(text: &str) -> BTreeSet<String> {
+    let mut terms = BTreeSet::new();
+    for word in text.split(|c: char| !c.is_alphanumeric()) {
+        let len = word.chars().count();
+        if (MIN_TERM_LEN..=MAX_TERM_LEN).contains(&len) {
+            terms.insert(word.to_lowercase());
+            if terms.len() >= MAX_TERMS {
+                break;
+            }
+        }
+    }
+    terms
+}
+
+// Decode the indexed prefix of a payload, dropping a code point cut at the limit
+pub(super) fn indexed_text(content: &[u8]) -> Option<&str> {
+    let slice = &content[..content.len().min(MAX_INDEXED_BYTES)];
+    match std::str::from_utf8(slice) {
+        Ok(text) => Some(text),
+        Err(e) if e.error_len().is_none() => std::str::from_utf8(&slice[..e.valid_up_to()]).ok(),
+        Err(_) => None,
+    }
+}
+
+impl Db {
+    /// Inscription ids whose text contains `phrase` (case-insensitive), in id order
+    /// starting after `after`. Also returns a cursor when more postings remain.
+    pub fn search_content(
+        &self,
+        phrase: &str,
+        limit: usize,
+        after: Option<&str>,
+    ) -> Result<(Vec<String>, Option<String>)> {
+        let needle = phrase.trim().to_lowercase();
+        let query_terms = terms(&needle);
+        // Longer words tend to be rarer, so their posting lists are shorter
+        let Some(lead) = query_terms.iter().max_by_key(|t| t.len()).cloned() else {
+            return Ok((Vec::new(), None));
+        };
+
+        let read_txn = self.db.begin_read()?;
+        let postings = read_txn.open_table(CONTENT_TERMS)?;
+        let inscriptions = read_txn.open_table(INSCRIPTIONS)?;
+        let blobs = read_txn.open_table(CONTENT_BLOBS)?;
+
+        let prefix = format!("{}:", lead);
+        let end = format!("{};", lead);
+        let start = match after {
+            Some(id) => Bound::Excluded(format!("{}{}", prefix, id)),
+            None => Bound::Included(prefix.clone()),
+        };
+        let bounds = (start.as_ref().map(|s| s.as_str()), Bound::Excluded(end.as_str()));
+
+        let mut ids = Vec::new();
+        let mut scanned = 0;
+        let mut last = None;
+        for item in postings.range::<&str>(bounds)? {
+            let (k, _) = item?;
+            let id = k.value()[prefix.len()..].to_string();
+            scanned += 1;
+            last = Some(id.clone());
+
+            let mut matched = true;
+            for term in query_terms.iter().filter(|t| **t != lead) {
+                if postings.get(format!("{}:{}", term, id).as_str())?.is_none() {
+                    matched = false;
+                    break;
+                }
+            }
+            // Postings are words; the payload decides whether the phrase itself occurs
+            if matched {
+                let digest = match inscriptions.get(id.as_str())? {
+                    Some(raw) => serde_json::from_str::<serde_json::Value>(raw.value())?["content_sha256"]
+                        .as_str()
+                        .map(|s| s.to_string()),
+                    None => None,
+                };
+                let body = match digest {
+                    Some(digest) => blobs.get(digest.as_str())?.map(|v| v.value().to_vec()),
+                    None => None,
+                };
+                matched = body
+                    .as_deref()
+                    .and_then(indexed_text)
+                    .is_some_and(|text| text.to_lowercase().contains(&needle));
+            }
+            if matched {
+                ids.push(id);
+            }
+            if ids.len() >= limit || scanned >= MAX_CANDIDATES {
+                break;
+            }
+        }
+
+        let exhausted = ids.len() < limit && scanned < MAX_CANDIDATES;
+        Ok((ids, if exhausted { None } else { last }))
+    }
+
+    /// Tokenize every stored text inscription, for databases that ran without
+    /// `CONTENT_INDEX`. Existing postings are rewritten unchanged. Returns the number of
+    /// inscriptions indexed.
+    pub fn backfill_content_terms(&self) -> Result<u64> {
+        let mut indexed = 0;
+        let snapshot = self.snapshot()?;
+        for batch in snapshot.batches(INSCRIPTIONS, SCAN_BATCH) {
+            let mut pending: Vec<(String, BTreeSet<String>)> = Vec::new();
+            for (id, meta) in batch? {
+                let meta: serde_json::Value = serde_json::from_str(&meta)?;
+                if !is_text_like(meta["content_type"].as_str().unwrap_or("")) {
+                    continue;
+                }
+                let Some(body) = self.get_content(&id)? else {
+                    continue;
+                };
+                if let Some(text) = indexed_text(&body) {
+                    pending.push((id, terms(text)));
+                }
+            }
+
+            let write_txn = self.begin_write()?;
+            {
+                let mut postings = write_txn.open_table(CONTENT_TERMS)?;
+                for (id, terms) in &pending {
+                    for term in terms {
+                        postings.insert(format!("{}:{}", term, id).as_str(), "")?;
+                    }
+                }
+            }
+            self.commit(write_txn)?;
+            indexed += pending.len() as u64;
+        }
+        tracing::info!("Indexed content terms for {} inscriptions", indexed);
+        Ok(indexed)
+    }
+}
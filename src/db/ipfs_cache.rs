@@ -0,0 +1,57 @@
+//! Cache of IPFS objects fetched through `IPFS_GATEWAY`.
+//!
+//! Entries are keyed by the IPFS path (`<cid>/<path>`) and stored as the fetch
+//! time, the gateway's content type and the body. They are never journaled:
+//! a path names immutable content, and the TTL only bounds how long a gateway
+//! error page or a mutable path can be served.
+
+use super::*;
+
+/// An object as last fetched from the gateway.
+pub struct CachedObject {
+    /// Unix seconds.
+    pub fetched_at: u64,
+    pub content_type: String,
+    pub bytes: Vec<u8>,
+}
+
+impl CachedObject {
+    // fetched_at (8 bytes, big endian), content type length (1 byte), content type, body
+    fn encode(&self) -> Vec<u8> {
+        let content_type = &self.content_type.as_bytes()[..self.content_type.len().min(255)];
+        let mut out = Vec::with_capacity(9 + content_type.len() + self.bytes.len());
+        out.extend_from_slice(&self.fetched_at.to_be_bytes());
+        out.push(content_type.len() as u8);
+        out.extend_from_slice(content_type);
+        out.extend_from_slice(&self.bytes);
+        out
+    }
+
+    fn decode(raw: &[u8]) -> Option<Self> {
+        let fetched_at = u64::from_be_bytes(raw.get(..8)?.try_into().ok()?);
+        let type_len = *raw.get(8)? as usize;
+        let content_type = std::str::from_utf8(raw.get(9..9 + type_len)?).ok()?.to_string();
+        Some(Self { fetched_at, content_type, bytes: raw[9 + type_len..].to_vec() })
+    }
+}
+
+impl Db {
+    pub fn get_ipfs_object(&self, path: &str) -> Result<Option<CachedObject>> {
+        let read_txn = self.db.begin_read()?;
+        let table = read_txn.open_table(IPFS_CACHE)?;
+        let val = table.get(path)?.and_then(|v| CachedObject::decode(v.value()));
+        Ok(val)
+    }
+
+    /// Store a fetched object; fails on read-only replicas, which fetch per request.
+    pub fn put_ipfs_object(&self, path: &str, object: &CachedObject) -> Result<()> {
+        let write_txn = self.begin_write()?;
+        {
+            let mut table = write_txn.open_table(IPFS_CACHE)?;
+            table.insert(path, object.encode().as_slice())?;
+        }
+        // Like thumbnails, cached objects never pass through the read cache
+        write_txn.commit()?;
+        Ok(())
+    }
+}
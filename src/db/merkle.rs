@@ -0,0 +1,267 @@
+//! Merkle commitments to ZRC-20 balances.
+//!
+//! At the end of every balance checkpoint epoch the writer stores, per ticker, the
+//! root over its positive holders sorted by address:
+//!
+//! - leaf = sha256(0x00 || address || 0x00 || overall balance in base units, decimal)
+//! - node = sha256(0x01 || left || right); the last node of an odd level moves up unchanged
+//! - the root of a ticker without holders is 32 zero bytes
+//!
+//! `/api/v1/zrc20/token/:tick/proof/:address` rebuilds the leaves for a committed
+//! epoch from the checkpoints, checks them against the stored root and returns the
+//! sibling path, so a client can verify a balance with nothing but the root.
+//!
+//! The same pass stores a checksum of the whole ZRC-20 state for `/api/v1/zrc20/checksum`:
+//! sha256 over one line per ticker in byte order,
+//! `tick 0x00 max 0x00 lim 0x00 dec 0x00 deployer 0x00 supply 0x00 burned 0x00 root 0x0a`,
+//! with amounts in base units as decimal and the root in hex. Two indexers agree on
+//! it exactly when they agree on every deploy, supply, burn and balance.
+
+use super::*;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+type Hash = [u8; 32];
+
+fn leaf_hash(address: &str, overall: u128) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update([0u8]);
+    hasher.update(address.as_bytes());
+    hasher.update([0u8]);
+    hasher.update(overall.to_string().as_bytes());
+    hasher.finalize().into()
+}
+
+fn node_hash(left: &Hash, right: &Hash) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update([1u8]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+// Each level of the tree, leaves first and the root level last
+fn levels(leaves: Vec<Hash>) -> Vec<Vec<Hash>> {
+    let mut levels = vec![leaves];
+    while levels.last().is_some_and(|level| level.len() > 1) {
+        let level = levels.last().expect("checked above");
+        let next = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => node_hash(left, right),
+                [single] => *single,
+                _ => unreachable!("chunks of two"),
+            })
+            .collect();
+        levels.push(next);
+    }
+    levels
+}
+
+fn root_of(levels: &[Vec<Hash>]) -> Hash {
+    levels.last().and_then(|level| level.first()).copied().unwrap_or([0u8; 32])
+}
+
+/// Root committed for a ticker at one epoch.
+#[derive(Debug, serde::Deserialize, serde::Serialize, utoipa::ToSchema)]
+pub struct BalanceRoot {
+    pub height: u64,
+    pub root: String,
+    pub leaves: usize,
+}
+
+/// Checksum of the ZRC-20 state at one epoch.
+#[derive(Debug, serde::Deserialize, serde::Serialize, utoipa::ToSchema)]
+pub struct StateChecksum {
+    pub height: u64,
+    pub checksum: String,
+    pub tokens: usize,
+}
+
+/// Sibling on the path from a leaf to the root.
+#[derive(Debug, serde::Serialize, utoipa::ToSchema)]
+pub struct ProofStep {
+    pub hash: String,
+    /// Whether the sibling is hashed on the `left` or the `right` of the running hash.
+    pub side: &'static str,
+}
+
+#[derive(Debug, serde::Serialize, utoipa::ToSchema)]
+pub struct BalanceProof {
+    pub tick: String,
+    pub address: String,
+    pub overall_base_units: String,
+    pub height: u64,
+    pub root: String,
+    pub leaves: usize,
+    pub index: usize,
+    pub leaf: String,
+    /// Bottom-up; levels where the node moved up without a sibling are skipped.
+    pub proof: Vec<ProofStep>,
+}
+
+// Positive holders of each ticker sorted by address: ticker -> [(address, overall)]
+fn holders_by_tick(txn: &WriteTransaction) -> Result<BTreeMap<String, Vec<(String, u128)>>> {
+    let balances = txn.open_table(BALANCES)?;
+    let mut by_tick: BTreeMap<String, Vec<(String, u128)>> = BTreeMap::new();
+    for item in balances.iter()? {
+        let (k, v) = item?;
+        let Some((address, tick)) = k.value().split_once(':') else {
+            continue;
+        };
+        let bal = serde_json::from_str::<Balance>(v.value())?;
+        if !bal.overall.is_zero() {
+            by_tick.entry(tick.to_string()).or_default().push((address.to_string(), bal.overall.base_units()));
+        }
+    }
+    for holders in by_tick.values_mut() {
+        holders.sort();
+    }
+    Ok(by_tick)
+}
+
+// Store every ticker's root and the state checksum at the end of epoch `height` inside
+// `BlockWriter::commit`, journaling into `ops`. Tickers are taken from the token table so
+// empty ones get a root too.
+pub(super) fn write_zrc20_commitments(txn: &WriteTransaction, height: u64, ops: &mut Vec<UndoOp>) -> Result<()> {
+    let mut holders = holders_by_tick(txn)?;
+    let tokens = txn.open_table(TOKENS)?;
+    let burns = txn.open_table(ZRC20_BURNS)?;
+    let mut roots = txn.open_table(ZRC20_BALANCE_ROOTS)?;
+    let mut state = Sha256::new();
+    let mut token_count = 0usize;
+    for item in tokens.iter()? {
+        let (k, v) = item?;
+        let tick = k.value();
+        let leaves: Vec<Hash> = holders
+            .remove(tick)
+            .unwrap_or_default()
+            .iter()
+            .map(|(address, overall)| leaf_hash(address, *overall))
+            .collect();
+        let count = leaves.len();
+        let entry = BalanceRoot {
+            height,
+            root: hex::encode(root_of(&levels(leaves))),
+            leaves: count,
+        };
+        let key = format!("{}:{:010}", tick, height);
+        ops.push(UndoOp::Text {
+            table: ZRC20_BALANCE_ROOTS.name().to_string(),
+            key: key.clone(),
+            prev: roots.get(key.as_str())?.map(|v| v.value().to_string()),
+        });
+        roots.insert(key.as_str(), serde_json::to_string(&entry)?.as_str())?;
+
+        let info: serde_json::Value = serde_json::from_str(v.value())?;
+        let burned = burns.get(tick)?.map(|b| b.value().to_string()).unwrap_or_else(|| "0".to_string());
+        let field = |name: &str| info[name].as_str().unwrap_or("").to_string();
+        let line = [
+            tick.to_string(),
+            field("max"),
+            field("lim"),
+            field("dec"),
+            field("deployer"),
+            field("supply"),
+            burned,
+            entry.root,
+        ]
+        .join("\0");
+        state.update(line.as_bytes());
+        state.update(b"\n");
+        token_count += 1;
+    }
+
+    let mut checksums = txn.open_table(ZRC20_CHECKSUMS)?;
+    let key = format!("{:010}", height);
+    let entry = StateChecksum {
+        height,
+        checksum: hex::encode(state.finalize()),
+        tokens: token_count,
+    };
+    ops.push(UndoOp::Text {
+        table: ZRC20_CHECKSUMS.name().to_string(),
+        key: key.clone(),
+        prev: checksums.get(key.as_str())?.map(|v| v.value().to_string()),
+    });
+    checksums.insert(key.as_str(), serde_json::to_string(&entry)?.as_str())?;
+    Ok(())
+}
+
+impl Db {
+    /// Root committed for `tick` at epoch `height`, or the latest one when `height` is `None`.
+    pub fn get_balance_root(&self, tick: &str, height: Option<u64>) -> Result<Option<BalanceRoot>> {
+        let needle = tick.to_lowercase();
+        let read_txn = self.db.begin_read()?;
+        let roots = read_txn.open_table(ZRC20_BALANCE_ROOTS)?;
+        let raw = match height {
+            Some(height) => roots.get(format!("{}:{:010}", needle, height).as_str())?.map(|v| v.value().to_string()),
+            None => {
+                let start = format!("{}:", needle);
+                let end = format!("{};", needle);
+                let last = roots.range::<&str>(start.as_str()..end.as_str())?.next_back();
+                match last {
+                    Some(item) => Some(item?.1.value().to_string()),
+                    None => None,
+                }
+            }
+        };
+        raw.map(|raw| serde_json::from_str(&raw).map_err(Into::into)).transpose()
+    }
+
+    /// State checksum at epoch `height`, or the latest one when `height` is `None`.
+    pub fn get_zrc20_checksum(&self, height: Option<u64>) -> Result<Option<StateChecksum>> {
+        let read_txn = self.db.begin_read()?;
+        let checksums = read_txn.open_table(ZRC20_CHECKSUMS)?;
+        let raw = match height {
+            Some(height) => checksums.get(format!("{:010}", height).as_str())?.map(|v| v.value().to_string()),
+            None => checksums.last()?.map(|(_, v)| v.value().to_string()),
+        };
+        raw.map(|raw| serde_json::from_str(&raw).map_err(Into::into)).transpose()
+    }
+
+    /// Proof that `address` held its balance of `tick` under the root committed at `root.height`.
+    /// `None` if the address held nothing then.
+    pub fn balance_proof(&self, tick: &str, address: &str, root: &BalanceRoot) -> Result<Option<BalanceProof>> {
+        let needle = tick.to_lowercase();
+        let (rows, _, _) = self.list_balances_for_tick_at(&needle, root.height, 0, usize::MAX, true)?;
+        let mut holders: Vec<(String, u128)> = rows.into_iter().map(|(a, b)| (a, b.overall.base_units())).collect();
+        holders.sort();
+        let leaves: Vec<Hash> = holders.iter().map(|(a, overall)| leaf_hash(a, *overall)).collect();
+        let tree = levels(leaves);
+        if hex::encode(root_of(&tree)) != root.root {
+            return Err(anyhow::anyhow!(
+                "Balances rebuilt for {} at height {} do not match the committed root",
+                needle,
+                root.height
+            ));
+        }
+        let Ok(index) = holders.binary_search_by(|(a, _)| a.as_str().cmp(address)) else {
+            return Ok(None);
+        };
+
+        let mut proof = Vec::new();
+        let mut position = index;
+        for level in &tree[..tree.len() - 1] {
+            let sibling = position ^ 1;
+            if let Some(hash) = level.get(sibling) {
+                proof.push(ProofStep {
+                    hash: hex::encode(hash),
+                    side: if sibling < position { "left" } else { "right" },
+                });
+            }
+            position /= 2;
+        }
+        Ok(Some(BalanceProof {
+            tick: needle,
+            address: address.to_string(),
+            overall_base_units: holders[index].1.to_string(),
+            height: root.height,
+            root: root.root.clone(),
+            leaves: holders.len(),
+            index,
+            leaf: hex::encode(tree[0][index]),
+            proof,
+        }))
+    }
+}
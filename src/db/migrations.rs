@@ -0,0 +1,1205 @@
+//! Ordered schema migrations applied when the database is opened.
+//!
+//! Each migration runs in its own write transaction together with the
+//! `schema_version` bump, so an interrupted upgrade resumes where it stopped.
+//! Append new entries to `MIGRATIONS`; never reorder or edit shipped ones.
+
+use super::{
+    content_digest, feed::feed_keys, holding_key, name_tld_key, next_history_key,
+    traits::trait_keys, trends, Zrc721Token, ACTIVE_ADDRESSES, ACTIVITY_STATS, ADDRESS_ACTIVITY,
+    ADDRESS_TRANSFERS, BLOCKS, BLOCK_HASHES, CONTENT, CONTENT_BLOBS, CONTENT_REFS, INSCRIPTIONS,
+    INSCRIPTION_FEED, INSCRIPTION_NUMBERS, INSCRIPTION_STATE, NAMES, NAME_HISTORY, NAME_OUTPOINTS,
+    NAME_PROOFS, NAME_SKELETONS, NAME_SUBDOMAINS, NAME_TLDS, OWNER_HOLDINGS, STATS, STATUS, TOKENS,
+    TRANSFER_INSCRIPTIONS, UNDO, UNDO_FLOOR_KEY, ZRC20_ADDRESS_HISTORY, ZRC20_EVENTS,
+    ZRC20_TICK_HISTORY, ZRC721_ACTIVITY, ZRC721_COLLECTIONS, ZRC721_HISTORY, ZRC721_OWNERS,
+    ZRC721_TOKENS, ZRC721_TRAITS,
+};
+use crate::names::{name_attributes, name_skeleton, normalize_name, parent_name};
+use anyhow::Result;
+use redb::{Database, ReadableTable, WriteTransaction};
+use std::collections::HashMap;
+
+/// Status key holding the schema version the database was last migrated to.
+pub(super) const SCHEMA_VERSION_KEY: &str = "schema_version";
+
+struct Migration {
+    version: u64,
+    description: &'static str,
+    apply: fn(&WriteTransaction) -> Result<()>,
+}
+
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "baseline layout with per-block undo records",
+        // Version 0 databases predate versioning; missing tables are created on open
+        apply: |_| Ok(()),
+    },
+    Migration {
+        version: 2,
+        description: "move inscription payloads into the content table",
+        apply: split_content,
+    },
+    Migration {
+        version: 3,
+        description: "deduplicate inscription payloads by sha256",
+        apply: dedup_content,
+    },
+    Migration {
+        version: 4,
+        description: "index unused transfer inscriptions by sender",
+        apply: index_address_transfers,
+    },
+    Migration {
+        version: 5,
+        description: "seed name ownership history",
+        apply: seed_name_history,
+    },
+    Migration {
+        version: 6,
+        description: "index names by TLD",
+        apply: index_name_tlds,
+    },
+    Migration {
+        version: 7,
+        description: "index inscriptions by feed facets",
+        apply: index_inscription_feed,
+    },
+    Migration {
+        version: 8,
+        description: "index names and ZRC-721 tokens by owner",
+        apply: index_owner_holdings,
+    },
+    Migration {
+        version: 9,
+        description: "index block hashes",
+        apply: index_block_hashes,
+    },
+    Migration {
+        version: 10,
+        description: "count daily and weekly activity",
+        apply: backfill_activity_stats,
+    },
+    Migration {
+        version: 11,
+        description: "build the ZRC-20 events feed",
+        apply: backfill_zrc20_events,
+    },
+    Migration {
+        version: 12,
+        description: "add ZRC-20 balance roots and state checksums",
+        // The tables are created on open and first filled at the next checkpoint epoch
+        apply: |_| Ok(()),
+    },
+    Migration {
+        version: 13,
+        description: "record ZRC-20 transfers spent back to the sender as cancellations",
+        apply: mark_transfer_cancellations,
+    },
+    Migration {
+        version: 14,
+        description: "index ZRC-721 traits from mint metadata",
+        apply: index_zrc721_traits,
+    },
+    Migration {
+        version: 15,
+        description: "count ZRC-721 tokens per collection owner",
+        apply: count_zrc721_owners,
+    },
+    Migration {
+        version: 16,
+        description: "build the ZRC-721 collection activity feed and daily mint counts",
+        apply: backfill_zrc721_activity,
+    },
+    Migration {
+        version: 17,
+        description: "track name outpoints",
+        apply: index_name_outpoints,
+    },
+    Migration {
+        version: 18,
+        description: "index ZNS subdomains under their parent names",
+        apply: index_name_subdomains,
+    },
+    Migration {
+        version: 19,
+        description: "index ZNS confusable skeletons and flag look-alike names",
+        apply: index_name_skeletons,
+    },
+    Migration {
+        version: 20,
+        description: "store label attributes on every ZNS name",
+        apply: backfill_name_attributes,
+    },
+    Migration {
+        version: 21,
+        description: "record how far back undo history reaches",
+        apply: record_undo_floor,
+    },
+    Migration {
+        version: 22,
+        description: "re-key ZNS names by their NFC form",
+        apply: rekey_names_nfc,
+    },
+];
+
+/// Schema version written by this binary.
+pub(super) fn current_version() -> u64 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+/// Bring the database up to `current_version()`, refusing files written by a newer binary.
+pub(super) fn run(db: &Database, fresh: bool) -> Result<()> {
+    let target = current_version();
+    let stored = {
+        let read_txn = db.begin_read()?;
+        let status = read_txn.open_table(STATUS)?;
+        let value = status.get(SCHEMA_VERSION_KEY)?.map(|v| v.value());
+        value
+    };
+
+    let version = match stored {
+        Some(v) => v,
+        // A brand-new file already has the latest layout
+        None if fresh => {
+            set_version(db, target)?;
+            return Ok(());
+        }
+        None => 0,
+    };
+
+    if version > target {
+        return Err(anyhow::anyhow!(
+            "Database schema v{} is newer than this binary supports (v{}); upgrade zord",
+            version,
+            target
+        ));
+    }
+
+    for migration in MIGRATIONS.iter().filter(|m| m.version > version) {
+        tracing::info!(
+            "Applying schema migration v{}: {}",
+            migration.version,
+            migration.description
+        );
+        let write_txn = db.begin_write()?;
+        (migration.apply)(&write_txn)?;
+        {
+            let mut status = write_txn.open_table(STATUS)?;
+            status.insert(SCHEMA_VERSION_KEY, migration.version)?;
+        }
+        write_txn.commit()?;
+    }
+
+    Ok(())
+}
+
+fn set_version(db: &Database, version: u64) -> Result<()> {
+    let write_txn = db.begin_write()?;
+    {
+        let mut status = write_txn.open_table(STATUS)?;
+        status.insert(SCHEMA_VERSION_KEY, version)?;
+    }
+    write_txn.commit()?;
+    Ok(())
+}
+
+// v2: strip `content`/`content_hex` from metadata, storing the decoded bytes separately
+fn split_content(txn: &WriteTransaction) -> Result<()> {
+    const BATCH: usize = 1000;
+    let mut inscriptions = txn.open_table(INSCRIPTIONS)?;
+    let mut content = txn.open_table(CONTENT)?;
+    let mut cursor: Option<String> = None;
+    let mut moved = 0u64;
+
+    loop {
+        let batch: Vec<(String, String)> = {
+            let iter = match cursor.as_deref() {
+                Some(last) => inscriptions.range::<&str>(last..)?,
+                None => inscriptions.iter()?,
+            };
+            let mut rows = Vec::with_capacity(BATCH);
+            for item in iter {
+                let (k, v) = item?;
+                if cursor.as_deref() == Some(k.value()) {
+                    continue;
+                }
+                rows.push((k.value().to_string(), v.value().to_string()));
+                if rows.len() >= BATCH {
+                    break;
+                }
+            }
+            rows
+        };
+        let Some((last, _)) = batch.last() else {
+            break;
+        };
+        cursor = Some(last.clone());
+
+        for (id, raw) in batch {
+            let mut meta: serde_json::Value = match serde_json::from_str(&raw) {
+                Ok(v) => v,
+                Err(_) => continue,
+            };
+            let Some(obj) = meta.as_object_mut() else {
+                continue;
+            };
+            let Some(hex_payload) = obj.remove("content_hex") else {
+                continue;
+            };
+            obj.remove("content");
+            let bytes = hex::decode(hex_payload.as_str().unwrap_or("")).unwrap_or_default();
+            obj.insert("content_length".to_string(), serde_json::json!(bytes.len()));
+            obj.insert(
+                "content_sha256".to_string(),
+                serde_json::json!(content_digest(&bytes)),
+            );
+            content.insert(id.as_str(), bytes.as_slice())?;
+            inscriptions.insert(id.as_str(), meta.to_string().as_str())?;
+            moved += 1;
+        }
+    }
+
+    tracing::info!("Moved {} inscription payloads into the content table", moved);
+    Ok(())
+}
+
+// v3: rekey payloads by digest with reference counts, then retire the per-id table
+fn dedup_content(txn: &WriteTransaction) -> Result<()> {
+    let mut blobs = txn.open_table(CONTENT_BLOBS)?;
+    let mut refs = txn.open_table(CONTENT_REFS)?;
+    let (mut rows, mut unique) = (0u64, 0u64);
+    {
+        let content = txn.open_table(CONTENT)?;
+        for item in content.iter()? {
+            let (_id, bytes) = item?;
+            let digest = content_digest(bytes.value());
+            let count = refs.get(digest.as_str())?.map(|v| v.value()).unwrap_or(0);
+            if count == 0 {
+                blobs.insert(digest.as_str(), bytes.value())?;
+                unique += 1;
+            }
+            refs.insert(digest.as_str(), count + 1)?;
+            rows += 1;
+        }
+    }
+    txn.delete_table(CONTENT)?;
+    tracing::info!("Deduplicated {} payloads into {} blobs", rows, unique);
+    Ok(())
+}
+
+// v4: build the sender -> unused transfer ids index from existing transfer inscriptions
+fn index_address_transfers(txn: &WriteTransaction) -> Result<()> {
+    let transfers = txn.open_table(TRANSFER_INSCRIPTIONS)?;
+    let states = txn.open_table(INSCRIPTION_STATE)?;
+    let mut by_sender: std::collections::BTreeMap<String, Vec<String>> = Default::default();
+    for item in transfers.iter()? {
+        let (k, v) = item?;
+        let id = k.value();
+        if states.get(id)?.map(|s| s.value() == "used").unwrap_or(false) {
+            continue;
+        }
+        let parsed: serde_json::Value = match serde_json::from_str(v.value()) {
+            Ok(v) => v,
+            Err(_) => continue,
+        };
+        if let Some(sender) = parsed["sender"].as_str() {
+            by_sender.entry(sender.to_string()).or_default().push(id.to_string());
+        }
+    }
+
+    let mut pending = txn.open_table(ADDRESS_TRANSFERS)?;
+    for (sender, ids) in &by_sender {
+        pending.insert(sender.as_str(), serde_json::to_string(ids)?.as_str())?;
+    }
+    tracing::info!("Indexed pending transfers for {} addresses", by_sender.len());
+    Ok(())
+}
+
+// v5: record the registration of every existing name as the start of its history
+fn seed_name_history(txn: &WriteTransaction) -> Result<()> {
+    let names = txn.open_table(NAMES)?;
+    let inscriptions = txn.open_table(INSCRIPTIONS)?;
+    let mut history = txn.open_table(NAME_HISTORY)?;
+    let mut seeded = 0u64;
+    for item in names.iter()? {
+        let (k, v) = item?;
+        let name = k.value();
+        let data: serde_json::Value = match serde_json::from_str(v.value()) {
+            Ok(v) => v,
+            Err(_) => continue,
+        };
+        let Some(inscription_id) = data["inscription_id"].as_str() else {
+            continue;
+        };
+        let meta: serde_json::Value = match inscriptions.get(inscription_id)? {
+            Some(raw) => serde_json::from_str(raw.value()).unwrap_or_default(),
+            None => continue,
+        };
+        let Some(txid) = meta["txid"].as_str() else {
+            continue;
+        };
+        let height = meta["block_height"].as_u64().unwrap_or(0);
+
+        let entry = serde_json::json!({
+            "event": "register",
+            "from": null,
+            "to": data["owner"],
+            "txid": txid,
+            "height": height,
+        });
+        let key = next_history_key(&history, name, height)?;
+        history.insert(key.as_str(), entry.to_string().as_str())?;
+        seeded += 1;
+    }
+    tracing::info!("Seeded ownership history for {} names", seeded);
+    Ok(())
+}
+
+// v6: build the "<tld>:<label>" name index and per-TLD counters
+fn index_name_tlds(txn: &WriteTransaction) -> Result<()> {
+    let names = txn.open_table(NAMES)?;
+    let mut tlds = txn.open_table(NAME_TLDS)?;
+    let mut counts: std::collections::BTreeMap<String, u64> = Default::default();
+    for item in names.iter()? {
+        let (k, _) = item?;
+        let name = k.value();
+        if let Some((tld, index_key)) = name_tld_key(name) {
+            tlds.insert(index_key.as_str(), name)?;
+            *counts.entry(tld.to_string()).or_default() += 1;
+        }
+    }
+
+    let mut stats = txn.open_table(STATS)?;
+    for (tld, count) in &counts {
+        stats.insert(format!("name_count:{}", tld).as_str(), *count)?;
+    }
+    tracing::info!("Indexed names across {} TLDs", counts.len());
+    Ok(())
+}
+
+// v7: build the feed facet keys for every numbered inscription
+fn index_inscription_feed(txn: &WriteTransaction) -> Result<()> {
+    let numbers = txn.open_table(INSCRIPTION_NUMBERS)?;
+    let inscriptions = txn.open_table(INSCRIPTIONS)?;
+    let mut feed = txn.open_table(INSCRIPTION_FEED)?;
+    let mut indexed = 0u64;
+    for item in numbers.iter()? {
+        let (k, v) = item?;
+        let id = v.value();
+        let meta: serde_json::Value = match inscriptions.get(id)? {
+            Some(raw) => serde_json::from_str(raw.value()).unwrap_or_default(),
+            None => continue,
+        };
+        for key in feed_keys(&meta, k.value()) {
+            feed.insert(key.as_str(), id)?;
+        }
+        indexed += 1;
+    }
+    tracing::info!("Indexed {} inscriptions for the feed", indexed);
+    Ok(())
+}
+
+// v8: build the owner -> names / ZRC-721 tokens index
+fn index_owner_holdings(txn: &WriteTransaction) -> Result<()> {
+    let names = txn.open_table(NAMES)?;
+    let tokens = txn.open_table(ZRC721_TOKENS)?;
+    let mut holdings = txn.open_table(OWNER_HOLDINGS)?;
+    let mut indexed = 0u64;
+    for item in names.iter()? {
+        let (k, v) = item?;
+        let data: serde_json::Value = serde_json::from_str(v.value()).unwrap_or_default();
+        if let Some(key) = data["owner"].as_str().and_then(|owner| holding_key(owner, "name", k.value())) {
+            holdings.insert(key.as_str(), "")?;
+            indexed += 1;
+        }
+    }
+    for item in tokens.iter()? {
+        let (k, v) = item?;
+        let Ok(token) = serde_json::from_str::<Zrc721Token>(v.value()) else {
+            continue;
+        };
+        if let Some(key) = holding_key(&token.owner, "nft", k.value()) {
+            holdings.insert(key.as_str(), "")?;
+            indexed += 1;
+        }
+    }
+    tracing::info!("Indexed {} owned names and tokens", indexed);
+    Ok(())
+}
+
+// v9: reverse the height -> hash table so blocks can be looked up by hash
+fn index_block_hashes(txn: &WriteTransaction) -> Result<()> {
+    let blocks = txn.open_table(BLOCKS)?;
+    let mut hashes = txn.open_table(BLOCK_HASHES)?;
+    let mut indexed = 0u64;
+    for item in blocks.iter()? {
+        let (k, v) = item?;
+        hashes.insert(v.value(), k.value().to_string().as_str())?;
+        indexed += 1;
+    }
+    tracing::info!("Indexed {} block hashes", indexed);
+    Ok(())
+}
+
+// v10: rebuild the activity counters from history. Block times are only stored on
+// inscriptions, so activity at heights that carried no inscription is not counted.
+fn backfill_activity_stats(txn: &WriteTransaction) -> Result<()> {
+    let mut times: HashMap<u64, u64> = HashMap::new();
+    let mut counters: HashMap<String, u64> = HashMap::new();
+    let mut count = |time: u64, metric: &str| {
+        for period in trends::period_keys(time) {
+            *counters.entry(format!("{}:{}", period, metric)).or_default() += 1;
+        }
+    };
+
+    let inscriptions = txn.open_table(INSCRIPTIONS)?;
+    for item in inscriptions.iter()? {
+        let (_, v) = item?;
+        let meta: serde_json::Value = serde_json::from_str(v.value()).unwrap_or_default();
+        let (Some(height), Some(time)) = (meta["block_height"].as_u64(), meta["block_time"].as_u64()) else {
+            continue;
+        };
+        times.insert(height, time);
+        count(time, trends::METRIC_INSCRIPTIONS);
+        count(time, &trends::category_metric(meta["content_type"].as_str().unwrap_or("")));
+    }
+
+    // History keys end in "<height>:<seq>"
+    let time_of = |key: &str| {
+        let height: u64 = key.rsplit(':').nth(1)?.parse().ok()?;
+        times.get(&height).copied()
+    };
+    for (def, field, value, metric) in [
+        (ZRC20_TICK_HISTORY, "op", "mint", trends::METRIC_ZRC20_MINTS),
+        (NAME_HISTORY, "event", "register", trends::METRIC_NAMES),
+    ] {
+        let history = txn.open_table(def)?;
+        for item in history.iter()? {
+            let (k, v) = item?;
+            let Some(time) = time_of(k.value()) else {
+                continue;
+            };
+            let row: serde_json::Value = serde_json::from_str(v.value()).unwrap_or_default();
+            if row[field] == value {
+                count(time, metric);
+            }
+        }
+    }
+
+    let activity = txn.open_table(ADDRESS_ACTIVITY)?;
+    let mut seen = txn.open_table(ACTIVE_ADDRESSES)?;
+    for item in activity.iter()? {
+        let (k, _) = item?;
+        let key = k.value();
+        let (Some(time), Some(address)) = (time_of(key), key.rsplitn(3, ':').nth(2)) else {
+            continue;
+        };
+        for period in trends::period_keys(time) {
+            let marker = format!("{}:{}", period, address);
+            if seen.insert(marker.as_str(), "")?.is_none() {
+                *counters.entry(format!("{}:{}", period, trends::METRIC_ACTIVE)).or_default() += 1;
+            }
+        }
+    }
+
+    let mut table = txn.open_table(ACTIVITY_STATS)?;
+    for (key, value) in &counters {
+        table.insert(key.as_str(), *value)?;
+    }
+    tracing::info!("Backfilled {} activity counters", counters.len());
+    Ok(())
+}
+
+// v11: rebuild the per-ticker events feed from deploys, metadata, transfer inscriptions
+// and settled history. Within a block, deploys come first, then transfer
+// inscriptions and metadata, then the history rows in their recorded order.
+fn backfill_zrc20_events(txn: &WriteTransaction) -> Result<()> {
+    let inscriptions = txn.open_table(INSCRIPTIONS)?;
+    let origin = |id: &str| -> Result<(u64, serde_json::Value)> {
+        let meta: serde_json::Value = match inscriptions.get(id)? {
+            Some(raw) => serde_json::from_str(raw.value()).unwrap_or_default(),
+            None => serde_json::Value::Null,
+        };
+        Ok((meta["block_height"].as_u64().unwrap_or(0), meta["txid"].clone()))
+    };
+    // (ticker, height, rank) orders the rows; the sort is stable for history rows
+    let mut events: Vec<(String, u64, u8, serde_json::Value)> = Vec::new();
+
+    let tokens = txn.open_table(TOKENS)?;
+    for item in tokens.iter()? {
+        let (k, v) = item?;
+        let tick = k.value().to_string();
+        let info: serde_json::Value = serde_json::from_str(v.value()).unwrap_or_default();
+        let Some(id) = info["inscription_id"].as_str() else {
+            continue;
+        };
+        let (height, txid) = origin(id)?;
+        events.push((tick.clone(), height, 0, serde_json::json!({
+            "op": "deploy",
+            "tick": tick,
+            "max": info["max"],
+            "lim": info["lim"],
+            "dec": info["dec"],
+            "from": info["deployer"],
+            "to": null,
+            "txid": txid,
+            "inscription_id": id,
+            "height": height,
+        })));
+        if let Some(meta_id) = info["meta"]["inscription_id"].as_str() {
+            let (height, txid) = origin(meta_id)?;
+            events.push((tick.clone(), height, 1, serde_json::json!({
+                "op": "meta",
+                "tick": tick,
+                "from": info["deployer"],
+                "to": null,
+                "txid": txid,
+                "inscription_id": meta_id,
+                "height": height,
+            })));
+        }
+    }
+
+    let transfers = txn.open_table(TRANSFER_INSCRIPTIONS)?;
+    for item in transfers.iter()? {
+        let (k, v) = item?;
+        let id = k.value();
+        let data: serde_json::Value = serde_json::from_str(v.value()).unwrap_or_default();
+        let Some(tick) = data["tick"].as_str() else {
+            continue;
+        };
+        let (height, txid) = origin(id)?;
+        events.push((tick.to_string(), height, 1, serde_json::json!({
+            "op": "transfer_inscribe",
+            "tick": tick,
+            "amt": data["amt"],
+            "from": data["sender"],
+            "to": null,
+            "txid": txid,
+            "inscription_id": id,
+            "height": height,
+        })));
+    }
+
+    let history = txn.open_table(ZRC20_TICK_HISTORY)?;
+    for item in history.iter()? {
+        let (k, v) = item?;
+        // "<ticker>:<height>:<seq>"
+        let mut parts = k.value().rsplitn(3, ':');
+        let (Some(_), Some(height), Some(tick)) = (parts.next(), parts.next(), parts.next()) else {
+            continue;
+        };
+        let row: serde_json::Value = serde_json::from_str(v.value()).unwrap_or_default();
+        events.push((tick.to_string(), height.parse().unwrap_or(0), 2, row));
+    }
+
+    events.sort_by(|a, b| (&a.0, a.1, a.2).cmp(&(&b.0, b.1, b.2)));
+    let mut table = txn.open_table(ZRC20_EVENTS)?;
+    for (tick, height, _, row) in &events {
+        let key = next_history_key(&table, tick, *height)?;
+        table.insert(key.as_str(), row.to_string().as_str())?;
+    }
+    tracing::info!("Backfilled {} ZRC-20 events", events.len());
+    Ok(())
+}
+
+// v13: rows of transfers settled back to their sender become "cancel" rows, and the
+// inscriptions move from "used" to "cancelled"
+fn mark_transfer_cancellations(txn: &WriteTransaction) -> Result<()> {
+    let mut cancelled: std::collections::BTreeSet<String> = Default::default();
+    for def in [ZRC20_TICK_HISTORY, ZRC20_ADDRESS_HISTORY, ZRC20_EVENTS, ADDRESS_ACTIVITY] {
+        let mut table = txn.open_table(def)?;
+        let mut rows = Vec::new();
+        for item in table.iter()? {
+            let (k, v) = item?;
+            let mut row: serde_json::Value = match serde_json::from_str(v.value()) {
+                Ok(row) => row,
+                Err(_) => continue,
+            };
+            if row["op"] != "transfer" || row["from"].is_null() || row["from"] != row["to"] {
+                continue;
+            }
+            row["op"] = "cancel".into();
+            if row["kind"] == "transfer_out" {
+                row["kind"] = "transfer_cancel".into();
+            }
+            if let Some(id) = row["inscription_id"].as_str() {
+                cancelled.insert(id.to_string());
+            }
+            rows.push((k.value().to_string(), row.to_string()));
+        }
+        for (key, row) in &rows {
+            table.insert(key.as_str(), row.as_str())?;
+        }
+    }
+
+    let mut states = txn.open_table(INSCRIPTION_STATE)?;
+    for id in &cancelled {
+        states.insert(id.as_str(), "cancelled")?;
+    }
+    tracing::info!("Marked {} transfer inscriptions as cancelled", cancelled.len());
+    Ok(())
+}
+
+// v14: trait index over the attributes of every token's mint metadata
+fn index_zrc721_traits(txn: &WriteTransaction) -> Result<()> {
+    let tokens = txn.open_table(ZRC721_TOKENS)?;
+    let mut traits = txn.open_table(ZRC721_TRAITS)?;
+    let mut indexed = 0u64;
+    for item in tokens.iter()? {
+        let (_, v) = item?;
+        let Ok(token) = serde_json::from_str::<Zrc721Token>(v.value()) else {
+            continue;
+        };
+        for key in trait_keys(&token.tick, &token.token_id, &token.metadata) {
+            traits.insert(key.as_str(), "")?;
+            indexed += 1;
+        }
+    }
+    tracing::info!("Indexed {} ZRC-721 token traits", indexed);
+    Ok(())
+}
+
+// v15: per-owner token tallies and each collection's `owners`, from the current token rows
+fn count_zrc721_owners(txn: &WriteTransaction) -> Result<()> {
+    let mut held: HashMap<String, u64> = HashMap::new();
+    for item in txn.open_table(ZRC721_TOKENS)?.iter()? {
+        let (_, v) = item?;
+        let Ok(token) = serde_json::from_str::<Zrc721Token>(v.value()) else {
+            continue;
+        };
+        if token.owner.is_empty() || token.owner == "shielded" {
+            continue;
+        }
+        *held.entry(format!("{}:{}", token.tick, token.owner)).or_default() += 1;
+    }
+
+    let mut owners_per_collection: HashMap<String, u64> = HashMap::new();
+    let mut owners = txn.open_table(ZRC721_OWNERS)?;
+    for (key, count) in &held {
+        owners.insert(key.as_str(), count.to_string().as_str())?;
+        if let Some((collection, _)) = key.split_once(':') {
+            *owners_per_collection.entry(collection.to_string()).or_default() += 1;
+        }
+    }
+
+    let mut collections = txn.open_table(ZRC721_COLLECTIONS)?;
+    let records: Vec<(String, String)> = collections
+        .iter()?
+        .filter_map(|item| item.ok())
+        .map(|(k, v)| (k.value().to_string(), v.value().to_string()))
+        .collect();
+    for (tick, raw) in records {
+        let Ok(mut info) = serde_json::from_str::<serde_json::Value>(&raw) else {
+            continue;
+        };
+        info["owners"] = serde_json::json!(owners_per_collection.get(&tick).copied().unwrap_or(0));
+        collections.insert(tick.as_str(), info.to_string().as_str())?;
+    }
+    tracing::info!("Counted {} ZRC-721 owner tallies", held.len());
+    Ok(())
+}
+
+// v16: rebuild each collection's activity feed from its tokens and their ownership
+// history. A mint's recipient is the sender of the token's first move, or else its
+// current owner; within a block, mints come before moves.
+fn backfill_zrc721_activity(txn: &WriteTransaction) -> Result<()> {
+    // (collection, height, rank, row)
+    let mut events: Vec<(String, u64, u8, serde_json::Value)> = Vec::new();
+    let mut first_sender: HashMap<String, serde_json::Value> = HashMap::new();
+    for item in txn.open_table(ZRC721_HISTORY)?.iter()? {
+        let (k, v) = item?;
+        // "<collection>#<id>:<height>:<seq>"
+        let mut parts = k.value().rsplitn(3, ':');
+        let (Some(_), Some(height), Some(token)) = (parts.next(), parts.next(), parts.next()) else {
+            continue;
+        };
+        let Some((collection, token_id)) = token.split_once('#') else {
+            continue;
+        };
+        let mut row: serde_json::Value = serde_json::from_str(v.value()).unwrap_or_default();
+        first_sender.entry(token.to_string()).or_insert_with(|| row["from"].clone());
+        row["token_id"] = token_id.into();
+        events.push((collection.to_string(), height.parse().unwrap_or(0), 1, row));
+    }
+
+    let inscriptions = txn.open_table(INSCRIPTIONS)?;
+    let mut minted: HashMap<String, u64> = HashMap::new();
+    for item in txn.open_table(ZRC721_TOKENS)?.iter()? {
+        let (k, v) = item?;
+        let Ok(token) = serde_json::from_str::<Zrc721Token>(v.value()) else {
+            continue;
+        };
+        let meta: serde_json::Value = match inscriptions.get(token.inscription_id.as_str())? {
+            Some(raw) => serde_json::from_str(raw.value()).unwrap_or_default(),
+            None => serde_json::Value::Null,
+        };
+        let height = meta["block_height"].as_u64().unwrap_or(0);
+        if let Some(time) = meta["block_time"].as_u64() {
+            *minted.entry(trends::zrc721_mint_key(&token.tick, time)).or_default() += 1;
+        }
+        let to = first_sender.remove(k.value()).unwrap_or_else(|| token.owner.clone().into());
+        events.push((token.tick.clone(), height, 0, serde_json::json!({
+            "event": "mint",
+            "token_id": token.token_id,
+            "from": null,
+            "to": to,
+            "inscription_id": token.inscription_id,
+            "height": height,
+        })));
+    }
+
+    // Stable, so moves keep their recorded order within a block
+    events.sort_by(|a, b| (&a.0, a.1, a.2).cmp(&(&b.0, b.1, b.2)));
+    let mut table = txn.open_table(ZRC721_ACTIVITY)?;
+    for (collection, height, _, row) in &events {
+        let key = next_history_key(&table, collection, *height)?;
+        table.insert(key.as_str(), row.to_string().as_str())?;
+    }
+    let mut counters = txn.open_table(ACTIVITY_STATS)?;
+    for (key, count) in &minted {
+        counters.insert(key.as_str(), *count)?;
+    }
+    tracing::info!("Backfilled {} ZRC-721 activity rows", events.len());
+    Ok(())
+}
+
+// v17: point each registered name at its reveal outpoint.
+// Names whose reveal output was already spent are tracked from here but miss past moves.
+fn index_name_outpoints(txn: &WriteTransaction) -> Result<()> {
+    let names = txn.open_table(NAMES)?;
+    let inscriptions = txn.open_table(INSCRIPTIONS)?;
+    let mut outpoints = txn.open_table(NAME_OUTPOINTS)?;
+    let mut indexed = 0u64;
+    for item in names.iter()? {
+        let (k, v) = item?;
+        let data: serde_json::Value = match serde_json::from_str(v.value()) {
+            Ok(v) => v,
+            Err(_) => continue,
+        };
+        let Some(inscription_id) = data["inscription_id"].as_str() else {
+            continue;
+        };
+        let meta: serde_json::Value = match inscriptions.get(inscription_id)? {
+            Some(raw) => serde_json::from_str(raw.value()).unwrap_or_default(),
+            None => continue,
+        };
+        let (Some(txid), Some(vout)) = (meta["txid"].as_str(), meta["vout"].as_u64()) else {
+            continue;
+        };
+        outpoints.insert(format!("{}:{}", txid, vout).as_str(), k.value())?;
+        indexed += 1;
+    }
+    tracing::info!("Indexed outpoints for {} names", indexed);
+    Ok(())
+}
+
+// v18: list every registered subdomain under its parent, at its registration height
+fn index_name_subdomains(txn: &WriteTransaction) -> Result<()> {
+    let history = txn.open_table(NAME_HISTORY)?;
+    let mut subdomains = txn.open_table(NAME_SUBDOMAINS)?;
+    let mut indexed = 0u64;
+    for item in history.iter()? {
+        let (k, v) = item?;
+        // "<name>:<height>:<seq>"; names may contain ':' themselves
+        let mut parts = k.value().rsplitn(3, ':');
+        let (Some(_), Some(height), Some(name)) = (parts.next(), parts.next(), parts.next()) else {
+            continue;
+        };
+        let Some(parent) = parent_name(name) else {
+            continue;
+        };
+        let entry: serde_json::Value = serde_json::from_str(v.value()).unwrap_or_default();
+        if entry["event"] != "register" {
+            continue;
+        }
+        let height = height.parse().unwrap_or(0);
+        let key = next_history_key(&subdomains, parent, height)?;
+        subdomains.insert(key.as_str(), name)?;
+        indexed += 1;
+    }
+    tracing::info!("Indexed {} ZNS subdomains", indexed);
+    Ok(())
+}
+
+// v19: walk registrations in chain order; the first name with a skeleton owns it and
+// later look-alikes are flagged `confusable_with` it
+fn index_name_skeletons(txn: &WriteTransaction) -> Result<()> {
+    let mut registered: Vec<(u64, String)> = Vec::new();
+    for item in txn.open_table(NAME_HISTORY)?.iter()? {
+        let (k, v) = item?;
+        let mut parts = k.value().rsplitn(3, ':');
+        let (Some(_), Some(height), Some(name)) = (parts.next(), parts.next(), parts.next()) else {
+            continue;
+        };
+        let entry: serde_json::Value = serde_json::from_str(v.value()).unwrap_or_default();
+        if entry["event"] == "register" {
+            registered.push((height.parse().unwrap_or(0), name.to_string()));
+        }
+    }
+    // Stable, so names registered in one block keep their key order
+    registered.sort_by_key(|(height, _)| *height);
+
+    let mut names = txn.open_table(NAMES)?;
+    let mut skeletons = txn.open_table(NAME_SKELETONS)?;
+    let mut flagged = 0u64;
+    for (_, name) in &registered {
+        let skeleton = name_skeleton(name);
+        let established = skeletons.get(skeleton.as_str())?.map(|v| v.value().to_string());
+        match established {
+            None => {
+                skeletons.insert(skeleton.as_str(), name.as_str())?;
+            }
+            Some(established) if established != *name => {
+                let mut data: serde_json::Value = match names.get(name.as_str())? {
+                    Some(raw) => serde_json::from_str(raw.value()).unwrap_or_default(),
+                    None => continue,
+                };
+                data["confusable_with"] = established.into();
+                names.insert(name.as_str(), data.to_string().as_str())?;
+                flagged += 1;
+            }
+            Some(_) => {}
+        }
+    }
+    tracing::info!("Indexed {} name skeletons, flagged {} look-alikes", skeletons.len()?, flagged);
+    Ok(())
+}
+
+// v20: grapheme count and character class flags for names registered earlier
+fn backfill_name_attributes(txn: &WriteTransaction) -> Result<()> {
+    let mut names = txn.open_table(NAMES)?;
+    let mut updated: Vec<(String, String)> = Vec::new();
+    for item in names.iter()? {
+        let (k, v) = item?;
+        let mut data: serde_json::Value = match serde_json::from_str(v.value()) {
+            Ok(v) => v,
+            Err(_) => continue,
+        };
+        data["attributes"] = name_attributes(k.value());
+        updated.push((k.value().to_string(), data.to_string()));
+    }
+    for (name, data) in &updated {
+        names.insert(name.as_str(), data.as_str())?;
+    }
+    tracing::info!("Stored label attributes for {} names", updated.len());
+    Ok(())
+}
+
+// v21: blocks indexed before the journal, or imported, have no undo record; the
+// highest such block is as far back as a rollback can go
+fn record_undo_floor(txn: &WriteTransaction) -> Result<()> {
+    let blocks = txn.open_table(BLOCKS)?;
+    let undo = txn.open_table(UNDO)?;
+    let mut floor = None;
+    for item in blocks.iter()?.rev() {
+        let height = item?.0.value();
+        if undo.get(height)?.is_none() {
+            floor = Some(height);
+            break;
+        }
+        floor = Some(height.saturating_sub(1));
+    }
+    if let Some(floor) = floor {
+        txn.open_table(STATUS)?.insert(UNDO_FLOOR_KEY, floor)?;
+        tracing::info!("Undo history reaches back to height {}", floor);
+    }
+    Ok(())
+}
+
+// v22: names registered before NFC keys sit under their lowercase spelling only.
+// Move each to `normalize_name` of it; when spellings collide, the first registered
+// (lowest inscription number) keeps the name and the later ones are dropped.
+fn rekey_names_nfc(txn: &WriteTransaction) -> Result<()> {
+    let mut stale = std::collections::HashSet::new();
+    for item in txn.open_table(NAMES)?.iter()? {
+        let (k, _) = item?;
+        let normalized = normalize_name(k.value());
+        if normalized != k.value() {
+            stale.insert(normalized);
+        }
+    }
+    if stale.is_empty() {
+        return Ok(());
+    }
+
+    // Every spelling of each affected name, including one already stored normalized
+    let mut groups: HashMap<String, Vec<(String, serde_json::Value)>> = HashMap::new();
+    for item in txn.open_table(NAMES)?.iter()? {
+        let (k, v) = item?;
+        let normalized = normalize_name(k.value());
+        if stale.contains(&normalized) {
+            let data = serde_json::from_str(v.value()).unwrap_or_default();
+            groups.entry(normalized).or_default().push((k.value().to_string(), data));
+        }
+    }
+    if groups.values().any(|spellings| spellings.len() > 1) {
+        let mut numbers = HashMap::new();
+        for item in txn.open_table(INSCRIPTION_NUMBERS)?.iter()? {
+            let (k, v) = item?;
+            numbers.insert(v.value().to_string(), k.value());
+        }
+        for spellings in groups.values_mut() {
+            spellings.sort_by_key(|(_, data)| {
+                data["inscription_id"].as_str().and_then(|id| numbers.get(id).copied()).unwrap_or(u64::MAX)
+            });
+        }
+    }
+
+    // Old key -> new key, or None for a dropped later registration
+    let mut moved: HashMap<String, Option<String>> = HashMap::new();
+    for (normalized, spellings) in &groups {
+        for (i, (key, data)) in spellings.iter().enumerate() {
+            if i > 0 {
+                drop_name(txn, key, data)?;
+                moved.insert(key.clone(), None);
+            }
+        }
+        let (key, data) = &spellings[0];
+        if key != normalized {
+            rename_name(txn, key, normalized, data)?;
+            moved.insert(key.clone(), Some(normalized.clone()));
+        }
+    }
+    let renamed = |name: &str| match moved.get(name) {
+        Some(target) => target.clone(),
+        None => Some(name.to_string()),
+    };
+
+    let mut outpoints = txn.open_table(NAME_OUTPOINTS)?;
+    let rows: Vec<(String, String)> = outpoints
+        .iter()?
+        .filter_map(|item| item.ok())
+        .filter(|(_, v)| moved.contains_key(v.value()))
+        .map(|(k, v)| (k.value().to_string(), v.value().to_string()))
+        .collect();
+    for (outpoint, name) in rows {
+        match renamed(&name) {
+            Some(name) => outpoints.insert(outpoint.as_str(), name.as_str())?,
+            None => outpoints.remove(outpoint.as_str())?,
+        };
+    }
+
+    // Subdomains whose parent or own key moved
+    let mut subdomains = txn.open_table(NAME_SUBDOMAINS)?;
+    let rows: Vec<(String, String)> = subdomains
+        .iter()?
+        .filter_map(|item| item.ok())
+        .map(|(k, v)| (k.value().to_string(), v.value().to_string()))
+        .filter(|(k, v)| {
+            moved.contains_key(v.as_str())
+                || k.rsplitn(3, ':').nth(2).is_some_and(|parent| moved.contains_key(parent))
+        })
+        .collect();
+    for (key, sub) in rows {
+        subdomains.remove(key.as_str())?;
+        let mut parts = key.rsplitn(3, ':');
+        let (Some(_), Some(height), Some(parent)) = (parts.next(), parts.next(), parts.next()) else {
+            continue;
+        };
+        let (Some(parent), Some(sub)) = (renamed(parent), renamed(&sub)) else {
+            continue;
+        };
+        let key = next_history_key(&subdomains, &parent, height.parse().unwrap_or(0))?;
+        subdomains.insert(key.as_str(), sub.as_str())?;
+    }
+
+    // Canonically equivalent spellings share a skeleton, so a dropped one hands it on
+    let mut skeletons = txn.open_table(NAME_SKELETONS)?;
+    let rows: Vec<(String, String)> = skeletons
+        .iter()?
+        .filter_map(|item| item.ok())
+        .filter(|(_, v)| moved.contains_key(v.value()))
+        .map(|(k, v)| (k.value().to_string(), normalize_name(v.value())))
+        .collect();
+    for (skeleton, name) in rows {
+        skeletons.insert(skeleton.as_str(), name.as_str())?;
+    }
+    let mut names = txn.open_table(NAMES)?;
+    let rows: Vec<(String, serde_json::Value)> = names
+        .iter()?
+        .filter_map(|item| item.ok())
+        .filter_map(|(k, v)| {
+            let data: serde_json::Value = serde_json::from_str(v.value()).ok()?;
+            let established = data["confusable_with"].as_str()?;
+            moved.contains_key(established).then(|| (k.value().to_string(), data))
+        })
+        .collect();
+    for (name, mut data) in rows {
+        let established = normalize_name(data["confusable_with"].as_str().unwrap_or_default());
+        data["confusable_with"] = established.into();
+        names.insert(name.as_str(), data.to_string().as_str())?;
+    }
+
+    let dropped = moved.values().filter(|target| target.is_none()).count();
+    tracing::info!("Re-keyed {} names to NFC, dropped {} later duplicates", moved.len() - dropped, dropped);
+    Ok(())
+}
+
+// Keys of every "<name>:<height>:<seq>" row of `name`
+fn history_keys(table: &redb::Table<&'static str, &'static str>, name: &str) -> Result<Vec<String>> {
+    let start = format!("{}:", name);
+    let end = format!("{};", name);
+    let mut keys = Vec::new();
+    for item in table.range::<&str>(start.as_str()..end.as_str())? {
+        keys.push(item?.0.value().to_string());
+    }
+    Ok(keys)
+}
+
+fn adjust_stat(txn: &WriteTransaction, key: &str, up: bool) -> Result<()> {
+    let mut stats = txn.open_table(STATS)?;
+    let count = stats.get(key)?.map(|v| v.value()).unwrap_or(0);
+    let count = if up { count + 1 } else { count.saturating_sub(1) };
+    stats.insert(key, count)?;
+    Ok(())
+}
+
+// Remove a later registration of a name that another spelling registered first
+fn drop_name(txn: &WriteTransaction, name: &str, data: &serde_json::Value) -> Result<()> {
+    txn.open_table(NAMES)?.remove(name)?;
+    adjust_stat(txn, "name_count", false)?;
+    if let Some((tld, index_key)) = name_tld_key(name) {
+        txn.open_table(NAME_TLDS)?.remove(index_key.as_str())?;
+        adjust_stat(txn, &format!("name_count:{}", tld), false)?;
+    }
+    if let Some(key) = holding_key(data["owner"].as_str().unwrap_or_default(), "name", name) {
+        txn.open_table(OWNER_HOLDINGS)?.remove(key.as_str())?;
+    }
+    let mut history = txn.open_table(NAME_HISTORY)?;
+    for key in history_keys(&history, name)? {
+        history.remove(key.as_str())?;
+    }
+    txn.open_table(NAME_PROOFS)?.remove(name)?;
+    Ok(())
+}
+
+// Move every row keyed by `name` to `target`
+fn rename_name(txn: &WriteTransaction, name: &str, target: &str, data: &serde_json::Value) -> Result<()> {
+    let mut names = txn.open_table(NAMES)?;
+    names.remove(name)?;
+    names.insert(target, data.to_string().as_str())?;
+
+    let mut tlds = txn.open_table(NAME_TLDS)?;
+    if let Some((tld, index_key)) = name_tld_key(name) {
+        tlds.remove(index_key.as_str())?;
+        adjust_stat(txn, &format!("name_count:{}", tld), false)?;
+    }
+    if let Some((tld, index_key)) = name_tld_key(target) {
+        tlds.insert(index_key.as_str(), target)?;
+        adjust_stat(txn, &format!("name_count:{}", tld), true)?;
+    }
+
+    let owner = data["owner"].as_str().unwrap_or_default();
+    if let (Some(old), Some(new)) = (holding_key(owner, "name", name), holding_key(owner, "name", target)) {
+        let mut holdings = txn.open_table(OWNER_HOLDINGS)?;
+        holdings.remove(old.as_str())?;
+        holdings.insert(new.as_str(), "")?;
+    }
+
+    let mut history = txn.open_table(NAME_HISTORY)?;
+    for key in history_keys(&history, name)? {
+        let row = history.remove(key.as_str())?.map(|v| v.value().to_string());
+        if let Some(row) = row {
+            let moved = format!("{}{}", target, &key[name.len()..]);
+            history.insert(moved.as_str(), row.as_str())?;
+        }
+    }
+
+    let mut proofs = txn.open_table(NAME_PROOFS)?;
+    let proof = proofs.remove(name)?.map(|v| v.value().to_string());
+    if let Some(proof) = proof {
+        let mut proof: serde_json::Value = serde_json::from_str(&proof).unwrap_or_default();
+        proof["name"] = target.into();
+        proofs.insert(target, proof.to_string().as_str())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Db;
+
+    fn temp_path(name: &str) -> (std::path::PathBuf, std::path::PathBuf) {
+        let dir = std::env::temp_dir().join(format!("zord-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        (dir.join("zord.db"), dir)
+    }
+
+    fn stored_version(db: &Db) -> Option<u64> {
+        let read_txn = db.db.begin_read().unwrap();
+        let status = read_txn.open_table(STATUS).unwrap();
+        let version = status.get(SCHEMA_VERSION_KEY).unwrap().map(|v| v.value());
+        version
+    }
+
+    #[test]
+    fn versions_run_one_to_current_without_gaps() {
+        let versions: Vec<u64> = MIGRATIONS.iter().map(|m| m.version).collect();
+        assert_eq!(versions, (1..=current_version()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn new_files_start_at_the_current_version() {
+        let (path, dir) = temp_path("migrate-fresh");
+        let db = Db::new(&path, false).unwrap();
+        assert_eq!(stored_version(&db), Some(current_version()));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn files_from_a_newer_binary_are_refused() {
+        let (path, dir) = temp_path("migrate-newer");
+        let db = Db::new(&path, false).unwrap();
+        set_version(&db.db, current_version() + 1).unwrap();
+        drop(db);
+        let err = Db::new(&path, false).err().expect("newer schema refused");
+        assert!(err.to_string().contains("newer than this binary"), "{}", err);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn inline_payloads_from_v1_move_into_the_deduplicated_store() {
+        let (path, dir) = temp_path("migrate-v1");
+        let db = Db::new(&path, false).unwrap();
+        {
+            // v1 kept the payload in the metadata, as hex plus a text copy
+            let write_txn = db.db.begin_write().unwrap();
+            {
+                let mut inscriptions = write_txn.open_table(INSCRIPTIONS).unwrap();
+                for id in ["i1", "i2"] {
+                    let meta = serde_json::json!({
+                        "id": id,
+                        "content_type": "text/plain",
+                        "content": "hello",
+                        "content_hex": hex::encode("hello"),
+                        "block_height": 1,
+                    });
+                    inscriptions.insert(id, meta.to_string().as_str()).unwrap();
+                }
+            }
+            write_txn.commit().unwrap();
+        }
+        set_version(&db.db, 1).unwrap();
+        drop(db);
+
+        let db = Db::new(&path, false).unwrap();
+        assert_eq!(stored_version(&db), Some(current_version()));
+        for id in ["i1", "i2"] {
+            assert_eq!(db.get_content(id).unwrap().as_deref(), Some(&b"hello"[..]));
+            let meta: serde_json::Value = serde_json::from_str(&db.get_inscription(id).unwrap().unwrap()).unwrap();
+            assert!(meta.get("content_hex").is_none() && meta.get("content").is_none());
+            assert_eq!(meta["content_length"], 5);
+        }
+        {
+            // Both inscriptions share one blob
+            let read_txn = db.db.begin_read().unwrap();
+            let refs = read_txn.open_table(CONTENT_REFS).unwrap();
+            assert_eq!(refs.get(content_digest(b"hello").as_str()).unwrap().map(|v| v.value()), Some(2));
+            assert!(read_txn.open_table(CONTENT).is_err());
+        }
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
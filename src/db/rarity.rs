@@ -0,0 +1,214 @@
+//! Statistical rarity of ZRC-721 tokens, computed from the trait index.
+//!
+//! A token's score is the sum over the collection's trait types of
+//! `tokens / tokens sharing its value`, where a token without a trait type counts
+//! as having the value "none" for it. Rank 1 is the rarest token; equal scores
+//! share a rank. Scores are recomputed by a background job (`src/rarity.rs`)
+//! whenever a collection's `minted` count moved since its last run, so tokens
+//! minted in between have no rarity yet.
+//!
+//! Rows are "<collection>#<id>" -> `{score, rank, of}`, plus one "<collection>"
+//! row recording the `minted` count the scores were computed at. The table is
+//! derived data: never journaled, but copied into snapshots so replicas serve it.
+
+use super::*;
+use std::collections::{BTreeSet, HashMap};
+
+/// Rarity of one token among the `of` tokens scored in its collection.
+#[derive(Debug, serde::Deserialize, serde::Serialize, utoipa::ToSchema)]
+pub struct Rarity {
+    pub score: f64,
+    pub rank: u64,
+    pub of: u64,
+}
+
+impl Db {
+    pub fn get_zrc721_rarity(&self, collection: &str, token_id: &str) -> Result<Option<Rarity>> {
+        let read_txn = self.db.begin_read()?;
+        let table = read_txn.open_table(ZRC721_RARITY)?;
+        let val = match table.get(format!("{}#{}", collection, token_id).as_str())? {
+            Some(raw) => Some(serde_json::from_str(raw.value())?),
+            None => None,
+        };
+        Ok(val)
+    }
+
+    /// A page of the tokens of `collection`, rarest first, optionally only those with
+    /// every trait in `filters`. Tokens not scored yet follow the ranked ones by id.
+    pub fn list_zrc721_tokens_by_rarity(
+        &self,
+        collection: &str,
+        filters: Option<&[(String, String)]>,
+        page: usize,
+        limit: usize,
+    ) -> Result<Vec<Zrc721Token>> {
+        let read_txn = self.db.begin_read()?;
+        let tokens = read_txn.open_table(ZRC721_TOKENS)?;
+        let ids: BTreeSet<String> = match filters {
+            Some(filters) => traits::ids_with_traits(&read_txn, collection, filters)?,
+            None => token_ids(&tokens, collection)?.into_iter().collect(),
+        };
+        let rarity = read_txn.open_table(ZRC721_RARITY)?;
+        let mut ranked = Vec::with_capacity(ids.len());
+        for id in ids {
+            let rank = rarity
+                .get(format!("{}#{}", collection, id).as_str())?
+                .and_then(|raw| serde_json::from_str::<Rarity>(raw.value()).ok())
+                .map(|r| r.rank)
+                .unwrap_or(u64::MAX);
+            ranked.push((rank, id));
+        }
+        ranked.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| by_token_id(&a.1, &b.1)));
+
+        let mut rows = Vec::new();
+        for (_, id) in ranked.iter().skip(page.saturating_mul(limit)).take(limit) {
+            if let Some(raw) = tokens.get(format!("{}#{}", collection, id).as_str())? {
+                rows.push(serde_json::from_str(raw.value())?);
+            }
+        }
+        Ok(rows)
+    }
+
+    /// Rescore every collection minted into since its last scoring; returns how many
+    /// were rescored. Fails on read-only replicas.
+    pub fn refresh_zrc721_rarity(&self) -> Result<usize> {
+        let stale: Vec<(String, u64)> = {
+            let read_txn = self.db.begin_read()?;
+            let rarity = read_txn.open_table(ZRC721_RARITY)?;
+            let mut stale = Vec::new();
+            for item in read_txn.open_table(ZRC721_COLLECTIONS)?.iter()? {
+                let (k, v) = item?;
+                let minted = serde_json::from_str::<serde_json::Value>(v.value())?["minted"]
+                    .as_u64()
+                    .unwrap_or(0);
+                let scored_at = rarity
+                    .get(k.value())?
+                    .and_then(|raw| serde_json::from_str::<serde_json::Value>(raw.value()).ok())
+                    .and_then(|marker| marker["minted"].as_u64());
+                if minted > 0 && scored_at != Some(minted) {
+                    stale.push((k.value().to_string(), minted));
+                }
+            }
+            stale
+        };
+        for (collection, minted) in &stale {
+            self.score_zrc721_collection(collection, *minted)?;
+        }
+        Ok(stale.len())
+    }
+
+    fn score_zrc721_collection(&self, collection: &str, minted: u64) -> Result<()> {
+        let (ids, held) = {
+            let read_txn = self.db.begin_read()?;
+            let ids = token_ids(&read_txn.open_table(ZRC721_TOKENS)?, collection)?;
+            // (trait type, value) pairs of each token, as stored in the trait index
+            let mut held: HashMap<String, Vec<(String, String)>> = HashMap::new();
+            let start = format!("{}:", collection);
+            let end = format!("{};", collection);
+            for item in read_txn.open_table(ZRC721_TRAITS)?.range::<&str>(start.as_str()..end.as_str())? {
+                let (k, _) = item?;
+                let mut parts = k.value()[start.len()..].splitn(3, ':');
+                if let (Some(trait_type), Some(value), Some(id)) = (parts.next(), parts.next(), parts.next()) {
+                    held.entry(id.to_string())
+                        .or_default()
+                        .push((trait_type.to_string(), value.to_string()));
+                }
+            }
+            (ids, held)
+        };
+
+        let total = ids.len() as f64;
+        let mut counts: HashMap<(&str, &str), u64> = HashMap::new();
+        let mut types: BTreeSet<&str> = BTreeSet::new();
+        for pairs in held.values() {
+            for (trait_type, value) in pairs {
+                *counts.entry((trait_type, value)).or_default() += 1;
+                types.insert(trait_type);
+            }
+        }
+        let mut lacking: HashMap<&str, u64> = types.iter().map(|t| (*t, ids.len() as u64)).collect();
+        for pairs in held.values() {
+            let distinct: BTreeSet<&str> = pairs.iter().map(|(t, _)| t.as_str()).collect();
+            for trait_type in distinct {
+                if let Some(n) = lacking.get_mut(trait_type) {
+                    *n = n.saturating_sub(1);
+                }
+            }
+        }
+
+        let mut scored: Vec<(f64, &String)> = ids
+            .iter()
+            .map(|id| {
+                let pairs = held.get(id).map(Vec::as_slice).unwrap_or_default();
+                let score: f64 = types
+                    .iter()
+                    .map(|trait_type| {
+                        let mut shared = pairs
+                            .iter()
+                            .filter(|(t, _)| t == trait_type)
+                            .map(|(t, v)| counts[&(t.as_str(), v.as_str())]);
+                        let shared = shared.next().unwrap_or(lacking[trait_type]);
+                        if shared == 0 { 0.0 } else { total / shared as f64 }
+                    })
+                    .sum();
+                (score, id)
+            })
+            .collect();
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| by_token_id(a.1, b.1)));
+
+        let write_txn = self.begin_write()?;
+        {
+            let mut table = write_txn.open_table(ZRC721_RARITY)?;
+            let start = format!("{}#", collection);
+            let end = format!("{}$", collection);
+            let old: Vec<String> = table
+                .range::<&str>(start.as_str()..end.as_str())?
+                .map(|item| item.map(|(k, _)| k.value().to_string()))
+                .collect::<Result<_, _>>()?;
+            for key in old {
+                table.remove(key.as_str())?;
+            }
+            // Collections without traits get only the marker
+            if !types.is_empty() {
+                let mut rank = 0;
+                let mut previous: Option<f64> = None;
+                for (i, (score, id)) in scored.iter().enumerate() {
+                    if previous != Some(*score) {
+                        rank = i as u64 + 1;
+                        previous = Some(*score);
+                    }
+                    let rarity = Rarity {
+                        score: (score * 1000.0).round() / 1000.0,
+                        rank,
+                        of: ids.len() as u64,
+                    };
+                    table.insert(
+                        format!("{}#{}", collection, id).as_str(),
+                        serde_json::to_string(&rarity)?.as_str(),
+                    )?;
+                }
+            }
+            let marker = serde_json::json!({ "minted": minted, "tokens": ids.len(), "trait_types": types.len() });
+            table.insert(collection, marker.to_string().as_str())?;
+        }
+        // Rarity rows never pass through the read cache
+        write_txn.commit()?;
+        Ok(())
+    }
+}
+
+// Token ids of `collection` from the "<collection>#<id>" token rows
+fn token_ids(tokens: &impl ReadableTable<&'static str, &'static str>, collection: &str) -> Result<Vec<String>> {
+    let start = format!("{}#", collection);
+    let end = format!("{}$", collection);
+    let mut ids = Vec::new();
+    for item in tokens.range::<&str>(start.as_str()..end.as_str())? {
+        ids.push(item?.0.value()[start.len()..].to_string());
+    }
+    Ok(ids)
+}
+
+// Numeric ids in numeric order
+fn by_token_id(a: &str, b: &str) -> std::cmp::Ordering {
+    a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+}
@@ -0,0 +1,44 @@
+//! Operator grants of reserved ZNS names.
+//!
+//! A reserved name (see `names::reserved`) only registers when inscribed by the
+//! address the operator granted it to. Grants are operator configuration like the
+//! verified collections: written outside block transactions and kept across
+//! rollbacks, so revoking one does not undo a registration already indexed.
+
+use super::*;
+
+impl Db {
+    /// Grant the reserved `name` to the address in `entry`, replacing any earlier grant.
+    pub fn put_name_grant(&self, name: &str, entry: &str) -> Result<()> {
+        let write_txn = self.begin_write()?;
+        {
+            let mut table = write_txn.open_table(NAME_GRANTS)?;
+            table.insert(name, entry)?;
+        }
+        self.commit(write_txn)?;
+        Ok(())
+    }
+
+    /// Returns whether the name had a grant.
+    pub fn delete_name_grant(&self, name: &str) -> Result<bool> {
+        let write_txn = self.begin_write()?;
+        let removed = {
+            let mut table = write_txn.open_table(NAME_GRANTS)?;
+            let prev = table.remove(name)?;
+            prev.is_some()
+        };
+        self.commit(write_txn)?;
+        Ok(removed)
+    }
+
+    pub fn list_name_grants(&self) -> Result<Vec<(String, String)>> {
+        let read_txn = self.db.begin_read()?;
+        let table = read_txn.open_table(NAME_GRANTS)?;
+        let mut rows = Vec::new();
+        for item in table.iter()? {
+            let (k, v) = item?;
+            rows.push((k.value().to_string(), v.value().to_string()));
+        }
+        Ok(rows)
+    }
+}
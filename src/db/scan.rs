@@ -0,0 +1,131 @@
+//! Batched table scans pinned to one read transaction.
+//!
+//! A `Snapshot` holds a single redb read transaction; every batch it hands out
+//! resumes after the last key of the previous one inside that transaction, so a
+//! full walk sees one consistent state without loading the table into memory.
+
+use super::*;
+
+/// Rows per batch when the caller has no better figure.
+pub const SCAN_BATCH: usize = 1024;
+
+/// A point-in-time view of the database for long scans.
+pub struct Snapshot<'db> {
+    txn: ReadTransaction<'db>,
+}
+
+/// Iterator over a `&str -> &str` table in key order, `size` rows at a time.
+pub struct TextBatches<'s, 'db> {
+    txn: &'s ReadTransaction<'db>,
+    def: TableDefinition<'static, &'static str, &'static str>,
+    size: usize,
+    after: Option<String>,
+    done: bool,
+}
+
+impl Db {
+    /// Pin a read transaction for batched scans (export, verify, search indexing).
+    pub fn snapshot(&self) -> Result<Snapshot<'_>> {
+        Ok(Snapshot {
+            txn: self.db.begin_read()?,
+        })
+    }
+}
+
+impl<'db> Snapshot<'db> {
+    /// Highest indexed block as seen by this snapshot.
+    pub fn height(&self) -> Result<Option<u64>> {
+        Ok(self.txn.open_table(BLOCKS)?.last()?.map(|(k, _)| k.value()))
+    }
+
+    /// `(name_lower, name JSON)` rows.
+    pub fn names(&self, size: usize) -> TextBatches<'_, 'db> {
+        self.batches(NAMES, size)
+    }
+
+    /// `(tick, token info JSON)` rows.
+    pub fn tokens(&self, size: usize) -> TextBatches<'_, 'db> {
+        self.batches(TOKENS, size)
+    }
+
+    /// Up to `size` `(number, id, metadata)` rows in inscription number order,
+    /// starting after number `after`.
+    pub fn inscriptions_after(&self, after: Option<u64>, size: usize) -> Result<Vec<(u64, String, String)>> {
+        let numbers = self.txn.open_table(INSCRIPTION_NUMBERS)?;
+        let inscriptions = self.txn.open_table(INSCRIPTIONS)?;
+        let range = match after {
+            Some(number) => numbers.range::<u64>((Bound::Excluded(number), Bound::Unbounded))?,
+            None => numbers.range::<u64>(..)?,
+        };
+        let mut rows = Vec::with_capacity(size);
+        for item in range.take(size) {
+            let (number, id) = item?;
+            if let Some(meta) = inscriptions.get(id.value())? {
+                rows.push((number.value(), id.value().to_string(), meta.value().to_string()));
+            }
+        }
+        Ok(rows)
+    }
+
+    pub(super) fn batches(
+        &self,
+        def: TableDefinition<'static, &'static str, &'static str>,
+        size: usize,
+    ) -> TextBatches<'_, 'db> {
+        TextBatches {
+            txn: &self.txn,
+            def,
+            size: size.max(1),
+            after: None,
+            done: false,
+        }
+    }
+
+    pub(super) fn txn(&self) -> &ReadTransaction<'db> {
+        &self.txn
+    }
+}
+
+impl TextBatches<'_, '_> {
+    /// Resume the walk after `key` instead of at the start of the table.
+    pub fn starting_after(mut self, key: Option<String>) -> Self {
+        self.after = key;
+        self
+    }
+
+    fn next_batch(&mut self) -> Result<Vec<(String, String)>> {
+        let table = self.txn.open_table(self.def)?;
+        let range = match self.after.as_deref() {
+            Some(key) => table.range::<&str>((Bound::Excluded(key), Bound::Unbounded))?,
+            None => table.range::<&str>(..)?,
+        };
+        let mut rows = Vec::with_capacity(self.size);
+        for item in range.take(self.size) {
+            let (k, v) = item?;
+            rows.push((k.value().to_string(), v.value().to_string()));
+        }
+        if rows.len() < self.size {
+            self.done = true;
+        }
+        self.after = rows.last().map(|(k, _)| k.clone());
+        Ok(rows)
+    }
+}
+
+impl Iterator for TextBatches<'_, '_> {
+    type Item = Result<Vec<(String, String)>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.next_batch() {
+            Ok(rows) if rows.is_empty() => None,
+            Ok(rows) => Some(Ok(rows)),
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
@@ -0,0 +1,27 @@
+//! Cache of rendered inscription thumbnails.
+//!
+//! Entries are keyed by payload digest, so they stay valid across rollbacks and
+//! are shared by inscriptions with identical bodies.
+
+use super::*;
+
+impl Db {
+    pub fn get_thumbnail(&self, digest: &str, size: u32) -> Result<Option<Vec<u8>>> {
+        let read_txn = self.db.begin_read()?;
+        let table = read_txn.open_table(THUMBNAILS)?;
+        let val = table.get(format!("{}:{}", digest, size).as_str())?.map(|v| v.value().to_vec());
+        Ok(val)
+    }
+
+    /// Store a rendered thumbnail; fails on read-only replicas, which render per request.
+    pub fn put_thumbnail(&self, digest: &str, size: u32, bytes: &[u8]) -> Result<()> {
+        let write_txn = self.begin_write()?;
+        {
+            let mut table = write_txn.open_table(THUMBNAILS)?;
+            table.insert(format!("{}:{}", digest, size).as_str(), bytes)?;
+        }
+        // Thumbnails never pass through the read cache, so it is left intact
+        write_txn.commit()?;
+        Ok(())
+    }
+}
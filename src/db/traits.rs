@@ -0,0 +1,134 @@
+//! ZRC-721 trait index behind `/api/v1/zrc721/collection/:tick/traits` and the
+//! `trait` filter on `/tokens`.
+//!
+//! A token's traits are the `attributes` of the metadata it was minted with, in
+//! the usual `[{"trait_type": "Background", "value": "Blue"}]` form. Each one is
+//! a key `<collection>:<trait type>:<value>:<token id>`, with `%` and `:` in the
+//! type and value percent-encoded so a prefix never spans two traits. Metadata
+//! only reachable through the collection's IPFS root is not fetched or indexed.
+
+use super::*;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// One `trait_type` and the number of tokens per value.
+#[derive(Debug, serde::Serialize, utoipa::ToSchema)]
+pub struct TraitCounts {
+    pub trait_type: String,
+    pub values: BTreeMap<String, u64>,
+}
+
+fn escape(part: &str) -> String {
+    part.replace('%', "%25").replace(':', "%3A")
+}
+
+fn unescape(part: &str) -> String {
+    part.replace("%3A", ":").replace("%25", "%")
+}
+
+// Key prefix of the tokens of `collection` with `trait_type` = `value`
+fn trait_prefix(collection: &str, trait_type: &str, value: &str) -> String {
+    format!("{}:{}:{}:", collection, escape(trait_type), escape(value))
+}
+
+/// Trait index keys of a token minted with `metadata`.
+pub(super) fn trait_keys(collection: &str, token_id: &str, metadata: &serde_json::Value) -> Vec<String> {
+    let Some(attributes) = metadata["attributes"].as_array() else {
+        return Vec::new();
+    };
+    attributes
+        .iter()
+        .filter_map(|attribute| {
+            let trait_type = attribute["trait_type"].as_str()?;
+            let value = match &attribute["value"] {
+                serde_json::Value::String(s) => s.clone(),
+                serde_json::Value::Number(n) => n.to_string(),
+                serde_json::Value::Bool(b) => b.to_string(),
+                _ => return None,
+            };
+            Some(format!("{}{}", trait_prefix(collection, trait_type, &value), token_id))
+        })
+        .collect()
+}
+
+/// Parse `"Background:Blue,Eyes:Red"` into (trait type, value) pairs.
+pub fn parse_trait_filter(raw: &str) -> Result<Vec<(String, String)>> {
+    raw.split(',')
+        .map(|pair| {
+            pair.split_once(':')
+                .map(|(t, v)| (t.trim().to_string(), v.trim().to_string()))
+                .filter(|(t, _)| !t.is_empty())
+                .ok_or_else(|| anyhow::anyhow!("Trait filters look like <trait type>:<value>, got {:?}", pair))
+        })
+        .collect()
+}
+
+impl Db {
+    /// Every trait type of `collection` with its values and how many tokens have each.
+    pub fn zrc721_traits(&self, collection: &str) -> Result<Vec<TraitCounts>> {
+        let read_txn = self.db.begin_read()?;
+        let table = read_txn.open_table(ZRC721_TRAITS)?;
+        let start = format!("{}:", collection);
+        let end = format!("{};", collection);
+        let mut counts: BTreeMap<String, BTreeMap<String, u64>> = BTreeMap::new();
+        for item in table.range::<&str>(start.as_str()..end.as_str())? {
+            let (k, _) = item?;
+            let mut parts = k.value()[start.len()..].splitn(3, ':');
+            let (Some(trait_type), Some(value)) = (parts.next(), parts.next()) else {
+                continue;
+            };
+            *counts
+                .entry(unescape(trait_type))
+                .or_default()
+                .entry(unescape(value))
+                .or_default() += 1;
+        }
+        Ok(counts
+            .into_iter()
+            .map(|(trait_type, values)| TraitCounts { trait_type, values })
+            .collect())
+    }
+
+    /// A page of the tokens of `collection` that have every trait in `filters`, by id.
+    pub fn list_zrc721_tokens_with_traits(
+        &self,
+        collection: &str,
+        filters: &[(String, String)],
+        page: usize,
+        limit: usize,
+    ) -> Result<Vec<Zrc721Token>> {
+        let read_txn = self.db.begin_read()?;
+        let matching = ids_with_traits(&read_txn, collection, filters)?;
+        let tokens = read_txn.open_table(ZRC721_TOKENS)?;
+        let mut rows = Vec::new();
+        for id in matching.iter().skip(page.saturating_mul(limit)).take(limit) {
+            if let Some(raw) = tokens.get(format!("{}#{}", collection, id).as_str())? {
+                rows.push(serde_json::from_str(raw.value())?);
+            }
+        }
+        Ok(rows)
+    }
+}
+
+/// Ids of the tokens of `collection` that have every trait in `filters`.
+pub(super) fn ids_with_traits(
+    txn: &ReadTransaction,
+    collection: &str,
+    filters: &[(String, String)],
+) -> Result<BTreeSet<String>> {
+    let traits = txn.open_table(ZRC721_TRAITS)?;
+    let mut matching: Option<BTreeSet<String>> = None;
+    for (trait_type, value) in filters {
+        let prefix = trait_prefix(collection, trait_type, value);
+        let end = format!("{};", prefix.trim_end_matches(':'));
+        let mut ids = BTreeSet::new();
+        for item in traits.range::<&str>(prefix.as_str()..end.as_str())? {
+            let (k, _) = item?;
+            ids.insert(k.value()[prefix.len()..].to_string());
+        }
+        matching = Some(match matching {
+            Some(previous) => previous.intersection(&ids).cloned().collect(),
+            None => ids,
+        });
+    }
+    Ok(matching.unwrap_or_default())
+}
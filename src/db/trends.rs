@@ -0,0 +1,142 @@
+//! Daily and weekly activity counters behind `/api/v1/stats`.
+//!
+//! `BlockWriter` bumps the counters for the day and the week of each block's
+//! header time as it indexes, so a request reads a few rows instead of scanning
+//! history. Days are UTC calendar dates and weeks are ISO weeks; periods with no
+//! activity have no rows. Active addresses are counted once per period, which is
+//! why weeks are counted on their own rather than summed from days.
+//!
+//! The same table holds the tokens minted per day in each ZRC-721 collection,
+//! under "zrc721_mints:<collection>:<date>", for mint-out progress charts.
+
+use super::*;
+use chrono::{DateTime, Datelike};
+use std::collections::BTreeMap;
+
+pub(super) const METRIC_INSCRIPTIONS: &str = "inscriptions";
+pub(super) const METRIC_ZRC20_MINTS: &str = "zrc20_mints";
+pub(super) const METRIC_NAMES: &str = "names";
+pub(super) const METRIC_ACTIVE: &str = "active_addresses";
+const ZRC721_MINTS_PREFIX: &str = "zrc721_mints";
+
+/// Aggregation window of the activity counters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Period {
+    Day,
+    Week,
+}
+
+impl Period {
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw {
+            "day" | "daily" => Some(Period::Day),
+            "week" | "weekly" => Some(Period::Week),
+            _ => None,
+        }
+    }
+
+    fn prefix(self) -> &'static str {
+        match self {
+            Period::Day => "day",
+            Period::Week => "week",
+        }
+    }
+
+    // "2026-10-16" or "2026-W42" for the period containing unix time `time`
+    fn label(self, time: u64) -> String {
+        let date = DateTime::from_timestamp(time as i64, 0).unwrap_or_default().date_naive();
+        match self {
+            Period::Day => date.format("%Y-%m-%d").to_string(),
+            Period::Week => {
+                let week = date.iso_week();
+                format!("{}-W{:02}", week.year(), week.week())
+            }
+        }
+    }
+}
+
+/// Key prefixes ("day:<date>", "week:<week>") of both periods containing `time`.
+pub(super) fn period_keys(time: u64) -> [String; 2] {
+    [Period::Day, Period::Week].map(|period| format!("{}:{}", period.prefix(), period.label(time)))
+}
+
+/// Counter key for the tokens of `collection` minted on the day containing `time`.
+pub(super) fn zrc721_mint_key(collection: &str, time: u64) -> String {
+    format!("{}:{}:{}", ZRC721_MINTS_PREFIX, collection, Period::Day.label(time))
+}
+
+/// Counter key for a content category.
+pub(super) fn category_metric(content_type: &str) -> String {
+    format!("category:{}", classify_mime(content_type))
+}
+
+/// Activity counted in one day or week.
+#[derive(Debug, Default, serde::Serialize, utoipa::ToSchema)]
+pub struct PeriodStats {
+    pub period: String,
+    pub inscriptions: u64,
+    /// Inscriptions per `classify_mime` category.
+    pub categories: BTreeMap<String, u64>,
+    pub zrc20_mints: u64,
+    pub names: u64,
+    /// Distinct addresses with at least one address activity row in the period.
+    pub active_addresses: u64,
+}
+
+impl Db {
+    /// The `limit` most recent periods that saw any activity, newest first.
+    pub fn activity_stats(&self, period: Period, limit: usize) -> Result<Vec<PeriodStats>> {
+        let read_txn = self.db.begin_read()?;
+        let table = read_txn.open_table(ACTIVITY_STATS)?;
+        let start = format!("{}:", period.prefix());
+        let end = format!("{};", period.prefix());
+        let mut out: Vec<PeriodStats> = Vec::new();
+        for item in table.range::<&str>(start.as_str()..end.as_str())?.rev() {
+            let (k, v) = item?;
+            let Some((label, metric)) = k.value()[start.len()..].split_once(':') else {
+                continue;
+            };
+            if out.last().map(|s| s.period.as_str()) != Some(label) {
+                if out.len() == limit {
+                    break;
+                }
+                out.push(PeriodStats {
+                    period: label.to_string(),
+                    ..Default::default()
+                });
+            }
+            let stats = out.last_mut().expect("period pushed above");
+            let count = v.value();
+            match metric {
+                METRIC_INSCRIPTIONS => stats.inscriptions = count,
+                METRIC_ZRC20_MINTS => stats.zrc20_mints = count,
+                METRIC_NAMES => stats.names = count,
+                METRIC_ACTIVE => stats.active_addresses = count,
+                other => {
+                    if let Some(category) = other.strip_prefix("category:") {
+                        stats.categories.insert(category.to_string(), count);
+                    }
+                }
+            }
+        }
+        Ok(out)
+    }
+}
+
+impl Db {
+    /// Tokens of `collection` minted per UTC day, oldest first, over the `limit` most
+    /// recent days that saw a mint.
+    pub fn zrc721_mints_per_day(&self, collection: &str, limit: usize) -> Result<Vec<(String, u64)>> {
+        let read_txn = self.db.begin_read()?;
+        let table = read_txn.open_table(ACTIVITY_STATS)?;
+        let start = format!("{}:{}:", ZRC721_MINTS_PREFIX, collection);
+        let end = format!("{}:{};", ZRC721_MINTS_PREFIX, collection);
+        let mut days = Vec::new();
+        for item in table.range::<&str>(start.as_str()..end.as_str())?.rev().take(limit) {
+            let (k, v) = item?;
+            days.push((k.value()[start.len()..].to_string(), v.value()));
+        }
+        days.reverse();
+        Ok(days)
+    }
+}
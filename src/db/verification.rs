@@ -0,0 +1,52 @@
+//! Operator-curated registry of verified ZRC-721 collections.
+//!
+//! Ticks are first-come, so a copycat can deploy a look-alike collection under a
+//! similar name. Operators mark the official ones through the admin API and
+//! frontends read the `verified` flag. Like webhooks, the rows are operator
+//! configuration: written outside block transactions and kept across rollbacks.
+
+use super::*;
+
+impl Db {
+    /// Mark `collection` verified, replacing any earlier entry. `entry` is the JSON
+    /// record served back by the admin API.
+    pub fn put_zrc721_verification(&self, collection: &str, entry: &str) -> Result<()> {
+        let write_txn = self.begin_write()?;
+        {
+            let mut table = write_txn.open_table(ZRC721_VERIFIED)?;
+            table.insert(collection, entry)?;
+        }
+        self.commit(write_txn)?;
+        Ok(())
+    }
+
+    /// Returns whether the collection was verified.
+    pub fn delete_zrc721_verification(&self, collection: &str) -> Result<bool> {
+        let write_txn = self.begin_write()?;
+        let removed = {
+            let mut table = write_txn.open_table(ZRC721_VERIFIED)?;
+            let prev = table.remove(collection)?;
+            prev.is_some()
+        };
+        self.commit(write_txn)?;
+        Ok(removed)
+    }
+
+    pub fn is_zrc721_verified(&self, collection: &str) -> Result<bool> {
+        let read_txn = self.db.begin_read()?;
+        let table = read_txn.open_table(ZRC721_VERIFIED)?;
+        let found = table.get(collection)?.is_some();
+        Ok(found)
+    }
+
+    pub fn list_zrc721_verifications(&self) -> Result<Vec<(String, String)>> {
+        let read_txn = self.db.begin_read()?;
+        let table = read_txn.open_table(ZRC721_VERIFIED)?;
+        let mut rows = Vec::new();
+        for item in table.iter()? {
+            let (k, v) = item?;
+            rows.push((k.value().to_string(), v.value().to_string()));
+        }
+        Ok(rows)
+    }
+}
@@ -0,0 +1,193 @@
+//! Whole-database consistency checks behind `zord verify`.
+//!
+//! Every check reads from one snapshot, so the report describes a single
+//! indexed height even if the indexer commits while it runs.
+
+use super::*;
+use std::collections::{HashMap, HashSet};
+
+/// Result of `Db::verify`, printed as JSON by `zord verify`.
+#[derive(Debug, serde::Serialize)]
+pub struct VerifyReport {
+    pub height: Option<u64>,
+    pub tokens_checked: usize,
+    pub collections_checked: usize,
+    pub transfer_outpoints_checked: usize,
+    pub address_entries_checked: usize,
+    pub issues: Vec<VerifyIssue>,
+}
+
+/// One inconsistency found by `Db::verify`. Amounts are base units as strings.
+#[derive(Debug, serde::Serialize)]
+#[serde(tag = "check", rename_all = "snake_case")]
+pub enum VerifyIssue {
+    /// ZRC-20 `supply` differs from the sum of balances plus burns
+    SupplyMismatch {
+        tick: String,
+        supply: String,
+        balances: String,
+        burned: String,
+    },
+    /// ZRC-721 collection `minted` differs from its token rows
+    MintedMismatch {
+        collection: String,
+        minted: u64,
+        tokens: u64,
+    },
+    /// Transfer outpoint whose inscription is unknown or already settled
+    OrphanedTransferOutpoint {
+        outpoint: String,
+        inscription_id: String,
+        reason: String,
+    },
+    /// Address index entry pointing at a missing or settled inscription
+    DanglingAddressEntry {
+        table: String,
+        address: String,
+        inscription_id: String,
+    },
+}
+
+impl VerifyReport {
+    pub fn is_consistent(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+impl Db {
+    /// Check cross-table invariants over the whole database.
+    pub fn verify(&self) -> Result<VerifyReport> {
+        let snapshot = self.snapshot()?;
+        let read_txn = snapshot.txn();
+        let height = snapshot.height()?;
+        let mut issues = Vec::new();
+
+        // ZRC-20: supply == sum(overall) + burned, summed in one pass over balances
+        let mut sums: HashMap<String, u128> = HashMap::new();
+        for batch in snapshot.batches(BALANCES, scan::SCAN_BATCH) {
+            for (key, raw) in batch? {
+                if let Some((_address, tick)) = key.split_once(':') {
+                    let bal = serde_json::from_str::<Balance>(&raw)?;
+                    let sum = sums.entry(tick.to_string()).or_insert(0);
+                    *sum = sum
+                        .checked_add(bal.overall.base_units())
+                        .ok_or_else(|| anyhow::anyhow!("overall sum overflow for {}", tick))?;
+                }
+            }
+        }
+        let burns = read_txn.open_table(ZRC20_BURNS)?;
+        let mut tokens_checked = 0;
+        for batch in snapshot.tokens(scan::SCAN_BATCH) {
+            for (tick, raw) in batch? {
+                let info: serde_json::Value = serde_json::from_str(&raw)?;
+                let supply = info["supply"]
+                    .as_str()
+                    .and_then(|s| s.parse::<u128>().ok())
+                    .unwrap_or(0);
+                let burned = burns
+                    .get(tick.as_str())?
+                    .and_then(|v| v.value().parse::<u128>().ok())
+                    .unwrap_or(0);
+                let balances = sums.get(&tick).copied().unwrap_or(0);
+                if balances.checked_add(burned) != Some(supply) {
+                    issues.push(VerifyIssue::SupplyMismatch {
+                        tick,
+                        supply: supply.to_string(),
+                        balances: balances.to_string(),
+                        burned: burned.to_string(),
+                    });
+                }
+                tokens_checked += 1;
+            }
+        }
+
+        // ZRC-721: collection `minted` == number of "<collection>#<id>" rows
+        let mut minted: HashMap<String, u64> = HashMap::new();
+        for batch in snapshot.batches(ZRC721_TOKENS, scan::SCAN_BATCH) {
+            for (key, _) in batch? {
+                if let Some((collection, _id)) = key.split_once('#') {
+                    *minted.entry(collection.to_string()).or_insert(0) += 1;
+                }
+            }
+        }
+        let mut collections_checked = 0;
+        for item in read_txn.open_table(ZRC721_COLLECTIONS)?.iter()? {
+            let (k, v) = item?;
+            let info: serde_json::Value = serde_json::from_str(v.value())?;
+            let recorded = info["minted"].as_u64().unwrap_or(0);
+            let tokens = minted.get(k.value()).copied().unwrap_or(0);
+            if recorded != tokens {
+                issues.push(VerifyIssue::MintedMismatch {
+                    collection: k.value().to_string(),
+                    minted: recorded,
+                    tokens,
+                });
+            }
+            collections_checked += 1;
+        }
+
+        // Transfer outpoints must point at staged, unsettled transfer inscriptions
+        let transfers = read_txn.open_table(TRANSFER_INSCRIPTIONS)?;
+        let state = read_txn.open_table(INSCRIPTION_STATE)?;
+        let used: HashSet<String> = state
+            .iter()?
+            .filter_map(|item| item.ok())
+            .filter(|(_, v)| matches!(v.value(), "used" | "cancelled"))
+            .map(|(k, _)| k.value().to_string())
+            .collect();
+        let mut transfer_outpoints_checked = 0;
+        for batch in snapshot.batches(TRANSFER_OUTPOINTS, scan::SCAN_BATCH) {
+            for (outpoint, id) in batch? {
+                let reason = if transfers.get(id.as_str())?.is_none() {
+                    Some("unknown transfer inscription")
+                } else if used.contains(&id) {
+                    Some("transfer already settled")
+                } else {
+                    None
+                };
+                if let Some(reason) = reason {
+                    issues.push(VerifyIssue::OrphanedTransferOutpoint {
+                        outpoint,
+                        inscription_id: id,
+                        reason: reason.to_string(),
+                    });
+                }
+                transfer_outpoints_checked += 1;
+            }
+        }
+
+        // Address indexes must only list inscriptions that exist (and, for transfers, are unsettled)
+        let inscriptions = read_txn.open_table(INSCRIPTIONS)?;
+        let mut address_entries_checked = 0;
+        for def in [ADDRESS_INSCRIPTIONS, ADDRESS_TRANSFERS] {
+            for batch in snapshot.batches(def, scan::SCAN_BATCH) {
+                for (address, raw) in batch? {
+                    for id in serde_json::from_str::<Vec<String>>(&raw)? {
+                        let dangling = if def.name() == ADDRESS_TRANSFERS.name() {
+                            transfers.get(id.as_str())?.is_none() || used.contains(&id)
+                        } else {
+                            inscriptions.get(id.as_str())?.is_none()
+                        };
+                        if dangling {
+                            issues.push(VerifyIssue::DanglingAddressEntry {
+                                table: def.name().to_string(),
+                                address: address.clone(),
+                                inscription_id: id,
+                            });
+                        }
+                        address_entries_checked += 1;
+                    }
+                }
+            }
+        }
+
+        Ok(VerifyReport {
+            height,
+            tokens_checked,
+            collections_checked,
+            transfer_outpoints_checked,
+            address_entries_checked,
+            issues,
+        })
+    }
+}
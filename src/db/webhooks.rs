@@ -0,0 +1,70 @@
+//! Storage for webhook subscriptions and their dead-letter log.
+//!
+//! These rows are operator configuration rather than chain state, so they are
+//! written outside block transactions and survive rollbacks.
+
+use super::*;
+
+impl Db {
+    /// Insert or replace the webhook stored under `id`.
+    pub fn put_webhook(&self, id: &str, data: &str) -> Result<()> {
+        let write_txn = self.begin_write()?;
+        {
+            let mut table = write_txn.open_table(WEBHOOKS)?;
+            table.insert(id, data)?;
+        }
+        self.commit(write_txn)?;
+        Ok(())
+    }
+
+    /// Returns whether a webhook was removed.
+    pub fn delete_webhook(&self, id: &str) -> Result<bool> {
+        let write_txn = self.begin_write()?;
+        let removed = {
+            let mut table = write_txn.open_table(WEBHOOKS)?;
+            let prev = table.remove(id)?;
+            prev.is_some()
+        };
+        self.commit(write_txn)?;
+        Ok(removed)
+    }
+
+    pub fn list_webhooks(&self) -> Result<Vec<(String, String)>> {
+        let read_txn = self.db.begin_read()?;
+        let table = read_txn.open_table(WEBHOOKS)?;
+        let mut hooks = Vec::new();
+        for item in table.iter()? {
+            let (k, v) = item?;
+            hooks.push((k.value().to_string(), v.value().to_string()));
+        }
+        Ok(hooks)
+    }
+
+    /// Append a failed delivery to the dead-letter log.
+    pub fn record_dead_letter(&self, webhook_id: &str, entry: &str) -> Result<()> {
+        let now_ms = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let key = format!("{:013}:{}", now_ms, webhook_id);
+        let write_txn = self.begin_write()?;
+        {
+            let mut table = write_txn.open_table(WEBHOOK_DEAD_LETTERS)?;
+            table.insert(key.as_str(), entry)?;
+        }
+        self.commit(write_txn)?;
+        Ok(())
+    }
+
+    /// Newest-first dead letters.
+    pub fn list_dead_letters(&self, limit: usize) -> Result<Vec<(String, String)>> {
+        let read_txn = self.db.begin_read()?;
+        let table = read_txn.open_table(WEBHOOK_DEAD_LETTERS)?;
+        let mut rows = Vec::new();
+        for item in table.iter()?.rev().take(limit) {
+            let (k, v) = item?;
+            rows.push((k.value().to_string(), v.value().to_string()));
+        }
+        Ok(rows)
+    }
+}
@@ -0,0 +1,1485 @@
+//! Per-block write path for the indexer.
+//!
+//! Every mutation a block causes (inscriptions, balances, names, NFT moves,
+//! status heights) goes through one `BlockWriter` and lands in a single redb
+//! write transaction. Nothing is visible to readers until `commit`, and a
+//! block that fails halfway is dropped whole instead of leaving partial state.
+
+use super::*;
+use crate::names::{name_skeleton, parent_name};
+use crate::zrc721::parse_token_number;
+use std::collections::{HashMap, HashSet};
+
+/// Accumulates all writes for one block; obtain it with `Db::block_writer`.
+/// Reads through the writer see the block's own uncommitted writes.
+pub struct BlockWriter<'a> {
+    db: &'a Db,
+    txn: WriteTransaction<'a>,
+    height: u64,
+    // Block header time; picks the day and week the activity counters land in
+    time: u64,
+    // Inverse of every write so far, stored as the block's undo record on commit
+    ops: Mutex<Vec<UndoOp>>,
+    // Address activity rows written so far, handed to webhooks once the block commits
+    events: Mutex<Vec<serde_json::Value>>,
+    // (tx index, input index) of the input being indexed, stamped on ZRC-20 rows
+    position: Mutex<Option<(u32, u32)>>,
+    // (name, registering txid) of names registered so far, proven by `record_name_proofs`
+    registered_names: Mutex<Vec<(String, String)>>,
+    // Re-applying recorded operations (`Db::replay_writer`): no activity counters,
+    // webhook events or undo record
+    replay: bool,
+}
+
+impl Db {
+    /// Open the write transaction for block `height` mined at unix `time`. Dropping
+    /// the writer without calling `commit` aborts the block.
+    pub fn block_writer(&self, height: u64, time: u64) -> Result<BlockWriter<'_>> {
+        Ok(BlockWriter {
+            db: self,
+            txn: self.begin_write()?,
+            height,
+            time,
+            ops: Mutex::new(Vec::new()),
+            events: Mutex::new(Vec::new()),
+            position: Mutex::new(None),
+            registered_names: Mutex::new(Vec::new()),
+            replay: false,
+        })
+    }
+
+    /// Open a writer that re-applies operations already indexed once, such as a
+    /// token rebuild. Finish it with `BlockWriter::finish_replay`.
+    pub fn replay_writer(&self) -> Result<BlockWriter<'_>> {
+        Ok(BlockWriter {
+            replay: true,
+            ..self.block_writer(0, 0)?
+        })
+    }
+}
+
+impl BlockWriter<'_> {
+    /// Record the block as indexed, persist its undo record and commit everything at once.
+    /// Returns the block's address activity rows, each tagged with its `address`.
+    pub fn commit(self, hash: &str) -> Result<Vec<serde_json::Value>> {
+        let height = self.height;
+        let mut ops = self.ops.into_inner().unwrap_or_else(|e| e.into_inner());
+        {
+            if height > 0 && height.is_multiple_of(BALANCE_EPOCH) {
+                write_balance_checkpoint(&self.txn, height, &mut ops)?;
+                merkle::write_zrc20_commitments(&self.txn, height, &mut ops)?;
+            }
+
+            let mut table = self.txn.open_table(BLOCKS)?;
+            ops.push(UndoOp::Indexed {
+                table: BLOCKS.name().to_string(),
+                key: height,
+                prev: table.get(height)?.map(|v| v.value().to_string()),
+            });
+            table.insert(height, hash)?;
+
+            let mut hashes = self.txn.open_table(BLOCK_HASHES)?;
+            ops.push(UndoOp::Text {
+                table: BLOCK_HASHES.name().to_string(),
+                key: hash.to_string(),
+                prev: hashes.get(hash)?.map(|v| v.value().to_string()),
+            });
+            hashes.insert(hash, height.to_string().as_str())?;
+
+            let mut undo = self.txn.open_table(UNDO)?;
+            undo.insert(height, serde_json::to_string(&ops)?.as_str())?;
+
+            let mut status = self.txn.open_table(STATUS)?;
+            status.insert("core_height", height)?;
+            // The first journaled block: nothing below it can be unwound
+            if status.get(UNDO_FLOOR_KEY)?.is_none() {
+                status.insert(UNDO_FLOOR_KEY, height.saturating_sub(1))?;
+            }
+        }
+        self.db.commit(self.txn)?;
+        Ok(self.events.into_inner().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    /// Abort the block, returning the address activity rows it would have produced.
+    /// Used to preview what indexing a transaction would do.
+    pub fn discard(self) -> Vec<serde_json::Value> {
+        drop(self.txn);
+        self.events.into_inner().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Commit a replay writer. Its writes are not journaled, so the undo records of
+    /// blocks up to the current tip would restore pre-replay rows; they are dropped in
+    /// the same transaction and the undo floor raised to the tip, so rollbacks below
+    /// it stop with `UndoHistoryMissing` instead.
+    pub fn finish_replay(self) -> Result<()> {
+        if !self.replay {
+            return Err(anyhow::anyhow!("Only replay writers finish without a block"));
+        }
+        {
+            let tip = self.txn.open_table(BLOCKS)?.last()?.map(|(k, _)| k.value());
+            if let Some(tip) = tip {
+                // Dropping the iterator removes the whole range
+                drop(self.txn.open_table(UNDO)?.drain(..=tip)?);
+                let mut status = self.txn.open_table(STATUS)?;
+                let floor = status.get(UNDO_FLOOR_KEY)?.map(|v| v.value()).unwrap_or(0);
+                status.insert(UNDO_FLOOR_KEY, floor.max(tip))?;
+            }
+        }
+        self.db.commit(self.txn)
+    }
+
+    /// Drop the undo records of blocks more than `depth` below this one and raise the
+    /// undo floor to match. A reorg deeper than `depth` then stops at the floor.
+    pub fn prune_undo(&self, depth: u64) -> Result<()> {
+        let Some(below) = self.height.checked_sub(depth) else {
+            return Ok(());
+        };
+        // Dropping the iterator removes the whole range
+        drop(self.txn.open_table(UNDO)?.drain(..=below)?);
+        // Without a floor this is the first journaled block, which `commit` records
+        let mut status = self.txn.open_table(STATUS)?;
+        let floor = status.get(UNDO_FLOOR_KEY)?.map(|v| v.value());
+        if floor.is_some_and(|floor| floor < below) {
+            status.insert(UNDO_FLOOR_KEY, below)?;
+        }
+        Ok(())
+    }
+
+    /// Run one protocol operation as a savepoint: if `op` fails, every write it made is
+    /// undone from the journal and its activity rows and name registrations are dropped,
+    /// so an operation lands whole or not at all while the rest of the block stands.
+    /// The outer error is a failed undo, which leaves the block unusable.
+    pub fn atomically<T>(&self, op: impl FnOnce(&Self) -> Result<T>) -> Result<Result<T>> {
+        let ops = self.ops.lock().unwrap_or_else(|e| e.into_inner()).len();
+        let events = self.events.lock().unwrap_or_else(|e| e.into_inner()).len();
+        let names = self.registered_names.lock().unwrap_or_else(|e| e.into_inner()).len();
+        let result = op(self);
+        if result.is_err() {
+            let undone = self.ops.lock().unwrap_or_else(|e| e.into_inner()).split_off(ops);
+            for undo in undone.iter().rev() {
+                super::apply_undo(&self.txn, undo)?;
+            }
+            self.events.lock().unwrap_or_else(|e| e.into_inner()).truncate(events);
+            self.registered_names.lock().unwrap_or_else(|e| e.into_inner()).truncate(names);
+        }
+        Ok(result)
+    }
+
+    fn journal(&self, op: UndoOp) {
+        self.ops.lock().unwrap_or_else(|e| e.into_inner()).push(op);
+    }
+
+    pub fn set_status(&self, key: &str, value: u64) -> Result<()> {
+        let mut table = self.txn.open_table(STATUS)?;
+        table.insert(key, value)?;
+        Ok(())
+    }
+
+    // Reads the engines make mid-block; they must see writes staged earlier in the block
+
+    pub fn get_token_info(&self, ticker: &str) -> Result<Option<String>> {
+        self.get_text(TOKENS, ticker)
+    }
+
+    pub fn get_balance(&self, address: &str, ticker: &str) -> Result<Balance> {
+        let key = format!("{}:{}", address, ticker);
+        Ok(match self.get_text(BALANCES, &key)? {
+            Some(raw) => serde_json::from_str(&raw)?,
+            None => Balance::default(),
+        })
+    }
+
+    pub fn get_transfer_inscription(&self, inscription_id: &str) -> Result<Option<String>> {
+        self.get_text(TRANSFER_INSCRIPTIONS, inscription_id)
+    }
+
+    pub fn get_transfer_by_outpoint(&self, txid: &str, vout: u32) -> Result<Option<String>> {
+        self.get_text(TRANSFER_OUTPOINTS, &format!("{}:{}", txid, vout))
+    }
+
+    pub fn get_name(&self, name: &str) -> Result<Option<String>> {
+        self.get_text(NAMES, name)
+    }
+
+    pub fn get_name_commitment(&self, hash: &str) -> Result<Option<String>> {
+        self.get_text(NAME_COMMITMENTS, hash)
+    }
+
+    /// Record a commit-reveal commitment, as `{address, height, inscription_id}` JSON.
+    pub fn put_name_commitment(&self, hash: &str, entry: &str) -> Result<()> {
+        let mut table = self.txn.open_table(NAME_COMMITMENTS)?;
+        self.journal_text(&table, hash)?;
+        table.insert(hash, entry)?;
+        Ok(())
+    }
+
+    /// Consume a commitment once its reveal registered the name.
+    pub fn delete_name_commitment(&self, hash: &str) -> Result<()> {
+        let mut table = self.txn.open_table(NAME_COMMITMENTS)?;
+        self.journal_text(&table, hash)?;
+        table.remove(hash)?;
+        Ok(())
+    }
+
+    /// Operator grant of the reserved name `name`, as `{name, address, note, granted_at}` JSON.
+    pub fn get_name_grant(&self, name: &str) -> Result<Option<String>> {
+        self.get_text(NAME_GRANTS, name)
+    }
+
+    /// The earliest name with confusable skeleton `skeleton`, if any.
+    pub fn get_confusable_name(&self, skeleton: &str) -> Result<Option<String>> {
+        self.get_text(NAME_SKELETONS, skeleton)
+    }
+
+    fn get_text(&self, def: TableDefinition<&str, &str>, key: &str) -> Result<Option<String>> {
+        let table = self.txn.open_table(def)?;
+        let value = table.get(key)?.map(|v| v.value().to_string());
+        Ok(value)
+    }
+
+    /// Whether a transfer inscription was settled, either used or cancelled.
+    pub fn is_inscription_used(&self, inscription_id: &str) -> Result<bool> {
+        Ok(matches!(
+            self.get_text(INSCRIPTION_STATE, inscription_id)?.as_deref(),
+            Some("used" | "cancelled")
+        ))
+    }
+
+    pub fn zrc721_by_outpoint(&self, txid: &str, vout: u32) -> Result<Option<(String, String)>> {
+        let val = self.get_text(ZRC721_OUTPOINTS, &format!("{}:{}", txid, vout))?;
+        Ok(val.and_then(|s| {
+            s.split_once('#')
+                .map(|(c, id)| (c.to_string(), id.to_string()))
+        }))
+    }
+
+    /// Name whose inscription sits at `txid:vout`, if any.
+    pub fn name_by_outpoint(&self, txid: &str, vout: u32) -> Result<Option<String>> {
+        self.get_text(NAME_OUTPOINTS, &format!("{}:{}", txid, vout))
+    }
+
+    /// Store inscription metadata and its raw payload. The metadata is stamped with
+    /// `content_length` and `content_sha256`; identical payloads share one blob.
+    pub fn insert_inscription(&self, id: &str, data: &str, content: &[u8]) -> Result<()> {
+        let digest = content_digest(content);
+        let mut meta: serde_json::Value = serde_json::from_str(data)?;
+        meta["content_length"] = serde_json::json!(content.len());
+        meta["content_sha256"] = serde_json::json!(digest);
+        let data = meta.to_string();
+        let data = data.as_str();
+
+        let mut table = self.txn.open_table(INSCRIPTIONS)?;
+        self.journal_text(&table, id)?;
+        table.insert(id, data)?;
+
+        // Reference-count the blob so rollback can drop it once nothing points at it
+        let mut refs = self.txn.open_table(CONTENT_REFS)?;
+        self.journal_counter(&refs, &digest)?;
+        let count = refs.get(digest.as_str())?.map(|v| v.value()).unwrap_or(0);
+        refs.insert(digest.as_str(), count + 1)?;
+        if count == 0 {
+            let mut blobs = self.txn.open_table(CONTENT_BLOBS)?;
+            self.journal_bytes(&blobs, &digest)?;
+            blobs.insert(digest.as_str(), content)?;
+        }
+
+        // Maintain monotonic inscription numbering for API lookups
+        let mut stats = self.txn.open_table(STATS)?;
+        self.journal_counter(&stats, "inscription_count")?;
+        let count = stats
+            .get("inscription_count")?
+            .map(|v| v.value())
+            .unwrap_or(0);
+        let number = count + 1;
+        stats.insert("inscription_count", number)?;
+
+        let mut numbers = self.txn.open_table(INSCRIPTION_NUMBERS)?;
+        self.journal_indexed(&numbers, number)?;
+        numbers.insert(number, id)?;
+
+        self.count_activity(trends::METRIC_INSCRIPTIONS)?;
+        self.count_activity(&trends::category_metric(meta["content_type"].as_str().unwrap_or("")))?;
+
+        let mut feed = self.txn.open_table(INSCRIPTION_FEED)?;
+        for key in feed::feed_keys(&meta, number) {
+            self.journal_text(&feed, &key)?;
+            feed.insert(key.as_str(), id)?;
+        }
+
+        // Index sender so `/address/:addr/inscriptions` can return results
+        // Receiver tracking is future work; today we key by sender only
+        if let Some(sender) = meta["sender"].as_str() {
+            let mut addr_index = self.txn.open_table(ADDRESS_INSCRIPTIONS)?;
+            let mut list = if let Some(existing) = addr_index.get(sender)? {
+                serde_json::from_str::<Vec<String>>(existing.value()).unwrap_or_default()
+            } else {
+                Vec::new()
+            };
+            list.push(id.to_string());
+            self.journal_appended(&addr_index, sender, id);
+            addr_index.insert(sender, serde_json::to_string(&list)?.as_str())?;
+
+            let mut activity = self.txn.open_table(ADDRESS_ACTIVITY)?;
+            let height = meta["block_height"].as_u64().unwrap_or(0);
+            let entry = serde_json::json!({
+                "kind": "inscription_created",
+                "inscription_id": id,
+                "content_type": meta["content_type"],
+                "txid": meta["txid"],
+                "height": height,
+            });
+            self.append_activity(&mut activity, sender, height, &entry)?;
+        }
+        Ok(())
+    }
+
+    /// Add full-text postings for a text-like payload; other payloads are ignored.
+    pub fn index_content_terms(&self, id: &str, content_type: &str, content: &[u8]) -> Result<()> {
+        if !fulltext::is_text_like(content_type) {
+            return Ok(());
+        }
+        let Some(text) = fulltext::indexed_text(content) else {
+            return Ok(());
+        };
+        let mut postings = self.txn.open_table(CONTENT_TERMS)?;
+        for term in fulltext::terms(text) {
+            let key = format!("{}:{}", term, id);
+            self.journal_text(&postings, &key)?;
+            postings.insert(key.as_str(), "")?;
+        }
+        Ok(())
+    }
+
+    pub fn deploy_token(&self, ticker: &str, info: &str) -> Result<()> {
+        let mut table = self.txn.open_table(TOKENS)?;
+        if table.get(ticker)?.is_some() {
+            return Err(anyhow::anyhow!("Token already exists"));
+        }
+        self.journal_text(&table, ticker)?;
+        table.insert(ticker, info)?;
+
+        let mut stats = self.txn.open_table(STATS)?;
+        self.journal_counter(&stats, "token_count")?;
+        let count = stats.get("token_count")?.map(|v| v.value()).unwrap_or(0);
+        stats.insert("token_count", count + 1)?;
+        Ok(())
+    }
+
+    #[allow(dead_code)]
+    pub fn update_token_supply(&self, ticker: &str, new_supply: u128) -> Result<()> {
+        let mut table = self.txn.open_table(TOKENS)?;
+        let info_str = table
+            .get(ticker)?
+            .ok_or(anyhow::anyhow!("Token not found"))?
+            .value()
+            .to_string();
+
+        let mut info: serde_json::Value = serde_json::from_str(&info_str)?;
+        info["supply"] = serde_json::Value::String(new_supply.to_string());
+        self.journal_text(&table, ticker)?;
+        table.insert(ticker, info.to_string().as_str())?;
+        Ok(())
+    }
+
+    /// Attach `meta` to a deployed token's info record.
+    pub fn set_token_meta(&self, ticker: &str, meta: &serde_json::Value) -> Result<()> {
+        let mut tokens = self.txn.open_table(TOKENS)?;
+        let info_str = tokens
+            .get(ticker)?
+            .ok_or(anyhow::anyhow!("Token not found"))?
+            .value()
+            .to_string();
+        let mut info: serde_json::Value = serde_json::from_str(&info_str)?;
+        info["meta"] = meta.clone();
+        self.journal_text(&tokens, ticker)?;
+        tokens.insert(ticker, info.to_string().as_str())?;
+        Ok(())
+    }
+
+    /// Atomically credit a mint: increase token supply and holder balance
+    /// in a single write transaction to prevent supply/balance drift.
+    pub fn mint_credit_atomic(&self, ticker: &str, address: &str, amt: Amount) -> Result<()> {
+        // Update token supply
+        let mut tokens = self.txn.open_table(TOKENS)?;
+        let info_str = tokens
+            .get(ticker)?
+            .ok_or(anyhow::anyhow!("Token not found"))?
+            .value()
+            .to_string();
+        let mut info: serde_json::Value = serde_json::from_str(&info_str)?;
+        let current_supply = match info["supply"].as_str() {
+            Some(supply) => Amount::parse_base_units(supply)?,
+            None => Amount::ZERO,
+        };
+        let new_supply = current_supply
+            .checked_add(amt)
+            .ok_or_else(|| anyhow::anyhow!("Supply overflow"))?;
+        info["supply"] = serde_json::Value::String(new_supply.to_string());
+        self.journal_text(&tokens, ticker)?;
+        tokens.insert(ticker, info.to_string().as_str())?;
+
+        // Update holder balance (available and overall)
+        let mut balances = self.txn.open_table(BALANCES)?;
+        let key = format!("{}:{}", address, ticker);
+        let current = if let Some(val) = balances.get(key.as_str())? {
+            serde_json::from_str::<Balance>(val.value())?
+        } else {
+            Balance::default()
+        };
+
+        let next_available = current
+            .available
+            .checked_add(amt)
+            .ok_or_else(|| anyhow::anyhow!("Available balance overflow"))?;
+        let next_overall = current
+            .overall
+            .checked_add(amt)
+            .ok_or_else(|| anyhow::anyhow!("Overall balance overflow"))?;
+
+        let new_balance = Balance {
+            available: next_available,
+            overall: next_overall,
+        };
+        self.journal_text(&balances, key.as_str())?;
+        balances.insert(key.as_str(), serde_json::to_string(&new_balance)?.as_str())?;
+        Ok(())
+    }
+
+    pub fn update_balance(
+        &self,
+        address: &str,
+        ticker: &str,
+        available_delta: Delta,
+        overall_delta: Delta,
+    ) -> Result<()> {
+        let key = format!("{}:{}", address, ticker);
+        let mut table = self.txn.open_table(BALANCES)?;
+        let current = if let Some(val) = table.get(key.as_str())? {
+            serde_json::from_str::<Balance>(val.value())?
+        } else {
+            Balance::default()
+        };
+
+        let failure = |delta: Delta, which: &str| match delta {
+            Delta::Credit(_) => anyhow::anyhow!("{} balance overflow", which),
+            _ => anyhow::anyhow!("Insufficient {} balance", which.to_lowercase()),
+        };
+        let new_balance = Balance {
+            available: current
+                .available
+                .apply(available_delta)
+                .ok_or_else(|| failure(available_delta, "Available"))?,
+            overall: current
+                .overall
+                .apply(overall_delta)
+                .ok_or_else(|| failure(overall_delta, "Overall"))?,
+        };
+
+        self.journal_text(&table, key.as_str())?;
+        // Prune storage for true zero rows to keep holder counts tidy
+        if new_balance.available.is_zero() && new_balance.overall.is_zero() {
+            let _ = table.remove(key.as_str());
+        } else {
+            table.insert(key.as_str(), serde_json::to_string(&new_balance)?.as_str())?;
+        }
+        Ok(())
+    }
+
+    pub fn add_burned(&self, tick: &str, amt: Amount) -> Result<()> {
+        let mut burns = self.txn.open_table(ZRC20_BURNS)?;
+        let current = match burns.get(tick)? {
+            Some(v) => Amount::parse_base_units(v.value())?,
+            None => Amount::ZERO,
+        };
+        let next = current
+            .checked_add(amt)
+            .ok_or_else(|| anyhow::anyhow!("burn overflow"))?;
+        self.journal_text(&burns, tick)?;
+        burns.insert(tick, next.to_string().as_str())?;
+        Ok(())
+    }
+
+    pub fn get_mint_record(&self, tick: &str, address: &str) -> Result<Option<String>> {
+        self.get_text(ZRC20_MINTS, &format!("{}:{}", tick, address))
+    }
+
+    /// Add a mint of `amt` by `address` at `height` to its throttling record.
+    pub fn record_mint(&self, tick: &str, address: &str, amt: Amount, height: u64) -> Result<()> {
+        let key = format!("{}:{}", tick, address);
+        let mut mints = self.txn.open_table(ZRC20_MINTS)?;
+        let minted = mints
+            .get(key.as_str())?
+            .and_then(|v| serde_json::from_str::<serde_json::Value>(v.value()).ok())
+            .and_then(|v| v["minted"].as_str().and_then(|m| Amount::parse_base_units(m).ok()))
+            .unwrap_or_default();
+        let next = minted
+            .checked_add(amt)
+            .ok_or_else(|| anyhow::anyhow!("mint record overflow"))?;
+        let record = serde_json::json!({ "minted": next.to_string(), "last_height": height });
+        self.journal_text(&mints, key.as_str())?;
+        mints.insert(key.as_str(), record.to_string().as_str())?;
+        Ok(())
+    }
+
+    /// Append a row to the ticker's events feed. Settled rows arrive through
+    /// `record_zrc20_activity`; operations that move no balance are recorded directly.
+    pub fn record_zrc20_event(&self, tick: &str, height: u64, entry: &serde_json::Value) -> Result<()> {
+        let entry = self.with_position(entry);
+        let mut events = self.txn.open_table(ZRC20_EVENTS)?;
+        let key = next_history_key(&events, tick, height)?;
+        self.journal_text(&events, key.as_str())?;
+        events.insert(key.as_str(), entry.to_string().as_str())?;
+        Ok(())
+    }
+
+    /// Keep a deploy refused by the deploy policy for audit.
+    pub fn record_rejected_deploy(&self, tick: &str, height: u64, entry: &serde_json::Value) -> Result<()> {
+        let entry = self.with_position(entry);
+        let mut table = self.txn.open_table(ZRC20_REJECTED_DEPLOYS)?;
+        let key = next_history_key(&table, tick, height)?;
+        self.journal_text(&table, key.as_str())?;
+        table.insert(key.as_str(), entry.to_string().as_str())?;
+        Ok(())
+    }
+
+    /// Point the writer at input `input_index` of the block's transaction `tx_index`.
+    /// Operations are applied in this (tx index, input index) order, and ZRC-20 rows
+    /// record where in the block they happened. `None` leaves rows unstamped.
+    pub fn set_position(&self, position: Option<(u32, u32)>) {
+        *self.position.lock().unwrap_or_else(|e| e.into_inner()) = position;
+    }
+
+    fn with_position(&self, entry: &serde_json::Value) -> serde_json::Value {
+        let mut entry = entry.clone();
+        if let Some((tx_index, input_index)) = *self.position.lock().unwrap_or_else(|e| e.into_inner()) {
+            entry["tx_index"] = tx_index.into();
+            entry["input_index"] = input_index.into();
+        }
+        entry
+    }
+
+    /// Append a ZRC-20 history row under the ticker and under each address involved.
+    /// `entry` carries `from`/`to`; address rows also get the other side as `counterparty`.
+    pub fn record_zrc20_activity(&self, tick: &str, height: u64, entry: &serde_json::Value) -> Result<()> {
+        let entry = &self.with_position(entry);
+        self.record_zrc20_event(tick, height, entry)?;
+        let from = entry["from"].as_str();
+        let to = entry["to"].as_str();
+        let mut by_tick = self.txn.open_table(ZRC20_TICK_HISTORY)?;
+        let key = next_history_key(&by_tick, tick, height)?;
+        self.journal_text(&by_tick, key.as_str())?;
+        by_tick.insert(key.as_str(), entry.to_string().as_str())?;
+
+        let mut by_address = self.txn.open_table(ZRC20_ADDRESS_HISTORY)?;
+        let mut activity = self.txn.open_table(ADDRESS_ACTIVITY)?;
+        let op = entry["op"].as_str().unwrap_or("");
+        if op == "mint" {
+            self.count_activity(trends::METRIC_ZRC20_MINTS)?;
+        }
+        let kind = match op {
+            "burn" => "burn",
+            "cancel" => "transfer_cancel",
+            _ => "transfer_out",
+        };
+        let mut sides = vec![(from, to, kind)];
+        if to.is_some() && to != from {
+            sides.push((to, from, if op == "mint" { "token_mint" } else { "transfer_in" }));
+        }
+        for (address, counterparty, kind) in sides {
+            let Some(address) = address else {
+                continue;
+            };
+            let mut row = entry.clone();
+            row["counterparty"] = counterparty.into();
+            let key = next_history_key(&by_address, address, height)?;
+            self.journal_text(&by_address, key.as_str())?;
+            by_address.insert(key.as_str(), row.to_string().as_str())?;
+
+            row["kind"] = kind.into();
+            self.append_activity(&mut activity, address, height, &row)?;
+        }
+        Ok(())
+    }
+
+    /// Remove everything indexed for ZRC-20 token `tick` so it can be replayed: the
+    /// token, its balances, mint records, burn tally and history rows, and its
+    /// transfer inscriptions with their outpoints. Returns the state each of those
+    /// inscriptions was in.
+    pub fn clear_zrc20_token(&self, tick: &str) -> Result<HashMap<String, String>> {
+        let (start, end) = (format!("{}:", tick), format!("{};", tick));
+        for def in [ZRC20_EVENTS, ZRC20_TICK_HISTORY, ZRC20_MINTS] {
+            let mut table = self.txn.open_table(def)?;
+            let keys = range_keys(&table, &start, &end)?;
+            self.remove_text(&mut table, &keys)?;
+        }
+        for def in [TOKENS, ZRC20_BURNS] {
+            let mut table = self.txn.open_table(def)?;
+            self.remove_text(&mut table, &[tick.to_string()])?;
+        }
+
+        let mut balances = self.txn.open_table(BALANCES)?;
+        let keys = matching_keys(&balances, |k, _| k.split_once(':').is_some_and(|(_, t)| t == tick))?;
+        self.remove_text(&mut balances, &keys)?;
+        // Address rows are shared with other tickers and, for activity, other protocols
+        let is_zrc20_row = |v: &str| {
+            serde_json::from_str::<serde_json::Value>(v).is_ok_and(|row| row["tick"] == tick && row["op"].is_string())
+        };
+        for def in [ZRC20_ADDRESS_HISTORY, ADDRESS_ACTIVITY] {
+            let mut table = self.txn.open_table(def)?;
+            let keys = matching_keys(&table, |_, v| is_zrc20_row(v))?;
+            self.remove_text(&mut table, &keys)?;
+        }
+
+        let mut transfers = self.txn.open_table(TRANSFER_INSCRIPTIONS)?;
+        let ids = matching_keys(&transfers, |_, v| {
+            serde_json::from_str::<serde_json::Value>(v).is_ok_and(|data| data["tick"] == tick)
+        })?;
+        let mut state = self.txn.open_table(INSCRIPTION_STATE)?;
+        let mut states = HashMap::new();
+        for id in &ids {
+            if let Some(prev) = state.get(id.as_str())? {
+                states.insert(id.clone(), prev.value().to_string());
+            }
+        }
+        self.remove_text(&mut transfers, &ids)?;
+        self.remove_text(&mut state, &ids)?;
+        let ids: HashSet<String> = ids.into_iter().collect();
+        let mut outpoints = self.txn.open_table(TRANSFER_OUTPOINTS)?;
+        let keys = matching_keys(&outpoints, |_, v| ids.contains(v))?;
+        self.remove_text(&mut outpoints, &keys)?;
+
+        let mut pending = self.txn.open_table(ADDRESS_TRANSFERS)?;
+        let mut lists = Vec::new();
+        for item in pending.iter()? {
+            let (k, v) = item?;
+            let list: Vec<String> = serde_json::from_str(v.value()).unwrap_or_default();
+            if list.iter().any(|id| ids.contains(id)) {
+                lists.push((k.value().to_string(), list));
+            }
+        }
+        for (sender, list) in lists {
+            let list: Vec<String> = list.into_iter().filter(|id| !ids.contains(id)).collect();
+            self.journal_text(&pending, &sender)?;
+            if list.is_empty() {
+                pending.remove(sender.as_str())?;
+            } else {
+                pending.insert(sender.as_str(), serde_json::to_string(&list)?.as_str())?;
+            }
+        }
+        Ok(states)
+    }
+
+    fn remove_text(&self, table: &mut Table<&'static str, &'static str>, keys: &[String]) -> Result<()> {
+        for key in keys {
+            self.journal_text(table, key)?;
+            table.remove(key.as_str())?;
+        }
+        Ok(())
+    }
+
+    pub fn register_zrc721_collection(
+        &self,
+        tick: &str,
+        payload: &serde_json::Value,
+    ) -> Result<()> {
+        let mut table = self.txn.open_table(ZRC721_COLLECTIONS)?;
+        if table.get(tick)?.is_some() {
+            return Err(anyhow::anyhow!("Collection already exists"));
+        }
+        self.journal_text(&table, tick)?;
+        table.insert(tick, payload.to_string().as_str())?;
+        Ok(())
+    }
+
+    /// Deploy record of a collection.
+    pub fn get_zrc721_collection(&self, tick: &str) -> Result<Option<serde_json::Value>> {
+        match self.get_text(ZRC721_COLLECTIONS, tick)? {
+            Some(raw) => Ok(Some(serde_json::from_str(&raw)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Replace a collection's record with `amended` and keep `changes` (field -> previous
+    /// and new value) in its update log.
+    pub fn amend_zrc721_collection(
+        &self,
+        tick: &str,
+        amended: &serde_json::Value,
+        changes: &serde_json::Value,
+        inscription_id: &str,
+        height: u64,
+    ) -> Result<()> {
+        let mut collections = self.txn.open_table(ZRC721_COLLECTIONS)?;
+        if collections.get(tick)?.is_none() {
+            return Err(anyhow::anyhow!("Collection not found"));
+        }
+        self.journal_text(&collections, tick)?;
+        collections.insert(tick, amended.to_string().as_str())?;
+
+        let row = serde_json::json!({
+            "inscription_id": inscription_id,
+            "height": height,
+            "changes": changes,
+        });
+        let mut updates = self.txn.open_table(ZRC721_UPDATES)?;
+        let key = next_history_key(&updates, tick, height)?;
+        self.journal_text(&updates, key.as_str())?;
+        updates.insert(key.as_str(), row.to_string().as_str())?;
+        Ok(())
+    }
+
+    pub fn register_zrc721_allowlist(&self, tick: &str, addresses: &[String]) -> Result<()> {
+        let mut table = self.txn.open_table(ZRC721_ALLOWLIST)?;
+        for address in addresses {
+            let key = format!("{}:{}", tick, address);
+            self.journal_text(&table, &key)?;
+            table.insert(key.as_str(), "")?;
+        }
+        Ok(())
+    }
+
+    pub fn is_zrc721_allowlisted(&self, tick: &str, address: &str) -> Result<bool> {
+        Ok(self.get_text(ZRC721_ALLOWLIST, &format!("{}:{}", tick, address))?.is_some())
+    }
+
+    /// Whether an inscription was indexed earlier, including earlier in this block.
+    pub fn inscription_exists(&self, id: &str) -> Result<bool> {
+        Ok(self.get_text(INSCRIPTIONS, id)?.is_some())
+    }
+
+    /// Payload of an inscription indexed earlier, including earlier in this block.
+    pub fn get_inscription_content(&self, id: &str) -> Result<Option<Vec<u8>>> {
+        let digest = match self.get_text(INSCRIPTIONS, id)? {
+            Some(raw) => serde_json::from_str::<serde_json::Value>(&raw)?["content_sha256"]
+                .as_str()
+                .map(|s| s.to_string()),
+            None => None,
+        };
+        let Some(digest) = digest else {
+            return Ok(None);
+        };
+        let blobs = self.txn.open_table(CONTENT_BLOBS)?;
+        let val = blobs.get(digest.as_str())?.map(|v| v.value().to_vec());
+        Ok(val)
+    }
+
+    /// Mint every id in `token_ids` to `owner`, or none of them: the whole batch is
+    /// checked against the supply and existing tokens before anything is written.
+    pub fn insert_zrc721_tokens(
+        &self,
+        tick: &str,
+        token_ids: &[String],
+        owner: &str,
+        inscription_id: &str,
+        metadata: &serde_json::Value,
+        height: u64,
+    ) -> Result<()> {
+        let mut collections = self.txn.open_table(ZRC721_COLLECTIONS)?;
+        let mut tokens = self.txn.open_table(ZRC721_TOKENS)?;
+
+        let mut collection: serde_json::Value = match collections.get(tick)? {
+            Some(raw) => serde_json::from_str(raw.value())?,
+            None => return Err(anyhow::anyhow!("Collection not found")),
+        };
+        let mut seen = HashSet::new();
+        for token_id in token_ids {
+            if !seen.insert(token_id.as_str()) {
+                return Err(anyhow::anyhow!("Token {} listed twice", token_id));
+            }
+            if tokens.get(format!("{}#{}", tick, token_id).as_str())?.is_some() {
+                return Err(anyhow::anyhow!("Token already minted"));
+            }
+        }
+        // Enforce supply-based cap and token id range (0..=supply-1)
+        let current_minted = collection["minted"].as_u64().unwrap_or(0);
+        let supply = collection["supply"]
+            .as_str()
+            .ok_or_else(|| anyhow::anyhow!("Collection has no supply"))?;
+        let max_total = parse_token_number(supply).map_err(|e| anyhow::anyhow!("Invalid collection supply: {}", e))?;
+        if u128::from(current_minted) + token_ids.len() as u128 > max_total {
+            return Err(anyhow::anyhow!("Max token count reached"));
+        }
+        for token_id in token_ids {
+            let id = parse_token_number(token_id).map_err(|e| anyhow::anyhow!("Invalid token id: {}", e))?;
+            if id >= max_total {
+                return Err(anyhow::anyhow!("Token id {} out of range (supply {})", token_id, max_total));
+            }
+        }
+        let minted = current_minted + token_ids.len() as u64;
+        collection["minted"] = serde_json::json!(minted);
+        let gained = self.move_zrc721_tally(tick, None, Some(owner), token_ids.len() as u64)?;
+        collection["owners"] = serde_json::json!(shift_owners(&collection, gained));
+        self.journal_text(&collections, tick)?;
+        collections.insert(tick, collection.to_string().as_str())?;
+
+        let mut activity = self.txn.open_table(ADDRESS_ACTIVITY)?;
+        let mut trait_index = self.txn.open_table(ZRC721_TRAITS)?;
+        for token_id in token_ids {
+            let key = format!("{}#{}", tick, token_id);
+            let token = Zrc721Token {
+                tick: tick.to_string(),
+                token_id: token_id.to_string(),
+                owner: owner.to_string(),
+                inscription_id: inscription_id.to_string(),
+                metadata: metadata.clone(),
+                shielded_burn: false,
+            };
+            self.journal_text(&tokens, key.as_str())?;
+            tokens.insert(key.as_str(), serde_json::to_string(&token)?.as_str())?;
+            self.move_holding("nft", &key, None, Some(owner))?;
+            for trait_key in traits::trait_keys(tick, token_id, metadata) {
+                self.journal_text(&trait_index, &trait_key)?;
+                trait_index.insert(trait_key.as_str(), "")?;
+            }
+
+            let row = serde_json::json!({
+                "kind": "nft_received",
+                "collection": tick,
+                "token_id": token_id,
+                "counterparty": null,
+                "inscription_id": inscription_id,
+                "height": height,
+            });
+            self.append_activity(&mut activity, owner, height, &row)?;
+            let mint = serde_json::json!({
+                "event": "mint",
+                "token_id": token_id,
+                "from": null,
+                "to": owner,
+                "inscription_id": inscription_id,
+                "height": height,
+            });
+            self.record_zrc721_activity(tick, height, &mint)?;
+        }
+        self.count_zrc721_mints(tick, token_ids.len() as u64)?;
+        Ok(())
+    }
+
+    pub fn get_zrc721_token(&self, collection: &str, token_id: &str) -> Result<Option<Zrc721Token>> {
+        match self.get_text(ZRC721_TOKENS, &format!("{}#{}", collection, token_id))? {
+            Some(raw) => Ok(Some(serde_json::from_str(&raw)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Stop tracking the output that carries a token, once it has moved without it.
+    /// Outpoints are keyed by output, so this walks the table.
+    pub fn drop_zrc721_outpoint(&self, collection: &str, token_id: &str) -> Result<()> {
+        let value = format!("{}#{}", collection, token_id);
+        let mut table = self.txn.open_table(ZRC721_OUTPOINTS)?;
+        let keys = matching_keys(&table, |_, v| v == value)?;
+        self.remove_text(&mut table, &keys)
+    }
+
+    pub fn register_zrc721_outpoint(&self, txid: &str, vout: u32, collection: &str, token_id: &str) -> Result<()> {
+        let key = format!("{}:{}", txid, vout);
+        let value = format!("{}#{}", collection, token_id);
+        let mut table = self.txn.open_table(ZRC721_OUTPOINTS)?;
+        self.journal_text(&table, key.as_str())?;
+        table.insert(key.as_str(), value.as_str())?;
+        Ok(())
+    }
+
+    pub fn move_zrc721_outpoint(&self, prev_txid: &str, prev_vout: u32, new_txid: &str, new_vout: u32) -> Result<()> {
+        let prev = format!("{}:{}", prev_txid, prev_vout);
+        let next = format!("{}:{}", new_txid, new_vout);
+        let mut table = self.txn.open_table(ZRC721_OUTPOINTS)?;
+        let v = match table.get(prev.as_str())? {
+            Some(val) => val.value().to_string(),
+            None => {
+                // nothing to move
+                return Ok(());
+            }
+        };
+        self.journal_text(&table, next.as_str())?;
+        table.insert(next.as_str(), v.as_str())?;
+        self.journal_text(&table, prev.as_str())?;
+        let _ = table.remove(prev.as_str());
+        Ok(())
+    }
+
+    /// Append a mint, transfer or burn to the collection's activity feed.
+    pub fn record_zrc721_activity(&self, collection: &str, height: u64, entry: &serde_json::Value) -> Result<()> {
+        let mut activity = self.txn.open_table(ZRC721_ACTIVITY)?;
+        let key = next_history_key(&activity, collection, height)?;
+        self.journal_text(&activity, key.as_str())?;
+        activity.insert(key.as_str(), entry.to_string().as_str())?;
+        Ok(())
+    }
+
+    /// Move a token to `owner` (by `txid` at `height`) and append the move to its history.
+    /// `via` is the transfer inscription for moves made by a `transfer` operation rather
+    /// than by spending the token's output; `sale` holds the terms of a move that paid
+    /// the collection's royalty address.
+    #[allow(clippy::too_many_arguments)]
+    pub fn update_zrc721_owner(
+        &self,
+        collection: &str,
+        token_id: &str,
+        owner: &str,
+        shielded_burn: bool,
+        txid: &str,
+        height: u64,
+        via: Option<&str>,
+        sale: Option<&serde_json::Value>,
+    ) -> Result<()> {
+        let key = format!("{}#{}", collection, token_id);
+        let mut table = self.txn.open_table(ZRC721_TOKENS)?;
+        let current = match table.get(key.as_str())? { Some(r) => r.value().to_string(), None => return Ok(()) };
+        let mut t: Zrc721Token = serde_json::from_str(&current)?;
+        let previous_owner = std::mem::replace(&mut t.owner, owner.to_string());
+        t.shielded_burn = shielded_burn;
+        let s = serde_json::to_string(&t)?;
+        self.journal_text(&table, key.as_str())?;
+        table.insert(key.as_str(), s.as_str())?;
+        self.move_holding("nft", &key, Some(&previous_owner), Some(owner))?;
+        let gained = self.move_zrc721_tally(collection, Some(&previous_owner), Some(owner), 1)?;
+        if gained != 0 {
+            let mut collections = self.txn.open_table(ZRC721_COLLECTIONS)?;
+            let raw = collections.get(collection)?.map(|v| v.value().to_string());
+            if let Some(raw) = raw {
+                let mut info: serde_json::Value = serde_json::from_str(&raw)?;
+                info["owners"] = serde_json::json!(shift_owners(&info, gained));
+                self.journal_text(&collections, collection)?;
+                collections.insert(collection, info.to_string().as_str())?;
+            }
+        }
+
+        let mut entry = serde_json::json!({
+            "event": if shielded_burn { "shielded_burn" } else { "transfer" },
+            "from": previous_owner,
+            "to": owner,
+            "txid": txid,
+            "height": height,
+        });
+        if let Some(via) = via {
+            entry["inscription_id"] = via.into();
+        }
+        if let Some(sale) = sale {
+            entry["sale"] = sale.clone();
+            let mut record = sale.clone();
+            record["token_id"] = token_id.into();
+            record["txid"] = txid.into();
+            record["height"] = height.into();
+            let mut sales = self.txn.open_table(ZRC721_SALES)?;
+            let sale_key = next_history_key(&sales, collection, height)?;
+            self.journal_text(&sales, sale_key.as_str())?;
+            sales.insert(sale_key.as_str(), record.to_string().as_str())?;
+        }
+        let mut history = self.txn.open_table(ZRC721_HISTORY)?;
+        let history_key = next_history_key(&history, key.as_str(), height)?;
+        self.journal_text(&history, history_key.as_str())?;
+        history.insert(history_key.as_str(), entry.to_string().as_str())?;
+        entry["token_id"] = token_id.into();
+        self.record_zrc721_activity(collection, height, &entry)?;
+
+        let mut activity = self.txn.open_table(ADDRESS_ACTIVITY)?;
+        let sides = [
+            (previous_owner.as_str(), "nft_sent", owner),
+            (owner, "nft_received", previous_owner.as_str()),
+        ];
+        for (address, kind, counterparty) in sides {
+            if address == "shielded" {
+                continue;
+            }
+            let mut row = serde_json::json!({
+                "kind": kind,
+                "collection": collection,
+                "token_id": token_id,
+                "counterparty": counterparty,
+                "txid": txid,
+                "height": height,
+            });
+            if let Some(via) = via {
+                row["inscription_id"] = via.into();
+            }
+            self.append_activity(&mut activity, address, height, &row)?;
+        }
+        Ok(())
+    }
+
+    // Transfer inscription helpers
+    pub fn create_transfer_inscription(&self, inscription_id: &str, data: &str) -> Result<()> {
+        let mut table = self.txn.open_table(TRANSFER_INSCRIPTIONS)?;
+        self.journal_text(&table, inscription_id)?;
+        table.insert(inscription_id, data)?;
+
+        let mut state_table = self.txn.open_table(INSCRIPTION_STATE)?;
+        self.journal_text(&state_table, inscription_id)?;
+        state_table.insert(inscription_id, "unused")?;
+
+        // Track the id under its sender until it is settled
+        let parsed: serde_json::Value = serde_json::from_str(data)?;
+        if let Some(sender) = parsed["sender"].as_str() {
+            let mut pending = self.txn.open_table(ADDRESS_TRANSFERS)?;
+            let mut list = if let Some(existing) = pending.get(sender)? {
+                serde_json::from_str::<Vec<String>>(existing.value()).unwrap_or_default()
+            } else {
+                Vec::new()
+            };
+            if !list.iter().any(|id| id == inscription_id) {
+                list.push(inscription_id.to_string());
+                self.journal_appended(&pending, sender, inscription_id);
+                pending.insert(sender, serde_json::to_string(&list)?.as_str())?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn register_transfer_outpoint(&self, txid: &str, vout: u32, inscription_id: &str) -> Result<()> {
+        let key = format!("{}:{}", txid, vout);
+        let mut table = self.txn.open_table(TRANSFER_OUTPOINTS)?;
+        self.journal_text(&table, key.as_str())?;
+        table.insert(key.as_str(), inscription_id)?;
+        Ok(())
+    }
+
+    pub fn remove_transfer_outpoint(&self, txid: &str, vout: u32) -> Result<()> {
+        let key = format!("{}:{}", txid, vout);
+        let mut table = self.txn.open_table(TRANSFER_OUTPOINTS)?;
+        self.journal_text(&table, key.as_str())?;
+        let _ = table.remove(key.as_str());
+        Ok(())
+    }
+
+    pub fn mark_inscription_used(&self, inscription_id: &str) -> Result<()> {
+        self.settle_inscription(inscription_id, "used")
+    }
+
+    /// Settle a transfer inscription that was spent back to its sender.
+    pub fn mark_inscription_cancelled(&self, inscription_id: &str) -> Result<()> {
+        self.settle_inscription(inscription_id, "cancelled")
+    }
+
+    fn settle_inscription(&self, inscription_id: &str, state: &str) -> Result<()> {
+        let mut table = self.txn.open_table(INSCRIPTION_STATE)?;
+        self.journal_text(&table, inscription_id)?;
+        table.insert(inscription_id, state)?;
+
+        // Drop it from the sender's transferable list
+        let transfers = self.txn.open_table(TRANSFER_INSCRIPTIONS)?;
+        let sender = match transfers.get(inscription_id)? {
+            Some(raw) => serde_json::from_str::<serde_json::Value>(raw.value())?["sender"]
+                .as_str()
+                .map(|s| s.to_string()),
+            None => None,
+        };
+        if let Some(sender) = sender {
+            let mut pending = self.txn.open_table(ADDRESS_TRANSFERS)?;
+            let list = match pending.get(sender.as_str())? {
+                Some(existing) => serde_json::from_str::<Vec<String>>(existing.value())?,
+                None => Vec::new(),
+            };
+            if list.iter().any(|id| id == inscription_id) {
+                let list: Vec<String> = list.into_iter().filter(|id| id != inscription_id).collect();
+                self.journal_text(&pending, sender.as_str())?;
+                if list.is_empty() {
+                    pending.remove(sender.as_str())?;
+                } else {
+                    pending.insert(sender.as_str(), serde_json::to_string(&list)?.as_str())?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Name (ZNS) helpers
+    /// Register a name carried by the inscription at `txid:vout` and open its ownership history.
+    pub fn register_name(&self, name: &str, data: &str, txid: &str, vout: u32, height: u64) -> Result<()> {
+        let mut table = self.txn.open_table(NAMES)?;
+        // Enforce first-writer-wins
+        if table.get(name)?.is_some() {
+            return Err(anyhow::anyhow!("Name already registered"));
+        }
+        self.journal_text(&table, name)?;
+        table.insert(name, data)?;
+
+        let mut stats = self.txn.open_table(STATS)?;
+        self.journal_counter(&stats, "name_count")?;
+        let count = stats.get("name_count")?.map(|v| v.value()).unwrap_or(0);
+        stats.insert("name_count", count + 1)?;
+
+        if let Some((tld, index_key)) = name_tld_key(name) {
+            let mut tlds = self.txn.open_table(NAME_TLDS)?;
+            self.journal_text(&tlds, index_key.as_str())?;
+            tlds.insert(index_key.as_str(), name)?;
+
+            let counter = format!("name_count:{}", tld);
+            self.journal_counter(&stats, &counter)?;
+            let count = stats.get(counter.as_str())?.map(|v| v.value()).unwrap_or(0);
+            stats.insert(counter.as_str(), count + 1)?;
+        }
+        let skeleton = name_skeleton(name);
+        let mut skeletons = self.txn.open_table(NAME_SKELETONS)?;
+        if skeletons.get(skeleton.as_str())?.is_none() {
+            self.journal_text(&skeletons, skeleton.as_str())?;
+            skeletons.insert(skeleton.as_str(), name)?;
+        }
+        if let Some(parent) = parent_name(name) {
+            let mut subdomains = self.txn.open_table(NAME_SUBDOMAINS)?;
+            let key = next_history_key(&subdomains, parent, height)?;
+            self.journal_text(&subdomains, key.as_str())?;
+            subdomains.insert(key.as_str(), name)?;
+        }
+        self.count_activity(trends::METRIC_NAMES)?;
+
+        let outpoint = format!("{}:{}", txid, vout);
+        let mut outpoints = self.txn.open_table(NAME_OUTPOINTS)?;
+        self.journal_text(&outpoints, outpoint.as_str())?;
+        outpoints.insert(outpoint.as_str(), name)?;
+        self.registered_names
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push((name.to_string(), txid.to_string()));
+
+        let parsed: serde_json::Value = serde_json::from_str(data)?;
+        let entry = serde_json::json!({
+            "event": "register",
+            "from": null,
+            "to": parsed["owner"],
+            "txid": txid,
+            "height": height,
+        });
+        let mut history = self.txn.open_table(NAME_HISTORY)?;
+        let key = next_history_key(&history, name, height)?;
+        self.journal_text(&history, key.as_str())?;
+        history.insert(key.as_str(), entry.to_string().as_str())?;
+
+        if let Some(owner) = parsed["owner"].as_str() {
+            self.move_holding("name", name, None, Some(owner))?;
+            let mut activity = self.txn.open_table(ADDRESS_ACTIVITY)?;
+            let row = serde_json::json!({
+                "kind": "name_registered",
+                "name": parsed["name"],
+                "inscription_id": parsed["inscription_id"],
+                "txid": txid,
+                "height": height,
+            });
+            self.append_activity(&mut activity, owner, height, &row)?;
+        }
+        Ok(())
+    }
+
+    /// Store the inclusion proof of every name this block registered, given the block's
+    /// `hash`, its `txids` in block order and the header's `merkle_root` when the node
+    /// reported one. A computed root that disagrees with the header stores nothing.
+    pub fn record_name_proofs(&self, hash: &str, txids: &[String], merkle_root: Option<&str>) -> Result<()> {
+        let registered = std::mem::take(&mut *self.registered_names.lock().unwrap_or_else(|e| e.into_inner()));
+        if registered.is_empty() {
+            return Ok(());
+        }
+        let mut table = self.txn.open_table(NAME_PROOFS)?;
+        for (name, txid) in registered {
+            let Some(tx_index) = txids.iter().position(|t| *t == txid) else {
+                tracing::warn!("Registering tx {} of {} not in block {}", txid, name, hash);
+                continue;
+            };
+            let (root, branch) = crate::names::proof::merkle_branch(txids, tx_index)?;
+            if merkle_root.is_some_and(|header| header != root) {
+                tracing::warn!("Computed merkle root {} of block {} does not match its header", root, hash);
+                return Ok(());
+            }
+            let inscription_id = match self.get_name(&name)? {
+                Some(raw) => serde_json::from_str::<serde_json::Value>(&raw)?["inscription_id"].clone(),
+                None => serde_json::Value::Null,
+            };
+            let entry = serde_json::json!({
+                "name": name,
+                "inscription_id": inscription_id,
+                "txid": txid,
+                "height": self.height,
+                "block_hash": hash,
+                "tx_index": tx_index,
+                "merkle_root": root,
+                "merkle_path": branch,
+            });
+            self.journal_text(&table, name.as_str())?;
+            table.insert(name.as_str(), entry.to_string().as_str())?;
+        }
+        Ok(())
+    }
+
+    /// Move a name to `owner` after its outpoint `prev` was spent by `txid` at `height`.
+    /// `vout` is the output of `txid` now carrying the inscription; `None` (shielded) stops tracking.
+    pub fn transfer_name(
+        &self,
+        name: &str,
+        prev: (&str, u32),
+        txid: &str,
+        vout: Option<u32>,
+        owner: &str,
+        height: u64,
+    ) -> Result<()> {
+        let prev_key = format!("{}:{}", prev.0, prev.1);
+        let mut names = self.txn.open_table(NAMES)?;
+        let mut data: serde_json::Value = match names.get(name)? {
+            Some(raw) => serde_json::from_str(raw.value())?,
+            None => return Ok(()),
+        };
+        let previous_owner = data["owner"].clone();
+        data["owner"] = owner.into();
+        // Records were set by the previous owner; the new owner starts from none
+        let records_cleared = data
+            .as_object_mut()
+            .and_then(|d| d.remove("records"))
+            .is_some_and(|r| r.as_object().is_some_and(|m| !m.is_empty()));
+        self.journal_text(&names, name)?;
+        names.insert(name, data.to_string().as_str())?;
+        self.move_holding("name", name, previous_owner.as_str(), Some(owner))?;
+
+        let mut outpoints = self.txn.open_table(NAME_OUTPOINTS)?;
+        self.journal_text(&outpoints, prev_key.as_str())?;
+        outpoints.remove(prev_key.as_str())?;
+        if let Some(vout) = vout {
+            let next_key = format!("{}:{}", txid, vout);
+            self.journal_text(&outpoints, next_key.as_str())?;
+            outpoints.insert(next_key.as_str(), name)?;
+        }
+
+        let mut entry = serde_json::json!({
+            "event": "transfer",
+            "from": previous_owner,
+            "to": owner,
+            "txid": txid,
+            "height": height,
+        });
+        if records_cleared {
+            entry["records_cleared"] = true.into();
+        }
+        let mut history = self.txn.open_table(NAME_HISTORY)?;
+        let key = next_history_key(&history, name, height)?;
+        self.journal_text(&history, key.as_str())?;
+        history.insert(key.as_str(), entry.to_string().as_str())?;
+
+        let mut activity = self.txn.open_table(ADDRESS_ACTIVITY)?;
+        let sides = [
+            (previous_owner.as_str(), "name_sent", Some(owner)),
+            (Some(owner), "name_received", previous_owner.as_str()),
+        ];
+        for (address, kind, counterparty) in sides {
+            let Some(address) = address.filter(|a| *a != "shielded") else {
+                continue;
+            };
+            let row = serde_json::json!({
+                "kind": kind,
+                "name": data["name"],
+                "counterparty": counterparty,
+                "txid": txid,
+                "height": height,
+            });
+            self.append_activity(&mut activity, address, height, &row)?;
+        }
+        Ok(())
+    }
+
+    /// Store the records `data` of `name` after its owner's `set` inscription
+    /// `inscription_id`; `changes` maps each changed key to its `{from, to}` values.
+    pub fn set_name_records(
+        &self,
+        name: &str,
+        data: &serde_json::Value,
+        changes: &serde_json::Value,
+        inscription_id: &str,
+        txid: &str,
+        height: u64,
+    ) -> Result<()> {
+        let mut names = self.txn.open_table(NAMES)?;
+        self.journal_text(&names, name)?;
+        names.insert(name, data.to_string().as_str())?;
+
+        let entry = serde_json::json!({
+            "event": "records",
+            "from": data["owner"],
+            "to": data["owner"],
+            "changes": changes,
+            "inscription_id": inscription_id,
+            "txid": txid,
+            "height": height,
+        });
+        let mut history = self.txn.open_table(NAME_HISTORY)?;
+        let key = next_history_key(&history, name, height)?;
+        self.journal_text(&history, key.as_str())?;
+        history.insert(key.as_str(), entry.to_string().as_str())?;
+        Ok(())
+    }
+
+    // Re-point an owner index entry from `from` to `to`
+    fn move_holding(&self, kind: &str, item: &str, from: Option<&str>, to: Option<&str>) -> Result<()> {
+        let mut table = self.txn.open_table(OWNER_HOLDINGS)?;
+        if let Some(key) = from.and_then(|owner| holding_key(owner, kind, item)) {
+            self.journal_text(&table, &key)?;
+            table.remove(key.as_str())?;
+        }
+        if let Some(key) = to.and_then(|owner| holding_key(owner, kind, item)) {
+            self.journal_text(&table, &key)?;
+            table.insert(key.as_str(), "")?;
+        }
+        Ok(())
+    }
+
+    // Move `count` tokens of `collection` between owner tallies and return the change in
+    // the collection's number of owners
+    fn move_zrc721_tally(&self, collection: &str, from: Option<&str>, to: Option<&str>, count: u64) -> Result<i64> {
+        let mut table = self.txn.open_table(ZRC721_OWNERS)?;
+        let mut gained = 0i64;
+        for (owner, credit) in [(from, false), (to, true)] {
+            let Some(owner) = owner.filter(|o| !o.is_empty() && *o != "shielded") else {
+                continue;
+            };
+            let key = format!("{}:{}", collection, owner);
+            let held: u64 = table.get(key.as_str())?.and_then(|v| v.value().parse().ok()).unwrap_or(0);
+            let next = if credit { held + count } else { held.saturating_sub(count) };
+            self.journal_text(&table, &key)?;
+            if next == 0 {
+                table.remove(key.as_str())?;
+            } else {
+                table.insert(key.as_str(), next.to_string().as_str())?;
+            }
+            gained += i64::from(next > 0) - i64::from(held > 0);
+        }
+        Ok(gained)
+    }
+
+    // Add a row to an address's activity feed within the caller's write transaction
+    fn append_activity(
+        &self,
+        table: &mut Table<&'static str, &'static str>,
+        address: &str,
+        height: u64,
+        entry: &serde_json::Value,
+    ) -> Result<()> {
+        let key = next_history_key(table, address, height)?;
+        self.journal_text(table, key.as_str())?;
+        table.insert(key.as_str(), entry.to_string().as_str())?;
+        if self.replay {
+            return Ok(());
+        }
+        self.mark_active(address)?;
+
+        let mut event = entry.clone();
+        event["address"] = address.into();
+        self.events.lock().unwrap_or_else(|e| e.into_inner()).push(event);
+        Ok(())
+    }
+
+    // Add one to `metric` for the day and the week of this block
+    fn count_activity(&self, metric: &str) -> Result<()> {
+        if self.replay {
+            return Ok(());
+        }
+        for period in trends::period_keys(self.time) {
+            self.bump_activity(&format!("{}:{}", period, metric), 1)?;
+        }
+        Ok(())
+    }
+
+    // Add `minted` tokens to the collection's count for the day of this block
+    fn count_zrc721_mints(&self, collection: &str, minted: u64) -> Result<()> {
+        if self.replay {
+            return Ok(());
+        }
+        self.bump_activity(&trends::zrc721_mint_key(collection, self.time), minted)
+    }
+
+    // Count `address` as active in this block's day and week, once per period
+    fn mark_active(&self, address: &str) -> Result<()> {
+        let mut seen = self.txn.open_table(ACTIVE_ADDRESSES)?;
+        for period in trends::period_keys(self.time) {
+            let key = format!("{}:{}", period, address);
+            if seen.get(key.as_str())?.is_some() {
+                continue;
+            }
+            self.journal_text(&seen, &key)?;
+            seen.insert(key.as_str(), "")?;
+            self.bump_activity(&format!("{}:{}", period, trends::METRIC_ACTIVE), 1)?;
+        }
+        Ok(())
+    }
+
+    fn bump_activity(&self, key: &str, by: u64) -> Result<()> {
+        let mut counters = self.txn.open_table(ACTIVITY_STATS)?;
+        self.journal_counter(&counters, key)?;
+        let count = counters.get(key)?.map(|v| v.value()).unwrap_or(0);
+        counters.insert(key, count + by)?;
+        Ok(())
+    }
+
+    // Snapshot the current value of `key` before it is overwritten or removed
+    fn journal_text(&self, table: &Table<&'static str, &'static str>, key: &str) -> Result<()> {
+        let prev = table.get(key)?.map(|v| v.value().to_string());
+        self.journal(UndoOp::Text {
+            table: table.name().to_string(),
+            key: key.to_string(),
+            prev,
+        });
+        Ok(())
+    }
+
+    // Record that `item` was pushed onto the JSON list under `key`
+    fn journal_appended(&self, table: &Table<&'static str, &'static str>, key: &str, item: &str) {
+        self.journal(UndoOp::Appended {
+            table: table.name().to_string(),
+            key: key.to_string(),
+            item: item.to_string(),
+        });
+    }
+
+    fn journal_counter(&self, table: &Table<&'static str, u64>, key: &str) -> Result<()> {
+        let prev = table.get(key)?.map(|v| v.value());
+        self.journal(UndoOp::Counter {
+            table: table.name().to_string(),
+            key: key.to_string(),
+            prev,
+        });
+        Ok(())
+    }
+
+    fn journal_indexed(&self, table: &Table<u64, &'static str>, key: u64) -> Result<()> {
+        let prev = table.get(key)?.map(|v| v.value().to_string());
+        self.journal(UndoOp::Indexed {
+            table: table.name().to_string(),
+            key,
+            prev,
+        });
+        Ok(())
+    }
+
+    fn journal_bytes(&self, table: &Table<&'static str, &'static [u8]>, key: &str) -> Result<()> {
+        let prev = table.get(key)?.map(|v| hex::encode(v.value()));
+        self.journal(UndoOp::Bytes {
+            table: table.name().to_string(),
+            key: key.to_string(),
+            prev,
+        });
+        Ok(())
+    }
+}
+
+// Keys of `table` in `start..end`
+fn range_keys(table: &Table<&'static str, &'static str>, start: &str, end: &str) -> Result<Vec<String>> {
+    let mut keys = Vec::new();
+    for item in table.range::<&str>(start..end)? {
+        keys.push(item?.0.value().to_string());
+    }
+    Ok(keys)
+}
+
+// Keys of the rows of `table` that satisfy `keep(key, value)`
+fn matching_keys(
+    table: &Table<&'static str, &'static str>,
+    keep: impl Fn(&str, &str) -> bool,
+) -> Result<Vec<String>> {
+    let mut keys = Vec::new();
+    for item in table.iter()? {
+        let (k, v) = item?;
+        if keep(k.value(), v.value()) {
+            keys.push(k.value().to_string());
+        }
+    }
+    Ok(keys)
+}
+
+// A collection record's `owners` after `gained` owners came or went
+fn shift_owners(collection: &serde_json::Value, gained: i64) -> u64 {
+    (collection["owners"].as_u64().unwrap_or(0) as i64 + gained).max(0) as u64
+}
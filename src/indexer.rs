@@ -1,32 +1,52 @@
-use crate::db::Db;
+use crate::blockfiles::BlockFiles;
+use crate::db::{BlockWriter, Db, UndoHistoryMissing};
 use crate::names::NamesEngine;
-use crate::rpc::{ScriptPubKey, ZcashRpcClient};
-use crate::zrc20::Zrc20Engine;
-use crate::zrc721::Zrc721Engine;
+use crate::rpc::{RpcError, ScriptPubKey, TxResponse, ZcashRpcClient};
+use crate::webhooks::Dispatcher;
+use crate::zrc20::{self, Zrc20Engine};
+use crate::zrc721::{self, Zrc721Engine};
 use anyhow::Result;
-use std::collections::HashMap;
 use std::time::Duration;
 use tokio::time::sleep;
 
+// Least wait between polls while the node is loading its block index
+const WARMUP_WAIT: Duration = Duration::from_secs(10);
+
+// Blocks of undo history kept by default: far past any reorg the node accepts, and one
+// balance epoch, so each checkpoint can still be written from the epoch's records
+const DEFAULT_UNDO_DEPTH: u64 = 1000;
+
 pub struct Indexer {
     rpc: ZcashRpcClient,
     db: Db,
-    zrc20: Zrc20Engine,
-    names: NamesEngine,
-    zrc721: Zrc721Engine,
+    webhooks: Dispatcher,
+    // CONTENT_INDEX: maintain full-text postings for text inscriptions
+    content_index: bool,
+    // UNDO_DEPTH: blocks of undo history to keep below the tip; `None` keeps all of it
+    undo_depth: Option<u64>,
+}
+
+/// What `index_block` did with the height it was given.
+enum Step {
+    Indexed,
+    /// The node's chain forked below the tip; the index was unwound to this height.
+    Unwound(u64),
 }
 
 impl Indexer {
-    pub fn new(rpc: ZcashRpcClient, db: Db) -> Self {
-        let zrc20 = Zrc20Engine::new(db.clone());
-        let names = NamesEngine::new(db.clone());
-        let zrc721 = Zrc721Engine::new(db.clone());
+    pub fn new(rpc: ZcashRpcClient, db: Db, webhooks: Dispatcher) -> Self {
         Self {
             rpc,
             db,
-            zrc20,
-            names,
-            zrc721,
+            webhooks,
+            content_index: std::env::var("CONTENT_INDEX")
+                .map(|v| matches!(v.as_str(), "1" | "true" | "TRUE" | "yes" | "YES"))
+                .unwrap_or(false),
+            undo_depth: match std::env::var("UNDO_DEPTH").ok().as_deref() {
+                Some("all") => None,
+                Some(depth) => depth.parse().ok().or(Some(DEFAULT_UNDO_DEPTH)),
+                None => Some(DEFAULT_UNDO_DEPTH),
+            },
         }
     }
 
@@ -45,32 +65,67 @@ impl Indexer {
             tracing::warn!("ZMQ_URL not set, falling back to polling only");
         }
 
+        // ZCASH_BLOCKS_DIR: catch up from the node's block files, then switch to RPC
+        let resume_height = self.db.get_latest_indexed_height()?.map_or(start_height, |h| h + 1);
+        let mut files = tokio::task::spawn_blocking(move || BlockFiles::from_env(resume_height)).await??;
+
         loop {
             let current_height = self
                 .db
                 .get_latest_indexed_height()?
                 .unwrap_or(start_height - 1);
 
-            // Retry RPC calls with backoff to handle transient network errors
-            let chain_height = match self.rpc.get_block_count().await {
-                Ok(height) => height,
-                Err(e) => {
-                    tracing::warn!("Failed to get block count: {} - retrying in 10s", e);
-                    sleep(Duration::from_secs(10)).await;
-                    continue;
-                }
+            // Blocks on disk need no node; it is only asked once they run out
+            let local_tip = files.as_ref().and_then(BlockFiles::tip).filter(|tip| current_height < *tip);
+            if files.is_some() && local_tip.is_none() {
+                tracing::info!("Block files exhausted at height {}; following the node over RPC", current_height);
+                files = None;
+            }
+
+            // The client already retried with backoff; wait out its breaker before asking again
+            let chain_height = match local_tip {
+                Some(tip) => tip,
+                None => match self.rpc.get_block_count().await {
+                    Ok(height) => height,
+                    Err(e @ RpcError::WarmingUp { .. }) => {
+                        tracing::info!("Waiting for the node to start: {}", e);
+                        sleep(self.rpc.retry_in().max(WARMUP_WAIT)).await;
+                        continue;
+                    }
+                    Err(e) => {
+                        let wait = self.rpc.retry_in();
+                        tracing::warn!("Failed to get block count: {} - retrying in {:?}", e, wait);
+                        sleep(wait).await;
+                        continue;
+                    }
+                },
             };
             let _ = self.db.set_status("chain_tip", chain_height);
 
             if current_height < chain_height {
                 let next_height = current_height + 1;
-                match self.index_block(next_height).await {
-                    Ok(_) => {
+                match self.index_block(next_height, files.as_ref()).await {
+                    Ok(Step::Indexed) => {
                         tracing::info!("Indexed block {}", next_height);
                     }
+                    Ok(Step::Unwound(fork)) => {
+                        tracing::info!("Resuming from the fork point at block {}", fork);
+                    }
                     Err(e) => {
-                        tracing::error!("Error indexing block {}: {}", next_height, e);
-                        sleep(Duration::from_secs(5)).await;
+                        // A reorg deeper than the undo history repeats forever; stop instead
+                        if e.is::<UndoHistoryMissing>() {
+                            return Err(e);
+                        }
+                        // RPC failures come back after the client's own retries
+                        let wait = match e.downcast_ref::<RpcError>() {
+                            // The block at this height changed between calls (a reorg); look again now
+                            Some(RpcError::NotFound { .. }) => Duration::from_secs(1),
+                            Some(RpcError::WarmingUp { .. }) => self.rpc.retry_in().max(WARMUP_WAIT),
+                            Some(rpc) if rpc.is_transient() => self.rpc.retry_in(),
+                            _ => Duration::from_secs(5),
+                        };
+                        tracing::error!("Error indexing block {}: {} - retrying in {:?}", next_height, e, wait);
+                        sleep(wait).await;
                     }
                 }
             } else {
@@ -88,330 +143,531 @@ impl Indexer {
         }
     }
 
-    async fn index_block(&self, height: u64) -> Result<()> {
-        let hash = self.rpc.get_block_hash(height).await?;
-        let block = self.rpc.get_block(&hash).await?;
-
-        // Keep a map to correlate parent/child inscriptions if needed later
-        let mut inscriptions_in_block: HashMap<String, (String, String)> = HashMap::new();
+    async fn index_block(&self, height: u64, files: Option<&BlockFiles>) -> Result<Step> {
+        let stored_parent = self.db.get_block_hash(height.saturating_sub(1))?;
+        // A block from a stale branch on disk is left to the node rather than unwinding for it
+        let local = files.and_then(|files| files.read_block(height)).filter(|local| {
+            match (local.block.previousblockhash.as_deref(), stored_parent.as_deref()) {
+                (Some(parent), Some(stored)) if parent != stored => {
+                    tracing::warn!("Block {} on disk does not extend the index; fetching over RPC", height);
+                    false
+                }
+                _ => true,
+            }
+        });
+        let (hash, block, mut local_txs) = match local {
+            Some(local) => (local.hash, local.block, Some(local.txs.into_iter())),
+            None => {
+                let hash = self.rpc.get_block_hash(height).await?;
+                let block = self.rpc.get_block(&hash).await?;
+                (hash, block, None)
+            }
+        };
+
+        // Reorg: our tip is no longer the parent of this block, so unwind to the last block
+        // both chains share in one rollback and let the loop index the new branch from there
+        if let (Some(parent), Some(stored)) = (block.previousblockhash.as_deref(), stored_parent) {
+            if parent != stored {
+                tracing::warn!(
+                    "Reorg detected at block {}: stored parent {} != {}",
+                    height,
+                    stored,
+                    parent
+                );
+                let fork = self.fork_point(height - 1).await?;
+                self.db.rollback_to(fork)?;
+                return Ok(Step::Unwound(fork));
+            }
+        }
 
-        // First pass: index every new inscription carried by the block
+        // Every transaction is in hand before the write transaction opens, so no RPC round trip
+        // ever runs while the database is locked for writing
+        let mut txs = Vec::with_capacity(block.tx.len());
         for txid in &block.tx {
-            let tx = self.rpc.get_raw_transaction(&txid).await?;
-
-            // Zcash ordinals place the payload in scriptSig; walk each input
-            for (_vin_index, vin) in tx.vin.iter().enumerate() {
-                if let Some(script_sig) = &vin.script_sig {
-                    if let Some(inscription) = self.parse_inscription(&script_sig.asm, &txid, &tx) {
-                        let inscription_id = inscription.0;
-                        let sender = inscription.1;
-                        let receiver = inscription.2;
-                        let content_type = inscription.3;
-                        let content = inscription.4;
-                        let content_hex = inscription.5;
-
-                        // Track so later phases can link child inscriptions if required
-                        inscriptions_in_block
-                            .insert(inscription_id.clone(), (sender.clone(), content.clone()));
-
-                        // Persist enough metadata for the HTTP layer to render without additional RPC calls
-                        // Pick an assigned vout for the inscription: prefer the first output with an address
-                        // Prefer an output paying back to the sender; otherwise first address-bearing output
-                        let mut assigned_vout: Option<u32> = None;
-                        for o in &tx.vout {
-                            if let Some(addrs) = &o.script_pub_key.addresses {
-                                if addrs.iter().any(|a| a == &sender) {
-                                    assigned_vout = Some(o.n);
-                                    break;
-                                }
-                            }
-                        }
-                        if assigned_vout.is_none() {
-                            assigned_vout = tx
-                                .vout
-                                .iter()
-                                .find(|o| o.script_pub_key.addresses.as_ref().map(|a| !a.is_empty()).unwrap_or(false))
-                                .map(|o| o.n);
-                        }
-                        let assigned_vout = assigned_vout.unwrap_or(0);
-
-                        let metadata = serde_json::json!({
-                            "id": inscription_id,
-                            "content": content,
-                            "content_hex": content_hex,
-                            "content_type": content_type,
-                            "txid": txid,
-                            "vout": assigned_vout,
-                            "sender": sender,
-                            "receiver": receiver,
-                            "block_height": height,
-                            "block_time": block.time,
-                        });
-
-                        self.db
-                            .insert_inscription(&inscription_id, &metadata.to_string())?;
-
-                        // Emit structured logs so ops can watch which payload types arrive
-                        if content_type == "application/json" {
-                            tracing::info!(
-                                "Found JSON inscription {} in block {}: {}",
-                                inscription_id,
-                                height,
-                                content
-                            );
-                        } else if content_type.starts_with("text/") {
-                            let preview = if content.len() > 100 {
-                                format!("{}...", &content[..100])
-                            } else {
-                                content.clone()
-                            };
-                            tracing::info!(
-                                "Found text inscription {} in block {} ({}): {}",
-                                inscription_id,
-                                height,
-                                content_type,
-                                preview
-                            );
-                        } else {
-                            tracing::info!(
-                                "Found inscription {} in block {} ({}): {} bytes",
-                                inscription_id,
-                                height,
-                                content_type,
-                                content_hex.len() / 2
-                            );
-                        }
+            let fetched = match local_txs.as_mut().and_then(Iterator::next) {
+                Some(tx) => Ok(tx),
+                None => self.rpc.get_raw_transaction(txid).await,
+            };
+            txs.push(match fetched {
+                Ok(tx) => tx,
+                // A mined transaction the node cannot find is a configuration problem, not a reorg
+                Err(RpcError::NotFound { .. }) => {
+                    return Err(anyhow::anyhow!(
+                        "Transaction {} of block {} not found; the node must run with -txindex=1",
+                        txid,
+                        height
+                    ))
+                }
+                Err(e) => return Err(e.into()),
+            });
+        }
 
-                        // Accept JSON payloads using robust MIME detection:
-                        // - application/json
-                        // - application/*+json (RFC 6839 structured suffix)
-                        // - text/* when the body looks like JSON (starts with { or [)
-                        // Case-insensitive, ignore parameters (e.g., "; charset=utf-8").
-                        let looks_json = {
-                            let s = content.trim_start();
-                            s.starts_with('{') || s.starts_with('[')
-                        };
-                        let ct_simple = {
-                            let lower = content_type.to_lowercase();
-                            lower.split(';').next().unwrap_or("").trim().to_string()
-                        };
-                        let is_json_mime = ct_simple == "application/json" || ct_simple.ends_with("+json");
-                        let is_text_like_json = ct_simple.starts_with("text/") && looks_json;
-                        if is_json_mime || is_text_like_json {
-                            if let Err(e) = self.zrc20.process(
-                                "inscribe",
-                                &inscription_id,
-                                &sender,
-                                Some(&receiver),
-                                &content,
-                                Some(txid),
-                                Some(assigned_vout),
-                            ) {
-                                tracing::debug!("Not a valid ZRC-20 operation: {}", e);
-                            }
+        // Stage every write below in one transaction: the block lands whole or not at all
+        let writer = self.db.block_writer(height, block.time)?;
 
-                            if let Err(e) = self.zrc721.process(
-                                "inscribe",
-                                &inscription_id,
-                                &sender,
-                                &content,
-                                Some(txid),
-                                Some(assigned_vout),
-                            ) {
-                                tracing::debug!("Not a valid ZRC-721 operation: {}", e);
-                            }
-                        }
+        // Strictly in block order: when mints race for the last of a supply, the earlier
+        // (tx index, input index) wins
+        for (tx_index, tx) in txs.iter().enumerate() {
+            index_transaction(&writer, tx, tx_index as u32, height, block.time, self.content_index)?;
+        }
 
-                        // Plain text payloads may be ZNS registrations
-                        if ct_simple == "text/plain" && !looks_json {
-                            if let Err(e) = self.names.process(
-                                &inscription_id,
-                                &sender,
-                                &content,
-                                &content_type,
-                            ) {
-                                tracing::debug!("Not a valid name registration: {}", e);
-                            }
+        // Names registered above get the Merkle path of their transaction for `/proof`
+        writer.record_name_proofs(&hash, &block.tx, block.merkleroot.as_deref())?;
+
+        // Transfer tracking is not implemented; full UTXO tracing will be required when
+        // inscription ownership is needed beyond insert-time metadata
+
+        writer.set_status("zrc20_height", height)?;
+        writer.set_status("names_height", height)?;
+        writer.set_status("zrc721_height", height)?;
+        // Header time of the tip, for ord's `/r/blocktime`
+        writer.set_status("tip_time", block.time)?;
+        if let Some(depth) = self.undo_depth {
+            writer.prune_undo(depth)?;
+        }
+        let events = writer.commit(&hash)?;
+        self.webhooks.notify(height, &hash, events);
+        Ok(Step::Indexed)
+    }
+
+    // Highest indexed block at or below `height` that is still on the node's chain
+    async fn fork_point(&self, mut height: u64) -> Result<u64> {
+        loop {
+            match self.db.get_block_hash(height)? {
+                Some(stored) if stored != self.rpc.get_block_hash(height).await? => {}
+                // On the node's chain, or below the first indexed block
+                _ => return Ok(height),
+            }
+            height = height
+                .checked_sub(1)
+                .ok_or_else(|| anyhow::anyhow!("The node's chain shares no block with the index"))?;
+        }
+    }
+}
+
+
+/// Index the inscriptions `tx` reveals and settle the ZRC-20 transfers, ZRC-721 tokens
+/// and names its inputs spend, staging every write in `writer`. `tx_index` is the
+/// transaction's position in the block. Inscriptions apply in input order, then the
+/// transfers the inputs spend, again in input order. Returns one report per
+/// inscription with each protocol's verdict (`null` when the payload is not a candidate).
+pub fn index_transaction(
+    writer: &BlockWriter,
+    tx: &TxResponse,
+    tx_index: u32,
+    height: u64,
+    time: u64,
+    content_index: bool,
+) -> Result<Vec<serde_json::Value>> {
+    let txid = tx.txid.as_str();
+    let mut reports = Vec::new();
+
+    // Zcash ordinals place the payload in scriptSig; walk each input
+    for (input_index, vin) in tx.vin.iter().enumerate() {
+        if let Some(script_sig) = &vin.script_sig {
+            if let Some(inscription) = parse_inscription(&script_sig.asm, txid, tx) {
+                writer.set_position(Some((tx_index, input_index as u32)));
+                let inscription_id = inscription.0;
+                let sender = inscription.1;
+                let receiver = inscription.2;
+                let content_type = inscription.3;
+                let content = inscription.4;
+                let content_bytes = inscription.5;
+
+                // Persist enough metadata for the HTTP layer to render without additional RPC calls
+                // Pick an assigned vout for the inscription: prefer the first output with an address
+                // Prefer an output paying back to the sender; otherwise first address-bearing output
+                let mut assigned_vout: Option<u32> = None;
+                for o in &tx.vout {
+                    if let Some(addrs) = &o.script_pub_key.addresses {
+                        if addrs.iter().any(|a| a == &sender) {
+                            assigned_vout = Some(o.n);
+                            break;
                         }
                     }
                 }
-            }
-            // After indexing inscriptions in this tx, scan inputs to detect transfer reveals
-            for vin in &tx.vin {
-                if let (Some(prev_txid), Some(prev_vout)) = (&vin.txid, vin.vout) {
-                    if let Ok(Some(inscription_id)) = self.db.get_transfer_by_outpoint(prev_txid, prev_vout) {
-                        // Heuristic receiver: first transparent address in current tx outputs
-                        let mut receiver: Option<String> = None;
-                        for out in &tx.vout {
-                            if let Some(addrs) = &out.script_pub_key.addresses {
-                                if let Some(first) = addrs.first() {
-                                    receiver = Some(first.clone());
-                                    break;
-                                }
-                            }
-                        }
+                if assigned_vout.is_none() {
+                    assigned_vout = tx
+                        .vout
+                        .iter()
+                        .find(|o| o.script_pub_key.addresses.as_ref().map(|a| !a.is_empty()).unwrap_or(false))
+                        .map(|o| o.n);
+                }
+                let assigned_vout = assigned_vout.unwrap_or(0);
+
+                let metadata = serde_json::json!({
+                    "id": inscription_id,
+                    "content_type": content_type,
+                    "content_length": content_bytes.len(),
+                    "txid": txid,
+                    "vout": assigned_vout,
+                    "sender": sender,
+                    "receiver": receiver,
+                    "block_height": height,
+                    "block_time": time,
+                });
+
+                // Raw bytes go to the content store; metadata only carries length/digest
+                writer.insert_inscription(
+                    &inscription_id,
+                    &metadata.to_string(),
+                    &content_bytes,
+                )?;
+                if content_index {
+                    writer.index_content_terms(&inscription_id, &content_type, &content_bytes)?;
+                }
 
-                        let _ = self.zrc20.settle_transfer(
+                // Emit structured logs so ops can watch which payload types arrive
+                if content_type == "application/json" {
+                    tracing::info!(
+                        "Found JSON inscription {} in block {}: {}",
+                        inscription_id,
+                        height,
+                        content
+                    );
+                } else if content_type.starts_with("text/") {
+                    let preview = if content.len() > 100 {
+                        format!("{}...", &content[..100])
+                    } else {
+                        content.clone()
+                    };
+                    tracing::info!(
+                        "Found text inscription {} in block {} ({}): {}",
+                        inscription_id,
+                        height,
+                        content_type,
+                        preview
+                    );
+                } else {
+                    tracing::info!(
+                        "Found inscription {} in block {} ({}): {} bytes",
+                        inscription_id,
+                        height,
+                        content_type,
+                        content_bytes.len()
+                    );
+                }
+
+                // Accept JSON payloads using robust MIME detection:
+                // - application/json
+                // - application/*+json (RFC 6839 structured suffix)
+                // - text/* when the body looks like JSON (starts with { or [)
+                // Case-insensitive, ignore parameters (e.g., "; charset=utf-8").
+                let looks_json = {
+                    let s = content.trim_start();
+                    s.starts_with('{') || s.starts_with('[')
+                };
+                let ct_simple = {
+                    let lower = content_type.to_lowercase();
+                    lower.split(';').next().unwrap_or("").trim().to_string()
+                };
+                let is_json_mime = ct_simple == "application/json" || ct_simple.ends_with("+json");
+                let is_text_like_json = ct_simple.starts_with("text/") && looks_json;
+                let (mut zrc20, mut zrc721, mut name) = (None, None, None);
+                if is_json_mime || is_text_like_json {
+                    let result = writer.atomically(|writer| {
+                        Zrc20Engine.process(
+                            writer,
+                            "inscribe",
+                            &inscription_id,
+                            &sender,
+                            Some(&receiver),
+                            &content,
+                            Some(txid),
+                            Some(assigned_vout),
+                            height,
+                        )
+                    })?;
+                    if let Err(e) = &result {
+                        tracing::debug!("Not a valid ZRC-20 operation: {}", e);
+                    }
+                    zrc20 = Some(result);
+
+                    let result = writer.atomically(|writer| {
+                        Zrc721Engine.process(
+                            writer,
+                            "inscribe",
+                            &inscription_id,
+                            &sender,
+                            &content,
+                            Some(txid),
+                            Some(assigned_vout),
+                            height,
+                        )
+                    })?;
+                    if let Err(e) = &result {
+                        tracing::debug!("Not a valid ZRC-721 operation: {}", e);
+                    }
+                    zrc721 = Some(result);
+
+                    let result = writer.atomically(|writer| {
+                        NamesEngine.process_json(
+                            writer,
+                            &inscription_id,
+                            &sender,
+                            &content,
+                            txid,
+                            assigned_vout,
+                            height,
+                        )
+                    })?;
+                    if let Err(e) = &result {
+                        tracing::debug!("Not a valid ZNS operation: {}", e);
+                    }
+                    name = Some(result);
+                }
+
+                // Plain text payloads may be ZNS registrations
+                if ct_simple == "text/plain" && !looks_json {
+                    let result = writer.atomically(|writer| {
+                        NamesEngine.process(
+                            writer,
                             &inscription_id,
-                            receiver.as_deref(),
-                        );
-                        let _ = self.db.mark_inscription_used(&inscription_id);
-                        let _ = self.db.remove_transfer_outpoint(prev_txid, prev_vout);
-                        tracing::info!("Settled transfer reveal {} -> receiver {:?}", inscription_id, receiver);
+                            &sender,
+                            &content,
+                            &content_type,
+                            txid,
+                            assigned_vout,
+                            height,
+                        )
+                    })?;
+                    if let Err(e) = &result {
+                        tracing::debug!("Not a valid name registration: {}", e);
                     }
+                    name = Some(result);
+                }
 
-                    // ZRC-721: ownership move if mint outpoint is spent
-                    if let Ok(Some((collection, token_id))) = self.db.zrc721_by_outpoint(prev_txid, prev_vout) {
-                        // Determine receiver: first transparent address in outputs; if none, mark shielded burn
-                        let mut receiver: Option<String> = None;
-                        let mut new_vout: Option<u32> = None;
-                        for out in &tx.vout {
-                            if let Some(addrs) = &out.script_pub_key.addresses {
-                                if let Some(first) = addrs.first() {
-                                    if !first.starts_with('z') {
-                                        receiver = Some(first.clone());
-                                        new_vout = Some(out.n);
-                                        break;
-                                    }
-                                }
+                // Each JSON payload goes to every engine; report only the one it names
+                let protocol = serde_json::from_str::<serde_json::Value>(&content)
+                    .ok()
+                    .and_then(|v| v["p"].as_str().map(str::to_lowercase));
+                let zrc20 = zrc20.filter(|_| protocol.as_deref() == Some("zrc-20"));
+                let zrc721 = zrc721.filter(|_| protocol.as_deref() == Some("zrc-721"));
+                let name = name.filter(|_| ct_simple == "text/plain" && !looks_json || protocol.as_deref() == Some("zns"));
+                reports.push(serde_json::json!({
+                    "id": inscription_id,
+                    "content_type": content_type,
+                    "content_length": content_bytes.len(),
+                    "vout": assigned_vout,
+                    "sender": sender,
+                    "receiver": receiver,
+                    "zrc20": zrc20.as_ref().map(verdict),
+                    "zrc721": zrc721.as_ref().map(verdict),
+                    "name": name.as_ref().map(verdict),
+                }));
+            }
+        }
+    }
+    // After indexing inscriptions in this tx, scan inputs to detect transfer reveals
+    for (input_index, vin) in tx.vin.iter().enumerate() {
+        if let (Some(prev_txid), Some(prev_vout)) = (&vin.txid, vin.vout) {
+            writer.set_position(Some((tx_index, input_index as u32)));
+            if let Ok(Some(inscription_id)) = writer.get_transfer_by_outpoint(prev_txid, prev_vout) {
+                let destination = zrc20::transfer_destination(tx, height);
+
+                // A settlement that fails part way leaves neither the debit nor the credit behind
+                if let Err(e) = writer
+                    .atomically(|writer| Zrc20Engine.settle_transfer(writer, &inscription_id, destination, txid, height))?
+                {
+                    tracing::warn!("Transfer {} did not settle: {}", inscription_id, e);
+                }
+                // Settlement failed: still retire the inscription so it cannot settle later
+                if !writer.is_inscription_used(&inscription_id).unwrap_or(true) {
+                    let _ = writer.mark_inscription_used(&inscription_id);
+                }
+                let _ = writer.remove_transfer_outpoint(prev_txid, prev_vout);
+                tracing::info!("Settled transfer reveal {} -> {:?}", inscription_id, destination);
+            }
+
+            // ZRC-721: ownership move if mint outpoint is spent
+            if let Ok(Some((collection, token_id))) = writer.zrc721_by_outpoint(prev_txid, prev_vout) {
+                // Determine receiver: first transparent address in outputs; if none, mark shielded burn
+                let mut receiver: Option<String> = None;
+                let mut new_vout: Option<u32> = None;
+                for out in &tx.vout {
+                    if let Some(addrs) = &out.script_pub_key.addresses {
+                        if let Some(first) = addrs.first() {
+                            if !first.starts_with('z') {
+                                receiver = Some(first.clone());
+                                new_vout = Some(out.n);
+                                break;
                             }
                         }
-                        match (receiver, new_vout) {
-                            (Some(addr), Some(vout)) => {
-                                let _ = self.db.update_zrc721_owner(&collection, &token_id, &addr, false);
-                                let _ = self.db.move_zrc721_outpoint(prev_txid, prev_vout, txid, vout);
-                                tracing::info!("ZRC-721 moved: {}#{} -> {} (vout {})", collection, token_id, addr, vout);
-                            }
-                            _ => {
-                                let _ = self.db.update_zrc721_owner(&collection, &token_id, "shielded", true);
-                                // Remove outpoint mapping to prevent further attribution
-                                let _ = self.db.move_zrc721_outpoint(prev_txid, prev_vout, txid, 0);
-                                tracing::info!("ZRC-721 shielded burn: {}#{}", collection, token_id);
-                            }
+                    }
+                }
+                match (receiver, new_vout) {
+                    (Some(addr), Some(vout)) => {
+                        // A move that also pays the royalty address is recorded as a sale
+                        let seller = writer.get_zrc721_token(&collection, &token_id).ok().flatten().map(|t| t.owner);
+                        let info = writer.get_zrc721_collection(&collection).ok().flatten();
+                        let sale = seller
+                            .zip(info)
+                            .and_then(|(seller, info)| zrc721::detect_sale(&info, &seller, &addr, vout, tx));
+                        let moved = writer.atomically(|writer| {
+                            writer.update_zrc721_owner(
+                                &collection,
+                                &token_id,
+                                &addr,
+                                false,
+                                txid,
+                                height,
+                                None,
+                                sale.as_ref(),
+                            )?;
+                            writer.move_zrc721_outpoint(prev_txid, prev_vout, txid, vout)
+                        })?;
+                        match moved {
+                            Ok(()) => tracing::info!("ZRC-721 moved: {}#{} -> {} (vout {})", collection, token_id, addr, vout),
+                            Err(e) => tracing::warn!("ZRC-721 move {}#{} failed: {}", collection, token_id, e),
+                        }
+                    }
+                    _ => {
+                        let burned = writer.atomically(|writer| {
+                            writer.update_zrc721_owner(&collection, &token_id, "shielded", true, txid, height, None, None)?;
+                            // Remove outpoint mapping to prevent further attribution
+                            writer.move_zrc721_outpoint(prev_txid, prev_vout, txid, 0)
+                        })?;
+                        match burned {
+                            Ok(()) => tracing::info!("ZRC-721 shielded burn: {}#{}", collection, token_id),
+                            Err(e) => tracing::warn!("ZRC-721 shielded burn {}#{} failed: {}", collection, token_id, e),
                         }
                     }
                 }
             }
-        }
 
-        // Transfer tracking is not implemented; full UTXO tracing will be required when
-        // inscription ownership is needed beyond insert-time metadata
-
-        self.db.insert_block(height, &hash)?;
-        let _ = self.db.set_status("zrc20_height", height);
-        let _ = self.db.set_status("names_height", height);
-        let _ = self.db.set_status("zrc721_height", height);
-        Ok(())
+            // ZNS: the name follows its inscription UTXO (same receiver rule as ZRC-721)
+            if let Ok(Some(name)) = writer.name_by_outpoint(prev_txid, prev_vout) {
+                let next = tx.vout.iter().find_map(|out| {
+                    let first = out.script_pub_key.addresses.as_ref()?.first()?;
+                    (!first.starts_with('z')).then(|| (first.clone(), out.n))
+                });
+                let (owner, vout) = match &next {
+                    Some((addr, vout)) => (addr.as_str(), Some(*vout)),
+                    None => ("shielded", None),
+                };
+                match writer.atomically(|writer| writer.transfer_name(&name, (prev_txid, prev_vout), txid, vout, owner, height))? {
+                    Ok(()) => tracing::info!("Name moved: {} -> {}", name, owner),
+                    Err(e) => tracing::warn!("Name transfer {} failed: {}", name, e),
+                }
+            }
+        }
     }
+    Ok(reports)
+}
 
-    /// Parse inscription from scriptSig ASM
-    /// Returns: (inscription_id, sender, receiver, content_type, content_utf8, content_hex)
-    fn parse_inscription(
-        &self,
-        asm: &str,
-        txid: &str,
-        tx: &crate::rpc::TxResponse,
-    ) -> Option<(String, String, String, String, String, String)> {
-        let parts: Vec<&str> = asm.split_whitespace().collect();
-
-        // Zcash inscriptions embed "<mime-type-hex> <payload-hex> ..." in scriptSig
-        for i in 0..parts.len() {
-            // Interpret the part as UTF-8 and treat it as a MIME type if it looks sane
-            if let Ok(bytes) = hex::decode(parts[i]) {
-                if let Ok(s) = String::from_utf8(bytes) {
-                    if s.contains("/") && s.len() > 3 && s.len() < 100 {
-                        let content_type = s;
-
-                        // Consume subsequent hex pushes until we hit what looks like sig/pubkey data
-                        let mut content_chunks = Vec::new();
-                        let mut j = i + 1;
-
-                        while j < parts.len() {
-                            let part = parts[j];
-
-                            // Tiny tokens are usually opcodes; ignore them
-                            if part.len() <= 2 {
-                                j += 1;
-                                continue;
-                            }
-
-                            if let Ok(data) = hex::decode(part) {
-                                let near_end = j >= parts.len() - 3;
-
-                                // DER signatures start with 0x30 and are ~70 bytes
-                                let is_signature = data.len() >= 70
-                                    && data.len() <= 74
-                                    && data.get(0) == Some(&0x30);
-
-                                // Pubkeys are either 33/65-byte blobs with the usual prefixes or
-                                // an OP_PUSH marker followed by 33 bytes
-                                let is_pubkey = (data.len() == 33
-                                    && (data.get(0) == Some(&0x02) || data.get(0) == Some(&0x03)))
-                                    || (data.len() == 65 && data.get(0) == Some(&0x04))
-                                    || (data.get(0) == Some(&0x21) && data.len() >= 34); // 0x21 => push 33 bytes
-
-                                // Stop accumulating once we bump into DER sigs or pubkeys near the end
-                                if near_end && (is_signature || is_pubkey) {
-                                    break;
-                                }
-
-                                if data.len() > 0 {
-                                    content_chunks.push(data);
-                                }
-                            }
+fn verdict(result: &Result<()>) -> serde_json::Value {
+    match result {
+        Ok(()) => serde_json::json!({ "valid": true }),
+        Err(e) => serde_json::json!({ "valid": false, "error": e.to_string() }),
+    }
+}
 
+/// Parse inscription from scriptSig ASM
+/// Returns: (inscription_id, sender, receiver, content_type, content_utf8, content_bytes)
+fn parse_inscription(
+    asm: &str,
+    txid: &str,
+    tx: &TxResponse,
+) -> Option<(String, String, String, String, String, Vec<u8>)> {
+    let parts: Vec<&str> = asm.split_whitespace().collect();
+
+    // Zcash inscriptions embed "<mime-type-hex> <payload-hex> ..." in scriptSig
+    for i in 0..parts.len() {
+        // Interpret the part as UTF-8 and treat it as a MIME type if it looks sane
+        if let Ok(bytes) = hex::decode(parts[i]) {
+            if let Ok(s) = String::from_utf8(bytes) {
+                if s.contains("/") && s.len() > 3 && s.len() < 100 {
+                    let content_type = s;
+
+                    // Consume subsequent hex pushes until we hit what looks like sig/pubkey data
+                    let mut content_chunks = Vec::new();
+                    let mut j = i + 1;
+
+                    while j < parts.len() {
+                        let part = parts[j];
+
+                        // Tiny tokens are usually opcodes; ignore them
+                        if part.len() <= 2 {
                             j += 1;
+                            continue;
                         }
 
-                        if content_chunks.is_empty() {
-                            continue;
+                        if let Ok(data) = hex::decode(part) {
+                            let near_end = j >= parts.len() - 3;
+
+                            // DER signatures start with 0x30 and are ~70 bytes
+                            let is_signature = data.len() >= 70
+                                && data.len() <= 74
+                                && data.first() == Some(&0x30);
+
+                            // Pubkeys are either 33/65-byte blobs with the usual prefixes or
+                            // an OP_PUSH marker followed by 33 bytes
+                            let is_pubkey = (data.len() == 33
+                                && (data.first() == Some(&0x02) || data.first() == Some(&0x03)))
+                                || (data.len() == 65 && data.first() == Some(&0x04))
+                                || (data.first() == Some(&0x21) && data.len() >= 34); // 0x21 => push 33 bytes
+
+                            // Stop accumulating once we bump into DER sigs or pubkeys near the end
+                            if near_end && (is_signature || is_pubkey) {
+                                break;
+                            }
+
+                            if !data.is_empty() {
+                                content_chunks.push(data);
+                            }
                         }
 
-                        // Flatten collected chunks into a single buffer
-                        let content_bytes: Vec<u8> = content_chunks.into_iter().flatten().collect();
-                        let content_hex = hex::encode(&content_bytes);
-
-                        // Keep UTF-8 for text/json payloads so higher layers get a preview
-                        let content_utf8 = if content_type.starts_with("text/")
-                            || content_type == "application/json"
-                        {
-                            String::from_utf8(content_bytes.clone())
-                                .unwrap_or_else(|_| content_hex.clone())
-                        } else {
-                            content_hex.clone()
-                        };
+                        j += 1;
+                    }
 
-                        let (sender, _shielded) = tx
-                            .vout
-                            .first()
-                            .map(|vout| classify_address(&vout.script_pub_key))
-                            .unwrap_or_else(|| ("unknown".to_string(), false));
-
-                        let receiver = sender.clone();
-                        let inscription_id = format!("{}i0", txid);
-
-                        tracing::info!(
-                            "Found inscription {} with content type: {} ({} bytes)",
-                            inscription_id,
-                            content_type,
-                            content_bytes.len()
-                        );
-
-                        return Some((
-                            inscription_id,
-                            sender,
-                            receiver,
-                            content_type,
-                            content_utf8,
-                            content_hex,
-                        ));
+                    if content_chunks.is_empty() {
+                        continue;
                     }
+
+                    // Flatten collected chunks into a single buffer
+                    let content_bytes: Vec<u8> = content_chunks.into_iter().flatten().collect();
+                    let content_hex = hex::encode(&content_bytes);
+
+                    // Keep UTF-8 for text/json payloads so higher layers get a preview
+                    let content_utf8 = if content_type.starts_with("text/")
+                        || content_type == "application/json"
+                    {
+                        String::from_utf8(content_bytes.clone())
+                            .unwrap_or_else(|_| content_hex.clone())
+                    } else {
+                        content_hex.clone()
+                    };
+
+                    let (sender, _shielded) = tx
+                        .vout
+                        .first()
+                        .map(|vout| classify_address(&vout.script_pub_key))
+                        .unwrap_or_else(|| ("unknown".to_string(), false));
+
+                    let receiver = sender.clone();
+                    let inscription_id = format!("{}i0", txid);
+
+                    tracing::info!(
+                        "Found inscription {} with content type: {} ({} bytes)",
+                        inscription_id,
+                        content_type,
+                        content_bytes.len()
+                    );
+
+                    return Some((
+                        inscription_id,
+                        sender,
+                        receiver,
+                        content_type,
+                        content_utf8,
+                        content_bytes,
+                    ));
                 }
             }
         }
-
-        None
     }
+
+    None
 }
 
 fn classify_address(script: &ScriptPubKey) -> (String, bool) {
@@ -422,3 +678,246 @@ fn classify_address(script: &ScriptPubKey) -> (String, bool) {
     }
     ("unknown".to_string(), false)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A one-input transaction inscribing `payload` as text/plain for `address`; the
+    // scriptSig ends with a signature and pubkey like a real reveal
+    fn inscribe(txid: &str, address: &str, payload: &str) -> TxResponse {
+        inscribe_as(txid, address, "text/plain;charset=utf-8", payload)
+    }
+
+    fn inscribe_as(txid: &str, address: &str, content_type: &str, payload: &str) -> TxResponse {
+        let asm = format!(
+            "{} {} 30{} 02{}",
+            hex::encode(content_type),
+            hex::encode(payload),
+            "11".repeat(70),
+            "22".repeat(32),
+        );
+        serde_json::from_value(serde_json::json!({
+            "txid": txid,
+            "hex": "",
+            "vin": [{ "scriptSig": { "hex": "", "asm": asm } }],
+            "vout": [{
+                "value": 0.0001,
+                "n": 0,
+                "scriptPubKey": { "hex": "", "asm": "", "type": "pubkeyhash", "addresses": [address] }
+            }]
+        }))
+        .expect("valid transaction")
+    }
+
+    fn temp_db(name: &str) -> (Db, std::path::PathBuf) {
+        let dir = std::env::temp_dir().join(format!("zord-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        (Db::new(dir.join("zord.db"), false).expect("open db"), dir)
+    }
+
+    // Deploy a token with room for one full mint, then stage two mints of the full
+    // limit in block 2, as transactions 1 and 2 in the given order
+    fn race(name: &str, mints: [(&str, &str); 2]) -> (Db, std::path::PathBuf) {
+        let (db, dir) = temp_db(name);
+        let deploy = r#"{"p":"zrc-20","op":"deploy","tick":"race","max":"1000","lim":"600","dec":"0"}"#;
+        let writer = db.block_writer(1, 0).unwrap();
+        index_transaction(&writer, &inscribe(&"d".repeat(64), "t1deployer", deploy), 1, 1, 0, false).unwrap();
+        writer.commit("block1").unwrap();
+
+        let writer = db.block_writer(2, 0).unwrap();
+        let mint = r#"{"p":"zrc-20","op":"mint","tick":"race","amt":"600"}"#;
+        for (tx_index, (txid, address)) in mints.iter().enumerate() {
+            index_transaction(&writer, &inscribe(txid, address, mint), tx_index as u32 + 1, 2, 0, false).unwrap();
+        }
+        writer.commit("block2").unwrap();
+        (db, dir)
+    }
+
+    fn overall(db: &Db, address: &str) -> String {
+        db.get_balance(address, "race").unwrap().overall.to_string()
+    }
+
+    #[test]
+    fn earlier_transaction_wins_oversubscribed_final_mint() {
+        let (first, second) = ("a".repeat(64), "b".repeat(64));
+        let (db, dir) = race("mint-order", [(&first, "t1first"), (&second, "t1second")]);
+
+        assert_eq!(overall(&db, "t1first"), "600");
+        assert_eq!(overall(&db, "t1second"), "0");
+        let info: serde_json::Value = serde_json::from_str(&db.get_token_info("race").unwrap().unwrap()).unwrap();
+        assert_eq!(info["supply"], "600");
+
+        // The credited mint records where in the block it was applied
+        let rows = db.list_zrc20_events("race", 0, 10, None).unwrap();
+        let mints: Vec<serde_json::Value> = rows
+            .iter()
+            .map(|(_, raw)| serde_json::from_str::<serde_json::Value>(raw).unwrap())
+            .filter(|row| row["op"] == "mint")
+            .collect();
+        assert_eq!(mints.len(), 1);
+        assert_eq!(mints[0]["to"], "t1first");
+        assert_eq!(mints[0]["tx_index"], 1);
+        assert_eq!(mints[0]["input_index"], 0);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn reveal_from_block_files_parses_like_rpc() {
+        let payload = "x".repeat(300);
+        let sig = format!("3044022011{}022022{}01", "aa".repeat(31), "bb".repeat(31));
+        let pubkey = format!("02{}", "cc".repeat(32));
+        let mut script = vec![24];
+        script.extend(b"text/plain;charset=utf-8");
+        script.extend([0x4d, 0x2c, 0x01]);
+        script.extend(payload.as_bytes());
+        script.push(71);
+        script.extend(hex::decode(&sig).unwrap());
+        script.push(33);
+        script.extend(hex::decode(&pubkey).unwrap());
+
+        // zcashd prints the signature's hash type instead of its last byte
+        let asm = crate::blockfiles::script::asm(&script, true);
+        assert_eq!(
+            asm,
+            format!(
+                "{} {} {}[ALL] {}",
+                hex::encode("text/plain;charset=utf-8"),
+                hex::encode(&payload),
+                &sig[..sig.len() - 2],
+                pubkey
+            )
+        );
+        let tx = inscribe(&"e".repeat(64), "t1reveal", "");
+        let (_, _, _, content_type, content, _) = parse_inscription(&asm, &tx.txid, &tx).expect("inscription");
+        assert_eq!(content_type, "text/plain;charset=utf-8");
+        assert_eq!(content, payload);
+    }
+
+    #[test]
+    fn block_order_not_txid_decides_the_winner() {
+        // The later-sorting txid comes first in the block and takes the mint
+        let (first, second) = ("f".repeat(64), "0".repeat(64));
+        let (db, dir) = race("mint-order-swapped", [(&first, "t1second"), (&second, "t1first")]);
+
+        assert_eq!(overall(&db, "t1second"), "600");
+        assert_eq!(overall(&db, "t1first"), "0");
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn failed_operation_leaves_no_partial_writes() {
+        use crate::zrc20::amount::Delta;
+        let (db, dir) = race("savepoint", [(&"a".repeat(64), "t1first"), (&"b".repeat(64), "t1second")]);
+
+        // The debit lands, then the matching credit fails: the savepoint takes the debit back
+        let writer = db.block_writer(3, 0).unwrap();
+        let held = writer.get_balance("t1first", "race").unwrap().overall;
+        let result = writer
+            .atomically(|writer| {
+                writer.update_balance("t1first", "race", Delta::Debit(held), Delta::Debit(held))?;
+                Err::<(), _>(anyhow::anyhow!("credit failed"))
+            })
+            .unwrap();
+        assert!(result.is_err());
+        assert_eq!(writer.get_balance("t1first", "race").unwrap().overall.to_string(), "600");
+        writer.commit("block3").unwrap();
+        assert_eq!(overall(&db, "t1first"), "600");
+
+        // Unwinding the block still restores block 2 exactly
+        db.rollback_to(2).unwrap();
+        assert_eq!(overall(&db, "t1first"), "600");
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn address_lists_journal_the_append_and_unwind_exactly() {
+        let (db, dir) = temp_db("undo-append");
+        for (height, id) in [(1, "i1"), (2, "i2"), (3, "i3")] {
+            let writer = db.block_writer(height, 0).unwrap();
+            let meta = serde_json::json!({ "sender": "t1alice", "block_height": height, "content_type": "text/plain" });
+            writer.insert_inscription(id, &meta.to_string(), b"hi").unwrap();
+            writer.commit(&format!("block{}", height)).unwrap();
+        }
+        assert_eq!(db.get_inscriptions_by_address("t1alice").unwrap(), ["i1", "i2", "i3"]);
+
+        // Each record holds the appended id, not a copy of the list it grew
+        let summary = db.list_block_summaries(0, 1, None).unwrap();
+        assert_eq!(summary[0].inscriptions, 1);
+
+        db.rollback_to(2).unwrap();
+        assert_eq!(db.get_inscriptions_by_address("t1alice").unwrap(), ["i1", "i2"]);
+        db.rollback_to(0).unwrap();
+        assert!(db.get_inscriptions_by_address("t1alice").unwrap().is_empty());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    fn dump(db: &Db) -> String {
+        let mut out = Vec::new();
+        db.export_jsonl(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    // The per-engine heights `index_block` records; `tip_time` is left out because a
+    // rollback drops it until the next block
+    fn engine_heights(writer: &BlockWriter, height: u64) {
+        for key in ["zrc20_height", "names_height", "zrc721_height"] {
+            writer.set_status(key, height).unwrap();
+        }
+    }
+
+    #[test]
+    fn rollback_restores_every_table_exactly() {
+        let (db, dir) = temp_db("undo-roundtrip");
+        let deploy = r#"{"p":"zrc-20","op":"deploy","tick":"trip","max":"1000","lim":"600","dec":"0"}"#;
+        let writer = db.block_writer(1, 0).unwrap();
+        index_transaction(&writer, &inscribe(&"d".repeat(64), "t1deployer", deploy), 1, 1, 0, false).unwrap();
+        engine_heights(&writer, 1);
+        writer.commit("block1").unwrap();
+        let before = dump(&db);
+
+        // A mint, a transfer inscription and a name registration touch most of the tables
+        let block2 = [
+            inscribe(&"a".repeat(64), "t1alice", r#"{"p":"zrc-20","op":"mint","tick":"trip","amt":"600"}"#),
+            inscribe(&"b".repeat(64), "t1alice", r#"{"p":"zrc-20","op":"transfer","tick":"trip","amt":"100"}"#),
+            inscribe_as(&"c".repeat(64), "t1alice", "text/plain", "alice.zec"),
+        ];
+        let apply_block2 = || {
+            let writer = db.block_writer(2, 0).unwrap();
+            for (tx_index, tx) in block2.iter().enumerate() {
+                index_transaction(&writer, tx, tx_index as u32 + 1, 2, 0, false).unwrap();
+            }
+            engine_heights(&writer, 2);
+            writer.commit("block2").unwrap();
+        };
+        apply_block2();
+        let after = dump(&db);
+        assert_ne!(after, before);
+        assert_eq!(db.get_balance("t1alice", "trip").unwrap().available.to_string(), "500");
+        assert!(db.get_name("alice.zec").unwrap().is_some());
+
+        assert_eq!(db.rollback_to(1).unwrap(), 1);
+        assert_eq!(dump(&db), before);
+
+        // Replaying the unwound block lands on the same state again
+        apply_block2();
+        assert_eq!(dump(&db), after);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn pruned_undo_history_raises_the_floor() {
+        let (db, dir) = temp_db("undo-prune");
+        for height in 1..=5 {
+            let writer = db.block_writer(height, 0).unwrap();
+            writer.prune_undo(2).unwrap();
+            writer.commit(&format!("block{}", height)).unwrap();
+        }
+        // Blocks 4 and 5 keep their records; 3 and below are gone
+        assert_eq!(db.get_status("undo_floor").unwrap(), Some(3));
+        let err = db.rollback_to(2).unwrap_err();
+        assert!(err.is::<UndoHistoryMissing>());
+        assert_eq!(db.rollback_to(3).unwrap(), 2);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
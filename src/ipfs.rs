@@ -0,0 +1,182 @@
+//! Optional IPFS resolver for ZRC-721 metadata and images.
+//!
+//! Collections only anchor a `meta` CID, so token metadata normally lives at
+//! `ipfs://<cid>/<id>.json` and explorers have to go to a gateway themselves.
+//! When `IPFS_GATEWAY` is set (e.g. `https://ipfs.io/ipfs/`), the API fetches
+//! those objects through it and keeps them in the `ipfs_cache` table for
+//! `IPFS_CACHE_TTL`. An expired entry is refetched, and served as-is if the
+//! gateway fails. Fetched content is never indexed or used by the protocol
+//! engines; it is whatever the gateway returned.
+
+use crate::db::{CachedObject, Db};
+use anyhow::Result;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// Objects larger than this are refused rather than buffered and cached
+const MAX_OBJECT_BYTES: usize = 8 * 1024 * 1024;
+const FETCH_TIMEOUT: Duration = Duration::from_secs(20);
+
+pub struct IpfsConfig {
+    gateway: String,
+    ttl: Duration,
+}
+
+impl IpfsConfig {
+    /// Read `IPFS_GATEWAY` and `IPFS_CACHE_TTL` (default `24h`). Returns `None`
+    /// when the resolver is not enabled.
+    pub fn from_env() -> Result<Option<Self>> {
+        let gateway = match std::env::var("IPFS_GATEWAY") {
+            Ok(url) if !url.is_empty() => url,
+            _ => return Ok(None),
+        };
+        reqwest::Url::parse(&gateway).map_err(|e| anyhow::anyhow!("Invalid IPFS_GATEWAY {}: {}", gateway, e))?;
+        let ttl = match std::env::var("IPFS_CACHE_TTL") {
+            Ok(raw) => crate::backup::parse_interval("IPFS_CACHE_TTL", &raw)?,
+            Err(_) => Duration::from_secs(86400),
+        };
+        Ok(Some(Self { gateway, ttl }))
+    }
+}
+
+struct Resolver {
+    client: reqwest::Client,
+    // Always ends in '/'
+    gateway: String,
+    ttl: Duration,
+}
+
+/// Handle shared by the API handlers; the default one resolves nothing.
+#[derive(Clone, Default)]
+pub struct IpfsResolver {
+    inner: Option<Arc<Resolver>>,
+}
+
+impl IpfsResolver {
+    pub fn new(config: IpfsConfig) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(FETCH_TIMEOUT)
+            .build()
+            .expect("Failed to build IPFS client");
+        let mut gateway = config.gateway;
+        if !gateway.ends_with('/') {
+            gateway.push('/');
+        }
+        tracing::info!("IPFS resolver enabled through {} (cache TTL {:?})", gateway, config.ttl);
+        Self {
+            inner: Some(Arc::new(Resolver { client, gateway, ttl: config.ttl })),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.inner.is_some()
+    }
+
+    /// The object at `uri` from the cache or the gateway. `None` when the resolver
+    /// is off or `uri` is not an IPFS reference.
+    pub async fn fetch(&self, db: &Db, uri: &str) -> Result<Option<CachedObject>> {
+        let (Some(resolver), Some(path)) = (&self.inner, ipfs_path(uri)) else {
+            return Ok(None);
+        };
+        let cached = {
+            let (db, path) = (db.clone(), path.clone());
+            tokio::task::spawn_blocking(move || db.get_ipfs_object(&path)).await??
+        };
+        if let Some(object) = &cached {
+            if now().saturating_sub(object.fetched_at) < resolver.ttl.as_secs() {
+                return Ok(cached);
+            }
+        }
+
+        let object = match resolver.download(&path).await {
+            Ok(object) => object,
+            Err(e) => {
+                return match cached {
+                    Some(stale) => {
+                        tracing::debug!("IPFS refetch of {} failed, serving cached copy: {}", path, e);
+                        Ok(Some(stale))
+                    }
+                    None => Err(e),
+                };
+            }
+        };
+        let db = db.clone();
+        tokio::task::spawn_blocking(move || {
+            if let Err(e) = db.put_ipfs_object(&path, &object) {
+                tracing::debug!("IPFS object not cached: {}", e);
+            }
+            object
+        })
+        .await
+        .map(Some)
+        .map_err(Into::into)
+    }
+
+    /// The JSON document at `uri`, see `fetch`.
+    pub async fn metadata(&self, db: &Db, uri: &str) -> Result<Option<serde_json::Value>> {
+        match self.fetch(db, uri).await? {
+            Some(object) => serde_json::from_slice(&object.bytes)
+                .map(Some)
+                .map_err(|e| anyhow::anyhow!("{} is not JSON: {}", uri, e)),
+            None => Ok(None),
+        }
+    }
+}
+
+impl Resolver {
+    async fn download(&self, path: &str) -> Result<CachedObject> {
+        let mut response = self
+            .client
+            .get(format!("{}{}", self.gateway, path))
+            .send()
+            .await?
+            .error_for_status()?;
+        if response.content_length().is_some_and(|len| len > MAX_OBJECT_BYTES as u64) {
+            return Err(anyhow::anyhow!("{} exceeds {} bytes", path, MAX_OBJECT_BYTES));
+        }
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("application/octet-stream")
+            .to_string();
+        let mut bytes = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            if bytes.len() + chunk.len() > MAX_OBJECT_BYTES {
+                return Err(anyhow::anyhow!("{} exceeds {} bytes", path, MAX_OBJECT_BYTES));
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+        Ok(CachedObject { fetched_at: now(), content_type, bytes })
+    }
+}
+
+/// `<cid>/<path>` for `ipfs://<cid>/<path>`, `/ipfs/<cid>/<path>` or a bare CID.
+/// Anything with characters outside a CID and plain path segments is refused, so
+/// a reference can never reach past the gateway's `/ipfs/` namespace.
+pub fn ipfs_path(uri: &str) -> Option<String> {
+    let uri = uri.trim();
+    let path = uri
+        .strip_prefix("ipfs://")
+        .or_else(|| uri.strip_prefix("/ipfs/"))
+        .unwrap_or(uri)
+        .trim_start_matches("ipfs/");
+    let mut segments = path.split('/');
+    let cid = segments.next()?;
+    if cid.len() < 10 || !cid.bytes().all(|b| b.is_ascii_alphanumeric()) {
+        return None;
+    }
+    for segment in segments {
+        let plain = segment
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.'));
+        if segment.is_empty() || segment == "." || segment == ".." || !plain {
+            return None;
+        }
+    }
+    Some(path.to_string())
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
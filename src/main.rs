@@ -1,8 +1,19 @@
 mod api;
+mod assets;
+mod auth;
+mod backup;
+mod blockfiles;
 mod db;
 mod indexer;
+mod ipfs;
+mod market;
 mod names;
+mod openapi;
+mod rarity;
 mod rpc;
+mod svg;
+mod thumbs;
+mod webhooks;
 mod zmq;
 mod zrc20;
 mod zrc721;
@@ -39,13 +50,52 @@ async fn main() -> Result<()> {
         .unwrap_or_else(|_| "8080".to_string())
         .parse::<u16>()?;
 
+    // Consensus rules must parse before anything indexes or validates under them
+    zrc20::load()?;
+    names::load()?;
+
+    // Operator subcommands run against the database and exit without starting services
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Some(command) = args.first() {
+        return run_command(command, &args[1..], &db_path);
+    }
+
+    // ROLE=api serves a read-only copy of the database without RPC credentials or an indexer
+    let role = env::var("ROLE").unwrap_or_else(|_| "all".to_string());
+    if role.eq_ignore_ascii_case("api") {
+        let db = db::Db::open_read_only(&db_path)?;
+        tracing::info!("Starting read-only API replica on port {}", api_port);
+        api::start_api(db, None, None, api_port).await;
+        return Ok(());
+    }
+
+    // Compact on startup only: the live handle cannot be swapped once the database is open
+    if let Some(hours) = env::var("COMPACT_ON_STARTUP_AFTER_HOURS").ok().and_then(|v| v.parse::<u64>().ok()) {
+        let max_age = std::time::Duration::from_secs(hours.saturating_mul(3600));
+        if let Err(e) = db::Db::compact_on_startup(&db_path, max_age) {
+            tracing::error!("Startup compaction failed: {}", e);
+        }
+    }
+
     // Construct core services
     let reindex = env::var("RE_INDEX")
         .map(|v| matches!(v.as_str(), "1" | "true" | "TRUE" | "yes" | "YES"))
         .unwrap_or(false);
     let db = db::Db::new(&db_path, reindex)?;
-    let rpc = rpc::ZcashRpcClient::new();
-    let indexer = indexer::Indexer::new(rpc, db.clone());
+    let rpc = rpc::ZcashRpcClient::new()?;
+    rpc.spawn_health_checks();
+    let webhooks = webhooks::spawn(db.clone());
+    let indexer = indexer::Indexer::new(rpc.clone(), db.clone(), webhooks.clone());
+
+    // Rotating hot backups when BACKUP_DIR is set
+    if let Some(config) = backup::BackupConfig::from_env()? {
+        backup::spawn(db.clone(), config);
+    }
+
+    // ZRC-721 rarity scores, refreshed as collections mint
+    if let Some(config) = rarity::RarityConfig::from_env()? {
+        rarity::spawn(db.clone(), config);
+    }
 
     // Indexer runs alongside the HTTP server with automatic retry
     let indexer_handle = tokio::spawn(async move {
@@ -58,6 +108,10 @@ async fn main() -> Result<()> {
                     tracing::warn!("Indexer exited normally (unexpected)");
                     break;
                 }
+                Err(e) if e.is::<db::UndoHistoryMissing>() => {
+                    tracing::error!("Indexer stopped: {}", e);
+                    break;
+                }
                 Err(e) => {
                     tracing::error!("Indexer failed: {} - retrying in {:?}", e, retry_delay);
                     tokio::time::sleep(retry_delay).await;
@@ -71,10 +125,127 @@ async fn main() -> Result<()> {
 
     // Start the public API
     tracing::info!("Starting API on port {}", api_port);
-    api::start_api(db, api_port).await;
+    api::start_api(db, Some(rpc), Some(webhooks), api_port).await;
 
     // Keep process alive even if API finishes unexpectedly
     let _ = indexer_handle.await;
 
     Ok(())
 }
+
+fn run_command(command: &str, args: &[String], db_path: &str) -> Result<()> {
+    match command {
+        // zord rollback --to-height <N>
+        "rollback" => {
+            let height = flag_value(args, "--to-height")
+                .ok_or_else(|| anyhow::anyhow!("usage: zord rollback --to-height <height>"))?
+                .parse::<u64>()?;
+            let db = db::Db::new(db_path, false)?;
+            let unwound = db.rollback_to(height)?;
+            tracing::info!("Rolled back {} blocks; tip is now {}", unwound, height);
+            Ok(())
+        }
+        // zord snapshot --out <path>
+        "snapshot" => {
+            let out = flag_value(args, "--out")
+                .ok_or_else(|| anyhow::anyhow!("usage: zord snapshot --out <path>"))?;
+            let db = open_offline(db_path)?;
+            db.export_snapshot(out)?;
+            Ok(())
+        }
+        // zord export --format jsonl|parquet --out <path>
+        "export" => {
+            let format = flag_value(args, "--format").unwrap_or("jsonl");
+            let out = flag_value(args, "--out")
+                .ok_or_else(|| anyhow::anyhow!("usage: zord export --format jsonl|parquet --out <path>"))?;
+            if !matches!(format, "jsonl" | "parquet") {
+                return Err(anyhow::anyhow!("Unknown export format: {}", format));
+            }
+            let db = open_offline(db_path)?;
+            let file = std::fs::File::create(out)?;
+            let rows = match format {
+                "parquet" => db.export_parquet(file)?,
+                _ => db.export_jsonl(std::io::BufWriter::new(file))?,
+            };
+            tracing::info!("Exported {} rows to {}", rows, out);
+            Ok(())
+        }
+        // zord import --in <path>; Parquet dumps are recognized by their magic bytes
+        "import" => {
+            let input = flag_value(args, "--in")
+                .ok_or_else(|| anyhow::anyhow!("usage: zord import --in <path>"))?;
+            let db = db::Db::new(db_path, false)?;
+            let mut file = std::fs::File::open(input)?;
+            let mut magic = [0u8; 4];
+            let is_parquet = std::io::Read::read_exact(&mut file, &mut magic).is_ok() && &magic == b"PAR1";
+            std::io::Seek::rewind(&mut file)?;
+            if is_parquet {
+                db.import_parquet(file)?;
+            } else {
+                db.import_jsonl(std::io::BufReader::new(file))?;
+            }
+            Ok(())
+        }
+        // zord verify; prints a JSON report and fails if any check does not hold
+        "verify" => {
+            let db = db::Db::open_read_only(db_path)?;
+            let report = db.verify()?;
+            println!("{}", serde_json::to_string_pretty(&report)?);
+            if !report.is_consistent() {
+                return Err(anyhow::anyhow!("Verification found {} issues", report.issues.len()));
+            }
+            Ok(())
+        }
+        // zord index-content; builds full-text postings for inscriptions indexed without CONTENT_INDEX
+        "index-content" => {
+            let db = db::Db::new(db_path, false)?;
+            db.backfill_content_terms()?;
+            Ok(())
+        }
+        // zord zrc20 rebuild --tick <tick>
+        "zrc20" => {
+            let usage = || anyhow::anyhow!("usage: zord zrc20 rebuild --tick <tick>");
+            if args.first().map(String::as_str) != Some("rebuild") {
+                return Err(usage());
+            }
+            let tick = flag_value(args, "--tick").ok_or_else(usage)?;
+            let db = db::Db::new(db_path, false)?;
+            let report = zrc20::rebuild::rebuild(&db, tick)?;
+            println!("{}", serde_json::to_string_pretty(&report)?);
+            Ok(())
+        }
+        // zord compact
+        "compact" => {
+            db::Db::compact(db_path)?;
+            Ok(())
+        }
+        other => Err(anyhow::anyhow!("Unknown command: {}", other)),
+    }
+}
+
+// `snapshot` and `export` need the file to themselves: redb locks it for the running
+// service, so say how to copy a live database instead of failing with the lock error
+fn open_offline(db_path: &str) -> Result<db::Db> {
+    db::Db::new(db_path, false).map_err(|e| {
+        let locked = matches!(e.downcast_ref(), Some(redb::DatabaseError::DatabaseAlreadyOpen));
+        if locked {
+            anyhow::anyhow!(
+                "{} is open by a running zord; stop it first, or take a live copy with POST /api/v1/admin/snapshot and run this command against that file",
+                db_path
+            )
+        } else {
+            e
+        }
+    })
+}
+
+fn flag_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    let prefix = format!("{}=", flag);
+    args.iter().enumerate().find_map(|(i, arg)| {
+        if arg == flag {
+            args.get(i + 1).map(|v| v.as_str())
+        } else {
+            arg.strip_prefix(&prefix)
+        }
+    })
+}
@@ -0,0 +1,152 @@
+//! Optional market data (prices, volumes) for ZRC-20 tokens.
+//!
+//! When `MARKET_DATA_URL` is set, a background task fetches that JSON document
+//! every `MARKET_DATA_INTERVAL` and keeps the latest quote per ticker in memory.
+//! Nothing here touches the database or the protocol engines: quotes are whatever
+//! the source reports and are served next to, never mixed into, indexed fields.
+//!
+//! The document is either an object keyed by ticker or an array of objects with a
+//! `tick` (or `ticker`) field; `MARKET_DATA_POINTER` (a JSON pointer such as
+//! `/data`) selects it from inside a larger response. Scalar fields of each entry
+//! are passed through as-is.
+
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// Quotes older than this many intervals are withheld rather than served stale
+const STALE_AFTER_INTERVALS: u32 = 3;
+
+pub struct MarketConfig {
+    url: String,
+    pointer: String,
+    interval: Duration,
+}
+
+impl MarketConfig {
+    /// Read `MARKET_DATA_URL`, `MARKET_DATA_POINTER` and `MARKET_DATA_INTERVAL`
+    /// (default `5m`). Returns `None` when market data is not enabled.
+    pub fn from_env() -> Result<Option<Self>> {
+        let url = match std::env::var("MARKET_DATA_URL") {
+            Ok(url) if !url.is_empty() => url,
+            _ => return Ok(None),
+        };
+        let pointer = std::env::var("MARKET_DATA_POINTER").unwrap_or_default();
+        let interval = match std::env::var("MARKET_DATA_INTERVAL") {
+            Ok(raw) => crate::backup::parse_interval("MARKET_DATA_INTERVAL", &raw)?,
+            Err(_) => Duration::from_secs(300),
+        };
+        Ok(Some(Self { url, pointer, interval }))
+    }
+}
+
+struct Quotes {
+    by_tick: HashMap<String, serde_json::Map<String, serde_json::Value>>,
+    fetched_at: u64,
+}
+
+/// Latest quotes, shared between the fetch loop and the API.
+#[derive(Clone, Default)]
+pub struct MarketData {
+    quotes: Arc<RwLock<Option<Quotes>>>,
+    source: Arc<str>,
+    stale_after: Duration,
+}
+
+impl MarketData {
+    /// `{ source, updated_at, ...fields }` for `tick`, or `None` when the source
+    /// has no fresh quote for it (or market data is off).
+    pub fn quote(&self, tick: &str) -> Option<serde_json::Value> {
+        let quotes = self.quotes.read().unwrap_or_else(|e| e.into_inner());
+        let quotes = quotes.as_ref()?;
+        if now().saturating_sub(quotes.fetched_at) > self.stale_after.as_secs() {
+            return None;
+        }
+        let mut quote = quotes.by_tick.get(tick)?.clone();
+        quote.insert("source".to_string(), serde_json::Value::String(self.source.to_string()));
+        quote.insert("updated_at".to_string(), serde_json::json!(quotes.fetched_at));
+        Some(serde_json::Value::Object(quote))
+    }
+}
+
+/// Start the fetch loop; the returned handle serves whatever it last fetched.
+pub fn spawn(config: MarketConfig) -> MarketData {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(15))
+        .build()
+        .expect("Failed to build market data client");
+    let data = MarketData {
+        quotes: Arc::new(RwLock::new(None)),
+        source: source_name(&config.url).into(),
+        stale_after: config.interval * STALE_AFTER_INTERVALS,
+    };
+    let shared = data.clone();
+    tokio::spawn(async move {
+        tracing::info!("Market data enabled: {} every {:?}", shared.source, config.interval);
+        let mut ticker = tokio::time::interval(config.interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            match fetch(&client, &config).await {
+                Ok(by_tick) => {
+                    tracing::debug!("Fetched market data for {} tokens", by_tick.len());
+                    *shared.quotes.write().unwrap_or_else(|e| e.into_inner()) =
+                        Some(Quotes { by_tick, fetched_at: now() });
+                }
+                Err(e) => tracing::warn!("Market data fetch failed: {}", e),
+            }
+        }
+    });
+    data
+}
+
+async fn fetch(
+    client: &reqwest::Client,
+    config: &MarketConfig,
+) -> Result<HashMap<String, serde_json::Map<String, serde_json::Value>>> {
+    let body: serde_json::Value = client.get(&config.url).send().await?.error_for_status()?.json().await?;
+    let document = if config.pointer.is_empty() {
+        &body
+    } else {
+        body.pointer(&config.pointer)
+            .ok_or_else(|| anyhow::anyhow!("MARKET_DATA_POINTER {} not found in response", config.pointer))?
+    };
+    let entries: Vec<(String, &serde_json::Value)> = match document {
+        serde_json::Value::Object(map) => map.iter().map(|(tick, entry)| (tick.clone(), entry)).collect(),
+        serde_json::Value::Array(items) => items
+            .iter()
+            .filter_map(|entry| {
+                let tick = entry.get("tick").or_else(|| entry.get("ticker"))?.as_str()?;
+                Some((tick.to_string(), entry))
+            })
+            .collect(),
+        _ => return Err(anyhow::anyhow!("market data is neither an object nor an array")),
+    };
+    Ok(entries
+        .into_iter()
+        .filter_map(|(tick, entry)| {
+            let fields: serde_json::Map<String, serde_json::Value> = entry
+                .as_object()?
+                .iter()
+                .filter(|(key, value)| {
+                    !matches!(key.as_str(), "tick" | "ticker" | "source" | "updated_at")
+                        && (value.is_number() || value.is_string() || value.is_boolean())
+                })
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect();
+            Some((tick.to_lowercase(), fields))
+        })
+        .collect())
+}
+
+// Host of the source, so clients can attribute the numbers without seeing the full URL
+fn source_name(url: &str) -> String {
+    let rest = url.split_once("://").map(|(_, rest)| rest).unwrap_or(url);
+    let host = rest.split(['/', '?', '#']).next().unwrap_or(rest);
+    host.rsplit_once('@').map(|(_, host)| host).unwrap_or(host).to_string()
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
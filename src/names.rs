@@ -1,37 +1,215 @@
-use crate::db::Db;
+use crate::db::BlockWriter;
 use anyhow::Result;
+use icu_normalizer::{ComposingNormalizerBorrowed, DecomposingNormalizerBorrowed};
+use icu_properties::props::{
+    DefaultIgnorableCodePoint, ExtendedPictographic, GeneralCategory, GeneralCategoryGroup, RegionalIndicator, Script,
+};
+use icu_properties::{CodePointMapData, CodePointSetData};
+use serde::Deserialize;
+use std::collections::HashSet;
 
-pub struct NamesEngine {
-    db: Db,
+pub mod commit;
+pub mod proof;
+pub mod reserved;
+
+/// JSON operation on a name: `set` records, or `commit` and `reveal` a registration.
+#[derive(Debug, Deserialize)]
+struct NameOperation {
+    p: String,
+    op: String,
+    #[serde(default)]
+    name: Option<String>,
+    // Record key -> new value, or null to delete it
+    #[serde(default)]
+    records: Option<serde_json::Map<String, serde_json::Value>>,
+    // Commitment hash of a `commit`, salt of a `reveal`
+    #[serde(default)]
+    hash: Option<String>,
+    #[serde(default)]
+    salt: Option<String>,
 }
 
-impl NamesEngine {
-    pub fn new(db: Db) -> Self {
-        Self { db }
-    }
+/// Most records one name may hold.
+const MAX_RECORDS: usize = 32;
+/// Longest record key, in bytes.
+const MAX_RECORD_KEY: usize = 32;
+/// Longest record value, in bytes.
+const MAX_RECORD_VALUE: usize = 512;
 
-    /// Process a plain text name inscription
+pub struct NamesEngine;
+
+impl NamesEngine {
+    /// Process a plain text name inscription revealed at `txid:vout`
     /// Content should be just the name itself: "satoshi.zec" or "🔥fire.zcash"
+    #[allow(clippy::too_many_arguments)]
     pub fn process(
         &self,
+        db: &BlockWriter,
         inscription_id: &str,
         owner: &str,
         content: &str,
         content_type: &str,
+        txid: &str,
+        vout: u32,
+        height: u64,
     ) -> Result<()> {
         // Ignore anything other than plain text payloads
         if content_type != "text/plain" {
-            return Ok(());
+            return Err(anyhow::anyhow!("Name inscriptions must be text/plain"));
         }
 
         let name = content.trim();
+        self.validate_name(name)?;
+        if commit::active_at(height).is_some() {
+            return Err(anyhow::anyhow!("Names register by commit and reveal from this height"));
+        }
 
         // Accept first writer only
-        if self.validate_name(name).is_ok() {
-            self.handle_registration(name, inscription_id, owner)?;
+        self.handle_registration(db, name, inscription_id, owner, txid, vout, height)
+    }
+
+    /// Process a JSON `zns` operation inscribed by `sender` at `txid:vout`: `set`
+    /// updates the records of a name `sender` currently owns, and `commit`/`reveal`
+    /// register names once commit-reveal is active (see `commit`).
+    #[allow(clippy::too_many_arguments)]
+    pub fn process_json(
+        &self,
+        db: &BlockWriter,
+        inscription_id: &str,
+        sender: &str,
+        content: &str,
+        txid: &str,
+        vout: u32,
+        height: u64,
+    ) -> Result<()> {
+        let op: NameOperation = serde_json::from_str(content)?;
+        if !op.p.eq_ignore_ascii_case("zns") {
+            return Err(anyhow::anyhow!("Not a ZNS operation"));
         }
+        match op.op.as_str() {
+            "set" => self.handle_set(db, &op, inscription_id, sender, txid, height),
+            "commit" => self.handle_commit(db, &op, inscription_id, sender, height),
+            "reveal" => self.handle_reveal(db, &op, inscription_id, sender, txid, vout, height),
+            other => Err(anyhow::anyhow!("Unknown ZNS op {}", other)),
+        }
+    }
 
-        Ok(())
+    fn handle_commit(
+        &self,
+        db: &BlockWriter,
+        op: &NameOperation,
+        inscription_id: &str,
+        sender: &str,
+        height: u64,
+    ) -> Result<()> {
+        if commit::active_at(height).is_none() {
+            return Err(anyhow::anyhow!("Commit-reveal registration is not active"));
+        }
+        let hash = op.hash.as_deref().unwrap_or("").to_ascii_lowercase();
+        if hash.len() != 64 || !hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(anyhow::anyhow!("Commitment hash must be 64 hex characters"));
+        }
+        if sender == "shielded" {
+            return Err(anyhow::anyhow!("Commitments need a transparent sender"));
+        }
+        // The first commitment of a hash stands
+        if db.get_name_commitment(&hash)?.is_some() {
+            return Err(anyhow::anyhow!("Commitment already exists"));
+        }
+        let entry = serde_json::json!({
+            "address": sender,
+            "height": height,
+            "inscription_id": inscription_id,
+        });
+        db.put_name_commitment(&hash, &entry.to_string())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn handle_reveal(
+        &self,
+        db: &BlockWriter,
+        op: &NameOperation,
+        inscription_id: &str,
+        sender: &str,
+        txid: &str,
+        vout: u32,
+        height: u64,
+    ) -> Result<()> {
+        let Some(config) = commit::active_at(height) else {
+            return Err(anyhow::anyhow!("Commit-reveal registration is not active"));
+        };
+        let (Some(name), Some(salt)) = (op.name.as_deref(), op.salt.as_deref()) else {
+            return Err(anyhow::anyhow!("Reveal needs a name and a salt"));
+        };
+        if salt.is_empty() || salt.len() > commit::MAX_SALT {
+            return Err(anyhow::anyhow!("Salt must be 1 to {} bytes", commit::MAX_SALT));
+        }
+        let name = name.trim();
+        self.validate_name(name)?;
+
+        let hash = commit::commitment(&normalize_name(name), sender, salt);
+        let committed_at = match db.get_name_commitment(&hash)? {
+            Some(raw) => serde_json::from_str::<serde_json::Value>(&raw)?["height"].as_u64().unwrap_or(u64::MAX),
+            None => return Err(anyhow::anyhow!("No commitment for this name, address and salt")),
+        };
+        if height < committed_at.saturating_add(config.delay) {
+            return Err(anyhow::anyhow!(
+                "Commitment from block {} can be revealed from block {}",
+                committed_at,
+                committed_at.saturating_add(config.delay)
+            ));
+        }
+        self.handle_registration(db, name, inscription_id, sender, txid, vout, height)?;
+        db.delete_name_commitment(&hash)
+    }
+
+    fn handle_set(
+        &self,
+        db: &BlockWriter,
+        op: &NameOperation,
+        inscription_id: &str,
+        sender: &str,
+        txid: &str,
+        height: u64,
+    ) -> Result<()> {
+        let Some(name) = op.name.as_deref() else {
+            return Err(anyhow::anyhow!("Missing name"));
+        };
+        let name_lower = normalize_name(name);
+        let mut data: serde_json::Value = match db.get_name(&name_lower)? {
+            Some(raw) => serde_json::from_str(&raw)?,
+            None => return Err(anyhow::anyhow!("Name not registered")),
+        };
+        if data["owner"].as_str() != Some(sender) {
+            return Err(anyhow::anyhow!("Only the owner of {} may set its records", name_lower));
+        }
+        let updates = match &op.records {
+            Some(records) if !records.is_empty() => records,
+            _ => return Err(anyhow::anyhow!("Missing records")),
+        };
+
+        let mut records = data["records"].as_object().cloned().unwrap_or_default();
+        let mut changes = serde_json::Map::new();
+        for (key, value) in updates {
+            validate_record(db, key, value)?;
+            let previous = records.get(key).cloned().unwrap_or(serde_json::Value::Null);
+            if previous == *value {
+                continue;
+            }
+            changes.insert(key.clone(), serde_json::json!({ "from": previous, "to": value }));
+            match value {
+                serde_json::Value::Null => records.remove(key),
+                value => records.insert(key.clone(), value.clone()),
+            };
+        }
+        if changes.is_empty() {
+            return Err(anyhow::anyhow!("Records unchanged"));
+        }
+        if records.len() > MAX_RECORDS {
+            return Err(anyhow::anyhow!("At most {} records per name", MAX_RECORDS));
+        }
+        data["records"] = serde_json::Value::Object(records);
+        db.set_name_records(&name_lower, &data, &serde_json::Value::Object(changes), inscription_id, txid, height)
     }
 
     fn validate_name(&self, name: &str) -> Result<()> {
@@ -48,16 +226,22 @@ impl NamesEngine {
         }
 
         // Strip the extension for validation
-        let base_name = if name.ends_with(".zcash") {
-            &name[..name.len() - 6]
-        } else {
-            &name[..name.len() - 4]
-        };
+        let base_name = name
+            .strip_suffix(".zcash")
+            .or_else(|| name.strip_suffix(".zec"))
+            .unwrap_or(name);
 
-        // Disallow empty labels (e.g. ".zec")
+        // Disallow empty labels (e.g. ".zec" or "pay..zec")
         if base_name.is_empty() {
             return Err(anyhow::anyhow!("Name cannot be empty"));
         }
+        if base_name.split('.').any(str::is_empty) {
+            return Err(anyhow::anyhow!("Name labels cannot be empty"));
+        }
+        // Subdomains are indexed under "<parent>:", so keep that prefix unambiguous
+        if base_name.contains('.') && name.contains(':') {
+            return Err(anyhow::anyhow!("Subdomains cannot contain ':'"));
+        }
 
         // Simple length guard
         if name.len() > 253 {
@@ -67,26 +251,236 @@ impl NamesEngine {
         Ok(())
     }
 
-    fn handle_registration(&self, name: &str, inscription_id: &str, owner: &str) -> Result<()> {
-        // Store lower-case key, but keep caller formatting for display
-        let name_lower = name.to_lowercase();
+    #[allow(clippy::too_many_arguments)]
+    fn handle_registration(
+        &self,
+        db: &BlockWriter,
+        name: &str,
+        inscription_id: &str,
+        owner: &str,
+        txid: &str,
+        vout: u32,
+        height: u64,
+    ) -> Result<()> {
+        // Store the normalized key, but keep caller formatting for display
+        let name_lower = normalize_name(name);
 
         // First registration wins
-        if self.db.get_name(&name_lower)?.is_some() {
+        if db.get_name(&name_lower)?.is_some() {
             return Err(anyhow::anyhow!("Name already registered"));
         }
 
-        let name_data = serde_json::json!({
+        let mut name_data = serde_json::json!({
             "name": name,
             "name_lower": name_lower,
             "owner": owner,
             "inscription_id": inscription_id,
         });
 
-        self.db.register_name(&name_lower, &name_data.to_string())?;
+        // Reserved names only go to the address an operator granted them to
+        if reserved::reserved().is_reserved(&name_lower) {
+            let grantee = match db.get_name_grant(&name_lower)? {
+                Some(raw) => serde_json::from_str::<serde_json::Value>(&raw)?["address"].clone(),
+                None => serde_json::Value::Null,
+            };
+            if grantee.as_str() != Some(owner) {
+                return Err(anyhow::anyhow!("Name {} is reserved", name_lower));
+            }
+        }
+
+        // Subdomains can only be created by the current owner of their parent
+        if let Some(parent) = parent_name(&name_lower) {
+            let parent_owner = match db.get_name(parent)? {
+                Some(raw) => serde_json::from_str::<serde_json::Value>(&raw)?["owner"].clone(),
+                None => return Err(anyhow::anyhow!("Parent name {} not registered", parent)),
+            };
+            if parent_owner.as_str() != Some(owner) {
+                return Err(anyhow::anyhow!("Only the owner of {} may register its subdomains", parent));
+            }
+            name_data["parent"] = parent.into();
+        }
+
+        // Flag look-alikes of an earlier name (Cyrillic "аlice.zec" for "alice.zec");
+        // the registration stands, but clients can warn before trusting it
+        if let Some(established) = db.get_confusable_name(&name_skeleton(&name_lower))? {
+            tracing::warn!("Name {} is confusable with {}", name_lower, established);
+            name_data["confusable_with"] = established.into();
+        }
+        name_data["attributes"] = name_attributes(&name_lower);
+
+        db.register_name(&name_lower, &name_data.to_string(), txid, vout, height)?;
 
         tracing::info!("Registered name: {} -> {}", name, owner);
 
         Ok(())
     }
 }
+
+/// Read the ZNS settings once at startup: reserved names and commit-reveal.
+pub fn load() -> Result<()> {
+    reserved::load()?;
+    commit::load()
+}
+
+/// Canonical key of a name: lowercased, then Unicode NFC, so composed and decomposed
+/// spellings of "café.zec" are the same name.
+pub fn normalize_name(raw: &str) -> String {
+    let lower = raw.trim().to_lowercase();
+    ComposingNormalizerBorrowed::new_nfc().normalize(&lower).into_owned()
+}
+
+/// Confusable skeleton of a normalized name, in the spirit of UTS #39: compatibility
+/// decomposition, marks and invisible characters dropped, and common look-alikes
+/// folded to Latin. Names sharing a skeleton read the same to a human.
+pub fn name_skeleton(name: &str) -> String {
+    let categories = CodePointMapData::<GeneralCategory>::new();
+    let ignorable = CodePointSetData::new::<DefaultIgnorableCodePoint>();
+    let folded: String = DecomposingNormalizerBorrowed::new_nfkd()
+        .normalize(name)
+        .chars()
+        .filter(|c| !GeneralCategoryGroup::Mark.contains(categories.get(*c)) && !ignorable.contains(*c))
+        .map(fold_confusable)
+        .collect();
+    folded.replace("rn", "m").replace("vv", "w")
+}
+
+fn fold_confusable(c: char) -> char {
+    match c {
+        // Cyrillic
+        'а' => 'a',
+        'с' => 'c',
+        'ԁ' => 'd',
+        'е' => 'e',
+        'һ' => 'h',
+        'і' => 'i',
+        'ј' => 'j',
+        'о' => 'o',
+        'р' => 'p',
+        'ԛ' => 'q',
+        'ѕ' => 's',
+        'у' => 'y',
+        'ԝ' => 'w',
+        'х' => 'x',
+        // Greek
+        'α' => 'a',
+        'ι' => 'i',
+        'κ' => 'k',
+        'ν' => 'v',
+        'ο' => 'o',
+        'ρ' => 'p',
+        'υ' => 'u',
+        'χ' => 'x',
+        // Latin variants and digits
+        'ɑ' => 'a',
+        'ɡ' => 'g',
+        'ı' => 'i',
+        'ȷ' => 'j',
+        '0' => 'o',
+        '1' => 'l',
+        other => other,
+    }
+}
+
+/// Shape of a normalized name's leftmost label, stored at registration so feeds can
+/// offer categories such as "3-digit" or "emoji" without reparsing names.
+pub fn name_attributes(name: &str) -> serde_json::Value {
+    let label = name.split('.').next().unwrap_or(name);
+    let clusters = graphemes(label);
+    let script_of = CodePointMapData::<Script>::new();
+    let scripts: HashSet<Script> = label
+        .chars()
+        .map(|c| script_of.get(c))
+        .filter(|s| *s != Script::Common && *s != Script::Inherited)
+        .collect();
+    serde_json::json!({
+        "graphemes": clusters.len(),
+        "ascii_only": label.is_ascii(),
+        "digits_only": !label.is_empty() && label.bytes().all(|b| b.is_ascii_digit()),
+        "emoji_only": !clusters.is_empty() && clusters.iter().all(|g| is_emoji_cluster(g)),
+        "mixed_script": scripts.len() > 1,
+        // ACE form such as "xn--ls8h.zec" for wallets that only display ASCII
+        "punycode": idna::domain_to_ascii(name).ok().filter(|ace| ace != name),
+    })
+}
+
+// User-perceived characters, approximating extended grapheme clusters: marks,
+// variation selectors, skin tones, tags and ZWJ sequences join the preceding
+// character, and regional indicators pair into flags.
+fn graphemes(text: &str) -> Vec<&str> {
+    let categories = CodePointMapData::<GeneralCategory>::new();
+    let regional = CodePointSetData::new::<RegionalIndicator>();
+    let mut clusters: Vec<&str> = Vec::new();
+    let mut start = 0;
+    let mut prev: Option<char> = None;
+    let mut open_flag = false;
+    for (i, c) in text.char_indices() {
+        let extends = GeneralCategoryGroup::Mark.contains(categories.get(c))
+            || matches!(c, '\u{200d}' | '\u{fe00}'..='\u{fe0f}' | '\u{1f3fb}'..='\u{1f3ff}' | '\u{e0020}'..='\u{e007f}')
+            || prev == Some('\u{200d}')
+            || (open_flag && regional.contains(c));
+        if prev.is_some() && !extends {
+            clusters.push(&text[start..i]);
+            start = i;
+        }
+        open_flag = regional.contains(c) && !(open_flag && extends);
+        prev = Some(c);
+    }
+    if prev.is_some() {
+        clusters.push(&text[start..]);
+    }
+    clusters
+}
+
+fn is_emoji_cluster(cluster: &str) -> bool {
+    let Some(first) = cluster.chars().next() else {
+        return false;
+    };
+    CodePointSetData::new::<ExtendedPictographic>().contains(first)
+        || CodePointSetData::new::<RegionalIndicator>().contains(first)
+        // Keycaps such as 1️⃣
+        || cluster.contains('\u{20e3}')
+}
+
+/// Parent of a subdomain: "alice.zec" for "pay.alice.zec". Top-level names have none.
+pub fn parent_name(name: &str) -> Option<&str> {
+    let (_, rest) = name.split_once('.')?;
+    rest.contains('.').then_some(rest)
+}
+
+// Keys are lowercase `[a-z0-9_.-]`; values are strings, or null to delete. The
+// well-known keys are checked so resolvers can use them without re-validating.
+fn validate_record(db: &BlockWriter, key: &str, value: &serde_json::Value) -> Result<()> {
+    let plain = key
+        .bytes()
+        .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || matches!(b, b'_' | b'.' | b'-'));
+    if key.is_empty() || key.len() > MAX_RECORD_KEY || !plain {
+        return Err(anyhow::anyhow!("Invalid record key {:?}", key));
+    }
+    let value = match value {
+        serde_json::Value::Null => return Ok(()),
+        serde_json::Value::String(s) if !s.is_empty() && s.len() <= MAX_RECORD_VALUE => s.as_str(),
+        _ => {
+            return Err(anyhow::anyhow!(
+                "Record {} must be a string of 1 to {} bytes, or null",
+                key,
+                MAX_RECORD_VALUE
+            ))
+        }
+    };
+    let alphanumeric = value.bytes().all(|b| b.is_ascii_alphanumeric());
+    let valid = match key {
+        "t_addr" => value.starts_with('t') && alphanumeric,
+        // Sapling, Sprout or unified addresses
+        "z_addr" => (value.starts_with('z') || value.starts_with("u1")) && alphanumeric,
+        "avatar" => db.inscription_exists(value)?,
+        "url" => {
+            ["https://", "http://", "ipfs://"].iter().any(|scheme| value.starts_with(scheme))
+                && !value.chars().any(char::is_whitespace)
+        }
+        _ => true,
+    };
+    if !valid {
+        return Err(anyhow::anyhow!("Invalid {} record {:?}", key, value));
+    }
+    Ok(())
+}
@@ -0,0 +1,83 @@
+//! Commit-reveal name registration.
+//!
+//! A plain text registration is visible in the mempool before it confirms, so a
+//! miner or mempool watcher can inscribe the same name first. From
+//! `ZNS_COMMIT_REVEAL_HEIGHT` on, names only register in two steps:
+//!
+//! 1. `{"p":"zns","op":"commit","hash":"<sha256 hex>"}`, where the hash covers
+//!    `"<name>:<address>:<salt>"` with the lowercased NFC name, the address that
+//!    will reveal and a secret salt. It says nothing about the name.
+//! 2. At least `ZNS_REVEAL_DELAY` blocks (default 6) later, the same address
+//!    inscribes `{"p":"zns","op":"reveal","name":"alice.zec","salt":"<salt>"}`.
+//!
+//! Copying a reveal is useless without a matured commitment of one's own, and the
+//! address in the hash stops a watcher from re-using someone else's. Like
+//! `ZRC20_PARTIAL_MINT_HEIGHT` this is consensus: every indexer needs the same
+//! height and delay, and changing either needs a reindex.
+
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+use std::sync::OnceLock;
+
+/// Longest accepted salt, in bytes.
+pub const MAX_SALT: usize = 128;
+
+#[derive(Clone, Copy, Debug)]
+pub struct CommitReveal {
+    /// First height at which plain registrations stop and reveals are accepted.
+    pub height: u64,
+    /// Blocks a commitment must age before its reveal counts.
+    pub delay: u64,
+}
+
+impl CommitReveal {
+    pub fn from_env() -> Result<Option<Self>> {
+        let height = match std::env::var("ZNS_COMMIT_REVEAL_HEIGHT") {
+            Ok(raw) if !raw.trim().is_empty() => raw.trim().parse::<u64>().map_err(|_| {
+                anyhow::anyhow!("ZNS_COMMIT_REVEAL_HEIGHT must be a block height, got {:?}", raw)
+            })?,
+            _ => return Ok(None),
+        };
+        let delay = match std::env::var("ZNS_REVEAL_DELAY") {
+            Ok(raw) if !raw.trim().is_empty() => raw
+                .trim()
+                .parse::<u64>()
+                .ok()
+                .filter(|d| *d > 0)
+                .ok_or_else(|| anyhow::anyhow!("ZNS_REVEAL_DELAY must be a positive block count, got {:?}", raw))?,
+            _ => 6,
+        };
+        Ok(Some(Self { height, delay }))
+    }
+}
+
+static COMMIT_REVEAL: OnceLock<Option<CommitReveal>> = OnceLock::new();
+
+/// Read the commit-reveal settings once at startup, failing on a malformed value
+/// rather than indexing under the wrong rules.
+pub fn load() -> Result<()> {
+    let config = CommitReveal::from_env()?;
+    if let Some(config) = config {
+        tracing::info!(
+            "ZNS commit-reveal registration from block {} ({} block delay)",
+            config.height,
+            config.delay
+        );
+    }
+    let _ = COMMIT_REVEAL.set(config);
+    Ok(())
+}
+
+/// The commit-reveal rules if they are in force at `height`.
+pub fn active_at(height: u64) -> Option<CommitReveal> {
+    COMMIT_REVEAL
+        .get()
+        .copied()
+        .flatten()
+        .filter(|config| height >= config.height)
+}
+
+/// Commitment hash for registering the normalized `name` to `address`.
+pub fn commitment(name: &str, address: &str, salt: &str) -> String {
+    hex::encode(Sha256::digest(format!("{}:{}:{}", name, address, salt)))
+}
@@ -0,0 +1,62 @@
+//! Inclusion proofs for name registrations.
+//!
+//! When a block registers a name, the indexer stores the Merkle branch of the
+//! registering transaction within that block, so `/api/v1/name/:name/proof` can
+//! hand a third party everything needed to check the claim against their own
+//! full node without trusting zord:
+//!
+//! 1. fetch the inscription from `txid` and confirm it carries the name,
+//! 2. fold the branch into the txid (below) and compare the result with the
+//!    `merkleroot` of `block_hash` as reported by `getblockheader`.
+//!
+//! The tree is the block header's transaction tree: hashes are double SHA-256
+//! over the txids in internal (reversed) byte order, and an odd level repeats its
+//! last hash. Folding walks the branch bottom-up with the transaction's index in
+//! the block: when the low bit is 0 the running hash goes on the left,
+//! `sha256d(running || sibling)`, otherwise on the right; then the index shifts
+//! right by one. Hashes in the API use the usual display (reversed) hex.
+
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+
+type Hash = [u8; 32];
+
+fn sha256d(left: &Hash, right: &Hash) -> Hash {
+    let first = Sha256::new().chain_update(left).chain_update(right).finalize();
+    Sha256::digest(first).into()
+}
+
+// Display hex (as RPC returns txids) to internal byte order
+fn internal(display: &str) -> Result<Hash> {
+    let mut bytes: Hash = hex::decode(display)?
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("Expected a 32-byte hash, got {:?}", display))?;
+    bytes.reverse();
+    Ok(bytes)
+}
+
+fn display(hash: &Hash) -> String {
+    let mut bytes = *hash;
+    bytes.reverse();
+    hex::encode(bytes)
+}
+
+/// Merkle root of `txids` (in block order) and the branch of the transaction at
+/// `index`, bottom-up, all in display hex.
+pub fn merkle_branch(txids: &[String], index: usize) -> Result<(String, Vec<String>)> {
+    if index >= txids.len() {
+        return Err(anyhow::anyhow!("Transaction {} is not in a block of {}", index, txids.len()));
+    }
+    let mut level = txids.iter().map(|txid| internal(txid)).collect::<Result<Vec<Hash>>>()?;
+    let mut branch = Vec::new();
+    let mut position = index;
+    while level.len() > 1 {
+        if level.len() % 2 == 1 {
+            level.push(level[level.len() - 1]);
+        }
+        branch.push(display(&level[position ^ 1]));
+        level = level.chunks(2).map(|pair| sha256d(&pair[0], &pair[1])).collect();
+        position /= 2;
+    }
+    Ok((display(&level[0]), branch))
+}
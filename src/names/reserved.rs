@@ -0,0 +1,98 @@
+//! Reserved names: which names nobody may register without an operator grant.
+//!
+//! `ZNS_RESERVED_NAMES` lists entries separated by commas, and
+//! `ZNS_RESERVED_NAMES_FILE` names a file with one entry per line (`#` starts a
+//! comment), for trademarks, protocol names or offensive terms:
+//!
+//! ```text
+//! zcash        # the label under every TLD: zcash.zec, zcash.zcash
+//! zord.zec     # one exact name
+//! ```
+//!
+//! Entries are compared by confusable skeleton, so look-alikes of a reserved name
+//! are reserved too. A bare label only covers top-level names; subdomains are
+//! already gated by their parent's owner. An operator grants a reserved name to an
+//! address through `/api/v1/admin/names/reserved/:name`, after which a registration
+//! inscribed by that address counts. Like `ZRC20_DEPLOY_POLICY` this is not shared
+//! by public indexers: a node with reservations diverges from those without, and a
+//! change only affects registrations indexed afterwards.
+
+use super::{name_skeleton, normalize_name, parent_name};
+use anyhow::Result;
+use std::collections::HashSet;
+use std::sync::OnceLock;
+
+#[derive(Clone, Debug, Default)]
+pub struct ReservedNames {
+    /// Normalized entries as configured, for the admin listing.
+    pub entries: Vec<String>,
+    // Skeletons of bare labels and of full names
+    labels: HashSet<String>,
+    names: HashSet<String>,
+}
+
+impl ReservedNames {
+    pub fn from_env() -> Result<Self> {
+        let mut raw: Vec<String> = std::env::var("ZNS_RESERVED_NAMES")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::to_string)
+            .collect();
+        if let Ok(path) = std::env::var("ZNS_RESERVED_NAMES_FILE") {
+            let contents = std::fs::read_to_string(&path)
+                .map_err(|e| anyhow::anyhow!("ZNS_RESERVED_NAMES_FILE {} is unreadable: {}", path, e))?;
+            raw.extend(contents.lines().map(str::to_string));
+        }
+        Ok(Self::parse(raw.iter().map(String::as_str)))
+    }
+
+    fn parse<'a>(lines: impl Iterator<Item = &'a str>) -> Self {
+        let mut reserved = Self::default();
+        for line in lines {
+            let entry = normalize_name(line.split('#').next().unwrap_or(""));
+            if entry.is_empty() || reserved.entries.contains(&entry) {
+                continue;
+            }
+            let skeleton = name_skeleton(&entry);
+            if entry.contains('.') {
+                reserved.names.insert(skeleton);
+            } else {
+                reserved.labels.insert(skeleton);
+            }
+            reserved.entries.push(entry);
+        }
+        reserved
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Whether the normalized `name` is reserved, directly or as a look-alike.
+    pub fn is_reserved(&self, name: &str) -> bool {
+        if self.names.contains(&name_skeleton(name)) {
+            return true;
+        }
+        match name.rsplit_once('.') {
+            Some((label, _)) if parent_name(name).is_none() => self.labels.contains(&name_skeleton(label)),
+            _ => false,
+        }
+    }
+}
+
+static RESERVED: OnceLock<ReservedNames> = OnceLock::new();
+
+/// Read the reserved names once at startup, failing on an unreadable file rather
+/// than indexing without them.
+pub fn load() -> Result<()> {
+    let reserved = ReservedNames::from_env()?;
+    if !reserved.is_empty() {
+        tracing::info!("ZNS reserved names: {} entries", reserved.entries.len());
+    }
+    let _ = RESERVED.set(reserved);
+    Ok(())
+}
+
+pub fn reserved() -> &'static ReservedNames {
+    RESERVED.get_or_init(ReservedNames::default)
+}
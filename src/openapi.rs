@@ -0,0 +1,316 @@
+//! OpenAPI 3.1 description of the JSON API (v1 and v2), served at `/api/v1/openapi.json`.
+//!
+//! Nothing here lists routes. Each JSON handler carries a `#[utoipa::path]` attribute
+//! with its path, parameters, request body and response type, and is mounted with
+//! [`ApiRouter::routes`], which registers the handler with axum and its operation in
+//! the document in one step. Response schemas come from the handlers' own return
+//! types (`ToSchema` derives), so the document changes when the code does.
+//!
+//! HTML pages, static assets and raw content bytes are mounted with
+//! [`ApiRouter::route`] and left out.
+
+use axum::handler::Handler;
+use axum::response::IntoResponse;
+use axum::routing::{MethodFilter, MethodRouter, Route};
+use axum::Router;
+use std::convert::Infallible;
+use tower::{Layer, Service};
+use utoipa::__dev::{SchemaReferences, Tags};
+use utoipa::openapi::path::{HttpMethod, Operation, Paths};
+use utoipa::openapi::security::{Http, HttpAuthScheme, SecurityRequirement, SecurityScheme};
+use utoipa::openapi::{
+    ComponentsBuilder, ContentBuilder, InfoBuilder, OpenApi, OpenApiBuilder, RefOr, ResponseBuilder, Schema,
+};
+use utoipa::{PartialSchema, ToSchema};
+
+type Schemas = Vec<(String, RefOr<Schema>)>;
+
+/// RFC 7807 body of every error response (see `api::error`).
+#[derive(ToSchema)]
+#[allow(dead_code)]
+struct Problem {
+    r#type: String,
+    title: String,
+    status: u16,
+    detail: String,
+}
+
+/// Who may call the operations of a router, mirroring the auth layer in front of it.
+#[derive(Clone, Copy)]
+pub(crate) enum Access {
+    /// Always needs a key (admin routes)
+    Key,
+    /// Needs a key only when the operator turns protection on (API_PROTECT_EXPENSIVE)
+    OptionalKey,
+}
+
+/// Handlers of one path with their operations; built by [`routes!`].
+pub(crate) struct Endpoint<S> {
+    path: String,
+    methods: MethodRouter<S>,
+    operations: Vec<(HttpMethod, Operation)>,
+    schemas: Schemas,
+}
+
+impl<S: Clone + Send + Sync + 'static> Endpoint<S> {
+    #[allow(clippy::new_without_default)]
+    pub(crate) fn new() -> Self {
+        Endpoint { path: String::new(), methods: MethodRouter::new(), operations: Vec::new(), schemas: Vec::new() }
+    }
+
+    /// Adds `handler`, documented by `P`, the item its `#[utoipa::path]` generated.
+    pub(crate) fn on<P, H, T>(mut self, handler: H) -> Self
+    where
+        P: utoipa::Path + Tags<'static> + SchemaReferences,
+        H: Handler<T, S>,
+        T: 'static,
+    {
+        let path = P::path();
+        assert!(self.path.is_empty() || self.path == path, "routes! mixes {} and {}", self.path, path);
+        self.path = path;
+        let mut operation = P::operation();
+        operation.tags.get_or_insert_with(Vec::new).extend(P::tags().iter().map(ToString::to_string));
+        P::schemas(&mut self.schemas);
+        for method in P::methods() {
+            self.methods = self.methods.on(method_filter(&method), handler.clone());
+            self.operations.push((method, operation.clone()));
+        }
+        self
+    }
+}
+
+/// [`Endpoint`] serving the given `#[utoipa::path]` handlers, which must share a path.
+macro_rules! routes {
+    ($($handler:ident),+ $(,)?) => {
+        paste::paste! {
+            $crate::openapi::Endpoint::new() $(.on::<[<__path_ $handler>], _, _>($handler))+
+        }
+    };
+}
+pub(crate) use routes;
+
+/// Operations collected by an [`ApiRouter`], ready for [`document`].
+pub(crate) struct Operations {
+    paths: Paths,
+    schemas: Schemas,
+}
+
+/// An `axum::Router` that records the OpenAPI operation of every handler it mounts.
+pub(crate) struct ApiRouter<S> {
+    router: Router<S>,
+    operations: Operations,
+}
+
+impl<S: Clone + Send + Sync + 'static> ApiRouter<S> {
+    pub(crate) fn new() -> Self {
+        ApiRouter { router: Router::new(), operations: Operations { paths: Paths::new(), schemas: Vec::new() } }
+    }
+
+    /// Mounts `endpoint` at the path of its `#[utoipa::path]` attributes.
+    pub(crate) fn routes(self, endpoint: Endpoint<S>) -> Self {
+        let path = endpoint.path.clone();
+        self.mount(&path, endpoint)
+    }
+
+    /// Mounts `endpoint` at a second path as well, documented the same way.
+    pub(crate) fn alias(self, path: &str, endpoint: Endpoint<S>) -> Self {
+        self.mount(path, endpoint)
+    }
+
+    fn mount(mut self, path: &str, endpoint: Endpoint<S>) -> Self {
+        self.router = self.router.route(&axum_path(path), endpoint.methods);
+        for (method, mut operation) in endpoint.operations {
+            operation.operation_id = Some(operation_id(&method, path));
+            self.operations.paths.add_path_operation(path, vec![method], operation);
+        }
+        self.operations.schemas.extend(endpoint.schemas);
+        self
+    }
+
+    /// Mounts a route that is not part of the JSON API.
+    pub(crate) fn route(mut self, path: &str, method_router: MethodRouter<S>) -> Self {
+        self.router = self.router.route(path, method_router);
+        self
+    }
+
+    pub(crate) fn merge(mut self, other: ApiRouter<S>) -> Self {
+        self.router = self.router.merge(other.router);
+        self.operations.paths.merge(other.operations.paths);
+        self.operations.schemas.extend(other.operations.schemas);
+        self
+    }
+
+    /// `Router::route_layer`; pair it with [`ApiRouter::access`] when the layer checks keys.
+    pub(crate) fn route_layer<L>(mut self, layer: L) -> Self
+    where
+        L: Layer<Route> + Clone + Send + Sync + 'static,
+        L::Service: Service<axum::extract::Request> + Clone + Send + 'static,
+        <L::Service as Service<axum::extract::Request>>::Response: IntoResponse + 'static,
+        <L::Service as Service<axum::extract::Request>>::Error: Into<Infallible> + 'static,
+        <L::Service as Service<axum::extract::Request>>::Future: Send + 'static,
+    {
+        self.router = self.router.route_layer(layer);
+        self
+    }
+
+    /// Marks every operation mounted so far as needing `access`.
+    pub(crate) fn access(mut self, access: Access) -> Self {
+        let mut requirements = vec![SecurityRequirement::new("bearer", Vec::<String>::new())];
+        if let Access::OptionalKey = access {
+            requirements.insert(0, SecurityRequirement::default());
+        }
+        for item in self.operations.paths.paths.values_mut() {
+            for operation in operations_mut(item) {
+                operation.security = Some(requirements.clone());
+            }
+        }
+        self
+    }
+
+    pub(crate) fn split(self) -> (Router<S>, Operations) {
+        (self.router, self.operations)
+    }
+}
+
+/// The document for everything the given routers mounted.
+pub(crate) fn document(parts: impl IntoIterator<Item = Operations>) -> OpenApi {
+    let mut paths = Paths::new();
+    let mut schemas: Schemas = vec![(Problem::name().into_owned(), Problem::schema())];
+    for part in parts {
+        paths.merge(part.paths);
+        schemas.extend(part.schemas);
+    }
+    // Errors are problem+json whatever the route
+    let problem = ResponseBuilder::new()
+        .description("Error")
+        .content("application/problem+json", ContentBuilder::new().schema(Some(RefOr::Ref(
+            utoipa::openapi::Ref::from_schema_name("Problem"),
+        ))).build())
+        .build();
+    for item in paths.paths.values_mut() {
+        for operation in operations_mut(item) {
+            // `description` is required; most handlers only give a body type
+            for response in operation.responses.responses.values_mut() {
+                if let RefOr::T(response) = response {
+                    if response.description.is_empty() {
+                        response.description = "OK".to_string();
+                    }
+                }
+            }
+            operation.responses.responses.entry("default".to_string()).or_insert_with(|| problem.clone().into());
+        }
+    }
+    let mut bearer = Http::new(HttpAuthScheme::Bearer);
+    bearer.description = Some("Key from API_KEYS".to_string());
+    OpenApiBuilder::new()
+        .info(
+            InfoBuilder::new()
+                .title("Zord API")
+                .version(env!("CARGO_PKG_VERSION"))
+                .description(Some("Inscriptions, ZRC-20, ZRC-721 and ZNS indexed from Zcash.")),
+        )
+        .paths(paths)
+        .components(Some(
+            ComponentsBuilder::new()
+                .schemas_from_iter(schemas)
+                .security_scheme("bearer", SecurityScheme::Http(bearer))
+                .build(),
+        ))
+        .build()
+}
+
+fn operations_mut(item: &mut utoipa::openapi::PathItem) -> impl Iterator<Item = &mut Operation> {
+    [&mut item.get, &mut item.put, &mut item.post, &mut item.delete, &mut item.head, &mut item.patch]
+        .into_iter()
+        .filter_map(Option::as_mut)
+}
+
+fn method_filter(method: &HttpMethod) -> MethodFilter {
+    match method {
+        HttpMethod::Get => MethodFilter::GET,
+        HttpMethod::Put => MethodFilter::PUT,
+        HttpMethod::Post => MethodFilter::POST,
+        HttpMethod::Delete => MethodFilter::DELETE,
+        HttpMethod::Head => MethodFilter::HEAD,
+        HttpMethod::Patch => MethodFilter::PATCH,
+        HttpMethod::Options => MethodFilter::OPTIONS,
+        HttpMethod::Trace => MethodFilter::TRACE,
+    }
+}
+
+// OpenAPI `{name}` segments in axum's `:name` syntax
+fn axum_path(path: &str) -> String {
+    path.split('/')
+        .map(|segment| match segment.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+            Some(name) => format!(":{}", name),
+            None => segment.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+// "get_api_v1_zrc20_token_tick_balances" style ids, unique per method and path
+fn operation_id(method: &HttpMethod, path: &str) -> String {
+    let mut id = match method {
+        HttpMethod::Get => "get",
+        HttpMethod::Put => "put",
+        HttpMethod::Post => "post",
+        HttpMethod::Delete => "delete",
+        HttpMethod::Head => "head",
+        HttpMethod::Patch => "patch",
+        HttpMethod::Options => "options",
+        HttpMethod::Trace => "trace",
+    }
+    .to_string();
+    for segment in path.split('/').filter(|s| !s.is_empty()) {
+        id.push('_');
+        id.push_str(&segment.trim_matches(['{', '}']).replace(['-', '.'], "_"));
+    }
+    id
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::Path;
+    use axum::Json;
+
+    #[derive(serde::Serialize, ToSchema)]
+    struct Echo {
+        name: String,
+    }
+
+    /// Echo a name
+    #[utoipa::path(get, path = "/echo/{name}", tag = "test", params(("name" = String, Path)), responses((status = 200, body = Echo)))]
+    async fn get_echo(Path(name): Path<String>) -> Json<Echo> {
+        Json(Echo { name })
+    }
+
+    #[test]
+    fn mounted_handlers_are_documented_with_their_access() {
+        let (_, public) = ApiRouter::<()>::new().routes(routes!(get_echo)).split();
+        let (_, guarded) = ApiRouter::<()>::new()
+            .alias("/guarded/{name}", routes!(get_echo))
+            .access(Access::OptionalKey)
+            .split();
+        let doc = serde_json::to_value(document([public, guarded])).unwrap();
+
+        let echo = &doc["paths"]["/echo/{name}"]["get"];
+        assert_eq!(echo["operationId"], "get_echo_name");
+        assert_eq!(echo["summary"], "Echo a name");
+        assert_eq!(echo["responses"]["200"]["content"]["application/json"]["schema"]["$ref"], "#/components/schemas/Echo");
+        assert_eq!(echo["responses"]["default"]["content"]["application/problem+json"]["schema"]["$ref"], "#/components/schemas/Problem");
+        assert!(echo.get("security").is_none());
+        assert!(doc["components"]["schemas"]["Echo"].is_object());
+
+        let guarded = &doc["paths"]["/guarded/{name}"]["get"];
+        assert_eq!(guarded["operationId"], "get_guarded_name");
+        assert_eq!(guarded["security"], serde_json::json!([{}, {"bearer": []}]));
+    }
+
+    #[test]
+    fn openapi_segments_become_axum_captures() {
+        assert_eq!(axum_path("/api/v1/name/{name}/proof"), "/api/v1/name/:name/proof");
+        assert_eq!(axum_path("/api/v1/status"), "/api/v1/status");
+    }
+}
@@ -0,0 +1,43 @@
+//! Background ZRC-721 rarity scoring.
+//!
+//! Every `RARITY_INTERVAL` (default `10m`, `0` disables it) the job rescores the
+//! collections that minted since their last scoring; see `db/rarity.rs` for the
+//! formula. Scoring reads the trait index only, so it never blocks the indexer
+//! for longer than one collection's write.
+
+use crate::db::Db;
+use anyhow::Result;
+use std::time::Duration;
+
+pub struct RarityConfig {
+    interval: Duration,
+}
+
+impl RarityConfig {
+    /// Read `RARITY_INTERVAL`. Returns `None` when scoring is disabled.
+    pub fn from_env() -> Result<Option<Self>> {
+        let interval = match std::env::var("RARITY_INTERVAL") {
+            Ok(raw) if raw.trim() == "0" => return Ok(None),
+            Ok(raw) => crate::backup::parse_interval("RARITY_INTERVAL", &raw)?,
+            Err(_) => Duration::from_secs(600),
+        };
+        Ok(Some(Self { interval }))
+    }
+}
+
+/// Run the scoring loop until the process exits, starting with a pass at startup.
+pub fn spawn(db: Db, config: RarityConfig) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        tracing::info!("Rarity scoring enabled: every {:?}", config.interval);
+        loop {
+            let db = db.clone();
+            match tokio::task::spawn_blocking(move || db.refresh_zrc721_rarity()).await {
+                Ok(Ok(0)) => {}
+                Ok(Ok(n)) => tracing::info!("Rescored rarity for {} ZRC-721 collection(s)", n),
+                Ok(Err(e)) => tracing::error!("Rarity scoring failed: {}", e),
+                Err(e) => tracing::error!("Rarity task panicked: {}", e),
+            }
+            tokio::time::sleep(config.interval).await;
+        }
+    })
+}
@@ -1,13 +1,26 @@
 use anyhow::Result;
-use base64::{engine::general_purpose, Engine as _};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::env;
+use std::sync::Arc;
+
+pub mod auth;
+pub mod endpoints;
+pub mod error;
+pub mod retry;
+
+use auth::RpcAuth;
+use endpoints::{Balance, Endpoint, Endpoints};
+pub use error::RpcError;
+use retry::{CallStats, CircuitBreaker, RetryPolicy};
 
 #[derive(Clone)]
 pub struct ZcashRpcClient {
-    url: String,
+    endpoints: Arc<Endpoints>,
+    auth: Arc<RpcAuth>,
     client: reqwest::Client,
+    policy: Arc<RetryPolicy>,
+    breaker: Arc<CircuitBreaker>,
+    stats: Arc<CallStats>,
 }
 
 #[allow(dead_code)]
@@ -18,6 +31,8 @@ pub struct BlockResponse {
     pub tx: Vec<String>, // transaction ids
     pub time: u64,
     pub previousblockhash: Option<String>,
+    #[serde(default)]
+    pub merkleroot: Option<String>,
 }
 
 #[allow(dead_code)]
@@ -49,11 +64,20 @@ pub struct ScriptSig {
 #[derive(Debug, Deserialize)]
 pub struct Vout {
     pub value: f64,
+    #[serde(default, rename = "valueZat")]
+    pub value_zat: Option<u64>,
     pub n: u32,
     #[serde(rename = "scriptPubKey")]
     pub script_pub_key: ScriptPubKey,
 }
 
+impl Vout {
+    /// Output value in zatoshis; nodes that omit `valueZat` only report ZEC.
+    pub fn zatoshis(&self) -> u64 {
+        self.value_zat.unwrap_or_else(|| (self.value * 100_000_000.0).round() as u64)
+    }
+}
+
 #[allow(dead_code)]
 #[derive(Debug, Deserialize)]
 pub struct ScriptPubKey {
@@ -64,26 +88,15 @@ pub struct ScriptPubKey {
 }
 
 impl ZcashRpcClient {
-    pub fn new() -> Self {
-        let url = env::var("ZCASH_RPC_URL")
-            .unwrap_or_else(|_| "https://rpc.zatoshi.market/api/rpc".to_string());
-
-        let username = env::var("ZCASH_RPC_USERNAME").unwrap_or_else(|_| "zatoshi".to_string());
-        let password = env::var("ZCASH_RPC_PASSWORD")
-            .expect("ZCASH_RPC_PASSWORD must be provided via environment variable");
-
-        // Compose HTTP Basic credentials
-        let auth = format!("{}:{}", username, password);
-        let auth_header = format!(
-            "Basic {}",
-            general_purpose::STANDARD.encode(auth.as_bytes())
-        );
+    /// Build the client from `ZCASH_RPC_*`; fails on missing credentials or a
+    /// malformed endpoint list instead of starting an indexer that cannot sync.
+    pub fn new() -> Result<Self> {
+        let endpoints = Endpoints::from_env()?;
+        let auth = RpcAuth::from_env()?;
+        let policy = RetryPolicy::from_env()?;
+        let breaker = CircuitBreaker::from_env()?;
 
         let mut headers = reqwest::header::HeaderMap::new();
-        headers.insert(
-            reqwest::header::AUTHORIZATION,
-            reqwest::header::HeaderValue::from_str(&auth_header).expect("Invalid auth header"),
-        );
         headers.insert(
             reqwest::header::CONTENT_TYPE,
             reqwest::header::HeaderValue::from_static("application/json"),
@@ -92,69 +105,188 @@ impl ZcashRpcClient {
         let client = reqwest::Client::builder()
             .default_headers(headers)
             .timeout(std::time::Duration::from_secs(30))
-            .build()
-            .expect("Failed to build client");
+            .build()?;
 
-        tracing::info!("Initialized Zcash RPC client: {}", url);
+        let urls: Vec<&str> = endpoints.all().iter().map(|e| e.label.as_str()).collect();
+        match endpoints.balance() {
+            Balance::Failover => tracing::info!("Initialized Zcash RPC client: {} ({})", urls.join(", "), auth.describe()),
+            Balance::RoundRobin => tracing::info!(
+                "Initialized Zcash RPC client (round-robin): {} ({})",
+                urls.join(", "),
+                auth.describe()
+            ),
+        }
 
-        Self { url, client }
+        Ok(Self {
+            endpoints: Arc::new(endpoints),
+            auth: Arc::new(auth),
+            client,
+            policy: Arc::new(policy),
+            breaker: Arc::new(breaker),
+            stats: Arc::new(CallStats::default()),
+        })
     }
 
-    async fn call<T: Serialize>(&self, method: &str, params: T) -> Result<Value> {
-        let body = serde_json::json!({
-            "jsonrpc": "1.0",
-            "id": "zord",
-            "method": method,
-            "params": params
-        });
+    /// Probe every endpoint in the background so failed ones rejoin once they answer
+    /// again. Only worth running with more than one endpoint.
+    pub fn spawn_health_checks(&self) -> Option<tokio::task::JoinHandle<()>> {
+        if self.endpoints.all().len() < 2 {
+            return None;
+        }
+        let rpc = self.clone();
+        Some(tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(rpc.endpoints.health_interval).await;
+                let body = request_body("getblockcount", Vec::<Value>::new());
+                for endpoint in rpc.endpoints.all() {
+                    let healthy = rpc.post(endpoint, &body).await.is_ok();
+                    rpc.endpoints.mark(endpoint, healthy);
+                }
+            }
+        }))
+    }
 
-        let res = self
-            .client
-            .post(&self.url)
-            .json(&body)
-            .send()
-            .await?
-            .json::<Value>()
-            .await?;
+    /// Retry counts per method, the breaker state and endpoint health, for `/api/v1/metrics`.
+    pub fn metrics(&self) -> Value {
+        let endpoints: Vec<Value> = self
+            .endpoints
+            .all()
+            .iter()
+            .map(|e| serde_json::json!({ "url": e.label, "healthy": e.is_healthy() }))
+            .collect();
+        serde_json::json!({
+            "methods": self.stats.snapshot(),
+            "circuit": self.breaker.snapshot(),
+            "endpoints": endpoints,
+        })
+    }
 
-        if let Some(err) = res.get("error") {
-            if !err.is_null() {
-                return Err(anyhow::anyhow!("RPC Error: {:?}", err));
+    /// How long a caller should wait before calling again after a transient failure:
+    /// the rest of the breaker's cooldown, or the longest backoff step when it is closed.
+    pub fn retry_in(&self) -> std::time::Duration {
+        match self.breaker.retry_in() {
+            wait if wait.is_zero() => self.policy.delay(self.policy.retries),
+            wait => wait,
+        }
+    }
+
+    // One request to one endpoint; errors here are the endpoint's fault, not the call's
+    async fn post(&self, endpoint: &Endpoint, body: &Value) -> Result<Value, RpcError> {
+        let mut request = self.client.post(&endpoint.url).json(body);
+        if !endpoint.has_credentials {
+            if let Some(value) = self.auth.header().map_err(RpcError::Transport)? {
+                request = request.header(reqwest::header::AUTHORIZATION, value);
             }
         }
+        let response = request.send().await.map_err(|e| RpcError::Transport(e.into()))?;
+        if matches!(response.status().as_u16(), 401 | 403) {
+            return Err(RpcError::Unauthorized {
+                endpoint: endpoint.label.clone(),
+            });
+        }
+        response.json::<Value>().await.map_err(|e| RpcError::Transport(e.into()))
+    }
 
-        Ok(res["result"].clone())
+    async fn call<T: Serialize>(&self, method: &'static str, params: T) -> Result<Value, RpcError> {
+        let body = request_body(method, params);
+        let mut retries = 0;
+        loop {
+            let result = match self.breaker.admit() {
+                Ok(()) => {
+                    let result = self.attempt(method, &body).await;
+                    self.breaker.record(result.as_ref().map(|_| ()));
+                    result
+                }
+                Err(open) => Err(open),
+            };
+            match result {
+                // An open breaker fails fast; waiting it out is the caller's decision
+                Err(e) if e.is_transient() && retries < self.policy.retries && !matches!(e, RpcError::CircuitOpen { .. }) => {
+                    let delay = self.policy.delay(retries);
+                    tracing::debug!("RPC {} failed ({}), retry {} in {:?}", method, e, retries + 1, delay);
+                    retries += 1;
+                    tokio::time::sleep(delay).await;
+                }
+                result => {
+                    self.stats.record(method, retries, result.as_ref().err());
+                    return result;
+                }
+            }
+        }
     }
 
-    pub async fn get_block_count(&self) -> Result<u64> {
+    // Try endpoints until one answers; a JSON-RPC error is an answer
+    async fn attempt(&self, method: &str, body: &Value) -> Result<Value, RpcError> {
+        let mut failure = None;
+        for endpoint in self.endpoints.order() {
+            match self.post(endpoint, body).await {
+                Ok(res) => {
+                    self.endpoints.mark(endpoint, true);
+                    if let Some(err) = res.get("error") {
+                        if !err.is_null() {
+                            return Err(RpcError::from_node(err));
+                        }
+                    }
+                    return Ok(res["result"].clone());
+                }
+                Err(e) => {
+                    tracing::debug!("RPC {} via {} failed: {}", method, endpoint.label, e);
+                    self.endpoints.mark(endpoint, false);
+                    failure = Some(e);
+                }
+            }
+        }
+        Err(failure.unwrap_or_else(|| RpcError::Transport(anyhow::anyhow!("No RPC endpoints configured"))))
+    }
+
+    pub async fn get_block_count(&self) -> Result<u64, RpcError> {
         let res = self.call("getblockcount", Vec::<Value>::new()).await?;
         Ok(res.as_u64().unwrap_or(0))
     }
 
-    pub async fn get_block_hash(&self, height: u64) -> Result<String> {
+    pub async fn get_block_hash(&self, height: u64) -> Result<String, RpcError> {
         let res = self
             .call("getblockhash", vec![serde_json::json!(height)])
             .await?;
         Ok(res.as_str().unwrap_or("").to_string())
     }
 
-    pub async fn get_block(&self, hash: &str) -> Result<BlockResponse> {
+    pub async fn get_block(&self, hash: &str) -> Result<BlockResponse, RpcError> {
         let res = self
             .call(
                 "getblock",
                 vec![serde_json::json!(hash), serde_json::json!(1)],
             )
             .await?;
-        serde_json::from_value(res).map_err(|e| anyhow::anyhow!("Failed to parse block: {}", e))
+        serde_json::from_value(res).map_err(|e| RpcError::Decode(format!("block: {}", e)))
     }
 
-    pub async fn get_raw_transaction(&self, txid: &str) -> Result<TxResponse> {
+    pub async fn get_raw_transaction(&self, txid: &str) -> Result<TxResponse, RpcError> {
         let res = self
             .call(
                 "getrawtransaction",
                 vec![serde_json::json!(txid), serde_json::json!(1)],
             )
             .await?;
-        serde_json::from_value(res).map_err(|e| anyhow::anyhow!("Failed to parse tx: {}", e))
+        serde_json::from_value(res).map_err(|e| RpcError::Decode(format!("tx: {}", e)))
     }
+
+    /// Decode a serialized transaction without it being mined or broadcast.
+    pub async fn decode_raw_transaction(&self, hex: &str) -> Result<TxResponse, RpcError> {
+        let mut res = self
+            .call("decoderawtransaction", vec![serde_json::json!(hex)])
+            .await?;
+        // Unlike getrawtransaction, the decoded form does not echo the hex
+        res["hex"] = serde_json::json!(hex);
+        serde_json::from_value(res).map_err(|e| RpcError::Decode(format!("tx: {}", e)))
+    }
+}
+
+fn request_body<T: Serialize>(method: &str, params: T) -> Value {
+    serde_json::json!({
+        "jsonrpc": "1.0",
+        "id": "zord",
+        "method": method,
+        "params": params
+    })
 }
@@ -0,0 +1,129 @@
+//! The node endpoints behind one `ZcashRpcClient`.
+//!
+//! `ZCASH_RPC_URL` may list several nodes separated by commas. Calls go to the
+//! first healthy endpoint in the listed order (`ZCASH_RPC_BALANCE=failover`, the
+//! default) or rotate over the healthy ones (`round-robin`). An endpoint that
+//! fails at the transport level (unreachable, timeout, a body that is not
+//! JSON-RPC) is marked down and the call moves on to the next one. A background
+//! check probes every endpoint with `getblockcount` each
+//! `ZCASH_RPC_HEALTH_INTERVAL` (default `15s`) and brings recovered ones back.
+//! When every endpoint is down they are all still tried, in order.
+
+use anyhow::Result;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Balance {
+    /// Prefer endpoints in the order they are listed.
+    Failover,
+    /// Spread calls evenly over the healthy endpoints.
+    RoundRobin,
+}
+
+pub struct Endpoint {
+    pub url: String,
+    /// `url` with any password removed, for logs.
+    pub label: String,
+    // `user:pass@` in the URL; reqwest sends those instead of the configured auth
+    pub has_credentials: bool,
+    healthy: AtomicBool,
+}
+
+pub struct Endpoints {
+    list: Vec<Endpoint>,
+    balance: Balance,
+    // Round-robin cursor
+    next: AtomicUsize,
+    pub health_interval: Duration,
+}
+
+impl Endpoints {
+    /// Read `ZCASH_RPC_URL`, `ZCASH_RPC_BALANCE` and `ZCASH_RPC_HEALTH_INTERVAL`.
+    pub fn from_env() -> Result<Self> {
+        let urls = std::env::var("ZCASH_RPC_URL")
+            .unwrap_or_else(|_| "https://rpc.zatoshi.market/api/rpc".to_string());
+        let list: Vec<Endpoint> = urls
+            .split(',')
+            .map(str::trim)
+            .filter(|url| !url.is_empty())
+            .map(|url| {
+                let parsed = reqwest::Url::parse(url).ok();
+                let label = match parsed.clone() {
+                    Some(mut parsed) if parsed.password().is_some() => {
+                        let _ = parsed.set_password(None);
+                        parsed.to_string()
+                    }
+                    _ => url.to_string(),
+                };
+                Endpoint {
+                    url: url.to_string(),
+                    label,
+                    has_credentials: parsed.is_some_and(|u| !u.username().is_empty()),
+                    healthy: AtomicBool::new(true),
+                }
+            })
+            .collect();
+        if list.is_empty() {
+            return Err(anyhow::anyhow!("ZCASH_RPC_URL lists no endpoints"));
+        }
+        let balance = match std::env::var("ZCASH_RPC_BALANCE").as_deref().map(str::trim) {
+            Err(_) | Ok("") | Ok("failover") => Balance::Failover,
+            Ok("round-robin") => Balance::RoundRobin,
+            Ok(other) => {
+                return Err(anyhow::anyhow!(
+                    "ZCASH_RPC_BALANCE must be failover or round-robin, got {:?}",
+                    other
+                ))
+            }
+        };
+        let health_interval = match std::env::var("ZCASH_RPC_HEALTH_INTERVAL") {
+            Ok(raw) => crate::backup::parse_interval("ZCASH_RPC_HEALTH_INTERVAL", &raw)?,
+            Err(_) => Duration::from_secs(15),
+        };
+        Ok(Self {
+            list,
+            balance,
+            next: AtomicUsize::new(0),
+            health_interval,
+        })
+    }
+
+    pub fn all(&self) -> &[Endpoint] {
+        &self.list
+    }
+
+    pub fn balance(&self) -> Balance {
+        self.balance
+    }
+
+    /// Endpoints in the order a call should try them: healthy ones first, starting
+    /// at the primary (failover) or the next in turn (round-robin), then the rest.
+    pub fn order(&self) -> Vec<&Endpoint> {
+        let start = match self.balance {
+            Balance::Failover => 0,
+            Balance::RoundRobin => self.next.fetch_add(1, Ordering::Relaxed) % self.list.len(),
+        };
+        let rotated = self.list[start..].iter().chain(&self.list[..start]);
+        let (mut up, down): (Vec<&Endpoint>, Vec<&Endpoint>) = rotated.partition(|e| e.is_healthy());
+        up.extend(down);
+        up
+    }
+
+    /// Record the outcome of a call or probe, logging when an endpoint goes down or recovers.
+    pub fn mark(&self, endpoint: &Endpoint, healthy: bool) {
+        if endpoint.healthy.swap(healthy, Ordering::Relaxed) != healthy {
+            if healthy {
+                tracing::info!("RPC endpoint {} is back up", endpoint.label);
+            } else {
+                tracing::warn!("RPC endpoint {} is down", endpoint.label);
+            }
+        }
+    }
+}
+
+impl Endpoint {
+    pub fn is_healthy(&self) -> bool {
+        self.healthy.load(Ordering::Relaxed)
+    }
+}
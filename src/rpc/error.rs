@@ -0,0 +1,93 @@
+//! Failures of a `ZcashRpcClient` call, split by whether trying again can help.
+//!
+//! Error answers from the node are classified by their JSON-RPC code, which
+//! zcashd numbers like bitcoind (`src/rpc/protocol.h`), so callers can tell a
+//! block or transaction that does not exist from a node that is still starting.
+
+use std::fmt;
+use std::time::Duration;
+
+/// No such block, transaction or address (`RPC_INVALID_ADDRESS_OR_KEY`).
+pub const RPC_INVALID_ADDRESS_OR_KEY: i64 = -5;
+/// A parameter was out of range or malformed (`RPC_INVALID_PARAMETER`).
+pub const RPC_INVALID_PARAMETER: i64 = -8;
+/// A parameter had the wrong JSON type (`RPC_TYPE_ERROR`).
+pub const RPC_TYPE_ERROR: i64 = -3;
+/// Raw transaction or block bytes did not decode (`RPC_DESERIALIZATION_ERROR`).
+pub const RPC_DESERIALIZATION_ERROR: i64 = -22;
+/// The node is loading its block index or verifying blocks (`RPC_IN_WARMUP`).
+pub const RPC_IN_WARMUP: i64 = -28;
+/// JSON-RPC 2.0 invalid params.
+pub const RPC_INVALID_PARAMS: i64 = -32602;
+
+#[derive(Debug)]
+pub enum RpcError {
+    /// No endpoint produced a JSON-RPC answer: unreachable, timed out, or a body
+    /// that is not JSON (such as a proxy's 502 page).
+    Transport(anyhow::Error),
+    /// An endpoint refused the credentials (HTTP 401 or 403).
+    Unauthorized { endpoint: String },
+    /// The block, transaction or address asked for does not exist (-5).
+    NotFound { message: String },
+    /// The node is still starting up and answers nothing yet (-28).
+    WarmingUp { message: String },
+    /// The node rejected the parameters, e.g. malformed hex or a height beyond the tip.
+    InvalidParams { code: i64, message: String },
+    /// Any other error answer from the node.
+    Node { code: i64, message: String },
+    /// The node answered, but `result` did not have the expected shape.
+    Decode(String),
+    /// The circuit breaker is open after repeated transient failures.
+    CircuitOpen { retry_in: Duration },
+}
+
+impl RpcError {
+    /// Classify the `error` object of a JSON-RPC answer.
+    pub fn from_node(error: &serde_json::Value) -> Self {
+        let code = error["code"].as_i64().unwrap_or(0);
+        let message = match error["message"].as_str() {
+            Some(message) => message.to_string(),
+            None => error.to_string(),
+        };
+        match code {
+            RPC_INVALID_ADDRESS_OR_KEY => Self::NotFound { message },
+            RPC_IN_WARMUP => Self::WarmingUp { message },
+            RPC_INVALID_PARAMETER | RPC_TYPE_ERROR | RPC_DESERIALIZATION_ERROR | RPC_INVALID_PARAMS => {
+                Self::InvalidParams { code, message }
+            }
+            _ => Self::Node { code, message },
+        }
+    }
+
+    /// The node's JSON-RPC error code, when the node answered with one.
+    pub fn code(&self) -> Option<i64> {
+        match self {
+            Self::NotFound { .. } => Some(RPC_INVALID_ADDRESS_OR_KEY),
+            Self::WarmingUp { .. } => Some(RPC_IN_WARMUP),
+            Self::InvalidParams { code, .. } | Self::Node { code, .. } => Some(*code),
+            _ => None,
+        }
+    }
+
+    /// Whether the same call may succeed if repeated later.
+    pub fn is_transient(&self) -> bool {
+        matches!(self, Self::Transport(_) | Self::WarmingUp { .. } | Self::CircuitOpen { .. })
+    }
+}
+
+impl fmt::Display for RpcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Transport(e) => write!(f, "RPC transport error: {:#}", e),
+            Self::Unauthorized { endpoint } => write!(f, "RPC credentials rejected by {}", endpoint),
+            Self::NotFound { message } => write!(f, "RPC not found ({}): {}", RPC_INVALID_ADDRESS_OR_KEY, message),
+            Self::WarmingUp { message } => write!(f, "RPC node warming up ({}): {}", RPC_IN_WARMUP, message),
+            Self::InvalidParams { code, message } => write!(f, "RPC invalid parameters ({}): {}", code, message),
+            Self::Node { code, message } => write!(f, "RPC error ({}): {}", code, message),
+            Self::Decode(message) => write!(f, "RPC answer did not parse: {}", message),
+            Self::CircuitOpen { retry_in } => write!(f, "RPC circuit open after repeated failures; retry in {:?}", retry_in),
+        }
+    }
+}
+
+impl std::error::Error for RpcError {}
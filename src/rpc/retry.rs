@@ -0,0 +1,207 @@
+//! Retries and the circuit breaker behind every `ZcashRpcClient` call.
+//!
+//! A call that fails transiently (no endpoint reachable, a timeout, a proxy error
+//! page, a node still warming up) is retried up to `ZCASH_RPC_RETRIES` times
+//! (default 4). The n-th retry waits a random time between zero and
+//! `ZCASH_RPC_RETRY_BASE_MS` (default 250) times 2^n, capped at 30s, so several
+//! indexers sharing a node do not retry in lockstep. Permanent failures, such as
+//! "not found", rejected parameters or rejected credentials, return at once.
+//!
+//! Every transient failure counts towards the breaker; `ZCASH_RPC_BREAKER_THRESHOLD`
+//! (default 8) in a row open it. While open, calls fail immediately with
+//! `RpcError::CircuitOpen` for `ZCASH_RPC_BREAKER_COOLDOWN` (default `30s`); then
+//! a single trial call goes through, which closes the breaker on success and
+//! reopens it on failure.
+
+use super::error::RpcError;
+use anyhow::Result;
+use std::collections::hash_map::RandomState;
+use std::collections::BTreeMap;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const MAX_DELAY: Duration = Duration::from_secs(30);
+
+pub struct RetryPolicy {
+    pub retries: u32,
+    base: Duration,
+}
+
+impl RetryPolicy {
+    pub fn from_env() -> Result<Self> {
+        Ok(Self {
+            retries: env_number("ZCASH_RPC_RETRIES", 4)?,
+            base: Duration::from_millis(env_number("ZCASH_RPC_RETRY_BASE_MS", 250)?),
+        })
+    }
+
+    /// Wait before retry number `retry` (0 for the first): full jitter over an
+    /// exponentially growing window.
+    pub fn delay(&self, retry: u32) -> Duration {
+        let window = self
+            .base
+            .saturating_mul(1u32.checked_shl(retry).unwrap_or(u32::MAX))
+            .min(MAX_DELAY);
+        window.mul_f64(jitter())
+    }
+}
+
+// Uniform in [0, 1); RandomState is seeded randomly per instance
+fn jitter() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(0);
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+fn env_number<T: std::str::FromStr>(name: &str, default: T) -> Result<T> {
+    match std::env::var(name) {
+        Ok(raw) if !raw.trim().is_empty() => raw
+            .trim()
+            .parse()
+            .map_err(|_| anyhow::anyhow!("{} must be a number, got {:?}", name, raw)),
+        _ => Ok(default),
+    }
+}
+
+pub struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    state: Mutex<BreakerState>,
+}
+
+#[derive(Default)]
+struct BreakerState {
+    // Failed attempts in a row
+    failures: u32,
+    open_until: Option<Instant>,
+    // When the trial call after the cooldown went out. A probe whose future was
+    // dropped never records; once another cooldown passes it is taken as lost.
+    probe_started: Option<Instant>,
+    trips: u64,
+}
+
+impl CircuitBreaker {
+    pub fn from_env() -> Result<Self> {
+        let cooldown = match std::env::var("ZCASH_RPC_BREAKER_COOLDOWN") {
+            Ok(raw) => crate::backup::parse_interval("ZCASH_RPC_BREAKER_COOLDOWN", &raw)?,
+            Err(_) => Duration::from_secs(30),
+        };
+        Ok(Self {
+            threshold: env_number("ZCASH_RPC_BREAKER_THRESHOLD", 8u32)?.max(1),
+            cooldown,
+            state: Mutex::new(BreakerState::default()),
+        })
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BreakerState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Let an attempt through, or refuse it while the breaker is open.
+    pub fn admit(&self) -> Result<(), RpcError> {
+        let mut state = self.lock();
+        let Some(until) = self.next_attempt(&state) else {
+            return Ok(());
+        };
+        let now = Instant::now();
+        if now < until {
+            return Err(RpcError::CircuitOpen {
+                retry_in: until.saturating_duration_since(now),
+            });
+        }
+        state.probe_started = Some(now);
+        Ok(())
+    }
+
+    /// Record an attempt's outcome. Only transient failures count against the node.
+    pub fn record(&self, result: Result<(), &RpcError>) {
+        let mut state = self.lock();
+        match result {
+            Err(e) if e.is_transient() => {
+                state.failures += 1;
+                let probing = state.probe_started.is_some();
+                if probing || (state.open_until.is_none() && state.failures >= self.threshold) {
+                    if !probing {
+                        tracing::warn!("RPC circuit open after {} failures: {}", state.failures, e);
+                    }
+                    state.open_until = Some(Instant::now() + self.cooldown);
+                    state.probe_started = None;
+                    state.trips += 1;
+                }
+            }
+            _ => {
+                if state.open_until.is_some() {
+                    tracing::info!("RPC circuit closed");
+                }
+                *state = BreakerState {
+                    trips: state.trips,
+                    ..BreakerState::default()
+                };
+            }
+        }
+    }
+
+    // Earliest next admission while open: the cooldown's end, or a cooldown after the last probe
+    fn next_attempt(&self, state: &BreakerState) -> Option<Instant> {
+        let until = state.open_until?;
+        Some(match state.probe_started {
+            Some(started) => until.max(started + self.cooldown),
+            None => until,
+        })
+    }
+
+    /// How long until the breaker lets a call through again; zero when closed.
+    pub fn retry_in(&self) -> Duration {
+        self.next_attempt(&self.lock())
+            .map(|until| until.saturating_duration_since(Instant::now()))
+            .unwrap_or_default()
+    }
+
+    pub fn snapshot(&self) -> serde_json::Value {
+        let state = self.lock();
+        serde_json::json!({
+            "open": state.open_until.is_some(),
+            "consecutive_failures": state.failures,
+            "trips": state.trips,
+            "retry_in_ms": state
+                .open_until
+                .map(|until| until.saturating_duration_since(Instant::now()).as_millis() as u64),
+        })
+    }
+}
+
+/// Calls, retries and failed calls per RPC method, with the node's error codes.
+#[derive(Default)]
+pub struct CallStats {
+    methods: Mutex<BTreeMap<&'static str, MethodStats>>,
+}
+
+#[derive(Default, Clone, serde::Serialize)]
+struct MethodStats {
+    calls: u64,
+    retries: u64,
+    failures: u64,
+    // JSON-RPC error code -> answers carrying it
+    error_codes: BTreeMap<i64, u64>,
+}
+
+impl CallStats {
+    pub fn record(&self, method: &'static str, retries: u32, error: Option<&RpcError>) {
+        let mut methods = self.methods.lock().unwrap_or_else(|e| e.into_inner());
+        let stats = methods.entry(method).or_default();
+        stats.calls += 1;
+        stats.retries += u64::from(retries);
+        if let Some(error) = error {
+            stats.failures += 1;
+            if let Some(code) = error.code() {
+                *stats.error_codes.entry(code).or_default() += 1;
+            }
+        }
+    }
+
+    pub fn snapshot(&self) -> serde_json::Value {
+        let methods = self.methods.lock().unwrap_or_else(|e| e.into_inner());
+        serde_json::to_value(&*methods).unwrap_or_default()
+    }
+}
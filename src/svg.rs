@@ -0,0 +1,256 @@
+//! Sanitizer for SVG inscriptions served to explorer users.
+//!
+//! SVG is XML that a browser will happily execute when it is opened as a document:
+//! `<script>`, `<foreignObject>` HTML, `on*` handlers and `javascript:` links all
+//! run. This is a streaming tag filter rather than a full XML parser: markup is
+//! copied through as written except for the constructs below, which are dropped
+//! together with everything they contain.
+
+use std::borrow::Cow;
+
+// Elements removed along with their whole subtree
+const DROPPED_ELEMENTS: [&str; 7] = ["script", "foreignobject", "iframe", "embed", "object", "handler", "listener"];
+// Attributes whose value is a URL that could be navigated to
+const LINK_ATTRIBUTES: [&str; 4] = ["href", "xlink:href", "src", "action"];
+
+/// `content` with scripts, foreign HTML, event handlers, script URLs, DOCTYPEs and
+/// processing instructions other than the XML declaration removed.
+pub fn sanitize(content: &[u8]) -> Vec<u8> {
+    let input = String::from_utf8_lossy(content);
+    let mut rest: &str = &input;
+    let mut out = String::with_capacity(rest.len());
+    // Open elements inside a dropped subtree; zero while copying
+    let mut skipping = 0usize;
+
+    while let Some(start) = rest.find('<') {
+        if skipping == 0 {
+            out.push_str(&rest[..start]);
+        }
+        rest = &rest[start..];
+        let markup = if let Some(body) = rest.strip_prefix("<!--") {
+            Markup::Dropped(4 + body.find("-->").map_or(body.len(), |e| e + 3))
+        } else if let Some(body) = rest.strip_prefix("<![CDATA[") {
+            Markup::Verbatim(9 + body.find("]]>").map_or(body.len(), |e| e + 3))
+        } else if rest.starts_with("<!") {
+            Markup::Dropped(declaration_end(rest))
+        } else if let Some(body) = rest.strip_prefix("<?") {
+            let len = 2 + body.find("?>").map_or(body.len(), |e| e + 2);
+            if rest.starts_with("<?xml ") && skipping == 0 {
+                Markup::Verbatim(len)
+            } else {
+                Markup::Dropped(len)
+            }
+        } else {
+            Markup::Tag(tag_end(rest))
+        };
+
+        match markup {
+            Markup::Verbatim(len) => {
+                if skipping == 0 {
+                    out.push_str(&rest[..len]);
+                }
+                rest = &rest[len..];
+            }
+            Markup::Dropped(len) => rest = &rest[len..],
+            Markup::Tag(len) => {
+                let tag = Tag::parse(&rest[..len]);
+                rest = &rest[len..];
+                if skipping > 0 {
+                    if tag.closing {
+                        skipping -= 1;
+                    } else if !tag.self_closing {
+                        skipping += 1;
+                    }
+                } else if tag.is_dropped() {
+                    if tag.closing || tag.self_closing {
+                        continue;
+                    }
+                    if local_name(tag.name).eq_ignore_ascii_case("script") {
+                        // Script bodies are not markup; jump straight to the end tag
+                        let close = format!("</{}", tag.name).to_ascii_lowercase();
+                        rest = match rest.to_ascii_lowercase().find(&close) {
+                            Some(at) => &rest[at + tag_end(&rest[at..])..],
+                            None => "",
+                        };
+                    } else {
+                        skipping = 1;
+                    }
+                } else {
+                    tag.write(&mut out);
+                }
+            }
+        }
+    }
+    if skipping == 0 {
+        out.push_str(rest);
+    }
+    out.into_bytes()
+}
+
+enum Markup {
+    /// Copied through as written
+    Verbatim(usize),
+    Dropped(usize),
+    Tag(usize),
+}
+
+// End of `<!...>`, including a bracketed DOCTYPE internal subset
+fn declaration_end(markup: &str) -> usize {
+    let mut depth = 0i32;
+    for (i, c) in markup.char_indices() {
+        match c {
+            '[' => depth += 1,
+            ']' => depth -= 1,
+            '>' if depth <= 0 => return i + 1,
+            _ => {}
+        }
+    }
+    markup.len()
+}
+
+// End of a tag, skipping `>` inside quoted attribute values
+fn tag_end(markup: &str) -> usize {
+    let mut quote = None;
+    for (i, c) in markup.char_indices().skip(1) {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '>') => return i + 1,
+            _ => {}
+        }
+    }
+    markup.len()
+}
+
+struct Tag<'a> {
+    name: &'a str,
+    closing: bool,
+    self_closing: bool,
+    // Attribute name and its raw value with quotes, if any
+    attributes: Vec<(&'a str, Option<&'a str>)>,
+}
+
+impl<'a> Tag<'a> {
+    fn parse(markup: &'a str) -> Self {
+        let inner = markup.strip_prefix('<').unwrap_or(markup);
+        let inner = inner.strip_suffix('>').unwrap_or(inner);
+        let (closing, inner) = match inner.strip_prefix('/') {
+            Some(rest) => (true, rest),
+            None => (false, inner),
+        };
+        let (self_closing, inner) = match inner.trim_end().strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, inner),
+        };
+        let name_end = inner.find(|c: char| c.is_whitespace()).unwrap_or(inner.len());
+        let (name, mut rest) = inner.split_at(name_end);
+
+        let mut attributes = Vec::new();
+        loop {
+            rest = rest.trim_start();
+            if rest.is_empty() {
+                break;
+            }
+            let end = rest.find(|c: char| c.is_whitespace() || c == '=').unwrap_or(rest.len());
+            let (attr, after) = rest.split_at(end);
+            rest = after.trim_start();
+            let value = match rest.strip_prefix('=') {
+                Some(after_eq) => {
+                    let after_eq = after_eq.trim_start();
+                    let len = match after_eq.chars().next() {
+                        Some(q @ ('"' | '\'')) => after_eq[1..].find(q).map_or(after_eq.len(), |e| e + 2),
+                        _ => after_eq.find(char::is_whitespace).unwrap_or(after_eq.len()),
+                    };
+                    rest = &after_eq[len..];
+                    Some(&after_eq[..len])
+                }
+                None => None,
+            };
+            if !attr.is_empty() {
+                attributes.push((attr, value));
+            }
+        }
+        Tag { name, closing, self_closing, attributes }
+    }
+
+    fn is_dropped(&self) -> bool {
+        let name = local_name(self.name).to_ascii_lowercase();
+        if DROPPED_ELEMENTS.contains(&name.as_str()) {
+            return true;
+        }
+        // Animations can rewrite a link or handler after sanitizing
+        matches!(name.as_str(), "animate" | "set")
+            && self.attributes.iter().any(|(attr, value)| {
+                attr.eq_ignore_ascii_case("attributeName")
+                    && value.is_some_and(|v| {
+                        let target = unquote(v).trim().to_ascii_lowercase();
+                        is_link_attribute(&target) || local_name(&target).starts_with("on")
+                    })
+            })
+    }
+
+    fn write(&self, out: &mut String) {
+        out.push('<');
+        if self.closing {
+            out.push('/');
+        }
+        out.push_str(self.name);
+        for (attr, value) in &self.attributes {
+            let lower = attr.to_ascii_lowercase();
+            if local_name(&lower).starts_with("on") {
+                continue;
+            }
+            if is_link_attribute(&lower) && value.is_some_and(|v| is_script_url(&unquote(v))) {
+                continue;
+            }
+            out.push(' ');
+            out.push_str(attr);
+            if let Some(value) = value {
+                out.push('=');
+                out.push_str(value);
+            }
+        }
+        if self.self_closing {
+            out.push_str("/>");
+        } else {
+            out.push('>');
+        }
+    }
+}
+
+fn local_name(name: &str) -> &str {
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+fn is_link_attribute(name: &str) -> bool {
+    LINK_ATTRIBUTES.iter().any(|a| a.eq_ignore_ascii_case(name.trim()))
+}
+
+// Attribute value without quotes, with character references decoded
+fn unquote(raw: &str) -> Cow<'_, str> {
+    let value = raw
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .or_else(|| raw.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+        .unwrap_or(raw);
+    if value.contains('&') {
+        Cow::Owned(html_escape::decode_html_entities(value).into_owned())
+    } else {
+        Cow::Borrowed(value)
+    }
+}
+
+// URLs that execute instead of navigating; browsers ignore embedded whitespace and
+// control characters in the scheme, so those are stripped before comparing
+fn is_script_url(value: &str) -> bool {
+    let scheme: String = value
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .take(16)
+        .collect::<String>()
+        .to_ascii_lowercase();
+    scheme.starts_with("javascript:")
+        || scheme.starts_with("vbscript:")
+        || (scheme.starts_with("data:") && (!scheme.starts_with("data:image/") || scheme.starts_with("data:image/svg")))
+}
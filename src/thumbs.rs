@@ -0,0 +1,72 @@
+//! Grid thumbnails for inscriptions.
+//!
+//! PNG, JPEG, GIF, WebP and BMP payloads are decoded, scaled down to the requested
+//! edge and always re-encoded as PNG, so raster thumbnails never echo inscribed
+//! bytes back. Animated images keep their first frame. SVG scales on its own, and
+//! everything else, including rasters that fail to decode, gets a generated
+//! placeholder tile.
+
+use anyhow::{anyhow, Result};
+use image::{imageops::FilterType, ImageFormat, ImageReader, Limits};
+use std::io::Cursor;
+
+/// Thumbnail edges that are rendered and cached; requests snap up to the next one.
+pub const SIZES: [u32; 4] = [64, 128, 256, 512];
+
+// Images above this many pixels get the placeholder instead of being decoded
+const MAX_PIXELS: u64 = 16 * 1024 * 1024;
+
+/// A rendered thumbnail and the MIME type to serve it with.
+pub struct Thumb {
+    pub content_type: String,
+    pub bytes: Vec<u8>,
+}
+
+/// Smallest cached edge covering `requested`.
+pub fn snap_size(requested: Option<u32>) -> u32 {
+    let requested = requested.unwrap_or(256);
+    SIZES.into_iter().find(|s| *s >= requested).unwrap_or(SIZES[SIZES.len() - 1])
+}
+
+/// Thumbnail for a payload. Only PNG output is worth caching; SVGs and placeholders
+/// are cheap to rebuild.
+pub fn render(content_type: &str, content: &[u8], size: u32, category: &str) -> Thumb {
+    let simple = content_type.split(';').next().unwrap_or("").trim().to_lowercase();
+    if simple == "image/svg+xml" {
+        return Thumb { content_type: simple, bytes: content.to_vec() };
+    }
+    if let Some(format) = ImageFormat::from_mime_type(&simple).filter(|f| f.reading_enabled()) {
+        match reencode(content, format, size) {
+            Ok(bytes) => return Thumb { content_type: "image/png".to_string(), bytes },
+            Err(e) => tracing::debug!("{} thumbnail fell back to placeholder: {}", simple, e),
+        }
+    }
+    Thumb { content_type: "image/svg+xml".to_string(), bytes: placeholder(category, size).into_bytes() }
+}
+
+// Square tile naming the content category
+fn placeholder(category: &str, size: u32) -> String {
+    format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{s}" height="{s}" viewBox="0 0 100 100"><rect width="100" height="100" fill="#0b0b10"/><text x="50" y="54" fill="#ffc837" font-family="monospace" font-size="12" text-anchor="middle">{c}</text></svg>"##,
+        s = size,
+        c = html_escape::encode_text(category),
+    )
+}
+
+// Decode, shrink to fit `size` (never enlarging) and encode as PNG
+fn reencode(content: &[u8], format: ImageFormat, size: u32) -> Result<Vec<u8>> {
+    let mut reader = ImageReader::with_format(Cursor::new(content), format);
+    let mut limits = Limits::default();
+    // RGBA8 is the widest layout the enabled decoders produce for 8-bit images
+    limits.max_alloc = Some(MAX_PIXELS * 4);
+    reader.limits(limits);
+    let mut image = reader.decode()?;
+    if image.width() > size || image.height() > size {
+        image = image.resize(size, size, FilterType::Triangle);
+    }
+    let mut out = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut out), ImageFormat::Png)
+        .map_err(|e| anyhow!("PNG encoding failed: {}", e))?;
+    Ok(out)
+}
@@ -0,0 +1,247 @@
+//! Outbound webhooks for indexed activity.
+//!
+//! Subscriptions live in the `webhooks` table and are managed through the admin
+//! API. After each block commits, the indexer hands its address activity rows to
+//! the dispatcher, which POSTs the rows matching each subscription's filter as one
+//! signed JSON payload. Failed deliveries are retried with exponential backoff and
+//! end up in the dead-letter log once the attempts run out.
+
+use crate::db::Db;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::sync::Arc;
+use tokio::sync::{broadcast, mpsc};
+
+const SIGNATURE_HEADER: &str = "X-Zord-Signature";
+const TIMESTAMP_HEADER: &str = "X-Zord-Timestamp";
+const MAX_ATTEMPTS: u32 = 6;
+const INITIAL_BACKOFF: Duration = Duration::from_secs(2);
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+// Blocks buffered per live subscriber before it is cut off as lagging
+const LIVE_BUFFER: usize = 64;
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct WebhookFilter {
+    /// ZRC-20 ticker (case-insensitive)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tick: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    /// Activity kinds such as `token_mint` or `name_received`; empty matches all
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub kinds: Vec<String>,
+}
+
+impl WebhookFilter {
+    pub fn matches(&self, event: &serde_json::Value) -> bool {
+        if let Some(tick) = &self.tick {
+            let event_tick = event["tick"].as_str().unwrap_or("");
+            if !event_tick.eq_ignore_ascii_case(tick) {
+                return false;
+            }
+        }
+        if let Some(address) = &self.address {
+            if event["address"].as_str() != Some(address.as_str()) {
+                return false;
+            }
+        }
+        self.kinds.is_empty() || self.kinds.iter().any(|k| event["kind"].as_str() == Some(k.as_str()))
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct Webhook {
+    pub id: String,
+    pub url: String,
+    /// HMAC-SHA256 key for the signature header
+    pub secret: String,
+    #[serde(default)]
+    pub filter: WebhookFilter,
+    pub created_at: u64,
+}
+
+impl Webhook {
+    /// Build a subscription with a fresh id, generating a secret when none is given.
+    pub fn new(url: String, filter: WebhookFilter, secret: Option<String>) -> Result<Self> {
+        let parsed = reqwest::Url::parse(&url)?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            return Err(anyhow::anyhow!("Webhook URL must be http or https"));
+        }
+        let created_at = unix_now();
+        let id = random_hex(&url)[..16].to_string();
+        let secret = match secret {
+            Some(secret) if !secret.is_empty() => secret,
+            _ => random_hex(&id),
+        };
+        Ok(Self { id, url, secret, filter, created_at })
+    }
+}
+
+/// Handle used by the indexer to queue a committed block's activity for delivery.
+#[derive(Clone)]
+pub struct Dispatcher {
+    tx: mpsc::UnboundedSender<Arc<BlockEvents>>,
+    live: broadcast::Sender<Arc<BlockEvents>>,
+}
+
+/// One committed block's activity rows, each tagged with its `address`.
+pub struct BlockEvents {
+    pub height: u64,
+    pub hash: String,
+    pub events: Vec<serde_json::Value>,
+}
+
+impl Dispatcher {
+    /// Queue a committed block's activity rows; never blocks the indexer.
+    pub fn notify(&self, height: u64, hash: &str, events: Vec<serde_json::Value>) {
+        if events.is_empty() {
+            return;
+        }
+        let block = Arc::new(BlockEvents { height, hash: hash.to_string(), events });
+        // No live subscribers is not an error
+        let _ = self.live.send(block.clone());
+        let _ = self.tx.send(block);
+    }
+
+    /// Receive every block's activity from now on, for streaming APIs.
+    pub fn subscribe(&self) -> broadcast::Receiver<Arc<BlockEvents>> {
+        self.live.subscribe()
+    }
+}
+
+/// Start the delivery loop. Subscriptions are re-read for every block, so changes made
+/// through the admin API apply from the next block on.
+pub fn spawn(db: Db) -> Dispatcher {
+    let (tx, mut rx) = mpsc::unbounded_channel::<Arc<BlockEvents>>();
+    let (live, _) = broadcast::channel(LIVE_BUFFER);
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .expect("Failed to build webhook client");
+
+    tokio::spawn(async move {
+        while let Some(block) = rx.recv().await {
+            let hooks = match db.list_webhooks() {
+                Ok(rows) => rows,
+                Err(e) => {
+                    tracing::error!("Failed to load webhooks: {}", e);
+                    continue;
+                }
+            };
+            for (id, raw) in hooks {
+                let hook: Webhook = match serde_json::from_str(&raw) {
+                    Ok(hook) => hook,
+                    Err(e) => {
+                        tracing::warn!("Skipping malformed webhook {}: {}", id, e);
+                        continue;
+                    }
+                };
+                let events: Vec<&serde_json::Value> =
+                    block.events.iter().filter(|e| hook.filter.matches(e)).collect();
+                if events.is_empty() {
+                    continue;
+                }
+                let payload = serde_json::json!({
+                    "webhook_id": hook.id,
+                    "height": block.height,
+                    "block_hash": block.hash,
+                    "events": events,
+                });
+                // Each delivery retries on its own so one slow endpoint cannot hold up the rest
+                tokio::spawn(deliver(db.clone(), client.clone(), hook, payload.to_string()));
+            }
+        }
+    });
+
+    Dispatcher { tx, live }
+}
+
+async fn deliver(db: Db, client: reqwest::Client, hook: Webhook, body: String) {
+    let mut backoff = INITIAL_BACKOFF;
+    let mut last_error = String::new();
+    for attempt in 1..=MAX_ATTEMPTS {
+        let timestamp = unix_now().to_string();
+        let signature = sign(&hook.secret, &timestamp, &body);
+        let result = client
+            .post(&hook.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(TIMESTAMP_HEADER, &timestamp)
+            .header(SIGNATURE_HEADER, format!("sha256={}", signature))
+            .body(body.clone())
+            .send()
+            .await;
+        match result {
+            Ok(res) if res.status().is_success() => return,
+            Ok(res) => last_error = format!("HTTP {}", res.status()),
+            Err(e) => last_error = e.to_string(),
+        }
+        tracing::warn!(
+            "Webhook {} delivery attempt {}/{} failed: {}",
+            hook.id,
+            attempt,
+            MAX_ATTEMPTS,
+            last_error
+        );
+        if attempt < MAX_ATTEMPTS {
+            tokio::time::sleep(backoff).await;
+            backoff = std::cmp::min(backoff * 2, MAX_BACKOFF);
+        }
+    }
+
+    let entry = serde_json::json!({
+        "webhook_id": hook.id,
+        "url": hook.url,
+        "attempts": MAX_ATTEMPTS,
+        "error": last_error,
+        "failed_at": unix_now(),
+        "payload": serde_json::from_str::<serde_json::Value>(&body).unwrap_or_default(),
+    });
+    let recorded = tokio::task::spawn_blocking(move || db.record_dead_letter(&hook.id, &entry.to_string())).await;
+    if !matches!(recorded, Ok(Ok(()))) {
+        tracing::error!("Failed to record webhook dead letter");
+    }
+}
+
+// Hex HMAC-SHA256 over "<timestamp>.<body>", so receivers can reject replays
+fn sign(secret: &str, timestamp: &str, body: &str) -> String {
+    use sha2::{Digest, Sha256};
+    const BLOCK: usize = 64;
+
+    let mut key = [0u8; BLOCK];
+    if secret.len() > BLOCK {
+        key[..32].copy_from_slice(&Sha256::digest(secret.as_bytes()));
+    } else {
+        key[..secret.len()].copy_from_slice(secret.as_bytes());
+    }
+    let mut inner = Sha256::new();
+    inner.update(key.map(|b| b ^ 0x36));
+    inner.update(timestamp.as_bytes());
+    inner.update(b".");
+    inner.update(body.as_bytes());
+    let mut outer = Sha256::new();
+    outer.update(key.map(|b| b ^ 0x5c));
+    outer.update(inner.finalize());
+    hex::encode(outer.finalize())
+}
+
+// Unpredictable hex derived from the process-random hasher seed and the clock
+fn random_hex(salt: &str) -> String {
+    use sha2::{Digest, Sha256};
+    use std::hash::BuildHasher;
+
+    let seed = std::collections::hash_map::RandomState::new().hash_one(salt);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let mut hasher = Sha256::new();
+    hasher.update(seed.to_le_bytes());
+    hasher.update(nanos.to_le_bytes());
+    hasher.update(salt.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
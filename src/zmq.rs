@@ -33,13 +33,11 @@ impl ZmqListener {
 
             loop {
                 // Consume the topic frame and the raw payload frame
-                if subscriber.recv_msg(0).is_ok() {
-                    if subscriber.recv_msg(0).is_ok() {
-                        // Signal the async loop so it rechecks RPC height
-                        if let Err(_) = sender.blocking_send(()) {
-                            tracing::info!("ZMQ receiver dropped, stopping listener");
-                            break;
-                        }
+                if subscriber.recv_msg(0).is_ok() && subscriber.recv_msg(0).is_ok() {
+                    // Signal the async loop so it rechecks RPC height
+                    if sender.blocking_send(()).is_err() {
+                        tracing::info!("ZMQ receiver dropped, stopping listener");
+                        break;
                     }
                 }
             }
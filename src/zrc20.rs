@@ -1,8 +1,186 @@
-use crate::db::Db;
+use crate::db::{Balance, BlockWriter, Db};
+use crate::rpc::{ScriptPubKey, TxResponse};
+use amount::{Amount, Delta};
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+pub mod amount;
+pub mod policy;
+pub mod rebuild;
+pub mod ticker;
+
+/// Where a revealed transfer inscription sends its amount.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Destination<'a> {
+    /// Credited to this transparent address.
+    Address(&'a str),
+    /// Destroyed; the reason is recorded as `burn` on the history row:
+    /// `"op_return"` (provably unspendable output), `"burn_address"` (a designated
+    /// burn address of the `BURN_PHASES` entry in force) or `"shielded"` (no
+    /// transparent output at all).
+    Burn(&'static str),
+}
+
+impl<'a> Destination<'a> {
+    /// The receiver of a transfer event; no transparent receiver is a shielded burn.
+    pub fn from_receiver(receiver: Option<&'a str>) -> Self {
+        receiver.map_or(Destination::Burn("shielded"), Destination::Address)
+    }
+}
+
+/// ZRC-20 burn rules in force from `height` on, until the next phase.
+struct BurnPhase {
+    height: u64,
+    /// A transfer whose first OP_RETURN-or-address output is an OP_RETURN burns.
+    /// Before this, OP_RETURN outputs were skipped over and the first transparent
+    /// address was credited.
+    op_return: bool,
+    /// Designated burn addresses: a transfer paid to one is burned, not credited.
+    addresses: &'static [&'static str],
+}
+
+/// Height from which OP_RETURN outputs burn transfers.
+pub const BURN_ACTIVATION_HEIGHT: u64 = 3_600_000;
+
+/// The burn rules by height. They are consensus and compiled in so every indexer
+/// agrees: a past phase must never change, and new rules get a phase starting
+/// above the tip of every deployed indexer.
+const BURN_PHASES: &[BurnPhase] = &[
+    BurnPhase {
+        height: 0,
+        op_return: false,
+        addresses: &[],
+    },
+    BurnPhase {
+        height: BURN_ACTIVATION_HEIGHT,
+        op_return: true,
+        addresses: &[],
+    },
+];
+
+fn burn_phase(height: u64) -> &'static BurnPhase {
+    BURN_PHASES
+        .iter()
+        .rev()
+        .find(|phase| phase.height <= height)
+        .unwrap_or(&BURN_PHASES[0])
+}
+
+static PARTIAL_MINT_HEIGHT: OnceLock<Option<u64>> = OnceLock::new();
+
+/// Read the ZRC-20 settings once at startup: the ticker rules,
+/// `ZRC20_PARTIAL_MINT_HEIGHT` and the deploy policy. A malformed value stops the
+/// process rather than indexing under the wrong rules.
+pub fn load() -> Result<()> {
+    ticker::load()?;
+    policy::load()?;
+    let height = match std::env::var("ZRC20_PARTIAL_MINT_HEIGHT") {
+        Ok(raw) if !raw.trim().is_empty() => Some(raw.trim().parse::<u64>().map_err(|_| {
+            anyhow::anyhow!("ZRC20_PARTIAL_MINT_HEIGHT must be a block height, got {:?}", raw)
+        })?),
+        _ => None,
+    };
+    if let Some(height) = height {
+        tracing::info!("ZRC-20 final mints are partially filled from block {}", height);
+    }
+    let _ = PARTIAL_MINT_HEIGHT.set(height);
+    Ok(())
+}
+
+/// Whether a mint at `height` that overshoots the supply is credited the remainder
+/// instead of being rejected. Off unless `ZRC20_PARTIAL_MINT_HEIGHT` is set; this is
+/// consensus, and moving the height needs a reindex.
+fn partial_mint_at(height: u64) -> bool {
+    PARTIAL_MINT_HEIGHT
+        .get()
+        .copied()
+        .flatten()
+        .is_some_and(|from| height >= from)
+}
+
+// Outcome of a mint that passed every check
+struct MintGrant {
+    // Credited; below `requested` when a final mint is partially filled
+    amt: Amount,
+    requested: Amount,
+    // Whether the token limits mints per address, so the mint must be recorded
+    throttled: bool,
+}
 
-#[derive(Debug, Deserialize, Serialize)]
+// "<64 hex txid>i<output index>"
+fn is_inscription_id(raw: &str) -> bool {
+    raw.split_once('i').is_some_and(|(txid, index)| {
+        txid.len() == 64
+            && txid.bytes().all(|b| b.is_ascii_hexdigit())
+            && !index.is_empty()
+            && index.bytes().all(|b| b.is_ascii_digit())
+    })
+}
+
+fn is_unspendable(script: &ScriptPubKey) -> bool {
+    script.r#type == "nulldata" || script.asm.starts_with("OP_RETURN")
+}
+
+/// Destination of a transfer revealed by `tx` at `height`, under the burn phase in
+/// force there.
+pub fn transfer_destination(tx: &TxResponse, height: u64) -> Destination<'_> {
+    destination_under(tx, burn_phase(height))
+}
+
+// Before `op_return` activates, the first transparent address wins wherever it is
+fn destination_under<'a>(tx: &'a TxResponse, phase: &BurnPhase) -> Destination<'a> {
+    for out in &tx.vout {
+        if phase.op_return && is_unspendable(&out.script_pub_key) {
+            return Destination::Burn("op_return");
+        }
+        if let Some(first) = out.script_pub_key.addresses.as_ref().and_then(|a| a.first()) {
+            if phase.addresses.contains(&first.as_str()) {
+                return Destination::Burn("burn_address");
+            }
+            return Destination::Address(first);
+        }
+    }
+    Destination::Burn("shielded")
+}
+
+/// State the ZRC-20 rules read: a block being indexed sees its own staged writes,
+/// a pre-flight check sees the committed index.
+pub trait Zrc20State {
+    fn get_token_info(&self, ticker: &str) -> Result<Option<String>>;
+    fn get_balance(&self, address: &str, ticker: &str) -> Result<Balance>;
+    fn get_mint_record(&self, ticker: &str, address: &str) -> Result<Option<String>>;
+}
+
+impl Zrc20State for BlockWriter<'_> {
+    fn get_token_info(&self, ticker: &str) -> Result<Option<String>> {
+        BlockWriter::get_token_info(self, ticker)
+    }
+
+    fn get_balance(&self, address: &str, ticker: &str) -> Result<Balance> {
+        BlockWriter::get_balance(self, address, ticker)
+    }
+
+    fn get_mint_record(&self, ticker: &str, address: &str) -> Result<Option<String>> {
+        BlockWriter::get_mint_record(self, ticker, address)
+    }
+}
+
+impl Zrc20State for Db {
+    fn get_token_info(&self, ticker: &str) -> Result<Option<String>> {
+        Db::get_token_info(self, ticker)
+    }
+
+    fn get_balance(&self, address: &str, ticker: &str) -> Result<Balance> {
+        Db::get_balance(self, address, ticker)
+    }
+
+    fn get_mint_record(&self, ticker: &str, address: &str) -> Result<Option<String>> {
+        Db::get_mint_record(self, ticker, address)
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, utoipa::ToSchema)]
 pub struct Zrc20Operation {
     pub p: String,
     pub op: String,
@@ -15,21 +193,39 @@ pub struct Zrc20Operation {
     pub amt: Option<String>,
     #[serde(default)]
     pub dec: Option<String>,
+    /// Deploy only: most any one address may mint over the token's lifetime.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit_per_address: Option<String>,
+    /// Deploy only: blocks an address must wait after a mint before minting again.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blocks_between_mints: Option<String>,
+    /// Deploy only, 5-byte tickers: `"true"` lets only the deployer's address mint.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub self_mint: Option<String>,
+    /// Meta only: display name, icon inscription id and description of the token.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
 }
 
-pub struct Zrc20Engine {
-    db: Db,
-}
+// Longest display name and description a meta operation may set, in characters
+const MAX_META_NAME: usize = 64;
+const MAX_META_DESCRIPTION: usize = 1024;
 
-impl Zrc20Engine {
-    pub fn new(db: Db) -> Self {
-        Self { db }
-    }
+const SELF_MINT_VIOLATION: &str = "Only the deployer can mint a self_mint token";
+
+pub struct Zrc20Engine;
 
+impl Zrc20Engine {
     /// Process an inscription event
     /// event_type: "inscribe" or "transfer" (for when inscription is moved)
+    #[allow(clippy::too_many_arguments)]
     pub fn process(
         &self,
+        db: &BlockWriter,
         event_type: &str,
         inscription_id: &str,
         sender: &str,
@@ -37,9 +233,10 @@ impl Zrc20Engine {
         content: &str,
         txid: Option<&str>,
         assigned_vout: Option<u32>,
+        height: u64,
     ) -> Result<()> {
         // Parse and validate JSON
-        let op = match self.parse_and_validate(content) {
+        let op = match self.parse_and_validate(content, height) {
             Ok(op) => op,
             Err(e) => {
                 tracing::debug!("ZRC-20 validation failed: {}", e);
@@ -48,16 +245,46 @@ impl Zrc20Engine {
         };
 
         match (op.op.as_str(), event_type) {
-            ("deploy", "inscribe") => self.handle_deploy_inscribe(&op, inscription_id, sender),
-            ("mint", "inscribe") => self.handle_mint_inscribe(&op, inscription_id, sender),
-            ("transfer", "inscribe") => self.handle_transfer_inscribe(&op, inscription_id, sender, txid, assigned_vout),
-            ("transfer", "transfer") => self.handle_transfer_transfer(inscription_id, receiver),
+            ("deploy", "inscribe") => self.handle_deploy_inscribe(db, &op, inscription_id, sender, txid, height),
+            ("mint", "inscribe") => self.handle_mint_inscribe(db, &op, inscription_id, sender, txid, height),
+            ("transfer", "inscribe") => {
+                self.handle_transfer_inscribe(db, &op, inscription_id, sender, txid, assigned_vout, height)
+            }
+            ("meta", "inscribe") => self.handle_meta_inscribe(db, &op, inscription_id, sender, txid, height),
+            ("transfer", "transfer") => {
+                let destination = Destination::from_receiver(receiver);
+                self.handle_transfer_transfer(db, inscription_id, destination, txid.unwrap_or(""), height)
+            }
+            (_, "inscribe") => Err(anyhow::anyhow!("Unsupported op")),
             _ => Ok(()),
         }
     }
 
-    /// Strict BRC-20 validation
-    fn parse_and_validate(&self, content: &str) -> Result<Zrc20Operation> {
+    /// Check whether `sender` inscribing `content` in a block at `height` would be
+    /// accepted against `state`, without changing anything. Returns the normalized operation.
+    pub fn validate(&self, state: &impl Zrc20State, sender: &str, content: &str, height: u64) -> Result<Zrc20Operation> {
+        let op = self.parse_and_validate(content, height)?;
+        match op.op.as_str() {
+            "deploy" => {
+                self.check_deploy(state, &op)?;
+                if let Some(reason) = policy::policy().check(&op.tick, sender) {
+                    return Err(anyhow::anyhow!("Deploy refused by policy: {}", reason));
+                }
+            }
+            "mint" => {
+                self.check_mint(state, &op, sender, height)?;
+            }
+            "transfer" => {
+                self.check_transfer(state, &op, sender)?;
+            }
+            "meta" => self.check_meta(state, &op, sender)?,
+            _ => return Err(anyhow::anyhow!("Unsupported op")),
+        }
+        Ok(op)
+    }
+
+    /// Strict BRC-20 validation, with the ticker rules in force at `height`
+    fn parse_and_validate(&self, content: &str, height: u64) -> Result<Zrc20Operation> {
         // Payloads must be strict JSON
         let op: Zrc20Operation = serde_json::from_str(content.trim())?;
 
@@ -71,14 +298,14 @@ impl Zrc20Engine {
             return Err(anyhow::anyhow!("Op must be lowercase"));
         }
 
-        // Tick comparison uses lowercase to avoid duplicates
-        let normalized_tick = op.tick.to_lowercase();
-
-        // Enforce BRC/ZRC ticker length limits
-        let tick_bytes = normalized_tick.as_bytes().len();
-        if tick_bytes < 4 || tick_bytes > 5 {
-            return Err(anyhow::anyhow!("Ticker must be 4-5 bytes"));
+        // Tick comparison uses the normalized form to avoid duplicates
+        let phase = ticker::rules().at(height);
+        let normalized_tick = phase.normalize(&op.tick);
+        phase.check(&normalized_tick)?;
+        if op.op == "deploy" && phase.is_reserved(&normalized_tick) {
+            return Err(anyhow::anyhow!("Ticker is reserved"));
         }
+        let tick_bytes = normalized_tick.len();
 
         // Persist the normalized ticker back into the struct
         let mut op = op;
@@ -97,57 +324,51 @@ impl Zrc20Engine {
         if let Some(ref dec) = op.dec {
             self.validate_decimals(dec)?;
         }
+        if let Some(ref limit) = op.limit_per_address {
+            self.validate_numeric_string(limit, &op.dec)?;
+        }
+        if let Some(ref blocks) = op.blocks_between_mints {
+            // A block count, so no fractional part; zero disables the cooldown
+            if blocks.is_empty() || !blocks.chars().all(|c| c.is_ascii_digit()) || blocks.parse::<u64>().is_err() {
+                return Err(anyhow::anyhow!("blocks_between_mints must be a whole number of blocks"));
+            }
+        }
+        if let Some(ref self_mint) = op.self_mint {
+            // As in the BRC-20 self-mint extension, the flag belongs to 5-byte tickers
+            if self_mint != "true" {
+                return Err(anyhow::anyhow!("self_mint must be \"true\""));
+            }
+            if tick_bytes != 5 {
+                return Err(anyhow::anyhow!("self_mint is only allowed on 5-byte tickers"));
+            }
+        }
+        if op.op == "meta" {
+            if op.name.is_none() && op.icon.is_none() && op.description.is_none() {
+                return Err(anyhow::anyhow!("Meta needs a name, icon or description"));
+            }
+            if op.name.as_ref().is_some_and(|n| n.trim().is_empty() || n.chars().count() > MAX_META_NAME) {
+                return Err(anyhow::anyhow!("Name must be 1-{} characters", MAX_META_NAME));
+            }
+            if op.description.as_ref().is_some_and(|d| d.chars().count() > MAX_META_DESCRIPTION) {
+                return Err(anyhow::anyhow!("Description exceeds {} characters", MAX_META_DESCRIPTION));
+            }
+            if op.icon.as_deref().is_some_and(|icon| !is_inscription_id(icon)) {
+                return Err(anyhow::anyhow!("Icon must be an inscription id"));
+            }
+        }
 
         Ok(op)
     }
 
     fn validate_numeric_string(&self, value: &str, dec: &Option<String>) -> Result<()> {
-        // Reject empty strings
-        if value.is_empty() {
-            return Err(anyhow::anyhow!("Empty numeric string"));
-        }
-
         // Treat literal 0 as invalid for value fields (decimals handled separately)
         if value == "0" {
             return Err(anyhow::anyhow!("Zero is invalid for this field"));
         }
 
-        // Allow digits plus a single decimal point
-        let dot_count = value.chars().filter(|&c| c == '.').count();
-        if dot_count > 1 {
-            return Err(anyhow::anyhow!("Multiple dots in numeric string"));
-        }
-
-        // Strip obvious malformed inputs
-        if value.starts_with('.') || value.ends_with('.') {
-            return Err(anyhow::anyhow!("Numeric string cannot start/end with dot"));
-        }
-
-        // ASCII-only numbers are accepted
-        if !value.chars().all(|c| c.is_ascii_digit() || c == '.') {
-            return Err(anyhow::anyhow!("Invalid characters in numeric string"));
-        }
-
-        // Enforce declared decimal precision if a fractional part is present
-        if let Some(dot_pos) = value.find('.') {
-            let decimal_places = value.len() - dot_pos - 1;
-            let max_decimals = if let Some(d) = dec {
-                d.parse::<usize>().unwrap_or(18)
-            } else {
-                18
-            };
-
-            if decimal_places > max_decimals {
-                return Err(anyhow::anyhow!("Too many decimal places"));
-            }
-        }
-
-        // Guard against values exceeding u64 once scaled
-        let _numeric_value: u64 = value
-            .replace('.', "")
-            .parse()
-            .map_err(|_| anyhow::anyhow!("Value exceeds uint64_max"))?;
-
+        // Strict digits, declared precision, and the scaled value must fit in base units
+        let dec = dec.as_deref().and_then(|d| d.parse().ok()).unwrap_or(18);
+        Amount::parse(value, dec)?;
         Ok(())
     }
 
@@ -176,15 +397,35 @@ impl Zrc20Engine {
 
     fn handle_deploy_inscribe(
         &self,
+        db: &BlockWriter,
         op: &Zrc20Operation,
         inscription_id: &str,
         deployer: &str,
+        txid: Option<&str>,
+        height: u64,
     ) -> Result<()> {
+        self.check_deploy(db, op)?;
+        if let Some(reason) = policy::policy().check(&op.tick, deployer) {
+            db.record_rejected_deploy(
+                &op.tick,
+                height,
+                &serde_json::json!({
+                    "tick": op.tick,
+                    "deployer": deployer,
+                    "reason": reason,
+                    "txid": txid,
+                    "inscription_id": inscription_id,
+                    "height": height,
+                }),
+            )?;
+            tracing::info!("Deploy {} of {} by {} refused by policy: {}", inscription_id, op.tick, deployer, reason);
+            return Err(anyhow::anyhow!("Deploy refused by policy: {}", reason));
+        }
         let max = op.max.as_ref().ok_or(anyhow::anyhow!("Missing max"))?;
         let lim = op.lim.as_ref().unwrap_or(max); // default lim=max
-        let dec = op.dec.as_ref().map(|s| s.as_str()).unwrap_or("18"); // default decimals
+        let dec = op.dec.as_deref().unwrap_or("18"); // default decimals
 
-        let token_info = serde_json::json!({
+        let mut token_info = serde_json::json!({
             "tick": op.tick.to_lowercase(),
             "max": max,
             "lim": lim,
@@ -193,9 +434,34 @@ impl Zrc20Engine {
             "supply": "0",
             "inscription_id": inscription_id
         });
+        // Throttling rules are only stored when set, so unthrottled tokens skip the mint records
+        if let Some(ref limit) = op.limit_per_address {
+            token_info["limit_per_address"] = serde_json::json!(limit);
+        }
+        if let Some(ref blocks) = op.blocks_between_mints {
+            token_info["blocks_between_mints"] = serde_json::json!(blocks);
+        }
+        if op.self_mint.is_some() {
+            token_info["self_mint"] = serde_json::json!("true");
+        }
 
-        self.db
-            .deploy_token(&op.tick.to_lowercase(), &token_info.to_string())?;
+        db.deploy_token(&op.tick.to_lowercase(), &token_info.to_string())?;
+        db.record_zrc20_event(
+            &op.tick.to_lowercase(),
+            height,
+            &serde_json::json!({
+                "op": "deploy",
+                "tick": op.tick.to_lowercase(),
+                "max": max,
+                "lim": lim,
+                "dec": dec,
+                "from": deployer,
+                "to": null,
+                "txid": txid,
+                "inscription_id": inscription_id,
+                "height": height,
+            }),
+        )?;
         tracing::info!(
             "✅ Deployed token: {} (max: {}, lim: {}, dec: {})",
             op.tick,
@@ -208,102 +474,286 @@ impl Zrc20Engine {
 
     fn handle_mint_inscribe(
         &self,
+        db: &BlockWriter,
         op: &Zrc20Operation,
-        _inscription_id: &str,
+        inscription_id: &str,
         minter: &str,
+        txid: Option<&str>,
+        height: u64,
     ) -> Result<()> {
+        let grant = self.check_mint(db, op, minter, height).inspect_err(|e| {
+            if e.to_string() == SELF_MINT_VIOLATION {
+                tracing::info!(
+                    "Rejected mint {} of self_mint token {} by non-deployer {}",
+                    inscription_id,
+                    op.tick.to_lowercase(),
+                    minter
+                );
+            }
+        })?;
+
+        // Atomically bump supply and credit holder balance to avoid drift
+        db.mint_credit_atomic(&op.tick.to_lowercase(), minter, grant.amt)?;
+        if grant.throttled {
+            db.record_mint(&op.tick.to_lowercase(), minter, grant.amt, height)?;
+        }
+
+        let mut entry = serde_json::json!({
+            "op": "mint",
+            "tick": op.tick.to_lowercase(),
+            "amt": grant.amt.to_string(),
+            "from": null,
+            "to": minter,
+            "txid": txid,
+            "inscription_id": inscription_id,
+            "height": height,
+        });
+        if grant.amt < grant.requested {
+            // Partially filled final mint
+            entry["requested_amt"] = grant.requested.to_string().into();
+        }
+        db.record_zrc20_activity(&op.tick.to_lowercase(), height, &entry)?;
+
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn handle_transfer_inscribe(
+        &self,
+        db: &BlockWriter,
+        op: &Zrc20Operation,
+        inscription_id: &str,
+        sender: &str,
+        txid: Option<&str>,
+        assigned_vout: Option<u32>,
+        height: u64,
+    ) -> Result<()> {
+        let amt = self.check_transfer(db, op, sender)?;
+
+        // Record the intent so the reveal can settle it later
+        let transfer_data = serde_json::json!({
+            "tick": op.tick.to_lowercase(),
+            "amt": amt.to_string(),
+            "sender": sender
+        });
+
+        db.create_transfer_inscription(inscription_id, &transfer_data.to_string())?;
+
+        // Register the actual outpoint for reveal detection when available
+        if let (Some(txid), Some(vout)) = (txid, assigned_vout) {
+            let _ = db.register_transfer_outpoint(txid, vout, inscription_id);
+        }
+
+        // Lock the amount by reducing only the spendable balance
+        db.update_balance(sender, &op.tick.to_lowercase(), Delta::Debit(amt), Delta::Zero)?;
+
+        db.record_zrc20_event(
+            &op.tick.to_lowercase(),
+            height,
+            &serde_json::json!({
+                "op": "transfer_inscribe",
+                "tick": op.tick.to_lowercase(),
+                "amt": amt.to_string(),
+                "from": sender,
+                "to": null,
+                "txid": txid,
+                "inscription_id": inscription_id,
+                "height": height,
+            }),
+        )?;
+
+        Ok(())
+    }
+
+    fn handle_meta_inscribe(
+        &self,
+        db: &BlockWriter,
+        op: &Zrc20Operation,
+        inscription_id: &str,
+        sender: &str,
+        txid: Option<&str>,
+        height: u64,
+    ) -> Result<()> {
+        self.check_meta(db, op, sender)?;
+        let meta = serde_json::json!({
+            "name": op.name,
+            "icon": op.icon,
+            "description": op.description,
+            "inscription_id": inscription_id,
+        });
+        db.set_token_meta(&op.tick.to_lowercase(), &meta)?;
+        db.record_zrc20_event(
+            &op.tick.to_lowercase(),
+            height,
+            &serde_json::json!({
+                "op": "meta",
+                "tick": op.tick.to_lowercase(),
+                "from": sender,
+                "to": null,
+                "txid": txid,
+                "inscription_id": inscription_id,
+                "height": height,
+            }),
+        )?;
+        tracing::info!("Set metadata of token {} from {}", op.tick, inscription_id);
+        Ok(())
+    }
+
+    // Metadata is set once, by the address that deployed the token
+    fn check_meta(&self, state: &impl Zrc20State, op: &Zrc20Operation, sender: &str) -> Result<()> {
+        let token_info_str = state
+            .get_token_info(&op.tick.to_lowercase())?
+            .ok_or(anyhow::anyhow!("Token not found"))?;
+        let token_info: serde_json::Value = serde_json::from_str(&token_info_str)?;
+        if token_info["deployer"].as_str() != Some(sender) {
+            return Err(anyhow::anyhow!("Only the deployer can set token metadata"));
+        }
+        if !token_info["meta"].is_null() {
+            return Err(anyhow::anyhow!("Token metadata already set"));
+        }
+        Ok(())
+    }
+
+    // A deploy needs a max and a ticker nobody has deployed yet
+    fn check_deploy(&self, state: &impl Zrc20State, op: &Zrc20Operation) -> Result<()> {
+        op.max.as_ref().ok_or(anyhow::anyhow!("Missing max"))?;
+        if state.get_token_info(&op.tick.to_lowercase())?.is_some() {
+            return Err(anyhow::anyhow!("Token already exists"));
+        }
+        Ok(())
+    }
+
+    // What a mint credits, if it fits the per-mint limit, the remaining supply and any
+    // per-address throttling
+    fn check_mint(
+        &self,
+        state: &impl Zrc20State,
+        op: &Zrc20Operation,
+        minter: &str,
+        height: u64,
+    ) -> Result<MintGrant> {
         let amt_str = op.amt.as_ref().ok_or(anyhow::anyhow!("Missing amt"))?;
 
         // Pull token metadata so we can enforce deployment limits
-        let token_info_str = self
-            .db
+        let token_info_str = state
             .get_token_info(&op.tick.to_lowercase())?
             .ok_or(anyhow::anyhow!("Token not found"))?;
         let token_info: serde_json::Value = serde_json::from_str(&token_info_str)?;
 
-        let max: u128 = self.parse_amount(
+        // Self-mint tokens are minted only by the address that inscribed the deploy
+        if token_info["self_mint"] == "true" && token_info["deployer"].as_str() != Some(minter) {
+            return Err(anyhow::anyhow!(SELF_MINT_VIOLATION));
+        }
+
+        let max = self.parse_amount(
             token_info["max"].as_str().unwrap_or("0"),
             token_info["dec"].as_str().unwrap_or("18"),
         )?;
-        let lim: u128 = self.parse_amount(
+        let lim = self.parse_amount(
             token_info["lim"].as_str().unwrap_or("0"),
             token_info["dec"].as_str().unwrap_or("18"),
         )?;
-        let current_supply: u128 = token_info["supply"].as_str()
-            .and_then(|s| s.parse::<u128>().ok())
-            .unwrap_or(0);
-        let amt: u128 = self.parse_amount(amt_str, token_info["dec"].as_str().unwrap_or("18"))?;
+        let current_supply = match token_info["supply"].as_str() {
+            Some(supply) => Amount::parse_base_units(supply)?,
+            None => Amount::ZERO,
+        };
+        let amt = self.parse_amount(amt_str, token_info["dec"].as_str().unwrap_or("18"))?;
 
         // Ensure mint fits within per-address limit and total supply
         if amt > lim {
             return Err(anyhow::anyhow!("Mint amount exceeds limit"));
         }
 
-        if current_supply + amt > max {
+        let requested = amt;
+        let remaining = max.saturating_sub(current_supply);
+        let amt = if amt <= remaining {
+            amt
+        } else if !remaining.is_zero() && partial_mint_at(height) {
+            // The final mint takes whatever supply is left
+            remaining
+        } else {
             return Err(anyhow::anyhow!("Max supply exceeded"));
-        }
+        };
 
-        // Atomically bump supply and credit holder balance to avoid drift
-        self.db.mint_credit_atomic(&op.tick.to_lowercase(), minter, amt)?;
+        let limit_per_address = match token_info["limit_per_address"].as_str() {
+            Some(limit) => Some(self.parse_amount(limit, token_info["dec"].as_str().unwrap_or("18"))?),
+            None => None,
+        };
+        let blocks_between_mints = token_info["blocks_between_mints"]
+            .as_str()
+            .and_then(|b| b.parse::<u64>().ok());
+        if limit_per_address.is_none() && blocks_between_mints.is_none() {
+            return Ok(MintGrant {
+                amt,
+                requested,
+                throttled: false,
+            });
+        }
 
-        Ok(())
+        let record: Option<serde_json::Value> = match state.get_mint_record(&op.tick.to_lowercase(), minter)? {
+            Some(raw) => Some(serde_json::from_str(&raw)?),
+            None => None,
+        };
+        if let Some(limit) = limit_per_address {
+            let minted = record
+                .as_ref()
+                .and_then(|r| r["minted"].as_str())
+                .and_then(|m| Amount::parse_base_units(m).ok())
+                .unwrap_or_default();
+            if minted.checked_add(amt).is_none_or(|total| total > limit) {
+                return Err(anyhow::anyhow!("Mint exceeds per-address limit"));
+            }
+        }
+        if let (Some(blocks), Some(last)) = (
+            blocks_between_mints,
+            record.as_ref().and_then(|r| r["last_height"].as_u64()),
+        ) {
+            let next = last.saturating_add(blocks);
+            if height < next {
+                return Err(anyhow::anyhow!("Address minted too recently; next mint allowed at height {}", next));
+            }
+        }
+        Ok(MintGrant {
+            amt,
+            requested,
+            throttled: true,
+        })
     }
 
-    fn handle_transfer_inscribe(
-        &self,
-        op: &Zrc20Operation,
-        inscription_id: &str,
-        sender: &str,
-        txid: Option<&str>,
-        assigned_vout: Option<u32>,
-    ) -> Result<()> {
+    // Transfer amount in base units, if `sender` has that much available
+    fn check_transfer(&self, state: &impl Zrc20State, op: &Zrc20Operation, sender: &str) -> Result<Amount> {
         let amt_str = op.amt.as_ref().ok_or(anyhow::anyhow!("Missing amt"))?;
 
         // Normalize the requested transfer amount using token decimals
-        let token_info_str = self
-            .db
+        let token_info_str = state
             .get_token_info(&op.tick.to_lowercase())?
             .ok_or(anyhow::anyhow!("Token not found"))?;
         let token_info: serde_json::Value = serde_json::from_str(&token_info_str)?;
-        let amt: u128 = self.parse_amount(amt_str, token_info["dec"].as_str().unwrap_or("18"))?;
+        let amt = self.parse_amount(amt_str, token_info["dec"].as_str().unwrap_or("18"))?;
 
         // Require unlocked balance before staging the transfer
-        let balance = self.db.get_balance(sender, &op.tick.to_lowercase())?;
+        let balance = state.get_balance(sender, &op.tick.to_lowercase())?;
         if balance.available < amt {
             return Err(anyhow::anyhow!("Insufficient available balance"));
         }
-
-        // Record the intent so the reveal can settle it later
-        let transfer_data = serde_json::json!({
-            "tick": op.tick.to_lowercase(),
-            "amt": amt.to_string(),
-            "sender": sender
-        });
-
-        self.db
-            .create_transfer_inscription(inscription_id, &transfer_data.to_string())?;
-
-        // Register the actual outpoint for reveal detection when available
-        if let (Some(txid), Some(vout)) = (txid, assigned_vout) {
-            let _ = self.db.register_transfer_outpoint(txid, vout, inscription_id);
-        }
-
-        // Lock the amount by reducing only the spendable balance
-        self.db
-            .update_balance(sender, &op.tick.to_lowercase(), -(amt as i128), 0)?;
-
-        Ok(())
+        Ok(amt)
     }
 
-    fn handle_transfer_transfer(&self, inscription_id: &str, receiver: Option<&str>) -> Result<()> {
+    fn handle_transfer_transfer(
+        &self,
+        db: &BlockWriter,
+        inscription_id: &str,
+        destination: Destination,
+        txid: &str,
+        height: u64,
+    ) -> Result<()> {
         // Prevent double-settlement of a transfer inscription
-        if self.db.is_inscription_used(inscription_id)? {
+        if db.is_inscription_used(inscription_id)? {
             return Err(anyhow::anyhow!("Transfer inscription already used"));
         }
 
         // Load the staged transfer data
-        let transfer_data_str = self
-            .db
+        let transfer_data_str = db
             .get_transfer_inscription(inscription_id)?
             .ok_or(anyhow::anyhow!("Transfer inscription not found"))?;
         let transfer_data: serde_json::Value = serde_json::from_str(&transfer_data_str)?;
@@ -311,80 +761,155 @@ impl Zrc20Engine {
         let tick = transfer_data["tick"]
             .as_str()
             .ok_or(anyhow::anyhow!("Invalid tick"))?;
-        let amt = transfer_data["amt"]
-            .as_str()
-            .ok_or(anyhow::anyhow!("Invalid amount"))?
-            .parse::<u128>()?;
+        let amt = Amount::parse_base_units(
+            transfer_data["amt"]
+                .as_str()
+                .ok_or(anyhow::anyhow!("Invalid amount"))?,
+        )?;
         let sender = transfer_data["sender"]
             .as_str()
             .ok_or(anyhow::anyhow!("Invalid sender"))?;
 
-        // If no transparent receiver detected, treat as shielded burn
-        if receiver.is_none() {
-            // Burn: reduce sender's overall by amt; do not unlock available; add to burned tally
-            self.db.update_balance(sender, tick, 0, -(amt as i128))?;
-            self.db.add_burned(tick, amt)?;
-        } else {
-            let receiver = receiver.unwrap();
+        // Settle to the transparent receiver, or burn: the burn address is never credited
+        let receiver = match destination {
+            Destination::Address(receiver) => Some(receiver),
+            Destination::Burn(_) => None,
+        };
+        if let Some(receiver) = receiver {
             if receiver == sender {
                 // Unlock the funds if they ultimately returned to sender
-                self.db.update_balance(sender, tick, amt as i128, 0)?;
+                db.update_balance(sender, tick, Delta::Credit(amt), Delta::Zero)?;
             } else {
                 // Move value to the receiver and debit the sender
-                self.db.update_balance(sender, tick, 0, -(amt as i128))?;
-                self.db
-                    .update_balance(receiver, tick, amt as i128, amt as i128)?;
+                db.update_balance(sender, tick, Delta::Zero, Delta::Debit(amt))?;
+                db.update_balance(receiver, tick, Delta::Credit(amt), Delta::Credit(amt))?;
             }
+        } else {
+            // Burn: reduce sender's overall by amt; do not unlock available; add to burned tally
+            db.update_balance(sender, tick, Delta::Zero, Delta::Debit(amt))?;
+            db.add_burned(tick, amt)?;
         }
 
-        // Flag the inscription so reveal cannot replay
-        self.db.mark_inscription_used(inscription_id)?;
+        // Flag the inscription so reveal cannot replay; a spend back to the sender is a cancel
+        let cancelled = receiver == Some(sender);
+        if cancelled {
+            db.mark_inscription_cancelled(inscription_id)?;
+        } else {
+            db.mark_inscription_used(inscription_id)?;
+        }
+
+        let op = match receiver {
+            None => "burn",
+            Some(_) if cancelled => "cancel",
+            Some(_) => "transfer",
+        };
+        let mut entry = serde_json::json!({
+            "op": op,
+            "tick": tick,
+            "amt": amt.to_string(),
+            "from": sender,
+            "to": receiver,
+            "txid": txid,
+            "inscription_id": inscription_id,
+            "height": height,
+        });
+        if let Destination::Burn(reason) = destination {
+            entry["burn"] = serde_json::json!(reason);
+        }
+        db.record_zrc20_activity(tick, height, &entry)?;
 
         Ok(())
     }
 
-    /// Public entry to settle a staged transfer when the inscription is revealed (spent).
-    pub fn settle_transfer(&self, inscription_id: &str, receiver: Option<&str>) -> Result<()> {
-        self.handle_transfer_transfer(inscription_id, receiver)
+    /// Public entry to settle a staged transfer when the inscription is revealed (spent)
+    /// by `txid` at `height`.
+    pub fn settle_transfer(
+        &self,
+        db: &BlockWriter,
+        inscription_id: &str,
+        destination: Destination,
+        txid: &str,
+        height: u64,
+    ) -> Result<()> {
+        self.handle_transfer_transfer(db, inscription_id, destination, txid, height)
     }
 
     /// Parse amount string with decimals support using overflow-safe arithmetic.
-    fn parse_amount(&self, amount_str: &str, decimals: &str) -> Result<u128> {
-        let dec: u32 = decimals.parse().unwrap_or(18);
-        let scale = 10u128.pow(dec);
+    fn parse_amount(&self, amount_str: &str, decimals: &str) -> Result<Amount> {
+        Amount::parse(amount_str, decimals.parse().unwrap_or(18))
+    }
+}
 
-        let (whole_part, frac_part) = match amount_str.split_once('.') {
-            Some((whole, frac)) => (whole, frac),
-            None => (amount_str, ""),
-        };
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Outputs given as (type, address); nulldata outputs carry no address
+    fn tx(outputs: &[(&str, Option<&str>)]) -> TxResponse {
+        let vout: Vec<serde_json::Value> = outputs
+            .iter()
+            .enumerate()
+            .map(|(n, (kind, address))| {
+                serde_json::json!({
+                    "value": 0.0,
+                    "n": n,
+                    "scriptPubKey": {
+                        "hex": "",
+                        "asm": if *kind == "nulldata" { "OP_RETURN 1" } else { "" },
+                        "type": kind,
+                        "addresses": address.map(|a| vec![a]),
+                    }
+                })
+            })
+            .collect();
+        serde_json::from_value(serde_json::json!({ "txid": "00", "hex": "", "vin": [], "vout": vout })).unwrap()
+    }
 
-        let whole: u128 = if whole_part.is_empty() {
-            0
-        } else {
-            whole_part.parse::<u128>()?
-        };
+    #[test]
+    fn op_return_burns_only_from_activation() {
+        let reveal = tx(&[("nulldata", None), ("pubkeyhash", Some("t1after"))]);
+        assert_eq!(
+            transfer_destination(&reveal, BURN_ACTIVATION_HEIGHT - 1),
+            Destination::Address("t1after")
+        );
+        assert_eq!(
+            transfer_destination(&reveal, BURN_ACTIVATION_HEIGHT),
+            Destination::Burn("op_return")
+        );
+    }
 
-        let mut frac_string = frac_part.to_string();
-        if frac_string.len() > dec as usize {
-            return Err(anyhow::anyhow!("Too many decimal places"));
-        }
-        while frac_string.len() < dec as usize {
-            frac_string.push('0');
+    #[test]
+    fn first_transparent_address_is_credited() {
+        let reveal = tx(&[("pubkeyhash", Some("t1first")), ("nulldata", None), ("pubkeyhash", Some("t1second"))]);
+        for height in [0, BURN_ACTIVATION_HEIGHT] {
+            assert_eq!(transfer_destination(&reveal, height), Destination::Address("t1first"));
         }
+    }
 
-        let frac_value: u128 = if frac_string.is_empty() {
-            0
-        } else {
-            frac_string.parse::<u128>()?
-        };
+    #[test]
+    fn no_transparent_output_is_a_shielded_burn() {
+        assert_eq!(transfer_destination(&tx(&[]), 0), Destination::Burn("shielded"));
+        let unknown = tx(&[("nonstandard", None)]);
+        assert_eq!(transfer_destination(&unknown, BURN_ACTIVATION_HEIGHT), Destination::Burn("shielded"));
+    }
 
-        let whole_scaled = whole
-            .checked_mul(scale)
-            .ok_or_else(|| anyhow::anyhow!("Amount exceeds maximum representable value"))?;
-        let total = whole_scaled
-            .checked_add(frac_value)
-            .ok_or_else(|| anyhow::anyhow!("Amount exceeds maximum representable value"))?;
+    #[test]
+    fn designated_addresses_burn() {
+        let phase = BurnPhase {
+            height: 0,
+            op_return: true,
+            addresses: &["t1burn"],
+        };
+        let reveal = tx(&[("pubkeyhash", Some("t1burn")), ("pubkeyhash", Some("t1other"))]);
+        assert_eq!(destination_under(&reveal, &phase), Destination::Burn("burn_address"));
+        assert_eq!(transfer_destination(&reveal, BURN_ACTIVATION_HEIGHT), Destination::Address("t1burn"));
+    }
 
-        Ok(total)
+    #[test]
+    fn phases_are_ordered() {
+        assert_eq!(BURN_PHASES[0].height, 0);
+        assert!(BURN_PHASES.windows(2).all(|w| w[0].height < w[1].height));
+        assert!(!burn_phase(BURN_ACTIVATION_HEIGHT - 1).op_return);
+        assert!(burn_phase(u64::MAX).op_return);
     }
 }
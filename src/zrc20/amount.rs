@@ -0,0 +1,227 @@
+//! Fixed-point ZRC-20 quantities.
+//!
+//! Every amount is kept as an unsigned count of base units, `10^-dec` of a token,
+//! so a token with `dec = 18` counts in units of 0.000000000000000001. Operations
+//! spell amounts as decimal strings (`"21000000"`, `"0.5"`); `Amount::parse` is
+//! the one place they turn into base units. Arithmetic is checked, and amounts
+//! serialize as base-unit strings so JSON clients never lose precision; only the
+//! stored balance rows keep their original integer form.
+
+use anyhow::Result;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+
+/// Most decimals a token may declare.
+pub const MAX_DECIMALS: u32 = 18;
+
+/// A non-negative quantity in base units.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Amount(u128);
+
+/// Signed change applied to a balance.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Delta {
+    Zero,
+    Credit(Amount),
+    Debit(Amount),
+}
+
+impl Amount {
+    pub const ZERO: Amount = Amount(0);
+
+    pub const fn base_units(self) -> u128 {
+        self.0
+    }
+
+    pub const fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Parse an operation amount such as `"1000"` or `"0.25"` for a token with `dec`
+    /// decimals. Only ASCII digits with at most one inner dot are accepted, the
+    /// fraction may not be longer than `dec`, and the result must fit in base units.
+    pub fn parse(value: &str, dec: u32) -> Result<Self> {
+        if value.is_empty() {
+            return Err(anyhow::anyhow!("Empty numeric string"));
+        }
+        if !value.bytes().all(|b| b.is_ascii_digit() || b == b'.') {
+            return Err(anyhow::anyhow!("Invalid characters in numeric string"));
+        }
+        let (whole, frac) = match value.split_once('.') {
+            Some((whole, frac)) => (whole, frac),
+            None => (value, ""),
+        };
+        if frac.contains('.') {
+            return Err(anyhow::anyhow!("Multiple dots in numeric string"));
+        }
+        if whole.is_empty() || (value.contains('.') && frac.is_empty()) {
+            return Err(anyhow::anyhow!("Numeric string cannot start/end with dot"));
+        }
+        if dec > MAX_DECIMALS {
+            return Err(anyhow::anyhow!("Decimals must be 0-{}", MAX_DECIMALS));
+        }
+        if frac.len() > dec as usize {
+            return Err(anyhow::anyhow!("Too many decimal places"));
+        }
+
+        let too_large = || anyhow::anyhow!("Amount exceeds the largest representable value");
+        // Digits only from here on, so a failed parse means the value is too large
+        let whole: u128 = whole.parse().map_err(|_| too_large())?;
+        let frac_units: u128 = if frac.is_empty() {
+            0
+        } else {
+            let padded = format!("{:0<width$}", frac, width = dec as usize);
+            padded.parse().map_err(|_| too_large())?
+        };
+        whole
+            .checked_mul(10u128.pow(dec))
+            .and_then(|scaled| scaled.checked_add(frac_units))
+            .map(Amount)
+            .ok_or_else(too_large)
+    }
+
+    /// Parse a stored base-unit count such as a token's `supply`.
+    pub fn parse_base_units(value: &str) -> Result<Self> {
+        if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(anyhow::anyhow!("Invalid base-unit amount {:?}", value));
+        }
+        value
+            .parse()
+            .map(Amount)
+            .map_err(|_| anyhow::anyhow!("Base-unit amount {:?} is out of range", value))
+    }
+
+    pub fn checked_add(self, other: Amount) -> Option<Amount> {
+        self.0.checked_add(other.0).map(Amount)
+    }
+
+    pub fn checked_sub(self, other: Amount) -> Option<Amount> {
+        self.0.checked_sub(other.0).map(Amount)
+    }
+
+    pub fn saturating_sub(self, other: Amount) -> Amount {
+        Amount(self.0.saturating_sub(other.0))
+    }
+
+    /// `None` when a debit exceeds the amount or a credit overflows.
+    pub fn apply(self, delta: Delta) -> Option<Amount> {
+        match delta {
+            Delta::Zero => Some(self),
+            Delta::Credit(amt) => self.checked_add(amt),
+            Delta::Debit(amt) => self.checked_sub(amt),
+        }
+    }
+
+    /// Whole tokens with the fraction, trailing zeros trimmed: `1500000` at `dec = 6`
+    /// is `"1.5"`.
+    pub fn display(self, dec: u32) -> String {
+        let Some(scale) = 10u128.checked_pow(dec) else {
+            return self.0.to_string();
+        };
+        let (whole, frac) = (self.0 / scale, self.0 % scale);
+        if frac == 0 {
+            return whole.to_string();
+        }
+        let frac = format!("{:0width$}", frac, width = dec as usize);
+        format!("{}.{}", whole, frac.trim_end_matches('0'))
+    }
+}
+
+/// Base units, as stored.
+impl fmt::Display for Amount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl Serialize for Amount {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Amount {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = String::deserialize(deserializer)?;
+        Amount::parse_base_units(&raw).map_err(de::Error::custom)
+    }
+}
+
+/// Stored `Balance` rows keep their amounts as plain JSON integers; use with
+/// `#[serde(with = "amount::as_number")]`.
+pub mod as_number {
+    use super::Amount;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(amount: &Amount, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u128(amount.0)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Amount, D::Error> {
+        u128::deserialize(deserializer).map(Amount)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAX: Amount = Amount(u128::MAX);
+    const ONE: Amount = Amount(1);
+
+    // `digits` as a token amount: the last `dec` digits become the fraction
+    fn with_point(digits: &str, dec: u32) -> String {
+        let (whole, frac) = digits.split_at(digits.len() - dec as usize);
+        if frac.is_empty() {
+            whole.to_string()
+        } else {
+            format!("{}.{}", whole, frac)
+        }
+    }
+
+    #[test]
+    fn parse_reaches_u128_max_at_every_decimal_count() {
+        let max = u128::MAX.to_string();
+        // u128::MAX + 1, which ends in ...455 + 1
+        let over = format!("{}6", &max[..max.len() - 1]);
+        for dec in 0..=MAX_DECIMALS {
+            assert_eq!(Amount::parse(&with_point(&max, dec), dec).unwrap(), MAX, "dec {}", dec);
+            assert!(Amount::parse(&with_point(&over, dec), dec).is_err(), "dec {}", dec);
+        }
+    }
+
+    #[test]
+    fn parse_rejects_a_whole_part_that_only_overflows_once_scaled() {
+        for dec in 1..=MAX_DECIMALS {
+            let whole = u128::MAX / 10u128.pow(dec) + 1;
+            assert!(Amount::parse(&whole.to_string(), dec).is_err(), "dec {}", dec);
+        }
+    }
+
+    #[test]
+    fn checked_add_stops_at_u128_max() {
+        assert_eq!(Amount(u128::MAX - 1).checked_add(ONE), Some(MAX));
+        assert_eq!(MAX.checked_add(Amount::ZERO), Some(MAX));
+        assert_eq!(MAX.checked_add(ONE), None);
+        assert_eq!(MAX.checked_add(MAX), None);
+    }
+
+    #[test]
+    fn saturating_sub_floors_at_zero() {
+        assert_eq!(MAX.saturating_sub(ONE), Amount(u128::MAX - 1));
+        assert_eq!(MAX.saturating_sub(MAX), Amount::ZERO);
+        assert_eq!(Amount::ZERO.saturating_sub(MAX), Amount::ZERO);
+        assert_eq!(ONE.saturating_sub(MAX), Amount::ZERO);
+    }
+
+    #[test]
+    fn delta_application_at_u128_max() {
+        assert_eq!(MAX.apply(Delta::Zero), Some(MAX));
+        assert_eq!(MAX.apply(Delta::Credit(Amount::ZERO)), Some(MAX));
+        assert_eq!(MAX.apply(Delta::Credit(ONE)), None);
+        assert_eq!(Amount(u128::MAX - 1).apply(Delta::Credit(ONE)), Some(MAX));
+        assert_eq!(MAX.apply(Delta::Debit(MAX)), Some(Amount::ZERO));
+        assert_eq!(Amount::ZERO.apply(Delta::Credit(MAX)), Some(MAX));
+        assert_eq!(Amount(u128::MAX - 1).apply(Delta::Debit(MAX)), None);
+    }
+}
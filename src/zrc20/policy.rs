@@ -0,0 +1,102 @@
+//! Deploy policy: which tickers may be deployed, and by whom.
+//!
+//! `ZRC20_DEPLOY_POLICY` holds a JSON object for private deployments that need to
+//! keep abusive tickers out or limit deploys to known addresses:
+//!
+//! ```json
+//! {"deny_ticks": ["scam", "rug"], "deny_substrings": ["xxx"],
+//!  "allow_deployers": ["t1Issuer..."], "deny_deployers": ["t1Spam..."]}
+//! ```
+//!
+//! Every field is optional; an empty `allow_deployers` lets anyone deploy. Tickers
+//! are compared lowercased. A deploy the policy turns away is rejected like
+//! any invalid deploy and recorded for audit. Unlike the ticker rules this is not
+//! shared by public indexers: a node with a policy diverges from those without one,
+//! and changing the policy only affects deploys indexed afterwards.
+
+use anyhow::Result;
+use serde::Deserialize;
+use std::sync::OnceLock;
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DeployPolicy {
+    #[serde(default)]
+    pub deny_ticks: Vec<String>,
+    /// Tickers containing any of these are denied.
+    #[serde(default)]
+    pub deny_substrings: Vec<String>,
+    /// When non-empty, only these addresses may deploy.
+    #[serde(default)]
+    pub allow_deployers: Vec<String>,
+    #[serde(default)]
+    pub deny_deployers: Vec<String>,
+}
+
+impl DeployPolicy {
+    pub fn from_env() -> Result<Self> {
+        match std::env::var("ZRC20_DEPLOY_POLICY") {
+            Ok(raw) if !raw.trim().is_empty() => Self::parse(&raw),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    fn parse(raw: &str) -> Result<Self> {
+        let mut policy: DeployPolicy = serde_json::from_str(raw)
+            .map_err(|e| anyhow::anyhow!("ZRC20_DEPLOY_POLICY is not a valid policy: {}", e))?;
+        for tick in policy.deny_ticks.iter_mut().chain(policy.deny_substrings.iter_mut()) {
+            *tick = tick.to_lowercase();
+        }
+        if policy.deny_substrings.iter().any(|s| s.is_empty()) {
+            return Err(anyhow::anyhow!("ZRC20_DEPLOY_POLICY deny_substrings may not be empty"));
+        }
+        Ok(policy)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.deny_ticks.is_empty()
+            && self.deny_substrings.is_empty()
+            && self.allow_deployers.is_empty()
+            && self.deny_deployers.is_empty()
+    }
+
+    /// Why `deployer` may not deploy the normalized `tick`, if the policy forbids it.
+    pub fn check(&self, tick: &str, deployer: &str) -> Option<&'static str> {
+        if self.deny_ticks.iter().any(|t| t == tick) {
+            return Some("ticker_denied");
+        }
+        if self.deny_substrings.iter().any(|s| tick.contains(s.as_str())) {
+            return Some("ticker_pattern_denied");
+        }
+        if self.deny_deployers.iter().any(|a| a == deployer) {
+            return Some("deployer_denied");
+        }
+        if !self.allow_deployers.is_empty() && !self.allow_deployers.iter().any(|a| a == deployer) {
+            return Some("deployer_not_allowed");
+        }
+        None
+    }
+}
+
+static POLICY: OnceLock<DeployPolicy> = OnceLock::new();
+
+/// Read `ZRC20_DEPLOY_POLICY` once at startup, failing on a malformed policy
+/// rather than indexing without it.
+pub fn load() -> Result<()> {
+    let policy = DeployPolicy::from_env()?;
+    if !policy.is_empty() {
+        tracing::info!(
+            "ZRC-20 deploy policy: {} denied tickers, {} denied patterns, {} allowed and {} denied deployers",
+            policy.deny_ticks.len(),
+            policy.deny_substrings.len(),
+            policy.allow_deployers.len(),
+            policy.deny_deployers.len()
+        );
+    }
+    let _ = POLICY.set(policy);
+    Ok(())
+}
+
+pub fn policy() -> &'static DeployPolicy {
+    POLICY.get_or_init(DeployPolicy::default)
+}
@@ -0,0 +1,138 @@
+//! `zord zrc20 rebuild --tick <tick>`: recompute one token from its event journal.
+//!
+//! The token's events feed records every operation that was applied to it, in order.
+//! A rebuild wipes the token's balances, supply, burns, mint records, history and
+//! transfer inscriptions, then runs each recorded inscription through the current
+//! rules again and re-settles each recorded reveal, all in one write transaction.
+//! This repairs a token after an accounting bug without reindexing the chain.
+//!
+//! Operations the original index rejected are not in the journal, so a fix that
+//! would accept them still needs a reindex. Balance checkpoints, roots and checksums
+//! of blocks below the tip keep their pre-rebuild values, and their undo records are
+//! dropped, so the index cannot roll back below the rebuild height.
+
+use super::{Destination, Zrc20Engine};
+use crate::db::Db;
+use anyhow::Result;
+
+/// Outcome of a rebuild.
+#[derive(Debug, serde::Serialize)]
+pub struct RebuildReport {
+    pub tick: String,
+    /// Journal rows replayed.
+    pub operations: usize,
+    pub applied: usize,
+    /// Rows the rules now reject, with the reason.
+    pub rejected: Vec<String>,
+}
+
+// Burn reasons recorded on history rows
+fn burn_reason(raw: &str) -> &'static str {
+    match raw {
+        "op_return" => "op_return",
+        "burn_address" => "burn_address",
+        _ => "shielded",
+    }
+}
+
+/// Replay `tick` from its event journal. The indexer must be stopped.
+pub fn rebuild(db: &Db, tick: &str) -> Result<RebuildReport> {
+    let tick = tick.to_lowercase();
+    let mut journal = db.list_zrc20_events(&tick, 0, usize::MAX, None)?;
+    if journal.is_empty() {
+        return Err(anyhow::anyhow!("No recorded operations for token {}", tick));
+    }
+    // Stored newest first
+    journal.reverse();
+
+    let writer = db.replay_writer()?;
+    let states = writer.clear_zrc20_token(&tick)?;
+    let mut report = RebuildReport {
+        tick: tick.clone(),
+        operations: journal.len(),
+        applied: 0,
+        rejected: Vec::new(),
+    };
+    for (key, raw) in &journal {
+        let row: serde_json::Value = serde_json::from_str(raw)?;
+        let position = row["tx_index"]
+            .as_u64()
+            .zip(row["input_index"].as_u64())
+            .map(|(tx, input)| (tx as u32, input as u32));
+        writer.set_position(position);
+        match replay_row(db, &writer, &row) {
+            Ok(()) => report.applied += 1,
+            Err(e) => {
+                tracing::warn!("Rebuild of {} rejected {}: {}", tick, key, e);
+                report.rejected.push(format!("{}: {}", key, e));
+            }
+        }
+    }
+
+    // Reveals that retired an inscription without settling it leave no journal row
+    for (id, state) in &states {
+        if state != "unused" && !writer.is_inscription_used(id)? {
+            writer.mark_inscription_used(id)?;
+            if let Some((txid, vout)) = inscription_outpoint(db, id)? {
+                writer.remove_transfer_outpoint(&txid, vout)?;
+            }
+        }
+    }
+    writer.finish_replay()?;
+    Ok(report)
+}
+
+fn replay_row(db: &Db, writer: &crate::db::BlockWriter, row: &serde_json::Value) -> Result<()> {
+    let op = row["op"].as_str().unwrap_or("");
+    let id = row["inscription_id"]
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("Row has no inscription id"))?;
+    let height = row["height"].as_u64().ok_or_else(|| anyhow::anyhow!("Row has no height"))?;
+    let txid = row["txid"].as_str();
+    match op {
+        "deploy" | "mint" | "transfer_inscribe" | "meta" => {
+            let meta: serde_json::Value = match db.get_inscription(id)? {
+                Some(raw) => serde_json::from_str(&raw)?,
+                None => return Err(anyhow::anyhow!("Inscription {} not found", id)),
+            };
+            let content = db
+                .get_content(id)?
+                .ok_or_else(|| anyhow::anyhow!("Content of {} not found", id))?;
+            Zrc20Engine.process(
+                writer,
+                "inscribe",
+                id,
+                meta["sender"].as_str().unwrap_or(""),
+                meta["receiver"].as_str(),
+                &String::from_utf8_lossy(&content),
+                txid,
+                meta["vout"].as_u64().map(|v| v as u32),
+                height,
+            )
+        }
+        "transfer" | "cancel" | "burn" => {
+            let destination = match row["burn"].as_str() {
+                Some(reason) => Destination::Burn(burn_reason(reason)),
+                None => Destination::from_receiver(row["to"].as_str()),
+            };
+            let settled = Zrc20Engine.settle_transfer(writer, id, destination, txid.unwrap_or(""), height);
+            if let Some((txid, vout)) = inscription_outpoint(db, id)? {
+                writer.remove_transfer_outpoint(&txid, vout)?;
+            }
+            settled
+        }
+        other => Err(anyhow::anyhow!("Unknown operation {:?}", other)),
+    }
+}
+
+// Output that carried the inscription and registered it for reveal detection
+fn inscription_outpoint(db: &Db, id: &str) -> Result<Option<(String, u32)>> {
+    let Some(raw) = db.get_inscription(id)? else {
+        return Ok(None);
+    };
+    let meta: serde_json::Value = serde_json::from_str(&raw)?;
+    Ok(meta["txid"]
+        .as_str()
+        .zip(meta["vout"].as_u64())
+        .map(|(txid, vout)| (txid.to_string(), vout as u32)))
+}
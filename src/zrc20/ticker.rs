@@ -0,0 +1,176 @@
+//! Ticker policy: which `tick` values a ZRC-20 operation may use, by height.
+//!
+//! `ZRC20_TICKER_RULES` holds a JSON array of phases, each taking effect at its
+//! `height` and lasting until the next one:
+//!
+//! ```json
+//! [{"height": 0},
+//!  {"height": 3100000, "max_bytes": 16, "nfc": true, "emoji": false, "reserved": ["zec", "zord"]}]
+//! ```
+//!
+//! Omitted fields keep the original rules: 4-5 bytes, lowercase only, emoji
+//! allowed, nothing reserved. This is consensus, so every indexer must run the
+//! same phases and changing a past phase needs a reindex.
+
+use anyhow::Result;
+use icu_normalizer::ComposingNormalizerBorrowed;
+use icu_properties::props::{ExtendedPictographic, RegionalIndicator};
+use icu_properties::CodePointSetData;
+use serde::Deserialize;
+use std::sync::OnceLock;
+
+/// Ticker rules in force from `height` on.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TickerPhase {
+    #[serde(default)]
+    pub height: u64,
+    /// Allowed length of the normalized ticker in UTF-8 bytes.
+    #[serde(default = "default_min_bytes")]
+    pub min_bytes: usize,
+    #[serde(default = "default_max_bytes")]
+    pub max_bytes: usize,
+    /// Normalize to Unicode NFC after lowercasing, so composed and decomposed
+    /// spellings name the same token.
+    #[serde(default)]
+    pub nfc: bool,
+    #[serde(default = "default_emoji")]
+    pub emoji: bool,
+    /// Tickers nobody may deploy, compared after normalization. Tokens deployed
+    /// before the phase keep working.
+    #[serde(default)]
+    pub reserved: Vec<String>,
+}
+
+fn default_min_bytes() -> usize {
+    4
+}
+
+fn default_max_bytes() -> usize {
+    5
+}
+
+fn default_emoji() -> bool {
+    true
+}
+
+impl Default for TickerPhase {
+    fn default() -> Self {
+        TickerPhase {
+            height: 0,
+            min_bytes: default_min_bytes(),
+            max_bytes: default_max_bytes(),
+            nfc: false,
+            emoji: default_emoji(),
+            reserved: Vec::new(),
+        }
+    }
+}
+
+impl TickerPhase {
+    /// Canonical form of `raw` under this phase; the key the token is stored under.
+    pub fn normalize(&self, raw: &str) -> String {
+        let lower = raw.to_lowercase();
+        if self.nfc {
+            ComposingNormalizerBorrowed::new_nfc().normalize(&lower).into_owned()
+        } else {
+            lower
+        }
+    }
+
+    /// Check an already normalized ticker against the length and emoji rules.
+    pub fn check(&self, tick: &str) -> Result<()> {
+        let bytes = tick.len();
+        if !(self.min_bytes..=self.max_bytes).contains(&bytes) {
+            return Err(anyhow::anyhow!(
+                "Ticker must be {}-{} bytes",
+                self.min_bytes,
+                self.max_bytes
+            ));
+        }
+        if !self.emoji && tick.chars().any(is_emoji) {
+            return Err(anyhow::anyhow!("Emoji are not allowed in tickers"));
+        }
+        Ok(())
+    }
+
+    pub fn is_reserved(&self, tick: &str) -> bool {
+        self.reserved.iter().any(|r| self.normalize(r) == tick)
+    }
+}
+
+fn is_emoji(c: char) -> bool {
+    CodePointSetData::new::<ExtendedPictographic>().contains(c)
+        || CodePointSetData::new::<RegionalIndicator>().contains(c)
+}
+
+/// All phases, ordered by activation height.
+#[derive(Clone, Debug)]
+pub struct TickerRules {
+    phases: Vec<TickerPhase>,
+}
+
+impl Default for TickerRules {
+    fn default() -> Self {
+        TickerRules {
+            phases: vec![TickerPhase::default()],
+        }
+    }
+}
+
+impl TickerRules {
+    pub fn from_env() -> Result<Self> {
+        match std::env::var("ZRC20_TICKER_RULES") {
+            Ok(raw) if !raw.trim().is_empty() => Self::parse(&raw),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    fn parse(raw: &str) -> Result<Self> {
+        let mut phases: Vec<TickerPhase> = serde_json::from_str(raw)
+            .map_err(|e| anyhow::anyhow!("ZRC20_TICKER_RULES is not a valid phase list: {}", e))?;
+        if phases.is_empty() {
+            return Err(anyhow::anyhow!("ZRC20_TICKER_RULES needs at least one phase"));
+        }
+        for phase in &phases {
+            if phase.min_bytes == 0 || phase.min_bytes > phase.max_bytes {
+                return Err(anyhow::anyhow!(
+                    "ZRC20_TICKER_RULES phase at height {} has an empty byte range",
+                    phase.height
+                ));
+            }
+        }
+        phases.sort_by_key(|p| p.height);
+        if phases[0].height > 0 {
+            // Before the first configured phase the original rules apply
+            phases.insert(0, TickerPhase::default());
+        }
+        Ok(TickerRules { phases })
+    }
+
+    /// The phase in force at `height`.
+    pub fn at(&self, height: u64) -> &TickerPhase {
+        self.phases
+            .iter()
+            .rev()
+            .find(|p| p.height <= height)
+            .unwrap_or(&self.phases[0])
+    }
+}
+
+static RULES: OnceLock<TickerRules> = OnceLock::new();
+
+/// Read `ZRC20_TICKER_RULES` once at startup, failing on a malformed policy
+/// rather than indexing under the wrong one.
+pub fn load() -> Result<()> {
+    let rules = TickerRules::from_env()?;
+    if rules.phases.len() > 1 {
+        tracing::info!("ZRC-20 ticker rules: {} phases", rules.phases.len());
+    }
+    let _ = RULES.set(rules);
+    Ok(())
+}
+
+pub fn rules() -> &'static TickerRules {
+    RULES.get_or_init(TickerRules::default)
+}
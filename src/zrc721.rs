@@ -1,6 +1,8 @@
-use crate::db::Db;
+use crate::db::BlockWriter;
+use crate::rpc::TxResponse;
 use anyhow::Result;
 use serde::Deserialize;
+use sha2::{Digest, Sha256};
 
 #[derive(Debug, Deserialize)]
 struct Zrc721Operation {
@@ -17,29 +19,101 @@ struct Zrc721Operation {
     meta: Option<serde_json::Value>, // string CID or object; we store as JSON
     #[serde(default)]
     royalty: Option<String>,
+    // Where royalties are paid; the deployer unless set
+    #[serde(default)]
+    royalty_address: Option<String>,
+    #[serde(default)]
+    description: Option<String>,
+    // Launch phases: height public minting opens at, and who may mint before it
+    #[serde(default)]
+    mint_start: Option<String>,
+    // Inscription id of the allowed addresses
+    #[serde(default)]
+    allowlist: Option<String>,
+    // Merkle root over the allowed addresses; mints prove membership with `proof`
+    #[serde(default)]
+    allowlist_root: Option<String>,
+    #[serde(default)]
+    proof: Option<Vec<String>>,
     #[serde(default)]
     id: Option<String>,
+    // Batch mint: a list of ids or an inclusive "<first>-<last>" range
+    #[serde(default)]
+    ids: Option<TokenIds>,
     #[serde(default)]
     to: Option<String>,
 }
 
-pub struct Zrc721Engine {
-    db: Db,
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum TokenIds {
+    List(Vec<String>),
+    Range(String),
 }
 
-impl Zrc721Engine {
-    pub fn new(db: Db) -> Self {
-        Self { db }
+/// Most token ids one mint inscription may carry.
+const MAX_BATCH_MINT: usize = 1000;
+/// Most addresses an allowlist inscription may name.
+const MAX_ALLOWLIST: usize = 10_000;
+/// Longest collection description, in characters.
+const MAX_DESCRIPTION: usize = 1000;
+
+impl TokenIds {
+    fn expand(&self) -> Result<Vec<String>> {
+        let ids = match self {
+            TokenIds::List(ids) => ids.clone(),
+            TokenIds::Range(range) => {
+                let (first, last) = range
+                    .split_once('-')
+                    .ok_or(anyhow::anyhow!("Range must be \"<first>-<last>\""))?;
+                let parse = |n: &str| parse_token_number(n).map_err(|e| anyhow::anyhow!("Invalid range bound: {}", e));
+                let (first, last) = (parse(first)?, parse(last)?);
+                if first > last {
+                    return Err(anyhow::anyhow!("Range is empty"));
+                }
+                if last - first >= MAX_BATCH_MINT as u128 {
+                    return Err(anyhow::anyhow!("At most {} tokens per mint", MAX_BATCH_MINT));
+                }
+                (first..=last).map(|id| id.to_string()).collect()
+            }
+        };
+        if ids.is_empty() {
+            return Err(anyhow::anyhow!("No token ids"));
+        }
+        if ids.len() > MAX_BATCH_MINT {
+            return Err(anyhow::anyhow!("At most {} tokens per mint", MAX_BATCH_MINT));
+        }
+        Ok(ids)
     }
+}
+
+/// Parse a token id or supply: decimal digits without leading zeros that fit in a
+/// `u128`. Ids are compared as numbers, so "07" would otherwise name token 7 twice.
+pub fn parse_token_number(raw: &str) -> Result<u128> {
+    if raw.is_empty() || !raw.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(anyhow::anyhow!("{:?} is not a decimal integer", raw));
+    }
+    if raw.len() > 1 && raw.starts_with('0') {
+        return Err(anyhow::anyhow!("{:?} has leading zeros", raw));
+    }
+    raw.parse::<u128>()
+        .map_err(|_| anyhow::anyhow!("{:?} is out of range", raw))
+}
+
+pub struct Zrc721Engine;
 
+impl Zrc721Engine {
+    #[allow(clippy::too_many_arguments)]
     pub fn process(
         &self,
+        db: &BlockWriter,
         event_type: &str,
         inscription_id: &str,
         sender: &str,
         content: &str,
         txid: Option<&str>,
         assigned_vout: Option<u32>,
+        height: u64,
     ) -> Result<()> {
         if event_type != "inscribe" {
             return Ok(());
@@ -51,14 +125,17 @@ impl Zrc721Engine {
         }
 
         match op.op.as_str() {
-            "deploy" => self.handle_deploy(&op, inscription_id, sender),
-            "mint" => self.handle_mint(&op, inscription_id, sender, txid, assigned_vout),
+            "deploy" => self.handle_deploy(db, &op, inscription_id, sender),
+            "mint" => self.handle_mint(db, &op, inscription_id, sender, txid, assigned_vout, height),
+            "transfer" => self.handle_transfer(db, &op, inscription_id, sender, txid, height),
+            "update" => self.handle_update(db, &op, inscription_id, sender, height),
             _ => Err(anyhow::anyhow!("Unsupported op")),
         }
     }
 
     fn handle_deploy(
         &self,
+        db: &BlockWriter,
         op: &Zrc721Operation,
         inscription_id: &str,
         deployer: &str,
@@ -74,31 +151,200 @@ impl Zrc721Engine {
             .supply
             .as_ref()
             .ok_or(anyhow::anyhow!("Missing supply"))?;
+        if parse_token_number(supply).map_err(|e| anyhow::anyhow!("Invalid supply: {}", e))? == 0 {
+            return Err(anyhow::anyhow!("Supply must be at least 1"));
+        }
 
         // meta may be a string (CID) or JSON; store as JSON string or object
-        let meta = op.meta.clone().unwrap_or_else(|| serde_json::json!(null));
+        let meta = op.meta.clone().unwrap_or(serde_json::Value::Null);
         let royalty = op.royalty.clone().unwrap_or_default();
 
-        let payload = serde_json::json!({
+        check_mutable_fields(op)?;
+        if let Some(start) = &op.mint_start {
+            if start.is_empty() || !start.bytes().all(|b| b.is_ascii_digit()) || start.parse::<u64>().is_err() {
+                return Err(anyhow::anyhow!("mint_start must be a block height"));
+            }
+        }
+        let allowed = match (&op.allowlist, &op.allowlist_root) {
+            (Some(_), Some(_)) => return Err(anyhow::anyhow!("Use either allowlist or allowlist_root")),
+            (Some(list_id), None) => Some(read_allowlist(db, list_id)?),
+            (None, Some(root)) => {
+                if root.len() != 64 || hex::decode(root).is_err() {
+                    return Err(anyhow::anyhow!("allowlist_root must be 32 bytes of hex"));
+                }
+                None
+            }
+            (None, None) => None,
+        };
+
+        let mut payload = serde_json::json!({
             "collection": tick,
             "supply": supply,
             "meta": meta,
             "royalty": royalty,
             "minted": 0,
+            "owners": 0,
             "deployer": deployer,
             "inscription_id": inscription_id
         });
+        if let Some(start) = &op.mint_start {
+            payload["mint_start"] = serde_json::json!(start);
+        }
+        if let Some(address) = &op.royalty_address {
+            payload["royalty_address"] = serde_json::json!(address);
+        }
+        if let Some(description) = &op.description {
+            payload["description"] = serde_json::json!(description);
+        }
+        if let (Some(list_id), Some(addresses)) = (&op.allowlist, &allowed) {
+            payload["allowlist"] = serde_json::json!(list_id);
+            payload["allowlist_size"] = serde_json::json!(addresses.len());
+        }
+        if let Some(root) = &op.allowlist_root {
+            payload["allowlist_root"] = serde_json::json!(root.to_lowercase());
+        }
+
+        db.register_zrc721_collection(&tick, &payload)?;
+        if let Some(addresses) = allowed {
+            db.register_zrc721_allowlist(&tick, &addresses)?;
+        }
+        Ok(())
+    }
+
+    // The deployer amends the collection's meta, royalty address or description; every
+    // change is logged with the value it replaced
+    fn handle_update(
+        &self,
+        db: &BlockWriter,
+        op: &Zrc721Operation,
+        inscription_id: &str,
+        sender: &str,
+        height: u64,
+    ) -> Result<()> {
+        let tick = op
+            .tick
+            .as_ref()
+            .or(op.collection.as_ref())
+            .ok_or(anyhow::anyhow!("Missing collection/tick"))?
+            .to_lowercase();
+        let fixed = op.supply.is_some()
+            || op.royalty.is_some()
+            || op.mint_start.is_some()
+            || op.allowlist.is_some()
+            || op.allowlist_root.is_some();
+        if fixed {
+            return Err(anyhow::anyhow!("Only meta, royalty_address and description can be updated"));
+        }
+        check_mutable_fields(op)?;
+        let mut info = db
+            .get_zrc721_collection(&tick)?
+            .ok_or(anyhow::anyhow!("Collection not found"))?;
+        if info["deployer"].as_str() != Some(sender) {
+            return Err(anyhow::anyhow!("Only the deployer can update a collection"));
+        }
 
-        self.db.register_zrc721_collection(&tick, &payload)
+        let proposed = [
+            ("meta", op.meta.clone()),
+            ("royalty_address", op.royalty_address.clone().map(serde_json::Value::from)),
+            ("description", op.description.clone().map(serde_json::Value::from)),
+        ];
+        let mut changes = serde_json::Map::new();
+        for (field, value) in proposed {
+            let Some(value) = value else {
+                continue;
+            };
+            if info[field] != value {
+                changes.insert(field.to_string(), serde_json::json!({ "from": info[field], "to": value }));
+                info[field] = value;
+            }
+        }
+        if changes.is_empty() {
+            return Err(anyhow::anyhow!("Update changes nothing"));
+        }
+        db.amend_zrc721_collection(&tick, &info, &serde_json::Value::Object(changes), inscription_id, height)
     }
 
+    // Before `mint_start`, or always when a collection has an allowlist but no start,
+    // only allowlisted senders may mint
+    fn check_mint_phase(&self, db: &BlockWriter, tick: &str, op: &Zrc721Operation, sender: &str, height: u64) -> Result<()> {
+        let collection = db
+            .get_zrc721_collection(tick)?
+            .ok_or(anyhow::anyhow!("Collection not found"))?;
+        let start = collection["mint_start"].as_str().and_then(|s| s.parse::<u64>().ok());
+        let root = collection["allowlist_root"].as_str();
+        let has_list = collection["allowlist"].is_string() || root.is_some();
+        let public = match start {
+            Some(start) => height >= start,
+            None => !has_list,
+        };
+        if public {
+            return Ok(());
+        }
+        let allowed = match root {
+            Some(root) => op
+                .proof
+                .as_deref()
+                .is_some_and(|proof| verify_allowlist_proof(root, sender, proof)),
+            None if has_list => db.is_zrc721_allowlisted(tick, sender)?,
+            None => false,
+        };
+        match (allowed, has_list, start) {
+            (true, _, _) => Ok(()),
+            (false, false, Some(start)) => Err(anyhow::anyhow!("Minting opens at height {}", start)),
+            (false, _, _) => Err(anyhow::anyhow!("Sender is not on the allowlist")),
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
     fn handle_mint(
         &self,
+        db: &BlockWriter,
         op: &Zrc721Operation,
         inscription_id: &str,
         sender: &str,
         txid: Option<&str>,
         assigned_vout: Option<u32>,
+        height: u64,
+    ) -> Result<()> {
+        let tick = op
+            .tick
+            .as_ref()
+            .or(op.collection.as_ref())
+            .ok_or(anyhow::anyhow!("Missing collection/tick"))?
+            .to_lowercase();
+        let token_ids = match (&op.id, &op.ids) {
+            (Some(id), None) => vec![id.clone()],
+            (None, Some(ids)) => ids.expand()?,
+            (Some(_), Some(_)) => return Err(anyhow::anyhow!("Use either id or ids")),
+            (None, None) => return Err(anyhow::anyhow!("Missing token id")),
+        };
+
+        // Ids index 0..supply, so each must be a canonical integer
+        for id in &token_ids {
+            parse_token_number(id).map_err(|e| anyhow::anyhow!("Invalid token id: {}", e))?;
+        }
+        let owner = op.to.as_deref().unwrap_or(sender);
+        self.check_mint_phase(db, &tick, op, sender, height)?;
+
+        let metadata = op.meta.clone().unwrap_or_else(|| serde_json::json!({}));
+        db.insert_zrc721_tokens(&tick, &token_ids, owner, inscription_id, &metadata, height)?;
+        // One output carries one token; batch-minted tokens move with transfer inscriptions
+        if let ([token_id], Some(txid), Some(vout)) = (token_ids.as_slice(), txid, assigned_vout) {
+            let _ = db.register_zrc721_outpoint(txid, vout, &tick, token_id);
+        }
+        Ok(())
+    }
+
+    // The owner hands a token to `to` with an inscription instead of spending the
+    // mint output; the output stops carrying the token from then on
+    fn handle_transfer(
+        &self,
+        db: &BlockWriter,
+        op: &Zrc721Operation,
+        inscription_id: &str,
+        sender: &str,
+        txid: Option<&str>,
+        height: u64,
     ) -> Result<()> {
         let tick = op
             .tick
@@ -110,18 +356,134 @@ impl Zrc721Engine {
             .id
             .as_ref()
             .ok_or(anyhow::anyhow!("Missing token id"))?;
-
-        // Validate that the token id is numeric (common convention for 0..max indexing)
-        if token_id.chars().any(|c| !c.is_ascii_digit()) {
-            return Err(anyhow::anyhow!("Token id must be numeric"));
+        let to = op
+            .to
+            .as_deref()
+            .map(str::trim)
+            .filter(|to| !to.is_empty())
+            .ok_or(anyhow::anyhow!("Missing recipient"))?;
+        // Same rule as output moves: z-addresses are shielded and cannot be tracked
+        if to.starts_with('z') {
+            return Err(anyhow::anyhow!("Recipient must be a transparent address"));
         }
-        let owner = op.to.as_deref().unwrap_or(sender);
 
-        let metadata = op.meta.clone().unwrap_or_else(|| serde_json::json!({}));
-        self.db.insert_zrc721_token(&tick, token_id, owner, inscription_id, &metadata)?;
-        if let (Some(txid), Some(vout)) = (txid, assigned_vout) {
-            let _ = self.db.register_zrc721_outpoint(txid, vout, &tick, token_id);
+        let token = db
+            .get_zrc721_token(&tick, token_id)?
+            .ok_or(anyhow::anyhow!("Token not found"))?;
+        if token.shielded_burn || token.owner != sender {
+            return Err(anyhow::anyhow!("Only the owner can transfer a token"));
+        }
+        if token.owner == to {
+            return Err(anyhow::anyhow!("Token already owned by recipient"));
         }
+
+        db.update_zrc721_owner(&tick, token_id, to, false, txid.unwrap_or(""), height, Some(inscription_id), None)?;
+        db.drop_zrc721_outpoint(&tick, token_id)?;
+        tracing::info!("ZRC-721 transferred by inscription {}: {}#{} -> {}", inscription_id, tick, token_id, to);
         Ok(())
     }
 }
+
+/// Sale terms of a token that `tx` moves from `seller` to `buyer` in output `token_vout`,
+/// when the same transaction pays the collection's royalty address (`royalty_address`,
+/// or else its deployer).
+/// The price is what the seller and the royalty address receive; the token's own
+/// output is not counted. Sales by or to the royalty address are not royalty sales.
+pub fn detect_sale(
+    collection: &serde_json::Value,
+    seller: &str,
+    buyer: &str,
+    token_vout: u32,
+    tx: &TxResponse,
+) -> Option<serde_json::Value> {
+    let royalty_address = collection["royalty_address"]
+        .as_str()
+        .or_else(|| collection["deployer"].as_str())
+        .filter(|a| !a.is_empty())?;
+    let bps: u64 = collection["royalty"].as_str().and_then(|r| r.parse().ok()).filter(|b| *b > 0)?;
+    if seller == royalty_address || buyer == royalty_address {
+        return None;
+    }
+    let paid_to = |address: &str| -> u64 {
+        tx.vout
+            .iter()
+            .filter(|out| out.n != token_vout)
+            .filter(|out| {
+                out.script_pub_key
+                    .addresses
+                    .as_ref()
+                    .and_then(|a| a.first())
+                    .is_some_and(|first| first == address)
+            })
+            .map(|out| out.zatoshis())
+            .sum()
+    };
+    let royalty_paid = paid_to(royalty_address);
+    if royalty_paid == 0 {
+        return None;
+    }
+    let price = paid_to(seller).saturating_add(royalty_paid);
+    let royalty_due = (price as u128 * bps.min(10_000) as u128 / 10_000) as u64;
+    Some(serde_json::json!({
+        "seller": seller,
+        "buyer": buyer,
+        "price_zat": price,
+        "royalty_address": royalty_address,
+        "royalty_bps": bps,
+        "royalty_paid_zat": royalty_paid,
+        "royalty_due_zat": royalty_due,
+        "compliant": royalty_paid >= royalty_due,
+    }))
+}
+
+// Fields a deploy may set and an update may change
+fn check_mutable_fields(op: &Zrc721Operation) -> Result<()> {
+    if op.meta.as_ref().is_some_and(|meta| meta.is_null()) {
+        return Err(anyhow::anyhow!("meta may not be null"));
+    }
+    if let Some(address) = &op.royalty_address {
+        if address.is_empty() || address.starts_with('z') {
+            return Err(anyhow::anyhow!("royalty_address must be a transparent address"));
+        }
+    }
+    if op.description.as_ref().is_some_and(|d| d.chars().count() > MAX_DESCRIPTION) {
+        return Err(anyhow::anyhow!("description is longer than {} characters", MAX_DESCRIPTION));
+    }
+    Ok(())
+}
+
+// Addresses in an allowlist inscription: a JSON array of strings, or one per line
+fn read_allowlist(db: &BlockWriter, list_id: &str) -> Result<Vec<String>> {
+    let content = db
+        .get_inscription_content(list_id)?
+        .ok_or(anyhow::anyhow!("Allowlist inscription not found"))?;
+    let text = String::from_utf8(content).map_err(|_| anyhow::anyhow!("Allowlist is not text"))?;
+    let mut addresses: Vec<String> = match serde_json::from_str::<Vec<String>>(text.trim()) {
+        Ok(list) => list,
+        Err(_) => text.lines().map(|line| line.to_string()).collect(),
+    };
+    addresses.iter_mut().for_each(|a| *a = a.trim().to_string());
+    addresses.retain(|a| !a.is_empty());
+    addresses.sort();
+    addresses.dedup();
+    if addresses.is_empty() {
+        return Err(anyhow::anyhow!("Allowlist is empty"));
+    }
+    if addresses.len() > MAX_ALLOWLIST {
+        return Err(anyhow::anyhow!("At most {} allowlisted addresses", MAX_ALLOWLIST));
+    }
+    Ok(addresses)
+}
+
+// Sorted-pair Merkle proof: leaf = sha256(0x00 || address), node = sha256(0x01 || lower || higher)
+fn verify_allowlist_proof(root: &str, address: &str, proof: &[String]) -> bool {
+    let mut hash: [u8; 32] = Sha256::new().chain_update([0u8]).chain_update(address.as_bytes()).finalize().into();
+    for sibling in proof {
+        let Some(sibling) = hex::decode(sibling).ok().and_then(|s| <[u8; 32]>::try_from(s).ok()) else {
+            return false;
+        };
+        let (low, high) = if hash <= sibling { (hash, sibling) } else { (sibling, hash) };
+        hash = Sha256::new().chain_update([1u8]).chain_update(low).chain_update(high).finalize().into();
+    }
+    hex::encode(hash) == root.to_lowercase()
+}
@@ -187,7 +187,14 @@ class InscriptionFeed extends PaginatedComponent {
             const lowerType = (item.content_type || '').toLowerCase();
             const isJson = lowerType.includes('json');
 
-            if (isJson) {
+            if (lowerType.startsWith('image/')) {
+                const img = document.createElement('img');
+                img.src = `/thumb/${item.id}?size=256`;
+                img.alt = item.id;
+                img.loading = 'lazy';
+                img.className = 'thumb';
+                card.appendChild(img);
+            } else if (isJson) {
                 const pre = document.createElement('pre');
                 pre.textContent = 'loading json…';
                 card.appendChild(pre);
@@ -257,6 +264,7 @@ class TokenTable extends PaginatedComponent {
             '<th data-sort="max">Max&nbsp;Supply</th>' +
             '<th data-sort="limit">Mint&nbsp;Limit</th>' +
             '<th data-sort="progress">Progress</th>' +
+            '<th data-sort="price" title="From an external market feed, not from the chain">Price</th>' +
             '<th>Inscription</th>' +
         '</tr>';
         thead.addEventListener('click', (e) => {
@@ -355,6 +363,16 @@ class TokenTable extends PaginatedComponent {
             progressCell.appendChild(bar);
             row.appendChild(progressCell);
 
+            const price = document.createElement('td');
+            const quote = token.market || {};
+            price.textContent = quote.price !== undefined ? String(quote.price) : '—';
+            if (token.market) {
+                const updated = new Date(quote.updated_at * 1000).toISOString();
+                price.title = `source: ${quote.source}, updated ${updated}` +
+                    (quote.volume_24h !== undefined ? `, 24h volume: ${quote.volume_24h}` : '');
+            }
+            row.appendChild(price);
+
             const insc = document.createElement('td');
             const link = document.createElement('a');
             link.href = `/inscription/${token.inscription_id}`;
@@ -386,6 +404,7 @@ class TokenTable extends PaginatedComponent {
             if (key === 'max') return toBig(t.max_base_units || '0');
             if (key === 'limit') return decToBig(t.lim || '0', t.dec || '0');
             if (key === 'progress') return Number(t.progress || 0);
+            if (key === 'price') return Number((t.market && t.market.price) || 0);
             return 0;
         };
         const cmp = (a, b) => {